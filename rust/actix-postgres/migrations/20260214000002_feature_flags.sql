@@ -0,0 +1,15 @@
+-- Feature flags: one row per (name, environment). The '*' environment holds
+-- the default used when no environment-specific row exists.
+CREATE TABLE IF NOT EXISTS feature_flags (
+    name VARCHAR(100) NOT NULL,
+    environment VARCHAR(50) NOT NULL DEFAULT '*',
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    description TEXT NOT NULL DEFAULT '',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (name, environment)
+);
+
+INSERT INTO feature_flags (name, environment, enabled, description) VALUES
+    ('new_feed', '*', FALSE, 'Rank the article list by favorites instead of recency'),
+    ('new_feed', 'development', TRUE, 'Rank the article list by favorites instead of recency')
+ON CONFLICT (name, environment) DO NOTHING;
@@ -0,0 +1,39 @@
+-- Handles, for profile URLs and the author filter: display names change and
+-- needn't be unique. Lowercase letters, digits, `-` and `_`, 3 to 30
+-- characters, starting with a letter or digit.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS username VARCHAR(30);
+
+-- Backfill from the display name, slugified with `-` and cut to 20
+-- characters. A user whose slug is taken, or shorter than 3 characters, gets
+-- `_<id>` appended: slugs never contain `_`, so those can't collide.
+WITH slugs AS (
+    SELECT id,
+           rtrim(left(trim(BOTH '-' FROM regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g')), 20), '-') AS slug
+    FROM users
+    WHERE username IS NULL
+),
+ranked AS (
+    SELECT id, slug, row_number() OVER (PARTITION BY slug ORDER BY id) AS rank
+    FROM slugs
+)
+UPDATE users u
+SET username = CASE
+    WHEN r.slug = '' THEN 'user_' || u.id
+    WHEN r.rank > 1 OR length(r.slug) < 3 THEN r.slug || '_' || u.id
+    ELSE r.slug
+END
+FROM ranked r
+WHERE u.id = r.id;
+
+ALTER TABLE users ALTER COLUMN username SET NOT NULL;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'users_username_key') THEN
+        ALTER TABLE users ADD CONSTRAINT users_username_key UNIQUE (username);
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'users_username_check') THEN
+        ALTER TABLE users ADD CONSTRAINT users_username_check
+            CHECK (username ~ '^[a-z0-9][a-z0-9_-]{2,29}$');
+    END IF;
+END $$;
@@ -77,7 +77,8 @@ test_endpoint "GET" "/api/health" "200" "" "" "Health check"
 # Register User
 TIMESTAMP=$(date +%s)
 USER_EMAIL="test${TIMESTAMP}@example.com"
-USER_DATA="{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"name\":\"Test User\"}"
+USERNAME="test-${TIMESTAMP}"
+USER_DATA="{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"username\":\"$USERNAME\",\"name\":\"Test User\"}"
 
 log_info "Registering user: $USER_EMAIL"
 REGISTER_RESPONSE=$(curl -s -X POST "$BASE_URL/api/register" \
@@ -145,6 +146,29 @@ test_endpoint "GET" "/api/user" "200" "" "$TOKEN" "Get user profile (authenticat
 # Get User Profile - Unauthorized
 test_endpoint "GET" "/api/user" "401" "" "" "Get user profile (unauthorized)"
 
+# Usernames
+test_endpoint "POST" "/api/register" "409" "{\"email\":\"other-$USER_EMAIL\",\"password\":\"password123\",\"username\":\"$USERNAME\",\"name\":\"Other\"}" "" "Register with a taken username"
+test_endpoint "POST" "/api/register" "400" "{\"email\":\"bad-$USER_EMAIL\",\"password\":\"password123\",\"username\":\"not a handle\",\"name\":\"Bad\"}" "" "Register with an invalid username"
+test_endpoint "GET" "/api/profiles/$USERNAME" "200" "" "" "Get public profile"
+test_endpoint "GET" "/api/profiles/no-such-user-$TIMESTAMP" "404" "" "" "Get public profile (unknown username)"
+
+# Two-factor authentication: enrolling only starts until a code confirms it,
+# so the logins below still get a token straight away
+log_info "Starting two-factor setup"
+SETUP_RESPONSE=$(curl -s -X POST "$BASE_URL/api/user/2fa/setup" \
+    -H "Authorization: Bearer $TOKEN")
+
+if echo "$SETUP_RESPONSE" | grep -q '"otpauth_uri":"otpauth://totp/'; then
+    log_pass "Two-factor setup returns an otpauth URI"
+else
+    log_fail "Two-factor setup - no otpauth URI"
+    echo "$SETUP_RESPONSE"
+fi
+test_endpoint "POST" "/api/user/2fa/verify" "400" '{"code":"000000x"}' "$TOKEN" "Confirm two-factor setup (invalid code)"
+test_endpoint "POST" "/api/user/2fa/setup" "401" "" "" "Two-factor setup (unauthorized)"
+test_endpoint "POST" "/api/login/2fa" "401" '{"challenge_token":"not-a-challenge","code":"123456"}' "" "Two-factor login (invalid challenge)"
+test_endpoint "POST" "/api/login/2fa" "401" "{\"challenge_token\":\"$TOKEN\",\"code\":\"123456\"}" "" "Two-factor login (access token as challenge)"
+
 # Create Article
 ARTICLE_DATA="{\"title\":\"Test Article $TIMESTAMP\",\"description\":\"Test description\",\"body\":\"This is the article body.\"}"
 log_info "Creating article"
@@ -166,6 +190,7 @@ echo ""
 
 # Create Article - Unauthorized
 test_endpoint "POST" "/api/articles" "401" "$ARTICLE_DATA" "" "Create article (unauthorized)"
+test_endpoint "POST" "/api/articles" "400" '{"title":"  ","body":"b"}' "$TOKEN" "Create article (blank title)"
 
 # Create Second Article
 ARTICLE2_DATA="{\"title\":\"Second Article $TIMESTAMP\",\"body\":\"Second body\"}"
@@ -190,6 +215,9 @@ test_endpoint "GET" "/api/articles" "200" "" "" "List articles (public)"
 # List Articles with pagination
 test_endpoint "GET" "/api/articles?limit=1&offset=0" "200" "" "" "List articles (paginated)"
 
+# Filter by author username
+test_endpoint "GET" "/api/articles?author=$USERNAME" "200" "" "" "List articles by author"
+
 # Get Single Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "GET" "/api/articles/$ARTICLE_SLUG" "200" "" "" "Get single article"
@@ -227,7 +255,7 @@ fi
 
 # Register second user for ownership tests
 USER2_EMAIL="test2-${TIMESTAMP}@example.com"
-USER2_DATA="{\"email\":\"$USER2_EMAIL\",\"password\":\"password123\",\"name\":\"Test User 2\"}"
+USER2_DATA="{\"email\":\"$USER2_EMAIL\",\"password\":\"password123\",\"username\":\"test2-${TIMESTAMP}\",\"name\":\"Test User 2\"}"
 USER2_RESPONSE=$(curl -s -X POST "$BASE_URL/api/register" \
     -H "Content-Type: application/json" \
     -d "$USER2_DATA")
@@ -248,6 +276,13 @@ if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "POST" "/api/articles/$ARTICLE_SLUG/favorite" "200" "" "$TOKEN" "Favorite article (idempotent)"
 fi
 
+# Own articles and favorites
+if [ -n "$ARTICLE_SLUG" ]; then
+    test_endpoint "GET" "/api/user/articles" "200" "" "$TOKEN" "List own articles"
+    test_endpoint "GET" "/api/user/favorites?limit=5" "200" "" "$TOKEN" "List favorited articles"
+fi
+test_endpoint "GET" "/api/user/favorites" "401" "" "" "List favorited articles (unauthorized)"
+
 # Unfavorite Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "DELETE" "/api/articles/$ARTICLE_SLUG/favorite" "200" "" "$TOKEN" "Unfavorite article"
@@ -271,6 +306,55 @@ if [ -n "$ARTICLE2_SLUG" ]; then
     test_endpoint "DELETE" "/api/articles/$ARTICLE2_SLUG" "204" "" "$TOKEN" "Delete second article"
 fi
 
+# Admin: Feature Flags
+ADMIN_TOKEN="${ADMIN_TOKEN:-change-me-admin-token}"
+
+log_info "Testing: List feature flags (admin)"
+FLAGS_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/admin/flags" \
+    -H "X-Admin-Token: $ADMIN_TOKEN")
+if [ "$FLAGS_STATUS" = "200" ]; then
+    log_pass "List feature flags (status: $FLAGS_STATUS)"
+else
+    log_fail "List feature flags - expected 200, got $FLAGS_STATUS"
+fi
+echo ""
+
+log_info "Testing: Toggle feature flag (admin)"
+TOGGLE_STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X PUT "$BASE_URL/api/admin/flags/new_feed" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" \
+    -H "Content-Type: application/json" \
+    -d '{"enabled":true}')
+if [ "$TOGGLE_STATUS" = "200" ]; then
+    log_pass "Toggle feature flag (status: $TOGGLE_STATUS)"
+else
+    log_fail "Toggle feature flag - expected 200, got $TOGGLE_STATUS"
+fi
+echo ""
+
+test_endpoint "GET" "/api/admin/flags" "401" "" "" "List feature flags (no admin token)"
+
+log_info "Testing: Effective config (admin)"
+CONFIG_BODY=$(curl -s "$BASE_URL/api/admin/config" -H "X-Admin-Token: $ADMIN_TOKEN")
+if echo "$CONFIG_BODY" | grep -q '"JWT_SECRET":{"value":"\[REDACTED\]"'; then
+    log_pass "Effective config redacts JWT_SECRET"
+else
+    log_fail "Effective config - expected redacted JWT_SECRET, got $CONFIG_BODY"
+fi
+echo ""
+
+log_info "Testing: Queue an email (admin)"
+EMAIL_BODY=$(curl -s -X POST "$BASE_URL/api/admin/emails" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" -H "Content-Type: application/json" \
+    -d '{"to":"ada@example.com","template":"verification","name":"Ada","url":"https://example.com/verify"}')
+if echo "$EMAIL_BODY" | grep -q '"job_id":'; then
+    log_pass "Email queued as a job"
+else
+    log_fail "Queue email - expected a job_id, got $EMAIL_BODY"
+fi
+echo ""
+
+test_endpoint "POST" "/api/admin/emails" "401" '{"to":"ada@example.com","template":"verification","name":"Ada","url":"https://example.com/verify"}' "" "Queue email (no admin token)"
+
 # Logout
 test_endpoint "POST" "/api/logout" "200" "" "$TOKEN" "Logout"
 
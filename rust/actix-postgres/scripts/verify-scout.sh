@@ -84,7 +84,7 @@ USER_EMAIL="scout-test-${TIMESTAMP}@example.com"
 echo "Creating test user..."
 REGISTER_RESPONSE=$(curl -s -X POST "$BASE_URL/api/register" \
     -H "Content-Type: application/json" \
-    -d "{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"name\":\"Scout Test\"}")
+    -d "{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"username\":\"scout-${TIMESTAMP}\",\"name\":\"Scout Test\"}")
 
 TOKEN=$(echo "$REGISTER_RESPONSE" | grep -o '"token":"[^"]*"' | cut -d'"' -f4)
 
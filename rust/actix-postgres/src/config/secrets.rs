@@ -0,0 +1,567 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::{OffsetDateTime, format_description::BorrowedFormatItem, macros::format_description};
+
+const AMZ_DATE: &[BorrowedFormatItem<'static>] =
+    format_description!("[year][month][day]T[hour][minute][second]Z");
+const AMZ_DAY: &[BorrowedFormatItem<'static>] = format_description!("[year][month][day]");
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Where secret keys come from besides plain env vars: `{KEY}_FILE` mounts
+/// (Docker/Kubernetes secrets) and an optional external secret manager.
+#[derive(Clone, Default)]
+pub struct SecretResolver {
+    pinned: Arc<HashSet<String>>,
+    files: Arc<HashMap<String, PathBuf>>,
+    provider: Option<Arc<SecretsProvider>>,
+}
+
+impl SecretResolver {
+    pub fn from_env(
+        env: &HashMap<String, String>,
+        secret_keys: &[&str],
+    ) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut pinned = HashSet::new();
+        let mut files = HashMap::new();
+
+        for key in secret_keys {
+            let file_key = format!("{}_FILE", key);
+            match (env.get(*key), env.get(&file_key)) {
+                (Some(_), Some(_)) => {
+                    errors.push(format!("set either {} or {}, not both", key, file_key))
+                }
+                (Some(_), None) => {
+                    pinned.insert(key.to_string());
+                }
+                (None, Some(path)) => {
+                    files.insert(key.to_string(), PathBuf::from(path));
+                }
+                (None, None) => {}
+            }
+        }
+
+        let provider = SecretsProvider::from_env(env).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+
+        if errors.is_empty() {
+            Ok(Self {
+                pinned: Arc::new(pinned),
+                files: Arc::new(files),
+                provider: provider.map(Arc::new),
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn provider_name(&self) -> Option<&'static str> {
+        self.provider.as_ref().map(|p| p.name())
+    }
+
+    pub fn read_files(&self) -> (HashMap<String, String>, Vec<String>) {
+        let mut values = HashMap::new();
+        let mut errors = Vec::new();
+        for (key, path) in self.files.iter() {
+            match read_secret_file(path) {
+                Ok(value) => {
+                    values.insert(key.clone(), value);
+                }
+                Err(e) => errors.push(format!("{}_FILE: {}", key, e)),
+            }
+        }
+        (values, errors)
+    }
+
+    pub async fn fetch_managed(
+        &self,
+        secret_keys: &[&str],
+    ) -> Result<HashMap<String, String>, String> {
+        let Some(provider) = &self.provider else {
+            return Ok(HashMap::new());
+        };
+        let mut values = provider.fetch().await?;
+        values.retain(|key, _| secret_keys.contains(&key.as_str()));
+        Ok(values)
+    }
+
+    /// Whether `key` may change at runtime, i.e. it is not pinned by an env var
+    /// and comes from a file or the secret manager.
+    pub fn is_dynamic(&self, key: &str) -> bool {
+        !self.pinned.contains(key) && (self.files.contains_key(key) || self.provider.is_some())
+    }
+
+    /// Re-reads `key` using the same precedence as startup. `None` means the
+    /// key is not backed by a file or the secret manager.
+    pub async fn reload(&self, key: &str) -> Result<Option<String>, String> {
+        if !self.is_dynamic(key) {
+            return Ok(None);
+        }
+        if let Some(path) = self.files.get(key) {
+            return read_secret_file(path).map(Some);
+        }
+        match &self.provider {
+            Some(provider) => Ok(provider.fetch().await?.remove(key)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn read_secret_file(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+/// A secret that can rotate while the service runs. The previous value is
+/// kept so tokens signed just before a rotation still verify.
+#[derive(Clone)]
+pub struct RotatingSecret {
+    key: &'static str,
+    versions: Arc<RwLock<Versions>>,
+}
+
+struct Versions {
+    current: String,
+    previous: Option<String>,
+}
+
+impl RotatingSecret {
+    pub fn new(key: &'static str, value: impl Into<String>) -> Self {
+        Self {
+            key,
+            versions: Arc::new(RwLock::new(Versions {
+                current: value.into(),
+                previous: None,
+            })),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.versions.read().unwrap().current.clone()
+    }
+
+    /// Current value first, then the one it replaced.
+    pub fn candidates(&self) -> Vec<String> {
+        let versions = self.versions.read().unwrap();
+        std::iter::once(versions.current.clone())
+            .chain(versions.previous.clone())
+            .collect()
+    }
+
+    pub fn rotate(&self, value: String) -> bool {
+        let mut versions = self.versions.write().unwrap();
+        if value.is_empty() || value == versions.current {
+            return false;
+        }
+        let previous = std::mem::replace(&mut versions.current, value);
+        versions.previous = Some(previous);
+        true
+    }
+
+    pub fn spawn_refresh(&self, resolver: SecretResolver, interval: Duration) {
+        if !resolver.is_dynamic(self.key) {
+            return;
+        }
+        let secret = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match resolver.reload(secret.key).await {
+                    Ok(Some(value)) => {
+                        if secret.rotate(value) {
+                            tracing::info!(key = secret.key, "Secret rotated");
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!(key = secret.key, error = %e, "Secret reload failed")
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// External secret manager selected by `SECRETS_PROVIDER`. The stored secret
+/// is a JSON object keyed by config name, e.g. `{"JWT_SECRET": "..."}`.
+pub enum SecretsProvider {
+    Vault(VaultSecrets),
+    Aws(AwsSecrets),
+}
+
+pub struct VaultSecrets {
+    client: reqwest::Client,
+    addr: String,
+    token: String,
+    path: String,
+}
+
+pub struct AwsSecrets {
+    client: reqwest::Client,
+    endpoint: String,
+    host: String,
+    region: String,
+    secret_id: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl SecretsProvider {
+    pub fn from_env(env: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        let get = |key: &str| env.get(key).filter(|v| !v.is_empty()).cloned();
+        let require = |key: &str, provider: &str| {
+            get(key)
+                .ok_or_else(|| format!("{} must be set when SECRETS_PROVIDER={}", key, provider))
+        };
+        let client = || {
+            reqwest::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .map_err(|e| format!("failed to build secrets client: {}", e))
+        };
+
+        match get("SECRETS_PROVIDER").as_deref() {
+            None | Some("none") => Ok(None),
+            Some("vault") => {
+                let token = match get("VAULT_TOKEN_FILE") {
+                    Some(path) => read_secret_file(Path::new(&path))
+                        .map_err(|e| format!("VAULT_TOKEN_FILE: {}", e))?,
+                    None => require("VAULT_TOKEN", "vault")?,
+                };
+                Ok(Some(Self::Vault(VaultSecrets {
+                    client: client()?,
+                    addr: require("VAULT_ADDR", "vault")?
+                        .trim_end_matches('/')
+                        .to_string(),
+                    token,
+                    path: require("VAULT_SECRET_PATH", "vault")?
+                        .trim_matches('/')
+                        .to_string(),
+                })))
+            }
+            Some("aws") => {
+                let region = require("AWS_REGION", "aws")?;
+                let endpoint = get("AWS_ENDPOINT_URL")
+                    .unwrap_or_else(|| format!("https://secretsmanager.{}.amazonaws.com", region))
+                    .trim_end_matches('/')
+                    .to_string();
+                let host = endpoint
+                    .split("://")
+                    .nth(1)
+                    .unwrap_or(&endpoint)
+                    .to_string();
+                Ok(Some(Self::Aws(AwsSecrets {
+                    client: client()?,
+                    endpoint,
+                    host,
+                    region,
+                    secret_id: require("AWS_SECRET_ID", "aws")?,
+                    access_key_id: require("AWS_ACCESS_KEY_ID", "aws")?,
+                    secret_access_key: require("AWS_SECRET_ACCESS_KEY", "aws")?,
+                    session_token: get("AWS_SESSION_TOKEN"),
+                })))
+            }
+            Some(other) => Err(format!(
+                "SECRETS_PROVIDER must be \"vault\", \"aws\" or \"none\", got {:?}",
+                other
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Vault(_) => "vault",
+            Self::Aws(_) => "aws",
+        }
+    }
+
+    pub async fn fetch(&self) -> Result<HashMap<String, String>, String> {
+        match self {
+            Self::Vault(vault) => vault.fetch().await,
+            Self::Aws(aws) => aws.fetch().await,
+        }
+    }
+}
+
+impl VaultSecrets {
+    async fn fetch(&self) -> Result<HashMap<String, String>, String> {
+        let response = self
+            .client
+            .get(format!("{}/v1/{}", self.addr, self.path))
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .map_err(|e| format!("Vault request failed: {}", e))?;
+
+        parse_vault_secret(&read_json(response, "Vault").await?)
+    }
+}
+
+impl AwsSecrets {
+    async fn fetch(&self) -> Result<HashMap<String, String>, String> {
+        let payload = serde_json::json!({ "SecretId": self.secret_id }).to_string();
+        let mut request = self
+            .client
+            .post(format!("{}/", self.endpoint))
+            .header(CONTENT_TYPE, "application/x-amz-json-1.1")
+            .header("X-Amz-Target", "secretsmanager.GetSecretValue");
+        for (name, value) in self.sign(&payload, OffsetDateTime::now_utc()) {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .body(payload)
+            .send()
+            .await
+            .map_err(|e| format!("Secrets Manager request failed: {}", e))?;
+
+        parse_aws_secret(&read_json(response, "Secrets Manager").await?)
+    }
+
+    /// SigV4 headers for a `GetSecretValue` call.
+    fn sign(&self, payload: &str, now: OffsetDateTime) -> Vec<(&'static str, String)> {
+        let amz_date = now.format(AMZ_DATE).unwrap_or_default();
+        let day = now.format(AMZ_DAY).unwrap_or_default();
+        let scope = format!("{day}/{}/secretsmanager/aws4_request", self.region);
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", self.host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", "secretsmanager.GetSecretValue".to_string()));
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+            sha256_hex(payload.as_bytes())
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            sha256_hex(canonical_request.as_bytes())
+        );
+
+        let k_date = hmac_sha256(
+            format!("AWS4{}", self.secret_access_key).as_bytes(),
+            day.as_bytes(),
+        );
+        let k_region = hmac_sha256(&k_date, self.region.as_bytes());
+        let k_service = hmac_sha256(&k_region, b"secretsmanager");
+        let k_signing = hmac_sha256(&k_service, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+        let mut out = vec![
+            (
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                    self.access_key_id
+                ),
+            ),
+            ("X-Amz-Date", amz_date),
+        ];
+        if let Some(token) = &self.session_token {
+            out.push(("X-Amz-Security-Token", token.clone()));
+        }
+        out
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+async fn read_json(response: reqwest::Response, provider: &str) -> Result<Value, String> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("{} returned {}: {}", provider, status, body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("{} returned invalid JSON: {}", provider, e))
+}
+
+/// KV v2 nests the secret under `data.data`; KV v1 returns it as `data`.
+fn parse_vault_secret(body: &Value) -> Result<HashMap<String, String>, String> {
+    let data = &body["data"];
+    let secret = if data.get("metadata").is_some() {
+        &data["data"]
+    } else {
+        data
+    };
+    to_string_map(secret).ok_or_else(|| "Vault secret is not a JSON object".to_string())
+}
+
+fn parse_aws_secret(body: &Value) -> Result<HashMap<String, String>, String> {
+    let secret: Value = body["SecretString"]
+        .as_str()
+        .ok_or("Secrets Manager secret has no SecretString")?
+        .parse()
+        .map_err(|e| format!("SecretString is not JSON: {}", e))?;
+    to_string_map(&secret).ok_or_else(|| "SecretString is not a JSON object".to_string())
+}
+
+fn to_string_map(value: &Value) -> Option<HashMap<String, String>> {
+    Some(
+        value
+            .as_object()?
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (key.to_uppercase(), value)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolver_reads_secret_files() {
+        let path = std::env::temp_dir().join(format!("jwt-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "from-file\n").unwrap();
+
+        let resolver = SecretResolver::from_env(
+            &env(&[("JWT_SECRET_FILE", path.to_str().unwrap())]),
+            &["JWT_SECRET"],
+        )
+        .expect("valid resolver");
+        let (values, errors) = resolver.read_files();
+        std::fs::remove_file(&path).ok();
+
+        assert!(errors.is_empty());
+        assert_eq!(values["JWT_SECRET"], "from-file");
+        assert!(resolver.is_dynamic("JWT_SECRET"));
+    }
+
+    #[test]
+    fn test_resolver_rejects_value_and_file() {
+        let errors = SecretResolver::from_env(
+            &env(&[("JWT_SECRET", "x"), ("JWT_SECRET_FILE", "/run/secrets/jwt")]),
+            &["JWT_SECRET"],
+        )
+        .err()
+        .expect("conflict should fail");
+
+        assert_eq!(
+            errors,
+            vec!["set either JWT_SECRET or JWT_SECRET_FILE, not both"]
+        );
+    }
+
+    #[test]
+    fn test_provider_requires_settings() {
+        let err = SecretsProvider::from_env(&env(&[("SECRETS_PROVIDER", "vault")]))
+            .err()
+            .expect("missing settings should fail");
+        assert!(err.contains("VAULT_TOKEN"));
+
+        assert!(SecretsProvider::from_env(&env(&[])).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_vault_kv_v1_and_v2() {
+        let v2 = serde_json::json!({
+            "data": { "data": { "JWT_SECRET": "abc" }, "metadata": { "version": 3 } }
+        });
+        let v1 = serde_json::json!({ "data": { "jwt_secret": "abc" } });
+
+        assert_eq!(parse_vault_secret(&v2).unwrap()["JWT_SECRET"], "abc");
+        assert_eq!(parse_vault_secret(&v1).unwrap()["JWT_SECRET"], "abc");
+    }
+
+    #[test]
+    fn test_parse_aws_secret_string() {
+        let body = serde_json::json!({
+            "Name": "actix-postgres",
+            "SecretString": "{\"DATABASE_URL\":\"postgres://db\"}"
+        });
+        assert_eq!(
+            parse_aws_secret(&body).unwrap()["DATABASE_URL"],
+            "postgres://db"
+        );
+    }
+
+    #[test]
+    fn test_aws_sign_scopes_credential() {
+        let provider = SecretsProvider::from_env(&env(&[
+            ("SECRETS_PROVIDER", "aws"),
+            ("AWS_REGION", "eu-west-1"),
+            ("AWS_SECRET_ID", "actix-postgres"),
+            ("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+        ]))
+        .unwrap();
+        let Some(SecretsProvider::Aws(aws)) = provider else {
+            panic!("expected aws provider");
+        };
+
+        let now = time::macros::datetime!(2026-01-15 12:00:00 UTC);
+        let headers = aws.sign("{}", now);
+        let (_, auth) = &headers[0];
+
+        assert_eq!(aws.host, "secretsmanager.eu-west-1.amazonaws.com");
+        assert!(auth.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20260115/eu-west-1/secretsmanager/aws4_request"
+        ));
+        assert!(auth.contains("SignedHeaders=content-type;host;x-amz-date;x-amz-target"));
+        assert_eq!(headers, aws.sign("{}", now));
+    }
+
+    #[test]
+    fn test_rotating_secret_keeps_previous() {
+        let secret = RotatingSecret::new("JWT_SECRET", "one");
+
+        assert!(!secret.rotate("one".to_string()));
+        assert!(secret.rotate("two".to_string()));
+        assert_eq!(secret.current(), "two");
+        assert_eq!(secret.candidates(), vec!["two", "one"]);
+    }
+}
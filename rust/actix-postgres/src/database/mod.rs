@@ -1,3 +1,5 @@
 mod pool;
+mod query;
 
 pub use pool::create_pool;
+pub use query::{Persistent, Traced};
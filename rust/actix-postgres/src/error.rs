@@ -1,10 +1,15 @@
-use actix_web::{HttpResponse, http::StatusCode};
-use opentelemetry::trace::TraceContextExt;
+use std::time::Duration;
+
+use actix_web::{HttpResponse, http::StatusCode, http::header::RETRY_AFTER};
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{Status, TraceContextExt};
 use serde_json::json;
 use thiserror::Error;
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::telemetry::current_request_id;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Authentication required")]
@@ -25,6 +30,15 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Request timed out")]
+    Timeout,
+
+    #[error("Payload too large: limit is {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+
+    #[error("Too many failed attempts, retry in {}s", .0.as_secs())]
+    Locked(Duration),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -35,6 +49,26 @@ pub enum AppError {
     Internal(String),
 }
 
+impl AppError {
+    /// The variant name, reported as `exception.type`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized => "Unauthorized",
+            AppError::InvalidCredentials => "InvalidCredentials",
+            AppError::Forbidden => "Forbidden",
+            AppError::NotFound(_) => "NotFound",
+            AppError::Conflict(_) => "Conflict",
+            AppError::Validation(_) => "Validation",
+            AppError::Timeout => "Timeout",
+            AppError::PayloadTooLarge { .. } => "PayloadTooLarge",
+            AppError::Locked(_) => "Locked",
+            AppError::Database(_) => "Database",
+            AppError::Jwt(_) => "Jwt",
+            AppError::Internal(_) => "Internal",
+        }
+    }
+}
+
 fn get_trace_id() -> Option<String> {
     let span = Span::current();
     let context = span.context();
@@ -58,6 +92,9 @@ impl actix_web::ResponseError for AppError {
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::Conflict(_) => StatusCode::CONFLICT,
             AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Timeout => StatusCode::REQUEST_TIMEOUT,
+            AppError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Locked(_) => StatusCode::TOO_MANY_REQUESTS,
             AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -89,29 +126,87 @@ impl actix_web::ResponseError for AppError {
             _ => self.to_string(),
         };
 
-        let body = if let Some(trace_id) = get_trace_id() {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-                "trace_id": trace_id,
-            })
-        } else {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-            })
-        };
+        let mut body = json!({
+            "error": error_message,
+            "status": status.as_u16(),
+        });
+        if let Some(trace_id) = get_trace_id() {
+            body["trace_id"] = trace_id.into();
+        }
+        if let Some(request_id) = current_request_id() {
+            body["request_id"] = request_id.into();
+        }
 
-        HttpResponse::build(status).json(body)
+        let mut response = HttpResponse::build(status);
+        if let AppError::Locked(retry_after) = self {
+            response.insert_header((RETRY_AFTER, retry_after.as_secs()));
+        }
+        response.json(body)
     }
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
+impl From<blog_domain::ValidationError> for AppError {
+    fn from(e: blog_domain::ValidationError) -> Self {
+        AppError::Validation(e.to_string())
+    }
+}
+
+impl From<two_factor::Error<sqlx::Error>> for AppError {
+    fn from(e: two_factor::Error<sqlx::Error>) -> Self {
+        match e {
+            two_factor::Error::AlreadyEnabled => {
+                AppError::Conflict("Two-factor authentication is already enabled".to_string())
+            }
+            two_factor::Error::NotEnrolled => {
+                AppError::Validation("Two-factor authentication is not set up".to_string())
+            }
+            two_factor::Error::InvalidCode => {
+                AppError::Validation("Invalid two-factor code".to_string())
+            }
+            two_factor::Error::Locked(retry_after) => AppError::Locked(retry_after),
+            two_factor::Error::Store(e) => AppError::Database(e),
+        }
+    }
+}
+
+/// Marks the current span as failed when the result is an error:
+/// `otel.status_code=ERROR` plus an `exception` event with the error's type
+/// and message. Services call it on the result of each instrumented method.
+pub trait RecordErr {
+    fn record_err(self) -> Self;
+}
+
+impl<T> RecordErr for AppResult<T> {
+    fn record_err(self) -> Self {
+        if let Err(err) = &self {
+            let span = Span::current();
+            let message = err.to_string();
+            span.set_status(Status::error(message.clone()));
+            span.add_event(
+                "exception",
+                vec![
+                    KeyValue::new("exception.type", err.kind()),
+                    KeyValue::new("exception.message", message),
+                ],
+            );
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_record_err_passes_result_through() {
+        let error: AppResult<()> = Err(AppError::Conflict("slug taken".to_string())).record_err();
+        assert_eq!(error.unwrap_err().kind(), "Conflict");
+        assert_eq!(Ok::<_, AppError>(1).record_err().unwrap(), 1);
+    }
+
     #[test]
     fn test_unauthorized_error() {
         let error = AppError::Unauthorized;
@@ -148,6 +243,15 @@ mod tests {
         assert_eq!(error.to_string(), "Validation error: Email is required");
     }
 
+    #[test]
+    fn test_domain_validation_error_is_a_bad_request() {
+        let error = AppError::from(blog_domain::ValidationError::Empty("Title"));
+        assert_eq!(
+            error.to_string(),
+            "Validation error: Title must not be empty"
+        );
+    }
+
     #[test]
     fn test_internal_error() {
         let error = AppError::Internal("Something went wrong".to_string());
@@ -171,6 +275,15 @@ mod tests {
                 AppError::Validation("test".to_string()),
                 StatusCode::BAD_REQUEST,
             ),
+            (AppError::Timeout, StatusCode::REQUEST_TIMEOUT),
+            (
+                AppError::PayloadTooLarge { limit: 1024 },
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                AppError::Locked(Duration::from_secs(60)),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
             (
                 AppError::Internal("test".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -182,6 +295,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_locked_response_has_retry_after() {
+        use actix_web::ResponseError;
+
+        let response = AppError::Locked(Duration::from_secs(90)).error_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "90");
+    }
+
     #[test]
     fn test_app_result_ok() {
         fn returns_ok() -> AppResult<i32> {
@@ -0,0 +1,53 @@
+use std::future::{Ready, ready};
+use std::marker::PhantomData;
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+
+use super::FeatureFlags;
+use crate::error::AppError;
+
+pub trait FlagName {
+    const NAME: &'static str;
+}
+
+macro_rules! feature_flag {
+    ($ty:ident, $name:literal) => {
+        pub struct $ty;
+
+        impl FlagName for $ty {
+            const NAME: &'static str = $name;
+        }
+    };
+}
+
+feature_flag!(NewFeed, "new_feed");
+
+/// Evaluates flag `F` for the current request, e.g. `new_feed: Flag<NewFeed>`.
+pub struct Flag<F: FlagName> {
+    enabled: bool,
+    _flag: PhantomData<F>,
+}
+
+impl<F: FlagName> Flag<F> {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl<F: FlagName> FromRequest for Flag<F> {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = req
+            .app_data::<web::Data<FeatureFlags>>()
+            .ok_or(AppError::Internal(
+                "FeatureFlags not configured".to_string(),
+            ))
+            .map(|flags| Flag {
+                enabled: flags.is_enabled(F::NAME),
+                _flag: PhantomData,
+            });
+        ready(result)
+    }
+}
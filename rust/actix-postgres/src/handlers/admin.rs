@@ -0,0 +1,50 @@
+use actix_web::{HttpResponse, web};
+use mailer::EmailJob;
+use serde_json::json;
+
+use crate::{
+    config::Config, error::AppResult, feature_flags::FeatureFlags, jobs::JobQueue,
+    middleware::AdminAuth, models::UpdateFeatureFlagInput,
+};
+
+pub async fn get_config(config: web::Data<Config>, _admin: AdminAuth) -> HttpResponse {
+    HttpResponse::Ok().json(config.effective())
+}
+
+pub async fn list_feature_flags(
+    feature_flags: web::Data<FeatureFlags>,
+    _admin: AdminAuth,
+) -> HttpResponse {
+    HttpResponse::Ok().json(feature_flags.list())
+}
+
+pub async fn update_feature_flag(
+    feature_flags: web::Data<FeatureFlags>,
+    _admin: AdminAuth,
+    name: web::Path<String>,
+    input: web::Json<UpdateFeatureFlagInput>,
+) -> AppResult<HttpResponse> {
+    let input = input.into_inner();
+    let flag = feature_flags
+        .set(
+            &name,
+            input.environment.as_deref(),
+            input.enabled,
+            input.description.as_deref(),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(flag))
+}
+
+/// Queues any template to any address, to check the mail transport end to
+/// end; the worker sends it.
+pub async fn send_email(
+    job_queue: web::Data<JobQueue>,
+    _admin: AdminAuth,
+    email: web::Json<EmailJob>,
+) -> AppResult<HttpResponse> {
+    let job_id = job_queue.enqueue_email(email.into_inner()).await?;
+
+    Ok(HttpResponse::Accepted().json(json!({ "job_id": job_id })))
+}
@@ -1,9 +1,11 @@
 use actix_web::{HttpResponse, web};
+use authz::Authors;
 
 use crate::{
     error::AppResult,
+    feature_flags::{Flag, NewFeed},
     middleware::{AuthUser, OptionalAuthUser},
-    models::{CreateArticleInput, ListArticlesQuery, UpdateArticleInput},
+    models::{CreateArticleInput, ListArticlesQuery, UpdateArticleInput, UserArticlesQuery},
     services::ArticleService,
 };
 
@@ -30,9 +32,36 @@ pub async fn get_article(
 pub async fn list_articles(
     article_service: web::Data<ArticleService>,
     auth: OptionalAuthUser,
+    new_feed: Flag<NewFeed>,
     query: web::Query<ListArticlesQuery>,
 ) -> AppResult<HttpResponse> {
-    let response = article_service.list(query.into_inner(), auth.0).await?;
+    let response = article_service
+        .list(query.into_inner(), auth.0, new_feed.is_enabled())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn list_user_articles(
+    article_service: web::Data<ArticleService>,
+    auth: AuthUser,
+    query: web::Query<UserArticlesQuery>,
+) -> AppResult<HttpResponse> {
+    let response = article_service
+        .list_by_user(auth.0, query.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn list_user_favorites(
+    article_service: web::Data<ArticleService>,
+    auth: AuthUser,
+    query: web::Query<UserArticlesQuery>,
+) -> AppResult<HttpResponse> {
+    let response = article_service
+        .list_favorites(auth.0, query.into_inner())
+        .await?;
 
     Ok(HttpResponse::Ok().json(response))
 }
@@ -40,11 +69,12 @@ pub async fn list_articles(
 pub async fn update_article(
     article_service: web::Data<ArticleService>,
     auth: AuthUser,
+    authors: web::ReqData<Authors>,
     slug: web::Path<String>,
     input: web::Json<UpdateArticleInput>,
 ) -> AppResult<HttpResponse> {
     let response = article_service
-        .update(&slug, auth.0, input.into_inner())
+        .update(&slug, &authors, auth.0, input.into_inner())
         .await?;
 
     Ok(HttpResponse::Ok().json(response))
@@ -53,9 +83,10 @@ pub async fn update_article(
 pub async fn delete_article(
     article_service: web::Data<ArticleService>,
     auth: AuthUser,
+    authors: web::ReqData<Authors>,
     slug: web::Path<String>,
 ) -> AppResult<HttpResponse> {
-    article_service.delete(&slug, auth.0).await?;
+    article_service.delete(&slug, &authors, auth.0).await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
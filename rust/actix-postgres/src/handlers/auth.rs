@@ -4,7 +4,10 @@ use serde_json::json;
 use crate::{
     error::AppResult,
     middleware::AuthUser,
-    models::{LoginInput, ProfileResponse, RegisterInput, UserResponse},
+    models::{
+        LoginInput, LoginOutcome, ProfileResponse, PublicProfileResponse, RegisterInput,
+        TwoFactorChallengeResponse, TwoFactorCodeInput, TwoFactorLoginInput, UserResponse,
+    },
     services::AuthService,
 };
 
@@ -21,11 +24,42 @@ pub async fn login(
     auth_service: web::Data<AuthService>,
     input: web::Json<LoginInput>,
 ) -> AppResult<HttpResponse> {
-    let user = auth_service.login(input.into_inner()).await?;
+    Ok(match auth_service.login(input.into_inner()).await? {
+        LoginOutcome::Authenticated(user) => HttpResponse::Ok().json(UserResponse { user }),
+        LoginOutcome::TwoFactorRequired(two_factor) => {
+            HttpResponse::Ok().json(TwoFactorChallengeResponse { two_factor })
+        }
+    })
+}
+
+pub async fn login_two_factor(
+    auth_service: web::Data<AuthService>,
+    input: web::Json<TwoFactorLoginInput>,
+) -> AppResult<HttpResponse> {
+    let user = auth_service.login_two_factor(input.into_inner()).await?;
 
     Ok(HttpResponse::Ok().json(UserResponse { user }))
 }
 
+pub async fn setup_two_factor(
+    auth_service: web::Data<AuthService>,
+    auth: AuthUser,
+) -> AppResult<HttpResponse> {
+    let setup = auth_service.setup_two_factor(auth.0).await?;
+
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+pub async fn verify_two_factor(
+    auth_service: web::Data<AuthService>,
+    auth: AuthUser,
+    input: web::Json<TwoFactorCodeInput>,
+) -> AppResult<HttpResponse> {
+    let codes = auth_service.enable_two_factor(auth.0, &input.code).await?;
+
+    Ok(HttpResponse::Ok().json(codes))
+}
+
 pub async fn get_user(
     auth_service: web::Data<AuthService>,
     auth: AuthUser,
@@ -35,6 +69,15 @@ pub async fn get_user(
     Ok(HttpResponse::Ok().json(ProfileResponse::from(user)))
 }
 
+pub async fn get_profile(
+    auth_service: web::Data<AuthService>,
+    username: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let user = auth_service.get_profile(&username).await?;
+
+    Ok(HttpResponse::Ok().json(PublicProfileResponse::from(user)))
+}
+
 pub async fn logout() -> HttpResponse {
     HttpResponse::Ok().json(json!({ "message": "Logged out successfully" }))
 }
@@ -1,28 +1,31 @@
+use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, web};
-use serde_json::json;
-use sqlx::{PgPool, Row};
+use health::Health;
+use sqlx::PgPool;
 
+use crate::database::Persistent;
+
+const SERVICE: &str = "actix-postgres";
+
+/// Liveness: answers without touching the database.
+pub async fn liveness() -> HttpResponse {
+    respond(Health::live(SERVICE))
+}
+
+/// Readiness, and `/api/health`: 503 while the database is unreachable.
 pub async fn health_check(pool: web::Data<PgPool>) -> HttpResponse {
-    let db_status = sqlx::query("SELECT 1 as one")
-        .fetch_one(pool.get_ref())
+    respond(Health::ready(SERVICE, ping(&pool)).await)
+}
+
+async fn ping(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1")
+        .persistent_statement()
+        .execute(pool)
         .await
-        .map(|row: sqlx::postgres::PgRow| {
-            let _: i32 = row.get("one");
-            "healthy"
-        })
-        .unwrap_or("unhealthy");
+        .map(|_| ())
+}
 
-    if db_status == "healthy" {
-        HttpResponse::Ok().json(json!({
-            "status": "ok",
-            "database": db_status,
-            "service": "actix-postgres",
-        }))
-    } else {
-        HttpResponse::ServiceUnavailable().json(json!({
-            "status": "error",
-            "database": db_status,
-            "service": "actix-postgres",
-        }))
-    }
+fn respond(health: Health) -> HttpResponse {
+    let status = StatusCode::from_u16(health.status_code()).expect("200 or 503");
+    HttpResponse::build(status).json(health)
 }
@@ -1,10 +1,15 @@
+mod admin;
 mod articles;
 mod auth;
 mod health;
 
+pub use admin::{get_config, list_feature_flags, send_email, update_feature_flag};
 pub use articles::{
     create_article, delete_article, favorite_article, get_article, list_articles,
-    unfavorite_article, update_article,
+    list_user_articles, list_user_favorites, unfavorite_article, update_article,
 };
-pub use auth::{get_user, login, logout, register};
-pub use health::health_check;
+pub use auth::{
+    get_profile, get_user, login, login_two_factor, logout, register, setup_two_factor,
+    verify_two_factor,
+};
+pub use health::{health_check, liveness};
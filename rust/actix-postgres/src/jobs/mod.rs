@@ -1,3 +1,7 @@
+#[allow(dead_code)]
+mod notification;
 mod queue;
 
+#[allow(unused_imports)]
+pub use notification::NotificationHandler;
 pub use queue::JobQueue;
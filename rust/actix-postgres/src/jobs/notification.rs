@@ -0,0 +1,31 @@
+use domain_events::{ArticleCreated, Envelope};
+use job_worker::Job;
+use tracing::instrument;
+
+#[allow(dead_code)]
+pub struct NotificationHandler;
+
+impl NotificationHandler {
+    #[instrument(name = "job.notification.handle", skip(job), fields(job_id = job.id))]
+    pub async fn handle(job: &Job) -> Result<(), anyhow::Error> {
+        let event = Envelope::<ArticleCreated>::from_value(job.payload.clone())?;
+        let payload = event.data;
+
+        tracing::info!(
+            event_id = %event.id,
+            article_id = payload.article_id,
+            title = %payload.title,
+            "Processing notification for new article"
+        );
+
+        // Simulate notification processing (email, push, etc.)
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        tracing::info!(
+            article_id = payload.article_id,
+            "Notification sent successfully"
+        );
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
+use domain_events::{ArticleCreated, Envelope};
+use mailer::{EMAIL_JOB_KIND, EmailJob};
 use serde::Serialize;
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use tracing::{Span, instrument};
 
+use crate::database::Persistent;
 use crate::telemetry::JOBS_ENQUEUED;
 
 #[derive(Clone)]
@@ -34,6 +37,7 @@ impl JobQueue {
         .bind(kind)
         .bind(&payload_json)
         .bind(&trace_context)
+        .persistent_statement()
         .fetch_one(&self.pool)
         .await?;
 
@@ -45,18 +49,22 @@ impl JobQueue {
         Ok(job_id)
     }
 
-    #[instrument(name = "job.enqueue_notification", skip(self))]
-    pub async fn enqueue_notification(
-        &self,
-        article_id: i32,
-        title: &str,
-    ) -> Result<i64, sqlx::Error> {
-        let payload = serde_json::json!({
-            "article_id": article_id,
-            "title": title,
-        });
+    #[instrument(
+        name = "job.enqueue_email",
+        skip(self, email),
+        fields(email.template = email.template.name())
+    )]
+    pub async fn enqueue_email(&self, email: EmailJob) -> Result<i64, sqlx::Error> {
+        self.enqueue(EMAIL_JOB_KIND, email).await
+    }
 
-        self.enqueue("notification", payload).await
+    #[instrument(
+        name = "job.enqueue_notification",
+        skip(self, event),
+        fields(article_id = event.article_id, title = %event.title)
+    )]
+    pub async fn enqueue_notification(&self, event: ArticleCreated) -> Result<i64, sqlx::Error> {
+        self.enqueue("notification", Envelope::new(event)).await
     }
 
     fn capture_trace_context(&self) -> Option<serde_json::Value> {
@@ -1,6 +1,7 @@
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod feature_flags;
 pub mod handlers;
 pub mod jobs;
 pub mod middleware;
@@ -8,6 +9,8 @@ pub mod models;
 pub mod repository;
 pub mod routes;
 pub mod services;
+pub mod synthetic;
 pub mod telemetry;
+pub mod tls;
 
 pub use config::Config;
@@ -1,9 +1,14 @@
-use actix_web::{App, HttpServer, web};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{App, HttpRequest, HttpServer, web};
 use tracing_actix_web::TracingLogger;
 
 mod config;
 mod database;
 mod error;
+mod feature_flags;
 mod handlers;
 mod jobs;
 mod middleware;
@@ -11,19 +16,26 @@ mod models;
 mod repository;
 mod routes;
 mod services;
+mod synthetic;
 mod telemetry;
+mod tls;
 
-use config::Config;
+use config::{Config, RotatingSecret};
 use database::create_pool;
+use feature_flags::FeatureFlags;
 use jobs::JobQueue;
-use middleware::MetricsMiddleware;
-use repository::{ArticleRepository, FavoriteRepository, UserRepository};
+use middleware::{HttpRootSpan, MetricsMiddleware, RequestIdMiddleware, json_config, load_policy};
+use repository::{
+    ArticleRepository, FavoriteRepository, FeatureFlagRepository, TwoFactorRepository,
+    UserRepository,
+};
 use services::{ArticleService, AuthService};
-use telemetry::{TelemetryGuard, init_metrics, init_telemetry};
+use synthetic::SyntheticProbe;
+use telemetry::{TelemetryGuard, init_metrics, init_telemetry, record_connection};
 
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
-    let config = Config::from_env();
+    let config = Config::load().await?;
 
     let telemetry_guard: TelemetryGuard = init_telemetry(&config)?;
     init_metrics();
@@ -31,6 +43,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!(
         port = config.port,
         environment = %config.environment,
+        secrets_provider = config.secrets.provider_name().unwrap_or("none"),
         "Starting server"
     );
 
@@ -39,32 +52,110 @@ async fn main() -> anyhow::Result<()> {
     let user_repo = UserRepository::new(pool.clone());
     let article_repo = ArticleRepository::new(pool.clone());
     let favorite_repo = FavoriteRepository::new(pool.clone());
+    let two_factor_repo = TwoFactorRepository::new(pool.clone());
+    let feature_flags = FeatureFlags::new(
+        FeatureFlagRepository::new(pool.clone()),
+        &config.environment,
+    );
     let job_queue = JobQueue::new(pool);
 
+    let flag_count = feature_flags.refresh().await?;
+    feature_flags.spawn_refresh(Duration::from_secs(config.feature_flags_refresh_secs));
+
+    tracing::info!(flags = flag_count, "Feature flags loaded");
+
     let pool_data = web::Data::new(job_queue.pool().clone());
 
-    let auth_service = AuthService::new(user_repo, &config);
-    let article_service = ArticleService::new(article_repo, favorite_repo, job_queue);
+    let jwt_secret = RotatingSecret::new("JWT_SECRET", config.jwt_secret.clone());
+    jwt_secret.spawn_refresh(
+        config.secrets.clone(),
+        Duration::from_secs(config.secrets_refresh_secs),
+    );
+    let auth_service = AuthService::new(user_repo, two_factor_repo, jwt_secret, &config);
+    let article_service = ArticleService::new(article_repo, favorite_repo, job_queue.clone());
     let auth_data = web::Data::new(auth_service);
     let article_data = web::Data::new(article_service);
+    let flags_data = web::Data::new(feature_flags);
+    let job_queue_data = web::Data::new(job_queue);
+    let config_data = web::Data::new(config.clone());
+    let policy = Arc::new(load_policy(&config.authz_policy_file)?);
 
     let bind_addr = format!("0.0.0.0:{}", config.port);
 
-    tracing::info!(addr = %bind_addr, "Server listening");
+    let scheme = if config.tls_enabled() {
+        "https"
+    } else {
+        "http"
+    };
+    tracing::info!(addr = %bind_addr, scheme, "Server listening");
+
+    if config.synthetic_check_interval_secs > 0 {
+        SyntheticProbe::new(
+            &format!("{scheme}://127.0.0.1:{}", config.port),
+            Duration::from_secs(config.synthetic_check_timeout_secs),
+        )?
+        .spawn(Duration::from_secs(config.synthetic_check_interval_secs));
+    }
 
-    HttpServer::new(move || {
+    // MAX_CONNECTIONS is for the whole server, actix-web's limit is per
+    // worker, so the workers are set explicitly (by default to its default)
+    // to divide it between them.
+    let workers = match config.http_workers {
+        0 => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        workers => workers,
+    };
+    let max_connections_per_worker = match config.max_connections {
+        0 => usize::MAX,
+        max => max.div_ceil(workers),
+    };
+    let server = HttpServer::new(move || {
         App::new()
             .wrap(MetricsMiddleware)
-            .wrap(TracingLogger::default())
+            .wrap(TracingLogger::<HttpRootSpan>::new())
+            .wrap(RequestIdMiddleware)
             .wrap(actix_web::middleware::Compress::default())
             .app_data(pool_data.clone())
             .app_data(auth_data.clone())
             .app_data(article_data.clone())
-            .configure(routes::configure)
+            .app_data(flags_data.clone())
+            .app_data(job_queue_data.clone())
+            .app_data(config_data.clone())
+            .app_data(json_config(config_data.json_body_limit_bytes))
+            .configure(|cfg| routes::configure(cfg, &config_data, &policy))
     })
-    .bind(&bind_addr)?
-    .run()
-    .await?;
+    .workers(workers)
+    .shutdown_timeout(config.shutdown_timeout_secs)
+    .keep_alive(Duration::from_secs(config.http_keep_alive_secs))
+    .max_connections(max_connections_per_worker)
+    .on_connect(record_connection);
+    let server = if config.tls_enabled() {
+        let tls_config = tls::load_config(&config.tls_cert_path, &config.tls_key_path)?;
+        server.bind_rustls_0_23(&bind_addr, tls_config)?
+    } else if config.http2_enabled {
+        server.bind_auto_h2c(&bind_addr)?
+    } else {
+        server.bind(&bind_addr)?
+    }
+    .run();
+
+    if config.tls_enabled() && config.http_redirect_port != 0 {
+        let https_port = config.port;
+        let redirect_addr = format!("0.0.0.0:{}", config.http_redirect_port);
+        tracing::info!(addr = %redirect_addr, "Redirecting HTTP to HTTPS");
+        let redirect = HttpServer::new(move || {
+            App::new().default_service(web::to(move |req: HttpRequest| async move {
+                tls::redirect(&req, https_port)
+            }))
+        })
+        .workers(1)
+        .shutdown_timeout(config.shutdown_timeout_secs)
+        .on_connect(record_connection)
+        .bind(&redirect_addr)?
+        .run();
+        tokio::try_join!(server, redirect)?;
+    } else {
+        server.await?;
+    }
 
     tracing::info!("Server shutdown complete");
     telemetry_guard.shutdown();
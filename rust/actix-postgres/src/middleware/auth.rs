@@ -27,7 +27,35 @@ impl FromRequest for OptionalAuthUser {
     }
 }
 
-fn extract_and_validate(req: &HttpRequest, optional: bool) -> Result<Option<i32>, AppError> {
+pub struct AdminAuth;
+
+impl FromRequest for AdminAuth {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(validate_admin(req).map(|_| AdminAuth))
+    }
+}
+
+pub(super) fn validate_admin(req: &HttpRequest) -> Result<(), AppError> {
+    let auth_service = req
+        .app_data::<web::Data<AuthService>>()
+        .ok_or(AppError::Internal("AuthService not configured".to_string()))?;
+
+    let token = req
+        .headers()
+        .get("X-Admin-Token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::Unauthorized)?;
+
+    auth_service.validate_admin_token(token)
+}
+
+pub(super) fn extract_and_validate(
+    req: &HttpRequest,
+    optional: bool,
+) -> Result<Option<i32>, AppError> {
     let auth_service = req
         .app_data::<web::Data<AuthService>>()
         .ok_or(AppError::Internal("AuthService not configured".to_string()))?;
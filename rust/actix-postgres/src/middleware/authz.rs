@@ -0,0 +1,309 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{HttpMessage, web};
+use authz::{Decision, Policy, Subject};
+use opentelemetry::KeyValue;
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::auth::{extract_and_validate, validate_admin};
+use crate::error::AppError;
+use crate::services::ArticleService;
+use crate::telemetry::AUTHZ_DENIED;
+
+/// The rules in `config/policy.toml`, built in so the binary runs without
+/// the file.
+const DEFAULT_POLICY: &str = include_str!("../../config/policy.toml");
+
+/// Rules the article service relies on instead of checking ownership
+/// itself. A request no rule matches is let through, so a policy without
+/// one of these is refused.
+const REQUIRED_RULES: &[&str] = &["article.update", "article.delete"];
+
+/// `AUTHZ_POLICY_FILE`, or the built-in policy when it's empty.
+pub fn load_policy(path: &str) -> anyhow::Result<Policy> {
+    let source = if path.is_empty() {
+        DEFAULT_POLICY.to_string()
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("AUTHZ_POLICY_FILE {path}: {e}"))?
+    };
+    let policy = Policy::from_toml(&source)?;
+    policy.require(REQUIRED_RULES)?;
+    Ok(policy)
+}
+
+/// Applies the authorization policy before the handler runs. Wrap the
+/// `/api` scope: rules are written against the path under it. A request no
+/// rule matches is left to the handler and its extractors. An `owner` or
+/// `author` rule's handler gets the [`Authors`](authz::Authors) it was
+/// decided on as `web::ReqData`.
+///
+/// A refusal is an `Err`, like [`Timeout`](super::Timeout)'s, which
+/// actix-web renders.
+pub struct Authorize(pub Arc<Policy>);
+
+impl<S, B> Transform<S, ServiceRequest> for Authorize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AuthorizeService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizeService {
+            service: Rc::new(service),
+            policy: self.0.clone(),
+        }))
+    }
+}
+
+pub struct AuthorizeService<S> {
+    service: Rc<S>,
+    policy: Arc<Policy>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizeService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let policy = self.policy.clone();
+
+        Box::pin(async move {
+            let path = req.match_info().unprocessed().to_string();
+            let Some(matched) = policy.find(req.method().as_str(), &path) else {
+                return service.call(req).await;
+            };
+
+            let user = matched
+                .needs_user()
+                .then(|| extract_and_validate(req.request(), false));
+            let admin = validate_admin(req.request());
+            let user_id = user
+                .as_ref()
+                .and_then(|user| user.as_ref().ok().copied().flatten());
+            let subject = Subject {
+                user_id,
+                admin: admin.is_ok(),
+            };
+            let article =
+                match (matched.article(), user_id) {
+                    (Some(slug), Some(_)) => {
+                        let articles = req.app_data::<web::Data<ArticleService>>().ok_or(
+                            AppError::Internal("ArticleService not configured".to_string()),
+                        )?;
+                        articles.authors(slug).await?
+                    }
+                    _ => None,
+                };
+
+            let decision = matched.decide(&subject, article.as_ref());
+            if decision != Decision::Allow {
+                AUTHZ_DENIED.add(
+                    1,
+                    &[
+                        KeyValue::new("authz.rule", matched.rule().to_string()),
+                        KeyValue::new("authz.decision", decision.as_str()),
+                    ],
+                );
+            }
+            let error = match decision {
+                Decision::Allow => {
+                    // The service changes the article only while it is still
+                    // theirs, so a change of owner since can't ride on this.
+                    if let Some(authors) = article {
+                        req.extensions_mut().insert(authors);
+                    }
+                    return service.call(req).await;
+                }
+                // The credential's own error says what was wrong with it.
+                Decision::Unauthenticated => match (user, admin) {
+                    (Some(Err(e)), _) => e,
+                    (_, Err(e)) if !matched.needs_user() => e,
+                    _ => AppError::Unauthorized,
+                },
+                Decision::Forbidden => AppError::Forbidden,
+                Decision::NotFound => AppError::NotFound("Article not found".to_string()),
+            };
+            Err(error.into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, try_call_service};
+    use actix_web::{App, HttpResponse};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::config::{Config, Layers, RotatingSecret};
+    use crate::repository::{TwoFactorRepository, UserRepository};
+    use crate::services::AuthService;
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    /// Never connects: nothing here reaches the database.
+    fn auth_service() -> AuthService {
+        let env = [
+            ("DATABASE_URL", "postgres://localhost/db"),
+            ("JWT_SECRET", "test-secret"),
+            ("ADMIN_TOKEN", "admin-secret"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let config =
+            Config::from_layers(Layers::from_parts(env, HashMap::new(), &[]), None).unwrap();
+        let pool = PgPool::connect_lazy(&config.database_url).unwrap();
+        AuthService::new(
+            UserRepository::new(pool.clone()),
+            TwoFactorRepository::new(pool),
+            RotatingSecret::new("JWT_SECRET", config.jwt_secret.clone()),
+            &config,
+        )
+    }
+
+    #[test]
+    fn test_policy_without_an_ownership_rule_is_refused() {
+        let path = std::env::temp_dir().join(format!("policy-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            DEFAULT_POLICY.replace("article.delete", "article.remove"),
+        )
+        .unwrap();
+
+        let err = load_policy(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            err.to_string()
+                .contains("article.delete: required rule is missing"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_built_in_policy_covers_the_ownership_checks() {
+        let policy = load_policy("").unwrap();
+
+        for (method, path, rule) in [
+            ("PUT", "/articles/a", "article.update"),
+            ("DELETE", "/articles/a", "article.delete"),
+            ("GET", "/admin/config", "admin"),
+        ] {
+            assert_eq!(
+                policy.find(method, path).map(|m| m.rule().to_string()),
+                Some(rule.to_string()),
+                "{method} {path}"
+            );
+        }
+        assert!(policy.find("GET", "/articles/a").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_rule_sees_the_slug_the_handler_extracts() {
+        let policy = Arc::new(load_policy("").unwrap());
+        let seen = Rc::new(std::cell::RefCell::new(None));
+        let app = {
+            let seen = seen.clone();
+            init_service(
+                App::new().service(
+                    web::scope("/api")
+                        // The lookup `Authorize` starts with.
+                        .wrap_fn(move |req, service| {
+                            let path = req.match_info().unprocessed().to_string();
+                            *seen.borrow_mut() = policy
+                                .find(req.method().as_str(), &path)
+                                .and_then(|m| m.article().map(String::from));
+                            service.call(req)
+                        })
+                        .route(
+                            "/articles/{slug}",
+                            web::put()
+                                .to(|slug: web::Path<String>| async move { slug.into_inner() }),
+                        ),
+                ),
+            )
+            .await
+        };
+
+        for (uri, slug) in [
+            ("/api/articles/caf%C3%A9", "café"),
+            ("/api/articles/a%2Fb", "a/b"),
+        ] {
+            let response = call_service(&app, TestRequest::put().uri(uri).to_request()).await;
+            assert_eq!(
+                actix_web::test::read_body(response).await,
+                slug.as_bytes(),
+                "{uri}"
+            );
+            assert_eq!(seen.borrow().as_deref(), Some(slug), "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rules_apply_to_the_path_under_the_scope() {
+        let app = init_service(
+            App::new().app_data(web::Data::new(auth_service())).service(
+                web::scope("/api")
+                    .wrap(Authorize(Arc::new(load_policy("").unwrap())))
+                    .route("/admin/config", web::get().to(ok))
+                    .route("/articles/{slug}", web::get().to(ok))
+                    .route("/articles/{slug}", web::put().to(ok)),
+            ),
+        )
+        .await;
+
+        for request in [
+            TestRequest::get().uri("/api/admin/config"),
+            TestRequest::get()
+                .uri("/api/admin/config")
+                .insert_header(("X-Admin-Token", "wrong")),
+            TestRequest::put()
+                .uri("/api/articles/a")
+                .insert_header(("Authorization", "Bearer not-a-jwt")),
+        ] {
+            let err = try_call_service(&app, request.to_request())
+                .await
+                .unwrap_err();
+            assert_eq!(err.error_response().status(), StatusCode::UNAUTHORIZED);
+        }
+
+        for request in [
+            TestRequest::get()
+                .uri("/api/admin/config")
+                .insert_header(("X-Admin-Token", "admin-secret")),
+            TestRequest::get().uri("/api/articles/a"),
+        ] {
+            assert_eq!(
+                call_service(&app, request.to_request()).await.status(),
+                StatusCode::OK
+            );
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::error::JsonPayloadError;
+use actix_web::web;
+
+use crate::error::AppError;
+
+/// `web::Json` bodies up to `limit` bytes. A larger one is a 413 and any
+/// other unreadable body a 400, both with the usual JSON error body rather
+/// than actix-web's plain text.
+pub fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            match err {
+                JsonPayloadError::OverflowKnownLength { limit, .. }
+                | JsonPayloadError::Overflow { limit } => AppError::PayloadTooLarge { limit },
+                other => AppError::Validation(other.to_string()),
+            }
+            .into()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, HttpResponse, test};
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    async fn echo(body: web::Json<Value>) -> HttpResponse {
+        HttpResponse::Ok().json(body.into_inner())
+    }
+
+    #[actix_web::test]
+    async fn test_json_limit_maps_to_413_and_400() {
+        let app = test::init_service(
+            App::new()
+                .app_data(json_config(32))
+                .route("/echo", web::post().to(echo)),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/echo")
+            .set_json(json!({ "title": "short" }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, request).await.status(),
+            StatusCode::OK
+        );
+
+        let request = test::TestRequest::post()
+            .uri("/echo")
+            .set_json(json!({ "body": "x".repeat(64) }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "Payload too large: limit is 32 bytes");
+
+        let request = test::TestRequest::post()
+            .uri("/echo")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(response).await;
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Validation error")
+        );
+    }
+}
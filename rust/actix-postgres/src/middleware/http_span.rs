@@ -0,0 +1,205 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Version;
+use actix_web::{Error, HttpMessage};
+use tracing::Span;
+use tracing_actix_web::RootSpanBuilder;
+
+use super::request_id::RequestId;
+
+/// The HTTP span, with the same attributes as the axum example's: the route
+/// pattern (the path when no route matched), `http.request_id` from
+/// [`RequestId`], the user agent and `http.response.status_code`. 5xx
+/// responses mark it `ERROR`. tracing-actix-web's own root span generates a
+/// `request_id` that never matches what the caller sent, names unmatched
+/// routes `default` and spells HTTP/2 as `2.0`.
+pub struct HttpRootSpan;
+
+impl RootSpanBuilder for HttpRootSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let method = request.method().as_str();
+        let route = request
+            .match_pattern()
+            .unwrap_or_else(|| request.path().to_string());
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default();
+        let scheme = request.connection_info().scheme().to_string();
+
+        tracing::info_span!(
+            "HTTP request",
+            otel.name = %format!("{method} {route}"),
+            otel.kind = "server",
+            http.method = %method,
+            http.route = %route,
+            http.target = %request.uri(),
+            http.scheme = %scheme,
+            http.flavor = ?request.version(),
+            network.protocol.version = protocol_version(request.version()),
+            http.user_agent = request
+                .headers()
+                .get("user-agent")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or(""),
+            http.request_id = %request_id,
+            http.response.status_code = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+        )
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        // An `Err` is an error from a middleware further in, e.g. a timeout,
+        // that actix-web renders after this span closes.
+        let (status, error) = match outcome {
+            Ok(res) => (res.status(), res.response().error()),
+            Err(err) => (err.as_response_error().status_code(), Some(err)),
+        };
+        span.record("http.response.status_code", i64::from(status.as_u16()));
+        if status.is_server_error() {
+            span.record("otel.status_code", "ERROR");
+            if let Some(error) = error {
+                span.record("exception.message", error.to_string());
+            }
+        } else {
+            span.record("otel.status_code", "OK");
+        }
+    }
+}
+
+/// `network.protocol.version` for a request, as OpenTelemetry spells it.
+fn protocol_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_11 => "1.1",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, HttpResponse, test, web};
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::Status;
+    use tracing_actix_web::TracingLogger;
+
+    use super::*;
+    use crate::error::AppError;
+    use crate::middleware::{MetricsMiddleware, RequestIdMiddleware, request_id::X_REQUEST_ID};
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    /// The telemetry middlewares from `main`, around stub routes.
+    macro_rules! app {
+        () => {
+            test::init_service(
+                App::new()
+                    .wrap(MetricsMiddleware)
+                    .wrap(TracingLogger::<HttpRootSpan>::new())
+                    .wrap(RequestIdMiddleware)
+                    .route(
+                        "/api/teapots/{id}",
+                        web::get().to(|| async { HttpResponse::new(StatusCode::IM_A_TEAPOT) }),
+                    )
+                    .route(
+                        "/api/unavailable",
+                        web::get().to(|| async {
+                            Err::<HttpResponse, _>(AppError::Internal("pool closed".to_string()))
+                        }),
+                    ),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn test_http_span_has_request_attributes() {
+        let spans = SpanCapture::start();
+        let app = app!();
+        let request = test::TestRequest::get()
+            .uri("/api/teapots/7?page=2")
+            .insert_header((X_REQUEST_ID, "req-123"))
+            .insert_header(("user-agent", "curl/8.5.0"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        drop(response);
+
+        let span = spans.span("GET /api/teapots/{id}");
+        assert_eq!(span.status, Status::Ok);
+        assert_attributes(
+            &span,
+            &[
+                KeyValue::new("http.method", "GET"),
+                KeyValue::new("http.route", "/api/teapots/{id}"),
+                KeyValue::new("http.target", "/api/teapots/7?page=2"),
+                KeyValue::new("http.user_agent", "curl/8.5.0"),
+                KeyValue::new("http.request_id", "req-123"),
+                KeyValue::new("network.protocol.version", "1.1"),
+                KeyValue::new("http.response.status_code", 418),
+            ],
+        );
+        let attributes = [
+            KeyValue::new("http.route", "/api/teapots/{id}"),
+            KeyValue::new("http.status_code", "418"),
+            KeyValue::new("http.status_class", "4xx"),
+        ];
+        assert_metric_recorded("http.requests.total", &attributes);
+        assert_metric_recorded("http.request.duration", &attributes);
+    }
+
+    #[actix_web::test]
+    async fn test_http_span_marks_server_errors() {
+        let spans = SpanCapture::start();
+        let app = app!();
+        let request = test::TestRequest::get()
+            .uri("/api/unavailable")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let request_id = response
+            .headers()
+            .get(X_REQUEST_ID)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        drop(response);
+
+        let span = spans.span("GET /api/unavailable");
+        assert!(matches!(span.status, Status::Error { .. }));
+        assert_attributes(
+            &span,
+            &[
+                KeyValue::new("http.request_id", request_id),
+                KeyValue::new("http.response.status_code", 500),
+            ],
+        );
+        assert_metric_recorded(
+            "http.requests.total",
+            &[
+                KeyValue::new("http.route", "/api/unavailable"),
+                KeyValue::new("http.status_class", "5xx"),
+            ],
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_unmatched_route_is_named_by_path() {
+        let spans = SpanCapture::start();
+        let app = app!();
+        let request = test::TestRequest::get().uri("/nowhere").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        drop(response);
+
+        let span = spans.span("GET /nowhere");
+        assert_eq!(span.status, Status::Ok);
+        assert_attributes(&span, &[KeyValue::new("http.response.status_code", 404)]);
+    }
+}
@@ -5,8 +5,10 @@ use std::future::{Future, Ready, ready};
 use std::pin::Pin;
 use std::time::Instant;
 
-use crate::telemetry::{HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION};
+use crate::telemetry::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL};
 
+/// Records `http.requests.total` and `http.request.duration` by method, route
+/// pattern, status code and status class, like the axum example.
 pub struct MetricsMiddleware;
 
 impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
@@ -47,26 +49,35 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let method = req.method().to_string();
-        let path = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+        let path = req
+            .match_pattern()
+            .unwrap_or_else(|| req.path().to_string());
         let start = Instant::now();
 
         let fut = self.service.call(req);
 
         Box::pin(async move {
-            let res = fut.await?;
-            let status = res.status().as_u16().to_string();
+            // An error from a middleware further in, e.g. a timeout, is
+            // still a response once actix-web renders it.
+            let res = fut.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            }
+            .as_u16();
             let duration = start.elapsed().as_secs_f64() * 1000.0;
 
             let labels = [
                 KeyValue::new("http.method", method),
                 KeyValue::new("http.route", path),
-                KeyValue::new("http.status_code", status),
+                KeyValue::new("http.status_code", status.to_string()),
+                KeyValue::new("http.status_class", format!("{}xx", status / 100)),
             ];
 
             HTTP_REQUESTS_TOTAL.add(1, &labels);
             HTTP_REQUEST_DURATION.record(duration, &labels);
 
-            Ok(res)
+            res
         })
     }
 }
@@ -1,5 +1,15 @@
 mod auth;
+mod authz;
+mod body_limit;
+mod http_span;
 mod metrics;
+mod request_id;
+mod timeout;
 
-pub use auth::{AuthUser, OptionalAuthUser};
+pub use auth::{AdminAuth, AuthUser, OptionalAuthUser};
+pub use authz::{Authorize, load_policy};
+pub use body_limit::json_config;
+pub use http_span::HttpRootSpan;
 pub use metrics::MetricsMiddleware;
+pub use request_id::RequestIdMiddleware;
+pub use timeout::Timeout;
@@ -0,0 +1,124 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage};
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use uuid::Uuid;
+
+pub const X_REQUEST_ID: &str = "x-request-id";
+
+/// The caller's `x-request-id`, or a generated UUID when it sends none.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Sets [`RequestId`] on each request and echoes it in the `x-request-id`
+/// response header. Wrap it outside `TracingLogger`, so the root span can
+/// record it.
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddlewareService { service }))
+    }
+}
+
+pub struct RequestIdMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(X_REQUEST_ID), value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+    use tracing_actix_web::TracingLogger;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::error::{AppError, AppResult};
+    use crate::middleware::HttpRootSpan;
+    use crate::telemetry::correlation::CorrelationLayer;
+
+    async fn missing() -> AppResult<String> {
+        Err(AppError::NotFound("Article not found".to_string()))
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_is_echoed_and_in_error_body() {
+        let subscriber = tracing_subscriber::registry().with(CorrelationLayer::default());
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = test::init_service(
+            App::new()
+                .wrap(TracingLogger::<HttpRootSpan>::new())
+                .wrap(RequestIdMiddleware)
+                .route("/missing", web::get().to(missing)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((X_REQUEST_ID, "req-123"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.headers().get(X_REQUEST_ID).unwrap(), "req-123");
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "Not found: Article not found");
+        assert_eq!(body["request_id"], "req-123");
+
+        let request = test::TestRequest::get().uri("/missing").to_request();
+        let response = test::call_service(&app, request).await;
+        let generated = response.headers().get(X_REQUEST_ID).unwrap();
+        let generated = generated.to_str().unwrap().to_string();
+        assert!(Uuid::parse_str(&generated).is_ok());
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["request_id"], generated.as_str());
+    }
+}
@@ -0,0 +1,130 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::error::AppError;
+
+/// Fails the request with [`AppError::Timeout`], a 408, when the handler
+/// takes longer than the deadline, dropping its future, like axum's
+/// `TimeoutLayer`. Wrap a scope to give it its own deadline;
+/// `Duration::ZERO` turns it off.
+///
+/// The timeout is an `Err` rather than a response, since building one needs
+/// the request the scope has already routed: the middlewares outside see an
+/// error, and actix-web renders it.
+pub struct Timeout(pub Duration);
+
+impl<S, B> Transform<S, ServiceRequest> for Timeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = TimeoutService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TimeoutService {
+            service,
+            timeout: self.0,
+        }))
+    }
+}
+
+pub struct TimeoutService<S> {
+    service: S,
+    timeout: Duration,
+}
+
+impl<S, B> Service<ServiceRequest> for TimeoutService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let timeout = self.timeout;
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            if timeout.is_zero() {
+                return fut.await;
+            }
+            tokio::time::timeout(timeout, fut)
+                .await
+                .unwrap_or_else(|_| {
+                    tracing::warn!(timeout_ms = timeout.as_millis() as u64, "Request timed out");
+                    Err(AppError::Timeout.into())
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, HttpResponse, test, web};
+
+    use super::*;
+
+    async fn slow() -> HttpResponse {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_timeout_applies_per_scope() {
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/strict")
+                        .wrap(Timeout(Duration::from_millis(20)))
+                        .route("/slow", web::get().to(slow)),
+                )
+                .service(
+                    web::scope("/relaxed")
+                        .wrap(Timeout(Duration::from_secs(5)))
+                        .route("/slow", web::get().to(slow)),
+                )
+                .service(
+                    web::scope("/off")
+                        .wrap(Timeout(Duration::ZERO))
+                        .route("/slow", web::get().to(slow)),
+                ),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/strict/slow").to_request();
+        let err = test::try_call_service(&app, request).await.unwrap_err();
+        let response = err.error_response();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Request timed out");
+        assert_eq!(body["status"], 408);
+
+        for uri in ["/relaxed/slow", "/off/slow"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            assert_eq!(
+                test::call_service(&app, request).await.status(),
+                StatusCode::OK
+            );
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use sqlx::FromRow;
 use time::OffsetDateTime;
 
@@ -28,6 +28,7 @@ pub struct ArticleWithAuthor {
     pub favorites_count: i32,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    pub author_username: String,
     pub author_name: String,
     pub author_email: String,
     pub author_bio: String,
@@ -76,6 +77,7 @@ impl ArticleDto {
             author: ProfileResponse {
                 id: article.author_id,
                 email: article.author_email,
+                username: article.author_username,
                 name: article.author_name,
                 bio: article.author_bio,
                 image: article.author_image,
@@ -84,33 +86,6 @@ impl ArticleDto {
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateArticleInput {
-    pub title: String,
-    pub description: Option<String>,
-    pub body: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct UpdateArticleInput {
-    pub title: Option<String>,
-    pub description: Option<String>,
-    pub body: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ListArticlesQuery {
-    #[serde(default = "default_limit")]
-    pub limit: i64,
-    #[serde(default)]
-    pub offset: i64,
-    pub author: Option<String>,
-}
-
-fn default_limit() -> i64 {
-    20
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +102,7 @@ mod tests {
             favorites_count: 10,
             created_at: datetime!(2024-01-15 10:30:00 UTC),
             updated_at: datetime!(2024-01-16 15:45:00 UTC),
+            author_username: "john".to_string(),
             author_name: "John Doe".to_string(),
             author_email: "john@example.com".to_string(),
             author_bio: "A test user".to_string(),
@@ -147,6 +123,7 @@ mod tests {
         assert_eq!(dto.favorites_count, article.favorites_count);
         assert!(dto.favorited);
         assert_eq!(dto.author.id, article.author_id);
+        assert_eq!(dto.author.username, article.author_username);
         assert_eq!(dto.author.name, article.author_name);
         assert_eq!(dto.author.email, article.author_email);
     }
@@ -184,60 +161,4 @@ mod tests {
         assert!(json.contains("\"articles\":["));
         assert!(json.contains("\"total\":1"));
     }
-
-    #[test]
-    fn test_create_article_input_deserialization() {
-        let json =
-            r#"{"title": "New Article", "description": "Description", "body": "Body content"}"#;
-        let input: CreateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, "New Article");
-        assert_eq!(input.description, Some("Description".to_string()));
-        assert_eq!(input.body, "Body content");
-    }
-
-    #[test]
-    fn test_create_article_input_without_description() {
-        let json = r#"{"title": "New Article", "body": "Body content"}"#;
-        let input: CreateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, "New Article");
-        assert!(input.description.is_none());
-        assert_eq!(input.body, "Body content");
-    }
-
-    #[test]
-    fn test_update_article_input_partial() {
-        let json = r#"{"title": "Updated Title"}"#;
-        let input: UpdateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, Some("Updated Title".to_string()));
-        assert!(input.description.is_none());
-        assert!(input.body.is_none());
-    }
-
-    #[test]
-    fn test_list_articles_query_defaults() {
-        let json = r#"{}"#;
-        let query: ListArticlesQuery =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(query.limit, 20);
-        assert_eq!(query.offset, 0);
-        assert!(query.author.is_none());
-    }
-
-    #[test]
-    fn test_list_articles_query_with_params() {
-        let json = r#"{"limit": 10, "offset": 5, "author": "john"}"#;
-        let query: ListArticlesQuery =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(query.limit, 10);
-        assert_eq!(query.offset, 5);
-        assert_eq!(query.author, Some("john".to_string()));
-    }
 }
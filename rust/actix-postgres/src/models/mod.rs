@@ -1,7 +1,15 @@
 mod article;
 mod favorite;
+mod feature_flag;
 mod user;
 
 pub use article::*;
+pub use blog_domain::{
+    CreateArticleInput, ListArticlesQuery, LoginInput, ProfileResponse, PublicProfile,
+    PublicProfileResponse, RecoveryCodesResponse, TwoFactorChallenge, TwoFactorChallengeResponse,
+    TwoFactorCodeInput, TwoFactorLoginInput, TwoFactorSetupResponse, UpdateArticleInput,
+    UserArticlesQuery,
+};
 pub use favorite::*;
+pub use feature_flag::*;
 pub use user::*;
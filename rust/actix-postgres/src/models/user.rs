@@ -2,12 +2,15 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
 
+use super::{ProfileResponse, PublicProfile, PublicProfileResponse, TwoFactorChallenge};
+
 #[derive(Debug, Clone, FromRow, Serialize)]
 pub struct User {
     pub id: i32,
     pub email: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    pub username: String,
     pub name: String,
     pub bio: String,
     pub image: String,
@@ -21,24 +24,29 @@ pub struct User {
 pub struct RegisterInput {
     pub email: String,
     pub password: String,
+    /// Unique handle for `/api/profiles/{username}` and `?author=`.
+    pub username: String,
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct LoginInput {
-    pub email: String,
-    pub password: String,
-}
-
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub user: UserWithToken,
 }
 
+/// What a correct password gets: the user and their token, or, with
+/// two-factor authentication enabled, a challenge for the code.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Authenticated(UserWithToken),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserWithToken {
     pub id: i32,
     pub email: String,
+    pub username: String,
     pub name: String,
     pub bio: String,
     pub image: String,
@@ -50,6 +58,7 @@ impl UserWithToken {
         Self {
             id: user.id,
             email: user.email.clone(),
+            username: user.username.clone(),
             name: user.name.clone(),
             bio: user.bio.clone(),
             image: user.image.clone(),
@@ -58,20 +67,12 @@ impl UserWithToken {
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ProfileResponse {
-    pub id: i32,
-    pub email: String,
-    pub name: String,
-    pub bio: String,
-    pub image: String,
-}
-
 impl From<User> for ProfileResponse {
     fn from(user: User) -> Self {
         Self {
             id: user.id,
             email: user.email,
+            username: user.username,
             name: user.name,
             bio: user.bio,
             image: user.image,
@@ -79,6 +80,19 @@ impl From<User> for ProfileResponse {
     }
 }
 
+impl From<User> for PublicProfileResponse {
+    fn from(user: User) -> Self {
+        Self {
+            profile: PublicProfile {
+                username: user.username,
+                name: user.name,
+                bio: user.bio,
+                image: user.image,
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,6 +103,7 @@ mod tests {
             id: 1,
             email: "test@example.com".to_string(),
             password_hash: "hashed_password".to_string(),
+            username: "test-user".to_string(),
             name: "Test User".to_string(),
             bio: "A bio".to_string(),
             image: "https://example.com/avatar.jpg".to_string(),
@@ -137,24 +152,16 @@ mod tests {
 
     #[test]
     fn test_register_input_deserialization() {
-        let json = r#"{"email": "new@example.com", "password": "secret123", "name": "New User"}"#;
+        let json = r#"{"email": "new@example.com", "password": "secret123", "username": "new-user", "name": "New User"}"#;
         let input: RegisterInput =
             serde_json::from_str(json).expect("deserialization should succeed");
 
         assert_eq!(input.email, "new@example.com");
         assert_eq!(input.password, "secret123");
+        assert_eq!(input.username, "new-user");
         assert_eq!(input.name, "New User");
     }
 
-    #[test]
-    fn test_login_input_deserialization() {
-        let json = r#"{"email": "test@example.com", "password": "secret123"}"#;
-        let input: LoginInput = serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.email, "test@example.com");
-        assert_eq!(input.password, "secret123");
-    }
-
     #[test]
     fn test_user_response_serialization() {
         let user = create_test_user();
@@ -168,6 +175,15 @@ mod tests {
         assert!(json.contains("\"token\":\"token\""));
     }
 
+    #[test]
+    fn test_public_profile_omits_email() {
+        let response = PublicProfileResponse::from(create_test_user());
+
+        let json = serde_json::to_string(&response).expect("serialization should succeed");
+        assert!(json.contains("\"profile\":{\"username\":\"test-user\""));
+        assert!(!json.contains("test@example.com"));
+    }
+
     #[test]
     fn test_profile_response_serialization() {
         let user = create_test_user();
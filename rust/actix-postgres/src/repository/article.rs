@@ -1,6 +1,7 @@
 use sqlx::{PgPool, Row};
 use tracing::instrument;
 
+use crate::database::Traced;
 use crate::models::{Article, ArticleWithAuthor};
 
 #[derive(Clone)]
@@ -34,6 +35,7 @@ impl ArticleRepository {
         .bind(description)
         .bind(body)
         .bind(author_id)
+        .traced(&self.pool)
         .fetch_one(&self.pool)
         .await
     }
@@ -45,7 +47,7 @@ impl ArticleRepository {
             SELECT
                 a.id, a.slug, a.title, a.description, a.body, a.author_id,
                 a.favorites_count, a.created_at, a.updated_at,
-                u.name as author_name, u.email as author_email,
+                u.username as author_username, u.name as author_name, u.email as author_email,
                 u.bio as author_bio, u.image as author_image
             FROM articles a
             JOIN users u ON a.author_id = u.id
@@ -53,10 +55,20 @@ impl ArticleRepository {
             "#,
         )
         .bind(slug)
+        .traced(&self.pool)
         .fetch_optional(&self.pool)
         .await
     }
 
+    #[instrument(name = "db.article.find_owner", skip(self))]
+    pub async fn find_owner(&self, slug: &str) -> Result<Option<i32>, sqlx::Error> {
+        sqlx::query_scalar::<_, i32>("SELECT author_id FROM articles WHERE slug = $1")
+            .bind(slug)
+            .traced(&self.pool)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
     #[instrument(name = "db.article.find_by_id", skip(self))]
     pub async fn find_by_id(&self, id: i32) -> Result<Option<ArticleWithAuthor>, sqlx::Error> {
         sqlx::query_as::<_, ArticleWithAuthor>(
@@ -64,7 +76,7 @@ impl ArticleRepository {
             SELECT
                 a.id, a.slug, a.title, a.description, a.body, a.author_id,
                 a.favorites_count, a.created_at, a.updated_at,
-                u.name as author_name, u.email as author_email,
+                u.username as author_username, u.name as author_name, u.email as author_email,
                 u.bio as author_bio, u.image as author_image
             FROM articles a
             JOIN users u ON a.author_id = u.id
@@ -72,6 +84,7 @@ impl ArticleRepository {
             "#,
         )
         .bind(id)
+        .traced(&self.pool)
         .fetch_optional(&self.pool)
         .await
     }
@@ -81,26 +94,31 @@ impl ArticleRepository {
         &self,
         limit: i64,
         offset: i64,
-        author_name: Option<&str>,
+        author: Option<&str>,
+        rank_by_favorites: bool,
     ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
-        if let Some(author) = author_name {
+        if let Some(author) = author {
             sqlx::query_as::<_, ArticleWithAuthor>(
                 r#"
                 SELECT
                     a.id, a.slug, a.title, a.description, a.body, a.author_id,
                     a.favorites_count, a.created_at, a.updated_at,
-                    u.name as author_name, u.email as author_email,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
                     u.bio as author_bio, u.image as author_image
                 FROM articles a
                 JOIN users u ON a.author_id = u.id
-                WHERE u.name = $1
-                ORDER BY a.created_at DESC
+                WHERE u.username = $1
+                ORDER BY
+                    CASE WHEN $4 THEN a.favorites_count ELSE 0 END DESC,
+                    a.created_at DESC
                 LIMIT $2 OFFSET $3
                 "#,
             )
             .bind(author)
             .bind(limit)
             .bind(offset)
+            .bind(rank_by_favorites)
+            .traced(&self.pool)
             .fetch_all(&self.pool)
             .await
         } else {
@@ -109,37 +127,116 @@ impl ArticleRepository {
                 SELECT
                     a.id, a.slug, a.title, a.description, a.body, a.author_id,
                     a.favorites_count, a.created_at, a.updated_at,
-                    u.name as author_name, u.email as author_email,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
                     u.bio as author_bio, u.image as author_image
                 FROM articles a
                 JOIN users u ON a.author_id = u.id
-                ORDER BY a.created_at DESC
+                ORDER BY
+                    CASE WHEN $3 THEN a.favorites_count ELSE 0 END DESC,
+                    a.created_at DESC
                 LIMIT $1 OFFSET $2
                 "#,
             )
             .bind(limit)
             .bind(offset)
+            .bind(rank_by_favorites)
+            .traced(&self.pool)
             .fetch_all(&self.pool)
             .await
         }
     }
 
+    /// Newest first. Filters by `author_id` rather than by name, which
+    /// another user can share.
+    #[instrument(name = "db.article.list_by_author", skip(self))]
+    pub async fn list_by_author(
+        &self,
+        author_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        sqlx::query_as::<_, ArticleWithAuthor>(
+            r#"
+            SELECT
+                a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                a.favorites_count, a.created_at, a.updated_at,
+                u.username as author_username, u.name as author_name, u.email as author_email,
+                u.bio as author_bio, u.image as author_image
+            FROM articles a
+            JOIN users u ON a.author_id = u.id
+            WHERE a.author_id = $1
+            ORDER BY a.created_at DESC, a.id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(author_id)
+        .bind(limit)
+        .bind(offset)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.article.count_by_author", skip(self))]
+    pub async fn count_by_author(&self, author_id: i32) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM articles WHERE author_id = $1")
+            .bind(author_id)
+            .traced(&self.pool)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get::<i64, _>("count"))
+    }
+
+    /// Most recently favorited first.
+    #[instrument(name = "db.article.list_favorited_by", skip(self))]
+    pub async fn list_favorited_by(
+        &self,
+        user_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        sqlx::query_as::<_, ArticleWithAuthor>(
+            r#"
+            SELECT
+                a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                a.favorites_count, a.created_at, a.updated_at,
+                u.username as author_username, u.name as author_name, u.email as author_email,
+                u.bio as author_bio, u.image as author_image
+            FROM favorites f
+            JOIN articles a ON a.id = f.article_id
+            JOIN users u ON a.author_id = u.id
+            WHERE f.user_id = $1
+            ORDER BY f.created_at DESC, f.id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
     #[instrument(name = "db.article.count", skip(self))]
-    pub async fn count(&self, author_name: Option<&str>) -> Result<i64, sqlx::Error> {
-        let row = if let Some(author) = author_name {
+    pub async fn count(&self, author: Option<&str>) -> Result<i64, sqlx::Error> {
+        let row = if let Some(author) = author {
             sqlx::query(
                 r#"
                 SELECT COUNT(*) as count
                 FROM articles a
                 JOIN users u ON a.author_id = u.id
-                WHERE u.name = $1
+                WHERE u.username = $1
                 "#,
             )
             .bind(author)
+            .traced(&self.pool)
             .fetch_one(&self.pool)
             .await?
         } else {
             sqlx::query("SELECT COUNT(*) as count FROM articles")
+                .traced(&self.pool)
                 .fetch_one(&self.pool)
                 .await?
         };
@@ -147,15 +244,18 @@ impl ArticleRepository {
         Ok(row.get::<i64, _>("count"))
     }
 
+    /// `None` unless `owner` still owns the article, as when the
+    /// authorization policy allowed the change. So is `delete`'s `false`.
     #[instrument(name = "db.article.update", skip(self))]
     pub async fn update(
         &self,
         id: i32,
+        owner: i32,
         slug: Option<&str>,
         title: Option<&str>,
         description: Option<&str>,
         body: Option<&str>,
-    ) -> Result<Article, sqlx::Error> {
+    ) -> Result<Option<Article>, sqlx::Error> {
         sqlx::query_as::<_, Article>(
             r#"
             UPDATE articles
@@ -164,7 +264,7 @@ impl ArticleRepository {
                 title = COALESCE($3, title),
                 description = COALESCE($4, description),
                 body = COALESCE($5, body)
-            WHERE id = $1
+            WHERE id = $1 AND author_id = $6
             RETURNING id, slug, title, description, body, author_id, favorites_count, created_at, updated_at
             "#,
         )
@@ -173,23 +273,28 @@ impl ArticleRepository {
         .bind(title)
         .bind(description)
         .bind(body)
-        .fetch_one(&self.pool)
+        .bind(owner)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
         .await
     }
 
     #[instrument(name = "db.article.delete", skip(self))]
-    pub async fn delete(&self, id: i32) -> Result<(), sqlx::Error> {
-        sqlx::query("DELETE FROM articles WHERE id = $1")
+    pub async fn delete(&self, id: i32, owner: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM articles WHERE id = $1 AND author_id = $2")
             .bind(id)
+            .bind(owner)
+            .traced(&self.pool)
             .execute(&self.pool)
             .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     #[instrument(name = "db.article.exists_by_slug", skip(self))]
     pub async fn exists_by_slug(&self, slug: &str) -> Result<bool, sqlx::Error> {
         let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM articles WHERE slug = $1) as exists")
             .bind(slug)
+            .traced(&self.pool)
             .fetch_one(&self.pool)
             .await?;
 
@@ -200,6 +305,7 @@ impl ArticleRepository {
     pub async fn increment_favorites(&self, id: i32) -> Result<(), sqlx::Error> {
         sqlx::query("UPDATE articles SET favorites_count = favorites_count + 1 WHERE id = $1")
             .bind(id)
+            .traced(&self.pool)
             .execute(&self.pool)
             .await?;
         Ok(())
@@ -211,6 +317,7 @@ impl ArticleRepository {
             "UPDATE articles SET favorites_count = GREATEST(favorites_count - 1, 0) WHERE id = $1",
         )
         .bind(id)
+        .traced(&self.pool)
         .execute(&self.pool)
         .await?;
         Ok(())
@@ -1,6 +1,7 @@
 use sqlx::{PgPool, Row};
 use tracing::instrument;
 
+use crate::database::Traced;
 use crate::models::Favorite;
 
 #[derive(Clone)]
@@ -25,6 +26,7 @@ impl FavoriteRepository {
         )
         .bind(user_id)
         .bind(article_id)
+        .traced(&self.pool)
         .fetch_one(&self.pool)
         .await
     }
@@ -34,6 +36,7 @@ impl FavoriteRepository {
         let result = sqlx::query("DELETE FROM favorites WHERE user_id = $1 AND article_id = $2")
             .bind(user_id)
             .bind(article_id)
+            .traced(&self.pool)
             .execute(&self.pool)
             .await?;
 
@@ -51,12 +54,24 @@ impl FavoriteRepository {
         )
         .bind(user_id)
         .bind(article_id)
+        .traced(&self.pool)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(row.get::<bool, _>("exists"))
     }
 
+    #[instrument(name = "db.favorite.count_by_user", skip(self))]
+    pub async fn count_by_user(&self, user_id: i32) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM favorites WHERE user_id = $1")
+            .bind(user_id)
+            .traced(&self.pool)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get::<i64, _>("count"))
+    }
+
     #[instrument(name = "db.favorite.is_favorited_batch", skip(self, article_ids))]
     pub async fn is_favorited_batch(
         &self,
@@ -72,6 +87,7 @@ impl FavoriteRepository {
         )
         .bind(user_id)
         .bind(article_ids)
+        .traced(&self.pool)
         .fetch_all(&self.pool)
         .await?;
 
@@ -1,7 +1,11 @@
 mod article;
 mod favorite;
+mod feature_flag;
+mod two_factor;
 mod user;
 
 pub use article::ArticleRepository;
 pub use favorite::FavoriteRepository;
+pub use feature_flag::FeatureFlagRepository;
+pub use two_factor::TwoFactorRepository;
 pub use user::UserRepository;
@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tracing::instrument;
+use two_factor::{Attempt, Enrollment, Store};
+
+use crate::database::Traced;
+
+/// `user_two_factor` and `recovery_codes`, keyed by user.
+#[derive(Clone)]
+pub struct TwoFactorRepository {
+    pool: PgPool,
+}
+
+impl TwoFactorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl Store for TwoFactorRepository {
+    type Error = sqlx::Error;
+
+    #[instrument(name = "db.two_factor.enrollment", skip(self))]
+    async fn enrollment(&self, user_id: i32) -> Result<Option<Enrollment>, sqlx::Error> {
+        let row = sqlx::query_as::<_, (String, bool, Option<i64>)>(
+            r#"
+            SELECT secret, enabled_at IS NOT NULL, last_step
+            FROM user_two_factor
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|(secret, enabled, last_step)| Enrollment {
+            secret,
+            enabled,
+            last_step,
+        }))
+    }
+
+    #[instrument(name = "db.two_factor.save_pending", skip(self, secret))]
+    async fn save_pending(&self, user_id: i32, secret: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_two_factor (user_id, secret) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+                SET secret = EXCLUDED.secret, last_step = NULL, created_at = CURRENT_TIMESTAMP
+                WHERE user_two_factor.enabled_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.two_factor.enable", skip(self, recovery_code_hashes))]
+    async fn enable(
+        &self,
+        user_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let enabled = sqlx::query(
+            r#"
+            UPDATE user_two_factor
+            SET enabled_at = CURRENT_TIMESTAMP, last_step = $2, failed_attempts = 0
+            WHERE user_id = $1 AND enabled_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .traced(&self.pool)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if !enabled {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .traced(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, unnest($2::text[])",
+        )
+        .bind(user_id)
+        .bind(recovery_code_hashes)
+        .traced(&self.pool)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    #[instrument(name = "db.two_factor.accept_step", skip(self))]
+    async fn accept_step(&self, user_id: i32, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_two_factor SET last_step = $2, failed_attempts = 0
+            WHERE user_id = $1
+                AND enabled_at IS NOT NULL
+                AND (last_step IS NULL OR last_step < $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.two_factor.use_recovery_code", skip(self, code_hash))]
+    async fn use_recovery_code(
+        &self,
+        user_id: i32,
+        code_hash: &str,
+    ) -> Result<Option<u32>, sqlx::Error> {
+        // The outer SELECT sees the codes as they were before the UPDATE.
+        let (used, remaining) = sqlx::query_as::<_, (bool, i64)>(
+            r#"
+            WITH used AS (
+                UPDATE recovery_codes SET used_at = CURRENT_TIMESTAMP
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                RETURNING id
+            ),
+            cleared AS (
+                UPDATE user_two_factor SET failed_attempts = 0
+                WHERE user_id = $1 AND EXISTS (SELECT 1 FROM used)
+            )
+            SELECT
+                EXISTS (SELECT 1 FROM used),
+                (SELECT COUNT(*) FROM recovery_codes
+                 WHERE user_id = $1 AND used_at IS NULL AND id NOT IN (SELECT id FROM used))
+            "#,
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(used.then_some(remaining as u32))
+    }
+
+    /// Postgres re-checks the `WHERE` of a concurrent `UPDATE` on the row
+    /// once the first commits, so two requests can't both take the last
+    /// attempt.
+    #[instrument(name = "db.two_factor.claim_attempt", skip(self))]
+    async fn claim_attempt(
+        &self,
+        user_id: i32,
+        max_failures: u32,
+        lockout: Duration,
+    ) -> Result<Attempt, sqlx::Error> {
+        let (claimed, retry_after_secs) = sqlx::query_as::<_, (bool, Option<f64>)>(
+            r#"
+            WITH claimed AS (
+                UPDATE user_two_factor
+                SET failed_attempts = failed_attempts + 1, last_attempt_at = CURRENT_TIMESTAMP
+                WHERE user_id = $1
+                    AND (failed_attempts < $2
+                         OR last_attempt_at <= CURRENT_TIMESTAMP - make_interval(secs => $3))
+                RETURNING user_id
+            )
+            SELECT
+                EXISTS (SELECT 1 FROM claimed),
+                (SELECT EXTRACT(EPOCH FROM
+                            last_attempt_at + make_interval(secs => $3) - CURRENT_TIMESTAMP)::float8
+                 FROM user_two_factor WHERE user_id = $1)
+            "#,
+        )
+        .bind(user_id)
+        .bind(max_failures as i32)
+        .bind(lockout.as_secs_f64())
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await?;
+        if claimed {
+            return Ok(Attempt::Allowed);
+        }
+        Ok(Attempt::Locked(Duration::from_secs_f64(
+            retry_after_secs.unwrap_or(0.0).max(0.0).ceil(),
+        )))
+    }
+}
@@ -1,6 +1,7 @@
 use sqlx::{PgPool, Row};
 use tracing::instrument;
 
+use crate::database::Traced;
 use crate::models::User;
 
 #[derive(Clone)]
@@ -18,18 +19,21 @@ impl UserRepository {
         &self,
         email: &str,
         password_hash: &str,
+        username: &str,
         name: &str,
     ) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
             r#"
-            INSERT INTO users (email, password_hash, name)
-            VALUES ($1, $2, $3)
-            RETURNING id, email, password_hash, name, bio, image, created_at, updated_at
+            INSERT INTO users (email, password_hash, username, name)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, email, password_hash, username, name, bio, image, created_at, updated_at
             "#,
         )
         .bind(email)
         .bind(password_hash)
+        .bind(username)
         .bind(name)
+        .traced(&self.pool)
         .fetch_one(&self.pool)
         .await
     }
@@ -38,12 +42,13 @@ impl UserRepository {
     pub async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>(
             r#"
-            SELECT id, email, password_hash, name, bio, image, created_at, updated_at
+            SELECT id, email, password_hash, username, name, bio, image, created_at, updated_at
             FROM users
             WHERE email = $1
             "#,
         )
         .bind(email)
+        .traced(&self.pool)
         .fetch_optional(&self.pool)
         .await
     }
@@ -52,12 +57,28 @@ impl UserRepository {
     pub async fn find_by_id(&self, id: i32) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>(
             r#"
-            SELECT id, email, password_hash, name, bio, image, created_at, updated_at
+            SELECT id, email, password_hash, username, name, bio, image, created_at, updated_at
             FROM users
             WHERE id = $1
             "#,
         )
         .bind(id)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.user.find_by_username", skip(self))]
+    pub async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, email, password_hash, username, name, bio, image, created_at, updated_at
+            FROM users
+            WHERE username = $1
+            "#,
+        )
+        .bind(username)
+        .traced(&self.pool)
         .fetch_optional(&self.pool)
         .await
     }
@@ -66,6 +87,7 @@ impl UserRepository {
     pub async fn exists_by_email(&self, email: &str) -> Result<bool, sqlx::Error> {
         let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) as exists")
             .bind(email)
+            .traced(&self.pool)
             .fetch_one(&self.pool)
             .await?;
 
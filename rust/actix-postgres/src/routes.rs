@@ -1,30 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use actix_web::web;
+use authz::Policy;
 
+use crate::config::Config;
 use crate::handlers;
+use crate::middleware::{Authorize, Timeout};
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.route("/api/health", web::get().to(handlers::health_check))
-        .route("/api/register", web::post().to(handlers::register))
-        .route("/api/login", web::post().to(handlers::login))
-        .route("/api/user", web::get().to(handlers::get_user))
-        .route("/api/logout", web::post().to(handlers::logout))
-        .route("/api/articles", web::get().to(handlers::list_articles))
-        .route("/api/articles", web::post().to(handlers::create_article))
-        .route("/api/articles/{slug}", web::get().to(handlers::get_article))
-        .route(
-            "/api/articles/{slug}",
-            web::put().to(handlers::update_article),
-        )
-        .route(
-            "/api/articles/{slug}",
-            web::delete().to(handlers::delete_article),
-        )
-        .route(
-            "/api/articles/{slug}/favorite",
-            web::post().to(handlers::favorite_article),
-        )
-        .route(
-            "/api/articles/{slug}/favorite",
-            web::delete().to(handlers::unfavorite_article),
-        );
+/// Each scope gets its own deadline: the probes' is short, since a probe
+/// slower than the orchestrator's own timeout has failed anyway. The API
+/// checks the authorization policy inside its deadline.
+pub fn configure(cfg: &mut web::ServiceConfig, config: &Config, policy: &Arc<Policy>) {
+    let probe_timeout = Duration::from_secs(config.probe_timeout_secs);
+    cfg.service(
+        web::resource("/healthz")
+            .wrap(Timeout(probe_timeout))
+            .route(web::get().to(handlers::liveness)),
+    )
+    .service(
+        web::resource("/readyz")
+            .wrap(Timeout(probe_timeout))
+            .route(web::get().to(handlers::health_check)),
+    )
+    .service(
+        web::scope("/api")
+            .wrap(Authorize(policy.clone()))
+            .wrap(Timeout(Duration::from_secs(config.request_timeout_secs)))
+            .route("/health", web::get().to(handlers::health_check))
+            .route("/register", web::post().to(handlers::register))
+            .route("/login", web::post().to(handlers::login))
+            .route("/login/2fa", web::post().to(handlers::login_two_factor))
+            .route("/user", web::get().to(handlers::get_user))
+            .route(
+                "/user/2fa/setup",
+                web::post().to(handlers::setup_two_factor),
+            )
+            .route(
+                "/user/2fa/verify",
+                web::post().to(handlers::verify_two_factor),
+            )
+            .route(
+                "/user/articles",
+                web::get().to(handlers::list_user_articles),
+            )
+            .route(
+                "/user/favorites",
+                web::get().to(handlers::list_user_favorites),
+            )
+            .route("/logout", web::post().to(handlers::logout))
+            .route("/profiles/{username}", web::get().to(handlers::get_profile))
+            .route("/articles", web::get().to(handlers::list_articles))
+            .route("/articles", web::post().to(handlers::create_article))
+            .route("/articles/{slug}", web::get().to(handlers::get_article))
+            .route("/articles/{slug}", web::put().to(handlers::update_article))
+            .route(
+                "/articles/{slug}",
+                web::delete().to(handlers::delete_article),
+            )
+            .route(
+                "/articles/{slug}/favorite",
+                web::post().to(handlers::favorite_article),
+            )
+            .route(
+                "/articles/{slug}/favorite",
+                web::delete().to(handlers::unfavorite_article),
+            )
+            .route("/admin/config", web::get().to(handlers::get_config))
+            .route("/admin/emails", web::post().to(handlers::send_email))
+            .route("/admin/flags", web::get().to(handlers::list_feature_flags))
+            .route(
+                "/admin/flags/{name}",
+                web::put().to(handlers::update_feature_flag),
+            ),
+    );
 }
@@ -1,11 +1,13 @@
+use authz::Authors;
+use domain_events::ArticleCreated;
 use tracing::instrument;
 
 use crate::{
-    error::{AppError, AppResult},
+    error::{AppError, AppResult, RecordErr},
     jobs::JobQueue,
     models::{
-        ArticleDto, ArticleResponse, ArticlesResponse, CreateArticleInput, ListArticlesQuery,
-        UpdateArticleInput,
+        ArticleDto, ArticleResponse, ArticleWithAuthor, ArticlesResponse, CreateArticleInput,
+        ListArticlesQuery, UpdateArticleInput, UserArticlesQuery,
     },
     repository::{ArticleRepository, FavoriteRepository},
     telemetry::{
@@ -33,85 +35,105 @@ impl ArticleService {
         }
     }
 
+    /// Who wrote the article at `slug`, for the authorization policy's
+    /// `owner` and `author` rules. Articles here have no co-authors.
+    pub async fn authors(&self, slug: &str) -> AppResult<Option<Authors>> {
+        Ok(self
+            .article_repo
+            .find_owner(slug)
+            .await?
+            .map(|owner| Authors {
+                owner,
+                co_authors: Vec::new(),
+            }))
+    }
+
     #[instrument(name = "article.create", skip(self, input), fields(author_id))]
     pub async fn create(
         &self,
         author_id: i32,
         input: CreateArticleInput,
     ) -> AppResult<ArticleResponse> {
-        let title = input.title.trim();
-        if title.is_empty() {
-            return Err(AppError::Validation("Title must not be empty".to_string()));
-        }
-        let body = input.body.trim();
-        if body.is_empty() {
-            return Err(AppError::Validation("Body must not be empty".to_string()));
-        }
-
-        let slug = self.generate_slug(title);
-        let final_slug = if self.article_repo.exists_by_slug(&slug).await? {
-            format!(
-                "{}-{}",
-                slug,
-                time::OffsetDateTime::now_utc().unix_timestamp()
-            )
-        } else {
-            slug
-        };
-
-        let article = self
-            .article_repo
-            .create(
-                &final_slug,
-                title,
-                input.description.as_deref().unwrap_or(""),
-                body,
-                author_id,
-            )
-            .await?;
-
-        let article_with_author =
-            self.article_repo
-                .find_by_id(article.id)
-                .await?
-                .ok_or(AppError::Internal(
-                    "Failed to fetch created article".to_string(),
-                ))?;
-
-        if let Err(e) = self
-            .job_queue
-            .enqueue_notification(article.id, &article.title)
-            .await
-        {
-            tracing::warn!(article_id = article.id, error = %e, "Failed to enqueue notification");
+        async move {
+            let (title, body) = input.title_and_body()?;
+
+            let slug = self.generate_slug(title);
+            let final_slug = if self.article_repo.exists_by_slug(&slug).await? {
+                format!(
+                    "{}-{}",
+                    slug,
+                    time::OffsetDateTime::now_utc().unix_timestamp()
+                )
+            } else {
+                slug
+            };
+
+            let article = self
+                .article_repo
+                .create(
+                    &final_slug,
+                    title,
+                    input.description.as_deref().unwrap_or(""),
+                    body,
+                    author_id,
+                )
+                .await?;
+
+            let article_with_author =
+                self.article_repo
+                    .find_by_id(article.id)
+                    .await?
+                    .ok_or(AppError::Internal(
+                        "Failed to fetch created article".to_string(),
+                    ))?;
+
+            if let Err(e) = self
+                .job_queue
+                .enqueue_notification(ArticleCreated {
+                    article_id: article.id,
+                    org_id: None,
+                    slug: article.slug.clone(),
+                    title: article.title.clone(),
+                    author_id,
+                })
+                .await
+            {
+                tracing::warn!(article_id = article.id, error = %e, "Failed to enqueue notification");
+            }
+
+            ARTICLES_CREATED.add(1, &[]);
+
+            tracing::info!(article_id = article.id, slug = %article.slug, "Article created");
+
+            Ok(ArticleResponse {
+                article: ArticleDto::from_article_with_author(article_with_author, false),
+            })
         }
-
-        ARTICLES_CREATED.add(1, &[]);
-
-        tracing::info!(article_id = article.id, slug = %article.slug, "Article created");
-
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(article_with_author, false),
-        })
+        .await
+        .record_err()
     }
 
     #[instrument(name = "article.get", skip(self))]
     pub async fn get(&self, slug: &str, user_id: Option<i32>) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        let favorited = if let Some(uid) = user_id {
-            self.favorite_repo.exists(uid, article.id).await?
-        } else {
-            false
-        };
+            let favorited = if let Some(uid) = user_id {
+                self.favorite_repo.exists(uid, article.id).await?
+            } else {
+                false
+            };
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(article, favorited),
-        })
+            Ok(ArticleResponse {
+                article: ArticleDto::from_article_with_author(article, favorited),
+            })
+        }
+        .await
+        .record_err()
     }
 
     #[instrument(name = "article.list", skip(self))]
@@ -119,159 +141,240 @@ impl ArticleService {
         &self,
         query: ListArticlesQuery,
         user_id: Option<i32>,
+        rank_by_favorites: bool,
     ) -> AppResult<ArticlesResponse> {
-        let articles = self
-            .article_repo
-            .list(query.limit, query.offset, query.author.as_deref())
-            .await?;
-
-        let total = self.article_repo.count(query.author.as_deref()).await?;
-
-        let article_ids: Vec<i32> = articles.iter().map(|a| a.id).collect();
+        async move {
+            let articles = self
+                .article_repo
+                .list(
+                    query.limit,
+                    query.offset,
+                    query.author.as_deref(),
+                    rank_by_favorites,
+                )
+                .await?;
+
+            let total = self.article_repo.count(query.author.as_deref()).await?;
+
+            self.present_page(articles, total, user_id).await
+        }
+        .await
+        .record_err()
+    }
 
-        let favorited_ids = if let Some(uid) = user_id {
-            self.favorite_repo
-                .is_favorited_batch(uid, &article_ids)
-                .await?
-        } else {
-            vec![]
-        };
+    /// The caller's own articles, newest first.
+    #[instrument(name = "article.list_by_user", skip(self))]
+    pub async fn list_by_user(
+        &self,
+        user_id: i32,
+        query: UserArticlesQuery,
+    ) -> AppResult<ArticlesResponse> {
+        async move {
+            let articles = self
+                .article_repo
+                .list_by_author(user_id, query.limit(), query.offset())
+                .await?;
+            let total = self.article_repo.count_by_author(user_id).await?;
+
+            self.present_page(articles, total, Some(user_id)).await
+        }
+        .await
+        .record_err()
+    }
 
-        let articles_dto: Vec<ArticleDto> = articles
-            .into_iter()
-            .map(|a| {
-                let favorited = favorited_ids.contains(&a.id);
-                ArticleDto::from_article_with_author(a, favorited)
+    /// The articles the caller has favorited, most recently favorited first.
+    #[instrument(name = "article.list_favorites", skip(self))]
+    pub async fn list_favorites(
+        &self,
+        user_id: i32,
+        query: UserArticlesQuery,
+    ) -> AppResult<ArticlesResponse> {
+        async move {
+            let articles = self
+                .article_repo
+                .list_favorited_by(user_id, query.limit(), query.offset())
+                .await?;
+            let total = self.favorite_repo.count_by_user(user_id).await?;
+
+            Ok(ArticlesResponse {
+                articles: articles
+                    .into_iter()
+                    .map(|a| ArticleDto::from_article_with_author(a, true))
+                    .collect(),
+                total,
             })
-            .collect();
-
-        Ok(ArticlesResponse {
-            articles: articles_dto,
-            total,
-        })
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.update", skip(self, input))]
+    /// `authors` are the ones the authorization policy allowed the change
+    /// for, as are `delete`'s: if the article has changed hands since, the
+    /// request answers 409 instead of acting on a stale decision.
+    #[instrument(name = "article.update", skip(self, authors, input))]
     pub async fn update(
         &self,
         slug: &str,
+        authors: &Authors,
         user_id: i32,
         input: UpdateArticleInput,
     ) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
-
-        if article.author_id != user_id {
-            return Err(AppError::Forbidden);
-        }
-
-        let new_slug = input.title.as_ref().map(|t| self.generate_slug(t));
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        self.article_repo
-            .update(
-                article.id,
-                new_slug.as_deref(),
-                input.title.as_deref(),
-                input.description.as_deref(),
-                input.body.as_deref(),
-            )
-            .await?;
+            let new_slug = input.title.as_ref().map(|t| self.generate_slug(t));
 
-        let updated_article =
             self.article_repo
-                .find_by_id(article.id)
+                .update(
+                    article.id,
+                    authors.owner,
+                    new_slug.as_deref(),
+                    input.title.as_deref(),
+                    input.description.as_deref(),
+                    input.body.as_deref(),
+                )
                 .await?
-                .ok_or(AppError::Internal(
-                    "Failed to fetch updated article".to_string(),
-                ))?;
-
-        let favorited = self.favorite_repo.exists(user_id, article.id).await?;
+                .ok_or_else(authors_changed)?;
 
-        ARTICLES_UPDATED.add(1, &[]);
+            let updated_article =
+                self.article_repo
+                    .find_by_id(article.id)
+                    .await?
+                    .ok_or(AppError::Internal(
+                        "Failed to fetch updated article".to_string(),
+                    ))?;
 
-        tracing::info!(article_id = article.id, "Article updated");
+            let favorited = self.favorite_repo.exists(user_id, article.id).await?;
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, favorited),
-        })
-    }
+            ARTICLES_UPDATED.add(1, &[]);
 
-    #[instrument(name = "article.delete", skip(self))]
-    pub async fn delete(&self, slug: &str, user_id: i32) -> AppResult<()> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+            tracing::info!(article_id = article.id, "Article updated");
 
-        if article.author_id != user_id {
-            return Err(AppError::Forbidden);
+            Ok(ArticleResponse {
+                article: ArticleDto::from_article_with_author(updated_article, favorited),
+            })
         }
+        .await
+        .record_err()
+    }
 
-        self.article_repo.delete(article.id).await?;
+    #[instrument(name = "article.delete", skip(self, authors))]
+    pub async fn delete(&self, slug: &str, authors: &Authors, user_id: i32) -> AppResult<()> {
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+            if !self.article_repo.delete(article.id, authors.owner).await? {
+                return Err(authors_changed());
+            }
 
-        ARTICLES_DELETED.add(1, &[]);
+            ARTICLES_DELETED.add(1, &[]);
 
-        tracing::info!(article_id = article.id, "Article deleted");
+            tracing::info!(article_id = article.id, "Article deleted");
 
-        Ok(())
+            Ok(())
+        }
+        .await
+        .record_err()
     }
 
     #[instrument(name = "article.favorite", skip(self))]
     pub async fn favorite(&self, slug: &str, user_id: i32) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        let already_favorited = self.favorite_repo.exists(user_id, article.id).await?;
+            let already_favorited = self.favorite_repo.exists(user_id, article.id).await?;
 
-        if !already_favorited {
-            self.favorite_repo.create(user_id, article.id).await?;
-            self.article_repo.increment_favorites(article.id).await?;
-            FAVORITES_ADDED.add(1, &[]);
-            tracing::info!(article_id = article.id, user_id, "Article favorited");
-        }
+            if !already_favorited {
+                self.favorite_repo.create(user_id, article.id).await?;
+                self.article_repo.increment_favorites(article.id).await?;
+                FAVORITES_ADDED.add(1, &[]);
+                tracing::info!(article_id = article.id, user_id, "Article favorited");
+            }
 
-        let updated_article = self
-            .article_repo
-            .find_by_id(article.id)
-            .await?
-            .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
+            let updated_article = self
+                .article_repo
+                .find_by_id(article.id)
+                .await?
+                .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, true),
-        })
+            Ok(ArticleResponse {
+                article: ArticleDto::from_article_with_author(updated_article, true),
+            })
+        }
+        .await
+        .record_err()
     }
 
     #[instrument(name = "article.unfavorite", skip(self))]
     pub async fn unfavorite(&self, slug: &str, user_id: i32) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+            let was_favorited = self.favorite_repo.delete(user_id, article.id).await?;
+
+            if was_favorited {
+                self.article_repo.decrement_favorites(article.id).await?;
+                FAVORITES_REMOVED.add(1, &[]);
+                tracing::info!(article_id = article.id, user_id, "Article unfavorited");
+            }
 
-        let was_favorited = self.favorite_repo.delete(user_id, article.id).await?;
+            let updated_article = self
+                .article_repo
+                .find_by_id(article.id)
+                .await?
+                .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
 
-        if was_favorited {
-            self.article_repo.decrement_favorites(article.id).await?;
-            FAVORITES_REMOVED.add(1, &[]);
-            tracing::info!(article_id = article.id, user_id, "Article unfavorited");
+            Ok(ArticleResponse {
+                article: ArticleDto::from_article_with_author(updated_article, false),
+            })
         }
+        .await
+        .record_err()
+    }
 
-        let updated_article = self
-            .article_repo
-            .find_by_id(article.id)
-            .await?
-            .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
+    async fn present_page(
+        &self,
+        articles: Vec<ArticleWithAuthor>,
+        total: i64,
+        user_id: Option<i32>,
+    ) -> AppResult<ArticlesResponse> {
+        let article_ids: Vec<i32> = articles.iter().map(|a| a.id).collect();
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, false),
+        let favorited_ids = if let Some(uid) = user_id {
+            self.favorite_repo
+                .is_favorited_batch(uid, &article_ids)
+                .await?
+        } else {
+            vec![]
+        };
+
+        let articles_dto: Vec<ArticleDto> = articles
+            .into_iter()
+            .map(|a| {
+                let favorited = favorited_ids.contains(&a.id);
+                ArticleDto::from_article_with_author(a, favorited)
+            })
+            .collect();
+
+        Ok(ArticlesResponse {
+            articles: articles_dto,
+            total,
         })
     }
 
@@ -280,6 +383,12 @@ impl ArticleService {
     }
 }
 
+/// The article isn't owned by the user the request was authorized for any
+/// more; retrying asks the policy again.
+fn authors_changed() -> AppError {
+    AppError::Conflict("Article authors changed; try again".to_string())
+}
+
 pub fn generate_slug(title: &str) -> String {
     title
         .to_lowercase()
@@ -2,19 +2,32 @@ use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
+use blog_domain::normalize_username;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
 use time::{Duration, OffsetDateTime};
-use tracing::instrument;
+use tracing::{Span, instrument};
+use two_factor::{TwoFactor, Verified};
 
 use crate::{
-    config::Config,
-    error::{AppError, AppResult},
-    models::{LoginInput, RegisterInput, User, UserWithToken},
-    repository::UserRepository,
+    config::{Config, RotatingSecret},
+    error::{AppError, AppResult, RecordErr},
+    models::{
+        LoginInput, LoginOutcome, RecoveryCodesResponse, RegisterInput, TwoFactorChallenge,
+        TwoFactorLoginInput, TwoFactorSetupResponse, User, UserWithToken,
+    },
+    repository::{TwoFactorRepository, UserRepository},
     telemetry::USERS_REGISTERED,
 };
 
+/// The `aud` of a two-factor challenge. A JWT with an audience the
+/// validator wasn't told to expect fails validation, so
+/// [`AuthService::validate_token`] refuses challenges as access tokens.
+const TWO_FACTOR_AUDIENCE: &str = "login.2fa";
+
+/// Time to type a code after the password.
+const TWO_FACTOR_CHALLENGE_TTL: Duration = Duration::minutes(5);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: i32,
@@ -22,79 +35,240 @@ pub struct Claims {
     pub iat: i64,
 }
 
+/// Proves the password step of a login with two-factor authentication
+/// enabled; `POST /api/login/2fa` exchanges it and a code for a token.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChallengeClaims {
+    sub: i32,
+    aud: String,
+    exp: i64,
+    iat: i64,
+}
+
 #[derive(Clone)]
 pub struct AuthService {
     user_repo: UserRepository,
-    jwt_secret: String,
+    two_factor: TwoFactor<TwoFactorRepository>,
+    jwt_secret: RotatingSecret,
     jwt_expires_in_hours: i64,
+    admin_token: String,
 }
 
 impl AuthService {
-    pub fn new(user_repo: UserRepository, config: &Config) -> Self {
+    pub fn new(
+        user_repo: UserRepository,
+        two_factor_repo: TwoFactorRepository,
+        jwt_secret: RotatingSecret,
+        config: &Config,
+    ) -> Self {
         Self {
             user_repo,
-            jwt_secret: config.jwt_secret.clone(),
+            two_factor: TwoFactor::new(
+                two_factor_repo,
+                &config.two_factor_issuer,
+                config.two_factor_lockout(),
+            ),
+            jwt_secret,
             jwt_expires_in_hours: config.jwt_expires_in_hours,
+            admin_token: config.admin_token.clone(),
         }
     }
 
     #[instrument(name = "auth.register", skip(self, input), fields(email = %input.email))]
     pub async fn register(&self, input: RegisterInput) -> AppResult<UserWithToken> {
-        if self.user_repo.exists_by_email(&input.email).await? {
-            return Err(AppError::Conflict("Email already registered".to_string()));
+        async move {
+            let username = normalize_username(&input.username)?;
+            if self.user_repo.exists_by_email(&input.email).await? {
+                return Err(AppError::Conflict("Email already registered".to_string()));
+            }
+
+            let password_hash = self.hash_password(&input.password).await?;
+
+            // No lookup first: the unique constraint also catches two
+            // registrations racing for the same name.
+            let user = self
+                .user_repo
+                .create(&input.email, &password_hash, &username, &input.name)
+                .await
+                .map_err(|e| match &e {
+                    sqlx::Error::Database(db) if db.constraint() == Some("users_username_key") => {
+                        AppError::Conflict("Username already taken".to_string())
+                    }
+                    _ => AppError::from(e),
+                })?;
+
+            let token = self.generate_token(user.id)?;
+
+            USERS_REGISTERED.add(1, &[]);
+
+            tracing::info!(user_id = user.id, "User registered");
+
+            Ok(UserWithToken::from_user(&user, token))
         }
-
-        let password_hash = self.hash_password(&input.password).await?;
-
-        let user = self
-            .user_repo
-            .create(&input.email, &password_hash, &input.name)
-            .await?;
-
-        let token = self.generate_token(user.id)?;
-
-        USERS_REGISTERED.add(1, &[]);
-
-        tracing::info!(user_id = user.id, "User registered");
-
-        Ok(UserWithToken::from_user(&user, token))
+        .await
+        .record_err()
     }
 
+    /// With two-factor authentication enabled a correct password only gets
+    /// a challenge; [`AuthService::login_two_factor`] finishes the login.
     #[instrument(name = "auth.login", skip(self, input), fields(email = %input.email))]
-    pub async fn login(&self, input: LoginInput) -> AppResult<UserWithToken> {
-        let user = self
-            .user_repo
-            .find_by_email(&input.email)
-            .await?
-            .ok_or(AppError::InvalidCredentials)?;
+    pub async fn login(&self, input: LoginInput) -> AppResult<LoginOutcome> {
+        async move {
+            let user = self
+                .user_repo
+                .find_by_email(&input.email)
+                .await?
+                .ok_or(AppError::InvalidCredentials)?;
+
+            self.verify_password(&input.password, &user.password_hash)
+                .await?;
+
+            if self.two_factor.is_enabled(user.id).await? {
+                tracing::info!(user_id = user.id, "Two-factor code required");
+                return Ok(LoginOutcome::TwoFactorRequired(
+                    self.generate_challenge(user.id)?,
+                ));
+            }
+
+            let token = self.generate_token(user.id)?;
+
+            tracing::info!(user_id = user.id, "User logged in");
+
+            Ok(LoginOutcome::Authenticated(UserWithToken::from_user(
+                &user, token,
+            )))
+        }
+        .await
+        .record_err()
+    }
 
-        self.verify_password(&input.password, &user.password_hash)
-            .await?;
+    /// A wrong code answers 401 like a wrong password, and counts towards
+    /// the lockout.
+    #[instrument(name = "auth.login_two_factor", skip(self, input), fields(user_id))]
+    pub async fn login_two_factor(&self, input: TwoFactorLoginInput) -> AppResult<UserWithToken> {
+        async move {
+            let user_id = self.validate_challenge(&input.challenge_token)?;
+            Span::current().record("user_id", user_id);
+            let user = self
+                .user_repo
+                .find_by_id(user_id)
+                .await?
+                .ok_or(AppError::Unauthorized)?;
+
+            let verified =
+                self.two_factor
+                    .verify(user.id, &input.code)
+                    .await
+                    .map_err(|e| match e {
+                        two_factor::Error::InvalidCode | two_factor::Error::NotEnrolled => {
+                            AppError::InvalidCredentials
+                        }
+                        e => AppError::from(e),
+                    })?;
+            if let Verified::RecoveryCode { remaining } = verified {
+                tracing::warn!(user_id = user.id, remaining, "Recovery code used");
+            }
+
+            let token = self.generate_token(user.id)?;
+
+            tracing::info!(user_id = user.id, "User logged in");
+
+            Ok(UserWithToken::from_user(&user, token))
+        }
+        .await
+        .record_err()
+    }
 
-        let token = self.generate_token(user.id)?;
+    /// Starts over with a new secret until a code confirms one.
+    #[instrument(name = "auth.setup_two_factor", skip(self))]
+    pub async fn setup_two_factor(&self, user_id: i32) -> AppResult<TwoFactorSetupResponse> {
+        async move {
+            let user = self
+                .user_repo
+                .find_by_id(user_id)
+                .await?
+                .ok_or(AppError::NotFound("User not found".to_string()))?;
+            let setup = self.two_factor.setup(user.id, &user.email).await?;
+
+            Ok(TwoFactorSetupResponse {
+                secret: setup.secret,
+                otpauth_uri: setup.otpauth_uri,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.enable_two_factor", skip(self, code))]
+    pub async fn enable_two_factor(
+        &self,
+        user_id: i32,
+        code: &str,
+    ) -> AppResult<RecoveryCodesResponse> {
+        async move {
+            let recovery_codes = self.two_factor.confirm(user_id, code).await?;
 
-        tracing::info!(user_id = user.id, "User logged in");
+            tracing::info!(user_id, "Two-factor authentication enabled");
 
-        Ok(UserWithToken::from_user(&user, token))
+            Ok(RecoveryCodesResponse { recovery_codes })
+        }
+        .await
+        .record_err()
     }
 
     #[instrument(name = "auth.get_user", skip(self))]
     pub async fn get_user(&self, user_id: i32) -> AppResult<User> {
-        self.user_repo
-            .find_by_id(user_id)
-            .await?
-            .ok_or(AppError::NotFound("User not found".to_string()))
+        async move {
+            self.user_repo
+                .find_by_id(user_id)
+                .await?
+                .ok_or(AppError::NotFound("User not found".to_string()))
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.get_profile", skip(self))]
+    pub async fn get_profile(&self, username: &str) -> AppResult<User> {
+        async move {
+            self.user_repo
+                .find_by_username(&username.to_ascii_lowercase())
+                .await?
+                .ok_or(AppError::NotFound("Profile not found".to_string()))
+        }
+        .await
+        .record_err()
     }
 
+    /// Tokens signed with the secret that was just rotated out stay valid
+    /// until they expire or the secret rotates again.
     #[instrument(name = "auth.validate_token", skip(self, token))]
     pub fn validate_token(&self, token: &str) -> AppResult<i32> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )?;
+        let mut error = None;
+        for secret in self.jwt_secret.candidates() {
+            match decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &Validation::default(),
+            ) {
+                Ok(token_data) => return Ok(token_data.claims.sub),
+                Err(e) => error = Some(e),
+            }
+        }
 
-        Ok(token_data.claims.sub)
+        Err(error.map_or(AppError::Unauthorized, AppError::from)).record_err()
+    }
+
+    /// The admin API is disabled entirely when `ADMIN_TOKEN` is unset.
+    pub fn validate_admin_token(&self, token: &str) -> AppResult<()> {
+        if self.admin_token.is_empty() {
+            return Err(AppError::Forbidden);
+        }
+        if constant_time_eq(self.admin_token.as_bytes(), token.as_bytes()) {
+            Ok(())
+        } else {
+            Err(AppError::Unauthorized)
+        }
     }
 
     fn generate_token(&self, user_id: i32) -> AppResult<String> {
@@ -110,12 +284,51 @@ impl AuthService {
         let token = encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
+            &EncodingKey::from_secret(self.jwt_secret.current().as_bytes()),
         )?;
 
         Ok(token)
     }
 
+    fn generate_challenge(&self, user_id: i32) -> AppResult<TwoFactorChallenge> {
+        let now = OffsetDateTime::now_utc();
+        let claims = ChallengeClaims {
+            sub: user_id,
+            aud: TWO_FACTOR_AUDIENCE.to_string(),
+            exp: (now + TWO_FACTOR_CHALLENGE_TTL).unix_timestamp(),
+            iat: now.unix_timestamp(),
+        };
+
+        let challenge_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.jwt_secret.current().as_bytes()),
+        )?;
+
+        Ok(TwoFactorChallenge {
+            challenge_token,
+            expires_in: TWO_FACTOR_CHALLENGE_TTL.whole_seconds(),
+        })
+    }
+
+    fn validate_challenge(&self, token: &str) -> AppResult<i32> {
+        let mut validation = Validation::default();
+        validation.set_audience(&[TWO_FACTOR_AUDIENCE]);
+        let mut error = None;
+        for secret in self.jwt_secret.candidates() {
+            match decode::<ChallengeClaims>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &validation,
+            ) {
+                Ok(token_data) => return Ok(token_data.claims.sub),
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error.map_or(AppError::Unauthorized, AppError::from))
+    }
+
     async fn hash_password(&self, password: &str) -> AppResult<String> {
         let password = password.to_string();
         tokio::task::spawn_blocking(move || {
@@ -145,6 +358,10 @@ impl AuthService {
     }
 }
 
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,6 +447,45 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_challenge_is_not_an_access_token() {
+        let secret = "test-secret-key-for-jwt";
+        let now = OffsetDateTime::now_utc();
+        let claims = ChallengeClaims {
+            sub: 42,
+            aud: TWO_FACTOR_AUDIENCE.to_string(),
+            exp: (now + TWO_FACTOR_CHALLENGE_TTL).unix_timestamp(),
+            iat: now.unix_timestamp(),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .expect("encoding should succeed");
+        let key = DecodingKey::from_secret(secret.as_bytes());
+
+        let as_access = decode::<Claims>(&token, &key, &Validation::default());
+        assert!(matches!(
+            as_access.unwrap_err().kind(),
+            jsonwebtoken::errors::ErrorKind::InvalidAudience
+        ));
+
+        let mut validation = Validation::default();
+        validation.set_audience(&[TWO_FACTOR_AUDIENCE]);
+        let challenge = decode::<ChallengeClaims>(&token, &key, &validation).unwrap();
+        assert_eq!(challenge.claims.sub, 42);
+
+        // And an access token isn't a challenge.
+        let access = encode(
+            &Header::default(),
+            &create_test_claims(42, 24),
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+        assert!(decode::<ChallengeClaims>(&access, &key, &validation).is_err());
+    }
+
     #[test]
     fn test_password_hash_and_verify() {
         let password = "secure_password_123";
@@ -0,0 +1,47 @@
+use std::any::Any;
+use std::time::Instant;
+
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use opentelemetry::KeyValue;
+
+use super::{
+    HTTP_SERVER_ACTIVE_CONNECTIONS, HTTP_SERVER_CONNECTION_DURATION,
+    HTTP_SERVER_CONNECTIONS_ACCEPTED,
+};
+
+/// For `HttpServer::on_connect`, which actix calls once per accepted
+/// connection: counts it, and leaves a guard in the connection's extensions
+/// that is dropped, and counts it closed, when actix drops the connection.
+pub fn record_connection(io: &dyn Any, extensions: &mut Extensions) {
+    let stream = io.downcast_ref::<TcpStream>().or_else(|| {
+        io.downcast_ref::<TlsStream<TcpStream>>()
+            .map(|tls| tls.get_ref().0)
+    });
+    let attributes = stream
+        .and_then(|stream| stream.local_addr().ok())
+        .map(|addr| vec![KeyValue::new("server.port", i64::from(addr.port()))])
+        .unwrap_or_default();
+    HTTP_SERVER_CONNECTIONS_ACCEPTED.add(1, &attributes);
+    HTTP_SERVER_ACTIVE_CONNECTIONS.add(1, &attributes);
+    extensions.insert(OpenConnection {
+        opened: Instant::now(),
+        attributes,
+    });
+}
+
+struct OpenConnection {
+    opened: Instant,
+    attributes: Vec<KeyValue>,
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        HTTP_SERVER_ACTIVE_CONNECTIONS.add(-1, &self.attributes);
+        HTTP_SERVER_CONNECTION_DURATION.record(
+            self.opened.elapsed().as_secs_f64() * 1000.0,
+            &self.attributes,
+        );
+    }
+}
@@ -2,16 +2,29 @@ use opentelemetry::KeyValue;
 use opentelemetry::global;
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
 use opentelemetry_otlp::WithExportConfig;
-use opentelemetry_sdk::{Resource, logs::SdkLoggerProvider, trace::SdkTracerProvider};
+use opentelemetry_sdk::{
+    Resource,
+    logs::SdkLoggerProvider,
+    metrics::{PeriodicReader, SdkMeterProvider, Temporality},
+    trace::SdkTracerProvider,
+};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
+use tracing::Subscriber;
 use tracing_opentelemetry::OpenTelemetryLayer;
-use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{
+    EnvFilter, Layer, layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt,
+};
 
+use super::correlation::{CorrelatedFormat, CorrelationLayer};
+use super::otlp::ExporterAuth;
 use crate::config::Config;
 
 pub struct TelemetryGuard {
     pub tracer_provider: SdkTracerProvider,
     pub logger_provider: SdkLoggerProvider,
+    pub meter_provider: SdkMeterProvider,
 }
 
 impl TelemetryGuard {
@@ -22,10 +35,17 @@ impl TelemetryGuard {
         if let Err(e) = self.logger_provider.shutdown() {
             eprintln!("Error shutting down logger provider: {e}");
         }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Error shutting down meter provider: {e}");
+        }
     }
 }
 
 pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
+    if config.otel_sdk_disabled {
+        return Ok(init_without_sdk(config));
+    }
+
     let resource = Resource::builder()
         .with_service_name(config.otel_service_name.clone())
         .with_attribute(KeyValue::new("service.version", "1.0.0"))
@@ -36,9 +56,10 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
         ))
         .with_attribute(KeyValue::new("environment", config.environment.clone()))
         .build();
+    let auth = ExporterAuth::from_config(config)?;
 
-    let trace_exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
+    let trace_exporter = auth
+        .apply(opentelemetry_otlp::SpanExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
@@ -50,8 +71,28 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
 
     global::set_tracer_provider(tracer_provider.clone());
 
-    let log_exporter = opentelemetry_otlp::LogExporter::builder()
-        .with_tonic()
+    let metric_exporter = metric_exporter(
+        &config.otel_exporter_endpoint,
+        &auth,
+        config.metrics_temporality,
+    )?;
+
+    let metric_reader = PeriodicReader::builder(metric_exporter)
+        .with_interval(Duration::from_secs(15))
+        .build();
+
+    let meter_provider = config
+        .metric_views()
+        .map_err(anyhow::Error::msg)?
+        .register(SdkMeterProvider::builder())
+        .with_reader(metric_reader)
+        .with_resource(resource.clone())
+        .build();
+
+    global::set_meter_provider(meter_provider.clone());
+
+    let log_exporter = auth
+        .apply(opentelemetry_otlp::LogExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
@@ -66,30 +107,150 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
     let tracer = global::tracer(config.otel_service_name.clone());
     let telemetry_layer = OpenTelemetryLayer::new(tracer);
 
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,sqlx=warn"));
-
-    let fmt_layer = if config.is_production() {
-        tracing_subscriber::fmt::layer().json().boxed()
-    } else {
-        tracing_subscriber::fmt::layer().pretty().boxed()
-    };
-
     tracing_subscriber::registry()
-        .with(env_filter)
+        .with(env_filter())
         .with(telemetry_layer)
+        .with(CorrelationLayer::default())
         .with(otel_log_layer)
-        .with(fmt_layer)
+        .with(fmt_layer(config))
         .init();
 
     tracing::info!(
         service = %config.otel_service_name,
         endpoint = %config.otel_exporter_endpoint,
-        "Telemetry initialized with OTLP trace and log export"
+        "Telemetry initialized with OTLP trace, metric, and log export"
     );
 
     Ok(TelemetryGuard {
         tracer_provider,
         logger_provider,
+        meter_provider,
     })
 }
+
+/// `OTEL_SDK_DISABLED=true`: the same stdout logs and request IDs, but no
+/// spans, metrics or log records reach an SDK. The global providers stay the
+/// no-op ones, so instrumented code costs what it would without
+/// OpenTelemetry, which is what `cargo xtask bench` compares against.
+fn init_without_sdk(config: &Config) -> TelemetryGuard {
+    tracing_subscriber::registry()
+        .with(env_filter())
+        .with(CorrelationLayer::default())
+        .with(fmt_layer(config))
+        .init();
+
+    tracing::info!(
+        service = %config.otel_service_name,
+        "Telemetry disabled by OTEL_SDK_DISABLED, nothing is exported"
+    );
+
+    TelemetryGuard {
+        tracer_provider: SdkTracerProvider::builder().build(),
+        logger_provider: SdkLoggerProvider::builder().build(),
+        meter_provider: SdkMeterProvider::builder().build(),
+    }
+}
+
+fn env_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,sqlx=warn"))
+}
+
+fn fmt_layer<S>(config: &Config) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let service_name = config.otel_service_name.clone();
+    if config.is_production() {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .map_event_format(|f| CorrelatedFormat::json(f, service_name))
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer()
+            .pretty()
+            .map_event_format(|f| CorrelatedFormat::text(f, service_name))
+            .boxed()
+    }
+}
+
+/// `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE`. Backends that only
+/// ingest deltas, such as some Scout ingestion paths, need `delta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsTemporality {
+    Cumulative,
+    Delta,
+    LowMemory,
+}
+
+impl FromStr for MetricsTemporality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cumulative" => Ok(Self::Cumulative),
+            "delta" => Ok(Self::Delta),
+            "lowmemory" => Ok(Self::LowMemory),
+            other => Err(format!(
+                "expected cumulative, delta or lowmemory, got {other:?}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MetricsTemporality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cumulative => "cumulative",
+            Self::Delta => "delta",
+            Self::LowMemory => "lowmemory",
+        })
+    }
+}
+
+impl From<MetricsTemporality> for Temporality {
+    fn from(temporality: MetricsTemporality) -> Self {
+        match temporality {
+            MetricsTemporality::Cumulative => Temporality::Cumulative,
+            MetricsTemporality::Delta => Temporality::Delta,
+            MetricsTemporality::LowMemory => Temporality::LowMemory,
+        }
+    }
+}
+
+fn metric_exporter(
+    endpoint: &str,
+    auth: &ExporterAuth,
+    temporality: MetricsTemporality,
+) -> anyhow::Result<opentelemetry_otlp::MetricExporter> {
+    Ok(auth
+        .apply(opentelemetry_otlp::MetricExporter::builder().with_tonic())
+        .with_endpoint(endpoint)
+        .with_timeout(Duration::from_secs(10))
+        .with_temporality(temporality.into())
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metric_exporter_uses_temporality_preference() {
+        for (preference, expected) in [
+            ("cumulative", Temporality::Cumulative),
+            ("DELTA", Temporality::Delta),
+        ] {
+            let temporality = preference.parse().unwrap();
+            let exporter = metric_exporter(
+                "http://localhost:4317",
+                &ExporterAuth::default(),
+                temporality,
+            )
+            .unwrap();
+            assert_eq!(exporter.temporality(), expected);
+        }
+        assert!("sum".parse::<MetricsTemporality>().is_err());
+    }
+}
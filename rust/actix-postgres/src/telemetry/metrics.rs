@@ -1,10 +1,14 @@
 use opentelemetry::{
     global,
-    metrics::{Counter, Histogram, Meter},
+    metrics::{Counter, Gauge, Histogram, Meter, UpDownCounter},
 };
 use std::sync::LazyLock;
 
-pub static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("actix-postgres"));
+pub static METER: LazyLock<Meter> = LazyLock::new(|| {
+    #[cfg(test)]
+    super::testing::install_meter_provider();
+    global::meter("actix-postgres")
+});
 
 pub static HTTP_REQUESTS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
@@ -25,6 +29,33 @@ pub static HTTP_REQUEST_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
         .build()
 });
 
+pub static HTTP_SERVER_CONNECTIONS_ACCEPTED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("http.server.connections.accepted")
+        .with_description("TCP connections accepted by the HTTP server")
+        .with_unit("{connection}")
+        .build()
+});
+
+pub static HTTP_SERVER_ACTIVE_CONNECTIONS: LazyLock<UpDownCounter<i64>> = LazyLock::new(|| {
+    METER
+        .i64_up_down_counter("http.server.active_connections")
+        .with_description("TCP connections open to the HTTP server, idle keep-alives included")
+        .with_unit("{connection}")
+        .build()
+});
+
+pub static HTTP_SERVER_CONNECTION_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("http.server.connection.duration")
+        .with_description("How long TCP connections to the HTTP server stay open")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            10.0, 100.0, 1000.0, 5000.0, 15000.0, 30000.0, 60000.0, 300000.0, 900000.0,
+        ])
+        .build()
+});
+
 pub static ARTICLES_CREATED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
         .u64_counter("articles.created")
@@ -74,23 +105,19 @@ pub static JOBS_ENQUEUED: LazyLock<Counter<u64>> = LazyLock::new(|| {
         .build()
 });
 
-pub static JOBS_COMPLETED: LazyLock<Counter<u64>> = LazyLock::new(|| {
-    METER
-        .u64_counter("jobs.completed")
-        .with_description("Total jobs completed successfully")
-        .build()
-});
-
-pub static JOBS_FAILED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static AUTHZ_DENIED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("jobs.failed")
-        .with_description("Total jobs failed")
+        .u64_counter("authz.denied")
+        .with_description("Requests the authorization policy refused, by rule and decision")
         .build()
 });
 
 pub fn init_metrics() {
     LazyLock::force(&HTTP_REQUESTS_TOTAL);
     LazyLock::force(&HTTP_REQUEST_DURATION);
+    LazyLock::force(&HTTP_SERVER_CONNECTIONS_ACCEPTED);
+    LazyLock::force(&HTTP_SERVER_ACTIVE_CONNECTIONS);
+    LazyLock::force(&HTTP_SERVER_CONNECTION_DURATION);
     LazyLock::force(&ARTICLES_CREATED);
     LazyLock::force(&ARTICLES_UPDATED);
     LazyLock::force(&ARTICLES_DELETED);
@@ -98,6 +125,32 @@ pub fn init_metrics() {
     LazyLock::force(&FAVORITES_REMOVED);
     LazyLock::force(&USERS_REGISTERED);
     LazyLock::force(&JOBS_ENQUEUED);
-    LazyLock::force(&JOBS_COMPLETED);
-    LazyLock::force(&JOBS_FAILED);
+    LazyLock::force(&AUTHZ_DENIED);
 }
+
+pub static FEATURE_FLAG_EVALUATIONS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("feature_flag.evaluations")
+        .with_description("Total feature flag evaluations")
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_SUCCESS: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("synthetic.check.success")
+        .with_description(
+            "1 if the last synthetic check of the service's own critical path passed, else 0",
+        )
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("synthetic.check.duration")
+        .with_description("Synthetic check duration in milliseconds")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+        ])
+        .build()
+});
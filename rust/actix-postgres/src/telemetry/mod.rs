@@ -1,5 +1,15 @@
+mod connections;
+pub mod correlation;
 mod init;
 mod metrics;
+mod otlp;
+#[cfg(test)]
+pub mod testing;
+mod views;
 
-pub use init::{TelemetryGuard, init_telemetry};
+pub use connections::record_connection;
+pub use correlation::current_request_id;
+pub use init::{MetricsTemporality, TelemetryGuard, init_telemetry};
 pub use metrics::*;
+pub use otlp::parse_headers;
+pub use views::MetricViews;
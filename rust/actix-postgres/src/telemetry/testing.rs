@@ -0,0 +1,126 @@
+use std::sync::LazyLock;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::{KeyValue, global};
+use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::layer::SubscriberExt;
+
+use super::correlation::CorrelationLayer;
+
+/// Records the spans finished on the current thread while it is alive, so
+/// `#[actix_web::test]`s (single-threaded) see only their own spans.
+pub struct SpanCapture {
+    exporter: InMemorySpanExporter,
+    _provider: SdkTracerProvider,
+    _subscriber: DefaultGuard,
+}
+
+impl SpanCapture {
+    pub fn start() -> Self {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .with(CorrelationLayer::default());
+        Self {
+            exporter,
+            _provider: provider,
+            _subscriber: tracing::subscriber::set_default(subscriber),
+        }
+    }
+
+    pub fn spans(&self) -> Vec<SpanData> {
+        self.exporter.get_finished_spans().unwrap()
+    }
+
+    /// The first finished span called `name` (the `otel.name`, when set).
+    pub fn span(&self, name: &str) -> SpanData {
+        let spans = self.spans();
+        let names: Vec<_> = spans.iter().map(|s| s.name.to_string()).collect();
+        spans
+            .into_iter()
+            .find(|s| s.name == name)
+            .unwrap_or_else(|| panic!("no span named {name:?}, finished: {names:?}"))
+    }
+}
+
+pub fn assert_attributes(span: &SpanData, expected: &[KeyValue]) {
+    for kv in expected {
+        let actual = span.attributes.iter().find(|a| a.key == kv.key);
+        assert_eq!(
+            actual.map(|a| &a.value),
+            Some(&kv.value),
+            "span {:?} attribute {}, attributes: {:?}",
+            span.name,
+            kv.key,
+            span.attributes
+        );
+    }
+}
+
+static METRICS: LazyLock<(SdkMeterProvider, InMemoryMetricExporter)> = LazyLock::new(|| {
+    let exporter = InMemoryMetricExporter::default();
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone()).build())
+        .build();
+    global::set_meter_provider(provider.clone());
+    (provider, exporter)
+});
+
+/// Called from `METER`'s initializer, so the instruments in `metrics.rs`
+/// record into the in-memory exporter under test.
+pub fn install_meter_provider() {
+    LazyLock::force(&METRICS);
+}
+
+/// Asserts `name` has a data point carrying at least `attributes`. Metrics are
+/// process-wide and tests run in parallel, so pass attributes that only the
+/// calling test records.
+pub fn assert_metric_recorded(name: &str, attributes: &[KeyValue]) {
+    let (provider, exporter) = &*METRICS;
+    provider.force_flush().unwrap();
+    let exported = exporter.get_finished_metrics().unwrap();
+    let points: Vec<Vec<KeyValue>> = exported
+        .iter()
+        .flat_map(|r| r.scope_metrics())
+        .flat_map(|s| s.metrics())
+        .filter(|m| m.name() == name)
+        .flat_map(|m| match m.data() {
+            AggregatedMetrics::F64(data) => data_point_attributes(data),
+            AggregatedMetrics::U64(data) => data_point_attributes(data),
+            AggregatedMetrics::I64(data) => data_point_attributes(data),
+        })
+        .collect();
+    assert!(
+        points
+            .iter()
+            .any(|point| attributes.iter().all(|kv| point.contains(kv))),
+        "no {name} data point with {attributes:?}, recorded: {points:?}"
+    );
+}
+
+fn data_point_attributes<T>(data: &MetricData<T>) -> Vec<Vec<KeyValue>> {
+    match data {
+        MetricData::Gauge(gauge) => gauge
+            .data_points()
+            .map(|p| p.attributes().cloned().collect())
+            .collect(),
+        MetricData::Sum(sum) => sum
+            .data_points()
+            .map(|p| p.attributes().cloned().collect())
+            .collect(),
+        MetricData::Histogram(histogram) => histogram
+            .data_points()
+            .map(|p| p.attributes().cloned().collect())
+            .collect(),
+        MetricData::ExponentialHistogram(histogram) => histogram
+            .data_points()
+            .map(|p| p.attributes().cloned().collect())
+            .collect(),
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use opentelemetry::Key;
+use opentelemetry_sdk::metrics::{Aggregation, Instrument, MeterProviderBuilder, Stream};
+
+/// Per-instrument overrides registered as views on the meter provider, so
+/// bucket boundaries and attribute sets can be tuned per deployment.
+/// Instruments without an override keep what `metrics.rs` defines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricViews {
+    views: BTreeMap<String, View>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct View {
+    boundaries: Option<Vec<f64>>,
+    /// An empty list drops every attribute.
+    allowed_attributes: Option<Vec<String>>,
+}
+
+impl MetricViews {
+    /// `list` is comma-separated, e.g. `5,10,25,50,100`. Empty leaves the
+    /// instrument's own boundaries.
+    pub fn with_buckets(mut self, instrument: &str, list: &str) -> Result<Self, String> {
+        if list.trim().is_empty() {
+            return Ok(self);
+        }
+        let boundaries = list
+            .split(',')
+            .map(|b| b.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|b| b.iter().all(|b| b.is_finite()) && b.windows(2).all(|w| w[0] < w[1]))
+            .ok_or_else(|| {
+                format!("must be a comma-separated list of increasing numbers, got {list:?}")
+            })?;
+        self.views
+            .entry(instrument.to_string())
+            .or_default()
+            .boundaries = Some(boundaries);
+        Ok(self)
+    }
+
+    /// `filters` is `instrument=key,key;instrument=key`, keeping only the
+    /// listed attribute keys on each instrument (`instrument=` keeps none).
+    pub fn with_attribute_filters(mut self, filters: &str) -> Result<Self, String> {
+        for filter in filters.split(';').map(str::trim).filter(|f| !f.is_empty()) {
+            let (instrument, keys) = filter
+                .split_once('=')
+                .filter(|(instrument, _)| !instrument.trim().is_empty())
+                .ok_or_else(|| {
+                    format!("must be instrument=key,key entries separated by ';', got {filter:?}")
+                })?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(String::from)
+                .collect();
+            self.views
+                .entry(instrument.trim().to_string())
+                .or_default()
+                .allowed_attributes = Some(keys);
+        }
+        Ok(self)
+    }
+
+    pub fn register(&self, mut builder: MeterProviderBuilder) -> MeterProviderBuilder {
+        for (name, view) in &self.views {
+            let name = name.clone();
+            let view = view.clone();
+            builder = builder.with_view(move |instrument: &Instrument| {
+                if instrument.name() == name {
+                    view.stream()
+                } else {
+                    None
+                }
+            });
+        }
+        builder
+    }
+}
+
+impl View {
+    fn stream(&self) -> Option<Stream> {
+        let mut stream = Stream::builder();
+        if let Some(boundaries) = &self.boundaries {
+            stream = stream.with_aggregation(Aggregation::ExplicitBucketHistogram {
+                boundaries: boundaries.clone(),
+                record_min_max: true,
+            });
+        }
+        if let Some(keys) = &self.allowed_attributes {
+            stream = stream.with_allowed_attribute_keys(keys.iter().cloned().map(Key::new));
+        }
+        stream.build().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_invalid_values() {
+        assert_eq!(
+            MetricViews::default().with_buckets("http.request.duration", " "),
+            Ok(MetricViews::default())
+        );
+        assert!(
+            MetricViews::default()
+                .with_buckets("http.request.duration", "10,5")
+                .is_err()
+        );
+        assert!(
+            MetricViews::default()
+                .with_buckets("http.request.duration", "5,ten")
+                .is_err()
+        );
+        assert!(
+            MetricViews::default()
+                .with_attribute_filters("http.route")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_views_override_buckets_and_attributes() {
+        let views = MetricViews::default()
+            .with_buckets("http.request.duration", "50, 500")
+            .and_then(|v| v.with_attribute_filters("http.request.duration=http.route"))
+            .unwrap();
+        let exporter = InMemoryMetricExporter::default();
+        let provider = views
+            .register(
+                SdkMeterProvider::builder()
+                    .with_reader(PeriodicReader::builder(exporter.clone()).build()),
+            )
+            .build();
+
+        let histogram = provider
+            .meter("test")
+            .f64_histogram("http.request.duration")
+            .with_boundaries(vec![1.0, 10.0, 100.0])
+            .build();
+        for status in [200, 500] {
+            histogram.record(
+                42.0,
+                &[
+                    KeyValue::new("http.route", "/api/articles"),
+                    KeyValue::new("http.status_code", status),
+                ],
+            );
+        }
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metric = metrics[0].scope_metrics().next().unwrap().metrics().next();
+        let Some(AggregatedMetrics::F64(MetricData::Histogram(histogram))) =
+            metric.map(|m| m.data())
+        else {
+            panic!("expected an f64 histogram");
+        };
+        let points: Vec<_> = histogram.data_points().collect();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].bounds().collect::<Vec<_>>(), vec![50.0, 500.0]);
+        assert_eq!(points[0].count(), 2);
+        assert_eq!(
+            points[0]
+                .attributes()
+                .map(|kv| kv.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["http.route"]
+        );
+    }
+}
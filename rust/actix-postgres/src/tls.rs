@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use actix_web::http::header::{HOST, LOCATION};
+use actix_web::http::uri::Authority;
+use actix_web::{HttpRequest, HttpResponse};
+use anyhow::Context as _;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, crypto::ring};
+
+use crate::error::AppError;
+
+/// The rustls server config for `TLS_CERT_PATH` (PEM certificate chain,
+/// leaf first) and `TLS_KEY_PATH` (PEM private key, PKCS#8, PKCS#1 or SEC1).
+pub fn load_config(cert_path: &str, key_path: &str) -> anyhow::Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("TLS_CERT_PATH {cert_path} is not a PEM certificate chain"))?;
+    if certs.is_empty() {
+        anyhow::bail!("TLS_CERT_PATH {cert_path} has no certificates");
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("TLS_KEY_PATH {key_path} is not a PEM private key"))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("TLS_KEY_PATH does not match the TLS_CERT_PATH certificate")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// The default service on `HTTP_REDIRECT_PORT`: every request gets a
+/// permanent redirect to the same URL over HTTPS on `https_port`. 308
+/// rather than 301 so a POST is repeated as a POST.
+pub fn redirect(req: &HttpRequest, https_port: u16) -> Result<HttpResponse, AppError> {
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+        .ok_or_else(|| AppError::Validation("A valid Host header is required".to_string()))?;
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    let location = match https_port {
+        443 => format!("https://{}{path}", host.host()),
+        port => format!("https://{}:{port}{path}", host.host()),
+    };
+    Ok(HttpResponse::PermanentRedirect()
+        .insert_header((LOCATION, location))
+        .finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test, web};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_redirects_to_https() {
+        let app = test::init_service(App::new().default_service(web::to(
+            |req: HttpRequest| async move { redirect(&req, 8443) },
+        )))
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/articles?limit=5")
+            .insert_header((HOST, "example.com:8080"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.com:8443/api/articles?limit=5"
+        );
+
+        let request = test::TestRequest::get().uri("/").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_load_config() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = std::env::temp_dir().join(format!("actix-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("cert.pem").display().to_string();
+        let key_path = dir.join("key.pem").display().to_string();
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        assert!(load_config(&cert_path, &key_path).is_ok());
+        let err = load_config(&cert_path, &cert_path).unwrap_err();
+        assert!(err.to_string().starts_with("TLS_KEY_PATH"), "{err}");
+    }
+}
@@ -0,0 +1,7 @@
+-- Adds fx_rates (see db/schema.sql) to a database created before it.
+CREATE TABLE IF NOT EXISTS fx_rates (
+    currency CHAR(3) NOT NULL,
+    rate_date DATE NOT NULL,
+    usd_per_unit NUMERIC(20, 8) NOT NULL,
+    PRIMARY KEY (currency, rate_date)
+);
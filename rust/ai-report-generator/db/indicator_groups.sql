@@ -0,0 +1,11 @@
+-- Adds indicator_groups (see db/schema.sql) to a database created before it.
+CREATE TABLE IF NOT EXISTS indicator_groups (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    indicators TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_indicator_groups_name ON indicator_groups(lower(name));
@@ -0,0 +1,18 @@
+-- Adds the indicator_summaries view (see db/schema.sql) to a database created
+-- before it.
+CREATE MATERIALIZED VIEW IF NOT EXISTS indicator_summaries AS
+SELECT
+    indicator_id,
+    count(*) AS observations,
+    min(observation_date) AS first_date,
+    max(observation_date) AS last_date,
+    min(value)::float8 AS min_value,
+    max(value)::float8 AS max_value,
+    avg(value)::float8 AS avg_value,
+    (array_agg(value ORDER BY observation_date DESC))[1]::float8 AS last_value,
+    CURRENT_TIMESTAMP AS refreshed_at
+FROM data_points
+GROUP BY indicator_id;
+
+-- REFRESH ... CONCURRENTLY needs a unique index.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_indicator_summaries_indicator ON indicator_summaries(indicator_id);
@@ -0,0 +1,20 @@
+-- Adds llm_calls (see db/schema.sql) to a database created before it, and the
+-- columns that came after the table to one created with its first version.
+CREATE TABLE IF NOT EXISTS llm_calls (
+    id BIGSERIAL PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    stage VARCHAR(50) NOT NULL,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd NUMERIC(12, 6) NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+ALTER TABLE llm_calls
+    ADD COLUMN IF NOT EXISTS duration_ms INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS finish_reason VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS error_class VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS trace_id VARCHAR(32);
+
+CREATE INDEX IF NOT EXISTS idx_llm_calls_created ON llm_calls(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_llm_calls_trace ON llm_calls(trace_id);
@@ -0,0 +1,48 @@
+-- Converts a data_points table created before it was partitioned by year
+-- (see db/schema.sql); does nothing to one that already is. `xtask migrate`
+-- runs it against a database whose schema is already in place.
+DO $$
+DECLARE
+    first_year INT;
+BEGIN
+    IF (SELECT relkind FROM pg_class WHERE oid = 'data_points'::regclass) = 'p' THEN
+        RETURN;
+    END IF;
+
+    ALTER TABLE data_points RENAME TO data_points_unpartitioned;
+    ALTER TABLE data_points_unpartitioned
+        DROP CONSTRAINT data_points_pkey,
+        DROP CONSTRAINT data_points_indicator_id_fkey,
+        DROP CONSTRAINT data_points_indicator_id_observation_date_key;
+    DROP INDEX idx_data_points_indicator;
+    DROP INDEX idx_data_points_date;
+    DROP INDEX idx_data_points_indicator_date;
+
+    CREATE TABLE data_points (
+        LIKE data_points_unpartitioned INCLUDING DEFAULTS INCLUDING CONSTRAINTS,
+        PRIMARY KEY (indicator_id, observation_date),
+        FOREIGN KEY (indicator_id) REFERENCES indicators(id)
+    ) PARTITION BY RANGE (observation_date);
+    CREATE INDEX idx_data_points_indicator ON data_points(indicator_id);
+    CREATE INDEX idx_data_points_date ON data_points(observation_date);
+    CREATE INDEX idx_data_points_indicator_date
+        ON data_points(indicator_id, observation_date DESC);
+    CREATE TABLE data_points_default PARTITION OF data_points DEFAULT;
+
+    first_year := LEAST(
+        2000,
+        (SELECT extract(year FROM MIN(observation_date))::int FROM data_points_unpartitioned)
+    );
+    FOR year IN first_year .. extract(year FROM CURRENT_DATE)::int + 1 LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF data_points FOR VALUES FROM (%L) TO (%L)',
+            'data_points_p' || year,
+            make_date(year, 1, 1),
+            make_date(year + 1, 1, 1)
+        );
+    END LOOP;
+
+    INSERT INTO data_points SELECT * FROM data_points_unpartitioned;
+    ALTER SEQUENCE data_points_id_seq OWNED BY data_points.id;
+    DROP TABLE data_points_unpartitioned;
+END $$;
@@ -0,0 +1,14 @@
+-- Adds report_events (see db/schema.sql) to a database created before it.
+CREATE TABLE IF NOT EXISTS report_events (
+    id BIGSERIAL PRIMARY KEY,
+    report_id UUID NOT NULL,
+    stage VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    duration_ms INTEGER,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_report_events_report ON report_events(report_id, id);
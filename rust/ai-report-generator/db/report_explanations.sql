@@ -0,0 +1,6 @@
+-- Adds report_explanations (see db/schema.sql) to a database created before it.
+CREATE TABLE IF NOT EXISTS report_explanations (
+    report_id UUID PRIMARY KEY REFERENCES reports(id) ON DELETE CASCADE,
+    explanation JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,4 @@
+-- Adds reports.fingerprint (see db/schema.sql) to a database created before it.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS fingerprint VARCHAR(64);
+CREATE INDEX IF NOT EXISTS idx_reports_fingerprint ON reports(fingerprint, created_at DESC)
+    WHERE fingerprint IS NOT NULL;
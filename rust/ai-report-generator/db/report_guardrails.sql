@@ -0,0 +1,3 @@
+-- Adds reports.guardrail_violations (see db/schema.sql) to a database created
+-- before it.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS guardrail_violations JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,4 @@
+-- Adds reports.last_heartbeat_at and reports.checkpoint (see db/schema.sql)
+-- to a database created before them.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS last_heartbeat_at TIMESTAMPTZ;
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS checkpoint JSONB;
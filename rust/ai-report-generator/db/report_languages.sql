@@ -0,0 +1,2 @@
+-- Adds reports.language (see db/schema.sql) to a database created before it.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS language VARCHAR(10) NOT NULL DEFAULT 'en';
@@ -0,0 +1,2 @@
+-- Adds reports.request (see db/schema.sql) to a database created before it.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS request JSONB;
@@ -0,0 +1,5 @@
+-- Adds reports.report_type (see db/schema.sql) to a database created before it,
+-- taken from the stored request where there is one.
+ALTER TABLE reports ADD COLUMN IF NOT EXISTS report_type TEXT NOT NULL DEFAULT 'general';
+UPDATE reports SET report_type = request->>'report_type'
+    WHERE request->>'report_type' IS NOT NULL AND report_type <> request->>'report_type';
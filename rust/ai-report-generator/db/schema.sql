@@ -9,18 +9,81 @@ CREATE TABLE indicators (
 
 CREATE INDEX idx_indicators_code ON indicators(code);
 
-CREATE TABLE data_points (
+-- Named bundles of indicators, so a report can be requested by "groups"
+-- instead of listing codes. Names are unique regardless of case; members are
+-- indicator codes, in report order, checked against indicators on write.
+CREATE TABLE indicator_groups (
     id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    indicators TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_indicator_groups_name ON indicator_groups(lower(name));
+
+-- Range-partitioned by observation year, so a report over a time range only
+-- reads the years in it. The server creates the coming years' partitions and
+-- moves rows that landed in data_points_default into their own year
+-- (src/db/partitions.rs). db/partition_data_points.sql converts a database
+-- created before this.
+CREATE TABLE data_points (
+    id SERIAL NOT NULL,
     indicator_id INTEGER NOT NULL REFERENCES indicators(id),
     observation_date DATE NOT NULL,
     value NUMERIC(20, 6) NOT NULL,
-    UNIQUE(indicator_id, observation_date)
-);
+    -- A primary key on a partitioned table has to include the partition key.
+    PRIMARY KEY (indicator_id, observation_date)
+) PARTITION BY RANGE (observation_date);
 
 CREATE INDEX idx_data_points_indicator ON data_points(indicator_id);
 CREATE INDEX idx_data_points_date ON data_points(observation_date);
 CREATE INDEX idx_data_points_indicator_date ON data_points(indicator_id, observation_date DESC);
 
+CREATE TABLE data_points_default PARTITION OF data_points DEFAULT;
+
+DO $$
+BEGIN
+    FOR year IN 2000 .. extract(year FROM CURRENT_DATE)::int + 1 LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF data_points FOR VALUES FROM (%L) TO (%L)',
+            'data_points_p' || year,
+            make_date(year, 1, 1),
+            make_date(year + 1, 1, 1)
+        );
+    END LOOP;
+END $$;
+
+-- Per-indicator statistics over all of its data points, so GET /api/indicators
+-- and the retrieve stage don't scan data_points for them. The server refreshes
+-- it every INDICATOR_SUMMARY_REFRESH_SECS (src/db/indicator_summaries.rs), and
+-- db/seed.sql after loading data.
+CREATE MATERIALIZED VIEW indicator_summaries AS
+SELECT
+    indicator_id,
+    count(*) AS observations,
+    min(observation_date) AS first_date,
+    max(observation_date) AS last_date,
+    min(value)::float8 AS min_value,
+    max(value)::float8 AS max_value,
+    avg(value)::float8 AS avg_value,
+    (array_agg(value ORDER BY observation_date DESC))[1]::float8 AS last_value,
+    CURRENT_TIMESTAMP AS refreshed_at
+FROM data_points
+GROUP BY indicator_id;
+
+-- REFRESH ... CONCURRENTLY needs a unique index.
+CREATE UNIQUE INDEX idx_indicator_summaries_indicator ON indicator_summaries(indicator_id);
+
+-- US dollars per unit of each currency, used to convert currency indicators
+CREATE TABLE fx_rates (
+    currency CHAR(3) NOT NULL,
+    rate_date DATE NOT NULL,
+    usd_per_unit NUMERIC(20, 8) NOT NULL,
+    PRIMARY KEY (currency, rate_date)
+);
+
 CREATE TABLE reports (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     title VARCHAR(500) NOT NULL,
@@ -36,8 +99,70 @@ CREATE TABLE reports (
     generation_duration_ms INTEGER DEFAULT 0,
     trace_id VARCHAR(32),
     status VARCHAR(20) NOT NULL DEFAULT 'completed',
+    guardrail_violations JSONB NOT NULL DEFAULT '[]',
+    language VARCHAR(10) NOT NULL DEFAULT 'en',
+    -- The report template (pipeline::TemplateRegistry) that produced it.
+    report_type TEXT NOT NULL DEFAULT 'general',
+    -- Hash of the request and models that produced the report, to answer a
+    -- repeat of the request with it (ReportRequest::fingerprint).
+    fingerprint VARCHAR(64),
+    -- The POST /api/reports body that produced the report, for reportctl
+    -- retry and for repricing batch calls at the batch rate.
+    request JSONB,
+    -- Bumped every REPORT_HEARTBEAT_SECS while a pending report's pipeline
+    -- runs; a pending report whose heartbeat stalls is failed by the janitor.
+    last_heartbeat_at TIMESTAMPTZ,
+    -- Stage output saved as the pipeline goes, so POST /api/reports/{id}/resume
+    -- can pick a failed report up where it stopped (pipeline::Checkpoint).
+    checkpoint JSONB,
     created_at TIMESTAMPTZ DEFAULT NOW()
 );
 
 CREATE INDEX idx_reports_status ON reports(status);
 CREATE INDEX idx_reports_created ON reports(created_at DESC);
+CREATE INDEX idx_reports_fingerprint ON reports(fingerprint, created_at DESC)
+    WHERE fingerprint IS NOT NULL;
+
+-- Stage progress of each report, written as the pipeline runs. No foreign key:
+-- interactive reports only get their `reports` row once they are finished.
+CREATE TABLE report_events (
+    id BIGSERIAL PRIMARY KEY,
+    report_id UUID NOT NULL,
+    stage VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    duration_ms INTEGER,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_report_events_report ON report_events(report_id, id);
+
+-- Why an explained report (POST /api/reports with "explain": true) came out
+-- as it did: the pipeline's decisions, in order (explain::Decision).
+CREATE TABLE report_explanations (
+    report_id UUID PRIMARY KEY REFERENCES reports(id) ON DELETE CASCADE,
+    explanation JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per provider call attempt, failed ones included (with an
+-- error_class), for usage and cost reconciliation
+CREATE TABLE llm_calls (
+    id BIGSERIAL PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    stage VARCHAR(50) NOT NULL,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd NUMERIC(12, 6) NOT NULL DEFAULT 0,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    finish_reason VARCHAR(50),
+    error_class VARCHAR(50),
+    trace_id VARCHAR(32),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_llm_calls_created ON llm_calls(created_at DESC);
+CREATE INDEX idx_llm_calls_trace ON llm_calls(trace_id);
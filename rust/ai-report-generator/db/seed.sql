@@ -48,6 +48,15 @@ INSERT INTO indicators (code, name, frequency, unit, description) VALUES
 ('UMCSENT', 'University of Michigan: Consumer Sentiment', 'Monthly', 'Index 1966:Q1=100', 'The Index of Consumer Sentiment is a composite index of five survey questions on consumer financial conditions and attitudes about the economy.')
 ON CONFLICT (code) DO NOTHING;
 
+-- =============================================================================
+-- INDICATOR GROUPS
+-- =============================================================================
+
+INSERT INTO indicator_groups (name, description, indicators) VALUES
+('Labor Market', 'Employment, output and consumer demand', '{UNRATE,INDPRO,RETAILSMNSA,UMCSENT}'),
+('Inflation Basket', 'Consumer and producer prices, money supply and the policy rate', '{CPIAUCSL,PPIACO,M2SL,FEDFUNDS}')
+ON CONFLICT DO NOTHING;
+
 -- =============================================================================
 -- DATA POINTS: UNRATE (Unemployment Rate, Monthly, Percent)
 -- =============================================================================
@@ -2510,4 +2519,56 @@ FROM indicators i,
 WHERE i.code = 'UMCSENT'
 ON CONFLICT (indicator_id, observation_date) DO NOTHING;
 
+-- =============================================================================
+-- FX RATES (annual averages, US dollars per unit)
+-- =============================================================================
+
+INSERT INTO fx_rates (currency, rate_date, usd_per_unit) VALUES
+('EUR', '2003-01-01', 1.13),
+('EUR', '2004-01-01', 1.24),
+('EUR', '2005-01-01', 1.24),
+('EUR', '2006-01-01', 1.26),
+('EUR', '2007-01-01', 1.37),
+('EUR', '2008-01-01', 1.47),
+('EUR', '2009-01-01', 1.39),
+('EUR', '2010-01-01', 1.33),
+('EUR', '2011-01-01', 1.39),
+('EUR', '2012-01-01', 1.29),
+('EUR', '2013-01-01', 1.33),
+('EUR', '2014-01-01', 1.33),
+('EUR', '2015-01-01', 1.11),
+('EUR', '2016-01-01', 1.11),
+('EUR', '2017-01-01', 1.13),
+('EUR', '2018-01-01', 1.18),
+('EUR', '2019-01-01', 1.12),
+('EUR', '2020-01-01', 1.14),
+('EUR', '2021-01-01', 1.18),
+('EUR', '2022-01-01', 1.05),
+('EUR', '2023-01-01', 1.08),
+('GBP', '2003-01-01', 1.63),
+('GBP', '2004-01-01', 1.83),
+('GBP', '2005-01-01', 1.82),
+('GBP', '2006-01-01', 1.84),
+('GBP', '2007-01-01', 2.00),
+('GBP', '2008-01-01', 1.85),
+('GBP', '2009-01-01', 1.57),
+('GBP', '2010-01-01', 1.55),
+('GBP', '2011-01-01', 1.60),
+('GBP', '2012-01-01', 1.59),
+('GBP', '2013-01-01', 1.56),
+('GBP', '2014-01-01', 1.65),
+('GBP', '2015-01-01', 1.53),
+('GBP', '2016-01-01', 1.36),
+('GBP', '2017-01-01', 1.29),
+('GBP', '2018-01-01', 1.34),
+('GBP', '2019-01-01', 1.28),
+('GBP', '2020-01-01', 1.28),
+('GBP', '2021-01-01', 1.38),
+('GBP', '2022-01-01', 1.24),
+('GBP', '2023-01-01', 1.24)
+ON CONFLICT (currency, rate_date) DO NOTHING;
+
 COMMIT;
+
+-- The view is computed when db/schema.sql creates it, before any data.
+REFRESH MATERIALIZED VIEW indicator_summaries;
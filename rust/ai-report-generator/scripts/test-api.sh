@@ -102,9 +102,12 @@ if [[ -n "$REPORT_ID" ]]; then
     red "GET /api/reports/{id} missing title"
     FAIL=$((FAIL + 1))
   fi
+
+  EVENTS_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/reports/$REPORT_ID/events")
+  check "GET /api/reports/$REPORT_ID/events returns 200" "$EVENTS_STATUS" "200"
 else
   red "Skipping GET /api/reports/{id} — no report ID from step 3"
-  FAIL=$((FAIL + 2))
+  FAIL=$((FAIL + 3))
 fi
 
 # 6. Invalid request — empty indicators
@@ -123,6 +126,15 @@ check "POST /api/reports bad date returns 400" "$BAD_DATE_STATUS" "400"
 NOT_FOUND_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/reports/00000000-0000-0000-0000-000000000000")
 check "GET /api/reports/{nonexistent} returns 404" "$NOT_FOUND_STATUS" "404"
 
+# 9. Admin config
+ADMIN_TOKEN="${ADMIN_TOKEN:-change-me-admin-token}"
+CONFIG_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/admin/config")
+check "GET /api/admin/config without token returns 401" "$CONFIG_STATUS" "401"
+
+CONFIG_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/admin/config" \
+  -H "X-Admin-Token: $ADMIN_TOKEN")
+check "GET /api/admin/config returns 200" "$CONFIG_STATUS" "200"
+
 # Summary
 echo ""
 echo "─────────────────────────────"
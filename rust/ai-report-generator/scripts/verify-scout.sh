@@ -118,6 +118,8 @@ if [ "${SKIP_REQUESTS:-}" != "1" ]; then
   LLM_ERR_STATUS=$(echo "$LLM_ERR" | grep -o '"error_triggered"' || echo "")
   if [ -n "$LLM_ERR_STATUS" ]; then
     echo "  $(green "sent") POST /api/test/llm-error — error path exercised"
+  elif echo "$LLM_ERR" | grep -q '"status":404'; then
+    echo "  $(yellow "skip") POST /api/test/llm-error — DEMO_ENDPOINTS_ENABLED is off"
   else
     echo "  $(yellow "warn") POST /api/test/llm-error — unexpected response: ${LLM_ERR}"
   fi
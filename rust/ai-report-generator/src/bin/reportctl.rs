@@ -0,0 +1,387 @@
+//! Operator commands for stored reports, reading the same configuration as
+//! the server: `reportctl list --status failed`, `reportctl retry <id>`.
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use ai_report_generator::Config;
+use ai_report_generator::db::admin::{self, PricedLlmCall};
+use ai_report_generator::db::create_pool;
+use ai_report_generator::db::report_events::list_report_events;
+use ai_report_generator::db::reports::{self, ReportRow};
+use ai_report_generator::llm::openai::BATCH_COST_MULTIPLIER;
+use ai_report_generator::llm::pricing::calculate_cost;
+
+/// The provider name of the batch pipeline's client; calls a batch report
+/// made through the fallback provider were billed at interactive rates.
+const BATCH_PROVIDER: &str = "openai";
+
+#[derive(Parser)]
+#[command(name = "reportctl", about = "Administer stored reports")]
+struct Cli {
+    /// Print JSON lines instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List reports, newest first
+    List {
+        /// Only reports with this status, e.g. pending or failed
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Request the report again through the running server, ignoring any
+    /// identical recent report
+    Retry {
+        id: Uuid,
+        /// Server to send the request to; defaults to APP_PORT on this host
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Run a failed report's pipeline again through the running server,
+    /// from its checkpoint if it has one
+    Resume {
+        id: Uuid,
+        /// Server to send the request to; defaults to APP_PORT on this host
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Mark a pending report cancelled, so its pipeline doesn't store it
+    Cancel { id: Uuid },
+    /// Show the report's pipeline stage events
+    Events { id: Uuid },
+    /// Dump the LLM calls made for the report
+    LlmCalls { id: Uuid },
+    /// Price recorded LLM calls again from the current pricing.json, and
+    /// update the cost of the reports that made them
+    RecomputeCosts {
+        /// Only calls recorded since this date or RFC 3339 time
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+        /// Report what would change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load().await?;
+    let pool = create_pool(&config.database_url).await?;
+
+    match cli.command {
+        Command::List { status, limit } => {
+            let reports = reports::list_reports(&pool, limit, 0, None, status.as_deref()).await?;
+            if cli.json {
+                return print_json_lines(&reports);
+            }
+            for report in &reports {
+                println!(
+                    "{}  {:<9}  {}  ${:<9.6}  {}",
+                    report.id,
+                    report.status,
+                    report
+                        .created_at
+                        .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default(),
+                    report.total_cost_usd.unwrap_or_default(),
+                    report.title,
+                );
+            }
+        }
+        Command::Retry { id, url } => {
+            let report = find_report(&pool, id).await?;
+            if report.status == "pending" {
+                bail!("report {id} is still pending; cancel it first");
+            }
+            let request = admin::get_report_request(&pool, id).await?;
+            let url = url.unwrap_or_else(|| default_url(&config));
+            let body = post(&url, "/api/reports", Some(&retry_body(request, &report))).await?;
+            println!("{body}");
+        }
+        Command::Resume { id, url } => {
+            let url = url.unwrap_or_else(|| default_url(&config));
+            let body = post(&url, &format!("/api/reports/{id}/resume"), None).await?;
+            println!("{body}");
+        }
+        Command::Cancel { id } => {
+            if reports::cancel_report(&pool, id).await? {
+                println!("Cancelled report {id}");
+            } else {
+                let report = find_report(&pool, id).await?;
+                bail!(
+                    "report {id} is {}, only pending reports can be cancelled",
+                    report.status
+                );
+            }
+        }
+        Command::Events { id } => {
+            find_report(&pool, id).await?;
+            let events = list_report_events(&pool, id).await?;
+            if cli.json {
+                return print_json_lines(&events);
+            }
+            for event in &events {
+                println!(
+                    "{}  {:<12}  {:<9}  {:>7}ms  {:>6} in  {:>6} out  {}",
+                    event.created_at.format("%H:%M:%S%.3f"),
+                    event.stage,
+                    event.status,
+                    event.duration_ms.unwrap_or_default(),
+                    event.input_tokens.unwrap_or_default(),
+                    event.output_tokens.unwrap_or_default(),
+                    event.error.as_deref().unwrap_or(""),
+                );
+            }
+        }
+        Command::LlmCalls { id } => {
+            let report = find_report(&pool, id).await?;
+            if report.trace_id.is_none() {
+                bail!("report {id} has no trace ID to match its calls by");
+            }
+            let calls = admin::list_report_llm_calls(&pool, id).await?;
+            if cli.json {
+                return print_json_lines(&calls);
+            }
+            for call in &calls {
+                println!(
+                    "{}  {:<10}  {:<28}  {:<10}  {:>6} in  {:>6} out  ${:<9.6}  {:>7}ms  {}",
+                    call.created_at.format("%H:%M:%S%.3f"),
+                    call.provider,
+                    call.model,
+                    call.stage,
+                    call.input_tokens,
+                    call.output_tokens,
+                    call.cost_usd,
+                    call.duration_ms,
+                    call.error_class
+                        .as_deref()
+                        .or(call.finish_reason.as_deref())
+                        .unwrap_or(""),
+                );
+            }
+            let total: f64 = calls.iter().map(|call| call.cost_usd).sum();
+            println!(
+                "{} calls, ${total:.6}; report total ${:.6}",
+                calls.len(),
+                report.total_cost_usd.unwrap_or_default()
+            );
+        }
+        Command::RecomputeCosts { since, dry_run } => {
+            let calls = admin::list_priced_llm_calls(&pool, since).await?;
+            let (mut ids, mut costs) = (Vec::new(), Vec::new());
+            let (mut before, mut after) = (0.0, 0.0);
+            for call in &calls {
+                let cost = current_cost(call);
+                if !same_cost(cost, call.cost_usd) {
+                    before += call.cost_usd;
+                    after += cost;
+                    ids.push(call.id);
+                    costs.push(cost);
+                }
+            }
+            println!(
+                "{} of {} calls repriced: ${before:.6} -> ${after:.6}",
+                ids.len(),
+                calls.len()
+            );
+            if !dry_run && !ids.is_empty() {
+                let updated = admin::reprice_llm_calls(&pool, &ids, &costs).await?;
+                println!("{updated} report costs updated");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn find_report(pool: &PgPool, id: Uuid) -> anyhow::Result<ReportRow> {
+    reports::get_report(pool, id)
+        .await?
+        .with_context(|| format!("report {id} not found"))
+}
+
+/// POSTs to the server at `url` and returns the response body.
+async fn post(url: &str, path: &str, body: Option<&Value>) -> anyhow::Result<String> {
+    let mut request = reqwest::Client::new().post(format!("{}{path}", url.trim_end_matches('/')));
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("sending the request to {url}"))?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        bail!("server answered {status}: {body}");
+    }
+    Ok(body)
+}
+
+fn print_json_lines<T: Serialize>(rows: &[T]) -> anyhow::Result<()> {
+    for row in rows {
+        println!("{}", serde_json::to_string(row)?);
+    }
+    Ok(())
+}
+
+fn default_url(config: &Config) -> String {
+    let scheme = if config.tls_enabled() {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{scheme}://127.0.0.1:{}", config.port)
+}
+
+/// The stored request with `force` set, or for a report stored before
+/// requests were, one rebuilt from its columns with the default currency
+/// and mode.
+fn retry_body(request: Option<Value>, report: &ReportRow) -> Value {
+    let mut body = request.unwrap_or_else(|| {
+        json!({
+            "indicators": report.indicators_used,
+            "start_date": report.time_range_start.to_string(),
+            "end_date": report.time_range_end.to_string(),
+            "language": report.language,
+            "report_type": report.report_type,
+        })
+    });
+    body["force"] = true.into();
+    body
+}
+
+fn current_cost(call: &PricedLlmCall) -> f64 {
+    let cost = calculate_cost(
+        &call.model,
+        call.input_tokens.max(0) as u32,
+        call.output_tokens.max(0) as u32,
+    );
+    if call.batch && call.provider == BATCH_PROVIDER {
+        cost * BATCH_COST_MULTIPLIER
+    } else {
+        cost
+    }
+}
+
+/// Costs are stored to the millionth of a dollar.
+fn same_cost(a: f64, b: f64) -> bool {
+    (a * 1e6).round() == (b * 1e6).round()
+}
+
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| "use YYYY-MM-DD or an RFC 3339 time".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> ReportRow {
+        ReportRow {
+            id: Uuid::new_v4(),
+            title: "Labor market".into(),
+            executive_summary: String::new(),
+            sections: json!([]),
+            indicators_used: vec!["UNRATE".into(), "PAYEMS".into()],
+            time_range_start: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            time_range_end: NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+            total_data_points: 0,
+            total_tokens: None,
+            total_cost_usd: None,
+            providers_used: vec![],
+            generation_duration_ms: None,
+            trace_id: None,
+            status: "failed".into(),
+            guardrail_violations: json!([]),
+            language: "de".into(),
+            report_type: "labor".into(),
+            created_at: None,
+        }
+    }
+
+    fn call(provider: &str, batch: bool) -> PricedLlmCall {
+        PricedLlmCall {
+            id: 1,
+            provider: provider.into(),
+            model: "gpt-4o-mini".into(),
+            input_tokens: 1_000_000,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            batch,
+        }
+    }
+
+    #[test]
+    fn test_retry_body_forces_the_stored_request() {
+        let stored = json!({"indicators": ["UNRATE"], "start_date": "2021-01-01",
+            "end_date": "2022-01-01", "mode": "batch", "report_type": "labor"});
+        let body = retry_body(Some(stored), &report());
+        assert_eq!(body["mode"], "batch");
+        assert_eq!(body["report_type"], "labor");
+        assert_eq!(body["force"], true);
+    }
+
+    #[test]
+    fn test_retry_body_rebuilt_from_the_report() {
+        let body = retry_body(None, &report());
+        assert_eq!(body["indicators"], json!(["UNRATE", "PAYEMS"]));
+        assert_eq!(body["start_date"], "2020-01-01");
+        assert_eq!(body["end_date"], "2023-12-31");
+        assert_eq!(body["language"], "de");
+        assert_eq!(body["report_type"], "labor");
+        assert_eq!(body["force"], true);
+        assert!(body.get("mode").is_none());
+    }
+
+    #[test]
+    fn test_current_cost_discounts_batch_calls() {
+        let interactive = current_cost(&call("openai", false));
+        assert!(interactive > 0.0);
+        assert_eq!(
+            current_cost(&call("openai", true)),
+            interactive * BATCH_COST_MULTIPLIER
+        );
+        // A batch report's fallback calls went out interactively.
+        assert_eq!(current_cost(&call("anthropic", true)), interactive);
+    }
+
+    #[test]
+    fn test_same_cost_at_stored_precision() {
+        assert!(same_cost(0.1234564, 0.123456));
+        assert!(!same_cost(0.123457, 0.123456));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(
+            parse_since("2026-01-01").unwrap().to_rfc3339(),
+            "2026-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("2026-01-01T12:00:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2026-01-01T10:00:00+00:00"
+        );
+        assert!(parse_since("yesterday").is_err());
+    }
+}
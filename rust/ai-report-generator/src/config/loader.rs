@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::secrets::SecretResolver;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Error)]
+#[error("invalid configuration:\n  - {}", .errors.join("\n  - "))]
+pub struct ConfigError {
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Default,
+    File,
+    SecretManager,
+    SecretFile,
+    Env,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ConfigEntry {
+    pub value: String,
+    pub source: Source,
+    #[serde(skip)]
+    pub secret: bool,
+}
+
+impl ConfigEntry {
+    pub fn display_value(&self) -> &str {
+        if self.secret && !self.value.is_empty() {
+            REDACTED
+        } else {
+            &self.value
+        }
+    }
+}
+
+impl fmt::Debug for ConfigEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} ({:?})", self.display_value(), self.source)
+    }
+}
+
+/// Resolves keys as environment variable > `{KEY}_FILE` > secret manager >
+/// `config.{env}.toml` > default and records every problem instead of
+/// stopping at the first one.
+pub struct Layers {
+    env: HashMap<String, String>,
+    secret_files: HashMap<String, String>,
+    managed: HashMap<String, String>,
+    file: HashMap<String, String>,
+    resolver: SecretResolver,
+    secret_keys: &'static [&'static str],
+    entries: BTreeMap<String, ConfigEntry>,
+    errors: Vec<String>,
+}
+
+impl Layers {
+    pub async fn load(
+        environment: &str,
+        secret_keys: &'static [&'static str],
+    ) -> (Self, Option<PathBuf>) {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let path = env
+            .get("CONFIG_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(format!("config.{}.toml", environment)));
+
+        let mut errors = Vec::new();
+        let file = match read_file(&path) {
+            Ok(Some(file)) => Some((file, path)),
+            Ok(None) if env.contains_key("CONFIG_FILE") => {
+                errors.push(format!("CONFIG_FILE {} does not exist", path.display()));
+                None
+            }
+            Ok(None) => None,
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
+
+        let (file, path) = match file {
+            Some((file, path)) => (file, Some(path)),
+            None => (HashMap::new(), None),
+        };
+
+        let mut layers = Self::from_parts(env, file, secret_keys);
+        layers.errors.extend(errors);
+        match layers.resolver.fetch_managed(secret_keys).await {
+            Ok(managed) => layers.managed = managed,
+            Err(e) => layers.errors.push(e),
+        }
+        (layers, path)
+    }
+
+    pub fn from_parts(
+        env: HashMap<String, String>,
+        file: HashMap<String, String>,
+        secret_keys: &'static [&'static str],
+    ) -> Self {
+        // Compose files pass unset secrets through as `KEY=`; treat those as
+        // absent so `KEY_FILE` and the secret manager can still supply them.
+        let mut env = env;
+        env.retain(|key, value| !value.is_empty() || !secret_keys.contains(&key.as_str()));
+
+        let (resolver, mut errors) = match SecretResolver::from_env(&env, secret_keys) {
+            Ok(resolver) => (resolver, Vec::new()),
+            Err(errors) => (SecretResolver::default(), errors),
+        };
+        let (secret_files, file_errors) = resolver.read_files();
+        errors.extend(file_errors);
+
+        Self {
+            env,
+            secret_files,
+            managed: HashMap::new(),
+            file,
+            resolver,
+            secret_keys,
+            entries: BTreeMap::new(),
+            errors,
+        }
+    }
+
+    pub fn resolver(&self) -> SecretResolver {
+        self.resolver.clone()
+    }
+
+    fn lookup(&self, key: &str) -> Option<(String, Source)> {
+        [
+            (&self.env, Source::Env),
+            (&self.secret_files, Source::SecretFile),
+            (&self.managed, Source::SecretManager),
+            (&self.file, Source::File),
+        ]
+        .into_iter()
+        .find_map(|(layer, source)| layer.get(key).map(|value| (value.clone(), source)))
+    }
+
+    fn record(&mut self, key: &str, value: &str, source: Source) {
+        self.entries.insert(
+            key.to_string(),
+            ConfigEntry {
+                value: value.to_string(),
+                source,
+                secret: self.secret_keys.contains(&key),
+            },
+        );
+    }
+
+    pub fn string(&mut self, key: &str, default: &str) -> String {
+        let (value, source) = self
+            .lookup(key)
+            .unwrap_or_else(|| (default.to_string(), Source::Default));
+        self.record(key, &value, source);
+        value
+    }
+
+    pub fn required(&mut self, key: &str) -> String {
+        match self.lookup(key) {
+            Some((value, source)) if !value.is_empty() => {
+                self.record(key, &value, source);
+                value
+            }
+            _ => {
+                self.errors.push(format!("{} must be set", key));
+                String::new()
+            }
+        }
+    }
+
+    /// Empty values count as unset, matching `KEY=` lines in `.env` files.
+    pub fn optional(&mut self, key: &str) -> Option<String> {
+        let (value, source) = self.lookup(key).filter(|(value, _)| !value.is_empty())?;
+        self.record(key, &value, source);
+        Some(value)
+    }
+
+    pub fn parse<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr + fmt::Display,
+        T::Err: fmt::Display,
+    {
+        let Some((raw, source)) = self.lookup(key) else {
+            self.record(key, &default.to_string(), Source::Default);
+            return default;
+        };
+
+        match raw.trim().parse() {
+            Ok(value) => {
+                self.record(key, &raw, source);
+                value
+            }
+            Err(e) => {
+                self.errors
+                    .push(format!("{} has invalid value {:?}: {}", key, raw, e));
+                default
+            }
+        }
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(message.into());
+    }
+
+    pub fn finish(self) -> Result<BTreeMap<String, ConfigEntry>, ConfigError> {
+        if self.errors.is_empty() {
+            Ok(self.entries)
+        } else {
+            Err(ConfigError {
+                errors: self.errors,
+            })
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<Option<HashMap<String, String>>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+
+    let mut values = HashMap::new();
+    flatten("", &table, &mut values);
+    Ok(Some(values))
+}
+
+/// `[s3] bucket = "x"` becomes `S3_BUCKET`, matching the env var name.
+fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let name = if prefix.is_empty() {
+            key.to_uppercase()
+        } else {
+            format!("{}_{}", prefix, key.to_uppercase())
+        };
+        match value {
+            toml::Value::Table(nested) => flatten(&name, nested, out),
+            toml::Value::String(s) => {
+                out.insert(name, s.clone());
+            }
+            other => {
+                out.insert(name, other.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRETS: &[&str] = &["SECRET"];
+
+    fn layers(env: &[(&str, &str)], file: &str) -> Layers {
+        let env = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut values = HashMap::new();
+        flatten("", &file.parse().expect("valid toml"), &mut values);
+        Layers::from_parts(env, values, SECRETS)
+    }
+
+    #[test]
+    fn test_env_overrides_file_overrides_default() {
+        let mut layers = layers(&[("PORT", "9000")], "port = 8081\nname = \"from-file\"");
+
+        assert_eq!(layers.parse::<u16>("PORT", 8080), 9000);
+        assert_eq!(layers.string("NAME", "default"), "from-file");
+        assert_eq!(layers.string("OTHER", "default"), "default");
+
+        let entries = layers.finish().expect("no errors");
+        assert_eq!(entries["PORT"].source, Source::Env);
+        assert_eq!(entries["NAME"].source, Source::File);
+        assert_eq!(entries["OTHER"].source, Source::Default);
+    }
+
+    #[test]
+    fn test_nested_tables_flatten_to_env_names() {
+        let mut layers = layers(&[], "[s3]\nbucket = \"uploads\"");
+        assert_eq!(layers.string("S3_BUCKET", ""), "uploads");
+    }
+
+    #[test]
+    fn test_errors_are_collected() {
+        let mut layers = layers(&[("PORT", "abc"), ("TTL", "-")], "");
+        layers.parse::<u16>("PORT", 8080);
+        layers.parse::<i64>("TTL", 10);
+        layers.required("DATABASE_URL");
+
+        let err = layers.finish().expect_err("should fail");
+        assert_eq!(err.errors.len(), 3);
+        assert!(err.to_string().contains("DATABASE_URL must be set"));
+    }
+
+    #[test]
+    fn test_secret_layers_sit_between_env_and_file() {
+        let path = std::env::temp_dir().join(format!("secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "from-secret-file\n").unwrap();
+
+        let mut layers = layers(
+            &[("SECRET", ""), ("SECRET_FILE", path.to_str().unwrap())],
+            "secret = \"from-config\"",
+        );
+        layers
+            .managed
+            .insert("SECRET".to_string(), "from-manager".to_string());
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(layers.string("SECRET", ""), "from-secret-file");
+        let entries = layers.finish().expect("no errors");
+        assert_eq!(entries["SECRET"].source, Source::SecretFile);
+    }
+
+    #[test]
+    fn test_secret_entries_are_redacted() {
+        let mut layers = layers(&[("SECRET", "hunter2")], "");
+        layers.string("SECRET", "");
+
+        let entries = layers.finish().expect("no errors");
+        assert_eq!(entries["SECRET"].display_value(), REDACTED);
+        assert!(!format!("{:?}", entries["SECRET"]).contains("hunter2"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Queries behind `reportctl`, the operator CLI (src/bin/reportctl.rs).
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::Traced;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LlmCallRow {
+    pub id: i64,
+    pub provider: String,
+    pub model: String,
+    pub stage: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost_usd: f64,
+    pub duration_ms: i32,
+    pub finish_reason: Option<String>,
+    pub error_class: Option<String>,
+    pub trace_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A successful call's tokens and recorded cost, to price it again.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PricedLlmCall {
+    pub id: i64,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost_usd: f64,
+    /// Made for a report requested in batch mode.
+    pub batch: bool,
+}
+
+/// The request body stored with the report; reports from before it was
+/// stored have none.
+#[tracing::instrument(name = "db.reports.get_request", skip(pool))]
+pub async fn get_report_request(
+    pool: &PgPool,
+    id: Uuid,
+) -> Result<Option<serde_json::Value>, sqlx::Error> {
+    let row: Option<(Option<serde_json::Value>,)> =
+        sqlx::query_as("SELECT request FROM reports WHERE id = $1")
+            .bind(id)
+            .traced(pool)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row.and_then(|(request,)| request))
+}
+
+/// The calls made while generating the report, matched by its trace ID,
+/// oldest first.
+#[tracing::instrument(name = "db.llm_calls.list_for_report", skip(pool))]
+pub async fn list_report_llm_calls(
+    pool: &PgPool,
+    report_id: Uuid,
+) -> Result<Vec<LlmCallRow>, sqlx::Error> {
+    sqlx::query_as::<_, LlmCallRow>(
+        "SELECT c.id, c.provider, c.model, c.stage, c.input_tokens, c.output_tokens, \
+                c.cost_usd::float8 AS cost_usd, c.duration_ms, c.finish_reason, \
+                c.error_class, c.trace_id, c.created_at \
+         FROM llm_calls c JOIN reports r ON r.trace_id = c.trace_id \
+         WHERE r.id = $1 \
+         ORDER BY c.created_at, c.id",
+    )
+    .bind(report_id)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+/// Successful calls recorded since `since`, or ever.
+#[tracing::instrument(name = "db.llm_calls.list_priced", skip(pool))]
+pub async fn list_priced_llm_calls(
+    pool: &PgPool,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<PricedLlmCall>, sqlx::Error> {
+    sqlx::query_as::<_, PricedLlmCall>(
+        "WITH batch AS ( \
+           SELECT DISTINCT trace_id FROM reports \
+           WHERE request->>'mode' = 'batch' AND trace_id IS NOT NULL \
+         ) \
+         SELECT c.id, c.provider, c.model, c.input_tokens, c.output_tokens, \
+                c.cost_usd::float8 AS cost_usd, b.trace_id IS NOT NULL AS batch \
+         FROM llm_calls c LEFT JOIN batch b ON b.trace_id = c.trace_id \
+         WHERE c.error_class IS NULL AND ($1::timestamptz IS NULL OR c.created_at >= $1) \
+         ORDER BY c.id",
+    )
+    .bind(since)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+/// Sets each call in `ids` to the cost at the same index of `costs`, then
+/// each report that made one of them to the sum over all of its calls, in
+/// one transaction. Returns how many reports changed.
+#[tracing::instrument(name = "db.llm_calls.reprice", skip_all, fields(row_count = ids.len()))]
+pub async fn reprice_llm_calls(
+    pool: &PgPool,
+    ids: &[i64],
+    costs: &[f64],
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE llm_calls c SET cost_usd = v.cost_usd \
+         FROM unnest($1::int8[], $2::float8[]) AS v(id, cost_usd) \
+         WHERE c.id = v.id",
+    )
+    .bind(ids)
+    .bind(costs)
+    .traced(pool)
+    .execute(&mut *tx)
+    .await?;
+    let reports = sqlx::query(
+        "UPDATE reports r SET total_cost_usd = s.cost_usd \
+         FROM ( \
+           SELECT trace_id, SUM(cost_usd) AS cost_usd FROM llm_calls \
+           WHERE trace_id IN (SELECT trace_id FROM llm_calls WHERE id = ANY($1)) \
+           GROUP BY trace_id \
+         ) s \
+         WHERE r.trace_id = s.trace_id AND r.total_cost_usd IS DISTINCT FROM s.cost_usd",
+    )
+    .bind(ids)
+    .traced(pool)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+
+    Ok(reports)
+}
@@ -4,6 +4,8 @@ use chrono::NaiveDate;
 use serde::Serialize;
 use sqlx::PgPool;
 
+use crate::db::Traced;
+
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct DataPoint {
     pub observation_date: NaiveDate,
@@ -55,6 +57,7 @@ pub async fn query_indicator_data(
     .bind(codes)
     .bind(start_date)
     .bind(end_date)
+    .traced(pool)
     .fetch_all(pool)
     .await?;
 
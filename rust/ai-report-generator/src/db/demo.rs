@@ -0,0 +1,17 @@
+use sqlx::PgPool;
+
+use crate::db::Traced;
+
+/// A count over `data_points` that takes at least `seconds`, for the
+/// slow-query demo scenario.
+#[tracing::instrument(name = "db.data_points.slow_count", skip(pool))]
+pub async fn slow_count(pool: &PgPool, seconds: f64) -> Result<i64, sqlx::Error> {
+    let row: (i64,) =
+        sqlx::query_as("SELECT count(*) FROM data_points, (SELECT pg_sleep($1)) AS delay")
+            .bind(seconds)
+            .traced(pool)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(row.0)
+}
@@ -0,0 +1,35 @@
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::db::Traced;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FxRate {
+    pub currency: String,
+    pub rate_date: NaiveDate,
+    pub usd_per_unit: f64,
+}
+
+/// Rates up to `end_date` for `currencies`, oldest first per currency.
+#[tracing::instrument(name = "db.fx_rates.query", skip(pool), fields(rate_count))]
+pub async fn query_fx_rates(
+    pool: &PgPool,
+    currencies: &[String],
+    end_date: NaiveDate,
+) -> Result<Vec<FxRate>, sqlx::Error> {
+    let rates = sqlx::query_as::<_, FxRate>(
+        "SELECT currency, rate_date, usd_per_unit::float8 as usd_per_unit \
+         FROM fx_rates \
+         WHERE currency = ANY($1) AND rate_date <= $2 \
+         ORDER BY currency, rate_date",
+    )
+    .bind(currencies)
+    .bind(end_date)
+    .traced(pool)
+    .fetch_all(pool)
+    .await?;
+
+    tracing::Span::current().record("rate_count", rates.len());
+
+    Ok(rates)
+}
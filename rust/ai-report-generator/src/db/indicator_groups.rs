@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::Traced;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct IndicatorGroup {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    /// Indicator codes, in the order a report over the group lists them.
+    pub indicators: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct WriteIndicatorGroup<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub indicators: &'a [String],
+}
+
+#[tracing::instrument(name = "db.indicator_groups.list", skip(pool))]
+pub async fn list_indicator_groups(pool: &PgPool) -> Result<Vec<IndicatorGroup>, sqlx::Error> {
+    sqlx::query_as::<_, IndicatorGroup>(
+        "SELECT id, name, description, indicators, created_at, updated_at \
+         FROM indicator_groups ORDER BY name",
+    )
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(name = "db.indicator_groups.get", skip(pool))]
+pub async fn get_indicator_group(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Option<IndicatorGroup>, sqlx::Error> {
+    sqlx::query_as::<_, IndicatorGroup>(
+        "SELECT id, name, description, indicators, created_at, updated_at \
+         FROM indicator_groups WHERE id = $1",
+    )
+    .bind(id)
+    .traced(pool)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The groups named in `names`, matched regardless of case. Names without a
+/// group are left out.
+#[tracing::instrument(name = "db.indicator_groups.find_by_names", skip(pool))]
+pub async fn find_indicator_groups(
+    pool: &PgPool,
+    names: &[String],
+) -> Result<Vec<IndicatorGroup>, sqlx::Error> {
+    sqlx::query_as::<_, IndicatorGroup>(
+        "SELECT id, name, description, indicators, created_at, updated_at \
+         FROM indicator_groups \
+         WHERE lower(name) = ANY(SELECT lower(n) FROM unnest($1::text[]) n)",
+    )
+    .bind(names)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+/// Fails with a unique violation if another group has the name.
+#[tracing::instrument(name = "db.indicator_groups.insert", skip_all, fields(group.name = group.name))]
+pub async fn insert_indicator_group(
+    pool: &PgPool,
+    group: &WriteIndicatorGroup<'_>,
+) -> Result<IndicatorGroup, sqlx::Error> {
+    sqlx::query_as::<_, IndicatorGroup>(
+        "INSERT INTO indicator_groups (name, description, indicators) VALUES ($1, $2, $3) \
+         RETURNING id, name, description, indicators, created_at, updated_at",
+    )
+    .bind(group.name)
+    .bind(group.description)
+    .bind(group.indicators)
+    .traced(pool)
+    .fetch_one(pool)
+    .await
+}
+
+/// Replaces the group's name, description and members. Fails with a unique
+/// violation if another group has the name.
+#[tracing::instrument(name = "db.indicator_groups.update", skip(pool, group))]
+pub async fn update_indicator_group(
+    pool: &PgPool,
+    id: i32,
+    group: &WriteIndicatorGroup<'_>,
+) -> Result<Option<IndicatorGroup>, sqlx::Error> {
+    sqlx::query_as::<_, IndicatorGroup>(
+        "UPDATE indicator_groups \
+         SET name = $2, description = $3, indicators = $4, updated_at = NOW() \
+         WHERE id = $1 \
+         RETURNING id, name, description, indicators, created_at, updated_at",
+    )
+    .bind(id)
+    .bind(group.name)
+    .bind(group.description)
+    .bind(group.indicators)
+    .traced(pool)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Returns whether the group existed.
+#[tracing::instrument(name = "db.indicator_groups.delete", skip(pool))]
+pub async fn delete_indicator_group(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM indicator_groups WHERE id = $1")
+        .bind(id)
+        .traced(pool)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::Traced;
+
+/// An indicator's statistics over all of its data points, as of the last
+/// refresh of the `indicator_summaries` materialized view.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct IndicatorSummary {
+    pub observations: i64,
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub avg_value: f64,
+    pub last_value: f64,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+impl IndicatorSummary {
+    /// Whether any of the summarized observations fall in `[start, end]`.
+    pub fn overlaps(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.first_date <= end && self.last_date >= start
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SummaryRow {
+    code: String,
+    #[sqlx(flatten)]
+    summary: IndicatorSummary,
+}
+
+/// Summaries of `codes` by code. Indicators without data points, or added
+/// since the last refresh, have none.
+#[tracing::instrument(
+    name = "db.indicator_summaries.query",
+    skip(pool),
+    fields(summary_count)
+)]
+pub async fn query_indicator_summaries(
+    pool: &PgPool,
+    codes: &[String],
+) -> Result<HashMap<String, IndicatorSummary>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, SummaryRow>(
+        "SELECT i.code, s.observations, s.first_date, s.last_date, s.min_value, \
+                s.max_value, s.avg_value, s.last_value, s.refreshed_at \
+         FROM indicator_summaries s \
+         JOIN indicators i ON i.id = s.indicator_id \
+         WHERE i.code = ANY($1)",
+    )
+    .bind(codes)
+    .traced(pool)
+    .fetch_all(pool)
+    .await?;
+
+    tracing::Span::current().record("summary_count", rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.code, row.summary))
+        .collect())
+}
+
+/// `CONCURRENTLY` keeps the view readable while it recomputes.
+#[tracing::instrument(name = "db.indicator_summaries.refresh", skip(pool))]
+pub async fn refresh_indicator_summaries(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("REFRESH MATERIALIZED VIEW CONCURRENTLY indicator_summaries")
+        .traced(pool)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Refreshes `indicator_summaries` every `interval_secs`. Zero leaves it as
+/// it was last refreshed.
+pub fn spawn_refresh(pool: PgPool, interval_secs: u64) {
+    if interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(err) = refresh_indicator_summaries(&pool).await {
+                tracing::warn!(error = %err, "Failed to refresh indicator summaries");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(first: (i32, u32), last: (i32, u32)) -> IndicatorSummary {
+        let date = |(year, month)| NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        IndicatorSummary {
+            observations: 12,
+            first_date: date(first),
+            last_date: date(last),
+            min_value: 1.0,
+            max_value: 3.0,
+            avg_value: 2.0,
+            last_value: 2.5,
+            refreshed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let date = |year, month| NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        let s = summary((2010, 1), (2020, 12));
+
+        assert!(s.overlaps(date(2015, 1), date(2016, 1)));
+        assert!(s.overlaps(date(2000, 1), date(2010, 1)));
+        assert!(s.overlaps(date(2020, 12), date(2030, 1)));
+        assert!(s.overlaps(date(2000, 1), date(2030, 1)));
+        assert!(!s.overlaps(date(2000, 1), date(2009, 12)));
+        assert!(!s.overlaps(date(2021, 1), date(2030, 1)));
+    }
+}
@@ -1,6 +1,9 @@
 use serde::Serialize;
 use sqlx::PgPool;
 
+use crate::db::Traced;
+use crate::db::indicator_summaries::IndicatorSummary;
+
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct Indicator {
     pub id: i32,
@@ -9,6 +12,9 @@ pub struct Indicator {
     pub frequency: String,
     pub unit: String,
     pub description: Option<String>,
+    /// Filled in from `indicator_summaries` by the indicators endpoint.
+    #[sqlx(skip)]
+    pub summary: Option<IndicatorSummary>,
 }
 
 #[tracing::instrument(name = "db.indicators.list", skip(pool))]
@@ -16,6 +22,24 @@ pub async fn list_indicators(pool: &PgPool) -> Result<Vec<Indicator>, sqlx::Erro
     sqlx::query_as::<_, Indicator>(
         "SELECT id, code, name, frequency, unit, description FROM indicators ORDER BY code",
     )
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+/// The codes in `codes` that no indicator has, in the order given.
+#[tracing::instrument(name = "db.indicators.unknown_codes", skip(pool))]
+pub async fn unknown_indicator_codes(
+    pool: &PgPool,
+    codes: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT c.code FROM unnest($1::text[]) WITH ORDINALITY AS c(code, n) \
+         WHERE NOT EXISTS (SELECT 1 FROM indicators i WHERE i.code = c.code) \
+         ORDER BY c.n",
+    )
+    .bind(codes)
+    .traced(pool)
     .fetch_all(pool)
     .await
 }
@@ -30,6 +54,7 @@ pub async fn get_indicator_by_code(
         "SELECT id, code, name, frequency, unit, description FROM indicators WHERE code = $1",
     )
     .bind(code)
+    .traced(pool)
     .fetch_optional(pool)
     .await
 }
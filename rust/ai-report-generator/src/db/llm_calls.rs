@@ -0,0 +1,155 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+use crate::db::Traced;
+
+/// One `generate_once` attempt. Failed attempts carry `error_class` and no
+/// token counts.
+#[derive(Debug, Clone)]
+pub struct InsertLlmCall {
+    pub provider: String,
+    pub model: String,
+    pub stage: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost_usd: f64,
+    pub duration_ms: i32,
+    pub finish_reason: Option<String>,
+    pub error_class: Option<String>,
+    pub trace_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DailyUsage {
+    pub date: NaiveDate,
+    pub reports: i64,
+    pub report_tokens: i64,
+    pub report_cost_usd: f64,
+    pub llm_calls: i64,
+    pub failed_llm_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub llm_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub llm_calls: i64,
+    pub failed_llm_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[tracing::instrument(name = "db.llm_calls.insert", skip_all, fields(row_count = calls.len()))]
+pub async fn insert_llm_calls(pool: &PgPool, calls: &[InsertLlmCall]) -> Result<(), sqlx::Error> {
+    if calls.is_empty() {
+        return Ok(());
+    }
+    let mut query = QueryBuilder::<Postgres>::new(
+        "INSERT INTO llm_calls \
+         (provider, model, stage, input_tokens, output_tokens, cost_usd, \
+          duration_ms, finish_reason, error_class, trace_id, created_at) ",
+    );
+    query.push_values(calls, |mut row, call| {
+        row.push_bind(&call.provider)
+            .push_bind(&call.model)
+            .push_bind(&call.stage)
+            .push_bind(call.input_tokens)
+            .push_bind(call.output_tokens)
+            .push_bind(call.cost_usd)
+            .push_bind(call.duration_ms)
+            .push_bind(&call.finish_reason)
+            .push_bind(&call.error_class)
+            .push_bind(&call.trace_id)
+            .push_bind(call.created_at);
+    });
+    query.build().traced(pool).execute(pool).await?;
+
+    Ok(())
+}
+
+/// Deletes calls recorded before `cutoff`, returning how many went.
+#[tracing::instrument(name = "db.llm_calls.delete", skip(pool))]
+pub async fn delete_llm_calls_before(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM llm_calls WHERE created_at < $1")
+        .bind(cutoff)
+        .traced(pool)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Report totals next to the per-call totals for each UTC day in
+/// `[from, to)`, so the two sides of the accounting can be compared.
+#[tracing::instrument(name = "db.usage.daily", skip(pool))]
+pub async fn daily_usage(
+    pool: &PgPool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<DailyUsage>, sqlx::Error> {
+    sqlx::query_as::<_, DailyUsage>(
+        "WITH r AS ( \
+           SELECT (created_at AT TIME ZONE 'UTC')::date AS day, COUNT(*) AS reports, \
+                  COALESCE(SUM(total_tokens), 0)::int8 AS tokens, \
+                  COALESCE(SUM(total_cost_usd), 0)::float8 AS cost_usd \
+           FROM reports WHERE created_at >= $1 AND created_at < $2 GROUP BY 1 \
+         ), c AS ( \
+           SELECT (created_at AT TIME ZONE 'UTC')::date AS day, \
+                  COUNT(*) FILTER (WHERE error_class IS NULL) AS calls, \
+                  COUNT(*) FILTER (WHERE error_class IS NOT NULL) AS failed_calls, \
+                  SUM(input_tokens)::int8 AS input_tokens, \
+                  SUM(output_tokens)::int8 AS output_tokens, \
+                  SUM(cost_usd)::float8 AS cost_usd \
+           FROM llm_calls WHERE created_at >= $1 AND created_at < $2 GROUP BY 1 \
+         ) \
+         SELECT COALESCE(r.day, c.day) AS date, \
+                COALESCE(r.reports, 0) AS reports, \
+                COALESCE(r.tokens, 0) AS report_tokens, \
+                COALESCE(r.cost_usd, 0) AS report_cost_usd, \
+                COALESCE(c.calls, 0) AS llm_calls, \
+                COALESCE(c.failed_calls, 0) AS failed_llm_calls, \
+                COALESCE(c.input_tokens, 0) AS input_tokens, \
+                COALESCE(c.output_tokens, 0) AS output_tokens, \
+                COALESCE(c.cost_usd, 0) AS llm_cost_usd \
+         FROM r FULL OUTER JOIN c ON r.day = c.day \
+         ORDER BY 1",
+    )
+    .bind(from)
+    .bind(to)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(name = "db.usage.by_model", skip(pool))]
+pub async fn usage_by_model(
+    pool: &PgPool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<ModelUsage>, sqlx::Error> {
+    sqlx::query_as::<_, ModelUsage>(
+        "SELECT provider, model, \
+                COUNT(*) FILTER (WHERE error_class IS NULL) AS llm_calls, \
+                COUNT(*) FILTER (WHERE error_class IS NOT NULL) AS failed_llm_calls, \
+                SUM(input_tokens)::int8 AS input_tokens, \
+                SUM(output_tokens)::int8 AS output_tokens, \
+                SUM(cost_usd)::float8 AS cost_usd \
+         FROM llm_calls WHERE created_at >= $1 AND created_at < $2 \
+         GROUP BY provider, model \
+         ORDER BY cost_usd DESC",
+    )
+    .bind(from)
+    .bind(to)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
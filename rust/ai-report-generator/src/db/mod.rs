@@ -1,6 +1,18 @@
+#[allow(dead_code)]
+pub mod admin;
 pub mod data_points;
+pub mod demo;
+pub mod fx_rates;
+pub mod indicator_groups;
+pub mod indicator_summaries;
 pub mod indicators;
+pub mod llm_calls;
+pub mod partitions;
 pub mod pool;
+mod query;
+pub mod report_events;
+pub mod report_explanations;
 pub mod reports;
 
 pub use pool::create_pool;
+pub use query::Traced;
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate, Utc};
+use sqlx::PgPool;
+
+use crate::db::Traced;
+
+/// Years created ahead of the current one, so observations dated next year
+/// get their partition before they arrive.
+pub const YEARS_AHEAD: i32 = 1;
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Lock waits past this give up on a partition until the next run, rather
+/// than holding up the report queries queued behind the DDL.
+const LOCK_TIMEOUT: &str = "5s";
+
+/// The partition holding `year`'s observations, matching db/schema.sql.
+pub fn partition_name(year: i32) -> String {
+    format!("data_points_p{year}")
+}
+
+/// `[from, to)` bounds of `year`'s partition.
+pub fn year_bounds(year: i32) -> (NaiveDate, NaiveDate) {
+    let first = |year| NaiveDate::from_ymd_opt(year, 1, 1).expect("January 1st exists");
+    (first(year), first(year + 1))
+}
+
+#[tracing::instrument(name = "db.data_points.partition_exists", skip(pool))]
+async fn partition_exists(pool: &PgPool, name: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+        .bind(name)
+        .traced(pool)
+        .fetch_one(pool)
+        .await
+}
+
+/// Every year with an observation in `data_points_default`, which catches
+/// whatever no year partition covered when it was inserted.
+#[tracing::instrument(name = "db.data_points.stranded_years", skip(pool))]
+async fn stranded_years(pool: &PgPool) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT extract(year FROM observation_date)::int \
+         FROM data_points_default ORDER BY 1",
+    )
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
+
+/// Creates `year`'s partition and moves its rows out of
+/// `data_points_default` into it, in one transaction. A new partition can't
+/// be attached while the default holds rows in its range, so it starts as a
+/// plain table and is attached once they have moved. Returns how many moved.
+#[tracing::instrument(name = "db.data_points.attach_partition", skip(pool))]
+async fn attach_partition(pool: &PgPool, year: i32) -> Result<u64, sqlx::Error> {
+    let name = partition_name(year);
+    let (from, to) = year_bounds(year);
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT set_config('lock_timeout', $1, true)")
+        .bind(LOCK_TIMEOUT)
+        .traced(pool)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&format!(
+        "CREATE TABLE {name} (LIKE data_points INCLUDING DEFAULTS INCLUDING CONSTRAINTS)"
+    ))
+    .traced(pool)
+    .execute(&mut *tx)
+    .await?;
+    let moved = sqlx::query(&format!(
+        "WITH moved AS ( \
+             DELETE FROM data_points_default \
+             WHERE observation_date >= $1 AND observation_date < $2 \
+             RETURNING * \
+         ) \
+         INSERT INTO {name} SELECT * FROM moved"
+    ))
+    .bind(from)
+    .bind(to)
+    .traced(pool)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    sqlx::query(&format!(
+        "ALTER TABLE data_points ATTACH PARTITION {name} FOR VALUES FROM ('{from}') TO ('{to}')"
+    ))
+    .traced(pool)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(moved)
+}
+
+/// Creates the partitions for this year through `YEARS_AHEAD`, and for any
+/// year stranded in `data_points_default`. Returns how many it created.
+#[tracing::instrument(
+    name = "data_points.partitions.maintain",
+    skip(pool),
+    fields(created, moved)
+)]
+pub async fn maintain_partitions(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let current = Utc::now().year();
+    let mut years: Vec<i32> = (current..=current + YEARS_AHEAD).collect();
+    years.extend(stranded_years(pool).await?);
+    years.sort_unstable();
+    years.dedup();
+
+    let (mut created, mut moved) = (0, 0);
+    for year in years {
+        if partition_exists(pool, &partition_name(year)).await? {
+            continue;
+        }
+        moved += attach_partition(pool, year).await?;
+        created += 1;
+    }
+
+    let span = tracing::Span::current();
+    span.record("created", created);
+    span.record("moved", moved);
+
+    Ok(created)
+}
+
+/// Runs `maintain_partitions` at startup and then once an hour.
+pub fn spawn_maintenance(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match maintain_partitions(&pool).await {
+                Ok(0) => {}
+                Ok(created) => tracing::info!(created, "Created data_points partitions"),
+                Err(err) => {
+                    tracing::warn!(error = %err, "Failed to maintain data_points partitions")
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_name() {
+        assert_eq!(partition_name(2024), "data_points_p2024");
+    }
+
+    #[test]
+    fn test_year_bounds() {
+        let (from, to) = year_bounds(2024);
+        assert_eq!(from, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        assert_eq!(to, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert_eq!(from.to_string(), "2024-01-01");
+    }
+}
@@ -0,0 +1,271 @@
+use sqlx::{Execute, PgPool, Postgres};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Adds the OTel database semantic-convention attributes for a query to the
+/// current span, which in the `db` modules is the `db.<table>.<operation>`
+/// span from `#[instrument]`:
+///
+/// ```ignore
+/// sqlx::query("DELETE FROM llm_calls WHERE created_at < $1")
+///     .bind(cutoff)
+///     .traced(pool)
+///     .execute(pool)
+///     .await?;
+/// ```
+pub trait Traced: Sized {
+    fn traced(self, pool: &PgPool) -> Self;
+}
+
+impl<'q, Q: Execute<'q, Postgres>> Traced for Q {
+    fn traced(self, pool: &PgPool) -> Self {
+        record(pool, self.sql());
+        self
+    }
+}
+
+fn record(pool: &PgPool, sql: &str) {
+    let summary = QuerySummary::parse(sql);
+    let options = pool.connect_options();
+    let span = tracing::Span::current();
+    span.set_attribute("db.system", "postgresql");
+    if let Some(operation) = summary.operation {
+        span.set_attribute("db.operation.name", operation);
+    }
+    if let Some(collection) = summary.collection {
+        span.set_attribute("db.collection.name", collection);
+    }
+    span.set_attribute("db.query.text", summary.text);
+    span.set_attribute("server.address", options.get_host().to_string());
+    span.set_attribute("server.port", i64::from(options.get_port()));
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QuerySummary {
+    /// `SELECT`, `INSERT`, ...; for `WITH` queries, the main statement's.
+    pub operation: Option<String>,
+    /// The first table the query names, as semconv recommends for parsed
+    /// query text.
+    pub collection: Option<String>,
+    /// Whitespace collapsed and literals replaced with `?`; bind parameters
+    /// (`$1`) are kept.
+    pub text: String,
+}
+
+impl QuerySummary {
+    pub fn parse(sql: &str) -> Self {
+        let tokens = tokenize(sql);
+        Self {
+            operation: operation(&tokens),
+            collection: collection(&tokens),
+            text: sanitize(sql),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Symbol(String),
+    Literal,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self, Token::Symbol(s) if s == symbol)
+    }
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' {
+            // '' is an escaped quote inside the literal.
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' && chars.get(i + 1) == Some(&'\'') {
+                    i += 2;
+                } else if chars[i] == '\'' {
+                    i += 1;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+            tokens.push(Token::Literal);
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Literal);
+        } else if c.is_alphanumeric() || c == '_' || c == '$' || c == '"' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '"'))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c == ':' && chars.get(i + 1) == Some(&':') {
+            tokens.push(Token::Symbol("::".to_string()));
+            i += 2;
+        } else {
+            tokens.push(Token::Symbol(c.to_string()));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+fn sanitize(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut prev: Option<char> = None;
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if !out.is_empty() && chars.peek().is_some() {
+                out.push(' ');
+            }
+            prev = Some(' ');
+            continue;
+        }
+        if c == '\'' {
+            while let Some(c) = chars.next() {
+                if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                    break;
+                }
+            }
+            out.push('?');
+        } else if c.is_ascii_digit()
+            && !prev.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == '$')
+        {
+            while chars.next_if(|c| c.is_ascii_digit() || *c == '.').is_some() {}
+            out.push('?');
+        } else {
+            out.push(c);
+        }
+        prev = out.chars().last();
+    }
+    out
+}
+
+const OPERATIONS: &[&str] = &["SELECT", "INSERT", "UPDATE", "DELETE"];
+
+fn operation(tokens: &[Token]) -> Option<String> {
+    let Token::Word(first) = tokens.first()? else {
+        return None;
+    };
+    if !first.eq_ignore_ascii_case("WITH") {
+        return Some(first.to_uppercase());
+    }
+    let mut depth = 0i32;
+    for token in &tokens[1..] {
+        match token {
+            Token::Symbol(s) if s == "(" => depth += 1,
+            Token::Symbol(s) if s == ")" => depth -= 1,
+            Token::Word(w)
+                if depth == 0 && OPERATIONS.iter().any(|op| w.eq_ignore_ascii_case(op)) =>
+            {
+                return Some(w.to_uppercase());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn collection(tokens: &[Token]) -> Option<String> {
+    // Names defined by `WITH name AS (...)` are not tables.
+    let ctes: Vec<&str> = tokens
+        .windows(3)
+        .filter(|w| w[1].is_keyword("AS") && w[2].is_symbol("("))
+        .filter_map(|w| match &w[0] {
+            Token::Word(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    tokens.windows(3).find_map(|w| {
+        let introduces_table = ["FROM", "INTO", "UPDATE", "JOIN"]
+            .iter()
+            .any(|k| w[0].is_keyword(k));
+        // `FROM unnest(...)` is a function, not a table; `INTO t (...)` is.
+        let function = w[2].is_symbol("(") && !w[0].is_keyword("INTO");
+        match &w[1] {
+            Token::Word(name)
+                if introduces_table
+                    && !function
+                    && !ctes.contains(&name.as_str())
+                    && !OPERATIONS.iter().any(|op| name.eq_ignore_ascii_case(op)) =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_repository_queries() {
+        let summary = QuerySummary::parse(
+            r#"
+            SELECT a.id, u.name as author_name
+            FROM articles a
+            JOIN users u ON a.author_id = u.id
+            WHERE a.slug = $1
+            "#,
+        );
+        assert_eq!(summary.operation.as_deref(), Some("SELECT"));
+        assert_eq!(summary.collection.as_deref(), Some("articles"));
+        assert_eq!(
+            summary.text,
+            "SELECT a.id, u.name as author_name FROM articles a \
+             JOIN users u ON a.author_id = u.id WHERE a.slug = $1"
+        );
+
+        let exists = QuerySummary::parse("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)");
+        assert_eq!(exists.collection.as_deref(), Some("users"));
+
+        let insert =
+            QuerySummary::parse("insert into favorites (user_id, article_id) values ($1, $2)");
+        assert_eq!(insert.operation.as_deref(), Some("INSERT"));
+        assert_eq!(insert.collection.as_deref(), Some("favorites"));
+    }
+
+    #[test]
+    fn test_literals_are_sanitized() {
+        let summary = QuerySummary::parse(
+            "UPDATE jobs SET status = 'failed', attempts = attempts + 1 \
+             WHERE name = 'it''s' AND created_at < NOW() - INTERVAL '7 days'",
+        );
+        assert_eq!(
+            summary.text,
+            "UPDATE jobs SET status = ?, attempts = attempts + ? \
+             WHERE name = ? AND created_at < NOW() - INTERVAL ?"
+        );
+        assert_eq!(summary.collection.as_deref(), Some("jobs"));
+    }
+
+    #[test]
+    fn test_with_queries_use_the_main_statement() {
+        let summary = QuerySummary::parse(
+            "WITH next AS (SELECT id FROM jobs WHERE status = $1 LIMIT 1) \
+             UPDATE jobs SET status = $2 FROM next WHERE jobs.id = next.id",
+        );
+        assert_eq!(summary.operation.as_deref(), Some("UPDATE"));
+        assert_eq!(summary.collection.as_deref(), Some("jobs"));
+    }
+}
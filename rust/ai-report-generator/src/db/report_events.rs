@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::Traced;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReportEventRow {
+    pub stage: String,
+    pub status: String,
+    pub duration_ms: Option<i32>,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct InsertReportEvent<'a> {
+    pub report_id: Uuid,
+    pub stage: &'a str,
+    pub status: &'a str,
+    pub duration_ms: Option<i32>,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub error: Option<&'a str>,
+}
+
+#[tracing::instrument(
+    name = "db.report_events.insert",
+    skip_all,
+    fields(pipeline.stage = event.stage)
+)]
+pub async fn insert_report_event(
+    pool: &PgPool,
+    event: &InsertReportEvent<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO report_events \
+         (report_id, stage, status, duration_ms, input_tokens, output_tokens, error) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(event.report_id)
+    .bind(event.stage)
+    .bind(event.status)
+    .bind(event.duration_ms)
+    .bind(event.input_tokens)
+    .bind(event.output_tokens)
+    .bind(event.error)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The report's events, oldest first.
+#[tracing::instrument(name = "db.report_events.list", skip(pool))]
+pub async fn list_report_events(
+    pool: &PgPool,
+    report_id: Uuid,
+) -> Result<Vec<ReportEventRow>, sqlx::Error> {
+    sqlx::query_as::<_, ReportEventRow>(
+        "SELECT stage, status, duration_ms, input_tokens, output_tokens, error, created_at \
+         FROM report_events WHERE report_id = $1 ORDER BY id",
+    )
+    .bind(report_id)
+    .traced(pool)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::Traced;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReportExplanationRow {
+    pub report_id: Uuid,
+    /// The pipeline's decisions, in order.
+    pub explanation: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Replaces any explanation the report already has.
+#[tracing::instrument(name = "db.report_explanations.upsert", skip(pool, explanation))]
+pub async fn upsert_report_explanation(
+    pool: &PgPool,
+    report_id: Uuid,
+    explanation: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO report_explanations (report_id, explanation) VALUES ($1, $2) \
+         ON CONFLICT (report_id) DO UPDATE \
+         SET explanation = EXCLUDED.explanation, created_at = NOW()",
+    )
+    .bind(report_id)
+    .bind(explanation)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "db.report_explanations.get", skip(pool))]
+pub async fn get_report_explanation(
+    pool: &PgPool,
+    report_id: Uuid,
+) -> Result<Option<ReportExplanationRow>, sqlx::Error> {
+    sqlx::query_as::<_, ReportExplanationRow>(
+        "SELECT report_id, explanation, created_at FROM report_explanations \
+         WHERE report_id = $1",
+    )
+    .bind(report_id)
+    .traced(pool)
+    .fetch_optional(pool)
+    .await
+}
@@ -3,6 +3,8 @@ use serde::Serialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::db::Traced;
+
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct ReportRow {
     pub id: Uuid,
@@ -19,6 +21,10 @@ pub struct ReportRow {
     pub generation_duration_ms: Option<i32>,
     pub trace_id: Option<String>,
     pub status: String,
+    pub guardrail_violations: serde_json::Value,
+    pub language: String,
+    /// The template from `GET /api/report-types` that produced the report.
+    pub report_type: String,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -36,17 +42,33 @@ pub struct InsertReport<'a> {
     pub providers_used: &'a [String],
     pub generation_duration_ms: i32,
     pub trace_id: Option<&'a str>,
+    pub status: &'a str,
+    pub guardrail_violations: &'a serde_json::Value,
+    pub language: &'a str,
+    pub report_type: &'a str,
 }
 
+/// Inserts the report, or fills in the placeholder row left by
+/// `insert_pending_report` unless it has been cancelled meanwhile.
 #[tracing::instrument(name = "db.reports.insert", skip_all)]
 pub async fn insert_report(pool: &PgPool, params: &InsertReport<'_>) -> Result<Uuid, sqlx::Error> {
-    let row: (Uuid,) = sqlx::query_as(
+    sqlx::query(
         "INSERT INTO reports \
          (id, title, executive_summary, sections, indicators_used, \
           time_range_start, time_range_end, total_data_points, total_tokens, \
-          total_cost_usd, providers_used, generation_duration_ms, trace_id) \
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) \
-         RETURNING id",
+          total_cost_usd, providers_used, generation_duration_ms, trace_id, status, \
+          guardrail_violations, language, report_type) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) \
+         ON CONFLICT (id) DO UPDATE SET \
+          title = EXCLUDED.title, executive_summary = EXCLUDED.executive_summary, \
+          sections = EXCLUDED.sections, total_data_points = EXCLUDED.total_data_points, \
+          total_tokens = EXCLUDED.total_tokens, total_cost_usd = EXCLUDED.total_cost_usd, \
+          providers_used = EXCLUDED.providers_used, \
+          generation_duration_ms = EXCLUDED.generation_duration_ms, \
+          trace_id = EXCLUDED.trace_id, status = EXCLUDED.status, \
+          guardrail_violations = EXCLUDED.guardrail_violations, language = EXCLUDED.language, \
+          report_type = EXCLUDED.report_type \
+         WHERE reports.status <> 'cancelled'",
     )
     .bind(params.id)
     .bind(params.title)
@@ -61,10 +83,232 @@ pub async fn insert_report(pool: &PgPool, params: &InsertReport<'_>) -> Result<U
     .bind(params.providers_used)
     .bind(params.generation_duration_ms)
     .bind(params.trace_id)
-    .fetch_one(pool)
+    .bind(params.status)
+    .bind(params.guardrail_violations)
+    .bind(params.language)
+    .bind(params.report_type)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(params.id)
+}
+
+pub struct InsertPendingReport<'a> {
+    pub id: Uuid,
+    pub indicators: &'a [String],
+    pub time_range_start: NaiveDate,
+    pub time_range_end: NaiveDate,
+    pub language: &'a str,
+    pub report_type: &'a str,
+    pub fingerprint: &'a str,
+    pub request: &'a serde_json::Value,
+}
+
+/// Placeholder for a report whose pipeline runs in the background. Storing
+/// the report later keeps its `fingerprint` and `request`.
+#[tracing::instrument(name = "db.reports.insert_pending", skip_all, fields(report.id = %params.id))]
+pub async fn insert_pending_report(
+    pool: &PgPool,
+    params: &InsertPendingReport<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO reports \
+         (id, title, executive_summary, indicators_used, time_range_start, time_range_end, \
+          status, language, report_type, fingerprint, request, last_heartbeat_at) \
+         VALUES ($1, 'Pending report', '', $2, $3, $4, 'pending', $5, $6, $7, $8, NOW())",
+    )
+    .bind(params.id)
+    .bind(params.indicators)
+    .bind(params.time_range_start)
+    .bind(params.time_range_end)
+    .bind(params.language)
+    .bind(params.report_type)
+    .bind(params.fingerprint)
+    .bind(params.request)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Leaves a cancelled report cancelled.
+#[tracing::instrument(name = "db.reports.set_status", skip(pool))]
+pub async fn set_report_status(pool: &PgPool, id: Uuid, status: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE reports SET status = $2 WHERE id = $1 AND status <> 'cancelled'")
+        .bind(id)
+        .bind(status)
+        .traced(pool)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Marks a `pending` report `cancelled`, so a pipeline still running it
+/// elsewhere doesn't store it. Returns whether it was pending.
+#[tracing::instrument(name = "db.reports.cancel", skip(pool))]
+pub async fn cancel_report(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("UPDATE reports SET status = 'cancelled' WHERE id = $1 AND status = 'pending'")
+            .bind(id)
+            .traced(pool)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records what a report had cost when its pipeline was cancelled, and the
+/// trace its LLM calls were made in, marking it `cancelled` if it wasn't
+/// already.
+#[tracing::instrument(name = "db.reports.finish_cancelled", skip(pool))]
+pub async fn finish_cancelled_report(
+    pool: &PgPool,
+    id: Uuid,
+    total_cost_usd: f64,
+    trace_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE reports SET status = 'cancelled', title = 'Cancelled report', \
+          total_cost_usd = $2, trace_id = COALESCE($3, trace_id) \
+         WHERE id = $1 AND status IN ('pending', 'cancelled')",
+    )
+    .bind(id)
+    .bind(total_cost_usd)
+    .bind(trace_id)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Tells the janitor the pending report's pipeline is still running.
+#[tracing::instrument(name = "db.reports.heartbeat", skip(pool))]
+pub async fn heartbeat_report(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE reports SET last_heartbeat_at = NOW() WHERE id = $1 AND status = 'pending'",
+    )
+    .bind(id)
+    .traced(pool)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "db.reports.set_checkpoint", skip(pool, checkpoint))]
+pub async fn set_report_checkpoint(
+    pool: &PgPool,
+    id: Uuid,
+    checkpoint: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE reports SET checkpoint = $2 WHERE id = $1 AND status = 'pending'")
+        .bind(id)
+        .bind(checkpoint)
+        .traced(pool)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct StalledReport {
+    pub id: Uuid,
+    pub report_type: String,
+}
+
+/// Fails the pending reports without a heartbeat in the last
+/// `stall_secs`, keeping their checkpoint to resume from. Rows from before
+/// heartbeats count from when they were created.
+#[tracing::instrument(name = "db.reports.fail_stalled", skip(pool), fields(stalled_count))]
+pub async fn fail_stalled_reports(
+    pool: &PgPool,
+    stall_secs: i64,
+) -> Result<Vec<StalledReport>, sqlx::Error> {
+    let stalled = sqlx::query_as::<_, StalledReport>(
+        "UPDATE reports SET status = 'failed' \
+         WHERE status = 'pending' \
+           AND COALESCE(last_heartbeat_at, created_at) < NOW() - $1 * INTERVAL '1 second' \
+         RETURNING id, report_type",
+    )
+    .bind(stall_secs)
+    .traced(pool)
+    .fetch_all(pool)
     .await?;
 
-    Ok(row.0)
+    tracing::Span::current().record("stalled_count", stalled.len());
+
+    Ok(stalled)
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ResumedReport {
+    pub request: serde_json::Value,
+    pub checkpoint: Option<serde_json::Value>,
+}
+
+/// Puts a failed report with a stored request back to `pending` with a
+/// fresh heartbeat, for its pipeline to be run again. `None` if it isn't
+/// one, or another request resumed it first.
+#[tracing::instrument(name = "db.reports.resume", skip(pool))]
+pub async fn resume_report(pool: &PgPool, id: Uuid) -> Result<Option<ResumedReport>, sqlx::Error> {
+    sqlx::query_as::<_, ResumedReport>(
+        "UPDATE reports SET status = 'pending', last_heartbeat_at = NOW() \
+         WHERE id = $1 AND status = 'failed' AND request IS NOT NULL \
+         RETURNING request, checkpoint",
+    )
+    .bind(id)
+    .traced(pool)
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(name = "db.reports.set_origin", skip(pool, request))]
+pub async fn set_report_origin(
+    pool: &PgPool,
+    id: Uuid,
+    fingerprint: &str,
+    request: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE reports SET fingerprint = $2, request = $3 WHERE id = $1")
+        .bind(id)
+        .bind(fingerprint)
+        .bind(request)
+        .traced(pool)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// The newest completed report with `fingerprint` created in the last
+/// `max_age_secs`.
+#[tracing::instrument(name = "db.reports.find_fresh", skip(pool))]
+pub async fn find_fresh_report(
+    pool: &PgPool,
+    fingerprint: &str,
+    max_age_secs: i64,
+) -> Result<Option<ReportRow>, sqlx::Error> {
+    sqlx::query_as::<_, ReportRow>(
+        "SELECT id, title, executive_summary, sections, indicators_used, \
+         time_range_start, time_range_end, total_data_points, total_tokens, \
+         total_cost_usd::float8 as total_cost_usd, providers_used, \
+         generation_duration_ms, trace_id, status, guardrail_violations, language, report_type, \
+         created_at \
+         FROM reports \
+         WHERE fingerprint = $1 AND status = 'completed' \
+           AND created_at > NOW() - $2 * INTERVAL '1 second' \
+         ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(fingerprint)
+    .bind(max_age_secs)
+    .traced(pool)
+    .fetch_optional(pool)
+    .await
 }
 
 #[tracing::instrument(name = "db.reports.get", skip(pool))]
@@ -73,10 +317,12 @@ pub async fn get_report(pool: &PgPool, id: Uuid) -> Result<Option<ReportRow>, sq
         "SELECT id, title, executive_summary, sections, indicators_used, \
          time_range_start, time_range_end, total_data_points, total_tokens, \
          total_cost_usd::float8 as total_cost_usd, providers_used, \
-         generation_duration_ms, trace_id, status, created_at \
+         generation_duration_ms, trace_id, status, guardrail_violations, language, report_type, \
+         created_at \
          FROM reports WHERE id = $1",
     )
     .bind(id)
+    .traced(pool)
     .fetch_optional(pool)
     .await
 }
@@ -86,16 +332,24 @@ pub async fn list_reports(
     pool: &PgPool,
     limit: i64,
     offset: i64,
+    language: Option<&str>,
+    status: Option<&str>,
 ) -> Result<Vec<ReportRow>, sqlx::Error> {
     sqlx::query_as::<_, ReportRow>(
         "SELECT id, title, executive_summary, sections, indicators_used, \
          time_range_start, time_range_end, total_data_points, total_tokens, \
          total_cost_usd::float8 as total_cost_usd, providers_used, \
-         generation_duration_ms, trace_id, status, created_at \
-         FROM reports ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+         generation_duration_ms, trace_id, status, guardrail_violations, language, report_type, \
+         created_at \
+         FROM reports WHERE ($3::text IS NULL OR language = $3) \
+           AND ($4::text IS NULL OR status = $4) \
+         ORDER BY created_at DESC LIMIT $1 OFFSET $2",
     )
     .bind(limit)
     .bind(offset)
+    .bind(language)
+    .bind(status)
+    .traced(pool)
     .fetch_all(pool)
     .await
 }
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::time::Instant;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::error::AppError;
+
+/// The caller's remaining budget in grpc-timeout form (`2S`, `1500m`), as
+/// sent by rust-axum-postgres.
+pub const REQUEST_DEADLINE_HEADER: &str = "x-request-deadline";
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+/// When the caller stops waiting. [`Deadline::current`] finds it anywhere
+/// in the request's task, which is how pipeline stages bound their LLM
+/// calls by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|deadline| *deadline).ok()
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Runs `work` with this as the current deadline, dropping it with
+    /// [`AppError::DeadlineExceeded`] once the deadline passes.
+    pub async fn run<T>(
+        self,
+        work: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        CURRENT
+            .scope(self, tokio::time::timeout_at(self.0, work))
+            .await
+            .map_err(|_| AppError::DeadlineExceeded)?
+    }
+}
+
+/// A grpc-timeout value: up to 8 digits and a unit of `H`, `M`, `S`, `m`
+/// (milliseconds), `u` or `n`.
+pub fn parse_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(n * 3600),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    })
+}
+
+/// Runs the request under [`REQUEST_DEADLINE_HEADER`] when it is present,
+/// answering 504 once it passes. Batch reports carry on in their own task:
+/// the caller is only waiting for the 202.
+pub async fn propagate_deadline(request: Request, next: Next) -> Response {
+    let Some(value) = request.headers().get(REQUEST_DEADLINE_HEADER) else {
+        return next.run(request).await;
+    };
+    let Some(budget) = value.to_str().ok().and_then(parse_timeout) else {
+        return AppError::Validation(format!(
+            "{REQUEST_DEADLINE_HEADER} must be a grpc-timeout value such as 2S or 1500m"
+        ))
+        .into_response();
+    };
+    tracing::Span::current().set_attribute("http.request.deadline_ms", budget.as_millis() as i64);
+    if budget.is_zero() {
+        return AppError::DeadlineExceeded.into_response();
+    }
+
+    Deadline::after(budget)
+        .run(async move { Ok(next.run(request).await) })
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_timeout("1500m"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_timeout("3M"), Some(Duration::from_secs(180)));
+
+        for invalid in ["", "m", "15", "2s", "+2S", "123456789m", "2é"] {
+            assert_eq!(parse_timeout(invalid), None, "{invalid:?}");
+        }
+    }
+}
@@ -1,14 +1,17 @@
 use axum::{
     Json,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use opentelemetry::trace::TraceContextExt;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::Status;
 use serde_json::json;
 use thiserror::Error;
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::telemetry::{current_request_id, current_trace_id};
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Validation error: {0}")]
@@ -17,30 +20,66 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Authentication required")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
     #[error("LLM error: {0}")]
     Llm(String),
 
+    #[error("Too many report requests, retry later")]
+    Overloaded { retry_after_secs: u64 },
+
+    #[error("Report rejected by guardrails: {0}")]
+    GuardrailRejected(String),
+
     #[error("Pipeline error: {0}")]
     Pipeline(String),
 
+    #[error("{stage} stage timed out after {timeout_secs}s")]
+    StageTimeout {
+        stage: &'static str,
+        timeout_secs: u64,
+    },
+
+    #[error("Request deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("Report generation was cancelled")]
+    Cancelled,
+
     #[error("Internal error: {0}")]
     #[allow(dead_code)]
     Internal(String),
 }
 
-fn get_trace_id() -> Option<String> {
-    let span = Span::current();
-    let context = span.context();
-    let span_ref = context.span();
-    let span_context = span_ref.span_context();
-
-    if span_context.is_valid() {
-        Some(span_context.trace_id().to_string())
-    } else {
-        None
+impl AppError {
+    /// The variant name, reported as `exception.type`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "Validation",
+            AppError::NotFound(_) => "NotFound",
+            AppError::Unauthorized => "Unauthorized",
+            AppError::Forbidden => "Forbidden",
+            AppError::Conflict(_) => "Conflict",
+            AppError::Database(_) => "Database",
+            AppError::Llm(_) => "Llm",
+            AppError::Overloaded { .. } => "Overloaded",
+            AppError::GuardrailRejected(_) => "GuardrailRejected",
+            AppError::Pipeline(_) => "Pipeline",
+            AppError::StageTimeout { .. } => "StageTimeout",
+            AppError::DeadlineExceeded => "DeadlineExceeded",
+            AppError::Cancelled => "Cancelled",
+            AppError::Internal(_) => "Internal",
+        }
     }
 }
 
@@ -49,6 +88,9 @@ impl IntoResponse for AppError {
         let (status, error_message) = match &self {
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             AppError::Database(e) => {
                 tracing::error!(error = %e, "Database error");
                 (
@@ -63,6 +105,8 @@ impl IntoResponse for AppError {
                     "Internal server error".to_string(),
                 )
             }
+            AppError::Overloaded { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::GuardrailRejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
             AppError::Pipeline(msg) => {
                 tracing::error!(error = %msg, "Pipeline error");
                 (
@@ -70,6 +114,12 @@ impl IntoResponse for AppError {
                     "Internal server error".to_string(),
                 )
             }
+            AppError::StageTimeout { .. } => {
+                tracing::error!(error = %self, "Pipeline stage timed out");
+                (StatusCode::GATEWAY_TIMEOUT, self.to_string())
+            }
+            AppError::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
+            AppError::Cancelled => (StatusCode::CONFLICT, self.to_string()),
             AppError::Internal(msg) => {
                 tracing::error!(error = %msg, "Internal error");
                 (
@@ -79,30 +129,66 @@ impl IntoResponse for AppError {
             }
         };
 
-        let body = if let Some(trace_id) = get_trace_id() {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-                "trace_id": trace_id,
-            })
-        } else {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-            })
-        };
+        let mut body = json!({
+            "error": error_message,
+            "status": status.as_u16(),
+        });
+        if let Some(trace_id) = current_trace_id() {
+            body["trace_id"] = trace_id.into();
+        }
+        if let Some(request_id) = current_request_id() {
+            body["request_id"] = request_id.into();
+        }
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let AppError::Overloaded { retry_after_secs } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
     }
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
+/// Marks the current span as failed when the result is an error:
+/// `otel.status_code=ERROR` plus an `exception` event with the error's type
+/// and message. Services call it on the result of each instrumented method.
+pub trait RecordErr {
+    fn record_err(self) -> Self;
+}
+
+impl<T> RecordErr for AppResult<T> {
+    fn record_err(self) -> Self {
+        if let Err(err) = &self {
+            let span = Span::current();
+            let message = err.to_string();
+            span.set_status(Status::error(message.clone()));
+            span.add_event(
+                "exception",
+                vec![
+                    KeyValue::new("exception.type", err.kind()),
+                    KeyValue::new("exception.message", message),
+                ],
+            );
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum::http::StatusCode;
 
+    #[test]
+    fn test_record_err_passes_result_through() {
+        let error: AppResult<()> = Err(AppError::Llm("provider timeout".to_string())).record_err();
+        assert_eq!(error.unwrap_err().kind(), "Llm");
+        assert_eq!(Ok::<_, AppError>(1).record_err().unwrap(), 1);
+    }
+
     #[test]
     fn test_validation_error() {
         let error = AppError::Validation("field is required".to_string());
@@ -115,6 +201,12 @@ mod tests {
         assert_eq!(error.to_string(), "Not found: Report");
     }
 
+    #[test]
+    fn test_unauthorized_error() {
+        let error = AppError::Unauthorized;
+        assert_eq!(error.to_string(), "Authentication required");
+    }
+
     #[test]
     fn test_llm_error() {
         let error = AppError::Llm("provider timeout".to_string());
@@ -127,6 +219,16 @@ mod tests {
         assert_eq!(error.to_string(), "Pipeline error: stage failed");
     }
 
+    #[test]
+    fn test_stage_timeout_error() {
+        let error = AppError::StageTimeout {
+            stage: "generate",
+            timeout_secs: 180,
+        };
+        assert_eq!(error.to_string(), "generate stage timed out after 180s");
+        assert_eq!(error.kind(), "StageTimeout");
+    }
+
     #[test]
     fn test_internal_error() {
         let error = AppError::Internal("unexpected".to_string());
@@ -144,14 +246,36 @@ mod tests {
                 AppError::NotFound("test".to_string()),
                 StatusCode::NOT_FOUND,
             ),
+            (AppError::Unauthorized, StatusCode::UNAUTHORIZED),
+            (AppError::Forbidden, StatusCode::FORBIDDEN),
+            (AppError::Conflict("test".to_string()), StatusCode::CONFLICT),
+            (AppError::Cancelled, StatusCode::CONFLICT),
             (
                 AppError::Llm("test".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            (
+                AppError::Overloaded {
+                    retry_after_secs: 5,
+                },
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                AppError::GuardrailRejected("prompt_leak".to_string()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
             (
                 AppError::Pipeline("test".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            (
+                AppError::StageTimeout {
+                    stage: "analyze",
+                    timeout_secs: 90,
+                },
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (AppError::DeadlineExceeded, StatusCode::GATEWAY_TIMEOUT),
             (
                 AppError::Internal("test".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -162,6 +286,9 @@ mod tests {
             let (status, _) = match &error {
                 AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
                 AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+                AppError::Unauthorized => (StatusCode::UNAUTHORIZED, error.to_string()),
+                AppError::Forbidden => (StatusCode::FORBIDDEN, error.to_string()),
+                AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
                 AppError::Database(_) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
@@ -170,10 +297,17 @@ mod tests {
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
                 ),
+                AppError::Overloaded { .. } => (StatusCode::TOO_MANY_REQUESTS, error.to_string()),
+                AppError::GuardrailRejected(_) => {
+                    (StatusCode::UNPROCESSABLE_ENTITY, error.to_string())
+                }
                 AppError::Pipeline(_) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
                 ),
+                AppError::StageTimeout { .. } => (StatusCode::GATEWAY_TIMEOUT, error.to_string()),
+                AppError::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, error.to_string()),
+                AppError::Cancelled => (StatusCode::CONFLICT, error.to_string()),
                 AppError::Internal(_) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
@@ -183,6 +317,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_overloaded_sets_retry_after() {
+        let response = AppError::Overloaded {
+            retry_after_secs: 30,
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+    }
+
     #[test]
     fn test_app_result_ok() {
         fn returns_ok() -> AppResult<i32> {
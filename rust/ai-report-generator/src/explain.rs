@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+tokio::task_local! {
+    static CURRENT: Arc<Explainer>;
+}
+
+/// Why a report came out the way it did, for reports requested with
+/// `explain`. Stored as `report_explanations.explanation`, tagged by
+/// `decision`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Decision {
+    /// The report type the generate prompt was written from, and a hash of
+    /// its prompt, sections and length.
+    Template { name: String, version: String },
+    /// Resumed from a checkpoint instead of run again.
+    Resumed { stage: String },
+    /// The LLM stages saw minimized data; `aggregated` indicators were
+    /// averaged per month.
+    DataMinimized {
+        mode: String,
+        aggregated: Vec<String>,
+    },
+    /// Requested indicators with no observations in the date range.
+    IndicatorsSkipped { codes: Vec<String> },
+    /// Data left out of the prompt to fit the model's context.
+    PromptTruncated {
+        stage: String,
+        budget_tokens: usize,
+        estimated_tokens: usize,
+        dropped_blocks: usize,
+    },
+    /// A failed call that moved on to the provider's next endpoint.
+    EndpointFailover {
+        stage: String,
+        provider: String,
+        from_endpoint: String,
+        to_endpoint: String,
+        error: String,
+    },
+    /// A failed attempt that was sent again.
+    Retry {
+        stage: String,
+        provider: String,
+        model: String,
+        attempt: u32,
+        error: String,
+    },
+    /// The primary provider failed every retry.
+    Fallback {
+        stage: String,
+        from_provider: String,
+        to_provider: String,
+        model: String,
+        error: String,
+    },
+    /// The call whose response the stage used.
+    LlmResponse {
+        stage: String,
+        provider: String,
+        endpoint: String,
+        model: String,
+    },
+    /// Every provider failed, so the stage's output is statistics.
+    Degraded { stage: String, error: String },
+    /// A section chart keeps fewer points than the series has.
+    ChartDownsampled {
+        indicator: String,
+        points: usize,
+        kept: usize,
+    },
+}
+
+/// Collects [`Decision`]s made anywhere in [`Explainer::scope`], the way
+/// [`crate::llm::Spend`] collects costs.
+#[derive(Debug, Default)]
+pub struct Explainer {
+    decisions: Mutex<Vec<Decision>>,
+}
+
+impl Explainer {
+    pub async fn scope<F: Future>(self: Arc<Self>, work: F) -> F::Output {
+        CURRENT.scope(self, work).await
+    }
+
+    /// Adds to the current task's explanation, if it has one. `decision` is
+    /// only built then.
+    pub fn record(decision: impl FnOnce() -> Decision) {
+        let _ = CURRENT.try_with(|explainer| explainer.decisions.lock().unwrap().push(decision()));
+    }
+
+    /// The decisions recorded so far in the current task's scope, in order;
+    /// `None` outside one.
+    pub fn current() -> Option<Vec<Decision>> {
+        CURRENT
+            .try_with(|explainer| explainer.decisions.lock().unwrap().clone())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resumed() -> Decision {
+        Decision::Resumed {
+            stage: "analyze".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_only_within_scope() {
+        Explainer::record(resumed);
+        assert_eq!(Explainer::current(), None);
+
+        let decisions = Arc::new(Explainer::default())
+            .scope(async {
+                Explainer::record(resumed);
+                Explainer::current()
+            })
+            .await;
+
+        assert_eq!(decisions, Some(vec![resumed()]));
+    }
+
+    #[test]
+    fn test_decisions_are_tagged() {
+        let json = serde_json::to_value(Decision::IndicatorsSkipped {
+            codes: vec!["GDP".to_string()],
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "decision": "indicators_skipped", "codes": ["GDP"] })
+        );
+    }
+}
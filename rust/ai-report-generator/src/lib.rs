@@ -0,0 +1,36 @@
+pub mod config;
+pub mod db;
+pub mod deadline;
+pub mod error;
+pub mod explain;
+pub mod llm;
+pub mod pipeline;
+pub mod reload;
+pub mod routes;
+pub mod startup;
+pub mod synthetic;
+pub mod telemetry;
+pub mod tls;
+
+pub use config::Config;
+
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Config,
+    pub llm_client: Arc<reload::Reloadable<llm::LlmClient>>,
+    pub admission: Arc<pipeline::AdmissionController>,
+    pub guardrails: Arc<pipeline::Guardrails>,
+    pub templates: Arc<pipeline::TemplateRegistry>,
+    pub providers: Arc<reload::Reloadable<llm::ProviderDirectory>>,
+    pub batch: Option<pipeline::BatchPipeline>,
+    pub report_watch: Arc<pipeline::ReportWatch>,
+    pub running_reports: Arc<pipeline::RunningReports>,
+    /// `None` when `COST_ANOMALY_WINDOW_SECS` is 0.
+    pub cost_anomaly: Option<Arc<llm::CostAnomalyDetector>>,
+    pub readiness: Arc<startup::Readiness>,
+}
@@ -0,0 +1,353 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use opentelemetry::KeyValue;
+use serde::Serialize;
+use serde_json::json;
+
+use super::GenerateResponse;
+use super::observer::{CallContext, ProviderObserver};
+use crate::telemetry::metrics::{GEN_AI_COST_ANOMALY, GEN_AI_COST_BASELINE, GEN_AI_COST_RATE};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalySettings {
+    /// How often spend is compared with its baseline; zero turns detection
+    /// off.
+    pub window: Duration,
+    /// Weight of the newest window in the baseline's moving average.
+    pub alpha: f64,
+    /// A window spending more than this multiple of the baseline is
+    /// anomalous.
+    pub threshold: f64,
+    /// Windows averaged before any is judged.
+    pub warmup_windows: u32,
+    /// Windows below these are never anomalous, however small the baseline.
+    pub min_cost_usd: f64,
+    pub min_tokens: u64,
+    /// Receives a JSON POST when an anomaly starts.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            alpha: 0.1,
+            threshold: 3.0,
+            warmup_windows: 10,
+            min_cost_usd: 0.10,
+            min_tokens: 20_000,
+            webhook_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    Cost,
+    Tokens,
+}
+
+impl AnomalyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cost => "cost",
+            Self::Tokens => "tokens",
+        }
+    }
+}
+
+/// A window that spent more than `threshold` times the baseline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub value: f64,
+    pub baseline: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Usage {
+    cost_usd: f64,
+    tokens: u64,
+}
+
+/// One kind of spend's moving average.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Rate {
+    /// Spent in the last window.
+    pub last: f64,
+    /// Exponentially weighted moving average of the normal windows.
+    pub baseline: f64,
+    /// When the current anomaly started; `None` while spend is normal.
+    pub anomalous_since: Option<DateTime<Utc>>,
+}
+
+impl Rate {
+    /// Folds `value` into the baseline unless it is anomalous: a runaway
+    /// loop would otherwise soon become the new normal. Returns the anomaly
+    /// the first window of one.
+    fn observe(
+        &mut self,
+        kind: AnomalyKind,
+        value: f64,
+        floor: f64,
+        judged: bool,
+        settings: &AnomalySettings,
+    ) -> Option<Anomaly> {
+        self.last = value;
+        if judged && value >= floor && value > settings.threshold * self.baseline {
+            let started = self.anomalous_since.is_none();
+            self.anomalous_since.get_or_insert_with(Utc::now);
+            return started.then_some(Anomaly {
+                kind,
+                value,
+                baseline: self.baseline,
+            });
+        }
+        self.anomalous_since = None;
+        self.baseline = settings.alpha * value + (1.0 - settings.alpha) * self.baseline;
+        None
+    }
+}
+
+/// The detector's view of spend, for `GET /api/usage/rate`. Values are per
+/// window.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SpendRates {
+    pub window_secs: u64,
+    pub threshold: f64,
+    /// Windows seen since startup; none is judged until `warmup_windows`.
+    pub windows: u32,
+    pub warmup_windows: u32,
+    pub cost_usd: Rate,
+    pub tokens: Rate,
+}
+
+impl SpendRates {
+    /// Ends a window that spent `usage`.
+    fn observe(&mut self, usage: Usage, settings: &AnomalySettings) -> Vec<Anomaly> {
+        let judged = self.windows >= self.warmup_windows;
+        if self.windows == 0 {
+            // Start from the first window rather than from zero.
+            self.cost_usd.baseline = usage.cost_usd;
+            self.tokens.baseline = usage.tokens as f64;
+        }
+        self.windows += 1;
+        [
+            self.cost_usd.observe(
+                AnomalyKind::Cost,
+                usage.cost_usd,
+                settings.min_cost_usd,
+                judged,
+                settings,
+            ),
+            self.tokens.observe(
+                AnomalyKind::Tokens,
+                usage.tokens as f64,
+                settings.min_tokens as f64,
+                judged,
+                settings,
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Adds up the cost and tokens of every call and, once a window, compares
+/// them with their moving average. A window spending more than `threshold`
+/// times normal is counted in `gen_ai.cost.anomaly`, logged as a
+/// `gen_ai.cost.anomaly` event and posted to the webhook, once per anomaly.
+pub struct CostAnomalyDetector {
+    settings: AnomalySettings,
+    service: String,
+    current: Mutex<Usage>,
+    rates: Mutex<SpendRates>,
+    client: reqwest::Client,
+}
+
+impl CostAnomalyDetector {
+    pub fn new(settings: AnomalySettings, service: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            rates: Mutex::new(SpendRates {
+                window_secs: settings.window.as_secs(),
+                threshold: settings.threshold,
+                warmup_windows: settings.warmup_windows,
+                ..SpendRates::default()
+            }),
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()?,
+            settings,
+            service: service.to_string(),
+            current: Mutex::new(Usage::default()),
+        })
+    }
+
+    /// The first window ends one `window` after spawning.
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.settings.window);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.end_window().await;
+            }
+        });
+    }
+
+    pub fn rates(&self) -> SpendRates {
+        self.rates.lock().unwrap().clone()
+    }
+
+    async fn end_window(&self) {
+        let usage = std::mem::take(&mut *self.current.lock().unwrap());
+        let (anomalies, baseline) = {
+            let mut rates = self.rates.lock().unwrap();
+            (
+                rates.observe(usage, &self.settings),
+                rates.cost_usd.baseline,
+            )
+        };
+
+        let per_minute = 60.0 / self.settings.window.as_secs_f64();
+        GEN_AI_COST_RATE.record(usage.cost_usd * per_minute, &[]);
+        GEN_AI_COST_BASELINE.record(baseline * per_minute, &[]);
+
+        for anomaly in anomalies {
+            GEN_AI_COST_ANOMALY.add(
+                1,
+                &[KeyValue::new(
+                    "gen_ai.cost.anomaly.kind",
+                    anomaly.kind.as_str(),
+                )],
+            );
+            tracing::warn!(
+                event.name = "gen_ai.cost.anomaly",
+                gen_ai.cost.anomaly.kind = anomaly.kind.as_str(),
+                gen_ai.cost.anomaly.value = anomaly.value,
+                gen_ai.cost.anomaly.baseline = anomaly.baseline,
+                window_secs = self.settings.window.as_secs(),
+                "LLM spend is more than {}x its baseline",
+                self.settings.threshold
+            );
+            if let Some(url) = &self.settings.webhook_url {
+                self.alert(url, &anomaly).await;
+            }
+        }
+    }
+
+    /// Best effort: a failed alert is logged, and not sent again.
+    async fn alert(&self, url: &str, anomaly: &Anomaly) {
+        let body = json!({
+            "event": "gen_ai.cost.anomaly",
+            "service": self.service,
+            "kind": anomaly.kind,
+            "value": anomaly.value,
+            "baseline": anomaly.baseline,
+            "threshold": self.settings.threshold,
+            "window_secs": self.settings.window.as_secs(),
+            "detected_at": Utc::now(),
+        });
+        let result = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(err) = result {
+            // The URL may carry a token, so only the status or error kind.
+            tracing::warn!(
+                status = err.status().map(|s| s.as_u16()),
+                timeout = err.is_timeout(),
+                "Failed to send cost anomaly alert"
+            );
+        }
+    }
+}
+
+impl ProviderObserver for CostAnomalyDetector {
+    fn on_complete(&self, _call: &CallContext<'_>, resp: &GenerateResponse) -> anyhow::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        current.cost_usd += resp.cost_usd;
+        current.tokens += u64::from(resp.input_tokens) + u64::from(resp.output_tokens);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AnomalySettings {
+        AnomalySettings {
+            alpha: 0.5,
+            warmup_windows: 2,
+            min_cost_usd: 0.5,
+            min_tokens: 1_000,
+            ..AnomalySettings::default()
+        }
+    }
+
+    fn usage(cost_usd: f64, tokens: u64) -> Usage {
+        Usage { cost_usd, tokens }
+    }
+
+    #[test]
+    fn test_warmup_windows_are_not_judged() {
+        let settings = settings();
+        let mut rates = SpendRates {
+            warmup_windows: settings.warmup_windows,
+            ..SpendRates::default()
+        };
+        assert!(rates.observe(usage(1.0, 1_000), &settings).is_empty());
+        assert!(rates.observe(usage(9.0, 9_000), &settings).is_empty());
+        assert_eq!(rates.cost_usd.baseline, 5.0);
+        assert_eq!(rates.windows, 2);
+    }
+
+    #[test]
+    fn test_anomaly_fires_once_and_spares_the_baseline() {
+        let settings = settings();
+        let mut rates = SpendRates {
+            warmup_windows: settings.warmup_windows,
+            ..SpendRates::default()
+        };
+        rates.observe(usage(1.0, 2_000), &settings);
+        rates.observe(usage(1.0, 2_000), &settings);
+
+        let anomalies = rates.observe(usage(4.0, 2_000), &settings);
+        assert_eq!(
+            anomalies,
+            [Anomaly {
+                kind: AnomalyKind::Cost,
+                value: 4.0,
+                baseline: 1.0,
+            }]
+        );
+        assert!(rates.cost_usd.anomalous_since.is_some());
+        assert!(rates.observe(usage(5.0, 2_000), &settings).is_empty());
+        assert_eq!(rates.cost_usd.baseline, 1.0);
+
+        // Back to normal: the next anomaly fires again.
+        assert!(rates.observe(usage(1.0, 2_000), &settings).is_empty());
+        assert!(rates.cost_usd.anomalous_since.is_none());
+        let anomalies = rates.observe(usage(1.0, 20_000), &settings);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].kind, AnomalyKind::Tokens);
+    }
+
+    #[test]
+    fn test_spend_below_the_floor_is_never_anomalous() {
+        let settings = settings();
+        let mut rates = SpendRates::default();
+        rates.observe(usage(0.0, 0), &settings);
+        assert!(rates.observe(usage(0.4, 900), &settings).is_empty());
+    }
+}
@@ -1,19 +1,44 @@
 use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
 
-use super::{GenerateRequest, GenerateResponse, Provider};
+use super::{GenerateRequest, GenerateResponse, HttpSettings, Provider};
 
 pub struct AnthropicProvider {
     client: reqwest::Client,
+    http: HttpSettings,
     api_key: String,
+    base_url: String,
 }
 
 impl AnthropicProvider {
-    pub fn new(api_key: &str) -> Self {
-        Self {
-            client: reqwest::Client::new(),
+    pub fn new(api_key: &str, http: HttpSettings) -> anyhow::Result<Self> {
+        Self::with_base_url(api_key, "https://api.anthropic.com/v1", http)
+    }
+
+    /// The Messages API at `base_url`, e.g. a regional proxy.
+    pub fn with_base_url(
+        api_key: &str,
+        base_url: &str,
+        http: HttpSettings,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: http.client()?,
+            http,
             api_key: api_key.to_string(),
-        }
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn headers(&self) -> anyhow::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_str(&self.api_key)
+                .map_err(|e| anyhow::anyhow!("invalid API key header: {e}"))?,
+        );
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(headers)
     }
 }
 
@@ -52,6 +77,16 @@ struct AnthropicUsage {
     output_tokens: u32,
 }
 
+#[derive(Deserialize)]
+struct AnthropicModels {
+    data: Vec<AnthropicModel>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicModel {
+    id: String,
+}
+
 #[derive(Deserialize)]
 struct AnthropicError {
     error: AnthropicErrorDetail,
@@ -65,15 +100,6 @@ struct AnthropicErrorDetail {
 #[async_trait::async_trait]
 impl Provider for AnthropicProvider {
     async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-api-key",
-            HeaderValue::from_str(&self.api_key)
-                .map_err(|e| anyhow::anyhow!("invalid API key header: {e}"))?,
-        );
-        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
         let body = AnthropicRequest {
             model: req.model.clone(),
             max_tokens: req.max_tokens,
@@ -86,30 +112,12 @@ impl Provider for AnthropicProvider {
 
         let response = self
             .client
-            .post("https://api.anthropic.com/v1/messages")
-            .headers(headers)
+            .post(format!("{}/messages", self.base_url))
+            .headers(self.headers()?)
             .json(&body)
             .send()
             .await?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_body = response.text().await.unwrap_or_default();
-            if let Ok(err) = serde_json::from_str::<AnthropicError>(&error_body) {
-                return Err(anyhow::anyhow!(
-                    "Anthropic API error ({}): {}",
-                    status,
-                    err.error.message
-                ));
-            }
-            return Err(anyhow::anyhow!(
-                "Anthropic API error ({}): {}",
-                status,
-                error_body
-            ));
-        }
-
-        let resp: AnthropicResponse = response.json().await?;
+        let resp: AnthropicResponse = json_or_error(response).await?;
 
         let content = resp
             .content
@@ -127,10 +135,48 @@ impl Provider for AnthropicProvider {
             cost_usd: 0.0,
             finish_reason: resp.stop_reason.unwrap_or_default(),
             provider: String::new(),
+            span_context: None,
         })
     }
 
     fn name(&self) -> &str {
         "anthropic"
     }
+
+    fn http_settings(&self) -> Option<&HttpSettings> {
+        Some(&self.http)
+    }
+
+    async fn list_models(&self) -> anyhow::Result<Option<Vec<String>>> {
+        let response = self
+            .client
+            .get(format!("{}/models?limit=1000", self.base_url))
+            .headers(self.headers()?)
+            .send()
+            .await?;
+        let models: AnthropicModels = json_or_error(response).await?;
+        Ok(Some(models.data.into_iter().map(|m| m.id).collect()))
+    }
+}
+
+async fn json_or_error<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> anyhow::Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response.text().await.unwrap_or_default();
+        if let Ok(err) = serde_json::from_str::<AnthropicError>(&error_body) {
+            return Err(anyhow::anyhow!(
+                "Anthropic API error ({}): {}",
+                status,
+                err.error.message
+            ));
+        }
+        return Err(anyhow::anyhow!(
+            "Anthropic API error ({}): {}",
+            status,
+            error_body
+        ));
+    }
+    Ok(response.json().await?)
 }
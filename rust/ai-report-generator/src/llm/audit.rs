@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use opentelemetry::trace::TraceContextExt;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::GenerateResponse;
+use super::observer::{CallContext, ProviderObserver, classify_error};
+use crate::db::llm_calls::{InsertLlmCall, delete_llm_calls_before, insert_llm_calls};
+use crate::telemetry::metrics::LLM_AUDIT_DROPPED;
+
+/// Rows the writer inserts per statement.
+const BATCH_SIZE: usize = 100;
+
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Records every provider call, successful or not, in `llm_calls`. Rows go
+/// through a bounded queue to a single writer task, so a slow database never
+/// delays the report; when the queue is full the row is dropped and counted
+/// in `llm.audit.dropped`.
+pub struct AuditObserver {
+    tx: mpsc::Sender<InsertLlmCall>,
+}
+
+impl AuditObserver {
+    pub fn spawn(pool: PgPool, queue_size: usize) -> Self {
+        let (tx, rx) = mpsc::channel(queue_size);
+        tokio::spawn(write_rows(pool, rx));
+        Self { tx }
+    }
+
+    fn record(&self, row: InsertLlmCall) {
+        match self.tx.try_send(row) {
+            Ok(()) => {}
+            Err(TrySendError::Full(row)) => {
+                LLM_AUDIT_DROPPED.add(1, &[]);
+                tracing::warn!(stage = %row.stage, "LLM call audit queue full, dropping row");
+            }
+            Err(TrySendError::Closed(_)) => {
+                LLM_AUDIT_DROPPED.add(1, &[]);
+            }
+        }
+    }
+}
+
+impl ProviderObserver for AuditObserver {
+    fn on_success(&self, call: &CallContext<'_>, resp: &GenerateResponse) {
+        let mut row = row(call);
+        row.model = resp.model.clone();
+        row.input_tokens = resp.input_tokens as i32;
+        row.output_tokens = resp.output_tokens as i32;
+        row.cost_usd = resp.cost_usd;
+        row.finish_reason = Some(resp.finish_reason.clone()).filter(|r| !r.is_empty());
+        self.record(row);
+    }
+
+    fn on_error(&self, call: &CallContext<'_>, err: &anyhow::Error) {
+        let mut row = row(call);
+        row.error_class = Some(classify_error(err).to_string());
+        self.record(row);
+    }
+}
+
+fn row(call: &CallContext<'_>) -> InsertLlmCall {
+    let context = call.span.context();
+    let span_context = context.span().span_context().clone();
+    InsertLlmCall {
+        provider: call.provider_name.to_string(),
+        model: call.request.model.clone(),
+        stage: call.request.stage.clone(),
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: 0.0,
+        duration_ms: call.started.elapsed().as_millis().min(i32::MAX as u128) as i32,
+        finish_reason: None,
+        error_class: None,
+        trace_id: span_context
+            .is_valid()
+            .then(|| span_context.trace_id().to_string()),
+        created_at: Utc::now(),
+    }
+}
+
+async fn write_rows(pool: PgPool, mut rx: mpsc::Receiver<InsertLlmCall>) {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while rx.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+        if let Err(err) = insert_llm_calls(&pool, &batch).await {
+            tracing::warn!(error = %err, rows = batch.len(), "Failed to record LLM calls");
+        }
+        batch.clear();
+    }
+}
+
+/// Deletes `llm_calls` rows older than `retention_days` once an hour. Zero
+/// keeps them forever.
+pub fn spawn_retention(pool: PgPool, retention_days: u32) {
+    if retention_days == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let cutoff = Utc::now() - chrono::Duration::days(retention_days.into());
+            match delete_llm_calls_before(&pool, cutoff).await {
+                Ok(0) => {}
+                Ok(deleted) => {
+                    tracing::info!(deleted, retention_days, "Pruned LLM call audit rows")
+                }
+                Err(err) => tracing::warn!(error = %err, "Failed to prune LLM call audit rows"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::llm::GenerateRequest;
+
+    fn request() -> GenerateRequest {
+        GenerateRequest {
+            model: "gpt-4.1".to_string(),
+            system: String::new(),
+            prompt: "Summarize".to_string(),
+            temperature: 0.3,
+            max_tokens: 100,
+            stage: "generate".to_string(),
+        }
+    }
+
+    fn response() -> GenerateResponse {
+        GenerateResponse {
+            content: "Report".to_string(),
+            model: "gpt-4.1-2025-04-14".to_string(),
+            input_tokens: 120,
+            output_tokens: 40,
+            cost_usd: 0.0012,
+            finish_reason: "stop".to_string(),
+            provider: "openai".to_string(),
+            span_context: None,
+        }
+    }
+
+    #[test]
+    fn test_records_completed_and_failed_calls() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let observer = AuditObserver { tx };
+        let req = request();
+        let span = tracing::Span::none();
+        let call = CallContext {
+            provider_name: "openai",
+            request: &req,
+            span: &span,
+            started: Instant::now(),
+        };
+
+        observer.on_success(&call, &response());
+        observer.on_error(&call, &anyhow::anyhow!("429 rate limit exceeded"));
+
+        let ok = rx.try_recv().unwrap();
+        assert_eq!(ok.model, "gpt-4.1-2025-04-14");
+        assert_eq!((ok.input_tokens, ok.output_tokens), (120, 40));
+        assert_eq!(ok.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(ok.error_class, None);
+
+        let failed = rx.try_recv().unwrap();
+        assert_eq!(failed.model, "gpt-4.1");
+        assert_eq!(failed.stage, "generate");
+        assert_eq!(failed.input_tokens, 0);
+        assert_eq!(failed.error_class.as_deref(), Some("rate_limit"));
+    }
+
+    #[test]
+    fn test_full_queue_drops_instead_of_blocking() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let observer = AuditObserver { tx };
+        let req = request();
+        let span = tracing::Span::none();
+        let call = CallContext {
+            provider_name: "openai",
+            request: &req,
+            span: &span,
+            started: Instant::now(),
+        };
+
+        for _ in 0..3 {
+            observer.on_success(&call, &response());
+        }
+
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+}
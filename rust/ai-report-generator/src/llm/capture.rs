@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// How much prompt and completion text is attached to gen_ai spans, per the
+/// GenAI semantic conventions' opt-in content capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureMode {
+    None,
+    Truncated,
+    Full,
+}
+
+impl FromStr for CaptureMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "truncated" => Ok(Self::Truncated),
+            "full" => Ok(Self::Full),
+            other => Err(format!("expected none, truncated or full, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for CaptureMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Truncated => "truncated",
+            Self::Full => "full",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentCapture {
+    mode: CaptureMode,
+    max_input_chars: usize,
+    max_system_chars: usize,
+    max_output_chars: usize,
+    scrubbers: Vec<Regex>,
+}
+
+impl ContentCapture {
+    pub fn new(
+        mode: CaptureMode,
+        max_input_chars: usize,
+        max_system_chars: usize,
+        max_output_chars: usize,
+        scrub_patterns: &str,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            mode,
+            max_input_chars,
+            max_system_chars,
+            max_output_chars,
+            scrubbers: compile_patterns(scrub_patterns)?,
+        })
+    }
+
+    pub fn input(&self, prompt: &str) -> Option<String> {
+        self.capture(prompt, self.max_input_chars)
+    }
+
+    pub fn system(&self, system: &str) -> Option<String> {
+        self.capture(system, self.max_system_chars)
+    }
+
+    pub fn output(&self, content: &str) -> Option<String> {
+        self.capture(content, self.max_output_chars)
+    }
+
+    /// Scrubs before truncating so a match is never cut in half and left
+    /// partly visible.
+    fn capture(&self, text: &str, max: usize) -> Option<String> {
+        if self.mode == CaptureMode::None {
+            return None;
+        }
+
+        let mut text = text.to_string();
+        for scrubber in &self.scrubbers {
+            text = scrubber.replace_all(&text, REDACTED).into_owned();
+        }
+
+        match self.mode {
+            CaptureMode::Truncated => Some(truncate(&text, max)),
+            _ => Some(text),
+        }
+    }
+}
+
+/// Parses `;`-separated regexes, reporting the first that fails to compile.
+pub fn compile_patterns(patterns: &str) -> Result<Vec<Regex>, String> {
+    patterns
+        .split(';')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| Regex::new(p).map_err(|e| format!("invalid scrub pattern {p:?}: {e}")))
+        .collect()
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max {
+        s.to_string()
+    } else {
+        s.char_indices()
+            .take_while(|&(i, _)| i < max)
+            .map(|(_, c)| c)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMAIL: &str = r"[\w.+-]+@[\w-]+\.[\w.]+";
+
+    fn capture(mode: CaptureMode, patterns: &str) -> ContentCapture {
+        ContentCapture::new(mode, 10, 5, 20, patterns).expect("valid capture config")
+    }
+
+    #[test]
+    fn test_capture_mode_parse() {
+        assert_eq!("FULL".parse::<CaptureMode>(), Ok(CaptureMode::Full));
+        assert_eq!("none".parse::<CaptureMode>(), Ok(CaptureMode::None));
+        assert!("all".parse::<CaptureMode>().is_err());
+    }
+
+    #[test]
+    fn test_none_captures_nothing() {
+        let capture = capture(CaptureMode::None, "");
+        assert_eq!(capture.input("prompt"), None);
+        assert_eq!(capture.output("completion"), None);
+    }
+
+    #[test]
+    fn test_truncated_applies_limits_and_full_does_not() {
+        let text = "a".repeat(50);
+        let truncated = capture(CaptureMode::Truncated, "");
+        assert_eq!(truncated.input(&text).unwrap().len(), 10);
+        assert_eq!(truncated.system(&text).unwrap().len(), 5);
+        assert_eq!(truncated.output(&text).unwrap().len(), 20);
+
+        let full = capture(CaptureMode::Full, "");
+        assert_eq!(full.input(&text).unwrap().len(), 50);
+    }
+
+    #[test]
+    fn test_scrubbing_runs_before_truncation() {
+        let capture = capture(
+            CaptureMode::Full,
+            &format!("{EMAIL}; \\d{{3}}-\\d{{2}}-\\d{{4}}"),
+        );
+        assert_eq!(
+            capture
+                .input("mail jane.doe@example.com, ssn 123-45-6789")
+                .unwrap(),
+            "mail [REDACTED], ssn [REDACTED]"
+        );
+
+        let truncated = ContentCapture::new(CaptureMode::Truncated, 12, 0, 0, EMAIL).unwrap();
+        assert_eq!(
+            truncated.input("jane.doe@example.com").unwrap(),
+            "[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        assert!(compile_patterns("").unwrap().is_empty());
+        assert!(compile_patterns("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_truncate_short() {
+        assert_eq!(truncate("hello", 10), "hello");
+    }
+
+    #[test]
+    fn test_truncate_exact() {
+        assert_eq!(truncate("hello", 5), "hello");
+    }
+
+    #[test]
+    fn test_truncate_long() {
+        let result = truncate("hello world", 5);
+        assert_eq!(result, "hello");
+    }
+
+    #[test]
+    fn test_truncate_multibyte_safe() {
+        let result = truncate("hé世界!", 3);
+        assert!(result.len() <= 3);
+        assert!(result.is_char_boundary(result.len()));
+    }
+}
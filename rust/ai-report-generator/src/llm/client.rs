@@ -2,39 +2,53 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use opentelemetry::KeyValue;
+use opentelemetry::trace::TraceContextExt;
 use tracing::Instrument;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use super::endpoints::{Endpoint, Endpoints};
+use super::http;
+use super::models::{self, InvalidRequest};
+use super::observer::{CallContext, ProviderObserver, classify_error};
 use super::pricing::{PROVIDER_PORTS, PROVIDER_SERVERS, calculate_cost};
-use super::{GenerateRequest, GenerateResponse, Provider};
+use super::spend::Spend;
+use super::{GenerateRequest, GenerateResponse};
+use crate::explain::{Decision, Explainer};
 use crate::telemetry::metrics::{
-    GEN_AI_COST, GEN_AI_ERROR_COUNT, GEN_AI_FALLBACK_COUNT, GEN_AI_OPERATION_DURATION,
-    GEN_AI_RETRY_COUNT, GEN_AI_TOKEN_USAGE,
+    GEN_AI_ENDPOINT_FAILOVER_COUNT, GEN_AI_FALLBACK_COUNT, GEN_AI_RETRY_COUNT,
 };
 
 pub struct LlmClient {
-    pub primary: Arc<dyn Provider>,
-    pub fallback: Option<Arc<dyn Provider>>,
+    pub primary: Endpoints,
+    pub fallback: Option<Endpoints>,
     pub primary_provider: String,
     pub fallback_provider: String,
     pub fallback_model: String,
+    pub observers: Vec<Arc<dyn ProviderObserver>>,
+    pub max_retries: u32,
 }
 
 impl LlmClient {
     pub async fn generate_once(
         &self,
-        provider: &dyn Provider,
+        endpoint: &Endpoint,
         provider_name: &str,
         req: &GenerateRequest,
     ) -> anyhow::Result<GenerateResponse> {
         let span_display_name = format!("gen_ai.chat {}", req.model);
         let start = Instant::now();
-
-        let server_addr = PROVIDER_SERVERS
-            .get(provider_name)
-            .copied()
-            .unwrap_or("unknown");
-        let server_port = PROVIDER_PORTS.get(provider_name).copied().unwrap_or(443);
+        let provider = endpoint.provider.as_ref();
+
+        let (server_addr, server_port) = match &endpoint.server {
+            Some((address, port)) => (address.as_str(), *port),
+            None => (
+                PROVIDER_SERVERS
+                    .get(provider_name)
+                    .copied()
+                    .unwrap_or("unknown"),
+                PROVIDER_PORTS.get(provider_name).copied().unwrap_or(443),
+            ),
+        };
 
         let span = tracing::info_span!(
             "gen_ai.chat",
@@ -42,6 +56,7 @@ impl LlmClient {
             gen_ai.operation.name = "chat",
             gen_ai.provider.name = %provider_name,
             gen_ai.request.model = %req.model,
+            gen_ai.endpoint = %endpoint.name,
             server.address = %server_addr,
             server.port = server_port,
             gen_ai.request.temperature = req.temperature,
@@ -52,109 +67,158 @@ impl LlmClient {
             gen_ai.usage.cost_usd = tracing::field::Empty,
             gen_ai.response.finish_reasons = tracing::field::Empty,
             report.stage = %req.stage,
+            http.client.connect_timeout_ms = tracing::field::Empty,
+            http.client.request_timeout_ms = tracing::field::Empty,
+            http.client.pool_max_idle_per_host = tracing::field::Empty,
+            http.client.proxy = tracing::field::Empty,
             otel.status_code = tracing::field::Empty,
             error.type = tracing::field::Empty,
         );
-
-        {
-            let mut user_event_attrs =
-                vec![KeyValue::new("gen_ai.input.messages", truncate(&req.prompt, 1000))];
-            if !req.system.is_empty() {
-                user_event_attrs.push(KeyValue::new(
-                    "gen_ai.system_instructions",
-                    truncate(&req.system, 500),
-                ));
+        if let Some(http) = provider.http_settings() {
+            span.record(
+                "http.client.connect_timeout_ms",
+                http.connect_timeout.as_millis() as i64,
+            );
+            span.record(
+                "http.client.request_timeout_ms",
+                http.request_timeout.as_millis() as i64,
+            );
+            span.record(
+                "http.client.pool_max_idle_per_host",
+                http.pool_max_idle_per_host as i64,
+            );
+            if let Some(proxy) = http.proxy_address() {
+                span.record("http.client.proxy", proxy.as_str());
             }
-            span.add_event("gen_ai.user.message", user_event_attrs);
         }
 
-        let result = provider.generate(req).instrument(span.clone()).await;
-
-        let duration = start.elapsed().as_secs_f64();
-
-        match result {
-            Ok(mut resp) => {
-                resp.provider = provider_name.to_string();
-                resp.cost_usd = calculate_cost(&resp.model, resp.input_tokens, resp.output_tokens);
-
-                span.record("gen_ai.response.model", resp.model.as_str());
-                span.record("gen_ai.usage.input_tokens", resp.input_tokens as i64);
-                span.record("gen_ai.usage.output_tokens", resp.output_tokens as i64);
-                span.record("gen_ai.usage.cost_usd", resp.cost_usd);
-                if !resp.finish_reason.is_empty() {
-                    span.record(
-                        "gen_ai.response.finish_reasons",
-                        resp.finish_reason.as_str(),
-                    );
-                }
+        let call = CallContext {
+            provider_name,
+            request: req,
+            span: &span,
+            started: start,
+        };
+
+        let checked = models::validate(req)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| self.notify(|o| o.on_request(&call)));
+        let result = match checked {
+            Ok(()) => provider
+                .generate(req)
+                .instrument(span.clone())
+                .await
+                .map_err(|err| http::explain_error(provider.http_settings(), err)),
+            Err(err) => Err(err),
+        };
+
+        let result = result.and_then(|mut resp| {
+            resp.provider = provider_name.to_string();
+            resp.span_context = Some(span.context().span().span_context().clone());
+            resp.cost_usd = calculate_cost(&resp.model, resp.input_tokens, resp.output_tokens)
+                * provider.cost_multiplier();
+            Spend::record(resp.cost_usd);
+
+            let elapsed = start.elapsed();
+            for observer in &self.observers {
+                observer.on_first_token(&call, elapsed);
+            }
+            self.notify(|o| o.on_complete(&call, &resp))?;
+            for observer in &self.observers {
+                observer.on_success(&call, &resp);
+            }
+            Explainer::record(|| Decision::LlmResponse {
+                stage: req.stage.clone(),
+                provider: provider_name.to_string(),
+                endpoint: endpoint.name.clone(),
+                model: resp.model.clone(),
+            });
+            Ok(resp)
+        });
+
+        if let Err(err) = &result {
+            for observer in &self.observers {
+                observer.on_error(&call, err);
+            }
+        }
 
-                span.add_event(
-                    "gen_ai.assistant.message",
-                    vec![KeyValue::new(
-                        "gen_ai.output.messages",
-                        truncate(&resp.content, 2000),
-                    )],
-                );
+        result
+    }
 
-                let op_kv = KeyValue::new("gen_ai.operation.name", "chat");
-                let provider_kv = KeyValue::new("gen_ai.provider.name", provider_name.to_string());
-                let model_kv = KeyValue::new("gen_ai.request.model", resp.model.clone());
+    /// Runs a fallible hook on every observer, stopping at the first error.
+    fn notify(
+        &self,
+        hook: impl Fn(&dyn ProviderObserver) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.observers.iter().try_for_each(|o| hook(o.as_ref()))
+    }
 
-                GEN_AI_TOKEN_USAGE.record(
-                    f64::from(resp.input_tokens),
-                    &[
-                        KeyValue::new("gen_ai.token.type", "input"),
-                        op_kv.clone(),
-                        provider_kv.clone(),
-                        model_kv.clone(),
-                    ],
-                );
-                GEN_AI_TOKEN_USAGE.record(
-                    f64::from(resp.output_tokens),
-                    &[
-                        KeyValue::new("gen_ai.token.type", "output"),
-                        op_kv.clone(),
-                        provider_kv.clone(),
-                        model_kv.clone(),
-                    ],
-                );
-                GEN_AI_OPERATION_DURATION.record(
-                    duration,
-                    &[op_kv.clone(), provider_kv.clone(), model_kv.clone()],
+    /// One attempt across `endpoints`: a rate limit, timeout, server or
+    /// network error moves straight on to the next endpoint, any other error
+    /// ends the attempt.
+    async fn generate_on(
+        &self,
+        endpoints: &Endpoints,
+        provider_name: &str,
+        req: &GenerateRequest,
+    ) -> anyhow::Result<GenerateResponse> {
+        let mut last_err: Option<(String, anyhow::Error)> = None;
+
+        for endpoint in endpoints.ordered() {
+            if let Some((failed, err)) = &last_err {
+                tracing::warn!(
+                    provider = provider_name,
+                    endpoint = %endpoint.name,
+                    error = %err,
+                    "LLM endpoint failed, trying the next one"
                 );
-                GEN_AI_COST.add(resp.cost_usd, &[op_kv, provider_kv, model_kv]);
-
-                Ok(resp)
-            }
-            Err(err) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.type", classify_error(&err));
-
-                GEN_AI_ERROR_COUNT.add(
+                Explainer::record(|| Decision::EndpointFailover {
+                    stage: req.stage.clone(),
+                    provider: provider_name.to_string(),
+                    from_endpoint: failed.clone(),
+                    to_endpoint: endpoint.name.clone(),
+                    error: err.to_string(),
+                });
+                GEN_AI_ENDPOINT_FAILOVER_COUNT.add(
                     1,
                     &[
                         KeyValue::new("gen_ai.provider.name", provider_name.to_string()),
-                        KeyValue::new("gen_ai.request.model", req.model.clone()),
+                        KeyValue::new("gen_ai.endpoint", endpoint.name.clone()),
                     ],
                 );
+            }
 
-                Err(err)
+            match self.generate_once(endpoint, provider_name, req).await {
+                Ok(resp) => {
+                    endpoint.mark_healthy();
+                    return Ok(resp);
+                }
+                Err(err) => match classify_error(&err) {
+                    "rate_limit" | "timeout" | "server_error" | "network_error" => {
+                        endpoints.mark_unhealthy(endpoint);
+                        last_err = Some((endpoint.name.clone(), err));
+                    }
+                    _ => return Err(err),
+                },
             }
         }
+
+        Err(last_err.expect("a provider has at least one endpoint").1)
     }
 
     pub async fn generate_with_retry(
         &self,
-        provider: &dyn Provider,
+        endpoints: &Endpoints,
         provider_name: &str,
         req: &GenerateRequest,
     ) -> anyhow::Result<GenerateResponse> {
-        let max_retries: u32 = 3;
+        let max_retries = self.max_retries.max(1);
         let mut last_err = None;
 
         for attempt in 0..max_retries {
-            match self.generate_once(provider, provider_name, req).await {
+            match self.generate_on(endpoints, provider_name, req).await {
                 Ok(resp) => return Ok(resp),
+                // Sending it again would fail the same way.
+                Err(err) if err.is::<InvalidRequest>() => return Err(err),
                 Err(err) => {
                     tracing::warn!(
                         attempt = attempt + 1,
@@ -175,9 +239,14 @@ impl LlmClient {
                         );
                     }
 
-                    last_err = Some(err);
-
                     if attempt < max_retries - 1 {
+                        Explainer::record(|| Decision::Retry {
+                            stage: req.stage.clone(),
+                            provider: provider_name.to_string(),
+                            model: req.model.clone(),
+                            attempt: attempt + 1,
+                            error: err.to_string(),
+                        });
                         let base = Duration::from_secs(1) * 2u32.pow(attempt);
                         let base = base.min(Duration::from_secs(10));
                         // 25% jitter to avoid thundering herd
@@ -185,6 +254,8 @@ impl LlmClient {
                         let delay = base + Duration::from_millis(jitter_ms);
                         tokio::time::sleep(delay).await;
                     }
+
+                    last_err = Some(err);
                 }
             }
         }
@@ -194,7 +265,7 @@ impl LlmClient {
 
     pub async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
         let result = self
-            .generate_with_retry(self.primary.as_ref(), &self.primary_provider, req)
+            .generate_with_retry(&self.primary, &self.primary_provider, req)
             .await;
 
         match result {
@@ -209,18 +280,21 @@ impl LlmClient {
                     );
 
                     GEN_AI_FALLBACK_COUNT.add(1, &[]);
+                    Explainer::record(|| Decision::Fallback {
+                        stage: req.stage.clone(),
+                        from_provider: self.primary_provider.clone(),
+                        to_provider: self.fallback_provider.clone(),
+                        model: self.fallback_model.clone(),
+                        error: primary_err.to_string(),
+                    });
 
                     let fallback_req = GenerateRequest {
                         model: self.fallback_model.clone(),
                         ..req.clone()
                     };
 
-                    self.generate_with_retry(
-                        fallback.as_ref(),
-                        &self.fallback_provider,
-                        &fallback_req,
-                    )
-                    .await
+                    self.generate_with_retry(fallback, &self.fallback_provider, &fallback_req)
+                        .await
                 } else {
                     Err(anyhow::anyhow!(
                         "primary provider {} failed after retries: {}",
@@ -231,107 +305,350 @@ impl LlmClient {
             }
         }
     }
-}
-
-fn classify_error(err: &anyhow::Error) -> &'static str {
-    let msg = err.to_string().to_lowercase();
-    if msg.contains("rate limit") || msg.contains("429") {
-        "rate_limit"
-    } else if msg.contains("timeout") || msg.contains("timed out") || msg.contains("deadline") {
-        "timeout"
-    } else if msg.contains("401")
-        || msg.contains("403")
-        || msg.contains("auth")
-        || msg.contains("api key")
-    {
-        "auth_error"
-    } else if msg.contains("400") || msg.contains("422") || msg.contains("invalid") {
-        "invalid_request"
-    } else if msg.contains("500")
-        || msg.contains("502")
-        || msg.contains("503")
-        || msg.contains("server")
-    {
-        "server_error"
-    } else if msg.contains("connect")
-        || msg.contains("dns")
-        || msg.contains("network")
-        || msg.contains("reset")
-    {
-        "network_error"
-    } else {
-        "unknown_error"
-    }
-}
 
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        s.char_indices()
-            .take_while(|&(i, _)| i < max)
-            .map(|(_, c)| c)
-            .collect()
+    /// The context window a prompt for `model` must fit, which is the
+    /// smaller of the model's and the fallback model's.
+    pub fn context_window(&self, model: &str) -> u32 {
+        let window = models::context_window(model);
+        match self.fallback {
+            Some(_) => window.min(models::context_window(&self.fallback_model)),
+            None => window,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
+
+    use opentelemetry::trace::Status;
+
     use super::*;
+    use crate::llm::Provider;
+    use crate::llm::capture::{CaptureMode, ContentCapture};
+    use crate::llm::observer::{CallContext, TelemetryObserver};
+    use crate::telemetry::testing::{
+        SpanCapture, assert_attributes, assert_child_of, assert_metric_recorded,
+    };
+
+    struct StubProvider {
+        fail: bool,
+    }
 
-    #[test]
-    fn test_classify_error_categories() {
-        let cases = vec![
-            ("rate limit exceeded", "rate_limit"),
-            ("status 429: too many requests", "rate_limit"),
-            ("context deadline exceeded: timeout", "timeout"),
-            ("request timed out", "timeout"),
-            ("401 unauthorized", "auth_error"),
-            ("403 forbidden", "auth_error"),
-            ("authentication failed", "auth_error"),
-            ("invalid api key", "auth_error"),
-            ("400 bad request", "invalid_request"),
-            ("422 unprocessable entity", "invalid_request"),
-            ("invalid model name", "invalid_request"),
-            ("500 internal server error", "server_error"),
-            ("502 bad gateway", "server_error"),
-            ("503 service unavailable", "server_error"),
-            ("connection refused", "network_error"),
-            ("dns resolution failed", "network_error"),
-            ("connection reset by peer", "network_error"),
-            ("something unexpected", "unknown_error"),
-        ];
-
-        for (msg, expected) in cases {
-            let err = anyhow::anyhow!("{}", msg);
-            assert_eq!(
-                classify_error(&err),
-                expected,
-                "classify_error({msg:?}) should be {expected:?}"
-            );
+    #[async_trait::async_trait]
+    impl Provider for StubProvider {
+        async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            if self.fail {
+                anyhow::bail!("503 service unavailable");
+            }
+            Ok(GenerateResponse {
+                content: "ok".to_string(),
+                model: req.model.clone(),
+                input_tokens: 10,
+                output_tokens: 5,
+                cost_usd: 0.0,
+                finish_reason: "stop".to_string(),
+                provider: String::new(),
+                span_context: None,
+            })
         }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<String>>,
+        reject_completion: bool,
+    }
+
+    impl Recorder {
+        fn push(&self, call: &CallContext<'_>, hook: &str) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{}:{}", call.provider_name, hook));
+        }
+    }
+
+    impl ProviderObserver for Recorder {
+        fn on_request(&self, call: &CallContext<'_>) -> anyhow::Result<()> {
+            self.push(call, "request");
+            Ok(())
+        }
+
+        fn on_first_token(&self, call: &CallContext<'_>, _elapsed: Duration) {
+            self.push(call, "first_token");
+        }
+
+        fn on_complete(
+            &self,
+            call: &CallContext<'_>,
+            _response: &GenerateResponse,
+        ) -> anyhow::Result<()> {
+            self.push(call, "complete");
+            if self.reject_completion {
+                anyhow::bail!("blocked by moderation");
+            }
+            Ok(())
+        }
+
+        fn on_success(&self, call: &CallContext<'_>, _response: &GenerateResponse) {
+            self.push(call, "success");
+        }
+
+        fn on_error(&self, call: &CallContext<'_>, _error: &anyhow::Error) {
+            self.push(call, "error");
+        }
+    }
+
+    fn client(fail: bool, observer: Arc<Recorder>) -> LlmClient {
+        LlmClient {
+            primary: Endpoints::single(Arc::new(StubProvider { fail })),
+            fallback: None,
+            primary_provider: "stub".to_string(),
+            fallback_provider: "none".to_string(),
+            fallback_model: String::new(),
+            observers: vec![observer],
+            max_retries: 1,
+        }
+    }
+
+    fn request() -> GenerateRequest {
+        GenerateRequest {
+            model: "stub-model".to_string(),
+            system: String::new(),
+            prompt: "hello".to_string(),
+            temperature: 0.0,
+            max_tokens: 16,
+            stage: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_hooks_run_in_order() {
+        let recorder = Arc::new(Recorder::default());
+        let resp = client(false, recorder.clone())
+            .generate(&request())
+            .await
+            .unwrap();
+
+        assert_eq!(resp.provider, "stub");
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                "stub:request",
+                "stub:first_token",
+                "stub:complete",
+                "stub:success"
+            ]
+        );
     }
 
-    #[test]
-    fn test_truncate_short() {
-        assert_eq!(truncate("hello", 10), "hello");
+    #[tokio::test]
+    async fn test_observer_sees_provider_errors() {
+        let recorder = Arc::new(Recorder::default());
+        assert!(
+            client(true, recorder.clone())
+                .generate(&request())
+                .await
+                .is_err()
+        );
+
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec!["stub:request", "stub:error"]
+        );
     }
 
-    #[test]
-    fn test_truncate_exact() {
-        assert_eq!(truncate("hello", 5), "hello");
+    #[tokio::test]
+    async fn test_invalid_requests_are_not_sent_or_retried() {
+        let spans = SpanCapture::start();
+        let recorder = Arc::new(Recorder::default());
+        let mut client = client(false, recorder.clone());
+        client.max_retries = 3;
+        let req = GenerateRequest {
+            model: "gpt-4o".to_string(),
+            max_tokens: 100_000,
+            ..request()
+        };
+
+        let err = client.generate(&req).await.unwrap_err();
+        assert!(
+            err.to_string().contains("limit of 16384 output tokens"),
+            "{err}"
+        );
+        // No `request` hook: the call never reached the provider.
+        assert_eq!(*recorder.calls.lock().unwrap(), vec!["stub:error"]);
+        assert_eq!(spans.spans().len(), 1);
     }
 
-    #[test]
-    fn test_truncate_long() {
-        let result = truncate("hello world", 5);
-        assert_eq!(result, "hello");
+    #[tokio::test]
+    async fn test_observer_can_reject_a_response() {
+        let recorder = Arc::new(Recorder {
+            reject_completion: true,
+            ..Default::default()
+        });
+        let err = client(false, recorder.clone())
+            .generate(&request())
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("blocked by moderation"));
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                "stub:request",
+                "stub:first_token",
+                "stub:complete",
+                "stub:error"
+            ]
+        );
     }
 
-    #[test]
-    fn test_truncate_multibyte_safe() {
-        let result = truncate("hé世界!", 3);
-        assert!(result.len() <= 3);
-        assert!(result.is_char_boundary(result.len()));
+    fn telemetry_client(primary_fails: bool) -> LlmClient {
+        let capture = ContentCapture::new(CaptureMode::None, 0, 0, 0, "").unwrap();
+        LlmClient {
+            primary: Endpoints::single(Arc::new(StubProvider {
+                fail: primary_fails,
+            })),
+            fallback: Some(Endpoints::single(Arc::new(StubProvider { fail: false }))),
+            primary_provider: "stub".to_string(),
+            fallback_provider: "stub-fallback".to_string(),
+            fallback_model: "fallback-model".to_string(),
+            observers: vec![Arc::new(TelemetryObserver::new(capture))],
+            max_retries: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_span_and_metrics() {
+        let spans = SpanCapture::start();
+        let req = GenerateRequest {
+            model: "span-test-model".to_string(),
+            ..request()
+        };
+        telemetry_client(false).generate(&req).await.unwrap();
+
+        let chat = spans.span("gen_ai.chat span-test-model");
+        assert_attributes(
+            &chat,
+            &[
+                KeyValue::new("gen_ai.operation.name", "chat"),
+                KeyValue::new("gen_ai.provider.name", "stub"),
+                KeyValue::new("gen_ai.request.model", "span-test-model"),
+                KeyValue::new("gen_ai.response.model", "span-test-model"),
+                KeyValue::new("gen_ai.usage.input_tokens", 10),
+                KeyValue::new("gen_ai.usage.output_tokens", 5),
+                KeyValue::new("gen_ai.response.finish_reasons", "stop"),
+                KeyValue::new("report.stage", "test"),
+            ],
+        );
+        assert_metric_recorded(
+            "gen_ai.client.token.usage",
+            &[
+                KeyValue::new("gen_ai.token.type", "output"),
+                KeyValue::new("gen_ai.request.model", "span-test-model"),
+            ],
+        );
+        assert_metric_recorded(
+            "gen_ai.client.operation.duration",
+            &[KeyValue::new("gen_ai.request.model", "span-test-model")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_gets_its_own_chat_span() {
+        let spans = SpanCapture::start();
+        let req = GenerateRequest {
+            model: "failing-model".to_string(),
+            ..request()
+        };
+        async {
+            telemetry_client(true).generate(&req).await.unwrap();
+        }
+        .instrument(tracing::info_span!("report"))
+        .await;
+
+        let report = spans.span("report");
+        let failed = spans.span("gen_ai.chat failing-model");
+        let fallback = spans.span("gen_ai.chat fallback-model");
+        assert_child_of(&failed, &report);
+        assert_child_of(&fallback, &report);
+        assert!(matches!(failed.status, Status::Error { .. }));
+        assert_attributes(&failed, &[KeyValue::new("error.type", "server_error")]);
+        assert_attributes(
+            &fallback,
+            &[KeyValue::new("gen_ai.provider.name", "stub-fallback")],
+        );
+        assert_metric_recorded(
+            "gen_ai.client.error.count",
+            &[KeyValue::new("gen_ai.request.model", "failing-model")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_failover_skips_the_failed_endpoint() {
+        let spans = SpanCapture::start();
+        let endpoint =
+            |name, url, fail| Endpoint::new(name, Arc::new(StubProvider { fail }), url).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let client = LlmClient {
+            primary: Endpoints::new(
+                vec![
+                    endpoint("east", "https://east.example.com/v1", true),
+                    endpoint("west", "https://west.example.com:8443/v1", false),
+                ],
+                Duration::from_secs(60),
+            ),
+            ..client(false, recorder.clone())
+        };
+        let req = GenerateRequest {
+            model: "endpoint-model".to_string(),
+            ..request()
+        };
+
+        let resp = client.generate(&req).await.unwrap();
+        assert_eq!(resp.provider, "stub");
+        let chats: Vec<_> = spans
+            .spans()
+            .into_iter()
+            .filter(|s| s.name == "gen_ai.chat endpoint-model")
+            .collect();
+        assert_eq!(chats.len(), 2);
+        assert_attributes(
+            &chats[0],
+            &[
+                KeyValue::new("gen_ai.endpoint", "east"),
+                KeyValue::new("server.address", "east.example.com"),
+            ],
+        );
+        assert_attributes(
+            &chats[1],
+            &[
+                KeyValue::new("gen_ai.endpoint", "west"),
+                KeyValue::new("server.address", "west.example.com"),
+                KeyValue::new("server.port", 8443),
+            ],
+        );
+        assert_metric_recorded(
+            "gen_ai.client.endpoint_failover.count",
+            &[KeyValue::new("gen_ai.endpoint", "west")],
+        );
+
+        // East is cooling down, so the next call goes straight to west.
+        recorder.calls.lock().unwrap().clear();
+        client.generate(&req).await.unwrap();
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                "stub:request",
+                "stub:first_token",
+                "stub:complete",
+                "stub:success"
+            ]
+        );
     }
 }
@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::Provider;
+
+/// One place calls to a provider can go: a region, a deployment or an
+/// OpenAI-compatible gateway.
+pub struct Endpoint {
+    pub name: String,
+    pub provider: Arc<dyn Provider>,
+    /// `server.address` and `server.port` for its spans; `None` for the
+    /// provider's default API.
+    pub server: Option<(String, i64)>,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    /// The provider's default API.
+    pub fn default_for(provider: Arc<dyn Provider>) -> Self {
+        Self {
+            name: "default".to_string(),
+            provider,
+            server: None,
+            unhealthy_until: Mutex::new(None),
+        }
+    }
+
+    /// `provider` configured to call `base_url`.
+    pub fn new(name: &str, provider: Arc<dyn Provider>, base_url: &str) -> anyhow::Result<Self> {
+        let url = reqwest::Url::parse(base_url)?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("endpoint {name} URL has no host"))?;
+        Ok(Self {
+            name: name.to_string(),
+            provider,
+            server: Some((
+                host.to_string(),
+                url.port_or_known_default().map_or(443, i64::from),
+            )),
+            unhealthy_until: Mutex::new(None),
+        })
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until
+            .lock()
+            .unwrap()
+            .is_none_or(|until| until <= now)
+    }
+
+    pub(super) fn mark_healthy(&self) {
+        *self.unhealthy_until.lock().unwrap() = None;
+    }
+}
+
+/// One provider's endpoints in failover order. An endpoint that fails with a
+/// rate limit, timeout, server or network error goes to the back of the
+/// order for `cooldown`.
+#[derive(Clone)]
+pub struct Endpoints {
+    endpoints: Arc<[Endpoint]>,
+    cooldown: Duration,
+}
+
+impl Endpoints {
+    pub fn new(endpoints: Vec<Endpoint>, cooldown: Duration) -> Self {
+        assert!(!endpoints.is_empty(), "a provider needs an endpoint");
+        Self {
+            endpoints: endpoints.into(),
+            cooldown,
+        }
+    }
+
+    /// Just the provider's default API.
+    pub fn single(provider: Arc<dyn Provider>) -> Self {
+        Self::new(vec![Endpoint::default_for(provider)], Duration::ZERO)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Endpoint> {
+        self.endpoints.iter()
+    }
+
+    /// The healthy endpoints, then those cooling down, each in configured
+    /// order.
+    pub fn ordered(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        let (mut healthy, cooling): (Vec<_>, Vec<_>) =
+            self.endpoints.iter().partition(|e| e.is_healthy(now));
+        healthy.extend(cooling);
+        healthy
+    }
+
+    pub(super) fn mark_unhealthy(&self, endpoint: &Endpoint) {
+        *endpoint.unhealthy_until.lock().unwrap() = Some(Instant::now() + self.cooldown);
+    }
+}
+
+/// Parses `LLM_ENDPOINTS`-style lists: comma-separated `name=base_url` pairs.
+pub fn parse_list(list: &str) -> Result<Vec<(String, String)>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, url) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=base_url, got {entry:?}"))?;
+            let (name, url) = (name.trim(), url.trim());
+            if name.is_empty() {
+                return Err(format!("endpoint {entry:?} has no name"));
+            }
+            match reqwest::Url::parse(url) {
+                Ok(parsed) if parsed.has_host() => Ok((name.to_string(), url.to_string())),
+                _ => Err(format!("endpoint {name} has an invalid URL {url:?}")),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{GenerateRequest, GenerateResponse};
+
+    struct Named(&'static str);
+
+    #[async_trait::async_trait]
+    impl Provider for Named {
+        async fn generate(&self, _req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            unimplemented!()
+        }
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn names(endpoints: &Endpoints) -> Vec<&str> {
+        endpoints
+            .ordered()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_unhealthy_endpoints_go_last_until_they_cool_down() {
+        let endpoint = |name, url| Endpoint::new(name, Arc::new(Named("openai")), url).unwrap();
+        let endpoints = Endpoints::new(
+            vec![
+                endpoint("east", "https://east.example.com/v1"),
+                endpoint("west", "https://west.example.com:8443/v1"),
+                endpoint("gateway", "http://gateway:8080/v1"),
+            ],
+            Duration::from_millis(50),
+        );
+        assert_eq!(
+            endpoints.ordered()[1].server,
+            Some(("west.example.com".to_string(), 8443))
+        );
+
+        endpoints.mark_unhealthy(endpoints.ordered()[0]);
+        assert_eq!(names(&endpoints), ["west", "gateway", "east"]);
+        endpoints.mark_unhealthy(endpoints.ordered()[0]);
+        assert_eq!(names(&endpoints), ["gateway", "east", "west"]);
+
+        endpoints.iter().nth(1).unwrap().mark_healthy();
+        assert_eq!(names(&endpoints), ["west", "gateway", "east"]);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(names(&endpoints), ["east", "west", "gateway"]);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse_list(""), Ok(vec![]));
+        assert_eq!(
+            parse_list("east=https://east.example.com/v1, gateway = http://gw:8080/v1"),
+            Ok(vec![
+                (
+                    "east".to_string(),
+                    "https://east.example.com/v1".to_string()
+                ),
+                ("gateway".to_string(), "http://gw:8080/v1".to_string()),
+            ])
+        );
+        assert!(parse_list("https://east.example.com").is_err());
+        assert!(parse_list("east=not a url").is_err());
+        assert!(parse_list("=https://east.example.com").is_err());
+    }
+}
@@ -1,9 +1,29 @@
+pub mod anomaly;
 pub mod anthropic;
+pub mod audit;
+pub mod capture;
 pub mod client;
+pub mod endpoints;
+pub mod http;
+pub mod models;
+pub mod observer;
 pub mod openai;
 pub mod pricing;
+pub mod providers;
+pub mod spend;
+pub mod tokens;
 
+use opentelemetry::trace::SpanContext;
+
+pub use anomaly::{AnomalySettings, CostAnomalyDetector};
+pub use audit::AuditObserver;
+pub use capture::{CaptureMode, ContentCapture};
 pub use client::LlmClient;
+pub use endpoints::{Endpoint, Endpoints};
+pub use http::HttpSettings;
+pub use observer::{ProviderObserver, TelemetryObserver};
+pub use providers::ProviderDirectory;
+pub use spend::Spend;
 
 #[derive(Debug, Clone)]
 pub struct GenerateRequest {
@@ -24,10 +44,30 @@ pub struct GenerateResponse {
     pub cost_usd: f64,
     pub finish_reason: String,
     pub provider: String,
+    /// The `gen_ai.chat` span that produced this response.
+    pub span_context: Option<SpanContext>,
 }
 
 #[async_trait::async_trait]
 pub trait Provider: Send + Sync {
     async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse>;
     fn name(&self) -> &str;
+
+    /// Applied to list-price cost, e.g. for discounted batch requests.
+    fn cost_multiplier(&self) -> f64 {
+        1.0
+    }
+
+    /// Model IDs the API offers. Also serves as the reachability probe for
+    /// `GET /api/providers`, so `None` (no listing endpoint) still means the
+    /// provider is up.
+    async fn list_models(&self) -> anyhow::Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// The HTTP client settings its calls go out with, for the
+    /// `gen_ai.chat` span; `None` for providers that make no HTTP calls.
+    fn http_settings(&self) -> Option<&HttpSettings> {
+        None
+    }
 }
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::GenerateRequest;
+use super::tokens::count_tokens;
+
+/// Assumed for models the registry does not list, e.g. local Ollama models.
+const DEFAULT_CONTEXT_WINDOW: u32 = 8_192;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModelInfo {
+    /// Prompt plus completion tokens the model accepts.
+    pub context_window: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    pub supports_json_mode: bool,
+    pub supports_tools: bool,
+    pub supports_streaming: bool,
+    /// For models pricing.json does not list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<Price>,
+}
+
+/// USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+}
+
+#[derive(Deserialize)]
+struct ModelsFile {
+    models: HashMap<String, ModelInfo>,
+}
+
+/// `data/models.json`, with the entries of the file at `MODELS_JSON_PATH`
+/// added or replacing them.
+pub static MODELS: LazyLock<HashMap<String, ModelInfo>> = LazyLock::new(|| {
+    let mut models = serde_json::from_str::<ModelsFile>(include_str!("../../data/models.json"))
+        .expect("data/models.json is valid")
+        .models;
+    let path = std::env::var("MODELS_JSON_PATH").unwrap_or_default();
+    if !path.is_empty() {
+        let file = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(serde_json::from_str::<ModelsFile>(&data)?));
+        match file {
+            Ok(file) => models.extend(file.models),
+            Err(e) => tracing::warn!(path = %path, error = %e, "Ignoring MODELS_JSON_PATH"),
+        }
+    }
+    models
+});
+
+/// The registry entry for `model`, also matching dated and dashed variants,
+/// e.g. `claude-haiku-4-5-20251001` or `gpt-4.1-mini-2025-04-14`. The longest
+/// matching name wins, so `gpt-4.1-mini` is not read as `gpt-4.1`.
+pub fn lookup(model: &str) -> Option<&'static ModelInfo> {
+    let model = model.replace('.', "-");
+    MODELS
+        .iter()
+        .filter(|(name, _)| {
+            model
+                .strip_prefix(&name.replace('.', "-"))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+        })
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, info)| info)
+}
+
+pub fn context_window(model: &str) -> u32 {
+    lookup(model).map_or(DEFAULT_CONTEXT_WINDOW, |info| info.context_window)
+}
+
+/// A request the model cannot serve, rejected before it is sent. Classified
+/// as `invalid_request` and not retried.
+#[derive(Debug)]
+pub struct InvalidRequest(pub String);
+
+impl fmt::Display for InvalidRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid request: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRequest {}
+
+/// Checks `req` against its model's registry entry. Models the registry does
+/// not list are only checked for a positive `max_tokens`.
+pub fn validate(req: &GenerateRequest) -> Result<(), InvalidRequest> {
+    if req.max_tokens == 0 {
+        return Err(InvalidRequest("max_tokens must be positive".to_string()));
+    }
+    let Some(info) = lookup(&req.model) else {
+        return Ok(());
+    };
+    if let Some(limit) = info.max_output_tokens
+        && req.max_tokens > limit
+    {
+        return Err(InvalidRequest(format!(
+            "max_tokens {} exceeds {}'s limit of {limit} output tokens",
+            req.max_tokens, req.model
+        )));
+    }
+    let prompt_tokens = count_tokens(&req.system) + count_tokens(&req.prompt);
+    if prompt_tokens + req.max_tokens as usize > info.context_window as usize {
+        return Err(InvalidRequest(format!(
+            "the prompt (about {prompt_tokens} tokens) plus max_tokens {} exceeds {}'s \
+             context window of {} tokens",
+            req.max_tokens, req.model, info.context_window
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(model: &str, prompt: &str, max_tokens: u32) -> GenerateRequest {
+        GenerateRequest {
+            model: model.to_string(),
+            system: "You are an economist.".to_string(),
+            prompt: prompt.to_string(),
+            temperature: 0.3,
+            max_tokens,
+            stage: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lookup_matches_model_variants() {
+        assert_eq!(context_window("gpt-4.1-mini"), 1_047_576);
+        assert_eq!(
+            lookup("o1-mini-2024-09-12").unwrap().max_output_tokens,
+            Some(65_536)
+        );
+        assert_eq!(lookup("o1").unwrap().max_output_tokens, Some(100_000));
+        let haiku = lookup("claude-haiku-4-5-20251001").unwrap();
+        assert_eq!(
+            haiku.pricing,
+            Some(Price {
+                input: 1.0,
+                output: 5.0
+            })
+        );
+        assert!(!haiku.supports_json_mode);
+        // Unlisted Claude models get the family's entry, without pricing.
+        assert_eq!(lookup("claude-opus-9").unwrap().pricing, None);
+        assert!(lookup("o1x").is_none());
+        assert_eq!(context_window("llama3.2"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn test_every_entry_fits_its_context_window() {
+        for (name, info) in MODELS.iter() {
+            assert!(
+                info.max_output_tokens
+                    .is_none_or(|limit| limit <= info.context_window),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&request("gpt-4o", "Summarize GDP.", 4096)).is_ok());
+        assert!(validate(&request("llama3.2", "Summarize GDP.", 1_000_000)).is_ok());
+
+        let err = validate(&request("gpt-4o", "Summarize GDP.", 0)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid request: max_tokens must be positive"
+        );
+
+        let err = validate(&request("gpt-4o-2024-08-06", "Summarize GDP.", 20_000)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid request: max_tokens 20000 exceeds gpt-4o-2024-08-06's limit of 16384 \
+             output tokens"
+        );
+
+        let prompt = "GDP 27357.8 ".repeat(30_000);
+        let err = validate(&request("gpt-4o", &prompt, 4096)).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("plus max_tokens 4096 exceeds gpt-4o's context window of 128000 tokens"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::time::{Duration, Instant};
+
+use opentelemetry::KeyValue;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::capture::ContentCapture;
+use super::models::InvalidRequest;
+use super::{GenerateRequest, GenerateResponse};
+use crate::telemetry::metrics::{
+    GEN_AI_COST, GEN_AI_ERROR_COUNT, GEN_AI_OPERATION_DURATION, GEN_AI_TOKEN_USAGE,
+};
+
+/// One provider call (a single attempt, so retries and fallbacks each get
+/// their own).
+pub struct CallContext<'a> {
+    pub provider_name: &'a str,
+    pub request: &'a GenerateRequest,
+    /// The `gen_ai.chat` span the call runs in.
+    pub span: &'a Span,
+    pub started: Instant,
+}
+
+/// Hooks `LlmClient` runs around every provider call. Returning an error from
+/// `on_request` or `on_complete` fails the call as if the provider had, so an
+/// observer can act as a guardrail (e.g. content moderation); the error then
+/// goes through the usual retry and fallback handling. A call ends in exactly
+/// one of `on_success` and `on_error`, so record outcomes there.
+pub trait ProviderObserver: Send + Sync {
+    fn on_request(&self, _call: &CallContext<'_>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Providers do not stream yet, so this fires when the full response
+    /// arrives, just before `on_complete`.
+    fn on_first_token(&self, _call: &CallContext<'_>, _elapsed: Duration) {}
+
+    fn on_complete(
+        &self,
+        _call: &CallContext<'_>,
+        _response: &GenerateResponse,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Fires once every observer's `on_complete` has accepted the response.
+    fn on_success(&self, _call: &CallContext<'_>, _response: &GenerateResponse) {}
+
+    fn on_error(&self, _call: &CallContext<'_>, _error: &anyhow::Error) {}
+}
+
+/// The default observer: GenAI semconv span attributes, content events and
+/// metrics.
+pub struct TelemetryObserver {
+    capture: ContentCapture,
+}
+
+impl TelemetryObserver {
+    pub fn new(capture: ContentCapture) -> Self {
+        Self { capture }
+    }
+}
+
+impl ProviderObserver for TelemetryObserver {
+    fn on_request(&self, call: &CallContext<'_>) -> anyhow::Result<()> {
+        let req = call.request;
+        if let Some(prompt) = self.capture.input(&req.prompt) {
+            let mut user_event_attrs = vec![KeyValue::new("gen_ai.input.messages", prompt)];
+            if let Some(system) = self.capture.system(&req.system).filter(|s| !s.is_empty()) {
+                user_event_attrs.push(KeyValue::new("gen_ai.system_instructions", system));
+            }
+            call.span.add_event("gen_ai.user.message", user_event_attrs);
+        }
+        Ok(())
+    }
+
+    fn on_complete(&self, call: &CallContext<'_>, resp: &GenerateResponse) -> anyhow::Result<()> {
+        let span = call.span;
+        span.record("gen_ai.response.model", resp.model.as_str());
+        span.record("gen_ai.usage.input_tokens", resp.input_tokens as i64);
+        span.record("gen_ai.usage.output_tokens", resp.output_tokens as i64);
+        span.record("gen_ai.usage.cost_usd", resp.cost_usd);
+        if !resp.finish_reason.is_empty() {
+            span.record(
+                "gen_ai.response.finish_reasons",
+                resp.finish_reason.as_str(),
+            );
+        }
+
+        if let Some(content) = self.capture.output(&resp.content) {
+            span.add_event(
+                "gen_ai.assistant.message",
+                vec![KeyValue::new("gen_ai.output.messages", content)],
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The metrics wait for every observer to accept the response, so one
+    /// that is rejected counts as an error instead.
+    fn on_success(&self, call: &CallContext<'_>, resp: &GenerateResponse) {
+        let op_kv = KeyValue::new("gen_ai.operation.name", "chat");
+        let provider_kv = KeyValue::new("gen_ai.provider.name", call.provider_name.to_string());
+        let model_kv = KeyValue::new("gen_ai.request.model", resp.model.clone());
+
+        GEN_AI_TOKEN_USAGE.record(
+            f64::from(resp.input_tokens),
+            &[
+                KeyValue::new("gen_ai.token.type", "input"),
+                op_kv.clone(),
+                provider_kv.clone(),
+                model_kv.clone(),
+            ],
+        );
+        GEN_AI_TOKEN_USAGE.record(
+            f64::from(resp.output_tokens),
+            &[
+                KeyValue::new("gen_ai.token.type", "output"),
+                op_kv.clone(),
+                provider_kv.clone(),
+                model_kv.clone(),
+            ],
+        );
+        GEN_AI_OPERATION_DURATION.record(
+            call.started.elapsed().as_secs_f64(),
+            &[op_kv.clone(), provider_kv.clone(), model_kv.clone()],
+        );
+        GEN_AI_COST.add(resp.cost_usd, &[op_kv, provider_kv, model_kv]);
+    }
+
+    fn on_error(&self, call: &CallContext<'_>, err: &anyhow::Error) {
+        call.span.record("otel.status_code", "ERROR");
+        call.span.record("error.type", classify_error(err));
+
+        GEN_AI_ERROR_COUNT.add(
+            1,
+            &[
+                KeyValue::new("gen_ai.provider.name", call.provider_name.to_string()),
+                KeyValue::new("gen_ai.request.model", call.request.model.clone()),
+            ],
+        );
+    }
+}
+
+pub fn classify_error(err: &anyhow::Error) -> &'static str {
+    if err.is::<InvalidRequest>() {
+        return "invalid_request";
+    }
+    let msg = err.to_string().to_lowercase();
+    if msg.contains("rate limit") || msg.contains("429") {
+        "rate_limit"
+    } else if msg.contains("timeout") || msg.contains("timed out") || msg.contains("deadline") {
+        "timeout"
+    } else if msg.contains("401")
+        || msg.contains("403")
+        || msg.contains("auth")
+        || msg.contains("api key")
+    {
+        "auth_error"
+    } else if msg.contains("400") || msg.contains("422") || msg.contains("invalid") {
+        "invalid_request"
+    } else if msg.contains("500")
+        || msg.contains("502")
+        || msg.contains("503")
+        || msg.contains("server")
+    {
+        "server_error"
+    } else if msg.contains("connect")
+        || msg.contains("dns")
+        || msg.contains("network")
+        || msg.contains("reset")
+    {
+        "network_error"
+    } else {
+        "unknown_error"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_error_categories() {
+        let cases = vec![
+            ("rate limit exceeded", "rate_limit"),
+            ("status 429: too many requests", "rate_limit"),
+            ("context deadline exceeded: timeout", "timeout"),
+            ("request timed out", "timeout"),
+            ("401 unauthorized", "auth_error"),
+            ("403 forbidden", "auth_error"),
+            ("authentication failed", "auth_error"),
+            ("invalid api key", "auth_error"),
+            ("400 bad request", "invalid_request"),
+            ("422 unprocessable entity", "invalid_request"),
+            ("invalid model name", "invalid_request"),
+            ("500 internal server error", "server_error"),
+            ("502 bad gateway", "server_error"),
+            ("503 service unavailable", "server_error"),
+            ("connection refused", "network_error"),
+            ("dns resolution failed", "network_error"),
+            ("connection reset by peer", "network_error"),
+            ("something unexpected", "unknown_error"),
+        ];
+
+        for (msg, expected) in cases {
+            let err = anyhow::anyhow!("{}", msg);
+            assert_eq!(
+                classify_error(&err),
+                expected,
+                "classify_error({msg:?}) should be {expected:?}"
+            );
+        }
+
+        let err = anyhow::Error::from(InvalidRequest("a prompt of 4290 tokens".to_string()));
+        assert_eq!(classify_error(&err), "invalid_request");
+    }
+}
@@ -1,105 +1,453 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use async_openai::{
     Client,
     config::OpenAIConfig,
+    types::batches::{
+        Batch, BatchCompletionWindow, BatchEndpoint, BatchRequest, BatchRequestInput,
+        BatchRequestInputMethod, BatchRequestOutput, BatchStatus,
+    },
     types::chat::{
         ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
         ChatCompletionRequestSystemMessageContent, ChatCompletionRequestUserMessage,
         ChatCompletionRequestUserMessageContent, CreateChatCompletionRequest,
+        CreateChatCompletionResponse,
     },
+    types::files::{CreateFileRequest, FileInput, FilePurpose},
 };
 
-use super::{GenerateRequest, GenerateResponse, Provider};
+use super::{GenerateRequest, GenerateResponse, HttpSettings, Provider};
+
+/// Batch API requests are billed at half the interactive rate.
+pub const BATCH_COST_MULTIPLIER: f64 = 0.5;
 
 pub struct OpenAIProvider {
     client: Client<OpenAIConfig>,
+    http: HttpSettings,
     provider_name: String,
 }
 
 impl OpenAIProvider {
-    pub fn new(api_key: &str) -> Self {
+    pub fn new(api_key: &str, http: HttpSettings) -> anyhow::Result<Self> {
         let config = OpenAIConfig::new().with_api_key(api_key);
-        Self {
-            client: Client::with_config(config),
+        Ok(Self {
+            client: Client::with_config(config).with_http_client(http.client()?),
+            http,
             provider_name: "openai".to_string(),
-        }
+        })
+    }
+
+    pub fn new_google(api_key: &str, http: HttpSettings) -> anyhow::Result<Self> {
+        Self::with_base_url(
+            "google",
+            api_key,
+            "https://generativelanguage.googleapis.com/v1beta/openai",
+            http,
+        )
+    }
+
+    pub fn new_ollama(base_url: &str, http: HttpSettings) -> anyhow::Result<Self> {
+        Self::with_base_url("ollama", "ollama", &format!("{base_url}/v1"), http)
     }
 
-    pub fn new_google(api_key: &str) -> Self {
+    /// An OpenAI-compatible API at `base_url`, e.g. an Azure region or a
+    /// gateway, reported as `provider_name`.
+    pub fn with_base_url(
+        provider_name: &str,
+        api_key: &str,
+        base_url: &str,
+        http: HttpSettings,
+    ) -> anyhow::Result<Self> {
         let config = OpenAIConfig::new()
             .with_api_key(api_key)
-            .with_api_base("https://generativelanguage.googleapis.com/v1beta/openai");
-        Self {
-            client: Client::with_config(config),
-            provider_name: "google".to_string(),
+            .with_api_base(base_url);
+        Ok(Self {
+            client: Client::with_config(config).with_http_client(http.client()?),
+            http,
+            provider_name: provider_name.to_string(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for OpenAIProvider {
+    async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+        let response = self.client.chat().create(chat_request(req)).await?;
+        Ok(into_generate_response(response))
+    }
+
+    fn name(&self) -> &str {
+        &self.provider_name
+    }
+
+    async fn list_models(&self) -> anyhow::Result<Option<Vec<String>>> {
+        list_models(&self.client).await.map(Some)
+    }
+
+    fn http_settings(&self) -> Option<&HttpSettings> {
+        Some(&self.http)
+    }
+}
+
+/// Runs each request as a single-item OpenAI Batch API job: upload a JSONL
+/// file, create the batch, poll until it finishes and read the output file.
+/// Batches complete within 24 hours at half the interactive price, so this is
+/// only meant for reports nobody is waiting on.
+pub struct OpenAIBatchProvider {
+    client: Client<OpenAIConfig>,
+    http: HttpSettings,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl OpenAIBatchProvider {
+    /// `http` applies to each upload, poll and download, not to the batch
+    /// as a whole: that is what `timeout` is for.
+    pub fn new(
+        api_key: &str,
+        http: HttpSettings,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let config = OpenAIConfig::new().with_api_key(api_key);
+        Ok(Self {
+            client: Client::with_config(config).with_http_client(http.client()?),
+            http,
+            poll_interval,
+            timeout,
+        })
+    }
+
+    #[tracing::instrument(name = "openai.batch.submit", skip_all, fields(openai.batch.id))]
+    async fn submit(&self, req: &GenerateRequest) -> anyhow::Result<String> {
+        let custom_id = format!("{}-{}", req.stage, uuid::Uuid::new_v4());
+        let line = batch_input_line(&custom_id, req)?;
+
+        let file = self
+            .client
+            .files()
+            .create(CreateFileRequest {
+                file: FileInput::from_vec_u8(format!("{custom_id}.jsonl"), line.into_bytes()),
+                purpose: FilePurpose::Batch,
+                expires_after: None,
+            })
+            .await?;
+
+        let batch = self
+            .client
+            .batches()
+            .create(BatchRequest {
+                input_file_id: file.id,
+                endpoint: BatchEndpoint::V1ChatCompletions,
+                completion_window: BatchCompletionWindow::W24H,
+                metadata: Some(HashMap::from([(
+                    "report_stage".to_string(),
+                    serde_json::Value::String(req.stage.clone()),
+                )])),
+                output_expires_after: None,
+            })
+            .await?;
+
+        tracing::Span::current().record("openai.batch.id", batch.id.as_str());
+        tracing::info!(batch_id = %batch.id, stage = %req.stage, "OpenAI batch submitted");
+        Ok(batch.id)
+    }
+
+    #[tracing::instrument(name = "openai.batch.wait", skip(self), fields(openai.batch.status))]
+    async fn wait(&self, batch_id: &str) -> anyhow::Result<Batch> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let batch = self.client.batches().retrieve(batch_id).await?;
+            match batch.status {
+                BatchStatus::Completed => {
+                    tracing::Span::current().record("openai.batch.status", "completed");
+                    return Ok(batch);
+                }
+                BatchStatus::Failed
+                | BatchStatus::Expired
+                | BatchStatus::Cancelling
+                | BatchStatus::Cancelled => {
+                    let status = format!("{:?}", batch.status).to_lowercase();
+                    tracing::Span::current().record("openai.batch.status", status.as_str());
+                    anyhow::bail!("openai batch {batch_id} {status}: {}", batch_errors(&batch));
+                }
+                BatchStatus::Validating | BatchStatus::InProgress | BatchStatus::Finalizing => {}
+            }
+
+            if Instant::now() >= deadline {
+                // Nothing will collect the result, so stop paying for it.
+                let _ = self.client.batches().cancel(batch_id).await;
+                anyhow::bail!("openai batch {batch_id} timed out after {:?}", self.timeout);
+            }
+            tokio::time::sleep(self.poll_interval).await;
         }
     }
+}
 
-    pub fn new_ollama(base_url: &str) -> Self {
-        let config = OpenAIConfig::new()
-            .with_api_key("ollama")
-            .with_api_base(format!("{base_url}/v1"));
-        Self {
-            client: Client::with_config(config),
-            provider_name: "ollama".to_string(),
+/// Cancels the batch if the call is dropped before [`InFlightBatch::settle`],
+/// as it is when its report is cancelled: nothing would collect the result.
+struct InFlightBatch {
+    client: Client<OpenAIConfig>,
+    batch_id: Option<String>,
+}
+
+impl InFlightBatch {
+    fn settle(mut self) {
+        self.batch_id = None;
+    }
+}
+
+impl Drop for InFlightBatch {
+    fn drop(&mut self) {
+        if let Some(batch_id) = self.batch_id.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            let client = self.client.clone();
+            runtime.spawn(async move {
+                match client.batches().cancel(&batch_id).await {
+                    Ok(_) => tracing::info!(batch_id = %batch_id, "OpenAI batch cancelled"),
+                    Err(err) => {
+                        tracing::warn!(batch_id = %batch_id, error = %err, "Failed to cancel OpenAI batch")
+                    }
+                }
+            });
         }
     }
 }
 
 #[async_trait::async_trait]
-impl Provider for OpenAIProvider {
+impl Provider for OpenAIBatchProvider {
     async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
-        let messages = vec![
-            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
-                content: ChatCompletionRequestSystemMessageContent::Text(req.system.clone()),
-                name: None,
-            }),
-            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
-                content: ChatCompletionRequestUserMessageContent::Text(req.prompt.clone()),
-                name: None,
-            }),
-        ];
-
-        #[allow(deprecated)]
-        let request = CreateChatCompletionRequest {
-            model: req.model.clone(),
-            messages,
-            temperature: Some(req.temperature),
-            max_completion_tokens: Some(req.max_tokens),
-            ..Default::default()
+        let batch_id = self.submit(req).await?;
+        let in_flight = InFlightBatch {
+            client: self.client.clone(),
+            batch_id: Some(batch_id.clone()),
         };
+        let batch = self.wait(&batch_id).await;
+        in_flight.settle();
+        let batch = batch?;
 
-        let response = self.client.chat().create(request).await?;
+        let output_file_id = batch.output_file_id.ok_or_else(|| {
+            anyhow::anyhow!("openai batch {batch_id} completed without an output file")
+        })?;
+        let output = self.client.files().content(&output_file_id).await?;
 
-        let content = response
-            .choices
-            .first()
-            .and_then(|c| c.message.content.clone())
-            .unwrap_or_default();
+        parse_batch_output(&output)
+    }
 
-        let finish_reason = response
-            .choices
-            .first()
-            .and_then(|c| c.finish_reason)
-            .map(|r| format!("{r:?}").to_lowercase())
-            .unwrap_or_default();
+    fn name(&self) -> &str {
+        "openai"
+    }
 
-        let (input_tokens, output_tokens) = match &response.usage {
-            Some(usage) => (usage.prompt_tokens, usage.completion_tokens),
-            None => (0, 0),
-        };
+    fn cost_multiplier(&self) -> f64 {
+        BATCH_COST_MULTIPLIER
+    }
+
+    async fn list_models(&self) -> anyhow::Result<Option<Vec<String>>> {
+        list_models(&self.client).await.map(Some)
+    }
+
+    fn http_settings(&self) -> Option<&HttpSettings> {
+        Some(&self.http)
+    }
+}
+
+/// Only the IDs, so OpenAI-compatible APIs that leave out other `Model`
+/// fields still parse.
+#[derive(serde::Deserialize)]
+struct ModelList {
+    data: Vec<ModelId>,
+}
+
+#[derive(serde::Deserialize)]
+struct ModelId {
+    id: String,
+}
+
+async fn list_models(client: &Client<OpenAIConfig>) -> anyhow::Result<Vec<String>> {
+    let models: ModelList = client.models().list_byot().await?;
+    Ok(models.data.into_iter().map(|m| m.id).collect())
+}
+
+fn chat_request(req: &GenerateRequest) -> CreateChatCompletionRequest {
+    let messages = vec![
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            content: ChatCompletionRequestSystemMessageContent::Text(req.system.clone()),
+            name: None,
+        }),
+        ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+            content: ChatCompletionRequestUserMessageContent::Text(req.prompt.clone()),
+            name: None,
+        }),
+    ];
+
+    #[allow(deprecated)]
+    CreateChatCompletionRequest {
+        model: req.model.clone(),
+        messages,
+        temperature: Some(req.temperature),
+        max_completion_tokens: Some(req.max_tokens),
+        ..Default::default()
+    }
+}
+
+fn into_generate_response(response: CreateChatCompletionResponse) -> GenerateResponse {
+    let content = response
+        .choices
+        .first()
+        .and_then(|c| c.message.content.clone())
+        .unwrap_or_default();
+
+    let finish_reason = response
+        .choices
+        .first()
+        .and_then(|c| c.finish_reason)
+        .map(|r| format!("{r:?}").to_lowercase())
+        .unwrap_or_default();
+
+    let (input_tokens, output_tokens) = match &response.usage {
+        Some(usage) => (usage.prompt_tokens, usage.completion_tokens),
+        None => (0, 0),
+    };
 
-        Ok(GenerateResponse {
-            content,
-            model: response.model,
-            input_tokens,
-            output_tokens,
-            cost_usd: 0.0,
-            finish_reason,
-            provider: String::new(),
+    GenerateResponse {
+        content,
+        model: response.model,
+        input_tokens,
+        output_tokens,
+        cost_usd: 0.0,
+        finish_reason,
+        provider: String::new(),
+        span_context: None,
+    }
+}
+
+fn batch_input_line(custom_id: &str, req: &GenerateRequest) -> anyhow::Result<String> {
+    let input = BatchRequestInput {
+        custom_id: custom_id.to_string(),
+        method: BatchRequestInputMethod::POST,
+        url: BatchEndpoint::V1ChatCompletions,
+        body: Some(serde_json::to_value(chat_request(req))?),
+    };
+    Ok(serde_json::to_string(&input)? + "\n")
+}
+
+/// The output file holds one JSON line per request; a single-request batch
+/// has exactly one.
+fn parse_batch_output(output: &[u8]) -> anyhow::Result<GenerateResponse> {
+    let line = std::str::from_utf8(output)?
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("openai batch output file is empty"))?;
+    let output: BatchRequestOutput = serde_json::from_str(line)?;
+
+    if let Some(error) = output.error {
+        anyhow::bail!(
+            "openai batch request failed: {} {}",
+            error.code,
+            error.message
+        );
+    }
+    let response = output
+        .response
+        .ok_or_else(|| anyhow::anyhow!("openai batch output has no response"))?;
+    if response.status_code >= 400 {
+        anyhow::bail!(
+            "openai batch request failed with status {}: {}",
+            response.status_code,
+            response.body
+        );
+    }
+
+    Ok(into_generate_response(serde_json::from_value(
+        response.body,
+    )?))
+}
+
+fn batch_errors(batch: &Batch) -> String {
+    batch
+        .errors
+        .as_ref()
+        .map(|errors| {
+            errors
+                .data
+                .iter()
+                .map(|e| format!("{}: {}", e.code, e.message))
+                .collect::<Vec<_>>()
+                .join("; ")
         })
+        .unwrap_or_else(|| "no error details".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> GenerateRequest {
+        GenerateRequest {
+            model: "gpt-4.1-mini".to_string(),
+            system: "You are an analyst.".to_string(),
+            prompt: "Analyze UNRATE.".to_string(),
+            temperature: 0.3,
+            max_tokens: 2048,
+            stage: "analyze".to_string(),
+        }
     }
 
-    fn name(&self) -> &str {
-        &self.provider_name
+    #[test]
+    fn test_batch_input_line_targets_chat_completions() {
+        let line = batch_input_line("analyze-1", &request()).unwrap();
+        assert!(line.ends_with('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["custom_id"], "analyze-1");
+        assert_eq!(value["method"], "POST");
+        assert_eq!(value["url"], "/v1/chat/completions");
+        assert_eq!(value["body"]["model"], "gpt-4.1-mini");
+        assert_eq!(value["body"]["messages"][1]["content"], "Analyze UNRATE.");
+    }
+
+    #[test]
+    fn test_parse_batch_output() {
+        let line = serde_json::json!({
+            "id": "batch_req_1",
+            "custom_id": "analyze-1",
+            "response": {
+                "status_code": 200,
+                "request_id": "req_1",
+                "body": {
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1_700_000_000,
+                    "model": "gpt-4.1-mini-2025-04-14",
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "{\"trends\": []}" },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": { "prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150 }
+                }
+            },
+            "error": null
+        });
+
+        let resp = parse_batch_output(format!("{line}\n").as_bytes()).unwrap();
+        assert_eq!(resp.content, "{\"trends\": []}");
+        assert_eq!(resp.input_tokens, 120);
+        assert_eq!(resp.output_tokens, 30);
+        assert_eq!(resp.finish_reason, "stop");
+    }
+
+    #[test]
+    fn test_parse_batch_output_errors() {
+        let failed = serde_json::json!({
+            "id": "batch_req_1",
+            "custom_id": "analyze-1",
+            "response": { "status_code": 429, "request_id": "req_1", "body": {} },
+            "error": null
+        });
+        assert!(parse_batch_output(failed.to_string().as_bytes()).is_err());
+        assert!(parse_batch_output(b"").is_err());
     }
 }
@@ -2,6 +2,8 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::LazyLock;
 
+use super::models::{self, Price};
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct PriceEntry {
     #[allow(dead_code)]
@@ -40,14 +42,20 @@ pub static PRICING: LazyLock<HashMap<String, PriceEntry>> = LazyLock::new(|| {
     HashMap::new()
 });
 
+/// Prices from pricing.json, or else the model registry's default pricing.
 pub fn calculate_cost(model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
-    match PRICING.get(model) {
-        Some(entry) => {
-            (f64::from(input_tokens) * entry.input / 1_000_000.0)
-                + (f64::from(output_tokens) * entry.output / 1_000_000.0)
-        }
-        None => 0.0,
-    }
+    let price = match PRICING.get(model) {
+        Some(entry) => Price {
+            input: entry.input,
+            output: entry.output,
+        },
+        None => match models::lookup(model).and_then(|info| info.pricing) {
+            Some(price) => price,
+            None => return 0.0,
+        },
+    };
+    (f64::from(input_tokens) * price.input / 1_000_000.0)
+        + (f64::from(output_tokens) * price.output / 1_000_000.0)
 }
 
 pub static PROVIDER_SERVERS: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
@@ -88,6 +96,13 @@ mod tests {
         assert_eq!(cost, 0.0);
     }
 
+    #[test]
+    fn test_calculate_cost_falls_back_to_registry_pricing() {
+        assert!(!PRICING.contains_key("claude-haiku-4-5-20251001"));
+        let cost = calculate_cost("claude-haiku-4-5-20251001", 1_000_000, 1_000_000);
+        assert!((cost - 6.0).abs() < 1e-9, "{cost}");
+    }
+
     #[test]
     fn test_calculate_cost_zero_tokens() {
         let cost = calculate_cost("gpt-4.1", 0, 0);
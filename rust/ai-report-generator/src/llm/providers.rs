@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use super::http;
+use super::models::{self, ModelInfo};
+use super::{Endpoints, Provider};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Credentials {
+    Present,
+    Missing,
+    /// Local providers such as Ollama.
+    NotRequired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Probe {
+    pub reachable: bool,
+    pub latency_ms: u64,
+    pub checked_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What the API lists, when it has a listing endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_models: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub name: String,
+    /// `primary`, `fallback` or `batch`.
+    pub role: &'static str,
+    /// The `LLM_ENDPOINTS` or `FALLBACK_ENDPOINTS` name, or `default`.
+    pub endpoint: String,
+    /// The models this service sends to the provider.
+    pub models: Vec<String>,
+    /// The registry entries of `models`; unlisted models are left out.
+    pub capabilities: BTreeMap<String, &'static ModelInfo>,
+    pub credentials: Credentials,
+    /// Skipped (`null`) when credentials are missing.
+    pub probe: Option<Probe>,
+}
+
+struct Entry {
+    name: String,
+    role: &'static str,
+    endpoint: String,
+    models: Vec<String>,
+    credentials: Credentials,
+    provider: Arc<dyn Provider>,
+    probe: Mutex<Option<(Instant, Probe)>>,
+}
+
+/// The providers this service is configured to call, for `GET
+/// /api/providers`. Probes list the provider's models and are cached for
+/// `ttl`, so polling the endpoint does not turn into API traffic.
+pub struct ProviderDirectory {
+    entries: Vec<Entry>,
+    ttl: Duration,
+    timeout: Duration,
+}
+
+impl ProviderDirectory {
+    pub fn new(ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            entries: Vec::new(),
+            ttl,
+            timeout,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        role: &'static str,
+        provider: Arc<dyn Provider>,
+        credentials: Credentials,
+        models: Vec<String>,
+    ) {
+        self.push(role, "default", provider, credentials, models);
+    }
+
+    /// One entry per endpoint, so each is probed on its own.
+    pub fn add_endpoints(
+        &mut self,
+        role: &'static str,
+        endpoints: &Endpoints,
+        credentials: Credentials,
+        models: Vec<String>,
+    ) {
+        for endpoint in endpoints.iter() {
+            self.push(
+                role,
+                &endpoint.name,
+                endpoint.provider.clone(),
+                credentials,
+                models.clone(),
+            );
+        }
+    }
+
+    fn push(
+        &mut self,
+        role: &'static str,
+        endpoint: &str,
+        provider: Arc<dyn Provider>,
+        credentials: Credentials,
+        models: Vec<String>,
+    ) {
+        self.entries.push(Entry {
+            name: provider.name().to_string(),
+            role,
+            endpoint: endpoint.to_string(),
+            models,
+            credentials,
+            provider,
+            probe: Mutex::new(None),
+        });
+    }
+
+    pub async fn status(&self) -> Vec<ProviderStatus> {
+        futures::future::join_all(self.entries.iter().map(|entry| self.entry_status(entry))).await
+    }
+
+    async fn entry_status(&self, entry: &Entry) -> ProviderStatus {
+        let probe = if entry.credentials == Credentials::Missing {
+            None
+        } else {
+            // Held across the probe so concurrent requests share one call.
+            let mut cached = entry.probe.lock().await;
+            match cached.as_ref() {
+                Some((at, probe)) if at.elapsed() < self.ttl => Some(probe.clone()),
+                _ => {
+                    let probe = self.probe(entry.provider.as_ref()).await;
+                    *cached = Some((Instant::now(), probe.clone()));
+                    Some(probe)
+                }
+            }
+        };
+        ProviderStatus {
+            name: entry.name.clone(),
+            role: entry.role,
+            endpoint: entry.endpoint.clone(),
+            models: entry.models.clone(),
+            capabilities: entry
+                .models
+                .iter()
+                .filter_map(|model| Some((model.clone(), models::lookup(model)?)))
+                .collect(),
+            credentials: entry.credentials,
+            probe,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "provider_probe",
+        skip_all,
+        fields(gen_ai.provider.name = provider.name())
+    )]
+    async fn probe(&self, provider: &dyn Provider) -> Probe {
+        let started = Instant::now();
+        let result = tokio::time::timeout(self.timeout, provider.list_models()).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (available_models, error) = match result {
+            Ok(Ok(models)) => (
+                models.map(|mut m| {
+                    m.sort();
+                    m
+                }),
+                None,
+            ),
+            Ok(Err(err)) => (
+                None,
+                Some(http::explain_error(provider.http_settings(), err).to_string()),
+            ),
+            Err(_) => (
+                None,
+                Some(format!("timed out after {}s", self.timeout.as_secs())),
+            ),
+        };
+        if let Some(error) = &error {
+            tracing::warn!(provider = provider.name(), %error, "Provider probe failed");
+        }
+        Probe {
+            reachable: error.is_none(),
+            latency_ms,
+            checked_at: Utc::now(),
+            error,
+            available_models,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::llm::{GenerateRequest, GenerateResponse};
+
+    #[derive(Default)]
+    struct StubProvider {
+        fail: bool,
+        probes: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for StubProvider {
+        async fn generate(&self, _req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            unimplemented!()
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+
+        async fn list_models(&self) -> anyhow::Result<Option<Vec<String>>> {
+            self.probes.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                anyhow::bail!("401 invalid api key");
+            }
+            Ok(Some(vec!["model-b".to_string(), "model-a".to_string()]))
+        }
+    }
+
+    fn directory(provider: Arc<StubProvider>, credentials: Credentials) -> ProviderDirectory {
+        let mut directory = ProviderDirectory::new(Duration::from_secs(60), Duration::from_secs(1));
+        directory.add(
+            "primary",
+            provider,
+            credentials,
+            vec!["model-a".to_string()],
+        );
+        directory
+    }
+
+    #[tokio::test]
+    async fn test_probe_is_cached() {
+        let provider = Arc::new(StubProvider::default());
+        let directory = directory(provider.clone(), Credentials::Present);
+
+        let first = directory.status().await;
+        let second = directory.status().await;
+
+        assert_eq!(provider.probes.load(Ordering::SeqCst), 1);
+        let probe = first[0].probe.as_ref().unwrap();
+        assert!(probe.reachable);
+        assert_eq!(
+            probe.available_models.as_deref(),
+            Some(&["model-a".to_string(), "model-b".to_string()][..])
+        );
+        assert_eq!(
+            second[0].probe.as_ref().unwrap().checked_at,
+            probe.checked_at
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_lists_model_capabilities() {
+        let mut directory = ProviderDirectory::new(Duration::from_secs(60), Duration::from_secs(1));
+        directory.add(
+            "primary",
+            Arc::new(StubProvider::default()),
+            Credentials::Present,
+            vec!["gpt-4.1-mini".to_string(), "model-a".to_string()],
+        );
+
+        let status = directory.status().await;
+        let capabilities = &status[0].capabilities;
+        assert_eq!(capabilities.len(), 1);
+        assert_eq!(capabilities["gpt-4.1-mini"].context_window, 1_047_576);
+        assert!(capabilities["gpt-4.1-mini"].supports_json_mode);
+    }
+
+    #[tokio::test]
+    async fn test_failed_and_skipped_probes() {
+        let failing = Arc::new(StubProvider {
+            fail: true,
+            ..Default::default()
+        });
+        let status = directory(failing, Credentials::Present).status().await;
+        let probe = status[0].probe.as_ref().unwrap();
+        assert!(!probe.reachable);
+        assert!(probe.error.as_deref().unwrap().contains("401"));
+
+        let unconfigured = Arc::new(StubProvider::default());
+        let status = directory(unconfigured.clone(), Credentials::Missing)
+            .status()
+            .await;
+        assert!(status[0].probe.is_none());
+        assert_eq!(unconfigured.probes.load(Ordering::SeqCst), 0);
+    }
+}
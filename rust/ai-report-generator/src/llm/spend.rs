@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+tokio::task_local! {
+    static CURRENT: Arc<Spend>;
+}
+
+/// The cost of the LLM calls made within [`Spend::scope`], however the work
+/// in it ends, so a report cancelled midway still accounts for what it had
+/// spent by then.
+#[derive(Debug, Default)]
+pub struct Spend {
+    /// An `f64`, as bits.
+    cost_usd: AtomicU64,
+}
+
+impl Spend {
+    pub async fn scope<F: Future>(self: Arc<Self>, work: F) -> F::Output {
+        CURRENT.scope(self, work).await
+    }
+
+    /// Adds to the spend of the current task's scope, if it is in one.
+    pub fn record(cost_usd: f64) {
+        let _ = CURRENT.try_with(|spend| spend.add(cost_usd));
+    }
+
+    pub fn total_usd(&self) -> f64 {
+        f64::from_bits(self.cost_usd.load(Ordering::Relaxed))
+    }
+
+    fn add(&self, cost_usd: f64) {
+        let _ = self
+            .cost_usd
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + cost_usd).to_bits())
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_records_only_within_scope() {
+        let spend = Arc::new(Spend::default());
+        Spend::record(1.0);
+
+        spend
+            .clone()
+            .scope(async {
+                Spend::record(0.25);
+                Spend::record(0.5);
+            })
+            .await;
+        Spend::record(1.0);
+
+        assert_eq!(spend.total_usd(), 0.75);
+    }
+}
@@ -0,0 +1,35 @@
+/// Estimates `text`'s token count for BPE tokenizers like OpenAI's and
+/// Anthropic's: a token per started run of 4 letters or 3 digits and one per
+/// other symbol. Errs high on the numeric text the prompts are mostly made of.
+pub fn count_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let (mut letters, mut digits) = (0, 0);
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            letters += 1;
+            tokens += usize::from(letters % 4 == 1);
+            digits = 0;
+        } else if c.is_ascii_digit() {
+            digits += 1;
+            tokens += usize::from(digits % 3 == 1);
+            letters = 0;
+        } else {
+            tokens += usize::from(!c.is_whitespace());
+            (letters, digits) = (0, 0);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("GDP rose"), 2);
+        assert_eq!(count_tokens("2023-12-01: 3.70"), 10);
+        assert_eq!(count_tokens("Arbeitslosenquote"), 5);
+    }
+}
@@ -1,66 +1,119 @@
 use std::net::SocketAddr;
+use std::path::Path;
 use std::time::Duration;
 
 use axum::Router;
 use axum::http::{Request, Response, StatusCode};
 use axum::routing::{get, post};
-use opentelemetry::KeyValue;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
 use sqlx::PgPool;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tower_http::{
     cors::{Any, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     timeout::TimeoutLayer,
     trace::{MakeSpan, OnResponse, TraceLayer},
 };
-use tracing::Span;
+use tracing::{Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 mod config;
 mod db;
+mod deadline;
 mod error;
+mod explain;
 mod llm;
 mod pipeline;
+mod reload;
 mod routes;
+mod startup;
+mod synthetic;
 mod telemetry;
+mod tls;
 
 use std::sync::Arc;
 
 use config::Config;
-use telemetry::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL, init_telemetry};
+use synthetic::SyntheticProbe;
+use telemetry::{HttpMetricsLayer, init_telemetry};
+use tls::TlsListener;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub config: Config,
-    pub llm_client: Arc<llm::LlmClient>,
+    pub llm_client: Arc<reload::Reloadable<llm::LlmClient>>,
+    pub admission: Arc<pipeline::AdmissionController>,
+    pub guardrails: Arc<pipeline::Guardrails>,
+    pub templates: Arc<pipeline::TemplateRegistry>,
+    pub providers: Arc<reload::Reloadable<llm::ProviderDirectory>>,
+    pub batch: Option<pipeline::BatchPipeline>,
+    pub report_watch: Arc<pipeline::ReportWatch>,
+    pub running_reports: Arc<pipeline::RunningReports>,
+    /// `None` when `COST_ANOMALY_WINDOW_SECS` is 0.
+    pub cost_anomaly: Option<Arc<llm::CostAnomalyDetector>>,
+    pub readiness: Arc<startup::Readiness>,
+}
+
+const X_REQUEST_ID: &str = "x-request-id";
+
+struct HeaderExtractor<'a>(&'a axum::http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
 }
 
 #[derive(Clone)]
-struct HttpMakeSpan;
+struct HttpMakeSpan {
+    scheme: &'static str,
+}
 
 impl<B> MakeSpan<B> for HttpMakeSpan {
     fn make_span(&mut self, request: &Request<B>) -> Span {
         let method = request.method().as_str();
         let path = request.uri().path();
+        let request_id = request
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
 
-        tracing::info_span!(
+        let span = tracing::info_span!(
             "HTTP request",
             otel.name = %format!("{} {}", method, path),
+            otel.kind = "server",
             http.method = %method,
             http.route = %path,
             http.target = %request.uri(),
-            http.scheme = "http",
+            http.scheme = self.scheme,
             http.flavor = ?request.version(),
             http.user_agent = request.headers()
                 .get("user-agent")
                 .and_then(|v| v.to_str().ok())
                 .unwrap_or(""),
+            http.request_id = %request_id,
             http.response.status_code = tracing::field::Empty,
             otel.status_code = tracing::field::Empty,
-        )
+        );
+
+        // Join the caller's trace when it sent a W3C traceparent.
+        let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+        let _ = span.set_parent(parent);
+
+        span
     }
 }
 
+/// The span's status and the request's log line. The metrics are
+/// recorded by `HttpMetricsLayer`, which sees the request's method.
 #[derive(Clone)]
 struct HttpOnResponse;
 
@@ -76,28 +129,9 @@ impl<B> OnResponse<B> for HttpOnResponse {
             span.record("otel.status_code", "OK");
         }
 
-        let latency_ms = latency.as_secs_f64() * 1000.0;
-        let status_class = format!("{}xx", status / 100);
-
-        HTTP_REQUESTS_TOTAL.add(
-            1,
-            &[
-                KeyValue::new("http.status_code", status.to_string()),
-                KeyValue::new("http.status_class", status_class.clone()),
-            ],
-        );
-
-        HTTP_REQUEST_DURATION.record(
-            latency_ms,
-            &[
-                KeyValue::new("http.status_code", status.to_string()),
-                KeyValue::new("http.status_class", status_class),
-            ],
-        );
-
         tracing::info!(
             http.response.status_code = status,
-            latency_ms = latency_ms,
+            latency_ms = latency.as_secs_f64() * 1000.0,
             "finished processing request"
         );
     }
@@ -105,81 +139,231 @@ impl<B> OnResponse<B> for HttpOnResponse {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config = Config::from_env();
+    let config = Config::load().await?;
 
     let telemetry_guard = init_telemetry(&config)?;
 
     tracing::info!(
         port = config.port,
         environment = %config.environment,
+        secrets_provider = config.secrets.provider_name().unwrap_or("none"),
         "Starting ai-report-generator"
     );
 
-    let pool = db::create_pool(&config.database_url).await?;
-
-    let primary: Arc<dyn llm::Provider> = match config.llm_provider.as_str() {
-        "anthropic" => Arc::new(llm::anthropic::AnthropicProvider::new(
-            config.anthropic_api_key.as_deref().unwrap_or(""),
-        )),
-        "google" => Arc::new(llm::openai::OpenAIProvider::new_google(
-            config.google_api_key.as_deref().unwrap_or(""),
-        )),
-        "ollama" => Arc::new(llm::openai::OpenAIProvider::new_ollama(
-            &config.ollama_base_url,
-        )),
-        _ => Arc::new(llm::openai::OpenAIProvider::new(
-            config.openai_api_key.as_deref().unwrap_or(""),
-        )),
-    };
-
-    let fallback: Option<Arc<dyn llm::Provider>> = match config.fallback_provider.as_str() {
-        "anthropic" => Some(Arc::new(llm::anthropic::AnthropicProvider::new(
-            config.anthropic_api_key.as_deref().unwrap_or(""),
-        ))),
-        "openai" => Some(Arc::new(llm::openai::OpenAIProvider::new(
-            config.openai_api_key.as_deref().unwrap_or(""),
-        ))),
-        "google" => Some(Arc::new(llm::openai::OpenAIProvider::new_google(
-            config.google_api_key.as_deref().unwrap_or(""),
-        ))),
-        "ollama" => Some(Arc::new(llm::openai::OpenAIProvider::new_ollama(
-            &config.ollama_base_url,
-        ))),
-        _ => None,
-    };
+    // Each startup phase is a span under `startup`, which ends with the
+    // warm-up.
+    let startup = tracing::info_span!("startup");
+    let readiness = Arc::new(startup::Readiness::default());
+    let pool = readiness
+        .phase("db_connect", db::create_pool(&config.database_url))
+        .instrument(startup.clone())
+        .await?;
 
     tracing::info!(
         primary_provider = %config.llm_provider,
         fallback_provider = %config.fallback_provider,
+        gen_ai_capture_content = %config.gen_ai_capture_content,
+        guardrail_mode = %config.guardrail_mode,
+        prompt_data_minimization = %config.prompt_data_minimization,
+        batch_mode = config.openai_api_key.is_some(),
         "LLM client initialized"
     );
 
-    let llm_client = Arc::new(llm::LlmClient {
-        primary,
-        fallback,
-        primary_provider: config.llm_provider.clone(),
-        fallback_provider: config.fallback_provider.clone(),
-        fallback_model: config.fallback_model.clone(),
+    let capture = llm::ContentCapture::new(
+        config.gen_ai_capture_content,
+        config.gen_ai_capture_max_input_chars,
+        config.gen_ai_capture_max_system_chars,
+        config.gen_ai_capture_max_output_chars,
+        &config.gen_ai_capture_scrub_patterns,
+    )
+    .map_err(anyhow::Error::msg)?;
+    let telemetry_observer: Arc<dyn llm::ProviderObserver> =
+        Arc::new(llm::TelemetryObserver::new(capture));
+    let mut observers: Vec<Arc<dyn llm::ProviderObserver>> = vec![
+        telemetry_observer.clone(),
+        Arc::new(llm::AuditObserver::spawn(
+            pool.clone(),
+            config.llm_audit_queue_size,
+        )),
+    ];
+    let cost_anomaly = if config.cost_anomaly.window.is_zero() {
+        None
+    } else {
+        let detector = Arc::new(llm::CostAnomalyDetector::new(
+            config.cost_anomaly.clone(),
+            &config.otel_service_name,
+        )?);
+        detector.clone().spawn();
+        observers.push(detector.clone());
+        Some(detector)
+    };
+    llm::audit::spawn_retention(pool.clone(), config.llm_audit_retention_days);
+    db::partitions::spawn_maintenance(pool.clone());
+    db::indicator_summaries::spawn_refresh(pool.clone(), config.indicator_summary_refresh_secs);
+
+    let guardrails = Arc::new(pipeline::Guardrails::new(
+        config.guardrail_mode,
+        &config.guardrail_banned_phrases,
+    ));
+
+    let clients = reload::LlmClients::build(&config, &observers)?;
+
+    let report_watch = Arc::new(pipeline::ReportWatch::default());
+    let running_reports = Arc::new(pipeline::RunningReports::default());
+    pipeline::heartbeat::spawn_janitor(
+        pool.clone(),
+        report_watch.clone(),
+        config.report_stall_secs,
+        Duration::from_secs(config.report_heartbeat_secs),
+    );
+
+    let batch = clients.batch.map(|llm_client| pipeline::BatchPipeline {
+        pool: pool.clone(),
+        llm_client: Arc::new(reload::Reloadable::new(llm_client)),
+        guardrails: guardrails.clone(),
+        model_capable: config.openai_batch_model_capable.clone(),
+        model_fast: config.openai_batch_model_fast.clone(),
+        max_prompt_tokens: config.max_prompt_tokens,
+        minimization: config.prompt_data_minimization,
+        // Batch jobs are bounded by OPENAI_BATCH_TIMEOUT_SECS instead.
+        timeouts: pipeline::StageTimeouts {
+            analyze: Duration::ZERO,
+            generate: Duration::ZERO,
+            ..config.stage_timeouts()
+        },
+        heartbeat: Duration::from_secs(config.report_heartbeat_secs),
+        watch: report_watch.clone(),
+        running: running_reports.clone(),
+        admission: Arc::new(pipeline::AdmissionController::new(
+            config.openai_batch_max_reports,
+            0,
+            config.report_retry_after_secs,
+        )),
     });
 
+    let llm_client = &clients.interactive;
+    let hosts = startup::provider_hosts(
+        std::iter::once((llm_client.primary_provider.as_str(), &llm_client.primary)).chain(
+            llm_client
+                .fallback
+                .as_ref()
+                .map(|f| (llm_client.fallback_provider.as_str(), f)),
+        ),
+        &config.ollama_base_url,
+    );
+
+    let templates = Arc::new(
+        readiness
+            .phase("templates", async {
+                pipeline::TemplateRegistry::load(config.report_types_file.as_deref().map(Path::new))
+            })
+            .instrument(startup.clone())
+            .await
+            .map_err(anyhow::Error::msg)?,
+    );
+
+    let admission = Arc::new(pipeline::AdmissionController::new(
+        config.max_concurrent_reports,
+        config.report_queue_size,
+        config.report_retry_after_secs,
+    ));
+
+    let warm_up = {
+        let (readiness, pool) = (readiness.clone(), pool.clone());
+        async move { startup::warm_up(&readiness, startup, &pool, &hosts).await }
+    };
     let state = AppState {
         pool,
         config: config.clone(),
-        llm_client,
+        llm_client: Arc::new(reload::Reloadable::new(clients.interactive)),
+        admission,
+        guardrails,
+        templates,
+        providers: Arc::new(reload::Reloadable::new(clients.providers)),
+        batch,
+        report_watch,
+        running_reports,
+        cost_anomaly,
+        readiness,
     };
 
+    let scheme = if config.tls_enabled() {
+        "https"
+    } else {
+        "http"
+    };
     let app = Router::new()
         .route("/api/health", get(routes::health::health))
+        .route("/readyz", get(routes::health::readyz))
         .route("/api/reports", post(routes::reports::create_report))
         .route("/api/reports", get(routes::reports::list_reports))
         .route("/api/reports/{id}", get(routes::reports::get_report))
+        .route(
+            "/api/reports/{id}/events",
+            get(routes::reports::list_report_events),
+        )
+        .route(
+            "/api/reports/{id}/explanation",
+            get(routes::reports::get_report_explanation),
+        )
+        .route(
+            "/api/reports/{id}/cancel",
+            post(routes::reports::cancel_report),
+        )
+        .route(
+            "/api/reports/{id}/resume",
+            post(routes::reports::resume_report),
+        )
+        .route("/api/summaries", post(routes::summaries::create_summary))
         .route("/api/indicators", get(routes::indicators::list_indicators))
+        .route(
+            "/api/indicator-groups",
+            get(routes::indicator_groups::list_indicator_groups)
+                .post(routes::indicator_groups::create_indicator_group),
+        )
+        .route(
+            "/api/indicator-groups/{id}",
+            get(routes::indicator_groups::get_indicator_group)
+                .put(routes::indicator_groups::update_indicator_group)
+                .delete(routes::indicator_groups::delete_indicator_group),
+        )
+        .route(
+            "/api/report-types",
+            get(routes::report_types::list_report_types),
+        )
+        .route("/api/providers", get(routes::providers::list_providers))
         .route("/api/test/llm-error", post(routes::test::trigger_llm_error))
+        .route(
+            "/api/test/slow-query",
+            post(routes::test::trigger_slow_query),
+        )
+        .route("/api/test/fallback", post(routes::test::trigger_fallback))
+        .route("/api/test/retries", post(routes::test::trigger_retries))
+        .route(
+            "/api/test/partial-data",
+            post(routes::test::trigger_partial_data),
+        )
+        .route(
+            "/api/test/pipeline-error",
+            post(routes::test::trigger_pipeline_error),
+        )
+        .route("/api/admin/config", get(routes::admin::get_config))
+        .route("/api/admin/reload", post(routes::admin::reload))
+        .route("/api/usage/daily", get(routes::usage::daily))
+        .route("/api/usage/by-model", get(routes::usage::by_model))
+        .route("/api/usage/rate", get(routes::usage::rate))
+        .layer(axum::middleware::from_fn(deadline::propagate_deadline))
+        .layer(PropagateRequestIdLayer::new(X_REQUEST_ID.parse().unwrap()))
+        .layer(HttpMetricsLayer)
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(HttpMakeSpan)
+                .make_span_with(HttpMakeSpan { scheme })
                 .on_response(HttpOnResponse),
         )
+        .layer(SetRequestIdLayer::new(
+            X_REQUEST_ID.parse().unwrap(),
+            MakeRequestUuid,
+        ))
         .layer(TimeoutLayer::with_status_code(
             StatusCode::REQUEST_TIMEOUT,
             Duration::from_secs(300),
@@ -190,16 +374,45 @@ async fn main() -> anyhow::Result<()> {
                 .allow_methods(Any)
                 .allow_headers(Any),
         )
-        .with_state(state);
+        .with_state(state.clone());
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     let listener = TcpListener::bind(addr).await?;
 
-    tracing::info!(%addr, "Server listening");
+    tracing::info!(%addr, scheme, "Server listening");
+    tokio::spawn(warm_up);
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(state));
+
+    if config.synthetic_check_interval_secs > 0 {
+        SyntheticProbe::new(
+            &format!("{scheme}://127.0.0.1:{}", config.port),
+            Duration::from_secs(config.synthetic_check_timeout_secs),
+            vec![telemetry_observer],
+        )?
+        .spawn(Duration::from_secs(config.synthetic_check_interval_secs));
+    }
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    if config.tls_enabled() {
+        let tls_config = tls::load_config(&config.tls_cert_path, &config.tls_key_path)?;
+        if config.http_redirect_port != 0 {
+            let redirect_addr = SocketAddr::from(([0, 0, 0, 0], config.http_redirect_port));
+            let redirect_listener = TcpListener::bind(redirect_addr).await?;
+            tracing::info!(addr = %redirect_addr, "Redirecting HTTP to HTTPS");
+            tokio::spawn(
+                axum::serve(redirect_listener, tls::redirect_app(config.port))
+                    .with_graceful_shutdown(shutdown_signal())
+                    .into_future(),
+            );
+        }
+        axum::serve(TlsListener::new(listener, tls_config)?, app)
+            .with_graceful_shutdown(shutdown_signal())
+            .await?;
+    } else {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown_signal())
+            .await?;
+    }
 
     tracing::info!("Server shutdown complete");
     telemetry_guard.shutdown();
@@ -232,3 +445,108 @@ async fn shutdown_signal() {
 
     tracing::info!("Shutdown signal received");
 }
+
+/// Reloads the LLM clients on every SIGHUP, like `POST /api/admin/reload`.
+#[cfg(unix)]
+async fn reload_on_hangup(state: AppState) {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("Failed to install SIGHUP handler");
+    while hangup.recv().await.is_some() {
+        tracing::info!("SIGHUP received, reloading LLM clients");
+        // `reload` logs its outcome.
+        let _ = reload::reload(&state).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::{SpanId, SpanKind, Status};
+    use tower::ServiceExt;
+
+    use super::*;
+    use telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    fn app() -> Router {
+        Router::new()
+            .route("/api/teapot", get(|| async { StatusCode::IM_A_TEAPOT }))
+            .route(
+                "/api/unavailable",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            )
+            .layer(PropagateRequestIdLayer::new(X_REQUEST_ID.parse().unwrap()))
+            .layer(HttpMetricsLayer)
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(HttpMakeSpan { scheme: "http" })
+                    .on_response(HttpOnResponse),
+            )
+            .layer(SetRequestIdLayer::new(
+                X_REQUEST_ID.parse().unwrap(),
+                MakeRequestUuid,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_http_span_joins_the_caller_trace() {
+        let spans = SpanCapture::start();
+        let request = Request::get("/api/teapot")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .header("user-agent", "report-client/1.0")
+            .header(X_REQUEST_ID, "req-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(response.headers()[X_REQUEST_ID], "req-123");
+        drop(response);
+
+        let span = spans.span("GET /api/teapot");
+        assert_eq!(
+            span.span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(span.parent_span_id.to_string(), "00f067aa0ba902b7");
+        assert_eq!(span.span_kind, SpanKind::Server);
+        assert_eq!(span.status, Status::Ok);
+        assert_attributes(
+            &span,
+            &[
+                KeyValue::new("http.method", "GET"),
+                KeyValue::new("http.route", "/api/teapot"),
+                KeyValue::new("http.user_agent", "report-client/1.0"),
+                KeyValue::new("http.request_id", "req-123"),
+                KeyValue::new("http.response.status_code", 418),
+            ],
+        );
+        let attributes = [
+            KeyValue::new("http.method", "GET"),
+            KeyValue::new("http.status_code", "418"),
+            KeyValue::new("http.status_class", "4xx"),
+        ];
+        assert_metric_recorded("http.requests.total", &attributes);
+        assert_metric_recorded("http.request.duration", &attributes);
+    }
+
+    #[tokio::test]
+    async fn test_http_span_marks_server_errors() {
+        let spans = SpanCapture::start();
+        let request = Request::get("/api/unavailable")
+            .body(Body::empty())
+            .unwrap();
+        app().oneshot(request).await.unwrap();
+
+        let span = spans.span("GET /api/unavailable");
+        assert_eq!(span.parent_span_id, SpanId::INVALID);
+        assert!(matches!(span.status, Status::Error { .. }));
+        assert_attributes(&span, &[KeyValue::new("http.response.status_code", 503)]);
+        assert_metric_recorded(
+            "http.requests.total",
+            &[KeyValue::new("http.status_code", "503")],
+        );
+    }
+}
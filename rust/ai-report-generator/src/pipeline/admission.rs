@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::AppError;
+use crate::telemetry::metrics::{REPORT_QUEUE_DEPTH, REPORT_QUEUE_WAIT};
+
+/// Caps how many report pipelines run at once. Requests beyond the cap wait
+/// in a bounded queue; once that is full they are turned away with 429.
+pub struct AdmissionController {
+    permits: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    max_queued: usize,
+    retry_after_secs: u64,
+}
+
+impl AdmissionController {
+    pub fn new(max_concurrent: usize, max_queued: usize, retry_after_secs: u64) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            waiting: AtomicUsize::new(0),
+            max_queued,
+            retry_after_secs,
+        }
+    }
+
+    /// A slot if one is free now, without queueing: for work that holds it
+    /// far longer than a request would wait.
+    pub fn try_admit(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        self.permits.clone().try_acquire_owned().map_err(|_| {
+            tracing::warn!("No report slot free, rejecting request");
+            AppError::Overloaded {
+                retry_after_secs: self.retry_after_secs,
+            }
+        })
+    }
+
+    /// The returned permit frees the slot for the next queued request when
+    /// dropped.
+    pub async fn admit(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            REPORT_QUEUE_WAIT.record(0.0, &[]);
+            return Ok(permit);
+        }
+
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            tracing::warn!(
+                max_queued = self.max_queued,
+                "Report queue full, rejecting request"
+            );
+            return Err(AppError::Overloaded {
+                retry_after_secs: self.retry_after_secs,
+            });
+        }
+
+        // Leaves the queue even if the client disconnects while waiting.
+        let _queued = Queued(&self.waiting);
+        REPORT_QUEUE_DEPTH.add(1, &[]);
+        let start = Instant::now();
+
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| AppError::Internal(format!("report queue closed: {}", e)))?;
+
+        REPORT_QUEUE_WAIT.record(start.elapsed().as_secs_f64(), &[]);
+        Ok(permit)
+    }
+}
+
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+        REPORT_QUEUE_DEPTH.add(-1, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_excess_requests_queue_then_get_rejected() {
+        let controller = Arc::new(AdmissionController::new(1, 1, 7));
+
+        let running = controller.admit().await.expect("first request runs");
+
+        let queued = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.admit().await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(controller.waiting.load(Ordering::SeqCst), 1);
+
+        match controller.admit().await {
+            Err(AppError::Overloaded { retry_after_secs }) => assert_eq!(retry_after_secs, 7),
+            other => panic!("expected Overloaded, got {:?}", other.map(|_| ())),
+        }
+
+        drop(running);
+        queued
+            .await
+            .unwrap()
+            .expect("queued request runs once a slot frees");
+        assert_eq!(controller.waiting.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_try_admit_does_not_queue() {
+        let controller = AdmissionController::new(1, 4, 3);
+
+        let running = controller.try_admit().expect("first request runs");
+        match controller.try_admit() {
+            Err(AppError::Overloaded { retry_after_secs }) => assert_eq!(retry_after_secs, 3),
+            other => panic!("expected Overloaded, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(controller.waiting.load(Ordering::SeqCst), 0);
+
+        drop(running);
+        let _running = controller.try_admit().expect("slot is free again");
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_wait_leaves_queue() {
+        let controller = AdmissionController::new(1, 1, 1);
+        let _running = controller.admit().await.unwrap();
+
+        let timed_out = tokio::time::timeout(Duration::from_millis(20), controller.admit()).await;
+        assert!(timed_out.is_err());
+        assert_eq!(controller.waiting.load(Ordering::SeqCst), 0);
+    }
+}
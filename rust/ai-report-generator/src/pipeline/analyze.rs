@@ -1,10 +1,20 @@
+use std::time::Duration;
+
 use chrono::Datelike;
+use opentelemetry::trace::SpanContext;
 use serde::{Deserialize, Serialize};
 
 use crate::db::data_points::IndicatorData;
-use crate::error::AppError;
+use crate::error::{AppError, RecordErr};
 use crate::llm::{GenerateRequest, LlmClient};
 
+use super::context::{self, Block};
+use super::timeout;
+
+pub const SYSTEM_PROMPT: &str = include_str!("../../data/schema-context.txt");
+
+const MAX_TOKENS: u32 = 2048;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResult {
     pub trends: Vec<Trend>,
@@ -14,6 +24,8 @@ pub struct AnalysisResult {
     pub output_tokens: u32,
     pub cost_usd: f64,
     pub provider: String,
+    #[serde(skip)]
+    pub chat_span: Option<SpanContext>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,90 +37,117 @@ pub struct Trend {
 
 #[tracing::instrument(
     name = "pipeline_stage analyze",
-    skip(llm_client, data),
+    skip(llm_client, data, max_prompt_tokens, timeout),
     fields(
         pipeline.stage = "analyze",
         analysis.trends_found,
         analysis.key_findings,
+        prompt.estimated_tokens,
+        prompt.dropped_blocks,
+        error.type,
     )
 )]
 pub async fn analyze(
     llm_client: &LlmClient,
     model: &str,
     data: &[IndicatorData],
+    max_prompt_tokens: usize,
+    timeout: Duration,
 ) -> Result<AnalysisResult, AppError> {
-    let mut data_summary = String::new();
-    for ind in data {
-        data_summary.push_str(&format!("\n## {} ({})\n", ind.name, ind.code));
-        data_summary.push_str(&format!(
-            "Unit: {}, Frequency: {}\n",
-            ind.unit, ind.frequency
-        ));
+    timeout::within("analyze", timeout, async move {
+        let system = SYSTEM_PROMPT.to_string();
+        let instructions = "Analyze the following economic data and identify trends, correlations, and key findings.\n\
+            Return your analysis as JSON with this exact structure:\n\
+            {\n  \"trends\": [{\"indicator\": \"CODE\", \"direction\": \"increasing|decreasing|stable|volatile\", \"description\": \"...\"}],\n  \
+            \"correlations\": [\"description of correlation between indicators\"],\n  \
+            \"key_findings\": [\"important insight 1\", \"important insight 2\"]\n}\n\n\
+            Values are normalized: currency amounts are in billions of one currency and counts \
+            in thousands. Compare indicators with different units by their percent change, not \
+            their levels.\n\n\
+            DATA:\n";
 
-        if let (Some(first), Some(last)) = (ind.values.first(), ind.values.last()) {
-            data_summary.push_str(&format!(
-                "Range: {} to {}\n",
-                first.observation_date, last.observation_date
-            ));
-            data_summary.push_str(&format!(
-                "First value: {:.2}, Last value: {:.2}\n",
-                first.value, last.value
-            ));
-            data_summary.push_str(&format!("Data points: {}\n", ind.values.len()));
-
-            let values: Vec<f64> = ind.values.iter().map(|v| v.value).collect();
-            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
-            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-            let avg = values.iter().sum::<f64>() / values.len() as f64;
-            data_summary.push_str(&format!("Min: {min:.2}, Max: {max:.2}, Avg: {avg:.2}\n"));
-
-            for v in ind
-                .values
-                .iter()
-                .filter(|v| v.observation_date.month() == 1)
-            {
-                data_summary.push_str(&format!("  {}: {:.2}\n", v.observation_date, v.value));
-            }
-        }
-    }
+        // Every indicator's summary before any indicator's yearly values.
+        let blocks = data.iter().flat_map(data_blocks).collect();
+        let budget = context::budget(
+            llm_client.context_window(model),
+            MAX_TOKENS,
+            max_prompt_tokens,
+        );
+        let packed = context::pack(budget, &format!("{system}{instructions}"), blocks);
+        context::record("analyze", &packed);
+
+        let prompt = format!("{instructions}{}", packed.text);
+
+        let resp = llm_client
+            .generate(&GenerateRequest {
+                model: model.to_string(),
+                system,
+                prompt,
+                temperature: 0.3,
+                max_tokens: MAX_TOKENS,
+                stage: "analyze".to_string(),
+            })
+            .await
+            .map_err(|e| AppError::Llm(e.to_string()))?;
+
+        let provider = resp.provider.clone();
+        let mut analysis = parse_analysis_response(
+            &resp.content,
+            resp.input_tokens,
+            resp.output_tokens,
+            resp.cost_usd,
+        )?;
+        analysis.provider = provider;
+        analysis.chat_span = resp.span_context;
+
+        let span = tracing::Span::current();
+        span.record("analysis.trends_found", analysis.trends.len());
+        span.record("analysis.key_findings", analysis.key_findings.len());
 
-    let system = include_str!("../../data/schema-context.txt").to_string();
+        Ok(analysis)
+    })
+    .await
+    .record_err()
+}
 
-    let prompt = format!(
-        "Analyze the following economic data and identify trends, correlations, and key findings.\n\
-        Return your analysis as JSON with this exact structure:\n\
-        {{\n  \"trends\": [{{\"indicator\": \"CODE\", \"direction\": \"increasing|decreasing|stable|volatile\", \"description\": \"...\"}}],\n  \
-        \"correlations\": [\"description of correlation between indicators\"],\n  \
-        \"key_findings\": [\"important insight 1\", \"important insight 2\"]\n}}\n\n\
-        DATA:\n{data_summary}"
+/// An indicator's summary, then its January values, which go first when the
+/// prompt is too long.
+fn data_blocks(ind: &IndicatorData) -> Vec<Block> {
+    let mut summary = format!(
+        "\n## {} ({})\nUnit: {}, Frequency: {}\n",
+        ind.name, ind.code, ind.unit, ind.frequency
     );
+    let (Some(first), Some(last)) = (ind.values.first(), ind.values.last()) else {
+        return vec![Block::new(0, summary)];
+    };
+    summary.push_str(&format!(
+        "Range: {} to {}\n",
+        first.observation_date, last.observation_date
+    ));
+    summary.push_str(&format!(
+        "First value: {:.2}, Last value: {:.2}\n",
+        first.value, last.value
+    ));
+    summary.push_str(&format!("Data points: {}\n", ind.values.len()));
+    if let Some(stats) = super::stats::summarize(ind) {
+        summary.push_str(&format!(
+            "Min: {:.2}, Max: {:.2}, Avg: {:.2}\n",
+            stats.min, stats.max, stats.mean
+        ));
+        if let Some(pct) = stats.change_pct() {
+            summary.push_str(&format!("Change: {pct:+.1}%\n"));
+        }
+    }
 
-    let resp = llm_client
-        .generate(&GenerateRequest {
-            model: model.to_string(),
-            system,
-            prompt,
-            temperature: 0.3,
-            max_tokens: 2048,
-            stage: "analyze".to_string(),
-        })
-        .await
-        .map_err(|e| AppError::Llm(e.to_string()))?;
-
-    let provider = resp.provider.clone();
-    let mut analysis = parse_analysis_response(
-        &resp.content,
-        resp.input_tokens,
-        resp.output_tokens,
-        resp.cost_usd,
-    )?;
-    analysis.provider = provider;
-
-    let span = tracing::Span::current();
-    span.record("analysis.trends_found", analysis.trends.len());
-    span.record("analysis.key_findings", analysis.key_findings.len());
-
-    Ok(analysis)
+    let mut series = format!("{} January values:\n", ind.code);
+    for v in ind
+        .values
+        .iter()
+        .filter(|v| v.observation_date.month() == 1)
+    {
+        series.push_str(&format!("  {}: {:.2}\n", v.observation_date, v.value));
+    }
+    vec![Block::new(0, summary), Block::new(1, series)]
 }
 
 fn parse_analysis_response(
@@ -135,6 +174,7 @@ fn parse_analysis_response(
             output_tokens,
             cost_usd,
             provider: String::new(),
+            chat_span: None,
         }),
         Err(_) => Ok(AnalysisResult {
             trends: vec![],
@@ -144,6 +184,7 @@ fn parse_analysis_response(
             output_tokens,
             cost_usd,
             provider: String::new(),
+            chat_span: None,
         }),
     }
 }
@@ -172,7 +213,67 @@ pub(crate) fn extract_json(content: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::Status;
+
     use super::*;
+    use crate::llm::{Endpoints, GenerateResponse, Provider};
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_child_of};
+
+    struct StubProvider {
+        content: Option<&'static str>,
+    }
+
+    struct HungProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for HungProvider {
+        async fn generate(&self, _req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            anyhow::bail!("never answered")
+        }
+
+        fn name(&self) -> &str {
+            "hung"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for StubProvider {
+        async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            let Some(content) = self.content else {
+                anyhow::bail!("503 service unavailable");
+            };
+            Ok(GenerateResponse {
+                content: content.to_string(),
+                model: req.model.clone(),
+                input_tokens: 100,
+                output_tokens: 50,
+                cost_usd: 0.0,
+                finish_reason: "stop".to_string(),
+                provider: String::new(),
+                span_context: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+    }
+
+    fn client(content: Option<&'static str>) -> LlmClient {
+        LlmClient {
+            primary: Endpoints::single(Arc::new(StubProvider { content })),
+            fallback: None,
+            primary_provider: "stub".to_string(),
+            fallback_provider: "none".to_string(),
+            fallback_model: String::new(),
+            observers: vec![],
+            max_retries: 1,
+        }
+    }
 
     #[test]
     fn test_extract_json_raw() {
@@ -244,4 +345,66 @@ mod tests {
         assert!(result.correlations.is_empty());
         assert!(result.key_findings.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_analyze_span_wraps_the_chat_call() {
+        let spans = SpanCapture::start();
+        let content = r#"{"trends": [], "key_findings": ["economy grew"]}"#;
+        let analysis = analyze(&client(Some(content)), "stub-model", &[], 0, Duration::ZERO)
+            .await
+            .unwrap();
+
+        let stage = spans.span("pipeline_stage analyze");
+        let chat = spans.span("gen_ai.chat stub-model");
+        assert_child_of(&chat, &stage);
+        assert_eq!(
+            analysis.chat_span.map(|c| c.span_id()),
+            Some(chat.span_context.span_id())
+        );
+        assert_attributes(&stage, &[KeyValue::new("pipeline.stage", "analyze")]);
+        assert_attributes(&chat, &[KeyValue::new("report.stage", "analyze")]);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_span_records_llm_errors() {
+        let spans = SpanCapture::start();
+        assert!(
+            analyze(&client(None), "stub-model", &[], 0, Duration::ZERO)
+                .await
+                .is_err()
+        );
+
+        let stage = spans.span("pipeline_stage analyze");
+        assert!(matches!(stage.status, Status::Error { .. }));
+        let exception = stage.events.iter().find(|e| e.name == "exception").unwrap();
+        assert!(
+            exception
+                .attributes
+                .contains(&KeyValue::new("exception.type", "Llm"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_times_out_a_hung_provider() {
+        let spans = SpanCapture::start();
+        let client = LlmClient {
+            primary: Endpoints::single(Arc::new(HungProvider)),
+            ..client(None)
+        };
+
+        let err = analyze(&client, "stub-model", &[], 0, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::StageTimeout {
+                stage: "analyze",
+                ..
+            }
+        ));
+
+        let stage = spans.span("pipeline_stage analyze");
+        assert!(matches!(stage.status, Status::Error { .. }));
+        assert_attributes(&stage, &[KeyValue::new("error.type", "timeout")]);
+    }
 }
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use opentelemetry::trace::TraceContextExt;
+use sqlx::PgPool;
+use tokio::sync::OwnedSemaphorePermit;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+use crate::db::reports::{finish_cancelled_report, set_report_status};
+use crate::error::AppError;
+use crate::llm::LlmClient;
+use crate::reload::Reloadable;
+use crate::telemetry::current_trace_id;
+use crate::telemetry::metrics::REPORT_CANCELLED;
+
+use super::admission::AdmissionController;
+use super::cancel::RunningReports;
+use super::guardrail::Guardrails;
+use super::minimize::DataMinimization;
+use super::orchestrator::{Checkpoint, Pipeline, ReportRequest, generate_report};
+use super::sink::PgReportSink;
+use super::timeout::StageTimeouts;
+use super::watch::ReportWatch;
+
+#[derive(Clone)]
+pub struct BatchPipeline {
+    pub pool: PgPool,
+    pub llm_client: Arc<Reloadable<LlmClient>>,
+    pub guardrails: Arc<Guardrails>,
+    pub model_capable: String,
+    pub model_fast: String,
+    pub max_prompt_tokens: usize,
+    pub minimization: DataMinimization,
+    pub timeouts: StageTimeouts,
+    pub heartbeat: Duration,
+    pub watch: Arc<ReportWatch>,
+    pub running: Arc<RunningReports>,
+    /// `OPENAI_BATCH_MAX_REPORTS` slots, separate from the interactive ones
+    /// and without a queue.
+    pub admission: Arc<AdmissionController>,
+}
+
+impl BatchPipeline {
+    /// A slot for one more batch report, or 429 when they are all taken.
+    /// Take it before storing the pending report and pass it to
+    /// [`BatchPipeline::spawn`].
+    pub fn admit(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        self.admission.try_admit()
+    }
+
+    /// Runs the pipeline for an already-inserted pending report in the
+    /// background. The batch jobs can take hours, so the work gets its own
+    /// trace, linked from the request that queued it. A report being resumed
+    /// passes the checkpoint it is resumed from. The slot is held until the
+    /// pipeline finishes.
+    pub fn spawn(
+        &self,
+        permit: OwnedSemaphorePermit,
+        report_id: Uuid,
+        request: ReportRequest,
+        checkpoint: Option<Checkpoint>,
+    ) {
+        let span = tracing::info_span!(
+            parent: None,
+            "report batch",
+            report.id = %report_id,
+            report.mode = "batch",
+        );
+        let caller = tracing::Span::current()
+            .context()
+            .span()
+            .span_context()
+            .clone();
+        if caller.is_valid() {
+            span.add_link(caller);
+        }
+
+        let batch = self.clone();
+        tokio::spawn(
+            async move {
+                let _permit = permit;
+                batch.run(report_id, request, checkpoint).await;
+            }
+            .instrument(span),
+        );
+    }
+
+    async fn run(self, report_id: Uuid, request: ReportRequest, checkpoint: Option<Checkpoint>) {
+        let llm_client = self.llm_client.current();
+        let pipeline = Pipeline {
+            pool: &self.pool,
+            llm_client: &llm_client,
+            guardrails: &self.guardrails,
+            model_capable: &self.model_capable,
+            model_fast: &self.model_fast,
+            max_prompt_tokens: self.max_prompt_tokens,
+            minimization: self.minimization,
+            timeouts: self.timeouts,
+            heartbeat: Some(self.heartbeat),
+        };
+        let sink = PgReportSink::new(self.pool.clone(), self.watch.clone());
+        let run = self.running.start(report_id);
+        let result = run
+            .run(generate_report(
+                &pipeline,
+                &sink,
+                report_id,
+                &request,
+                checkpoint.as_ref(),
+            ))
+            .await;
+
+        match result {
+            Ok(report) => tracing::info!(
+                status = report.status.as_str(),
+                cost_usd = report.total_cost_usd,
+                "Batch report completed"
+            ),
+            Err(AppError::Cancelled) => {
+                let cost_usd = run.spent_usd();
+                tracing::info!(cost_usd, "Batch report cancelled");
+                REPORT_CANCELLED.add(
+                    1,
+                    &[KeyValue::new("report.type", request.template.name.clone())],
+                );
+                let trace_id = current_trace_id();
+                if let Err(db_err) =
+                    finish_cancelled_report(&self.pool, report_id, cost_usd, trace_id.as_deref())
+                        .await
+                {
+                    tracing::error!(error = %db_err, "Failed to record cancelled batch report");
+                }
+                self.watch.notify(report_id);
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "Batch report failed");
+                if let Err(db_err) = set_report_status(&self.pool, report_id, "failed").await {
+                    tracing::error!(error = %db_err, "Failed to mark batch report as failed");
+                }
+                self.watch.notify(report_id);
+            }
+        }
+    }
+}
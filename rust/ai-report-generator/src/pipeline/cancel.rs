@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::llm::Spend;
+
+/// Reports whose pipeline is running in this process, so
+/// `POST /api/reports/{id}/cancel` can stop them. In process only, like
+/// [`super::ReportWatch`]: a report another instance is generating isn't
+/// found here.
+#[derive(Default)]
+pub struct RunningReports {
+    runs: Mutex<HashMap<Uuid, watch::Sender<bool>>>,
+}
+
+impl RunningReports {
+    /// Registers the report until the returned run is dropped.
+    pub fn start(self: &Arc<Self>, report_id: Uuid) -> Run {
+        let (sender, cancelled) = watch::channel(false);
+        self.runs.lock().unwrap().insert(report_id, sender);
+        Run {
+            reports: self.clone(),
+            report_id,
+            cancelled,
+            spend: Arc::default(),
+        }
+    }
+
+    /// Signals the report's run to stop. False if it isn't running here.
+    pub fn cancel(&self, report_id: Uuid) -> bool {
+        match self.runs.lock().unwrap().get(&report_id) {
+            Some(sender) => {
+                sender.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[cfg(test)]
+    fn running(&self) -> usize {
+        self.runs.lock().unwrap().len()
+    }
+}
+
+pub struct Run {
+    reports: Arc<RunningReports>,
+    report_id: Uuid,
+    cancelled: watch::Receiver<bool>,
+    spend: Arc<Spend>,
+}
+
+impl Run {
+    /// Runs `work` to the end, or drops it once the report is cancelled and
+    /// returns [`AppError::Cancelled`]. Dropping it drops the provider
+    /// requests in flight with it.
+    pub async fn run<T>(
+        &self,
+        work: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        let mut cancelled = self.cancelled.clone();
+        tokio::select! {
+            result = self.spend.clone().scope(work) => result,
+            Ok(_) = cancelled.wait_for(|cancelled| *cancelled) => Err(AppError::Cancelled),
+        }
+    }
+
+    /// What the LLM calls made in [`Run::run`] cost, including those of
+    /// work that was cancelled.
+    pub fn spent_usd(&self) -> f64 {
+        self.spend.total_usd()
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.reports.runs.lock().unwrap().remove(&self.report_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_drops_the_work() {
+        let reports = Arc::new(RunningReports::default());
+        let id = Uuid::new_v4();
+        let run = reports.start(id);
+
+        let work = async {
+            Spend::record(0.5);
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        };
+        let (result, cancelled) = tokio::join!(run.run(work), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            reports.cancel(id)
+        });
+
+        assert!(cancelled);
+        assert!(matches!(result, Err(AppError::Cancelled)));
+        assert_eq!(run.spent_usd(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_finished_work_is_not_cancelled() {
+        let reports = Arc::new(RunningReports::default());
+        let run = reports.start(Uuid::new_v4());
+
+        assert_eq!(run.run(async { Ok(1) }).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_drop_unregisters_the_run() {
+        let reports = Arc::new(RunningReports::default());
+        let id = Uuid::new_v4();
+        let run = reports.start(id);
+        assert_eq!(reports.running(), 1);
+
+        drop(run);
+        assert_eq!(reports.running(), 0);
+        assert!(!reports.cancel(id));
+    }
+}
@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::db::data_points::IndicatorData;
+use crate::explain::{Decision, Explainer};
+
+use super::generate::NarrativeSection;
+
+/// Points kept per indicator in a section chart; longer series are
+/// downsampled so a report's `sections` stay small.
+pub const MAX_POINTS: usize = 60;
+
+/// The data behind a section and a Vega-Lite spec to plot it, so a front-end
+/// can render the chart without querying data points.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionChart {
+    /// The spec's `series` dataset: one row per indicator and date.
+    pub data: Vec<ChartPoint>,
+    pub spec: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChartPoint {
+    pub indicator: String,
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// Sets each section's `indicators` to the retrieved indicators it covers and
+/// charts them. Codes the LLM listed are kept if they were retrieved; without
+/// any, indicators whose code or name the section mentions are used.
+pub fn attach(sections: &mut [NarrativeSection], indicators: &[IndicatorData]) {
+    for section in sections.iter_mut() {
+        let relevant: Vec<&IndicatorData> = if section.indicators.is_empty() {
+            indicators.iter().filter(|i| mentions(section, i)).collect()
+        } else {
+            indicators
+                .iter()
+                .filter(|i| {
+                    section
+                        .indicators
+                        .iter()
+                        .any(|code| code.eq_ignore_ascii_case(&i.code))
+                })
+                .collect()
+        };
+        section.indicators = relevant.iter().map(|i| i.code.clone()).collect();
+        section.chart = chart(&relevant);
+    }
+
+    for indicator in indicators {
+        let charted = sections
+            .iter()
+            .any(|s| s.indicators.contains(&indicator.code));
+        if charted && indicator.values.len() > MAX_POINTS {
+            Explainer::record(|| Decision::ChartDownsampled {
+                indicator: indicator.code.clone(),
+                points: indicator.values.len(),
+                kept: MAX_POINTS,
+            });
+        }
+    }
+}
+
+fn mentions(section: &NarrativeSection, indicator: &IndicatorData) -> bool {
+    let text = format!("{} {}", section.heading, section.content);
+    let name = indicator.name.to_lowercase();
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| word.eq_ignore_ascii_case(&indicator.code))
+        || (!name.is_empty() && text.to_lowercase().contains(&name))
+}
+
+fn chart(indicators: &[&IndicatorData]) -> Option<SectionChart> {
+    let data: Vec<ChartPoint> = indicators
+        .iter()
+        .flat_map(|indicator| {
+            downsample(indicator.values.len())
+                .map(|i| &indicator.values[i])
+                .map(|point| ChartPoint {
+                    indicator: indicator.code.clone(),
+                    date: point.observation_date,
+                    value: point.value,
+                })
+        })
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    Some(SectionChart {
+        data,
+        spec: spec(indicators),
+    })
+}
+
+/// Indices of at most [`MAX_POINTS`] evenly spaced values, keeping the first
+/// and last.
+fn downsample(len: usize) -> impl Iterator<Item = usize> {
+    let kept = len.min(MAX_POINTS);
+    (0..kept).map(move |i| {
+        if kept == 1 {
+            0
+        } else {
+            i * (len - 1) / (kept - 1)
+        }
+    })
+}
+
+/// A line per indicator; indicators in different units get their own row and
+/// y axis.
+fn spec(indicators: &[&IndicatorData]) -> serde_json::Value {
+    let shared_unit = indicators
+        .iter()
+        .all(|i| i.unit == indicators[0].unit)
+        .then(|| indicators[0].unit.clone());
+
+    let mut spec = json!({
+        "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+        "data": { "name": "series" },
+        "mark": { "type": "line", "point": false },
+        "encoding": {
+            "x": { "field": "date", "type": "temporal", "title": null },
+            "y": { "field": "value", "type": "quantitative", "title": shared_unit },
+            "color": { "field": "indicator", "type": "nominal", "title": null },
+        },
+    });
+    if shared_unit.is_none() {
+        spec["encoding"]["row"] = json!({ "field": "indicator", "type": "nominal", "title": null });
+        spec["resolve"] = json!({ "scale": { "y": "independent" } });
+    }
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::data_points::DataPoint;
+
+    fn indicator(code: &str, name: &str, unit: &str, len: usize) -> IndicatorData {
+        let start = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        IndicatorData {
+            code: code.to_string(),
+            name: name.to_string(),
+            unit: unit.to_string(),
+            frequency: "Monthly".to_string(),
+            values: (0..len)
+                .map(|i| DataPoint {
+                    observation_date: start + chrono::Days::new(i as u64 * 30),
+                    value: i as f64,
+                })
+                .collect(),
+        }
+    }
+
+    fn section(content: &str, indicators: &[&str]) -> NarrativeSection {
+        NarrativeSection {
+            heading: "Trends".to_string(),
+            content: content.to_string(),
+            indicators: indicators.iter().map(|c| c.to_string()).collect(),
+            chart: None,
+            citations: Vec::new(),
+            uncited_claims: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_downsample_keeps_first_and_last() {
+        assert_eq!(downsample(3).collect::<Vec<_>>(), [0, 1, 2]);
+        let kept: Vec<usize> = downsample(240).collect();
+        assert_eq!(kept.len(), MAX_POINTS);
+        assert_eq!((kept[0], kept[MAX_POINTS - 1]), (0, 239));
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_attach_picks_relevant_indicators() {
+        let indicators = [
+            indicator("UNRATE", "Unemployment Rate", "Percent", 240),
+            indicator("CPIAUCSL", "Consumer Price Index", "Index 1982-84=100", 12),
+        ];
+        let mut sections = [
+            section("The unemployment rate fell to 3.7%.", &[]),
+            section("Prices rose.", &["cpiaucsl", "GDP"]),
+            section("UNRATE and CPIAUCSL moved together.", &[]),
+            section("Housing was flat.", &[]),
+        ];
+        attach(&mut sections, &indicators);
+
+        assert_eq!(sections[0].indicators, ["UNRATE"]);
+        let chart = sections[0].chart.as_ref().unwrap();
+        assert_eq!(chart.data.len(), MAX_POINTS);
+        assert_eq!(chart.spec["data"]["name"], "series");
+        assert_eq!(chart.spec["encoding"]["y"]["title"], "Percent");
+        assert!(chart.spec.get("resolve").is_none());
+
+        assert_eq!(sections[1].indicators, ["CPIAUCSL"]);
+        assert_eq!(sections[1].chart.as_ref().unwrap().data.len(), 12);
+
+        assert_eq!(sections[2].indicators, ["UNRATE", "CPIAUCSL"]);
+        let chart = sections[2].chart.as_ref().unwrap();
+        assert_eq!(chart.spec["encoding"]["row"]["field"], "indicator");
+        assert_eq!(chart.spec["resolve"]["scale"]["y"], "independent");
+
+        assert!(sections[3].indicators.is_empty());
+        assert!(sections[3].chart.is_none());
+    }
+}
@@ -0,0 +1,210 @@
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::db::data_points::IndicatorData;
+
+use super::generate::NarrativeSection;
+use super::guardrail;
+use super::locale::Language;
+
+/// `[UNRATE@2023-12-01]`: the data point the preceding claim is taken from.
+static MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([A-Za-z0-9_.]+)@(\d{4}-\d{2}-\d{2})\]").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Citation {
+    pub indicator: String,
+    pub date: NaiveDate,
+    /// The cited data point's value; `None` when the retrieved data has no
+    /// point for that indicator and date.
+    pub value: Option<f64>,
+}
+
+/// The generate prompt's instruction; the prompt lists [`citable_points`].
+pub const INSTRUCTIONS: &str = "After every number taken from the data, cite its data point \
+    from the citable data points above as [INDICATOR_CODE@YYYY-MM-DD], e.g. \
+    \"unemployment fell to 3.7% [UNRATE@2023-12-01]\".";
+
+/// The data points the generate prompt offers for citing: the indicator's
+/// first, last, lowest and highest observation.
+pub fn citable_points(indicator: &IndicatorData) -> Option<String> {
+    let values = &indicator.values;
+    let first = values.first()?;
+    let last = values.last()?;
+    let min = values.iter().min_by(|a, b| a.value.total_cmp(&b.value))?;
+    let max = values.iter().max_by(|a, b| a.value.total_cmp(&b.value))?;
+    let points: Vec<String> = [(first, "first"), (last, "last"), (min, "min"), (max, "max")]
+        .iter()
+        .map(|(p, label)| format!("{} {:.2} ({label})", p.observation_date, p.value))
+        .collect();
+    Some(format!("- {}: {}\n", indicator.code, points.join("; ")))
+}
+
+pub fn strip_markers(text: &str) -> Cow<'_, str> {
+    MARKER.replace_all(text, " ")
+}
+
+/// Sets each section's `citations` from its markers, checked against `data`,
+/// and its `uncited_claims`: the sentences with a number but no marker.
+pub fn attach(sections: &mut [NarrativeSection], data: &[IndicatorData], language: Language) {
+    for section in sections {
+        let mut citations: Vec<Citation> = Vec::new();
+        for caps in MARKER.captures_iter(&section.content) {
+            let Ok(date) = NaiveDate::parse_from_str(&caps[2], "%Y-%m-%d") else {
+                continue;
+            };
+            let code = &caps[1];
+            if citations
+                .iter()
+                .any(|c| c.indicator.eq_ignore_ascii_case(code) && c.date == date)
+            {
+                continue;
+            }
+            let indicator = data.iter().find(|i| i.code.eq_ignore_ascii_case(code));
+            citations.push(Citation {
+                indicator: indicator.map_or_else(|| code.to_string(), |i| i.code.clone()),
+                date,
+                value: indicator.and_then(|i| {
+                    i.values
+                        .iter()
+                        .find(|v| v.observation_date == date)
+                        .map(|v| v.value)
+                }),
+            });
+        }
+        section.citations = citations;
+        section.uncited_claims = sentences(&section.content)
+            .filter(|s| !MARKER.is_match(s) && !guardrail::claims(s, language).is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+}
+
+/// Splits at `.`, `!` or `?` followed by whitespace, except after a digit so
+/// "31. Dezember" stays whole.
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut start = 0;
+    let mut sentences = Vec::new();
+    let mut prev = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if matches!(c, '.' | '!' | '?')
+            && !prev.is_some_and(|p: char| p.is_ascii_digit())
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace())
+        {
+            sentences.push(&text[start..i + c.len_utf8()]);
+            start = i + c.len_utf8();
+        }
+        prev = Some(c);
+    }
+    sentences.push(&text[start..]);
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::data_points::DataPoint;
+
+    fn date(year: i32, month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+    }
+
+    fn unrate() -> Vec<IndicatorData> {
+        vec![IndicatorData {
+            code: "UNRATE".to_string(),
+            name: "Unemployment Rate".to_string(),
+            unit: "Percent".to_string(),
+            frequency: "Monthly".to_string(),
+            values: [
+                (date(2020, 1), 3.5),
+                (date(2020, 4), 14.7),
+                (date(2023, 12), 3.7),
+            ]
+            .into_iter()
+            .map(|(observation_date, value)| DataPoint {
+                observation_date,
+                value,
+            })
+            .collect(),
+        }]
+    }
+
+    fn section(content: &str) -> NarrativeSection {
+        NarrativeSection {
+            heading: "Labor market".to_string(),
+            content: content.to_string(),
+            indicators: Vec::new(),
+            chart: None,
+            citations: Vec::new(),
+            uncited_claims: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_citations_are_checked_against_data() {
+        let mut sections = [section(
+            "Unemployment peaked at 14.7% [UNRATE@2020-04-01] in 2020. \
+             It ended at 3.7% [unrate@2023-12-01] [UNRATE@2023-12-01]. \
+             Payrolls grew by 250.5 thousand [PAYEMS@2023-12-01]. \
+             Wages rose 4.1% over the year. It was a strong year.",
+        )];
+        attach(&mut sections, &unrate(), Language::En);
+
+        let citations = &sections[0].citations;
+        assert_eq!(
+            citations,
+            &[
+                Citation {
+                    indicator: "UNRATE".to_string(),
+                    date: date(2020, 4),
+                    value: Some(14.7),
+                },
+                Citation {
+                    indicator: "UNRATE".to_string(),
+                    date: date(2023, 12),
+                    value: Some(3.7),
+                },
+                Citation {
+                    indicator: "PAYEMS".to_string(),
+                    date: date(2023, 12),
+                    value: None,
+                },
+            ]
+        );
+        assert_eq!(
+            sections[0].uncited_claims,
+            ["Wages rose 4.1% over the year."]
+        );
+    }
+
+    #[test]
+    fn test_sentences_keep_decimals_and_ordinal_dates() {
+        let text = "Die Quote lag am 31. Dezember bei 3,7 %. Sie sank! Warum? Ja";
+        assert_eq!(
+            sentences(text).collect::<Vec<_>>(),
+            [
+                "Die Quote lag am 31. Dezember bei 3,7 %.",
+                "Sie sank!",
+                "Warum?",
+                "Ja"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_citable_points() {
+        assert_eq!(
+            citable_points(&unrate()[0]).unwrap(),
+            "- UNRATE: 2020-01-01 3.50 (first); 2023-12-01 3.70 (last); \
+             2020-01-01 3.50 (min); 2020-04-01 14.70 (max)\n"
+        );
+    }
+}
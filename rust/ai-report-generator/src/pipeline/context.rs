@@ -0,0 +1,128 @@
+use crate::explain::{Decision, Explainer};
+use crate::llm::tokens::count_tokens;
+
+/// The prompt tokens a request may use: the model's context window less the
+/// completion's `max_tokens`, capped at `max_prompt_tokens` unless that is 0.
+pub fn budget(context_window: u32, max_tokens: u32, max_prompt_tokens: usize) -> usize {
+    let available = context_window.saturating_sub(max_tokens) as usize;
+    if max_prompt_tokens == 0 {
+        available
+    } else {
+        available.min(max_prompt_tokens)
+    }
+}
+
+/// A piece of a prompt that may be left out. Lower `priority` is kept first.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub priority: u8,
+    pub text: String,
+}
+
+impl Block {
+    pub fn new(priority: u8, text: impl Into<String>) -> Self {
+        Self {
+            priority,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Packed {
+    /// The kept blocks, in their given order.
+    pub text: String,
+    pub budget: usize,
+    /// Estimated tokens of `fixed` and the kept blocks.
+    pub tokens: usize,
+    pub dropped: usize,
+}
+
+/// Keeps the blocks that fit in `budget` after the `fixed` rest of the
+/// prompt: by priority, then in their given order, skipping any block that
+/// no longer fits.
+pub fn pack(budget: usize, fixed: &str, blocks: Vec<Block>) -> Packed {
+    let mut tokens = count_tokens(fixed);
+    let mut order: Vec<usize> = (0..blocks.len()).collect();
+    order.sort_by_key(|&i| blocks[i].priority);
+
+    let mut kept = vec![false; blocks.len()];
+    for i in order {
+        let cost = count_tokens(&blocks[i].text);
+        if tokens + cost <= budget {
+            tokens += cost;
+            kept[i] = true;
+        }
+    }
+
+    let dropped = kept.iter().filter(|k| !**k).count();
+    let text = blocks
+        .into_iter()
+        .zip(kept)
+        .filter_map(|(block, kept)| kept.then_some(block.text))
+        .collect();
+    Packed {
+        text,
+        budget,
+        tokens,
+        dropped,
+    }
+}
+
+/// Records `prompt.estimated_tokens` and `prompt.dropped_blocks` on the
+/// current stage span, and any dropped blocks in the report's explanation.
+pub fn record(stage: &str, packed: &Packed) {
+    let span = tracing::Span::current();
+    span.record("prompt.estimated_tokens", packed.tokens);
+    span.record("prompt.dropped_blocks", packed.dropped);
+    if packed.dropped > 0 {
+        tracing::warn!(
+            dropped_blocks = packed.dropped,
+            estimated_tokens = packed.tokens,
+            "Prompt left out data to fit its context budget"
+        );
+        Explainer::record(|| Decision::PromptTruncated {
+            stage: stage.to_string(),
+            budget_tokens: packed.budget,
+            estimated_tokens: packed.tokens,
+            dropped_blocks: packed.dropped,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_leaves_room_for_the_completion() {
+        assert_eq!(budget(8_192, 2_048, 0), 6_144);
+        assert_eq!(budget(8_192, 2_048, 4_000), 4_000);
+        assert_eq!(budget(1_024, 2_048, 0), 0);
+    }
+
+    #[test]
+    fn test_pack_keeps_high_priority_blocks_in_order() {
+        let blocks = vec![
+            Block::new(0, "GDP summary. "),
+            Block::new(1, "GDP series 1 2 3 4 5 6. "),
+            Block::new(0, "UNRATE summary. "),
+            Block::new(2, "Correlation. "),
+        ];
+        let fixed = "DATA:";
+
+        let all = pack(100, fixed, blocks.clone());
+        assert_eq!(all.dropped, 0);
+        assert_eq!(
+            all.text,
+            "GDP summary. GDP series 1 2 3 4 5 6. UNRATE summary. Correlation. "
+        );
+
+        // Too little room for the series, but enough for the smaller,
+        // less important correlation.
+        let packed = pack(15, fixed, blocks);
+        assert_eq!(packed.text, "GDP summary. UNRATE summary. Correlation. ");
+        assert_eq!(packed.dropped, 1);
+        assert_eq!(packed.tokens, 15);
+    }
+}
@@ -4,11 +4,34 @@ use chrono::NaiveDate;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::db::data_points::IndicatorData;
 use crate::error::AppError;
+use crate::explain::Decision;
 
 use super::analyze::AnalysisResult;
 use super::generate::NarrativeResult;
+use super::guardrail::Violation;
+use super::locale::Language;
 use super::retrieve::RetrieveResult;
+use super::{chart, citation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    Completed,
+    /// Every LLM provider failed, so the analysis and narrative are
+    /// statistics computed directly from the data.
+    Degraded,
+}
+
+impl ReportStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Degraded => "degraded",
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Report {
@@ -25,10 +48,24 @@ pub struct Report {
     pub providers_used: Vec<String>,
     pub generation_duration_ms: u64,
     pub trace_id: String,
+    pub status: ReportStatus,
+    pub report_type: String,
+    pub language: Language,
+    /// `time_range_start` to `time_range_end`, written for `language`.
+    pub period: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub guardrail_violations: Vec<Violation>,
+    /// Stored apart from the report, for `GET /api/reports/{id}/explanation`.
+    #[serde(skip)]
+    pub explanation: Option<Vec<Decision>>,
 }
 
 pub struct FormatParams<'a> {
+    pub id: Uuid,
     pub retrieve_result: &'a RetrieveResult,
+    /// The data the narrative was written from, which its citations are
+    /// checked against.
+    pub cited: &'a [IndicatorData],
     pub analysis: &'a AnalysisResult,
     pub narrative: &'a NarrativeResult,
     pub indicators_requested: &'a [String],
@@ -36,6 +73,10 @@ pub struct FormatParams<'a> {
     pub end_date: NaiveDate,
     pub duration: Duration,
     pub trace_id: String,
+    pub status: ReportStatus,
+    pub guardrail_violations: Vec<Violation>,
+    pub report_type: &'a str,
+    pub language: Language,
 }
 
 #[tracing::instrument(
@@ -45,6 +86,9 @@ pub struct FormatParams<'a> {
         pipeline.stage = "format",
         report.title,
         report.sections_count,
+        report.charts_count,
+        report.citations_count,
+        report.uncited_claims,
     )
 )]
 pub fn format_report(params: FormatParams<'_>) -> Result<Report, AppError> {
@@ -57,16 +101,39 @@ pub fn format_report(params: FormatParams<'_>) -> Result<Report, AppError> {
     if params.narrative.provider != params.analysis.provider {
         providers_used.push(params.narrative.provider.clone());
     }
+    providers_used.retain(|p| !p.is_empty());
+
+    let mut sections = params.narrative.sections.clone();
+    chart::attach(&mut sections, &params.retrieve_result.indicators);
+    // A degraded report's narrative is computed, not written, so it cites nothing.
+    if params.status == ReportStatus::Completed {
+        citation::attach(&mut sections, params.cited, params.language);
+    }
 
     let span = tracing::Span::current();
     span.record("report.title", &params.narrative.title);
-    span.record("report.sections_count", params.narrative.sections.len());
+    span.record("report.sections_count", sections.len());
+    span.record(
+        "report.charts_count",
+        sections.iter().filter(|s| s.chart.is_some()).count(),
+    );
+    span.record(
+        "report.citations_count",
+        sections.iter().map(|s| s.citations.len()).sum::<usize>(),
+    );
+    span.record(
+        "report.uncited_claims",
+        sections
+            .iter()
+            .map(|s| s.uncited_claims.len())
+            .sum::<usize>(),
+    );
 
     Ok(Report {
-        id: Uuid::new_v4(),
+        id: params.id,
         title: params.narrative.title.clone(),
         executive_summary: params.narrative.executive_summary.clone(),
-        sections: params.narrative.sections.clone(),
+        sections,
         indicators_used: params.indicators_requested.to_vec(),
         time_range_start: params.start_date,
         time_range_end: params.end_date,
@@ -76,6 +143,16 @@ pub fn format_report(params: FormatParams<'_>) -> Result<Report, AppError> {
         providers_used,
         generation_duration_ms: params.duration.as_millis() as u64,
         trace_id: params.trace_id,
+        status: params.status,
+        report_type: params.report_type.to_string(),
+        language: params.language,
+        period: format!(
+            "{} – {}",
+            params.language.format_date(params.start_date),
+            params.language.format_date(params.end_date)
+        ),
+        guardrail_violations: params.guardrail_violations,
+        explanation: None,
     })
 }
 
@@ -104,6 +181,7 @@ mod tests {
             output_tokens: 200,
             cost_usd: 0.01,
             provider: "openai".to_string(),
+            chat_span: None,
         };
         let narrative = NarrativeResult {
             title: "Economic Overview 2023".to_string(),
@@ -112,20 +190,31 @@ mod tests {
                 NarrativeSection {
                     heading: "GDP Growth".to_string(),
                     content: "GDP increased by 3%.".to_string(),
+                    indicators: Vec::new(),
+                    chart: None,
+                    citations: Vec::new(),
+                    uncited_claims: Vec::new(),
                 },
                 NarrativeSection {
                     heading: "Employment".to_string(),
                     content: "Unemployment fell.".to_string(),
+                    indicators: Vec::new(),
+                    chart: None,
+                    citations: Vec::new(),
+                    uncited_claims: Vec::new(),
                 },
             ],
             input_tokens: 800,
             output_tokens: 400,
             cost_usd: 0.02,
             provider: "openai".to_string(),
+            chat_span: None,
         };
 
         let report = format_report(FormatParams {
+            id: Uuid::new_v4(),
             retrieve_result: &retrieve_result,
+            cited: &retrieve_result.indicators,
             analysis: &analysis,
             narrative: &narrative,
             indicators_requested: &["GDP".to_string(), "UNRATE".to_string()],
@@ -133,6 +222,10 @@ mod tests {
             end_date: NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
             duration: Duration::from_millis(5400),
             trace_id: "abc123trace".to_string(),
+            status: ReportStatus::Completed,
+            guardrail_violations: vec![],
+            report_type: "general",
+            language: Language::De,
         })
         .unwrap();
 
@@ -148,6 +241,7 @@ mod tests {
             report.time_range_end,
             NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()
         );
+        assert_eq!(report.period, "1. Januar 2003 – 31. Dezember 2023");
         assert_eq!(report.total_data_points, 250);
         assert_eq!(report.total_tokens, 500 + 200 + 800 + 400);
         assert_eq!(report.providers_used, vec!["openai"]);
@@ -1,10 +1,25 @@
+use std::time::Duration;
+
+use opentelemetry::trace::SpanContext;
 use serde::{Deserialize, Serialize};
 
 use crate::db::data_points::IndicatorData;
-use crate::error::AppError;
+use crate::error::{AppError, RecordErr};
 use crate::llm::{GenerateRequest, LlmClient};
 
 use super::analyze::AnalysisResult;
+use super::chart::SectionChart;
+use super::citation::{self, Citation};
+use super::context::{self, Block};
+use super::locale::Language;
+use super::orchestrator::ReportRequest;
+use super::timeout;
+
+pub const SYSTEM_PROMPT: &str = "You are an expert economic analyst writing structured reports. \
+    Write clear, data-driven narrative with specific numbers and dates. \
+    Be concise but thorough.";
+
+const MAX_TOKENS: u32 = 4096;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NarrativeResult {
@@ -15,21 +30,39 @@ pub struct NarrativeResult {
     pub output_tokens: u32,
     pub cost_usd: f64,
     pub provider: String,
+    #[serde(skip)]
+    pub chat_span: Option<SpanContext>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NarrativeSection {
     pub heading: String,
     pub content: String,
+    /// Codes of the indicators the section covers; set by the format stage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indicators: Vec<String>,
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub chart: Option<SectionChart>,
+    /// The `[CODE@date]` markers in `content`; set by the format stage.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    /// Sentences of `content` with a numeric claim but no citation.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub uncited_claims: Vec<String>,
 }
 
 #[tracing::instrument(
     name = "pipeline_stage generate",
-    skip(llm_client, data, analysis),
+    skip(llm_client, data, analysis, request, max_prompt_tokens, timeout),
     fields(
         pipeline.stage = "generate",
+        report.type = %request.template.name,
+        report.language = %request.language,
         narrative.title,
         narrative.sections_count,
+        prompt.estimated_tokens,
+        prompt.dropped_blocks,
+        error.type,
     )
 )]
 pub async fn generate(
@@ -37,69 +70,139 @@ pub async fn generate(
     model: &str,
     data: &[IndicatorData],
     analysis: &AnalysisResult,
+    request: &ReportRequest,
+    max_prompt_tokens: usize,
+    timeout: Duration,
 ) -> Result<NarrativeResult, AppError> {
-    let indicator_list: Vec<String> = data
-        .iter()
-        .map(|d| format!("{} ({})", d.name, d.code))
-        .collect();
-
-    let time_range = data
-        .first()
-        .and_then(|ind| {
-            let first_date = ind.values.first().map(|v| v.observation_date)?;
-            let last_date = ind.values.last().map(|v| v.observation_date)?;
-            Some(format!("{first_date} to {last_date}"))
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-
-    let analysis_json = serde_json::to_string_pretty(analysis).unwrap_or_default();
-
-    let system = "You are an expert economic analyst writing structured reports. \
-        Write clear, data-driven narrative with specific numbers and dates. \
-        Be concise but thorough."
-        .to_string();
-
-    let prompt = format!(
-        "Write a structured economic report based on this analysis.\n\n\
-        Indicators: {}\n\
-        Time period: {}\n\n\
-        Analysis:\n{}\n\n\
-        Return your report as JSON with this exact structure:\n\
-        {{\n  \"title\": \"Report title\",\n  \
-        \"executive_summary\": \"2-3 sentence overview\",\n  \
-        \"sections\": [\n    {{\"heading\": \"Section title\", \"content\": \"Section content with data references\"}}\n  ]\n}}\n\n\
-        Include 3-5 sections covering the major themes from the analysis.",
-        indicator_list.join(", "),
-        time_range,
-        analysis_json
-    );
+    timeout::within("generate", timeout, async move {
+        let (template, language) = (&request.template, request.language);
+        let indicator_list: Vec<String> = data
+            .iter()
+            .map(|d| format!("{} ({})", d.name, d.code))
+            .collect();
+
+        let time_range = data
+            .first()
+            .and_then(|ind| {
+                let first_date = ind.values.first().map(|v| v.observation_date)?;
+                let last_date = ind.values.last().map(|v| v.observation_date)?;
+                Some(format!(
+                    "{} to {}",
+                    language.format_date(first_date),
+                    language.format_date(last_date)
+                ))
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let mut instructions = template.instructions(&indicator_list.join(", "), &time_range);
+        if language != Language::En {
+            instructions.push_str(&format!(
+                "\n\nWrite the title, executive summary, section headings and section content \
+                in {name}, keeping the JSON keys in English. Write numbers and dates the way \
+                {name} readers expect, e.g. {number} and {date}.",
+                name = language.name(),
+                number = language.format_number(27357.8, 1),
+                date = language.format_date(chrono::NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()),
+            ));
+        }
+        instructions.push_str("\n\n");
+        instructions.push_str(citation::INSTRUCTIONS);
+
+        let system = SYSTEM_PROMPT.to_string();
+
+        let head = format!(
+            "Write a structured economic report based on this analysis.\n\n\
+            Indicators: {}\n\
+            Time period: {}\n\n\
+            Analysis:\n",
+            indicator_list.join(", "),
+            time_range,
+        );
+        let tail = format!(
+            "\nReturn your report as JSON with this exact structure:\n\
+            {{\n  \"title\": \"Report title\",\n  \
+            \"executive_summary\": \"2-3 sentence overview\",\n  \
+            \"sections\": [\n    {{\"heading\": \"Section title\", \"content\": \"Section content with cited data references\", \"indicators\": [\"indicator codes the section discusses\"]}}\n  ]\n}}\n\n\
+            {instructions}"
+        );
+
+        let budget = context::budget(
+            llm_client.context_window(model),
+            MAX_TOKENS,
+            max_prompt_tokens,
+        );
+        let packed = context::pack(
+            budget,
+            &format!("{system}{head}{tail}"),
+            analysis_blocks(analysis, data),
+        );
+        context::record("generate", &packed);
 
-    let resp = llm_client
-        .generate(&GenerateRequest {
-            model: model.to_string(),
-            system,
-            prompt,
-            temperature: 0.3,
-            max_tokens: 4096,
-            stage: "generate".to_string(),
-        })
-        .await
-        .map_err(|e| AppError::Llm(e.to_string()))?;
-
-    let provider = resp.provider.clone();
-    let mut narrative = parse_narrative_response(
-        &resp.content,
-        resp.input_tokens,
-        resp.output_tokens,
-        resp.cost_usd,
-    )?;
-    narrative.provider = provider;
-
-    let span = tracing::Span::current();
-    span.record("narrative.title", &narrative.title);
-    span.record("narrative.sections_count", narrative.sections.len());
-
-    Ok(narrative)
+        let prompt = format!("{head}{}{tail}", packed.text);
+
+        let resp = llm_client
+            .generate(&GenerateRequest {
+                model: model.to_string(),
+                system,
+                prompt,
+                temperature: 0.3,
+                max_tokens: MAX_TOKENS,
+                stage: "generate".to_string(),
+            })
+            .await
+            .map_err(|e| AppError::Llm(e.to_string()))?;
+
+        let provider = resp.provider.clone();
+        let mut narrative = parse_narrative_response(
+            &resp.content,
+            resp.input_tokens,
+            resp.output_tokens,
+            resp.cost_usd,
+        )?;
+        narrative.provider = provider;
+        narrative.chat_span = resp.span_context;
+
+        let span = tracing::Span::current();
+        span.record("narrative.title", &narrative.title);
+        span.record("narrative.sections_count", narrative.sections.len());
+
+        Ok(narrative)
+    })
+    .await
+    .record_err()
+}
+
+/// The analysis and the citable data points, by priority: key findings, then
+/// trends and data points, then correlations.
+fn analysis_blocks(analysis: &AnalysisResult, data: &[IndicatorData]) -> Vec<Block> {
+    let mut blocks = vec![Block::new(0, "Key findings:\n")];
+    blocks.extend(
+        analysis
+            .key_findings
+            .iter()
+            .map(|finding| Block::new(0, format!("- {finding}\n"))),
+    );
+    blocks.push(Block::new(1, "Trends:\n"));
+    blocks.extend(analysis.trends.iter().map(|t| {
+        Block::new(
+            1,
+            format!("- {} ({}): {}\n", t.indicator, t.direction, t.description),
+        )
+    }));
+    blocks.push(Block::new(2, "Correlations:\n"));
+    blocks.extend(
+        analysis
+            .correlations
+            .iter()
+            .map(|correlation| Block::new(2, format!("- {correlation}\n"))),
+    );
+    blocks.push(Block::new(1, "Citable data points:\n"));
+    blocks.extend(
+        data.iter()
+            .filter_map(citation::citable_points)
+            .map(|points| Block::new(1, points)),
+    );
+    blocks
 }
 
 fn parse_narrative_response(
@@ -128,6 +231,7 @@ fn parse_narrative_response(
             output_tokens,
             cost_usd,
             provider: String::new(),
+            chat_span: None,
         }),
         Err(_) => Ok(NarrativeResult {
             title: "Economic Report".to_string(),
@@ -135,18 +239,136 @@ fn parse_narrative_response(
             sections: vec![NarrativeSection {
                 heading: "Analysis".to_string(),
                 content: content.to_string(),
+                indicators: Vec::new(),
+                chart: None,
+                citations: Vec::new(),
+                uncited_claims: Vec::new(),
             }],
             input_tokens,
             output_tokens,
             cost_usd,
             provider: String::new(),
+            chat_span: None,
         }),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::NaiveDate;
+    use opentelemetry::KeyValue;
+
     use super::*;
+    use crate::db::data_points::DataPoint;
+    use crate::llm::{Endpoints, GenerateResponse, Provider};
+    use crate::pipeline::TemplateRegistry;
+    use crate::pipeline::analyze::Trend;
+    use crate::telemetry::testing::{SpanCapture, assert_attributes};
+
+    /// Answers with an empty report and keeps the prompts it was sent.
+    #[derive(Default)]
+    struct RecordingProvider {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for RecordingProvider {
+        async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            self.prompts.lock().unwrap().push(req.prompt.clone());
+            Ok(GenerateResponse {
+                content: "{}".to_string(),
+                model: req.model.clone(),
+                input_tokens: 100,
+                output_tokens: 50,
+                cost_usd: 0.0,
+                finish_reason: "stop".to_string(),
+                provider: String::new(),
+                span_context: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prompt_is_packed_into_the_budget() {
+        let provider = Arc::new(RecordingProvider::default());
+        let client = LlmClient {
+            primary: Endpoints::single(provider.clone()),
+            fallback: None,
+            primary_provider: "recording".to_string(),
+            fallback_provider: "none".to_string(),
+            fallback_model: String::new(),
+            observers: vec![],
+            max_retries: 1,
+        };
+        let data = vec![IndicatorData {
+            code: "UNRATE".to_string(),
+            name: "Unemployment Rate".to_string(),
+            unit: "Percent".to_string(),
+            frequency: "Monthly".to_string(),
+            values: vec![DataPoint {
+                observation_date: NaiveDate::from_ymd_opt(2023, 12, 1).unwrap(),
+                value: 3.7,
+            }],
+        }];
+        let analysis = AnalysisResult {
+            trends: vec![Trend {
+                indicator: "UNRATE".to_string(),
+                direction: "decreasing".to_string(),
+                description: "Unemployment fell".to_string(),
+            }],
+            correlations: vec!["Unemployment fell as payrolls grew ".repeat(200)],
+            key_findings: vec!["The labor market recovered".to_string()],
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            provider: String::new(),
+            chat_span: None,
+        };
+        let request = ReportRequest {
+            indicators: vec!["UNRATE".to_string()],
+            start_date: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+            currency: None,
+            language: Language::En,
+            template: TemplateRegistry::load(None).unwrap().get(None).unwrap(),
+            explain: false,
+        };
+        let generate_with = |max_prompt_tokens| {
+            generate(
+                &client,
+                "stub-model",
+                &data,
+                &analysis,
+                &request,
+                max_prompt_tokens,
+                Duration::ZERO,
+            )
+        };
+
+        let spans = SpanCapture::start();
+        generate_with(0).await.unwrap();
+        generate_with(1_200).await.unwrap();
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(prompts[0].contains("payrolls grew"));
+        assert!(!prompts[1].contains("payrolls grew"));
+        assert!(prompts[1].contains("- The labor market recovered\n"));
+        assert!(prompts[1].contains("- UNRATE (decreasing): Unemployment fell\n"));
+        assert!(prompts[1].contains("- UNRATE: 2023-12-01 3.70 (first)"));
+        let stages: Vec<_> = spans
+            .spans()
+            .into_iter()
+            .filter(|s| s.name == "pipeline_stage generate")
+            .collect();
+        assert_attributes(&stages[0], &[KeyValue::new("prompt.dropped_blocks", "0")]);
+        assert_attributes(&stages[1], &[KeyValue::new("prompt.dropped_blocks", "1")]);
+    }
 
     #[test]
     fn test_parse_narrative_valid() {
@@ -161,6 +383,14 @@ mod tests {
         assert!(result.provider.is_empty());
     }
 
+    #[test]
+    fn test_parse_narrative_keeps_indicators_but_not_charts() {
+        let content = r#"{"sections": [{"heading": "Jobs", "content": "Unemployment fell.", "indicators": ["UNRATE"], "chart": {"spec": {}}}]}"#;
+        let result = parse_narrative_response(content, 100, 50, 0.01).unwrap();
+        assert_eq!(result.sections[0].indicators, ["UNRATE"]);
+        assert!(result.sections[0].chart.is_none());
+    }
+
     #[test]
     fn test_parse_narrative_partial() {
         let content = r#"{"title": "Partial Report"}"#;
@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use opentelemetry::KeyValue;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::db::data_points::IndicatorData;
+use crate::error::{AppError, RecordErr};
+use crate::telemetry::metrics::REPORT_GUARDRAIL_VIOLATIONS;
+
+use super::generate::NarrativeResult;
+use super::locale::Language;
+use super::{analyze, citation, generate, stats};
+
+/// Consecutive words a narrative has to share with a system prompt before it
+/// counts as repeating it.
+const LEAK_WINDOW_WORDS: usize = 8;
+
+/// Numbers (with English, German or French separators), plus identifiers
+/// such as `COVID-19` or `M2SL` so their digits are not mistaken for claims.
+static NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z]+-?\d+|\d(?:[\d,.\u{a0}\u{202f}]*\d)?").unwrap());
+
+/// Day-and-month phrases ("December 31", "31. Dezember", "31 décembre"),
+/// removed before numbers are extracted.
+static DAY_OF_MONTH: LazyLock<Regex> = LazyLock::new(|| {
+    let month = r"(?:jan|feb|f[ée]v|m[äa]r|apr|avr|may|mai|jun|juin|jul|juil|aug|ao[ûu]|sep|o[ck]t|nov|d[ée][cz])\w*\.?";
+    Regex::new(&format!(
+        r"(?i)\b\d{{1,2}}\.?\s+{month}|\b{month}\s+\d{{1,2}}\b"
+    ))
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardrailMode {
+    Off,
+    /// Keep the report and list the violations on it.
+    Annotate,
+    /// Fail the request instead of returning a report with violations.
+    Reject,
+}
+
+impl FromStr for GuardrailMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "annotate" => Ok(Self::Annotate),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("expected off, annotate or reject, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for GuardrailMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "off",
+            Self::Annotate => "annotate",
+            Self::Reject => "reject",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    PromptLeak,
+    UnsupportedNumber,
+    BannedPhrase,
+}
+
+impl Rule {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PromptLeak => "prompt_leak",
+            Self::UnsupportedNumber => "unsupported_number",
+            Self::BannedPhrase => "banned_phrase",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub rule: Rule,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Guardrails {
+    mode: GuardrailMode,
+    banned_phrases: Vec<String>,
+    prompt_windows: HashSet<String>,
+}
+
+impl Guardrails {
+    /// `banned_phrases` is `;`-separated and matched case-insensitively.
+    pub fn new(mode: GuardrailMode, banned_phrases: &str) -> Self {
+        Self {
+            mode,
+            banned_phrases: banned_phrases
+                .split(';')
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            prompt_windows: [analyze::SYSTEM_PROMPT, generate::SYSTEM_PROMPT]
+                .iter()
+                .flat_map(|prompt| windows(&words(prompt)))
+                .collect(),
+        }
+    }
+
+    pub fn mode(&self) -> GuardrailMode {
+        self.mode
+    }
+
+    pub fn check(
+        &self,
+        narrative: &NarrativeResult,
+        data: &[IndicatorData],
+        language: Language,
+    ) -> Vec<Violation> {
+        let text = narrative_text(narrative);
+
+        let mut violations = Vec::new();
+        if let Some(window) = windows(&words(&text))
+            .into_iter()
+            .find(|w| self.prompt_windows.contains(w))
+        {
+            violations.push(Violation {
+                rule: Rule::PromptLeak,
+                detail: format!("narrative repeats system prompt text {window:?}"),
+            });
+        }
+        violations.extend(unsupported_numbers(&text, data, language));
+
+        let lower = text.to_lowercase();
+        violations.extend(
+            self.banned_phrases
+                .iter()
+                .filter(|phrase| lower.contains(phrase.as_str()))
+                .map(|phrase| Violation {
+                    rule: Rule::BannedPhrase,
+                    detail: format!("narrative contains banned phrase {phrase:?}"),
+                }),
+        );
+
+        violations
+    }
+}
+
+#[tracing::instrument(
+    name = "pipeline_stage guardrail",
+    skip_all,
+    fields(
+        pipeline.stage = "guardrail",
+        guardrail.mode = %guardrails.mode(),
+        guardrail.violations,
+    )
+)]
+pub fn review(
+    guardrails: &Guardrails,
+    narrative: &NarrativeResult,
+    data: &[IndicatorData],
+    language: Language,
+) -> Result<Vec<Violation>, AppError> {
+    let violations = guardrails.check(narrative, data, language);
+
+    let span = tracing::Span::current();
+    span.record("guardrail.violations", violations.len());
+    for violation in &violations {
+        span.add_event(
+            "guardrail.violation",
+            vec![
+                KeyValue::new("guardrail.rule", violation.rule.as_str()),
+                KeyValue::new("guardrail.detail", violation.detail.clone()),
+            ],
+        );
+        REPORT_GUARDRAIL_VIOLATIONS.add(
+            1,
+            &[
+                KeyValue::new("guardrail.rule", violation.rule.as_str()),
+                KeyValue::new("guardrail.mode", guardrails.mode().to_string()),
+            ],
+        );
+    }
+
+    if guardrails.mode() == GuardrailMode::Reject && !violations.is_empty() {
+        let rules: Vec<&str> = violations.iter().map(|v| v.rule.as_str()).collect();
+        return Err(AppError::GuardrailRejected(rules.join(", "))).record_err();
+    }
+    if !violations.is_empty() {
+        tracing::warn!(
+            violations = violations.len(),
+            "Report narrative has guardrail violations"
+        );
+    }
+
+    Ok(violations)
+}
+
+fn narrative_text(narrative: &NarrativeResult) -> String {
+    let mut text = format!("{}\n{}", narrative.title, narrative.executive_summary);
+    for section in &narrative.sections {
+        text.push_str(&format!("\n{}\n{}", section.heading, section.content));
+    }
+    text
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn windows(words: &[String]) -> Vec<String> {
+    words
+        .windows(LEAK_WINDOW_WORDS)
+        .map(|w| w.join(" "))
+        .collect()
+}
+
+/// A number is supported when it is a rounding of a data value or of one of
+/// the statistics the prompts are built from, optionally scaled by a
+/// thousand (e.g. billions written as trillions).
+fn unsupported_numbers(text: &str, data: &[IndicatorData], language: Language) -> Vec<Violation> {
+    let mut supported = Vec::new();
+    for indicator in data {
+        supported.extend(indicator.values.iter().map(|v| v.value.abs()));
+        if let Some(s) = stats::summarize(indicator) {
+            supported.extend([s.first, s.last, s.min, s.max, s.mean].map(f64::abs));
+            supported.push((s.last - s.first).abs());
+            supported.push(s.max - s.min);
+            supported.extend(s.change_pct().map(f64::abs));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    claims(text, language)
+        .into_iter()
+        .filter(|(token, _, _)| seen.insert(token.clone()))
+        .filter_map(|(token, value, decimals)| {
+            let tolerance = 0.5 * 10f64.powi(-(decimals as i32)) + 1e-9;
+            let matches = [1.0, 1000.0, 0.001].iter().any(|scale| {
+                supported
+                    .iter()
+                    .any(|v| (value * scale - v).abs() <= tolerance * scale)
+            });
+            (!matches).then(|| Violation {
+                rule: Rule::UnsupportedNumber,
+                detail: format!("{token} does not match any retrieved value"),
+            })
+        })
+        .collect()
+}
+
+/// The numbers in `text` that read as data claims, with their value and
+/// decimals: not years, small counts, days of the month or citation dates.
+pub fn claims(text: &str, language: Language) -> Vec<(String, f64, usize)> {
+    let text = citation::strip_markers(text);
+    let text = DAY_OF_MONTH.replace_all(&text, " ");
+    NUMBER
+        .find_iter(&text)
+        .map(|m| m.as_str())
+        .filter(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+        .filter_map(|token| {
+            let (value, decimals) = language.parse_number(token)?;
+            // Small counts ("3 sections") and years are not data claims.
+            if decimals == 0 && (value <= 12.0 || (1900.0..=2100.0).contains(&value)) {
+                return None;
+            }
+            Some((token.to_string(), value, decimals))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::db::data_points::DataPoint;
+    use crate::pipeline::generate::NarrativeSection;
+
+    fn unrate() -> Vec<IndicatorData> {
+        let values = [(2020, 3.5), (2021, 6.4), (2022, 3.9)]
+            .into_iter()
+            .map(|(year, value)| DataPoint {
+                observation_date: NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+                value,
+            })
+            .collect();
+        vec![IndicatorData {
+            code: "UNRATE".to_string(),
+            name: "Unemployment Rate".to_string(),
+            unit: "Percent".to_string(),
+            frequency: "Monthly".to_string(),
+            values,
+        }]
+    }
+
+    fn narrative(content: &str) -> NarrativeResult {
+        NarrativeResult {
+            title: "Labor market".to_string(),
+            executive_summary: "Unemployment spiked during COVID-19.".to_string(),
+            sections: vec![NarrativeSection {
+                heading: "Trends".to_string(),
+                content: content.to_string(),
+                indicators: Vec::new(),
+                chart: None,
+                citations: Vec::new(),
+                uncited_claims: Vec::new(),
+            }],
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            provider: "openai".to_string(),
+            chat_span: None,
+        }
+    }
+
+    fn rules(violations: &[Violation]) -> Vec<Rule> {
+        violations.iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn test_grounded_narrative_passes() {
+        let guardrails = Guardrails::new(GuardrailMode::Annotate, "as an ai language model");
+        let text = "Unemployment rose from 3.5% in 2020 to 6.4 percent in 2021, \
+                    then fell to 3.9% by January 31, 2022, an 11.4% change over 3 years.";
+
+        assert!(
+            guardrails
+                .check(&narrative(text), &unrate(), Language::En)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_each_rule_is_reported() {
+        let guardrails = Guardrails::new(GuardrailMode::Annotate, "As an AI language model");
+        let text = "As an AI language model, I note unemployment reached 14.7% in 2020. \
+                    You are an expert economic analyst writing structured reports.";
+
+        let violations = guardrails.check(&narrative(text), &unrate(), Language::En);
+        assert_eq!(
+            rules(&violations),
+            vec![
+                Rule::PromptLeak,
+                Rule::UnsupportedNumber,
+                Rule::BannedPhrase
+            ]
+        );
+        assert!(violations[1].detail.starts_with("14.7"));
+    }
+
+    #[test]
+    fn test_localized_numbers_and_dates() {
+        let guardrails = Guardrails::new(GuardrailMode::Annotate, "");
+        let text = "Die Arbeitslosenquote stieg vom 15. März 2020 bis 2021 auf 6,4 % \
+                    und lag am 31. Dezember 2022 bei 3,9 %.";
+
+        assert!(
+            guardrails
+                .check(&narrative(text), &unrate(), Language::De)
+                .is_empty()
+        );
+        assert_eq!(
+            rules(&guardrails.check(&narrative(text), &unrate(), Language::En)),
+            vec![Rule::UnsupportedNumber, Rule::UnsupportedNumber]
+        );
+    }
+
+    #[test]
+    fn test_citation_markers_are_not_claims() {
+        let guardrails = Guardrails::new(GuardrailMode::Annotate, "");
+        let text = "Unemployment rose to 6.4% [UNRATE@2021-01-01] and fell to 3.9% \
+                    [UNRATE@2022-01-01].";
+
+        assert!(
+            guardrails
+                .check(&narrative(text), &unrate(), Language::En)
+                .is_empty()
+        );
+        assert_eq!(claims(text, Language::En).len(), 2);
+    }
+
+    #[test]
+    fn test_reject_mode_fails_the_report() {
+        let guardrails = Guardrails::new(GuardrailMode::Reject, "");
+
+        assert!(
+            review(
+                &guardrails,
+                &narrative("Unemployment hit 3.9%."),
+                &unrate(),
+                Language::En
+            )
+            .is_ok()
+        );
+        let err = review(
+            &guardrails,
+            &narrative("Unemployment hit 25%."),
+            &unrate(),
+            Language::En,
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::GuardrailRejected(rules) if rules == "unsupported_number"));
+    }
+
+    #[test]
+    fn test_mode_parses() {
+        assert_eq!("Reject".parse::<GuardrailMode>(), Ok(GuardrailMode::Reject));
+        assert!("strict".parse::<GuardrailMode>().is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use sqlx::PgPool;
+use tokio::time::{Instant, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::db::reports::fail_stalled_reports;
+use crate::telemetry::metrics::REPORT_STALLED;
+
+use super::sink::ReportSink;
+use super::watch::ReportWatch;
+
+/// Runs `work`, sending the report's heartbeat to `sink` every `interval`
+/// until it finishes. The first one goes out an `interval` in, the row having
+/// just been written.
+pub async fn with_heartbeat<T>(
+    sink: &dyn ReportSink,
+    report_id: Uuid,
+    interval: Duration,
+    work: impl Future<Output = T>,
+) -> T {
+    let beats = async {
+        let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = sink.heartbeat(report_id).await {
+                tracing::warn!(
+                    report.id = %report_id,
+                    error = %err,
+                    "Failed to record report heartbeat"
+                );
+            }
+        }
+    };
+    tokio::select! {
+        result = work => result,
+        _ = beats => unreachable!("heartbeats run until the work is done"),
+    }
+}
+
+/// Every `interval`, fails the pending reports whose heartbeat is more than
+/// `stall_secs` old: their pipeline died with its replica. Each is counted
+/// in `report.stalled` and can be resumed from its checkpoint. Zero
+/// `stall_secs` leaves them pending.
+pub fn spawn_janitor(pool: PgPool, watch: Arc<ReportWatch>, stall_secs: u64, interval: Duration) {
+    if stall_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match fail_stalled_reports(&pool, stall_secs as i64).await {
+                Ok(stalled) => {
+                    for report in stalled {
+                        tracing::warn!(
+                            report.id = %report.id,
+                            report.type = %report.report_type,
+                            "Report heartbeat stalled, marked failed"
+                        );
+                        REPORT_STALLED.add(1, &[KeyValue::new("report.type", report.report_type)]);
+                        watch.notify(report.id);
+                    }
+                }
+                Err(err) => tracing::warn!(error = %err, "Failed to check for stalled reports"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::pipeline::sink::MemorySink;
+
+    #[tokio::test]
+    async fn test_heartbeats_while_the_work_runs() {
+        let sink = MemorySink::default();
+        let work = tokio::time::sleep(Duration::from_millis(110));
+
+        with_heartbeat(&sink, Uuid::new_v4(), Duration::from_millis(25), work).await;
+
+        assert!(sink.heartbeats.load(Ordering::Relaxed) >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_quick_work_sends_no_heartbeat() {
+        let sink = MemorySink::default();
+
+        let value =
+            with_heartbeat(&sink, Uuid::new_v4(), Duration::from_secs(1), async { 7 }).await;
+
+        assert_eq!(value, 7);
+        assert_eq!(sink.heartbeats.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use icu::datetime::DateTimeFormatter;
+use icu::datetime::fieldsets::YMD;
+use icu::datetime::input::Date;
+use icu::decimal::DecimalFormatter;
+use icu::decimal::input::Decimal;
+use icu::locale::{Locale, locale};
+use serde::{Deserialize, Serialize};
+
+/// Output language of a report: the narrative the LLM writes and the
+/// dates and numbers the pipeline formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl Language {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Fr => "fr",
+        }
+    }
+
+    /// English name, for prompt instructions.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::De => "German",
+            Self::Fr => "French",
+        }
+    }
+
+    fn locale(self) -> Locale {
+        match self {
+            Self::En => locale!("en"),
+            Self::De => locale!("de"),
+            Self::Fr => locale!("fr"),
+        }
+    }
+
+    pub fn format_date(self, date: NaiveDate) -> String {
+        let formatted = Date::try_new_iso(date.year(), date.month() as u8, date.day() as u8)
+            .ok()
+            .zip(DateTimeFormatter::try_new(self.locale().into(), YMD::long()).ok())
+            .map(|(input, formatter)| formatter.format(&input).to_string());
+        formatted.unwrap_or_else(|| date.to_string())
+    }
+
+    pub fn format_number(self, value: f64, decimals: u8) -> String {
+        let scaled = (value * 10f64.powi(decimals as i32)).round() as i64;
+        let mut decimal = Decimal::from(scaled);
+        decimal.multiply_pow10(-(decimals as i16));
+        match DecimalFormatter::try_new(self.locale().into(), Default::default()) {
+            Ok(formatter) => formatter.format(&decimal).to_string(),
+            Err(_) => format!("{value:.prec$}", prec = decimals as usize),
+        }
+    }
+
+    /// Reads a number written the way `format_number` writes it, returning
+    /// the value and how many decimals it was given with.
+    pub fn parse_number(self, token: &str) -> Option<(f64, usize)> {
+        let (grouping, decimal): (&[char], char) = match self {
+            Self::En => (&[','], '.'),
+            Self::De => (&['.'], ','),
+            Self::Fr => (&['\u{202f}', '\u{a0}', ' '], ','),
+        };
+        let digits: String = token.chars().filter(|c| !grouping.contains(c)).collect();
+        let decimals = digits.split_once(decimal).map_or(0, |(_, frac)| frac.len());
+        let value = digits.replace(decimal, ".").parse().ok()?;
+        Some((value, decimals))
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Self::En),
+            "de" => Ok(Self::De),
+            "fr" => Ok(Self::Fr),
+            other => Err(format!("expected en, de or fr, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_round_trip_per_language() {
+        for (language, formatted) in [
+            (Language::En, "27,357.80"),
+            (Language::De, "27.357,80"),
+            (Language::Fr, "27\u{202f}357,80"),
+        ] {
+            assert_eq!(language.format_number(27357.8, 2), formatted);
+            assert_eq!(language.parse_number(formatted), Some((27357.8, 2)));
+        }
+    }
+
+    #[test]
+    fn test_dates_are_localized() {
+        let date = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+
+        assert_eq!(Language::En.format_date(date), "December 31, 2023");
+        assert_eq!(Language::De.format_date(date), "31. Dezember 2023");
+        assert_eq!(Language::Fr.format_date(date), "31 décembre 2023");
+    }
+
+    #[test]
+    fn test_language_parses() {
+        assert_eq!("DE".parse::<Language>(), Ok(Language::De));
+        assert!("es".parse::<Language>().is_err());
+    }
+}
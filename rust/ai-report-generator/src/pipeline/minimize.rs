@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::db::data_points::{DataPoint, IndicatorData};
+
+/// Significant figures a value keeps in a strict prompt.
+const SIGNIFICANT_DIGITS: i32 = 2;
+
+/// How much of the retrieved data the LLM stages see.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataMinimization {
+    /// The data as retrieved.
+    #[default]
+    Off,
+    /// Values rounded to two significant figures, observations more frequent
+    /// than monthly averaged per month and dated the 1st, and indicators
+    /// named by their code only.
+    Strict,
+}
+
+impl DataMinimization {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Strict => "strict",
+        }
+    }
+}
+
+impl FromStr for DataMinimization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "strict" => Ok(Self::Strict),
+            other => Err(format!("expected off or strict, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for DataMinimization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The data as the LLM stages may see it under `mode`. The charts keep the
+/// retrieved data.
+pub fn minimize(mode: DataMinimization, data: &[IndicatorData]) -> Cow<'_, [IndicatorData]> {
+    match mode {
+        DataMinimization::Off => Cow::Borrowed(data),
+        DataMinimization::Strict => Cow::Owned(data.iter().map(strict).collect()),
+    }
+}
+
+fn strict(indicator: &IndicatorData) -> IndicatorData {
+    let mut months: Vec<(NaiveDate, f64, usize)> = Vec::new();
+    for point in &indicator.values {
+        let month = point.observation_date.with_day(1).unwrap();
+        match months.last_mut() {
+            Some((last, sum, count)) if *last == month => {
+                *sum += point.value;
+                *count += 1;
+            }
+            _ => months.push((month, point.value, 1)),
+        }
+    }
+    let aggregated = months.len() < indicator.values.len();
+
+    IndicatorData {
+        code: indicator.code.clone(),
+        name: indicator.code.clone(),
+        unit: indicator.unit.clone(),
+        frequency: if aggregated {
+            "Monthly".to_string()
+        } else {
+            indicator.frequency.clone()
+        },
+        values: months
+            .into_iter()
+            .map(|(month, sum, count)| DataPoint {
+                observation_date: month,
+                value: round_significant(sum / count as f64),
+            })
+            .collect(),
+    }
+}
+
+/// Codes of the indicators `minimize` averaged per month.
+pub fn aggregated(retrieved: &[IndicatorData], minimized: &[IndicatorData]) -> Vec<String> {
+    retrieved
+        .iter()
+        .zip(minimized)
+        .filter(|(r, m)| m.values.len() < r.values.len())
+        .map(|(r, _)| r.code.clone())
+        .collect()
+}
+
+fn round_significant(value: f64) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let factor = 10f64.powi(SIGNIFICANT_DIGITS - 1 - magnitude);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indicator(frequency: &str, points: &[(u32, u32, f64)]) -> IndicatorData {
+        IndicatorData {
+            code: "DGS10".to_string(),
+            name: "10-Year Treasury Constant Maturity Rate".to_string(),
+            unit: "Percent".to_string(),
+            frequency: frequency.to_string(),
+            values: points
+                .iter()
+                .map(|&(month, day, value)| DataPoint {
+                    observation_date: NaiveDate::from_ymd_opt(2023, month, day).unwrap(),
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_significant() {
+        assert_eq!(round_significant(27357.8), 27000.0);
+        assert_eq!(round_significant(3.68), 3.7);
+        assert_eq!(round_significant(-0.0456), -0.046);
+        assert_eq!(round_significant(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_strict_averages_daily_points_per_month() {
+        let data = vec![indicator(
+            "Daily",
+            &[(1, 3, 3.81), (1, 4, 3.91), (2, 1, 3.39), (2, 2, 3.53)],
+        )];
+
+        let minimized = minimize(DataMinimization::Strict, &data);
+        let ind = &minimized[0];
+        assert_eq!(ind.name, "DGS10");
+        assert_eq!(ind.frequency, "Monthly");
+        let values: Vec<(String, f64)> = ind
+            .values
+            .iter()
+            .map(|v| (v.observation_date.to_string(), v.value))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("2023-01-01".to_string(), 3.9),
+                ("2023-02-01".to_string(), 3.5)
+            ]
+        );
+        assert_eq!(aggregated(&data, &minimized), ["DGS10"]);
+    }
+
+    #[test]
+    fn test_strict_keeps_monthly_dates() {
+        let data = vec![indicator("Monthly", &[(1, 1, 3.4), (2, 1, 3.6)])];
+        let minimized = minimize(DataMinimization::Strict, &data);
+        assert_eq!(minimized[0].frequency, "Monthly");
+        assert_eq!(minimized[0].values.len(), 2);
+        assert!(aggregated(&data, &minimized).is_empty());
+
+        assert!(matches!(
+            minimize(DataMinimization::Off, &data),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_mode_parses() {
+        assert_eq!("STRICT".parse(), Ok(DataMinimization::Strict));
+        assert_eq!("off".parse(), Ok(DataMinimization::Off));
+        assert!("bucketed".parse::<DataMinimization>().is_err());
+    }
+}
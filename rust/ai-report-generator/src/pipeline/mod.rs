@@ -1,7 +1,34 @@
+pub mod admission;
 pub mod analyze;
+pub mod batch;
+pub mod cancel;
+pub mod chart;
+pub mod citation;
+pub mod context;
 pub mod format;
 pub mod generate;
+pub mod guardrail;
+pub mod heartbeat;
+pub mod locale;
+pub mod minimize;
 pub mod orchestrator;
 pub mod retrieve;
+pub mod sink;
+pub mod stats;
+pub mod summarize;
+pub mod templates;
+pub mod timeout;
+pub mod watch;
 
-pub use orchestrator::{ReportRequest, generate_report};
+pub use admission::AdmissionController;
+pub use batch::BatchPipeline;
+pub use cancel::RunningReports;
+pub use guardrail::{GuardrailMode, Guardrails};
+pub use minimize::DataMinimization;
+pub use orchestrator::{
+    Checkpoint, GenerationMode, Pipeline, ReportRequest, expand_groups, generate_report,
+};
+pub use sink::PgReportSink;
+pub use templates::TemplateRegistry;
+pub use timeout::StageTimeouts;
+pub use watch::ReportWatch;
@@ -1,107 +1,883 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use chrono::NaiveDate;
-use opentelemetry::trace::TraceContextExt;
-use serde::Deserialize;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
 
-use crate::db::reports::InsertReport;
-use crate::error::AppError;
+use crate::db::indicator_groups::{IndicatorGroup, find_indicator_groups};
+use crate::error::{AppError, RecordErr};
+use crate::explain::{Decision, Explainer};
 use crate::llm::LlmClient;
-use crate::telemetry::metrics::{REPORT_DATA_POINTS, REPORT_GENERATION_DURATION, REPORT_SECTIONS};
+use crate::telemetry::metrics::REPORT_DEGRADED;
 
-use super::format::{self, FormatParams, Report};
-use super::{analyze, generate, retrieve};
+use super::analyze::AnalysisResult;
+use super::format::{self, FormatParams, Report, ReportStatus};
+use super::guardrail::{self, GuardrailMode, Guardrails};
+use super::locale::Language;
+use super::minimize::{self, DataMinimization};
+use super::retrieve::RetrieveResult;
+use super::sink::{ReportEvent, ReportSink, StageStatus};
+use super::templates::ReportTemplate;
+use super::timeout::StageTimeouts;
+use super::{analyze, generate, heartbeat, retrieve, stats};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct ReportRequest {
     pub indicators: Vec<String>,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
+    pub currency: Option<String>,
+    pub language: Language,
+    /// Resolved from `report_type` and validated against `indicators`.
+    pub template: Arc<ReportTemplate>,
+    /// Record why the report came out as it did, see [`crate::explain`].
+    pub explain: bool,
+}
+
+impl ReportRequest {
+    /// Identifies the report this request would produce with `models` from
+    /// data minimized by `minimization`: the same indicators in any order,
+    /// date range, currency, language and report type, and whether it is
+    /// explained. Stored with the report, so a repeat of the request can be
+    /// answered with it.
+    pub fn fingerprint(&self, models: &[&str], minimization: DataMinimization) -> String {
+        let mut indicators = self.indicators.clone();
+        indicators.sort();
+        indicators.dedup();
+        let mut canonical = [
+            indicators.join(","),
+            self.start_date.to_string(),
+            self.end_date.to_string(),
+            self.currency.clone().unwrap_or_default(),
+            self.language.as_str().to_string(),
+            self.template.name.clone(),
+            models.join(","),
+        ]
+        .join("\n");
+        // Appended only when set, so unexplained reports keep their
+        // fingerprints from before explanations.
+        if self.explain {
+            canonical.push_str("\nexplain");
+        }
+        // Likewise, so reports from unminimized data keep theirs, and a
+        // stricter mode never reuses one made from more of the data.
+        if minimization != DataMinimization::Off {
+            canonical.push_str("\nminimization=");
+            canonical.push_str(minimization.as_str());
+        }
+        hex::encode(Sha256::digest(canonical))
+    }
+}
+
+/// `indicators` followed by the members of each of `groups` (indicator
+/// group names, in any case), without repeats. A name no group has is a
+/// validation error.
+#[tracing::instrument(name = "pipeline expand_groups", skip(pool, indicators))]
+pub async fn expand_groups(
+    pool: &PgPool,
+    indicators: &[String],
+    groups: &[String],
+) -> Result<Vec<String>, AppError> {
+    if groups.is_empty() {
+        return Ok(indicators.to_vec());
+    }
+    let found = find_indicator_groups(pool, groups)
+        .await
+        .map_err(AppError::Database)?;
+    merge_groups(indicators, groups, &found)
+}
+
+fn merge_groups(
+    indicators: &[String],
+    groups: &[String],
+    found: &[IndicatorGroup],
+) -> Result<Vec<String>, AppError> {
+    let mut expanded: Vec<String> = Vec::new();
+    let mut add = |code: &String| {
+        if !expanded.contains(code) {
+            expanded.push(code.clone());
+        }
+    };
+    indicators.iter().for_each(&mut add);
+
+    let mut unknown = Vec::new();
+    for name in groups {
+        match found.iter().find(|g| g.name.eq_ignore_ascii_case(name)) {
+            Some(group) => group.indicators.iter().for_each(&mut add),
+            None => unknown.push(name.as_str()),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(AppError::Validation(format!(
+            "unknown indicator groups: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(expanded)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerationMode {
+    /// Answer the request once the report is ready.
+    #[default]
+    Interactive,
+    /// Accept the request immediately and generate the report in the
+    /// background through the OpenAI Batch API.
+    Batch,
+}
+
+/// Stage output saved to the report row as the pipeline goes, so a report
+/// whose pipeline failed or died can be resumed without paying for its LLM
+/// calls again. Only the analysis is kept: it's the first of them, and the
+/// data is cheap to retrieve again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub analysis: AnalysisResult,
+}
+
+impl Checkpoint {
+    /// The last stage the checkpoint covers.
+    pub fn stage(&self) -> &'static str {
+        "analyze"
+    }
 }
 
+/// What the stages run against. Borrowed from the caller, so the interactive
+/// and batch paths share one orchestration.
+#[derive(Clone, Copy)]
+pub struct Pipeline<'a> {
+    pub pool: &'a PgPool,
+    pub llm_client: &'a LlmClient,
+    pub guardrails: &'a Guardrails,
+    pub model_capable: &'a str,
+    pub model_fast: &'a str,
+    /// Caps the analyze and generate prompts below the model's context
+    /// window; 0 for no cap.
+    pub max_prompt_tokens: usize,
+    /// Applied to the data the analyze and generate prompts are written
+    /// from.
+    pub minimization: DataMinimization,
+    pub timeouts: StageTimeouts,
+    /// How often `sink` gets the report's heartbeat; `None` for reports
+    /// that have no row until they are stored.
+    pub heartbeat: Option<Duration>,
+}
+
+/// Runs retrieve, analyze, generate and format, then hands the report to
+/// `sink`. Each stage's start and end go to `sink` as they happen, and the
+/// analysis as a checkpoint. With `checkpoint`, the stages it covers are
+/// taken from it instead of run. An explained request's report carries the
+/// decisions made along the way.
 #[tracing::instrument(
     name = "pipeline report",
-    skip(pool, llm_client),
+    skip(pipeline, sink, report_id, checkpoint),
     fields(
-        report.id,
+        report.id = %report_id,
+        report.type = %request.template.name,
+        report.language = %request.language,
         report.indicators_count,
         report.duration_ms,
+        report.status,
+        report.degraded_stage,
+        report.resumed_stage = checkpoint.map(Checkpoint::stage),
+        report.data_minimization = pipeline.minimization.as_str(),
+        report.total_tokens,
+        report.cost_usd,
+        report.analyze.tokens,
+        report.analyze.cost_usd,
+        report.generate.tokens,
+        report.generate.cost_usd,
     )
 )]
 pub async fn generate_report(
-    pool: &PgPool,
-    llm_client: &LlmClient,
-    model_capable: &str,
-    model_fast: &str,
+    pipeline: &Pipeline<'_>,
+    sink: &dyn ReportSink,
+    report_id: Uuid,
     request: &ReportRequest,
+    checkpoint: Option<&Checkpoint>,
 ) -> Result<Report, AppError> {
-    let start = std::time::Instant::now();
-
-    let span = tracing::Span::current();
-    let context = span.context();
-    let otel_span = context.span();
-    let trace_id = otel_span.span_context().trace_id().to_string();
-
-    // Stage 1: Retrieve data from PostgreSQL
-    let data = retrieve::retrieve(
-        pool,
-        &request.indicators,
-        request.start_date,
-        request.end_date,
-    )
-    .await?;
-
-    // Stage 2: Analyze trends via LLM (fast model)
-    let analysis = analyze::analyze(llm_client, model_fast, &data.indicators).await?;
-
-    // Stage 3: Generate narrative via LLM (capable model)
-    let narrative =
-        generate::generate(llm_client, model_capable, &data.indicators, &analysis).await?;
-
-    // Stage 4: Format final report
-    let duration = start.elapsed();
-    let report = format::format_report(FormatParams {
-        retrieve_result: &data,
-        analysis: &analysis,
-        narrative: &narrative,
-        indicators_requested: &request.indicators,
-        start_date: request.start_date,
-        end_date: request.end_date,
-        duration,
-        trace_id,
-    })?;
-
-    // Persist to database
-    let sections_json = serde_json::to_value(&report.sections).unwrap_or_default();
-    crate::db::reports::insert_report(
-        pool,
-        &InsertReport {
-            id: report.id,
-            title: &report.title,
-            executive_summary: &report.executive_summary,
-            sections: &sections_json,
-            indicators_used: &report.indicators_used,
-            time_range_start: report.time_range_start,
-            time_range_end: report.time_range_end,
-            total_data_points: report.total_data_points as i32,
-            total_tokens: report.total_tokens as i32,
-            total_cost_usd: report.total_cost_usd,
-            providers_used: &report.providers_used,
-            generation_duration_ms: report.generation_duration_ms as i32,
-            trace_id: Some(&report.trace_id),
-        },
-    )
-    .await
-    .map_err(AppError::Database)?;
+    let work = async move {
+        let start = Instant::now();
+        Explainer::record(|| Decision::Template {
+            name: request.template.name.clone(),
+            version: request.template.version(),
+        });
+
+        // Stage 1: Retrieve data from PostgreSQL
+        let stage = Stage::start(sink, report_id, "retrieve").await;
+        let data = retrieve::retrieve(
+            pipeline.pool,
+            &request.indicators,
+            request.start_date,
+            request.end_date,
+            request.currency.as_deref(),
+            pipeline.timeouts.retrieve,
+        )
+        .await;
+        let data = stage.end(data).await?;
+
+        let mut report = pipeline
+            .build(sink, report_id, request, checkpoint, &data, start)
+            .await?;
+        report.explanation = Explainer::current();
+        sink.store(&report).await?;
+
+        let span = tracing::Span::current();
+        span.record("report.indicators_count", report.indicators_used.len());
+        span.record("report.duration_ms", report.generation_duration_ms);
+        span.record("report.status", report.status.as_str());
+        span.record("report.total_tokens", report.total_tokens);
+        span.record("report.cost_usd", report.total_cost_usd);
+
+        Ok(report)
+    };
+    let run = async move {
+        match pipeline.heartbeat {
+            Some(interval) => heartbeat::with_heartbeat(sink, report_id, interval, work).await,
+            None => work.await,
+        }
+    };
+    let result = if request.explain {
+        Arc::new(Explainer::default()).scope(run).await
+    } else {
+        run.await
+    };
+    result.record_err()
+}
+
+impl Pipeline<'_> {
+    /// Stages 2 to 5 on the retrieved data. Writes nothing but the stage
+    /// events; `start` is when the report was started, for its duration.
+    async fn build(
+        &self,
+        sink: &dyn ReportSink,
+        report_id: Uuid,
+        request: &ReportRequest,
+        checkpoint: Option<&Checkpoint>,
+        data: &RetrieveResult,
+        start: Instant,
+    ) -> Result<Report, AppError> {
+        let span = tracing::Span::current();
+        let context = span.context();
+        let otel_span = context.span();
+        let trace_id = otel_span.span_context().trace_id().to_string();
+        let report_type = request.template.name.as_str();
+
+        // What the LLM stages see, and what the narrative is checked against
+        let prompt_data = minimize::minimize(self.minimization, &data.indicators);
+        if self.minimization != DataMinimization::Off {
+            Explainer::record(|| Decision::DataMinimized {
+                mode: self.minimization.as_str().to_string(),
+                aggregated: minimize::aggregated(&data.indicators, &prompt_data),
+            });
+        }
+
+        // Stage 2: Analyze trends via LLM (fast model)
+        let mut status = ReportStatus::Completed;
+        let analysis = if let Some(checkpoint) = checkpoint {
+            Stage::resume(sink, report_id, "analyze").await;
+            Explainer::record(|| Decision::Resumed {
+                stage: checkpoint.stage().to_string(),
+            });
+            checkpoint.analysis.clone()
+        } else {
+            let stage = Stage::start(sink, report_id, "analyze").await;
+            match analyze::analyze(
+                self.llm_client,
+                self.model_fast,
+                &prompt_data,
+                self.max_prompt_tokens,
+                self.timeouts.analyze,
+            )
+            .await
+            {
+                Ok(analysis) => {
+                    stage
+                        .finish(Some((analysis.input_tokens, analysis.output_tokens)))
+                        .await;
+                    stage.checkpoint(&analysis).await;
+                    analysis
+                }
+                Err(err @ (AppError::Llm(_) | AppError::StageTimeout { .. })) => {
+                    let err = err.to_string();
+                    status = degrade(&span, "analyze", &err, report_type);
+                    stage.degrade(&err).await;
+                    stats::analysis(&data.indicators)
+                }
+                Err(err) => return Err(stage.fail(err).await),
+            }
+        };
+        span.record(
+            "report.analyze.tokens",
+            analysis.input_tokens + analysis.output_tokens,
+        );
+        span.record("report.analyze.cost_usd", analysis.cost_usd);
+        link_chat_span(&span, "analyze", analysis.chat_span.clone());
+
+        // Stage 3: Generate narrative via LLM (capable model), skipped once the
+        // providers have already failed
+        let narrative = if status == ReportStatus::Degraded {
+            stats::narrative(&analysis)
+        } else {
+            let stage = Stage::start(sink, report_id, "generate").await;
+            let result = generate::generate(
+                self.llm_client,
+                self.model_capable,
+                &prompt_data,
+                &analysis,
+                request,
+                self.max_prompt_tokens,
+                self.timeouts.generate,
+            )
+            .await;
+            match result {
+                Ok(narrative) => {
+                    stage
+                        .finish(Some((narrative.input_tokens, narrative.output_tokens)))
+                        .await;
+                    narrative
+                }
+                Err(err @ (AppError::Llm(_) | AppError::StageTimeout { .. })) => {
+                    let err = err.to_string();
+                    status = degrade(&span, "generate", &err, report_type);
+                    stage.degrade(&err).await;
+                    stats::narrative(&stats::analysis(&data.indicators))
+                }
+                Err(err) => return Err(stage.fail(err).await),
+            }
+        };
+        span.record(
+            "report.generate.tokens",
+            narrative.input_tokens + narrative.output_tokens,
+        );
+        span.record("report.generate.cost_usd", narrative.cost_usd);
+        link_chat_span(&span, "generate", narrative.chat_span.clone());
+
+        // Stage 4: Check the LLM-written narrative against the retrieved data
+        let guardrail_violations =
+            if status == ReportStatus::Completed && self.guardrails.mode() != GuardrailMode::Off {
+                let stage = Stage::start(sink, report_id, "guardrail").await;
+                let violations =
+                    guardrail::review(self.guardrails, &narrative, &prompt_data, request.language);
+                stage.end(violations).await?
+            } else {
+                Vec::new()
+            };
+
+        // Stage 5: Format final report
+        let stage = Stage::start(sink, report_id, "format").await;
+        let report = format::format_report(FormatParams {
+            id: report_id,
+            retrieve_result: data,
+            cited: &prompt_data,
+            analysis: &analysis,
+            narrative: &narrative,
+            indicators_requested: &request.indicators,
+            start_date: request.start_date,
+            end_date: request.end_date,
+            duration: start.elapsed(),
+            trace_id,
+            status,
+            guardrail_violations,
+            report_type,
+            language: request.language,
+        });
+        stage.end(report).await
+    }
+}
+
+/// One stage's events, timed from `start`.
+struct Stage<'a> {
+    sink: &'a dyn ReportSink,
+    report_id: Uuid,
+    name: &'static str,
+    started: Instant,
+}
+
+impl<'a> Stage<'a> {
+    async fn start(sink: &'a dyn ReportSink, report_id: Uuid, name: &'static str) -> Self {
+        let stage = Self {
+            sink,
+            report_id,
+            name,
+            started: Instant::now(),
+        };
+        stage.emit(StageStatus::Started, None, None).await;
+        stage
+    }
+
+    /// Records the stage as taken from a checkpoint.
+    async fn resume(sink: &'a dyn ReportSink, report_id: Uuid, name: &'static str) {
+        let stage = Self {
+            sink,
+            report_id,
+            name,
+            started: Instant::now(),
+        };
+        stage.emit(StageStatus::Resumed, None, None).await;
+    }
+
+    /// Saves the analysis to resume from. Degraded analyses aren't saved;
+    /// they cost nothing to compute again.
+    async fn checkpoint(&self, analysis: &AnalysisResult) {
+        let checkpoint = Checkpoint {
+            analysis: analysis.clone(),
+        };
+        if let Err(err) = self.sink.checkpoint(self.report_id, &checkpoint).await {
+            tracing::warn!(
+                report.id = %self.report_id,
+                pipeline.stage = self.name,
+                error = %err,
+                "Failed to record report checkpoint"
+            );
+        }
+    }
+
+    /// `tokens` are the stage's input and output tokens, for LLM stages.
+    async fn finish(&self, tokens: Option<(u32, u32)>) {
+        self.emit(StageStatus::Finished, tokens, None).await;
+    }
+
+    async fn degrade(&self, error: &str) {
+        self.emit(StageStatus::Degraded, None, Some(error.to_string()))
+            .await;
+    }
+
+    async fn fail(&self, err: AppError) -> AppError {
+        self.emit(StageStatus::Failed, None, Some(err.to_string()))
+            .await;
+        err
+    }
+
+    /// Finishes or fails the stage by `result`.
+    async fn end<T>(&self, result: Result<T, AppError>) -> Result<T, AppError> {
+        match result {
+            Ok(value) => {
+                self.finish(None).await;
+                Ok(value)
+            }
+            Err(err) => Err(self.fail(err).await),
+        }
+    }
+
+    async fn emit(&self, status: StageStatus, tokens: Option<(u32, u32)>, error: Option<String>) {
+        let event = ReportEvent {
+            report_id: self.report_id,
+            stage: self.name,
+            status,
+            duration_ms: (status != StageStatus::Started)
+                .then(|| self.started.elapsed().as_millis() as u64),
+            input_tokens: tokens.map(|(input, _)| input),
+            output_tokens: tokens.map(|(_, output)| output),
+            error,
+        };
+        if let Err(err) = self.sink.event(&event).await {
+            tracing::warn!(
+                report.id = %self.report_id,
+                pipeline.stage = self.name,
+                error = %err,
+                "Failed to record report event"
+            );
+        }
+    }
+}
+
+/// Every provider (primary and fallback, with retries) has failed for
+/// `stage`, or the stage timed out; the rest of the report is built from
+/// statistics instead.
+fn degrade(
+    span: &tracing::Span,
+    stage: &'static str,
+    err: &str,
+    report_type: &str,
+) -> ReportStatus {
+    tracing::warn!(
+        pipeline.stage = stage,
+        error = %err,
+        "LLM providers unavailable, returning statistics-only report"
+    );
+    span.record("report.degraded_stage", stage);
+    Explainer::record(|| Decision::Degraded {
+        stage: stage.to_string(),
+        error: err.to_string(),
+    });
+    REPORT_DEGRADED.add(
+        1,
+        &[
+            KeyValue::new("pipeline.stage", stage),
+            KeyValue::new("report.type", report_type.to_string()),
+        ],
+    );
+    ReportStatus::Degraded
+}
+
+/// Links the report span to a stage's `gen_ai.chat` span so Scout can jump
+/// from the per-stage totals to the call that produced them.
+fn link_chat_span(span: &tracing::Span, stage: &'static str, chat_span: Option<SpanContext>) {
+    if let Some(cx) = chat_span.filter(SpanContext::is_valid) {
+        span.add_link_with_attributes(cx, vec![KeyValue::new("pipeline.stage", stage)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+    use opentelemetry::trace::Status;
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::db::data_points::{DataPoint, IndicatorData};
+    use crate::llm::{Endpoints, GenerateRequest, GenerateResponse, Provider};
+    use crate::pipeline::TemplateRegistry;
+    use crate::pipeline::sink::MemorySink;
+    use crate::telemetry::testing::SpanCapture;
+
+    struct DownProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for DownProvider {
+        async fn generate(&self, _req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            anyhow::bail!("503 service unavailable")
+        }
+
+        fn name(&self) -> &str {
+            "down"
+        }
+    }
+
+    fn date(month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, month, 1).unwrap()
+    }
+
+    fn request() -> ReportRequest {
+        ReportRequest {
+            indicators: vec!["UNRATE".to_string()],
+            start_date: date(1),
+            end_date: date(3),
+            currency: None,
+            language: Language::default(),
+            template: TemplateRegistry::load(None).unwrap().get(None).unwrap(),
+            explain: false,
+        }
+    }
+
+    /// A pool that fails every query quickly.
+    fn unreachable_pool() -> PgPool {
+        PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/db")
+            .unwrap()
+    }
+
+    async fn with_pipeline<T>(f: impl AsyncFnOnce(&Pipeline<'_>) -> T) -> T {
+        let pool = unreachable_pool();
+        let llm_client = LlmClient {
+            primary: Endpoints::single(Arc::new(DownProvider)),
+            fallback: None,
+            primary_provider: "down".to_string(),
+            fallback_provider: "none".to_string(),
+            fallback_model: String::new(),
+            observers: vec![],
+            max_retries: 1,
+        };
+        let guardrails = Guardrails::new(GuardrailMode::Annotate, "");
+        let pipeline = Pipeline {
+            pool: &pool,
+            llm_client: &llm_client,
+            guardrails: &guardrails,
+            model_capable: "capable",
+            model_fast: "fast",
+            max_prompt_tokens: 0,
+            minimization: DataMinimization::Off,
+            timeouts: StageTimeouts::default(),
+            heartbeat: None,
+        };
+        f(&pipeline).await
+    }
+
+    #[tokio::test]
+    async fn test_build_falls_back_to_statistics() {
+        let data = RetrieveResult {
+            indicators: vec![IndicatorData {
+                code: "UNRATE".to_string(),
+                name: "Unemployment Rate".to_string(),
+                unit: "Percent".to_string(),
+                frequency: "Monthly".to_string(),
+                values: (1..=3)
+                    .map(|month| DataPoint {
+                        observation_date: date(month),
+                        value: 3.0 + f64::from(month) / 10.0,
+                    })
+                    .collect(),
+            }],
+            total_data_points: 3,
+        };
+        let id = Uuid::new_v4();
+
+        let sink = MemorySink::default();
+
+        let report = with_pipeline(async |pipeline| {
+            pipeline
+                .build(&sink, id, &request(), None, &data, Instant::now())
+                .await
+                .unwrap()
+        })
+        .await;
+
+        assert_eq!(report.id, id);
+        assert_eq!(report.status, ReportStatus::Degraded);
+        assert_eq!(report.total_tokens, 0);
+        assert_eq!(report.total_data_points, 3);
+        assert_eq!(report.indicators_used, ["UNRATE"]);
+        assert_eq!(report.sections[0].indicators, ["UNRATE"]);
+        assert_eq!(report.sections[0].chart.as_ref().unwrap().data.len(), 3);
+        // Generate is skipped once analyze has found the providers down.
+        assert_eq!(
+            sink.event_log(),
+            [
+                "analyze:started",
+                "analyze:degraded",
+                "format:started",
+                "format:finished"
+            ]
+        );
+        let events = sink.events.lock().unwrap();
+        assert!(events.iter().all(|e| e.report_id == id));
+        assert!(events[1].error.as_deref().unwrap().contains("503"));
+        assert!(events[3].duration_ms.is_some());
+        drop(events);
+        assert!(sink.checkpoints.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_build_resumes_from_checkpoint() {
+        let data = RetrieveResult {
+            indicators: vec![IndicatorData {
+                code: "UNRATE".to_string(),
+                name: "Unemployment Rate".to_string(),
+                unit: "Percent".to_string(),
+                frequency: "Monthly".to_string(),
+                values: (1..=3)
+                    .map(|month| DataPoint {
+                        observation_date: date(month),
+                        value: 3.0 + f64::from(month) / 10.0,
+                    })
+                    .collect(),
+            }],
+            total_data_points: 3,
+        };
+        let mut analysis = stats::analysis(&data.indicators);
+        analysis.key_findings = vec!["Unemployment edged up".to_string()];
+        analysis.cost_usd = 0.25;
+        let checkpoint: Checkpoint =
+            serde_json::from_value(serde_json::to_value(Checkpoint { analysis }).unwrap()).unwrap();
+
+        let sink = MemorySink::default();
+        let report = with_pipeline(async |pipeline| {
+            pipeline
+                .build(
+                    &sink,
+                    Uuid::new_v4(),
+                    &request(),
+                    Some(&checkpoint),
+                    &data,
+                    Instant::now(),
+                )
+                .await
+                .unwrap()
+        })
+        .await;
+
+        // The providers are down, but analyze isn't run again to find out.
+        assert_eq!(
+            sink.event_log(),
+            [
+                "analyze:resumed",
+                "generate:started",
+                "generate:degraded",
+                "format:started",
+                "format:finished"
+            ]
+        );
+        assert_eq!(report.status, ReportStatus::Degraded);
+        assert_eq!(report.total_cost_usd, 0.25);
+        assert!(sink.checkpoints.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_build_explains_its_decisions() {
+        let data = RetrieveResult {
+            indicators: vec![IndicatorData {
+                code: "UNRATE".to_string(),
+                name: "Unemployment Rate".to_string(),
+                unit: "Percent".to_string(),
+                frequency: "Monthly".to_string(),
+                values: (0..120)
+                    .map(|day| DataPoint {
+                        observation_date: date(1) + chrono::Days::new(day),
+                        value: 3.5,
+                    })
+                    .collect(),
+            }],
+            total_data_points: 120,
+        };
+        let checkpoint = Checkpoint {
+            analysis: stats::analysis(&data.indicators),
+        };
+
+        let sink = MemorySink::default();
+        let request = request();
+        let decisions = with_pipeline(async |pipeline| {
+            let build = pipeline.build(
+                &sink,
+                Uuid::new_v4(),
+                &request,
+                Some(&checkpoint),
+                &data,
+                Instant::now(),
+            );
+            Arc::new(Explainer::default())
+                .scope(async {
+                    build.await.unwrap();
+                    Explainer::current().unwrap()
+                })
+                .await
+        })
+        .await;
+
+        assert_eq!(decisions.len(), 3);
+        assert_eq!(
+            decisions[0],
+            Decision::Resumed {
+                stage: "analyze".to_string()
+            }
+        );
+        assert!(matches!(
+            &decisions[1],
+            Decision::Degraded { stage, error } if stage == "generate" && error.contains("503")
+        ));
+        assert_eq!(
+            decisions[2],
+            Decision::ChartDownsampled {
+                indicator: "UNRATE".to_string(),
+                points: 120,
+                kept: crate::pipeline::chart::MAX_POINTS,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_report_is_not_stored() {
+        let spans = SpanCapture::start();
+        let sink = MemorySink::default();
+
+        let result = with_pipeline(async |pipeline| {
+            generate_report(pipeline, &sink, Uuid::new_v4(), &request(), None).await
+        })
+        .await;
+
+        assert!(matches!(result, Err(AppError::Database(_))));
+        assert!(sink.reports.lock().unwrap().is_empty());
+        assert_eq!(sink.event_log(), ["retrieve:started", "retrieve:failed"]);
+        assert!(matches!(
+            spans.span("pipeline report").status,
+            Status::Error { .. }
+        ));
+    }
+
+    fn group(name: &str, indicators: &[&str]) -> IndicatorGroup {
+        IndicatorGroup {
+            id: 1,
+            name: name.to_string(),
+            description: None,
+            indicators: indicators.iter().map(|c| c.to_string()).collect(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_merge_groups_appends_members_once() {
+        let found = [
+            group("Labor Market", &["UNRATE", "INDPRO"]),
+            group("Inflation Basket", &["CPIAUCSL", "UNRATE"]),
+        ];
+        let expanded = merge_groups(
+            &["GDP".to_string(), "INDPRO".to_string()],
+            &["labor market".to_string(), "Inflation Basket".to_string()],
+            &found,
+        )
+        .unwrap();
+
+        assert_eq!(expanded, ["GDP", "INDPRO", "UNRATE", "CPIAUCSL"]);
+    }
+
+    #[test]
+    fn test_merge_groups_rejects_unknown_names() {
+        let found = [group("Labor Market", &["UNRATE"])];
+        let err = merge_groups(
+            &[],
+            &["Labor Market".to_string(), "Housing".to_string()],
+            &found,
+        )
+        .unwrap_err();
+
+        assert!(matches!(&err, AppError::Validation(msg) if msg.ends_with(": Housing")));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_indicator_order() {
+        let mut reordered = request();
+        reordered.indicators = vec!["GDP".to_string(), "UNRATE".to_string()];
+        let mut other = request();
+        other.indicators = vec!["UNRATE".to_string(), "GDP".to_string(), "GDP".to_string()];
+
+        let models = ["openai", "gpt-4.1"];
+        assert_eq!(
+            reordered.fingerprint(&models, DataMinimization::Off),
+            other.fingerprint(&models, DataMinimization::Off)
+        );
+        assert_eq!(
+            reordered.fingerprint(&models, DataMinimization::Off).len(),
+            64
+        );
+    }
 
-    // Record domain metrics
-    REPORT_GENERATION_DURATION.record(duration.as_secs_f64(), &[]);
-    REPORT_DATA_POINTS.record(report.total_data_points as f64, &[]);
-    REPORT_SECTIONS.record(report.sections.len() as f64, &[]);
+    #[test]
+    fn test_fingerprint_changes_with_what_shapes_the_report() {
+        let models = ["openai", "gpt-4.1"];
+        let base = request().fingerprint(&models, DataMinimization::Off);
 
-    span.record("report.id", report.id.to_string());
-    span.record("report.indicators_count", report.indicators_used.len());
-    span.record("report.duration_ms", report.generation_duration_ms);
+        let mut later = request();
+        later.end_date = date(4);
+        let mut euros = request();
+        euros.currency = Some("EUR".to_string());
+        let mut typed = request();
+        typed.template = TemplateRegistry::load(None)
+            .unwrap()
+            .get(Some("inflation_brief"))
+            .unwrap();
+        let mut explained = request();
+        explained.explain = true;
 
-    Ok(report)
+        for changed in [
+            later.fingerprint(&models, DataMinimization::Off),
+            euros.fingerprint(&models, DataMinimization::Off),
+            typed.fingerprint(&models, DataMinimization::Off),
+            explained.fingerprint(&models, DataMinimization::Off),
+            request().fingerprint(&["openai", "gpt-4.1-mini"], DataMinimization::Off),
+            request().fingerprint(&models, DataMinimization::Strict),
+        ] {
+            assert_ne!(changed, base);
+        }
+    }
 }
@@ -1,8 +1,19 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
 use chrono::NaiveDate;
 use sqlx::PgPool;
 
 use crate::db::data_points::{IndicatorData, query_indicator_data};
-use crate::error::AppError;
+use crate::db::fx_rates::{FxRate, query_fx_rates};
+use crate::db::indicator_summaries::{IndicatorSummary, query_indicator_summaries};
+use crate::error::{AppError, RecordErr};
+use crate::explain::{Decision, Explainer};
+
+use super::timeout;
+
+/// Currency the indicator data is stored in and reports default to.
+pub const BASE_CURRENCY: &str = "USD";
 
 #[derive(Debug)]
 pub struct RetrieveResult {
@@ -12,11 +23,15 @@ pub struct RetrieveResult {
 
 #[tracing::instrument(
     name = "pipeline_stage retrieve",
-    skip(pool),
+    skip(pool, timeout),
     fields(
         pipeline.stage = "retrieve",
         report.indicators_count,
+        report.indicators_skipped,
         report.data_points,
+        report.currency,
+        report.units_normalized,
+        error.type,
     )
 )]
 pub async fn retrieve(
@@ -24,25 +39,451 @@ pub async fn retrieve(
     indicator_codes: &[String],
     start_date: NaiveDate,
     end_date: NaiveDate,
+    currency: Option<&str>,
+    timeout: Duration,
 ) -> Result<RetrieveResult, AppError> {
-    let indicators = query_indicator_data(pool, indicator_codes, start_date, end_date)
-        .await
-        .map_err(AppError::Database)?;
+    timeout::within("retrieve", timeout, async move {
+        let span = tracing::Span::current();
+        let summaries = query_indicator_summaries(pool, indicator_codes)
+            .await
+            .map_err(AppError::Database)?;
+        let codes = with_data_in_range(indicator_codes, &summaries, start_date, end_date);
+        span.record(
+            "report.indicators_skipped",
+            indicator_codes.len() - codes.len(),
+        );
 
-    let total_data_points: usize = indicators.iter().map(|i| i.values.len()).sum();
+        let mut indicators = if codes.is_empty() {
+            Vec::new()
+        } else {
+            query_indicator_data(pool, &codes, start_date, end_date)
+                .await
+                .map_err(AppError::Database)?
+        };
 
-    let span = tracing::Span::current();
-    span.record("report.indicators_count", indicators.len());
-    span.record("report.data_points", total_data_points);
+        let total_data_points: usize = indicators.iter().map(|i| i.values.len()).sum();
+        if indicators.len() < indicator_codes.len() {
+            Explainer::record(|| Decision::IndicatorsSkipped {
+                codes: indicator_codes
+                    .iter()
+                    .filter(|code| !indicators.iter().any(|i| &i.code == *code))
+                    .cloned()
+                    .collect(),
+            });
+        }
 
-    if indicators.is_empty() {
-        return Err(AppError::Pipeline(
-            "No data found for requested indicators".into(),
-        ));
-    }
+        span.record("report.indicators_count", indicators.len());
+        span.record("report.data_points", total_data_points);
+
+        if indicators.is_empty() {
+            return Err(AppError::Pipeline(
+                "No data found for requested indicators".into(),
+            ));
+        }
 
-    Ok(RetrieveResult {
-        indicators,
-        total_data_points,
+        let target = currency.unwrap_or(BASE_CURRENCY);
+        let rates = load_rates(pool, &indicators, target, end_date).await?;
+        let normalized = normalize(&mut indicators, target, &rates);
+        span.record("report.currency", target);
+        span.record("report.units_normalized", normalized);
+
+        Ok(RetrieveResult {
+            indicators,
+            total_data_points,
+        })
     })
+    .await
+    .record_err()
+}
+
+/// The codes worth querying `data_points` for: those whose summary has
+/// observations in `[start, end]`, and those without a summary, which may
+/// have data added since the view was last refreshed.
+fn with_data_in_range(
+    codes: &[String],
+    summaries: &HashMap<String, IndicatorSummary>,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<String> {
+    codes
+        .iter()
+        .filter(|code| summaries.get(*code).is_none_or(|s| s.overlaps(start, end)))
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitKind {
+    Percent,
+    /// Carries the base period, e.g. `1982-84=100`.
+    Index(String),
+    /// Counted things such as units or persons.
+    Count(String),
+    /// ISO 4217 code.
+    Currency(String),
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub kind: UnitKind,
+    /// Multiplier from the stored value to a single unit (1e3 for
+    /// "Thousands of ...").
+    pub scale: f64,
+}
+
+pub fn parse_unit(unit: &str) -> Unit {
+    let trimmed = unit.trim();
+    let lower = trimmed.to_lowercase();
+    if lower == "%" || lower.starts_with("percent") {
+        return Unit {
+            kind: UnitKind::Percent,
+            scale: 1.0,
+        };
+    }
+    if lower.starts_with("index") {
+        let base = trimmed["index".len()..].trim().to_string();
+        return Unit {
+            kind: UnitKind::Index(base),
+            scale: 1.0,
+        };
+    }
+
+    let scales = [
+        ("thousands of ", 1e3),
+        ("millions of ", 1e6),
+        ("billions of ", 1e9),
+        ("trillions of ", 1e12),
+    ];
+    let (scale, noun) = scales
+        .iter()
+        .find_map(|(prefix, scale)| {
+            trimmed
+                .get(..prefix.len())
+                .filter(|head| head.eq_ignore_ascii_case(prefix))
+                .map(|_| (Some(*scale), &trimmed[prefix.len()..]))
+        })
+        .unwrap_or((None, trimmed));
+
+    let kind = match currency_code(noun) {
+        Some(code) => UnitKind::Currency(code),
+        None if scale.is_some() => UnitKind::Count(noun.to_string()),
+        None => UnitKind::Other,
+    };
+    Unit {
+        kind,
+        scale: scale.unwrap_or(1.0),
+    }
+}
+
+/// Active ISO 4217 codes, sorted. A three-letter unit that isn't one, such
+/// as `bbl` or `ton`, is not a currency.
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XCG", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWG",
+];
+
+fn currency_code(noun: &str) -> Option<String> {
+    let code = match noun.to_lowercase().as_str() {
+        "dollars" | "u.s. dollars" | "us dollars" => "USD",
+        "euros" | "euro" => "EUR",
+        "pounds" | "pounds sterling" => "GBP",
+        "yen" | "japanese yen" => "JPY",
+        "yuan" | "chinese yuan" => "CNY",
+        _ => {
+            let code = noun.to_ascii_uppercase();
+            return ISO_4217
+                .binary_search(&code.as_str())
+                .is_ok()
+                .then_some(code);
+        }
+    };
+    Some(code.to_string())
+}
+
+/// US dollars per unit of each currency over time.
+#[derive(Debug, Default)]
+pub struct FxRates {
+    rates: HashMap<String, Vec<(NaiveDate, f64)>>,
+}
+
+impl FxRates {
+    pub fn new(rows: Vec<FxRate>) -> Self {
+        let mut rates: HashMap<String, Vec<(NaiveDate, f64)>> = HashMap::new();
+        for row in rows {
+            rates
+                .entry(row.currency.trim().to_string())
+                .or_default()
+                .push((row.rate_date, row.usd_per_unit));
+        }
+        for series in rates.values_mut() {
+            series.sort_by_key(|(date, _)| *date);
+        }
+        Self { rates }
+    }
+
+    pub fn has(&self, currency: &str) -> bool {
+        currency == BASE_CURRENCY || self.rates.contains_key(currency)
+    }
+
+    /// The latest rate on or before `date`, or the earliest one for dates
+    /// before the table starts.
+    fn usd_per_unit(&self, currency: &str, date: NaiveDate) -> Option<f64> {
+        if currency == BASE_CURRENCY {
+            return Some(1.0);
+        }
+        let series = self.rates.get(currency)?;
+        let idx = series.partition_point(|(d, _)| *d <= date);
+        series.get(idx.saturating_sub(1)).map(|(_, rate)| *rate)
+    }
+
+    fn convert(&self, from: &str, to: &str, date: NaiveDate) -> Option<f64> {
+        Some(self.usd_per_unit(from, date)? / self.usd_per_unit(to, date)?)
+    }
+}
+
+async fn load_rates(
+    pool: &PgPool,
+    indicators: &[IndicatorData],
+    target: &str,
+    end_date: NaiveDate,
+) -> Result<FxRates, AppError> {
+    let mut currencies: BTreeSet<String> = indicators
+        .iter()
+        .filter_map(|i| match parse_unit(&i.unit).kind {
+            UnitKind::Currency(code) => Some(code),
+            _ => None,
+        })
+        .collect();
+    if currencies.is_empty() || currencies.iter().all(|c| c == target) {
+        return Ok(FxRates::default());
+    }
+    currencies.insert(target.to_string());
+    currencies.remove(BASE_CURRENCY);
+
+    let codes: Vec<String> = currencies.into_iter().collect();
+    let rates = FxRates::new(
+        query_fx_rates(pool, &codes, end_date)
+            .await
+            .map_err(AppError::Database)?,
+    );
+    if !rates.has(target) {
+        return Err(AppError::Validation(format!(
+            "no FX rates available for currency {target}"
+        )));
+    }
+    Ok(rates)
+}
+
+/// Rewrites values and units so every indicator of a kind shares a scale:
+/// currency amounts in billions of `currency`, counts in thousands. Units
+/// whose values changed say what they were converted from. Returns how many
+/// indicators were rescaled or converted.
+pub fn normalize(indicators: &mut [IndicatorData], currency: &str, rates: &FxRates) -> usize {
+    let mut normalized = 0;
+    for indicator in indicators.iter_mut() {
+        let unit = parse_unit(&indicator.unit);
+        let (label, factor, source) = match &unit.kind {
+            UnitKind::Percent => ("Percent".to_string(), 1.0, None),
+            UnitKind::Index(base) if base.is_empty() => ("Index".to_string(), 1.0, None),
+            UnitKind::Index(base) => (format!("Index {base}"), 1.0, None),
+            UnitKind::Count(noun) => (format!("Thousands of {noun}"), unit.scale / 1e3, None),
+            UnitKind::Currency(code) if code == currency || !rates.has(code) => {
+                (format!("Billions of {code}"), unit.scale / 1e9, None)
+            }
+            UnitKind::Currency(code) => (
+                format!("Billions of {currency}"),
+                unit.scale / 1e9,
+                Some(code.as_str()),
+            ),
+            UnitKind::Other => continue,
+        };
+
+        let mut converted = false;
+        for point in &mut indicator.values {
+            let fx = source
+                .and_then(|code| rates.convert(code, currency, point.observation_date))
+                .unwrap_or(1.0);
+            point.value *= factor * fx;
+            converted |= factor != 1.0 || fx != 1.0;
+        }
+
+        indicator.unit = if converted {
+            normalized += 1;
+            format!("{label} (converted from {})", indicator.unit)
+        } else {
+            label
+        };
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::data_points::DataPoint;
+
+    fn date(year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, 6, 1).unwrap()
+    }
+
+    fn indicator(code: &str, unit: &str, values: &[(i32, f64)]) -> IndicatorData {
+        IndicatorData {
+            code: code.to_string(),
+            name: code.to_string(),
+            unit: unit.to_string(),
+            frequency: "Monthly".to_string(),
+            values: values
+                .iter()
+                .map(|&(year, value)| DataPoint {
+                    observation_date: date(year),
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    fn rates() -> FxRates {
+        FxRates::new(vec![
+            FxRate {
+                currency: "EUR".to_string(),
+                rate_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                usd_per_unit: 1.25,
+            },
+            FxRate {
+                currency: "EUR".to_string(),
+                rate_date: NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+                usd_per_unit: 1.0,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        assert_eq!(parse_unit("Percent").kind, UnitKind::Percent);
+        assert_eq!(
+            parse_unit("Index 1982-84=100").kind,
+            UnitKind::Index("1982-84=100".to_string())
+        );
+        assert_eq!(
+            parse_unit("Thousands of Persons"),
+            Unit {
+                kind: UnitKind::Count("Persons".to_string()),
+                scale: 1e3
+            }
+        );
+        assert_eq!(
+            parse_unit("Millions of Dollars"),
+            Unit {
+                kind: UnitKind::Currency("USD".to_string()),
+                scale: 1e6
+            }
+        );
+        assert_eq!(
+            parse_unit("Billions of EUR").kind,
+            UnitKind::Currency("EUR".to_string())
+        );
+        assert_eq!(parse_unit("Ratio").kind, UnitKind::Other);
+        assert_eq!(
+            parse_unit("Millions of bbl"),
+            Unit {
+                kind: UnitKind::Count("bbl".to_string()),
+                scale: 1e6
+            }
+        );
+        assert_eq!(parse_unit("pct").kind, UnitKind::Other);
+        assert_eq!(parse_unit("Ton").kind, UnitKind::Other);
+    }
+
+    #[test]
+    fn test_iso_4217_is_sorted() {
+        assert!(ISO_4217.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_normalize_rescales_to_shared_units() {
+        let mut data = vec![
+            indicator("GDP", "Billions of Dollars", &[(2020, 21000.0)]),
+            indicator("RETAILSMNSA", "Millions of Dollars", &[(2020, 550_000.0)]),
+            indicator("PAYEMS", "Millions of Persons", &[(2020, 150.0)]),
+            indicator("UNRATE", "Percent", &[(2020, 3.5)]),
+        ];
+
+        assert_eq!(normalize(&mut data, "USD", &FxRates::default()), 2);
+        assert_eq!(data[0].unit, "Billions of USD");
+        assert_eq!(data[0].values[0].value, 21000.0);
+        assert_eq!(
+            data[1].unit,
+            "Billions of USD (converted from Millions of Dollars)"
+        );
+        assert_eq!(data[1].values[0].value, 550.0);
+        assert_eq!(
+            data[2].unit,
+            "Thousands of Persons (converted from Millions of Persons)"
+        );
+        assert_eq!(data[2].values[0].value, 150_000.0);
+        assert_eq!(data[3].unit, "Percent");
+    }
+
+    #[test]
+    fn test_normalize_converts_currency_at_dated_rates() {
+        let mut data = vec![
+            indicator(
+                "GDP",
+                "Billions of Dollars",
+                &[(2019, 100.0), (2020, 100.0), (2021, 100.0)],
+            ),
+            indicator("EXPORTS", "Billions of GBP", &[(2020, 10.0)]),
+        ];
+
+        assert_eq!(normalize(&mut data, "EUR", &rates()), 1);
+        let values: Vec<f64> = data[0].values.iter().map(|v| v.value).collect();
+        assert_eq!(values, vec![80.0, 80.0, 100.0]);
+        assert_eq!(
+            data[0].unit,
+            "Billions of EUR (converted from Billions of Dollars)"
+        );
+        // No GBP rates: left in its own currency rather than mislabelled.
+        assert_eq!(data[1].unit, "Billions of GBP");
+        assert_eq!(data[1].values[0].value, 10.0);
+    }
+
+    #[test]
+    fn test_with_data_in_range_skips_indicators_without_data_there() {
+        let summary = |first: i32, last: i32| IndicatorSummary {
+            observations: 12,
+            first_date: date(first),
+            last_date: date(last),
+            min_value: 1.0,
+            max_value: 2.0,
+            avg_value: 1.5,
+            last_value: 2.0,
+            refreshed_at: chrono::Utc::now(),
+        };
+        let summaries = HashMap::from([
+            ("GDP".to_string(), summary(2003, 2023)),
+            ("OLD".to_string(), summary(1990, 1999)),
+        ]);
+        let codes = ["GDP", "OLD", "NEW"].map(String::from);
+
+        // NEW has no summary yet, so it is queried anyway.
+        assert_eq!(
+            with_data_in_range(&codes, &summaries, date(2010), date(2020)),
+            vec!["GDP", "NEW"]
+        );
+        assert_eq!(
+            with_data_in_range(&codes, &summaries, date(1995), date(2005)),
+            vec!["GDP", "OLD", "NEW"]
+        );
+    }
 }
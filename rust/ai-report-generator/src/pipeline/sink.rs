@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use opentelemetry::KeyValue;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::report_events::InsertReportEvent;
+use crate::db::reports::InsertReport;
+use crate::error::AppError;
+use crate::telemetry::metrics::{REPORT_DATA_POINTS, REPORT_GENERATION_DURATION, REPORT_SECTIONS};
+
+use super::format::Report;
+use super::orchestrator::Checkpoint;
+use super::watch::ReportWatch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    Started,
+    Finished,
+    /// The LLM providers failed and the stage's output was computed from
+    /// statistics instead.
+    Degraded,
+    Failed,
+    /// The stage's output was taken from the checkpoint of an earlier run.
+    Resumed,
+}
+
+impl StageStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Started => "started",
+            Self::Finished => "finished",
+            Self::Degraded => "degraded",
+            Self::Failed => "failed",
+            Self::Resumed => "resumed",
+        }
+    }
+}
+
+/// A pipeline stage starting or ending. Ended stages carry their duration,
+/// LLM stages their tokens and unsuccessful ones the error.
+#[derive(Debug, Clone)]
+pub struct ReportEvent {
+    pub report_id: Uuid,
+    pub stage: &'static str,
+    pub status: StageStatus,
+    pub duration_ms: Option<u64>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub error: Option<String>,
+}
+
+/// Where `generate_report` hands its progress and each finished report. The
+/// pipeline itself only reads, so callers choose what a report's side
+/// effects are.
+#[async_trait::async_trait]
+pub trait ReportSink: Send + Sync {
+    async fn store(&self, report: &Report) -> Result<(), AppError>;
+
+    /// Best effort: the pipeline logs a failed event and carries on.
+    async fn event(&self, event: &ReportEvent) -> Result<(), AppError>;
+
+    /// Sent every [`Pipeline::heartbeat`](super::Pipeline::heartbeat) while
+    /// the report is generated. Best effort, like `event`.
+    async fn heartbeat(&self, report_id: Uuid) -> Result<(), AppError>;
+
+    /// What the report can be resumed from if its pipeline fails later on.
+    /// Best effort, like `event`.
+    async fn checkpoint(&self, report_id: Uuid, checkpoint: &Checkpoint) -> Result<(), AppError>;
+}
+
+/// Saves reports to the `reports` table, their explanations to
+/// `report_explanations` and events to `report_events`, records the
+/// per-report domain metrics and wakes the report's long polls.
+pub struct PgReportSink {
+    pool: PgPool,
+    watch: Arc<ReportWatch>,
+}
+
+impl PgReportSink {
+    pub fn new(pool: PgPool, watch: Arc<ReportWatch>) -> Self {
+        Self { pool, watch }
+    }
+}
+
+#[async_trait::async_trait]
+impl ReportSink for PgReportSink {
+    async fn store(&self, report: &Report) -> Result<(), AppError> {
+        let sections_json = serde_json::to_value(&report.sections).unwrap_or_default();
+        let violations_json =
+            serde_json::to_value(&report.guardrail_violations).unwrap_or_default();
+        crate::db::reports::insert_report(
+            &self.pool,
+            &InsertReport {
+                id: report.id,
+                title: &report.title,
+                executive_summary: &report.executive_summary,
+                sections: &sections_json,
+                indicators_used: &report.indicators_used,
+                time_range_start: report.time_range_start,
+                time_range_end: report.time_range_end,
+                total_data_points: report.total_data_points as i32,
+                total_tokens: report.total_tokens as i32,
+                total_cost_usd: report.total_cost_usd,
+                providers_used: &report.providers_used,
+                generation_duration_ms: report.generation_duration_ms as i32,
+                trace_id: Some(&report.trace_id),
+                status: report.status.as_str(),
+                guardrail_violations: &violations_json,
+                language: report.language.as_str(),
+                report_type: &report.report_type,
+            },
+        )
+        .await
+        .map_err(AppError::Database)?;
+        if let Some(explanation) = &report.explanation {
+            let explanation = serde_json::to_value(explanation).unwrap_or_default();
+            crate::db::report_explanations::upsert_report_explanation(
+                &self.pool,
+                report.id,
+                &explanation,
+            )
+            .await
+            .map_err(AppError::Database)?;
+        }
+        self.watch.notify(report.id);
+
+        let attrs = [
+            KeyValue::new("report.type", report.report_type.clone()),
+            KeyValue::new("report.language", report.language.as_str()),
+        ];
+        REPORT_GENERATION_DURATION.record(report.generation_duration_ms as f64 / 1000.0, &attrs);
+        REPORT_DATA_POINTS.record(report.total_data_points as f64, &attrs);
+        REPORT_SECTIONS.record(report.sections.len() as f64, &attrs);
+        Ok(())
+    }
+
+    async fn event(&self, event: &ReportEvent) -> Result<(), AppError> {
+        crate::db::report_events::insert_report_event(
+            &self.pool,
+            &InsertReportEvent {
+                report_id: event.report_id,
+                stage: event.stage,
+                status: event.status.as_str(),
+                duration_ms: event.duration_ms.map(|ms| ms as i32),
+                input_tokens: event.input_tokens.map(|t| t as i32),
+                output_tokens: event.output_tokens.map(|t| t as i32),
+                error: event.error.as_deref(),
+            },
+        )
+        .await
+        .map_err(AppError::Database)
+    }
+
+    async fn heartbeat(&self, report_id: Uuid) -> Result<(), AppError> {
+        crate::db::reports::heartbeat_report(&self.pool, report_id)
+            .await
+            .map_err(AppError::Database)
+    }
+
+    async fn checkpoint(&self, report_id: Uuid, checkpoint: &Checkpoint) -> Result<(), AppError> {
+        let checkpoint = serde_json::to_value(checkpoint).unwrap_or_default();
+        crate::db::reports::set_report_checkpoint(&self.pool, report_id, &checkpoint)
+            .await
+            .map_err(AppError::Database)
+    }
+}
+
+/// Keeps reports and events in memory, for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemorySink {
+    pub reports: std::sync::Mutex<Vec<Report>>,
+    pub events: std::sync::Mutex<Vec<ReportEvent>>,
+    pub heartbeats: std::sync::atomic::AtomicUsize,
+    pub checkpoints: std::sync::Mutex<Vec<Checkpoint>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ReportSink for MemorySink {
+    async fn store(&self, report: &Report) -> Result<(), AppError> {
+        self.reports.lock().unwrap().push(report.clone());
+        Ok(())
+    }
+
+    async fn event(&self, event: &ReportEvent) -> Result<(), AppError> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+
+    async fn heartbeat(&self, _report_id: Uuid) -> Result<(), AppError> {
+        self.heartbeats
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn checkpoint(&self, _report_id: Uuid, checkpoint: &Checkpoint) -> Result<(), AppError> {
+        self.checkpoints.lock().unwrap().push(checkpoint.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl MemorySink {
+    /// `stage:status` for each event.
+    pub fn event_log(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| format!("{}:{}", e.stage, e.status.as_str()))
+            .collect()
+    }
+}
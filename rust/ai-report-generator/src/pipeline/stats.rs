@@ -0,0 +1,188 @@
+use crate::db::data_points::IndicatorData;
+
+use super::analyze::{AnalysisResult, Trend};
+use super::generate::{NarrativeResult, NarrativeSection};
+
+/// Changes smaller than this (in percent, first to last value) count as stable.
+const STABLE_THRESHOLD_PCT: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorStats {
+    pub first: f64,
+    pub last: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub points: usize,
+}
+
+impl IndicatorStats {
+    pub fn change_pct(&self) -> Option<f64> {
+        (self.first != 0.0).then(|| (self.last - self.first) / self.first.abs() * 100.0)
+    }
+
+    pub fn direction(&self) -> &'static str {
+        match self.change_pct() {
+            Some(pct) if pct >= STABLE_THRESHOLD_PCT => "increasing",
+            Some(pct) if pct <= -STABLE_THRESHOLD_PCT => "decreasing",
+            Some(_) => "stable",
+            None if self.last > self.first => "increasing",
+            None if self.last < self.first => "decreasing",
+            None => "stable",
+        }
+    }
+
+    fn describe(&self, indicator: &IndicatorData) -> String {
+        let change = self
+            .change_pct()
+            .map(|pct| format!(" ({pct:+.1}%)"))
+            .unwrap_or_default();
+        format!(
+            "{} moved from {:.2} to {:.2} {}{} over {} observations \
+             (min {:.2}, max {:.2}, mean {:.2}).",
+            indicator.name,
+            self.first,
+            self.last,
+            indicator.unit,
+            change,
+            self.points,
+            self.min,
+            self.max,
+            self.mean
+        )
+    }
+}
+
+pub fn summarize(indicator: &IndicatorData) -> Option<IndicatorStats> {
+    let first = indicator.values.first()?.value;
+    let last = indicator.values.last()?.value;
+    let values = indicator.values.iter().map(|v| v.value);
+
+    Some(IndicatorStats {
+        first,
+        last,
+        min: values.clone().fold(f64::INFINITY, f64::min),
+        max: values.clone().fold(f64::NEG_INFINITY, f64::max),
+        mean: values.sum::<f64>() / indicator.values.len() as f64,
+        points: indicator.values.len(),
+    })
+}
+
+/// Stand-in for the LLM analyze stage, built only from the data.
+pub fn analysis(data: &[IndicatorData]) -> AnalysisResult {
+    let trends: Vec<Trend> = data
+        .iter()
+        .filter_map(|ind| {
+            let stats = summarize(ind)?;
+            Some(Trend {
+                indicator: ind.code.clone(),
+                direction: stats.direction().to_string(),
+                description: stats.describe(ind),
+            })
+        })
+        .collect();
+    let key_findings = trends.iter().map(|t| t.description.clone()).collect();
+
+    AnalysisResult {
+        trends,
+        correlations: vec![],
+        key_findings,
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: 0.0,
+        provider: String::new(),
+        chat_span: None,
+    }
+}
+
+/// Stand-in for the LLM generate stage: one section per trend.
+pub fn narrative(analysis: &AnalysisResult) -> NarrativeResult {
+    let sections = analysis
+        .trends
+        .iter()
+        .map(|trend| NarrativeSection {
+            heading: format!("{} ({})", trend.indicator, trend.direction),
+            content: trend.description.clone(),
+            indicators: vec![trend.indicator.clone()],
+            chart: None,
+            citations: Vec::new(),
+            uncited_claims: Vec::new(),
+        })
+        .collect();
+
+    NarrativeResult {
+        title: "Economic Indicators Summary".to_string(),
+        executive_summary: "Narrative analysis is temporarily unavailable; this report \
+            contains summary statistics computed directly from the data."
+            .to_string(),
+        sections,
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: 0.0,
+        provider: String::new(),
+        chat_span: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::db::data_points::DataPoint;
+
+    fn indicator(code: &str, values: &[f64]) -> IndicatorData {
+        IndicatorData {
+            code: code.to_string(),
+            name: format!("{code} name"),
+            unit: "Percent".to_string(),
+            frequency: "Monthly".to_string(),
+            values: values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| DataPoint {
+                    observation_date: NaiveDate::from_ymd_opt(2020, i as u32 + 1, 1).unwrap(),
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let stats = summarize(&indicator("UNRATE", &[4.0, 6.0, 2.0, 5.0])).unwrap();
+
+        assert_eq!(stats.min, 2.0);
+        assert_eq!(stats.max, 6.0);
+        assert_eq!(stats.mean, 4.25);
+        assert_eq!(stats.change_pct(), Some(25.0));
+        assert_eq!(stats.direction(), "increasing");
+        assert!(summarize(&indicator("EMPTY", &[])).is_none());
+    }
+
+    #[test]
+    fn test_direction_thresholds() {
+        let direction = |values: &[f64]| summarize(&indicator("X", values)).unwrap().direction();
+
+        assert_eq!(direction(&[100.0, 100.5]), "stable");
+        assert_eq!(direction(&[100.0, 90.0]), "decreasing");
+        assert_eq!(direction(&[0.0, 0.25]), "increasing");
+    }
+
+    #[test]
+    fn test_statistical_report_has_a_section_per_indicator() {
+        let data = vec![
+            indicator("UNRATE", &[4.0, 3.5]),
+            indicator("FEDFUNDS", &[0.1, 5.3]),
+        ];
+
+        let analysis = analysis(&data);
+        let narrative = narrative(&analysis);
+
+        assert_eq!(analysis.trends.len(), 2);
+        assert_eq!(analysis.trends[0].direction, "decreasing");
+        assert_eq!(narrative.sections.len(), 2);
+        assert_eq!(narrative.sections[1].heading, "FEDFUNDS (increasing)");
+        assert_eq!(narrative.input_tokens + narrative.output_tokens, 0);
+    }
+}
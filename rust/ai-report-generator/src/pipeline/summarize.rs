@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::{AppError, RecordErr};
+use crate::llm::{GenerateRequest, LlmClient};
+
+use super::context::{self, Block};
+use super::timeout;
+
+const SYSTEM_PROMPT: &str = "You summarize articles for a blog's article cards. \
+    Reply with exactly two plain sentences and nothing else: no heading, no quotes, no markdown.";
+
+const MAX_TOKENS: u32 = 160;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub summary: String,
+    pub model: String,
+    pub provider: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+}
+
+/// A two-sentence summary of `text` for callers outside the report
+/// pipeline. Paragraphs that don't fit the model's context are left out
+/// from the end.
+#[tracing::instrument(
+    name = "pipeline_stage summarize",
+    skip(llm_client, title, text, max_prompt_tokens, timeout),
+    fields(
+        pipeline.stage = "summarize",
+        prompt.estimated_tokens,
+        prompt.dropped_blocks,
+        error.type,
+    )
+)]
+pub async fn summarize(
+    llm_client: &LlmClient,
+    model: &str,
+    title: &str,
+    text: &str,
+    max_prompt_tokens: usize,
+    timeout: Duration,
+) -> Result<Summary, AppError> {
+    timeout::within("summarize", timeout, async move {
+        let head = format!("Summarize this article in two sentences.\n\nTitle: {title}\n\n");
+        let budget = context::budget(
+            llm_client.context_window(model),
+            MAX_TOKENS,
+            max_prompt_tokens,
+        );
+        let blocks = text
+            .split("\n\n")
+            .map(|paragraph| Block::new(0, format!("{paragraph}\n\n")))
+            .collect();
+        let packed = context::pack(budget, &format!("{SYSTEM_PROMPT}{head}"), blocks);
+        context::record("summarize", &packed);
+
+        let resp = llm_client
+            .generate(&GenerateRequest {
+                model: model.to_string(),
+                system: SYSTEM_PROMPT.to_string(),
+                prompt: format!("{head}{}", packed.text),
+                temperature: 0.2,
+                max_tokens: MAX_TOKENS,
+                stage: "summarize".to_string(),
+            })
+            .await
+            .map_err(|e| AppError::Llm(e.to_string()))?;
+
+        let summary = clean_summary(&resp.content);
+        if summary.is_empty() {
+            return Err(AppError::Llm("model returned an empty summary".into()));
+        }
+
+        Ok(Summary {
+            summary,
+            model: resp.model,
+            provider: resp.provider,
+            input_tokens: resp.input_tokens,
+            output_tokens: resp.output_tokens,
+            cost_usd: resp.cost_usd,
+        })
+    })
+    .await
+    .record_err()
+}
+
+/// Models asked for plain sentences still sometimes quote them or spread
+/// them over lines.
+fn clean_summary(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c| c == '"' || c == '\u{201c}' || c == '\u{201d}')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_summary_unwraps_quotes_and_lines() {
+        assert_eq!(
+            clean_summary("\"Rates rose.\n\nInflation  fell.\"\n"),
+            "Rates rose. Inflation fell."
+        );
+        assert_eq!(clean_summary("  \n"), "");
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+pub const DEFAULT_REPORT_TYPE: &str = "general";
+
+const BUILT_IN: &str = include_str!("../../data/report-types.toml");
+
+/// A report product: what it must cover and how the generate stage is told
+/// to write it. See `data/report-types.toml` for the fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportTemplate {
+    #[serde(skip_deserializing)]
+    pub name: String,
+    pub description: String,
+    pub required_indicators: Vec<String>,
+    #[serde(skip_serializing)]
+    pub prompt: String,
+    pub sections: Vec<String>,
+    pub target_words: Option<u32>,
+}
+
+impl ReportTemplate {
+    pub fn validate(&self, indicators: &[String]) -> Result<(), AppError> {
+        let missing: Vec<&str> = self
+            .required_indicators
+            .iter()
+            .filter(|code| !indicators.iter().any(|i| i.eq_ignore_ascii_case(code)))
+            .map(String::as_str)
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(format!(
+                "report_type {} requires indicators {}",
+                self.name,
+                missing.join(", ")
+            )))
+        }
+    }
+
+    /// The closing instructions of the generate prompt.
+    pub fn instructions(&self, indicators: &str, time_range: &str) -> String {
+        let mut out = String::new();
+        if !self.prompt.trim().is_empty() {
+            out.push_str(
+                &self
+                    .prompt
+                    .trim()
+                    .replace("{indicators}", indicators)
+                    .replace("{time_range}", time_range),
+            );
+            out.push_str("\n\n");
+        }
+        if self.sections.is_empty() {
+            out.push_str("Include 3-5 sections covering the major themes from the analysis.");
+        } else {
+            out.push_str(&format!(
+                "Include exactly these sections, in this order: {}.",
+                self.sections.join("; ")
+            ));
+        }
+        if let Some(words) = self.target_words {
+            out.push_str(&format!(" Keep the whole report to about {words} words."));
+        }
+        out
+    }
+
+    /// Changes whenever the instructions it gives the generate prompt do,
+    /// so an explanation tells which wording a report was written from.
+    pub fn version(&self) -> String {
+        let instructions = self.instructions("{indicators}", "{time_range}");
+        hex::encode(&Sha256::digest(instructions)[..6])
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateRegistry {
+    templates: BTreeMap<String, Arc<ReportTemplate>>,
+}
+
+impl TemplateRegistry {
+    /// The built-in report types, plus (and overridden by) those in
+    /// `extra_file` when given.
+    pub fn load(extra_file: Option<&Path>) -> Result<Self, String> {
+        let mut templates = parse(BUILT_IN).map_err(|e| format!("built-in report types: {e}"))?;
+        if let Some(path) = extra_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            templates.extend(parse(&contents).map_err(|e| format!("{}: {e}", path.display()))?);
+        }
+        Ok(Self { templates })
+    }
+
+    pub fn get(&self, name: Option<&str>) -> Result<Arc<ReportTemplate>, AppError> {
+        let name = name.unwrap_or(DEFAULT_REPORT_TYPE);
+        self.templates.get(name).cloned().ok_or_else(|| {
+            AppError::Validation(format!(
+                "unknown report_type {name:?}, expected one of {}",
+                self.names().join(", ")
+            ))
+        })
+    }
+
+    pub fn list(&self) -> Vec<&ReportTemplate> {
+        self.templates.values().map(Arc::as_ref).collect()
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.templates.keys().map(String::as_str).collect()
+    }
+}
+
+fn parse(contents: &str) -> Result<BTreeMap<String, Arc<ReportTemplate>>, String> {
+    let raw: BTreeMap<String, ReportTemplate> =
+        toml::from_str(contents).map_err(|e| e.to_string())?;
+    Ok(raw
+        .into_iter()
+        .map(|(name, mut template)| {
+            template.name = name.clone();
+            template.required_indicators = template
+                .required_indicators
+                .iter()
+                .map(|code| code.to_uppercase())
+                .collect();
+            (name, Arc::new(template))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_built_in_types_load() {
+        let registry = TemplateRegistry::load(None).unwrap();
+
+        let general = registry.get(None).unwrap();
+        assert_eq!(general.name, "general");
+        assert!(general.required_indicators.is_empty());
+        assert_eq!(
+            general.instructions("GDP", "2020 to 2023"),
+            "Include 3-5 sections covering the major themes from the analysis."
+        );
+        assert!(registry.get(Some("inflation_brief")).is_ok());
+        assert!(registry.get(Some("labor_market_review")).is_ok());
+        assert!(registry.get(Some("earnings_call")).is_err());
+    }
+
+    #[test]
+    fn test_required_indicators_are_validated() {
+        let registry = TemplateRegistry::load(None).unwrap();
+        let brief = registry.get(Some("inflation_brief")).unwrap();
+
+        assert!(
+            brief
+                .validate(&codes(&["cpiaucsl", "FEDFUNDS", "GDP"]))
+                .is_ok()
+        );
+        let err = brief.validate(&codes(&["CPIAUCSL"])).unwrap_err();
+        assert!(err.to_string().contains("requires indicators FEDFUNDS"));
+    }
+
+    #[test]
+    fn test_template_instructions() {
+        let template = parse(
+            r#"
+            [housing_note]
+            prompt = "Cover {indicators} over {time_range}."
+            sections = ["Starts", "Outlook"]
+            target_words = 300
+            "#,
+        )
+        .unwrap()
+        .remove("housing_note")
+        .unwrap();
+
+        assert_eq!(
+            template.instructions("HOUST", "2020 to 2023"),
+            "Cover HOUST over 2020 to 2023.\n\n\
+             Include exactly these sections, in this order: Starts; Outlook. \
+             Keep the whole report to about 300 words."
+        );
+        assert!(parse("[bad]\nsection = []").is_err());
+    }
+
+    #[test]
+    fn test_version_follows_the_instructions() {
+        let mut template = ReportTemplate {
+            sections: vec!["Starts".to_string()],
+            ..ReportTemplate::default()
+        };
+        let version = template.version();
+        assert_eq!(version.len(), 12);
+
+        template.description = "Housing starts".to_string();
+        assert_eq!(template.version(), version);
+        template.target_words = Some(300);
+        assert_ne!(template.version(), version);
+    }
+}
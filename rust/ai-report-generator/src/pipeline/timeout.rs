@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use crate::deadline::Deadline;
+use crate::error::AppError;
+
+/// How long each stage may run; zero for no limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimeouts {
+    pub retrieve: Duration,
+    pub analyze: Duration,
+    pub generate: Duration,
+}
+
+/// Runs a stage's work for at most `limit`, or until the request's
+/// [`Deadline`] if that comes first. Past either the work is dropped,
+/// cancelling any provider call in flight, and the current stage span gets
+/// `error.type=timeout` or `deadline_exceeded`.
+pub async fn within<T>(
+    stage: &'static str,
+    limit: Duration,
+    work: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    let deadline = Deadline::current()
+        .map(|deadline| deadline.remaining())
+        .filter(|remaining| limit.is_zero() || *remaining < limit);
+    if let Some(remaining) = deadline {
+        return match tokio::time::timeout(remaining, work).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::Span::current().record("error.type", "deadline_exceeded");
+                Err(AppError::DeadlineExceeded)
+            }
+        };
+    }
+    if limit.is_zero() {
+        return work.await;
+    }
+    match tokio::time::timeout(limit, work).await {
+        Ok(result) => result,
+        Err(_) => {
+            tracing::Span::current().record("error.type", "timeout");
+            Err(AppError::StageTimeout {
+                stage,
+                timeout_secs: limit.as_secs(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn slow() -> Result<(), AppError> {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_shortens_the_stage_limit() {
+        let err = Deadline::after(Duration::from_millis(20))
+            .run(within("generate", Duration::from_secs(60), slow()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::DeadlineExceeded));
+
+        let err = Deadline::after(Duration::from_secs(60))
+            .run(within("generate", Duration::from_millis(20), slow()))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::StageTimeout {
+                stage: "generate",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Wakes the requests long-polling a report (`GET /api/reports/{id}?wait=`)
+/// once it is stored or has failed. In process only: a poll served by
+/// another instance than the one generating the report waits out its `wait`
+/// and then reads whatever the row says.
+#[derive(Default)]
+pub struct ReportWatch {
+    senders: Mutex<HashMap<Uuid, watch::Sender<bool>>>,
+}
+
+impl ReportWatch {
+    /// Subscribe before reading the row, so a report that finishes in
+    /// between still wakes the subscription.
+    pub fn subscribe(self: &Arc<Self>, report_id: Uuid) -> Subscription {
+        let receiver = self
+            .senders
+            .lock()
+            .unwrap()
+            .entry(report_id)
+            .or_insert_with(|| watch::channel(false).0)
+            .subscribe();
+        Subscription {
+            watch: self.clone(),
+            report_id,
+            receiver,
+        }
+    }
+
+    pub fn notify(&self, report_id: Uuid) {
+        if let Some(sender) = self.senders.lock().unwrap().remove(&report_id) {
+            sender.send_replace(true);
+        }
+    }
+
+    #[cfg(test)]
+    fn watched(&self) -> usize {
+        self.senders.lock().unwrap().len()
+    }
+}
+
+pub struct Subscription {
+    watch: Arc<ReportWatch>,
+    report_id: Uuid,
+    receiver: watch::Receiver<bool>,
+}
+
+impl Subscription {
+    /// Resolves once the report is notified. Pending forever if it never is,
+    /// so callers bound it with a timeout.
+    pub async fn finished(&mut self) {
+        // An error means the sender was dropped, i.e. notified and removed.
+        let _ = self.receiver.wait_for(|finished| *finished).await;
+    }
+}
+
+impl Drop for Subscription {
+    /// The last subscriber to give up on a report removes its entry, so
+    /// reports nobody notifies (one a restart left `pending`) don't pile up.
+    fn drop(&mut self) {
+        let mut senders = self.watch.senders.lock().unwrap();
+        // Closed means notified: the entry, if any, is a later subscriber's.
+        if self.receiver.has_changed().is_ok()
+            && let Some(sender) = senders.get(&self.report_id)
+            && sender.receiver_count() <= 1
+        {
+            senders.remove(&self.report_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notify_wakes_every_subscriber() {
+        let watch = Arc::new(ReportWatch::default());
+        let id = Uuid::new_v4();
+        let mut first = watch.subscribe(id);
+        let mut second = watch.subscribe(id);
+
+        watch.notify(id);
+
+        tokio::time::timeout(Duration::from_secs(1), first.finished())
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(1), second.finished())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_other_reports_do_not_wake() {
+        let watch = Arc::new(ReportWatch::default());
+        let mut subscription = watch.subscribe(Uuid::new_v4());
+
+        watch.notify(Uuid::new_v4());
+
+        let woken = tokio::time::timeout(Duration::from_millis(20), subscription.finished()).await;
+        assert!(woken.is_err());
+    }
+
+    #[test]
+    fn test_last_subscriber_removes_entry() {
+        let watch = Arc::new(ReportWatch::default());
+        let id = Uuid::new_v4();
+        let first = watch.subscribe(id);
+        let second = watch.subscribe(id);
+
+        drop(first);
+        assert_eq!(watch.watched(), 1);
+        drop(second);
+        assert_eq!(watch.watched(), 0);
+    }
+}
@@ -0,0 +1,354 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::Instrument;
+
+use crate::AppState;
+use crate::config::Config;
+use crate::llm::{self, LlmClient, ProviderDirectory, ProviderObserver};
+
+/// Serializes reloads, so a SIGHUP and a `POST /api/admin/reload` can't
+/// interleave their swaps.
+static RELOADING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A value swapped as a whole on reload. Callers keep the `Arc` that
+/// [`Reloadable::current`] gave them, so work that started before a swap
+/// finishes on the old value.
+pub struct Reloadable<T>(RwLock<Arc<T>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self(RwLock::new(Arc::new(value)))
+    }
+
+    pub fn current(&self) -> Arc<T> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Returns the value swapped out.
+    pub fn swap(&self, value: T) -> Arc<T> {
+        std::mem::replace(&mut *self.0.write().unwrap(), Arc::new(value))
+    }
+}
+
+/// The LLM clients the config describes.
+pub struct LlmClients {
+    pub interactive: LlmClient,
+    /// `None` without an `OPENAI_API_KEY`.
+    pub batch: Option<LlmClient>,
+    pub providers: ProviderDirectory,
+}
+
+impl LlmClients {
+    pub fn build(config: &Config, observers: &[Arc<dyn ProviderObserver>]) -> anyhow::Result<Self> {
+        let primary = endpoints(config, &config.llm_provider, &config.llm_endpoints)?
+            .expect("LLM_PROVIDER is validated");
+        let fallback = endpoints(
+            config,
+            &config.fallback_provider,
+            &config.fallback_endpoints,
+        )?;
+
+        let mut providers = ProviderDirectory::new(
+            Duration::from_secs(config.provider_probe_ttl_secs),
+            Duration::from_secs(config.provider_probe_timeout_secs),
+        );
+        providers.add_endpoints(
+            "primary",
+            &primary,
+            config.credentials(&config.llm_provider),
+            vec![
+                config.llm_model_capable.clone(),
+                config.llm_model_fast.clone(),
+            ],
+        );
+        if let Some(fallback) = &fallback {
+            providers.add_endpoints(
+                "fallback",
+                fallback,
+                config.credentials(&config.fallback_provider),
+                vec![config.fallback_model.clone()],
+            );
+        }
+
+        // Batch jobs run for hours, so a failed one falls back to the
+        // interactive provider rather than being resubmitted.
+        let batch = config
+            .openai_api_key
+            .as_deref()
+            .map(|api_key| {
+                let provider = Arc::new(llm::openai::OpenAIBatchProvider::new(
+                    api_key,
+                    config.http_settings("openai"),
+                    Duration::from_secs(config.openai_batch_poll_secs),
+                    Duration::from_secs(config.openai_batch_timeout_secs),
+                )?);
+                providers.add(
+                    "batch",
+                    provider.clone(),
+                    config.credentials("openai"),
+                    vec![
+                        config.openai_batch_model_capable.clone(),
+                        config.openai_batch_model_fast.clone(),
+                    ],
+                );
+                anyhow::Ok(LlmClient {
+                    primary: llm::Endpoints::single(provider),
+                    fallback: fallback.clone(),
+                    primary_provider: "openai".to_string(),
+                    fallback_provider: config.fallback_provider.clone(),
+                    fallback_model: config.fallback_model.clone(),
+                    observers: observers.to_vec(),
+                    max_retries: 1,
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            interactive: LlmClient {
+                primary,
+                fallback,
+                primary_provider: config.llm_provider.clone(),
+                fallback_provider: config.fallback_provider.clone(),
+                fallback_model: config.fallback_model.clone(),
+                observers: observers.to_vec(),
+                max_retries: 3,
+            },
+            batch,
+            providers,
+        })
+    }
+}
+
+/// What a reload swapped in, for `POST /api/admin/reload`.
+#[derive(Debug, Serialize)]
+pub struct Reloaded {
+    pub primary_provider: String,
+    pub primary_endpoints: Vec<String>,
+    /// `None` when the fallback provider isn't recognised, which disables it.
+    pub fallback_provider: Option<String>,
+    pub fallback_endpoints: Vec<String>,
+    pub batch: bool,
+}
+
+/// Loads the config again and swaps in clients built from its provider keys,
+/// endpoints and fallback. Reports already running finish on the clients they
+/// started with. Fails, keeping the running clients, when the config doesn't
+/// load or changes what only a restart can: the primary provider, or whether
+/// batch mode is on. The rest of the config still needs a restart.
+pub async fn reload(state: &AppState) -> Result<Reloaded, String> {
+    let _reloading = RELOADING.lock().await;
+    let span = tracing::info_span!(
+        "config.reload",
+        otel.status_code = tracing::field::Empty,
+        error.message = tracing::field::Empty,
+    );
+    let result = async {
+        let config = Config::load().await.map_err(|e| e.to_string())?;
+        check_reloadable(&state.config, &config)?;
+        let observers = state.llm_client.current().observers.clone();
+        let clients = LlmClients::build(&config, &observers).map_err(|e| format!("{e:#}"))?;
+
+        let reloaded = Reloaded {
+            primary_provider: clients.interactive.primary_provider.clone(),
+            primary_endpoints: endpoint_names(&clients.interactive.primary),
+            fallback_provider: clients
+                .interactive
+                .fallback
+                .as_ref()
+                .map(|_| clients.interactive.fallback_provider.clone()),
+            fallback_endpoints: clients
+                .interactive
+                .fallback
+                .as_ref()
+                .map(endpoint_names)
+                .unwrap_or_default(),
+            batch: clients.batch.is_some(),
+        };
+        state.llm_client.swap(clients.interactive);
+        state.providers.swap(clients.providers);
+        if let (Some(batch), Some(client)) = (&state.batch, clients.batch) {
+            batch.llm_client.swap(client);
+        }
+        Ok::<_, String>(reloaded)
+    }
+    .instrument(span.clone())
+    .await;
+
+    match &result {
+        Ok(reloaded) => tracing::info!(
+            parent: &span,
+            primary_provider = %reloaded.primary_provider,
+            fallback_provider = reloaded.fallback_provider.as_deref().unwrap_or("none"),
+            batch_mode = reloaded.batch,
+            "LLM clients reloaded"
+        ),
+        Err(error) => {
+            span.record("otel.status_code", "ERROR");
+            span.record("error.message", error.as_str());
+            tracing::warn!(parent: &span, error = %error, "Reload failed, keeping the running LLM clients");
+        }
+    }
+    result
+}
+
+fn check_reloadable(running: &Config, loaded: &Config) -> Result<(), String> {
+    if loaded.llm_provider != running.llm_provider {
+        return Err(format!(
+            "LLM_PROVIDER changed from {} to {}; switching the primary provider needs a restart",
+            running.llm_provider, loaded.llm_provider
+        ));
+    }
+    match (&running.openai_api_key, &loaded.openai_api_key) {
+        (Some(_), None) => Err(
+            "OPENAI_API_KEY was removed, which turns batch mode off; that needs a restart"
+                .to_string(),
+        ),
+        (None, Some(_)) => Err(
+            "OPENAI_API_KEY was added, which turns batch mode on; that needs a restart".to_string(),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn endpoint_names(endpoints: &llm::Endpoints) -> Vec<String> {
+    endpoints.iter().map(|e| e.name.clone()).collect()
+}
+
+/// `provider`'s client for the API at `base_url`, or for its default API.
+/// `None` for an unrecognised provider, which disables a fallback.
+fn provider_client(
+    config: &Config,
+    provider: &str,
+    base_url: Option<&str>,
+) -> anyhow::Result<Option<Arc<dyn llm::Provider>>> {
+    let key = |key: &Option<String>| key.clone().unwrap_or_default();
+    let http = config.http_settings(provider);
+    let client: Arc<dyn llm::Provider> = match (provider, base_url) {
+        ("anthropic", None) => Arc::new(llm::anthropic::AnthropicProvider::new(
+            &key(&config.anthropic_api_key),
+            http,
+        )?),
+        ("anthropic", Some(url)) => Arc::new(llm::anthropic::AnthropicProvider::with_base_url(
+            &key(&config.anthropic_api_key),
+            url,
+            http,
+        )?),
+        ("openai", None) => Arc::new(llm::openai::OpenAIProvider::new(
+            &key(&config.openai_api_key),
+            http,
+        )?),
+        ("google", None) => Arc::new(llm::openai::OpenAIProvider::new_google(
+            &key(&config.google_api_key),
+            http,
+        )?),
+        ("ollama", None) => Arc::new(llm::openai::OpenAIProvider::new_ollama(
+            &config.ollama_base_url,
+            http,
+        )?),
+        ("openai" | "google" | "ollama", Some(url)) => {
+            let api_key = match provider {
+                "openai" => key(&config.openai_api_key),
+                "google" => key(&config.google_api_key),
+                _ => "ollama".to_string(),
+            };
+            Arc::new(llm::openai::OpenAIProvider::with_base_url(
+                provider, &api_key, url, http,
+            )?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(client))
+}
+
+/// The endpoints of `LLM_ENDPOINTS` or `FALLBACK_ENDPOINTS`, or just the
+/// provider's default API when `list` is empty.
+fn endpoints(
+    config: &Config,
+    provider: &str,
+    list: &str,
+) -> anyhow::Result<Option<llm::Endpoints>> {
+    let list = llm::endpoints::parse_list(list).map_err(anyhow::Error::msg)?;
+    if list.is_empty() {
+        return Ok(provider_client(config, provider, None)?.map(llm::Endpoints::single));
+    }
+    let mut endpoints = Vec::new();
+    for (name, url) in list {
+        let Some(client) = provider_client(config, provider, Some(&url))? else {
+            return Ok(None);
+        };
+        endpoints.push(llm::Endpoint::new(&name, client, &url)?);
+    }
+    Ok(Some(llm::Endpoints::new(
+        endpoints,
+        Duration::from_secs(config.llm_endpoint_cooldown_secs),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::Layers;
+
+    fn config(vars: &[(&str, &str)]) -> Config {
+        let env = [("DATABASE_URL", "postgres://localhost/test")]
+            .iter()
+            .chain(vars)
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::from_layers(Layers::from_parts(env, HashMap::new(), &[]), None).unwrap()
+    }
+
+    #[test]
+    fn test_swap_leaves_held_value_intact() {
+        let value = Reloadable::new("old".to_string());
+        let held = value.current();
+        let old = value.swap("new".to_string());
+
+        assert_eq!(*held, "old");
+        assert!(Arc::ptr_eq(&held, &old));
+        assert_eq!(*value.current(), "new");
+    }
+
+    #[test]
+    fn test_build_uses_reloaded_keys_and_fallback() {
+        let clients = LlmClients::build(
+            &config(&[
+                ("OPENAI_API_KEY", "sk-rotated"),
+                ("FALLBACK_PROVIDER", "google"),
+                ("FALLBACK_MODEL", "gemini-2.5-flash"),
+                (
+                    "FALLBACK_ENDPOINTS",
+                    "a=https://a.example.com/v1,b=https://b.example.com/v1",
+                ),
+            ]),
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(clients.interactive.fallback_provider, "google");
+        assert_eq!(clients.interactive.fallback_model, "gemini-2.5-flash");
+        assert_eq!(
+            endpoint_names(clients.interactive.fallback.as_ref().unwrap()),
+            ["a", "b"]
+        );
+        assert!(clients.batch.is_some());
+    }
+
+    #[test]
+    fn test_restart_only_changes_are_rejected() {
+        let running = config(&[("OPENAI_API_KEY", "sk-old")]);
+        assert!(check_reloadable(&running, &config(&[("OPENAI_API_KEY", "sk-new")])).is_ok());
+
+        let switched = config(&[("OPENAI_API_KEY", "sk-old"), ("LLM_PROVIDER", "google")]);
+        assert!(
+            check_reloadable(&running, &switched)
+                .unwrap_err()
+                .contains("LLM_PROVIDER")
+        );
+        assert!(check_reloadable(&running, &config(&[])).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use axum::{Json, extract::State, http::HeaderMap};
+
+use crate::AppState;
+use crate::config::EffectiveConfig;
+use crate::error::{AppError, AppResult};
+use crate::reload::{self, Reloaded};
+
+const X_ADMIN_TOKEN: &str = "x-admin-token";
+
+pub async fn get_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<EffectiveConfig>> {
+    require_admin(&state, &headers)?;
+
+    Ok(Json(state.config.effective()))
+}
+
+/// Swaps in LLM clients built from the config as it is now, e.g. after
+/// rotating a provider key. SIGHUP does the same.
+pub async fn reload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<Reloaded>> {
+    require_admin(&state, &headers)?;
+
+    reload::reload(&state)
+        .await
+        .map(Json)
+        .map_err(AppError::Conflict)
+}
+
+/// The admin API is disabled entirely when `ADMIN_TOKEN` is unset.
+pub fn require_admin(state: &AppState, headers: &HeaderMap) -> AppResult<()> {
+    let expected = state.config.admin_token.as_bytes();
+    if expected.is_empty() {
+        return Err(AppError::Forbidden);
+    }
+
+    let token = headers
+        .get(X_ADMIN_TOKEN)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::Unauthorized)?
+        .as_bytes();
+
+    let matches = token.len() == expected.len()
+        && token
+            .iter()
+            .zip(expected)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+
+    if matches {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized)
+    }
+}
@@ -1,6 +1,10 @@
 use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
 use serde_json::{Value, json};
 
+use crate::AppState;
+
 pub async fn health() -> Json<Value> {
     Json(json!({
         "status": "ok",
@@ -8,3 +12,18 @@ pub async fn health() -> Json<Value> {
         "version": "1.0.0"
     }))
 }
+
+/// 503 until the startup warm-up has finished, then 200; both list the
+/// startup phases finished so far.
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let readiness = &state.readiness;
+    let (status, label) = if readiness.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "starting")
+    };
+    (
+        status,
+        Json(json!({ "status": label, "phases": readiness.phases() })),
+    )
+}
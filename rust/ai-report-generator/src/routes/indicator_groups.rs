@@ -0,0 +1,192 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::AppState;
+use crate::db::indicator_groups::{IndicatorGroup, WriteIndicatorGroup};
+use crate::error::{AppError, AppResult};
+
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct IndicatorGroupBody {
+    pub name: String,
+    pub description: Option<String>,
+    /// Indicator codes, in report order; repeats are dropped.
+    pub indicators: Vec<String>,
+}
+
+/// The body trimmed and without repeated codes, checked for everything but
+/// whether the codes exist.
+fn normalize(body: IndicatorGroupBody) -> AppResult<IndicatorGroupBody> {
+    let name = body.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(AppError::Validation(format!(
+            "name must be 1 to {MAX_NAME_LEN} characters"
+        )));
+    }
+
+    let mut indicators: Vec<String> = Vec::new();
+    for code in body.indicators {
+        let code = code.trim().to_string();
+        if !code.is_empty() && !indicators.contains(&code) {
+            indicators.push(code);
+        }
+    }
+    if indicators.is_empty() {
+        return Err(AppError::Validation("indicators must not be empty".into()));
+    }
+
+    Ok(IndicatorGroupBody {
+        name,
+        description: body
+            .description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty()),
+        indicators,
+    })
+}
+
+/// `normalize`, then checks every code is an indicator's.
+async fn validate(pool: &PgPool, body: IndicatorGroupBody) -> AppResult<IndicatorGroupBody> {
+    let body = normalize(body)?;
+    let unknown = crate::db::indicators::unknown_indicator_codes(pool, &body.indicators)
+        .await
+        .map_err(AppError::Database)?;
+    if !unknown.is_empty() {
+        return Err(AppError::Validation(format!(
+            "unknown indicators: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(body)
+}
+
+/// A name another group already has is a conflict.
+fn write_error(err: sqlx::Error, name: &str) -> AppError {
+    match err.as_database_error() {
+        Some(db_err) if db_err.is_unique_violation() => {
+            AppError::Conflict(format!("An indicator group named {name} already exists"))
+        }
+        _ => AppError::Database(err),
+    }
+}
+
+fn not_found(id: i32) -> AppError {
+    AppError::NotFound(format!("Indicator group {} not found", id))
+}
+
+pub async fn list_indicator_groups(
+    State(state): State<AppState>,
+) -> AppResult<Json<Vec<IndicatorGroup>>> {
+    let groups = crate::db::indicator_groups::list_indicator_groups(&state.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok(Json(groups))
+}
+
+pub async fn create_indicator_group(
+    State(state): State<AppState>,
+    Json(body): Json<IndicatorGroupBody>,
+) -> AppResult<(StatusCode, Json<IndicatorGroup>)> {
+    let body = validate(&state.pool, body).await?;
+    let group = crate::db::indicator_groups::insert_indicator_group(
+        &state.pool,
+        &WriteIndicatorGroup {
+            name: &body.name,
+            description: body.description.as_deref(),
+            indicators: &body.indicators,
+        },
+    )
+    .await
+    .map_err(|e| write_error(e, &body.name))?;
+
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+pub async fn get_indicator_group(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<IndicatorGroup>> {
+    crate::db::indicator_groups::get_indicator_group(&state.pool, id)
+        .await
+        .map_err(AppError::Database)?
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+/// Replaces the group; reports requested by its old name afterwards no
+/// longer find it.
+pub async fn update_indicator_group(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(body): Json<IndicatorGroupBody>,
+) -> AppResult<Json<IndicatorGroup>> {
+    let body = validate(&state.pool, body).await?;
+    crate::db::indicator_groups::update_indicator_group(
+        &state.pool,
+        id,
+        &WriteIndicatorGroup {
+            name: &body.name,
+            description: body.description.as_deref(),
+            indicators: &body.indicators,
+        },
+    )
+    .await
+    .map_err(|e| write_error(e, &body.name))?
+    .map(Json)
+    .ok_or_else(|| not_found(id))
+}
+
+pub async fn delete_indicator_group(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> AppResult<StatusCode> {
+    if !crate::db::indicator_groups::delete_indicator_group(&state.pool, id)
+        .await
+        .map_err(AppError::Database)?
+    {
+        return Err(not_found(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(name: &str, indicators: &[&str]) -> IndicatorGroupBody {
+        IndicatorGroupBody {
+            name: name.to_string(),
+            description: Some("  ".to_string()),
+            indicators: indicators.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_trims_and_drops_repeats() {
+        let group =
+            normalize(body(" Labor Market ", &["UNRATE", " PAYEMS", "UNRATE", ""])).unwrap();
+        assert_eq!(group.name, "Labor Market");
+        assert_eq!(group.indicators, ["UNRATE", "PAYEMS"]);
+        assert_eq!(group.description, None);
+    }
+
+    #[test]
+    fn test_normalize_rejects_empty_groups() {
+        for invalid in [
+            body("  ", &["UNRATE"]),
+            body(&"x".repeat(MAX_NAME_LEN + 1), &["UNRATE"]),
+            body("Labor Market", &[]),
+            body("Labor Market", &[" "]),
+        ] {
+            assert!(matches!(normalize(invalid), Err(AppError::Validation(_))));
+        }
+    }
+}
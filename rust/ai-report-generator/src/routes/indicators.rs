@@ -1,13 +1,22 @@
 use axum::{Json, extract::State};
 
 use crate::AppState;
+use crate::db::indicator_summaries::query_indicator_summaries;
 use crate::db::indicators::Indicator;
 use crate::error::{AppError, AppResult};
 
 pub async fn list_indicators(State(state): State<AppState>) -> AppResult<Json<Vec<Indicator>>> {
-    let indicators = crate::db::indicators::list_indicators(&state.pool)
+    let mut indicators = crate::db::indicators::list_indicators(&state.pool)
         .await
         .map_err(AppError::Database)?;
 
+    let codes: Vec<String> = indicators.iter().map(|i| i.code.clone()).collect();
+    let mut summaries = query_indicator_summaries(&state.pool, &codes)
+        .await
+        .map_err(AppError::Database)?;
+    for indicator in &mut indicators {
+        indicator.summary = summaries.remove(&indicator.code);
+    }
+
     Ok(Json(indicators))
 }
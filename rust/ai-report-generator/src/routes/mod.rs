@@ -1,4 +1,10 @@
+pub mod admin;
 pub mod health;
+pub mod indicator_groups;
 pub mod indicators;
+pub mod providers;
+pub mod report_types;
 pub mod reports;
+pub mod summaries;
 pub mod test;
+pub mod usage;
@@ -0,0 +1,8 @@
+use axum::{Json, extract::State};
+
+use crate::AppState;
+use crate::llm::providers::ProviderStatus;
+
+pub async fn list_providers(State(state): State<AppState>) -> Json<Vec<ProviderStatus>> {
+    Json(state.providers.current().status().await)
+}
@@ -0,0 +1,8 @@
+use axum::{Json, extract::State};
+
+use crate::AppState;
+use crate::pipeline::templates::ReportTemplate;
+
+pub async fn list_report_types(State(state): State<AppState>) -> Json<Vec<ReportTemplate>> {
+    Json(state.templates.list().into_iter().cloned().collect())
+}
@@ -1,34 +1,209 @@
+use std::time::{Duration, Instant};
+
 use axum::{
     Json,
     extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
-use serde::Deserialize;
+use opentelemetry::KeyValue;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::AppState;
-use crate::db::reports::ReportRow;
+use crate::db::report_events::ReportEventRow;
+use crate::db::reports::{InsertPendingReport, ReportRow};
+use crate::deadline::Deadline;
 use crate::error::{AppError, AppResult};
-use crate::pipeline::{ReportRequest, generate_report};
+use crate::pipeline::locale::Language;
+use crate::pipeline::{
+    Checkpoint, GenerationMode, PgReportSink, Pipeline, ReportRequest, TemplateRegistry,
+    expand_groups, generate_report,
+};
+use crate::telemetry::current_trace_id;
+use crate::telemetry::metrics::{REPORT_CANCELLED, REPORT_DEDUPLICATED, REPORT_WAIT_DURATION};
 
-#[derive(Debug, Deserialize)]
+/// Left of the caller's deadline for reading the report after a wait.
+const WAIT_DEADLINE_MARGIN: Duration = Duration::from_millis(250);
+
+/// How long cancelling a running report waits for its pipeline to stop and
+/// record it before answering with the row as it is.
+const CANCEL_WAIT: Duration = Duration::from_secs(5);
+
+/// Stored with the report as `request`, less `force`, so it can be sent again.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CreateReportBody {
+    #[serde(default)]
     pub indicators: Vec<String>,
+    /// Names of indicator groups whose members are added to `indicators`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
     pub start_date: String,
     pub end_date: String,
+    /// Chosen per request: `batch` is meant for scheduled jobs nobody waits
+    /// on, but nothing restricts it to them.
+    #[serde(default)]
+    pub mode: GenerationMode,
+    /// ISO 4217 code to convert currency indicators into.
+    pub currency: Option<String>,
+    /// A type from `GET /api/report-types`; defaults to `general`.
+    pub report_type: Option<String>,
+    #[serde(default)]
+    pub language: Language,
+    /// Record the pipeline's decisions, for
+    /// `GET /api/reports/{id}/explanation`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub explain: bool,
+    /// Generate the report even if an identical one was completed within
+    /// `REPORT_DEDUP_WINDOW_SECS`.
+    #[serde(default, skip_serializing)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetQuery {
+    /// How long to hold the request while the report is `pending`, e.g.
+    /// `30s` or `500ms`; capped at `REPORT_WAIT_MAX_SECS`.
+    pub wait: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ListQuery {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    pub language: Option<Language>,
 }
 
 pub async fn create_report(
     State(state): State<AppState>,
     Json(body): Json<CreateReportBody>,
-) -> AppResult<Json<serde_json::Value>> {
-    if body.indicators.is_empty() {
-        return Err(AppError::Validation("indicators must not be empty".into()));
+) -> AppResult<Response> {
+    let stored_request = serde_json::to_value(&body).unwrap_or_default();
+    let request = resolve_request(&state.pool, &state.templates, &body).await?;
+
+    let config = &state.config;
+    let fingerprint = match body.mode {
+        GenerationMode::Interactive => request.fingerprint(
+            &[
+                &config.llm_provider,
+                &config.llm_model_capable,
+                &config.llm_model_fast,
+            ],
+            config.prompt_data_minimization,
+        ),
+        GenerationMode::Batch => request.fingerprint(
+            &[
+                "openai",
+                &config.openai_batch_model_capable,
+                &config.openai_batch_model_fast,
+            ],
+            config.prompt_data_minimization,
+        ),
+    };
+    if !body.force && config.report_dedup_window_secs > 0 {
+        let window = config.report_dedup_window_secs as i64;
+        if let Some(report) =
+            crate::db::reports::find_fresh_report(&state.pool, &fingerprint, window)
+                .await
+                .map_err(AppError::Database)?
+        {
+            REPORT_DEDUPLICATED.add(
+                1,
+                &[KeyValue::new("report.type", request.template.name.clone())],
+            );
+            tracing::info!(report_id = %report.id, "Answered with an identical recent report");
+            return Ok(Json(with_deduplicated(&report, true)).into_response());
+        }
+    }
+
+    if body.mode == GenerationMode::Batch {
+        let batch = state
+            .batch
+            .as_ref()
+            .ok_or_else(|| AppError::Validation("batch mode requires OPENAI_API_KEY".into()))?;
+        let permit = batch.admit()?;
+
+        let id = Uuid::new_v4();
+        crate::db::reports::insert_pending_report(
+            &state.pool,
+            &InsertPendingReport {
+                id,
+                indicators: &request.indicators,
+                time_range_start: request.start_date,
+                time_range_end: request.end_date,
+                language: request.language.as_str(),
+                report_type: &request.template.name,
+                fingerprint: &fingerprint,
+                request: &stored_request,
+            },
+        )
+        .await
+        .map_err(AppError::Database)?;
+        batch.spawn(permit, id, request, None);
+
+        let body = json!({ "id": id, "status": "pending", "mode": "batch", "deduplicated": false });
+        return Ok((StatusCode::ACCEPTED, Json(body)).into_response());
+    }
+
+    let _permit = state.admission.admit().await?;
+
+    let llm_client = state.llm_client.current();
+    let pipeline = Pipeline {
+        pool: &state.pool,
+        llm_client: &llm_client,
+        guardrails: &state.guardrails,
+        model_capable: &state.config.llm_model_capable,
+        model_fast: &state.config.llm_model_fast,
+        max_prompt_tokens: state.config.max_prompt_tokens,
+        minimization: state.config.prompt_data_minimization,
+        timeouts: state.config.stage_timeouts(),
+        heartbeat: None,
+    };
+    let sink = PgReportSink::new(state.pool.clone(), state.report_watch.clone());
+    let id = Uuid::new_v4();
+    let run = state.running_reports.start(id);
+    let report = match run
+        .run(generate_report(&pipeline, &sink, id, &request, None))
+        .await
+    {
+        Err(AppError::Cancelled) => {
+            // Interactive reports have no row until they are stored, so the
+            // cancelled one gets its own.
+            let pending = InsertPendingReport {
+                id,
+                indicators: &request.indicators,
+                time_range_start: request.start_date,
+                time_range_end: request.end_date,
+                language: request.language.as_str(),
+                report_type: &request.template.name,
+                fingerprint: &fingerprint,
+                request: &stored_request,
+            };
+            record_cancelled(&state, &pending, &request.template.name, run.spent_usd()).await?;
+            return Err(AppError::Cancelled);
+        }
+        result => result?,
+    };
+    crate::db::reports::set_report_origin(&state.pool, report.id, &fingerprint, &stored_request)
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok(Json(with_deduplicated(&report, false)).into_response())
+}
+
+/// Validates the body into what the pipeline runs, with its groups
+/// expanded into their current members.
+async fn resolve_request(
+    pool: &PgPool,
+    templates: &TemplateRegistry,
+    body: &CreateReportBody,
+) -> AppResult<ReportRequest> {
+    if body.indicators.is_empty() && body.groups.is_empty() {
+        return Err(AppError::Validation(
+            "indicators or groups must not be empty".into(),
+        ));
     }
 
     let start_date = chrono::NaiveDate::parse_from_str(&body.start_date, "%Y-%m-%d")
@@ -43,22 +218,62 @@ pub async fn create_report(
         ));
     }
 
-    let request = ReportRequest {
-        indicators: body.indicators,
+    let currency = body
+        .currency
+        .as_ref()
+        .map(|c| c.trim().to_ascii_uppercase());
+    if let Some(code) = &currency
+        && !(code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+    {
+        return Err(AppError::Validation(
+            "currency must be a 3-letter ISO 4217 code".into(),
+        ));
+    }
+
+    let indicators = expand_groups(pool, &body.indicators, &body.groups).await?;
+    let template = templates.get(body.report_type.as_deref())?;
+    template.validate(&indicators)?;
+
+    Ok(ReportRequest {
+        indicators,
         start_date,
         end_date,
-    };
+        currency,
+        language: body.language,
+        template,
+        explain: body.explain,
+    })
+}
 
-    let report = generate_report(
+async fn record_cancelled(
+    state: &AppState,
+    pending: &InsertPendingReport<'_>,
+    report_type: &str,
+    cost_usd: f64,
+) -> AppResult<()> {
+    tracing::info!(report_id = %pending.id, cost_usd, "Report cancelled");
+    REPORT_CANCELLED.add(1, &[KeyValue::new("report.type", report_type.to_string())]);
+    crate::db::reports::insert_pending_report(&state.pool, pending)
+        .await
+        .map_err(AppError::Database)?;
+    crate::db::reports::finish_cancelled_report(
         &state.pool,
-        &state.llm_client,
-        &state.config.llm_model_capable,
-        &state.config.llm_model_fast,
-        &request,
+        pending.id,
+        cost_usd,
+        current_trace_id().as_deref(),
     )
-    .await?;
+    .await
+    .map_err(AppError::Database)?;
+    state.report_watch.notify(pending.id);
+    Ok(())
+}
 
-    Ok(Json(serde_json::to_value(report).unwrap()))
+/// `report` with `deduplicated`: whether it was generated for an earlier,
+/// identical request rather than this one.
+fn with_deduplicated(report: &impl Serialize, deduplicated: bool) -> serde_json::Value {
+    let mut value = serde_json::to_value(report).unwrap();
+    value["deduplicated"] = deduplicated.into();
+    value
 }
 
 pub async fn list_reports(
@@ -68,29 +283,240 @@ pub async fn list_reports(
     let limit = params.limit.unwrap_or(20);
     let offset = params.offset.unwrap_or(0);
 
-    let reports = crate::db::reports::list_reports(&state.pool, limit, offset)
+    let language = params.language.map(Language::as_str);
+    let reports = crate::db::reports::list_reports(&state.pool, limit, offset, language, None)
         .await
         .map_err(AppError::Database)?;
 
     Ok(Json(reports))
 }
 
+/// With `?wait=`, a `pending` report is held until it is stored or fails,
+/// or until the wait runs out, and then returned as it is: still `pending`
+/// on a timeout.
 pub async fn get_report(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    Query(params): Query<GetQuery>,
 ) -> AppResult<Json<ReportRow>> {
-    let report = crate::db::reports::get_report(&state.pool, id)
+    let wait = match params.wait.as_deref() {
+        Some(wait) => parse_wait(wait)
+            .ok_or_else(|| AppError::Validation("invalid wait, use e.g. 30s or 500ms".into()))?,
+        None => Duration::ZERO,
+    };
+    let mut wait = wait.min(Duration::from_secs(state.config.report_wait_max_secs));
+    if let Some(deadline) = Deadline::current() {
+        wait = wait.min(deadline.remaining().saturating_sub(WAIT_DEADLINE_MARGIN));
+    }
+
+    let subscription = (!wait.is_zero()).then(|| state.report_watch.subscribe(id));
+    let report = find_report(&state.pool, id).await?;
+    let Some(mut subscription) = subscription else {
+        return Ok(Json(report));
+    };
+    if is_terminal(&report.status) {
+        REPORT_WAIT_DURATION.record(0.0, &[KeyValue::new("report.wait.outcome", "ready")]);
+        return Ok(Json(report));
+    }
+
+    let start = Instant::now();
+    let outcome = match tokio::time::timeout(wait, subscription.finished()).await {
+        Ok(()) => "finished",
+        Err(_) => "timeout",
+    };
+    REPORT_WAIT_DURATION.record(
+        start.elapsed().as_secs_f64(),
+        &[KeyValue::new("report.wait.outcome", outcome)],
+    );
+
+    Ok(Json(find_report(&state.pool, id).await?))
+}
+
+async fn find_report(pool: &PgPool, id: Uuid) -> AppResult<ReportRow> {
+    crate::db::reports::get_report(pool, id)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound(format!("Report {} not found", id)))
+}
+
+/// Only batch reports are ever stored `pending`; everything else is final.
+fn is_terminal(status: &str) -> bool {
+    status != "pending"
+}
+
+/// `30s`, `1500ms`, `2m`, or a bare number of seconds.
+fn parse_wait(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let n: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "" | "s" => Duration::from_secs(n),
+        "ms" => Duration::from_millis(n),
+        "m" => Duration::from_secs(n.checked_mul(60)?),
+        _ => return None,
+    })
+}
+
+/// Stops the report's pipeline if it is running here: its provider requests
+/// are dropped, its admission slot freed, and the report stored `cancelled`
+/// with what it had cost so far. A `pending` report running elsewhere, or
+/// nowhere since a restart, is only marked `cancelled`, which keeps its
+/// pipeline from storing it.
+pub async fn cancel_report(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ReportRow>> {
+    let mut subscription = state.report_watch.subscribe(id);
+    if state.running_reports.cancel(id) {
+        let _ = tokio::time::timeout(CANCEL_WAIT, subscription.finished()).await;
+        return Ok(Json(find_report(&state.pool, id).await?));
+    }
+
+    if !crate::db::reports::cancel_report(&state.pool, id)
+        .await
+        .map_err(AppError::Database)?
+    {
+        let report = find_report(&state.pool, id).await?;
+        return Err(AppError::Conflict(format!(
+            "Report {} is already {}",
+            id, report.status
+        )));
+    }
+    state.report_watch.notify(id);
+
+    Ok(Json(find_report(&state.pool, id).await?))
+}
+
+/// Runs a `failed` report's pipeline again in the background, from its
+/// checkpoint if it has one. Only batch reports are ever stored `failed`, so
+/// it runs in batch mode, as it was requested.
+pub async fn resume_report(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Response> {
+    let batch = state
+        .batch
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("batch mode requires OPENAI_API_KEY".into()))?;
+    let permit = batch.admit()?;
+
+    let Some(resumed) = crate::db::reports::resume_report(&state.pool, id)
+        .await
+        .map_err(AppError::Database)?
+    else {
+        let report = find_report(&state.pool, id).await?;
+        return Err(AppError::Conflict(if report.status == "failed" {
+            format!("Report {} has no stored request to resume", id)
+        } else {
+            format!(
+                "Report {} is {}, only failed reports can be resumed",
+                id, report.status
+            )
+        }));
+    };
+    let request = serde_json::from_value::<CreateReportBody>(resumed.request)
+        .map_err(|e| AppError::Validation(format!("stored request: {e}")));
+    let request = match request {
+        Ok(body) => resolve_request(&state.pool, &state.templates, &body).await,
+        Err(err) => Err(err),
+    };
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => {
+            crate::db::reports::set_report_status(&state.pool, id, "failed")
+                .await
+                .map_err(AppError::Database)?;
+            return Err(err);
+        }
+    };
+    // A checkpoint from an older version that no longer parses is ignored,
+    // and the report generated from the start.
+    let checkpoint = resumed
+        .checkpoint
+        .and_then(|value| serde_json::from_value::<Checkpoint>(value).ok());
+    let resumed_from = checkpoint.as_ref().map(Checkpoint::stage);
+    tracing::info!(report_id = %id, resumed_from, "Resuming report");
+    batch.spawn(permit, id, request, checkpoint);
+
+    let body =
+        json!({ "id": id, "status": "pending", "mode": "batch", "resumed_from": resumed_from });
+    Ok((StatusCode::ACCEPTED, Json(body)).into_response())
+}
+
+/// The report's stage events, oldest first, including those of a report that
+/// is still being generated.
+pub async fn list_report_events(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Vec<ReportEventRow>>> {
+    let events = crate::db::report_events::list_report_events(&state.pool, id)
+        .await
+        .map_err(AppError::Database)?;
+
+    if events.is_empty()
+        && crate::db::reports::get_report(&state.pool, id)
+            .await
+            .map_err(AppError::Database)?
+            .is_none()
+    {
+        return Err(AppError::NotFound(format!("Report {} not found", id)));
+    }
+
+    Ok(Json(events))
+}
+
+/// The decisions recorded while generating a report requested with
+/// `explain`.
+pub async fn get_report_explanation(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    let report = find_report(&state.pool, id).await?;
+    let explanation = crate::db::report_explanations::get_report_explanation(&state.pool, id)
         .await
         .map_err(AppError::Database)?
-        .ok_or_else(|| AppError::NotFound(format!("Report {} not found", id)))?;
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Report {} has no explanation; request it with \"explain\": true",
+                id
+            ))
+        })?;
 
-    Ok(Json(report))
+    Ok(Json(json!({
+        "report_id": report.id,
+        "status": report.status,
+        "created_at": explanation.created_at,
+        "decisions": explanation.explanation,
+    })))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_wait() {
+        assert_eq!(parse_wait("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("1500ms"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_wait("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_wait("0s"), Some(Duration::ZERO));
+        assert_eq!(parse_wait("s"), None);
+        assert_eq!(parse_wait("-1s"), None);
+        assert_eq!(parse_wait("30h"), None);
+    }
+
+    #[test]
+    fn test_only_pending_reports_are_waited_on() {
+        assert!(!is_terminal("pending"));
+        for status in ["completed", "degraded", "failed", "cancelled"] {
+            assert!(is_terminal(status));
+        }
+    }
+
     #[test]
     fn test_list_query_defaults() {
         let query: ListQuery = serde_json::from_str("{}").unwrap();
@@ -116,6 +542,94 @@ mod tests {
         assert_eq!(body.end_date, "2023-12-31");
     }
 
+    #[test]
+    fn test_create_report_body_mode() {
+        let body: CreateReportBody = serde_json::from_str(
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31"}"#,
+        )
+        .unwrap();
+        assert_eq!(body.mode, GenerationMode::Interactive);
+
+        let body: CreateReportBody = serde_json::from_str(
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31", "mode": "batch"}"#,
+        )
+        .unwrap();
+        assert_eq!(body.mode, GenerationMode::Batch);
+    }
+
+    #[test]
+    fn test_create_report_body_stored_without_force() {
+        let body: CreateReportBody = serde_json::from_str(
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31", "mode": "batch", "force": true}"#,
+        )
+        .unwrap();
+        let stored = serde_json::to_value(&body).unwrap();
+        assert_eq!(stored["mode"], "batch");
+        assert_eq!(stored["language"], "en");
+        assert!(stored.get("force").is_none());
+
+        let replayed: CreateReportBody = serde_json::from_value(stored).unwrap();
+        assert_eq!(replayed.indicators, body.indicators);
+        assert_eq!(replayed.mode, GenerationMode::Batch);
+        assert!(!replayed.force);
+    }
+
+    #[test]
+    fn test_create_report_body_explain() {
+        let body: CreateReportBody = serde_json::from_str(
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31"}"#,
+        )
+        .unwrap();
+        assert!(!body.explain);
+        assert!(
+            serde_json::to_value(&body)
+                .unwrap()
+                .get("explain")
+                .is_none()
+        );
+
+        let body: CreateReportBody = serde_json::from_str(
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31", "explain": true}"#,
+        )
+        .unwrap();
+        assert_eq!(serde_json::to_value(&body).unwrap()["explain"], true);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_request() {
+        // Requests without groups don't query.
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost:1/db")
+            .unwrap();
+        let templates = TemplateRegistry::load(None).unwrap();
+        let body = |json: &str| serde_json::from_str::<CreateReportBody>(json).unwrap();
+
+        let request = resolve_request(
+            &pool,
+            &templates,
+            &body(
+                r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31", "currency": " eur "}"#,
+            ),
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.currency.as_deref(), Some("EUR"));
+        assert_eq!(request.template.name, "general");
+
+        for invalid in [
+            r#"{"indicators": [], "start_date": "2020-01-01", "end_date": "2023-12-31"}"#,
+            r#"{"start_date": "2020-01-01", "end_date": "2023-12-31"}"#,
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-13-01", "end_date": "2023-12-31"}"#,
+            r#"{"indicators": ["UNRATE"], "start_date": "2023-12-31", "end_date": "2020-01-01"}"#,
+            r#"{"indicators": ["UNRATE"], "start_date": "2020-01-01", "end_date": "2023-12-31", "currency": "euro"}"#,
+        ] {
+            assert!(matches!(
+                resolve_request(&pool, &templates, &body(invalid)).await,
+                Err(AppError::Validation(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_create_report_body_empty_indicators() {
         let body: CreateReportBody = serde_json::from_str(
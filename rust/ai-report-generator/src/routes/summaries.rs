@@ -0,0 +1,44 @@
+use axum::{Json, extract::State};
+use serde::Deserialize;
+
+use crate::AppState;
+use crate::error::{AppError, AppResult};
+use crate::pipeline::summarize::{Summary, summarize};
+
+/// Longer text would be mostly dropped from the prompt anyway.
+const MAX_TEXT_CHARS: usize = 100_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSummaryBody {
+    #[serde(default)]
+    pub title: String,
+    pub text: String,
+}
+
+/// Summarizes text with the fast model, for other services that want the
+/// provider setup, fallback and `gen_ai` telemetry without running a report.
+pub async fn create_summary(
+    State(state): State<AppState>,
+    Json(body): Json<CreateSummaryBody>,
+) -> AppResult<Json<Summary>> {
+    if body.text.trim().is_empty() {
+        return Err(AppError::Validation("text must not be empty".into()));
+    }
+    if body.text.chars().count() > MAX_TEXT_CHARS {
+        return Err(AppError::Validation(format!(
+            "text must be at most {MAX_TEXT_CHARS} characters"
+        )));
+    }
+
+    let summary = summarize(
+        &state.llm_client.current(),
+        &state.config.llm_model_fast,
+        &body.title,
+        &body.text,
+        state.config.max_prompt_tokens,
+        state.config.stage_timeouts().generate,
+    )
+    .await?;
+
+    Ok(Json(summary))
+}
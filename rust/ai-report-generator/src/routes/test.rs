@@ -1,25 +1,317 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use axum::Json;
-use axum::extract::State;
+use axum::extract::{Query, State};
+use serde::Deserialize;
 use serde_json::{Value, json};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
 
 use crate::AppState;
-use crate::llm::GenerateRequest;
+use crate::error::{AppError, AppResult};
+use crate::llm::{Endpoints, GenerateRequest, GenerateResponse, LlmClient, Provider};
+use crate::pipeline::{PgReportSink, Pipeline, ReportRequest, generate_report};
+
+/// Requested next to a real indicator for the partial-data scenario; it has
+/// no data points.
+const MISSING_INDICATOR: &str = "DEMO_NO_DATA";
+
+const MAX_SLOW_QUERY_SECS: f64 = 10.0;
+
+/// The demo scenarios answer `404` unless `DEMO_ENDPOINTS_ENABLED` is set.
+fn require_demo(state: &AppState) -> AppResult<()> {
+    if state.config.demo_endpoints_enabled {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("demo endpoints are disabled".into()))
+    }
+}
+
+/// The `demo <scenario>` span a scenario runs in. `demo.scenario` also goes on
+/// the HTTP span, so dashboards can filter requests by scenario.
+fn scenario_span(scenario: &'static str) -> tracing::Span {
+    tracing::Span::current().set_attribute("demo.scenario", scenario);
+    tracing::info_span!(
+        "demo",
+        otel.name = %format!("demo {scenario}"),
+        demo.scenario = scenario,
+    )
+}
+
+fn demo_request(model: &str) -> GenerateRequest {
+    GenerateRequest {
+        model: model.to_string(),
+        system: String::new(),
+        prompt: "Summarize the US labor market in one sentence.".to_string(),
+        temperature: 0.0,
+        max_tokens: 64,
+        stage: "demo".to_string(),
+    }
+}
+
+/// Fails its first `failures` calls with `error`, then hands calls to
+/// `inner`.
+struct DemoProvider {
+    inner: Arc<dyn Provider>,
+    failures: AtomicU32,
+    error: &'static str,
+}
+
+impl DemoProvider {
+    fn new(inner: Arc<dyn Provider>, failures: u32, error: &'static str) -> Self {
+        Self {
+            inner,
+            failures: AtomicU32::new(failures),
+            error,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for DemoProvider {
+    async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            anyhow::bail!("{} (demo)", self.error);
+        }
+        self.inner.generate(req).await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// `base` with `primary` in place of its primary provider.
+fn demo_client(base: &LlmClient, primary: DemoProvider, max_retries: u32) -> LlmClient {
+    LlmClient {
+        primary: Endpoints::single(Arc::new(primary)),
+        fallback: base.fallback.clone(),
+        primary_provider: base.primary_provider.clone(),
+        fallback_provider: base.fallback_provider.clone(),
+        fallback_model: base.fallback_model.clone(),
+        observers: base.observers.clone(),
+        max_retries,
+    }
+}
+
+fn primary_provider(state: &AppState) -> Arc<dyn Provider> {
+    let client = state.llm_client.current();
+    let endpoint = client.primary.iter().next();
+    endpoint
+        .expect("a provider has an endpoint")
+        .provider
+        .clone()
+}
 
-pub async fn trigger_llm_error(State(state): State<AppState>) -> Json<Value> {
+/// Runs the full pipeline and stores the report like `POST /api/reports`.
+async fn run_report(state: &AppState, indicators: Vec<String>) -> AppResult<Value> {
+    let request = ReportRequest {
+        indicators,
+        start_date: chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+        end_date: chrono::NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+        currency: None,
+        language: Default::default(),
+        template: state.templates.get(None)?,
+        explain: false,
+    };
+    let llm_client = state.llm_client.current();
+    let pipeline = Pipeline {
+        pool: &state.pool,
+        llm_client: &llm_client,
+        guardrails: &state.guardrails,
+        model_capable: &state.config.llm_model_capable,
+        model_fast: &state.config.llm_model_fast,
+        max_prompt_tokens: state.config.max_prompt_tokens,
+        minimization: state.config.prompt_data_minimization,
+        timeouts: state.config.stage_timeouts(),
+        heartbeat: None,
+    };
+    let sink = PgReportSink::new(state.pool.clone(), state.report_watch.clone());
+    let report = generate_report(&pipeline, &sink, Uuid::new_v4(), &request, None).await?;
+
+    Ok(json!({
+        "report_id": report.id,
+        "status": report.status.as_str(),
+        "indicators_requested": request.indicators,
+        "indicators_used": report.indicators_used,
+    }))
+}
+
+pub async fn trigger_llm_error(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
     let req = GenerateRequest {
         model: "nonexistent-model-99999".to_string(),
-        system: String::new(),
         prompt: "test error injection".to_string(),
-        temperature: 0.0,
         max_tokens: 1,
         stage: "test".to_string(),
+        ..demo_request("")
     };
 
-    match state.llm_client.generate(&req).await {
+    let result = state
+        .llm_client
+        .current()
+        .generate(&req)
+        .instrument(scenario_span("llm_error"))
+        .await;
+    Ok(match result {
         Ok(_) => Json(json!({"status": "unexpected_success"})),
         Err(e) => Json(json!({
             "status": "error_triggered",
             "error": e.to_string(),
         })),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlowQueryParams {
+    pub seconds: Option<f64>,
+}
+
+pub async fn trigger_slow_query(
+    State(state): State<AppState>,
+    Query(params): Query<SlowQueryParams>,
+) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
+    let seconds = params.seconds.unwrap_or(2.0);
+    if !(0.0..=MAX_SLOW_QUERY_SECS).contains(&seconds) {
+        return Err(AppError::Validation(format!(
+            "seconds must be between 0 and {MAX_SLOW_QUERY_SECS}"
+        )));
+    }
+
+    let rows = crate::db::demo::slow_count(&state.pool, seconds)
+        .instrument(scenario_span("slow_query"))
+        .await
+        .map_err(AppError::Database)?;
+    Ok(Json(
+        json!({"status": "ok", "seconds": seconds, "rows": rows}),
+    ))
+}
+
+/// The primary provider fails every call, so the fallback answers.
+pub async fn trigger_fallback(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
+    if state.llm_client.current().fallback.is_none() {
+        return Err(AppError::Validation(
+            "the fallback scenario needs a FALLBACK_PROVIDER".into(),
+        ));
+    }
+
+    let primary = DemoProvider::new(
+        primary_provider(&state),
+        u32::MAX,
+        "503 service unavailable",
+    );
+    let client = demo_client(&state.llm_client.current(), primary, 1);
+    let resp = client
+        .generate(&demo_request(&state.config.llm_model_fast))
+        .instrument(scenario_span("fallback"))
+        .await
+        .map_err(|e| AppError::Llm(e.to_string()))?;
+    Ok(Json(
+        json!({"status": "ok", "provider": resp.provider, "model": resp.model}),
+    ))
+}
+
+/// The primary provider is rate limited twice, then answers on the third
+/// attempt.
+pub async fn trigger_retries(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
+
+    let primary = DemoProvider::new(primary_provider(&state), 2, "429 rate limit exceeded");
+    let client = demo_client(&state.llm_client.current(), primary, 3);
+    let resp = client
+        .generate(&demo_request(&state.config.llm_model_fast))
+        .instrument(scenario_span("retries"))
+        .await
+        .map_err(|e| AppError::Llm(e.to_string()))?;
+    Ok(Json(
+        json!({"status": "ok", "provider": resp.provider, "model": resp.model}),
+    ))
+}
+
+/// A report on one real indicator and one without data.
+pub async fn trigger_partial_data(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
+
+    let indicators = vec!["UNRATE".to_string(), MISSING_INDICATOR.to_string()];
+    run_report(&state, indicators)
+        .instrument(scenario_span("partial_data"))
+        .await
+        .map(Json)
+}
+
+/// A report with no data at all, which fails in the retrieve stage.
+pub async fn trigger_pipeline_error(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    require_demo(&state)?;
+
+    run_report(&state, vec![MISSING_INDICATOR.to_string()])
+        .instrument(scenario_span("pipeline_error"))
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubProvider(&'static str);
+
+    #[async_trait::async_trait]
+    impl Provider for StubProvider {
+        async fn generate(&self, req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            Ok(GenerateResponse {
+                content: "ok".to_string(),
+                model: req.model.clone(),
+                input_tokens: 10,
+                output_tokens: 5,
+                cost_usd: 0.0,
+                finish_reason: "stop".to_string(),
+                provider: String::new(),
+                span_context: None,
+            })
+        }
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_demo_provider_fails_then_delegates() {
+        let provider = DemoProvider::new(Arc::new(StubProvider("stub")), 1, "429 rate limit");
+        let err = provider.generate(&demo_request("m")).await.unwrap_err();
+        assert_eq!(err.to_string(), "429 rate limit (demo)");
+        assert_eq!(
+            provider.generate(&demo_request("m")).await.unwrap().content,
+            "ok"
+        );
+        assert_eq!(provider.name(), "stub");
+    }
+
+    #[tokio::test]
+    async fn test_demo_client_falls_back() {
+        let base = LlmClient {
+            primary: Endpoints::single(Arc::new(StubProvider("stub"))),
+            fallback: Some(Endpoints::single(Arc::new(StubProvider("backup")))),
+            primary_provider: "stub".to_string(),
+            fallback_provider: "backup".to_string(),
+            fallback_model: "backup-model".to_string(),
+            observers: vec![],
+            max_retries: 3,
+        };
+        let primary = DemoProvider::new(Arc::new(StubProvider("stub")), u32::MAX, "503");
+        let resp = demo_client(&base, primary, 1)
+            .generate(&demo_request("m"))
+            .await
+            .unwrap();
+        assert_eq!(resp.provider, "backup");
+        assert_eq!(resp.model, "backup-model");
     }
 }
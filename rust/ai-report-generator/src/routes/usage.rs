@@ -0,0 +1,130 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::HeaderMap,
+};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::AppState;
+use crate::db::llm_calls::{DailyUsage, ModelUsage};
+use crate::error::{AppError, AppResult};
+use crate::llm::anomaly::SpendRates;
+
+use super::admin::require_admin;
+
+const DEFAULT_DAYS: u64 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+pub async fn daily(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> AppResult<Json<Vec<DailyUsage>>> {
+    require_admin(&state, &headers)?;
+    let (from, to) = query.range(Utc::now().date_naive())?;
+
+    let usage = crate::db::llm_calls::daily_usage(&state.pool, from, to)
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok(Json(usage))
+}
+
+pub async fn by_model(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> AppResult<Json<Vec<ModelUsage>>> {
+    require_admin(&state, &headers)?;
+    let (from, to) = query.range(Utc::now().date_naive())?;
+
+    let usage = crate::db::llm_calls::usage_by_model(&state.pool, from, to)
+        .await
+        .map_err(AppError::Database)?;
+
+    Ok(Json(usage))
+}
+
+/// The cost anomaly detector's current and baseline spend.
+pub async fn rate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<SpendRates>> {
+    require_admin(&state, &headers)?;
+    let detector = state.cost_anomaly.as_ref().ok_or_else(|| {
+        AppError::NotFound("Cost anomaly detection is off (COST_ANOMALY_WINDOW_SECS=0)".into())
+    })?;
+
+    Ok(Json(detector.rates()))
+}
+
+impl UsageQuery {
+    /// Both dates are inclusive UTC days; the default is the last
+    /// `DEFAULT_DAYS` days up to `today`.
+    fn range(&self, today: NaiveDate) -> AppResult<(DateTime<Utc>, DateTime<Utc>)> {
+        let end = match self.end_date.as_deref() {
+            Some(value) => parse_date("end_date", value)?,
+            None => today,
+        };
+        let start = match self.start_date.as_deref() {
+            Some(value) => parse_date("start_date", value)?,
+            None => end - Days::new(DEFAULT_DAYS - 1),
+        };
+        if start > end {
+            return Err(AppError::Validation(
+                "start_date must not be after end_date".into(),
+            ));
+        }
+
+        let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        Ok((midnight(start), midnight(end + Days::new(1))))
+    }
+}
+
+fn parse_date(field: &str, value: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("invalid {field} format, use YYYY-MM-DD")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(start: Option<&str>, end: Option<&str>) -> UsageQuery {
+        UsageQuery {
+            start_date: start.map(String::from),
+            end_date: end.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_range_defaults_to_last_30_days() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 31).unwrap();
+        let (from, to) = query(None, None).range(today).unwrap();
+
+        assert_eq!(from.to_rfc3339(), "2026-03-02T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2026-04-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_range_is_validated() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 31).unwrap();
+
+        let (from, to) = query(Some("2026-01-01"), Some("2026-01-01"))
+            .range(today)
+            .unwrap();
+        assert_eq!((to - from).num_hours(), 24);
+        assert!(
+            query(Some("2026-02-01"), Some("2026-01-01"))
+                .range(today)
+                .is_err()
+        );
+        assert!(query(Some("01/02/2026"), None).range(today).is_err());
+    }
+}
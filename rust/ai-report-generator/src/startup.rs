@@ -0,0 +1,249 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::task::JoinSet;
+use tracing::{Instrument, Span};
+
+use crate::llm::{Endpoints, models, pricing};
+
+/// A finished startup phase, for `GET /readyz`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Phase {
+    pub name: &'static str,
+    pub duration_ms: u64,
+    /// Why the phase failed; the service still becomes ready.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether startup has finished warming up. `/readyz` answers 503 until it
+/// has, so a load balancer holds traffic back from a cold replica.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    ready: AtomicBool,
+    phases: Mutex<Vec<Phase>>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    pub fn phases(&self) -> Vec<Phase> {
+        self.phases.lock().unwrap().clone()
+    }
+
+    /// Runs `phase` in a `startup.phase {name}` span and records how long it
+    /// took.
+    pub async fn phase<T, E: Display>(
+        &self,
+        name: &'static str,
+        phase: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let span = tracing::info_span!(
+            "startup.phase",
+            otel.name = %format!("startup.phase {name}"),
+            startup.phase = name,
+            otel.status_code = tracing::field::Empty,
+            error.message = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = phase.instrument(span.clone()).await;
+        let error = result.as_ref().err().map(ToString::to_string);
+        if let Some(error) = &error {
+            span.record("otel.status_code", "ERROR");
+            span.record("error.message", error.as_str());
+            tracing::warn!(parent: &span, startup.phase = name, error = %error, "Startup phase failed");
+        }
+        self.phases.lock().unwrap().push(Phase {
+            name,
+            duration_ms: started.elapsed().as_millis() as u64,
+            error,
+        });
+        result
+    }
+}
+
+/// Where the warm-up looks up provider hosts: each endpoint's server, or its
+/// proxy when it has one.
+pub fn provider_hosts<'a>(
+    endpoints: impl IntoIterator<Item = (&'a str, &'a Endpoints)>,
+    ollama_base_url: &str,
+) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for (provider, endpoints) in endpoints {
+        for endpoint in endpoints.iter() {
+            let proxy = endpoint
+                .provider
+                .http_settings()
+                .and_then(|http| http.proxy_address());
+            let host = match (&endpoint.server, provider) {
+                _ if proxy.is_some() => proxy,
+                (Some((host, port)), _) => Some(format!("{host}:{port}")),
+                (None, "ollama") => reqwest::Url::parse(ollama_base_url).ok().and_then(|url| {
+                    Some(format!(
+                        "{}:{}",
+                        url.host_str()?,
+                        url.port_or_known_default()?
+                    ))
+                }),
+                (None, _) => pricing::PROVIDER_SERVERS
+                    .get(provider)
+                    .zip(pricing::PROVIDER_PORTS.get(provider))
+                    .map(|(host, port)| format!("{host}:{port}")),
+            };
+            if let Some(host) = host.filter(|h| !hosts.contains(h)) {
+                hosts.push(host);
+            }
+        }
+    }
+    hosts
+}
+
+/// Opens the pool's minimum connections, looks up `hosts` and loads the
+/// pricing file and model registry, each as a phase of `startup`, then marks
+/// the service ready. A failed phase is logged and doesn't hold readiness
+/// back: a report would fail the same way later.
+pub async fn warm_up(readiness: &Readiness, startup: Span, pool: &PgPool, hosts: &[String]) {
+    let started = Instant::now();
+    async {
+        let _ = readiness.phase("db_pool", open_connections(pool)).await;
+        let _ = readiness.phase("dns", resolve(hosts)).await;
+        let _ = readiness
+            .phase("pricing", async {
+                LazyLock::force(&pricing::PRICING);
+                LazyLock::force(&models::MODELS);
+                Ok::<_, String>(())
+            })
+            .await;
+    }
+    .instrument(startup)
+    .await;
+
+    readiness.ready.store(true, Ordering::Release);
+    tracing::info!(
+        duration_ms = started.elapsed().as_millis() as u64,
+        "Warm-up finished, ready for traffic"
+    );
+}
+
+async fn open_connections(pool: &PgPool) -> Result<(), sqlx::Error> {
+    // Held together, so each acquire opens a connection of its own.
+    let mut acquires = JoinSet::new();
+    for _ in 0..pool.options().get_min_connections() {
+        let pool = pool.clone();
+        acquires.spawn(async move { pool.acquire().await });
+    }
+    let mut connections = Vec::new();
+    while let Some(conn) = acquires.join_next().await {
+        connections.push(conn.expect("acquire doesn't panic")?);
+    }
+    Ok(())
+}
+
+async fn resolve(hosts: &[String]) -> Result<(), String> {
+    let mut failed = Vec::new();
+    for host in hosts {
+        if let Err(e) = tokio::net::lookup_host(host.as_str()).await {
+            failed.push(format!("{host}: {e}"));
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("lookup failed for {}", failed.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::llm::{Endpoint, GenerateRequest, GenerateResponse, HttpSettings, Provider};
+
+    struct Proxied(HttpSettings);
+
+    #[async_trait::async_trait]
+    impl Provider for Proxied {
+        async fn generate(&self, _req: &GenerateRequest) -> anyhow::Result<GenerateResponse> {
+            anyhow::bail!("not called")
+        }
+
+        fn name(&self) -> &str {
+            "openai"
+        }
+
+        fn http_settings(&self) -> Option<&HttpSettings> {
+            Some(&self.0)
+        }
+    }
+
+    fn provider(proxy: Option<&str>) -> Arc<dyn Provider> {
+        Arc::new(Proxied(HttpSettings {
+            proxy: proxy.map(str::to_string),
+            ..HttpSettings::default()
+        }))
+    }
+
+    #[test]
+    fn test_provider_hosts() {
+        let openai = Endpoints::single(provider(None));
+        let regions = Endpoints::new(
+            vec![
+                Endpoint::new("eu", provider(None), "https://eu.example.com/v1").unwrap(),
+                Endpoint::new("us", provider(None), "https://api.openai.com/v1").unwrap(),
+            ],
+            std::time::Duration::ZERO,
+        );
+        let proxied = Endpoints::single(provider(Some("http://user:pw@egress.internal:3128")));
+        let ollama = Endpoints::single(provider(None));
+
+        let hosts = provider_hosts(
+            [
+                ("openai", &openai),
+                ("openai", &regions),
+                ("anthropic", &proxied),
+                ("ollama", &ollama),
+            ],
+            "http://ollama.internal:11434",
+        );
+        assert_eq!(
+            hosts,
+            [
+                "api.openai.com:443",
+                "eu.example.com:443",
+                "egress.internal:3128",
+                "ollama.internal:11434",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phases_are_recorded_and_failures_kept() {
+        let readiness = Readiness::default();
+        assert!(
+            readiness
+                .phase("ok", async { Ok::<_, String>(1) })
+                .await
+                .is_ok()
+        );
+        assert!(
+            readiness
+                .phase("dns", async { Err::<(), _>("lookup failed".to_string()) })
+                .await
+                .is_err()
+        );
+
+        let phases = readiness.phases();
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].name, "ok");
+        assert!(phases[0].error.is_none());
+        assert_eq!(phases[1].error.as_deref(), Some("lookup failed"));
+        assert!(!readiness.is_ready());
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use opentelemetry::trace::TraceContextExt;
+use tracing::dispatcher::WeakDispatch;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Dispatch, Event, Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+/// `http.request_id` is set on the HTTP span from `x-request-id`; it wins over
+/// `request_id`, which tracing-actix-web's root span generates on its own.
+const REQUEST_ID_FIELDS: &[&str] = &["http.request_id", "request_id"];
+
+#[derive(Debug, Default)]
+struct Correlation {
+    ids: Option<(String, String)>,
+    request_id: Option<String>,
+}
+
+/// Keeps each span's OTel trace and span ids, and its request id field, for
+/// [`CorrelatedFormat`]. Add it after the `OpenTelemetryLayer`: the ids are
+/// read when a span is first entered, after that layer has started it (and
+/// after any `set_parent`).
+#[derive(Default)]
+pub struct CorrelationLayer {
+    dispatch: OnceLock<WeakDispatch>,
+}
+
+impl<S> Layer<S> for CorrelationLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_register_dispatch(&self, subscriber: &Dispatch) {
+        let _ = self.dispatch.set(subscriber.downgrade());
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = RequestIdVisitor(None);
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Correlation {
+                ids: None,
+                request_id: visitor.request_id(),
+            });
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = RequestIdVisitor(None);
+        values.record(&mut visitor);
+        if let (Some(request_id), Some(span)) = (visitor.request_id(), ctx.span(id))
+            && let Some(correlation) = span.extensions_mut().get_mut::<Correlation>()
+        {
+            correlation.request_id = Some(request_id);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if span
+            .extensions()
+            .get::<Correlation>()
+            .is_none_or(|c| c.ids.is_some())
+        {
+            return;
+        }
+        // Must not hold the span's extensions here; this locks them.
+        let Some(cx) = self
+            .dispatch
+            .get()
+            .and_then(WeakDispatch::upgrade)
+            .and_then(|dispatch| tracing_opentelemetry::get_otel_context(id, &dispatch))
+        else {
+            return;
+        };
+        let otel_span = cx.span();
+        let span_context = otel_span.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+        if let Some(correlation) = span.extensions_mut().get_mut::<Correlation>() {
+            correlation.ids = Some((
+                span_context.trace_id().to_string(),
+                span_context.span_id().to_string(),
+            ));
+        }
+    }
+}
+
+/// The trace id of the current span, for error bodies and the reports row.
+pub fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span_ref = context.span();
+    let span_context = span_ref.span_context();
+
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// The request id of the current span or its nearest ancestor with one, for
+/// error bodies. Needs [`CorrelationLayer`] on the current subscriber.
+pub fn current_request_id() -> Option<String> {
+    Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            registry.span(id)?.scope().find_map(|span| {
+                span.extensions()
+                    .get::<Correlation>()
+                    .and_then(|c| c.request_id.clone())
+            })
+        })
+        .flatten()
+}
+
+/// Keeps the value of the highest-priority [`REQUEST_ID_FIELDS`] entry seen.
+struct RequestIdVisitor(Option<(usize, String)>);
+
+impl RequestIdVisitor {
+    fn request_id(self) -> Option<String> {
+        self.0.map(|(_, request_id)| request_id)
+    }
+
+    fn set(&mut self, rank: usize, value: String) {
+        if !value.is_empty() && self.0.as_ref().is_none_or(|(seen, _)| rank <= *seen) {
+            self.0 = Some((rank, value));
+        }
+    }
+}
+
+impl Visit for RequestIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if let Some(rank) = REQUEST_ID_FIELDS.iter().position(|f| *f == field.name()) {
+            self.set(rank, value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if let Some(rank) = REQUEST_ID_FIELDS.iter().position(|f| *f == field.name()) {
+            // `%value` fields arrive here as their `Display` output.
+            self.set(rank, format!("{value:?}"));
+        }
+    }
+}
+
+/// Wraps a `fmt` event formatter so every line carries `trace_id`, `span_id`
+/// (inside a span), `service.name` and `request_id` (inside a request): as
+/// top-level keys of JSON lines, or as a `key=value` prefix of text lines.
+/// Needs [`CorrelationLayer`] on the same subscriber.
+pub struct CorrelatedFormat<F> {
+    inner: F,
+    service_name: String,
+    json: bool,
+}
+
+impl<F> CorrelatedFormat<F> {
+    pub fn json(inner: F, service_name: impl Into<String>) -> Self {
+        Self {
+            inner,
+            service_name: service_name.into(),
+            json: true,
+        }
+    }
+
+    pub fn text(inner: F, service_name: impl Into<String>) -> Self {
+        Self {
+            inner,
+            service_name: service_name.into(),
+            json: false,
+        }
+    }
+
+    fn fields<S, N>(&self, ctx: &FmtContext<'_, S, N>) -> Vec<(&'static str, String)>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+        N: for<'a> FormatFields<'a> + 'static,
+    {
+        let (mut ids, mut request_id) = (None, None);
+        for span in ctx.event_scope().into_iter().flatten() {
+            let extensions = span.extensions();
+            let Some(correlation) = extensions.get::<Correlation>() else {
+                continue;
+            };
+            ids = ids.or_else(|| correlation.ids.clone());
+            request_id = request_id.or_else(|| correlation.request_id.clone());
+        }
+
+        let mut fields = Vec::with_capacity(4);
+        if let Some((trace_id, span_id)) = ids {
+            fields.push(("trace_id", trace_id));
+            fields.push(("span_id", span_id));
+        }
+        fields.push(("service.name", self.service_name.clone()));
+        if let Some(request_id) = request_id {
+            fields.push(("request_id", request_id));
+        }
+        fields
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for CorrelatedFormat<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let fields = self.fields(ctx);
+        if !self.json {
+            for (key, value) in &fields {
+                write!(writer, "{key}={value} ")?;
+            }
+            return self.inner.format_event(ctx, writer, event);
+        }
+
+        let mut line = String::new();
+        self.inner
+            .format_event(ctx, Writer::new(&mut line), event)?;
+        let Some(object) = line.trim_end().strip_suffix('}') else {
+            return writer.write_str(&line);
+        };
+        writer.write_str(object)?;
+        for (key, value) in &fields {
+            let value = serde_json::to_string(value).map_err(|_| fmt::Error)?;
+            write!(writer, ",\"{key}\":{value}")?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    /// Logs `f`'s events through the same layers as `init_telemetry`.
+    fn capture(json: bool, f: impl FnOnce()) -> (Vec<String>, Vec<SpanData>) {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let fmt_layer = tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || writer.clone());
+        let fmt_layer = if json {
+            fmt_layer
+                .json()
+                .map_event_format(|f| CorrelatedFormat::json(f, "test-service"))
+                .boxed()
+        } else {
+            fmt_layer
+                .map_event_format(|f| CorrelatedFormat::text(f, "test-service"))
+                .boxed()
+        };
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .with(CorrelationLayer::default())
+            .with(fmt_layer);
+        tracing::subscriber::with_default(subscriber, f);
+        (buffer.lines(), exporter.get_finished_spans().unwrap())
+    }
+
+    #[test]
+    fn test_json_lines_carry_trace_and_request_ids() {
+        let (lines, spans) = capture(true, || {
+            tracing::info!("starting");
+            let request = tracing::info_span!("HTTP request", http.request_id = %"req-1");
+            let _request = request.enter();
+            let _query = tracing::info_span!("db.query").entered();
+            tracing::info!(rows = 3, "query \"done\"");
+        });
+
+        let lines: Vec<serde_json::Value> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines[0]["service.name"], "test-service");
+        assert!(lines[0].get("trace_id").is_none());
+        assert!(lines[0].get("request_id").is_none());
+
+        let query = spans.iter().find(|s| s.name == "db.query").unwrap();
+        assert_eq!(lines[1]["fields"]["message"], "query \"done\"");
+        assert_eq!(
+            lines[1]["trace_id"],
+            query.span_context.trace_id().to_string()
+        );
+        assert_eq!(
+            lines[1]["span_id"],
+            query.span_context.span_id().to_string()
+        );
+        assert_eq!(lines[1]["request_id"], "req-1");
+    }
+
+    #[test]
+    fn test_current_request_id_prefers_http_request_id() {
+        let subscriber = tracing_subscriber::registry().with(CorrelationLayer::default());
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(current_request_id(), None);
+            let _request = tracing::info_span!(
+                "HTTP request",
+                request_id = "generated",
+                http.request_id = %"req-3"
+            )
+            .entered();
+            let _handler = tracing::info_span!("handler").entered();
+            assert_eq!(current_request_id().as_deref(), Some("req-3"));
+        });
+    }
+
+    #[test]
+    fn test_text_lines_are_prefixed() {
+        let (lines, _) = capture(false, || {
+            let _span = tracing::info_span!("HTTP request", request_id = "req-2").entered();
+            tracing::info!("handled");
+        });
+
+        assert!(lines[0].starts_with("trace_id="), "{}", lines[0]);
+        assert!(
+            lines[0].contains(" service.name=test-service request_id=req-2 "),
+            "{}",
+            lines[0]
+        );
+        assert!(lines[0].ends_with("handled"), "{}", lines[0]);
+    }
+}
@@ -0,0 +1,215 @@
+//! `http.requests.total` and `http.request.duration`, recorded without
+//! allocating: every attribute value is a `&'static str`, and the method
+//! and status class pairs are built once rather than per request.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use axum::http::{Method, Request, Response, StatusCode};
+use opentelemetry::KeyValue;
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use super::metrics::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL};
+
+/// The methods with their own `http.method`. Any other is `_OTHER`, as in
+/// the semantic conventions, so clients can't mint new series.
+const METHODS: [(Method, &str); 9] = [
+    (Method::GET, "GET"),
+    (Method::HEAD, "HEAD"),
+    (Method::POST, "POST"),
+    (Method::PUT, "PUT"),
+    (Method::DELETE, "DELETE"),
+    (Method::CONNECT, "CONNECT"),
+    (Method::OPTIONS, "OPTIONS"),
+    (Method::TRACE, "TRACE"),
+    (Method::PATCH, "PATCH"),
+];
+const OTHER_METHOD: usize = METHODS.len();
+
+/// A `StatusCode` is 100 to 999.
+const STATUS_CLASSES: [&str; 9] = [
+    "1xx", "2xx", "3xx", "4xx", "5xx", "6xx", "7xx", "8xx", "9xx",
+];
+
+static STATUS_DIGITS: [[u8; 3]; 900] = {
+    let mut digits = [[0; 3]; 900];
+    let mut i = 0;
+    while i < digits.len() {
+        let code = i + 100;
+        digits[i] = [
+            b'0' + (code / 100) as u8,
+            b'0' + (code / 10 % 10) as u8,
+            b'0' + (code % 10) as u8,
+        ];
+        i += 1;
+    }
+    digits
+};
+
+/// `"100"` to `"999"`, indexed by status code minus 100.
+static STATUS_CODES: [&str; 900] = {
+    let mut codes = [""; 900];
+    let mut i = 0;
+    while i < codes.len() {
+        codes[i] = match std::str::from_utf8(&STATUS_DIGITS[i]) {
+            Ok(code) => code,
+            Err(_) => panic!("status codes are ASCII digits"),
+        };
+        i += 1;
+    }
+    codes
+};
+
+/// `[http.method, http.status_class]` by method, then status class.
+static METHOD_AND_CLASS: LazyLock<[[[KeyValue; 2]; 9]; OTHER_METHOD + 1]> = LazyLock::new(|| {
+    std::array::from_fn(|method| {
+        let method = METHODS.get(method).map_or("_OTHER", |(_, name)| name);
+        STATUS_CLASSES.map(|class| {
+            [
+                KeyValue::new("http.method", method),
+                KeyValue::new("http.status_class", class),
+            ]
+        })
+    })
+});
+
+/// The attributes of a request's points. Cloning the prebuilt pairs copies
+/// `&'static str`s, so this doesn't allocate.
+fn attributes(method: &Method, status: StatusCode) -> [KeyValue; 3] {
+    let method = METHODS
+        .iter()
+        .position(|(known, _)| known == method)
+        .unwrap_or(OTHER_METHOD);
+    let code = usize::from(status.as_u16());
+    let [method, class] = &METHOD_AND_CLASS[method][code / 100 - 1];
+    [
+        method.clone(),
+        class.clone(),
+        KeyValue::new("http.status_code", STATUS_CODES[code - 100]),
+    ]
+}
+
+pub fn record_http_request(method: &Method, status: StatusCode, latency: Duration) {
+    let attributes = attributes(method, status);
+    HTTP_REQUESTS_TOTAL.add(1, &attributes);
+    HTTP_REQUEST_DURATION.record(latency.as_secs_f64() * 1000.0, &attributes);
+}
+
+/// Records every response of the service it wraps. `TraceLayer`'s
+/// `OnResponse` never sees the request, so the method is taken here;
+/// cloning one of the standard methods doesn't allocate either.
+#[derive(Clone, Copy, Default)]
+pub struct HttpMetricsLayer;
+
+impl<S> Layer<S> for HttpMetricsLayer {
+    type Service = HttpMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpMetrics { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpMetrics<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for HttpMetrics<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = HttpMetricsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        HttpMetricsFuture {
+            method: request.method().clone(),
+            started: Instant::now(),
+            inner: self.inner.call(request),
+        }
+    }
+}
+
+pin_project! {
+    pub struct HttpMetricsFuture<F> {
+        #[pin]
+        inner: F,
+        method: Method,
+        started: Instant,
+    }
+}
+
+impl<F, ResBody, E> Future for HttpMetricsFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        if let Ok(response) = &result {
+            record_http_request(this.method, response.status(), this.started.elapsed());
+        }
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use axum::body::Body;
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    #[test]
+    fn test_status_codes_are_static_strings() {
+        for code in [100, 200, 404, 503, 999] {
+            let status = StatusCode::from_u16(code).unwrap();
+            let [_, class, status_code] = attributes(&Method::GET, status);
+            assert_eq!(status_code.value.as_str(), code.to_string());
+            assert_eq!(class.value.as_str(), format!("{}xx", code / 100));
+        }
+    }
+
+    #[test]
+    fn test_unknown_methods_share_one_series() {
+        let purge = Method::from_bytes(b"PURGE").unwrap();
+        let [method, ..] = attributes(&purge, StatusCode::OK);
+        assert_eq!(method, KeyValue::new("http.method", "_OTHER"));
+
+        let [method, ..] = attributes(&Method::PATCH, StatusCode::OK);
+        assert_eq!(method, KeyValue::new("http.method", "PATCH"));
+    }
+
+    #[tokio::test]
+    async fn test_layer_records_the_request_method() {
+        let service = HttpMetricsLayer.layer(service_fn(|_: Request<Body>| async {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::ALREADY_REPORTED;
+            Ok::<_, Infallible>(response)
+        }));
+        let request = Request::delete("/api/thing").body(Body::empty()).unwrap();
+        service.oneshot(request).await.unwrap();
+
+        let attributes = [
+            KeyValue::new("http.method", "DELETE"),
+            KeyValue::new("http.status_code", "208"),
+            KeyValue::new("http.status_class", "2xx"),
+        ];
+        assert_metric_recorded("http.requests.total", &attributes);
+        assert_metric_recorded("http.request.duration", &attributes);
+    }
+}
@@ -5,13 +5,17 @@ use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::{
     Resource,
     logs::SdkLoggerProvider,
-    metrics::{PeriodicReader, SdkMeterProvider},
+    metrics::{PeriodicReader, SdkMeterProvider, Temporality},
     trace::SdkTracerProvider,
 };
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 use tracing_opentelemetry::OpenTelemetryLayer;
 use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
+use super::correlation::{CorrelatedFormat, CorrelationLayer};
+use super::otlp::ExporterAuth;
 use crate::config::Config;
 
 pub struct TelemetryGuard {
@@ -45,10 +49,11 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
         ))
         .with_attribute(KeyValue::new("environment", config.environment.clone()))
         .build();
+    let auth = ExporterAuth::from_config(config)?;
 
     // Traces
-    let trace_exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
+    let trace_exporter = auth
+        .apply(opentelemetry_otlp::SpanExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
@@ -61,17 +66,20 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
     global::set_tracer_provider(tracer_provider.clone());
 
     // Metrics
-    let metric_exporter = opentelemetry_otlp::MetricExporter::builder()
-        .with_tonic()
-        .with_endpoint(&config.otel_exporter_endpoint)
-        .with_timeout(Duration::from_secs(10))
-        .build()?;
+    let metric_exporter = metric_exporter(
+        &config.otel_exporter_endpoint,
+        &auth,
+        config.metrics_temporality,
+    )?;
 
     let metric_reader = PeriodicReader::builder(metric_exporter)
         .with_interval(Duration::from_secs(15))
         .build();
 
-    let meter_provider = SdkMeterProvider::builder()
+    let meter_provider = config
+        .metric_views()
+        .map_err(anyhow::Error::msg)?
+        .register(SdkMeterProvider::builder())
         .with_reader(metric_reader)
         .with_resource(resource.clone())
         .build();
@@ -79,8 +87,8 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
     global::set_meter_provider(meter_provider.clone());
 
     // Logs
-    let log_exporter = opentelemetry_otlp::LogExporter::builder()
-        .with_tonic()
+    let log_exporter = auth
+        .apply(opentelemetry_otlp::LogExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
@@ -98,15 +106,23 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info,tower_http=debug"));
 
+    let service_name = config.otel_service_name.clone();
     let fmt_layer = if config.is_production() {
-        tracing_subscriber::fmt::layer().json().boxed()
+        tracing_subscriber::fmt::layer()
+            .json()
+            .map_event_format(|f| CorrelatedFormat::json(f, service_name))
+            .boxed()
     } else {
-        tracing_subscriber::fmt::layer().pretty().boxed()
+        tracing_subscriber::fmt::layer()
+            .pretty()
+            .map_event_format(|f| CorrelatedFormat::text(f, service_name))
+            .boxed()
     };
 
     tracing_subscriber::registry()
         .with(env_filter)
         .with(telemetry_layer)
+        .with(CorrelationLayer::default())
         .with(otel_log_layer)
         .with(fmt_layer)
         .init();
@@ -123,3 +139,85 @@ pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
         meter_provider,
     })
 }
+
+/// `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE`. Backends that only
+/// ingest deltas, such as some Scout ingestion paths, need `delta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsTemporality {
+    Cumulative,
+    Delta,
+    LowMemory,
+}
+
+impl FromStr for MetricsTemporality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cumulative" => Ok(Self::Cumulative),
+            "delta" => Ok(Self::Delta),
+            "lowmemory" => Ok(Self::LowMemory),
+            other => Err(format!(
+                "expected cumulative, delta or lowmemory, got {other:?}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MetricsTemporality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cumulative => "cumulative",
+            Self::Delta => "delta",
+            Self::LowMemory => "lowmemory",
+        })
+    }
+}
+
+impl From<MetricsTemporality> for Temporality {
+    fn from(temporality: MetricsTemporality) -> Self {
+        match temporality {
+            MetricsTemporality::Cumulative => Temporality::Cumulative,
+            MetricsTemporality::Delta => Temporality::Delta,
+            MetricsTemporality::LowMemory => Temporality::LowMemory,
+        }
+    }
+}
+
+fn metric_exporter(
+    endpoint: &str,
+    auth: &ExporterAuth,
+    temporality: MetricsTemporality,
+) -> anyhow::Result<opentelemetry_otlp::MetricExporter> {
+    Ok(auth
+        .apply(opentelemetry_otlp::MetricExporter::builder().with_tonic())
+        .with_endpoint(endpoint)
+        .with_timeout(Duration::from_secs(10))
+        .with_temporality(temporality.into())
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metric_exporter_uses_temporality_preference() {
+        for (preference, expected) in [
+            ("cumulative", Temporality::Cumulative),
+            ("DELTA", Temporality::Delta),
+        ] {
+            let temporality = preference.parse().unwrap();
+            let exporter = metric_exporter(
+                "http://localhost:4317",
+                &ExporterAuth::default(),
+                temporality,
+            )
+            .unwrap();
+            assert_eq!(exporter.temporality(), expected);
+        }
+        assert!("sum".parse::<MetricsTemporality>().is_err());
+    }
+}
@@ -1,10 +1,14 @@
 use opentelemetry::{
     global,
-    metrics::{Counter, Histogram, Meter},
+    metrics::{Counter, Gauge, Histogram, Meter, UpDownCounter},
 };
 use std::sync::LazyLock;
 
-pub static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("ai-report-generator"));
+pub static METER: LazyLock<Meter> = LazyLock::new(|| {
+    #[cfg(test)]
+    super::testing::install_meter_provider();
+    global::meter("ai-report-generator")
+});
 
 // --- LLM Gateway Contract Metrics (6 required) ---
 
@@ -48,6 +52,14 @@ pub static GEN_AI_FALLBACK_COUNT: LazyLock<Counter<u64>> = LazyLock::new(|| {
         .build()
 });
 
+pub static GEN_AI_ENDPOINT_FAILOVER_COUNT: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("gen_ai.client.endpoint_failover.count")
+        .with_description("Number of LLM calls moved to another endpoint of the same provider")
+        .with_unit("{failover}")
+        .build()
+});
+
 pub static GEN_AI_ERROR_COUNT: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
         .u64_counter("gen_ai.client.error.count")
@@ -56,6 +68,32 @@ pub static GEN_AI_ERROR_COUNT: LazyLock<Counter<u64>> = LazyLock::new(|| {
         .build()
 });
 
+pub static GEN_AI_COST_RATE: LazyLock<Gauge<f64>> = LazyLock::new(|| {
+    METER
+        .f64_gauge("gen_ai.cost.rate")
+        .with_description("LLM spend in the last anomaly detection window, per minute")
+        .with_unit("usd/min")
+        .build()
+});
+
+pub static GEN_AI_COST_BASELINE: LazyLock<Gauge<f64>> = LazyLock::new(|| {
+    METER
+        .f64_gauge("gen_ai.cost.baseline")
+        .with_description("Moving average of normal LLM spend, per minute")
+        .with_unit("usd/min")
+        .build()
+});
+
+pub static GEN_AI_COST_ANOMALY: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("gen_ai.cost.anomaly")
+        .with_description(
+            "LLM spend anomalies: windows spending a multiple of the baseline, counted once each",
+        )
+        .with_unit("{anomaly}")
+        .build()
+});
+
 // --- Domain Metrics ---
 
 pub static REPORT_GENERATION_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
@@ -82,6 +120,80 @@ pub static REPORT_SECTIONS: LazyLock<Histogram<f64>> = LazyLock::new(|| {
         .build()
 });
 
+pub static REPORT_DEGRADED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("report.degraded")
+        .with_description("Reports served from statistics because every LLM provider failed")
+        .with_unit("{report}")
+        .build()
+});
+
+pub static REPORT_CANCELLED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("report.cancelled")
+        .with_description("Report pipelines stopped by POST /api/reports/{id}/cancel")
+        .with_unit("{report}")
+        .build()
+});
+
+pub static REPORT_STALLED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("report.stalled")
+        .with_description(
+            "Pending reports failed because their pipeline stopped sending heartbeats",
+        )
+        .with_unit("{report}")
+        .build()
+});
+
+pub static REPORT_DEDUPLICATED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("report.deduplicated")
+        .with_description("Report requests answered with an identical recent report")
+        .with_unit("{report}")
+        .build()
+});
+
+pub static REPORT_GUARDRAIL_VIOLATIONS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("report.guardrail.violations")
+        .with_description("Guardrail rules violated by generated report narratives")
+        .with_unit("{violation}")
+        .build()
+});
+
+pub static REPORT_QUEUE_DEPTH: LazyLock<UpDownCounter<i64>> = LazyLock::new(|| {
+    METER
+        .i64_up_down_counter("report.queue.depth")
+        .with_description("Report requests waiting for a pipeline slot")
+        .with_unit("{request}")
+        .build()
+});
+
+pub static REPORT_QUEUE_WAIT: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("report.queue.wait")
+        .with_description("Time a report request waited for a pipeline slot")
+        .with_unit("s")
+        .build()
+});
+
+pub static REPORT_WAIT_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("report.wait.duration")
+        .with_description("Time a report fetch with ?wait= was held before answering")
+        .with_unit("s")
+        .build()
+});
+
+pub static LLM_AUDIT_DROPPED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("llm.audit.dropped")
+        .with_description("LLM call audit rows dropped because the writer queue was full")
+        .with_unit("{call}")
+        .build()
+});
+
 // --- HTTP Metrics ---
 
 pub static HTTP_REQUESTS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -102,3 +214,36 @@ pub static HTTP_REQUEST_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
         ])
         .build()
 });
+
+pub static TLS_HANDSHAKE_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("tls.handshake.duration")
+        .with_description(
+            "Time from accepting a connection to finishing its TLS handshake, failed ones by error.type",
+        )
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0,
+        ])
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_SUCCESS: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("synthetic.check.success")
+        .with_description(
+            "1 if the last synthetic check of the service's own critical path passed, else 0",
+        )
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("synthetic.check.duration")
+        .with_description("Synthetic check duration in milliseconds")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+        ])
+        .build()
+});
@@ -1,5 +1,15 @@
+pub mod correlation;
+pub mod http;
 pub mod init;
 pub mod metrics;
+pub mod otlp;
+#[cfg(test)]
+pub mod testing;
+pub mod views;
 
-pub use init::init_telemetry;
+pub use correlation::{current_request_id, current_trace_id};
+pub use http::HttpMetricsLayer;
+pub use init::{MetricsTemporality, init_telemetry};
 pub use metrics::*;
+pub use otlp::parse_headers;
+pub use views::MetricViews;
@@ -0,0 +1,518 @@
+//! Route-level authorization for the axum-postgres and actix-postgres
+//! examples. Who may call what is written down once, in a TOML [`Policy`],
+//! instead of as ownership checks scattered through the services: each
+//! example's middleware finds the rule for a request, loads the article it
+//! names when the rule is about ownership, and turns the [`Decision`] into
+//! its own response.
+
+mod route;
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use route::Route;
+
+/// The path parameter naming the article that `owner` and `author` are
+/// checked against.
+pub const ARTICLE_PARAM: &str = "slug";
+
+/// The path parameter that `self` compares with the caller.
+pub const USER_PARAM: &str = "user_id";
+
+#[derive(Debug, Error)]
+#[error("invalid authorization policy:\n  - {}", .errors.join("\n  - "))]
+pub struct PolicyError {
+    pub errors: Vec<String>,
+}
+
+/// What a rule can require of the caller, regardless of the resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Signed in with a bearer token.
+    User,
+    /// Holding the admin token.
+    Admin,
+}
+
+/// How the caller can relate to what the route names. A rule with several
+/// allows the request when any one holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relation {
+    /// Owns the article at `{slug}`.
+    Owner,
+    /// Owns or co-authors the article at `{slug}`.
+    Author,
+    /// Is the user at `{user_id}`.
+    #[serde(rename = "self")]
+    Myself,
+}
+
+impl Relation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Relation::Owner => "owner",
+            Relation::Author => "author",
+            Relation::Myself => "self",
+        }
+    }
+
+    fn param(self) -> &'static str {
+        match self {
+            Relation::Owner | Relation::Author => ARTICLE_PARAM,
+            Relation::Myself => USER_PARAM,
+        }
+    }
+}
+
+/// Who is asking, as the example's middleware authenticated them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Subject {
+    pub user_id: Option<i32>,
+    pub admin: bool,
+}
+
+impl Subject {
+    fn has(&self, role: Role) -> bool {
+        match role {
+            Role::User => self.user_id.is_some(),
+            Role::Admin => self.admin,
+        }
+    }
+}
+
+/// Who wrote the article a rule is about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Authors {
+    pub owner: i32,
+    /// May include the owner.
+    pub co_authors: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    /// The caller lacks the rule's role, or it needs a signed-in user and
+    /// there isn't one: a 401.
+    Unauthenticated,
+    /// The caller holds none of the rule's relations: a 403.
+    Forbidden,
+    /// The article the rule is about doesn't exist: a 404, as the handler
+    /// would have answered.
+    NotFound,
+}
+
+impl Decision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::Unauthenticated => "unauthenticated",
+            Decision::Forbidden => "forbidden",
+            Decision::NotFound => "not_found",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    rule: Vec<RuleFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    name: String,
+    route: String,
+    role: Option<Role>,
+    #[serde(default)]
+    allow: Vec<Relation>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    route: Route,
+    role: Option<Role>,
+    allow: Vec<Relation>,
+}
+
+/// Rules in file order; the first whose route matches decides, and a
+/// request none matches is left to its handler.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Parses and checks every rule, reporting all the problems at once.
+    pub fn from_toml(source: &str) -> Result<Self, PolicyError> {
+        let file: PolicyFile = toml::from_str(source).map_err(|e| PolicyError {
+            errors: vec![e.message().to_string()],
+        })?;
+
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        let mut rules = Vec::with_capacity(file.rule.len());
+        for rule in file.rule {
+            if !names.insert(rule.name.clone()) {
+                errors.push(format!("{}: duplicate rule name", rule.name));
+            }
+            let route = match Route::parse(&rule.route) {
+                Ok(route) => route,
+                Err(e) => {
+                    errors.push(format!("{}: {e}", rule.name));
+                    continue;
+                }
+            };
+            for relation in &rule.allow {
+                if !route.has_param(relation.param()) {
+                    errors.push(format!(
+                        "{}: `{}` needs a `{{{}}}` in the route",
+                        rule.name,
+                        relation.as_str(),
+                        relation.param()
+                    ));
+                }
+            }
+            rules.push(Rule {
+                name: rule.name,
+                route,
+                role: rule.role,
+                allow: rule.allow,
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(Self { rules })
+        } else {
+            Err(PolicyError { errors })
+        }
+    }
+
+    /// Fails unless every rule in `names` is in the policy. An example
+    /// calls it for the rules its services rely on, so a policy file that
+    /// leaves one out, and with it the route, unguarded, is refused at
+    /// startup.
+    pub fn require(&self, names: &[&str]) -> Result<(), PolicyError> {
+        let errors: Vec<String> = names
+            .iter()
+            .filter(|name| !self.rules.iter().any(|rule| rule.name == **name))
+            .map(|name| format!("{name}: required rule is missing"))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PolicyError { errors })
+        }
+    }
+
+    /// The rule for a request. `path` is relative to the API root, the way
+    /// the rules are written.
+    pub fn find<'a>(&'a self, method: &str, path: &'a str) -> Option<Match<'a>> {
+        self.rules.iter().find_map(|rule| {
+            let params = rule.route.matches(method, path)?;
+            Some(Match { rule, params })
+        })
+    }
+}
+
+/// A request's rule and the path parameters it captured.
+#[derive(Debug)]
+pub struct Match<'a> {
+    rule: &'a Rule,
+    params: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl Match<'_> {
+    pub fn rule(&self) -> &str {
+        &self.rule.name
+    }
+
+    /// Whether the rule asks who the caller is, so the bearer token is
+    /// worth validating.
+    pub fn needs_user(&self) -> bool {
+        self.rule.role == Some(Role::User) || !self.rule.allow.is_empty()
+    }
+
+    /// The slug of the article to load before [`Match::decide`], when the
+    /// rule is about one.
+    pub fn article(&self) -> Option<&str> {
+        self.rule
+            .allow
+            .iter()
+            .any(|relation| relation.param() == ARTICLE_PARAM)
+            .then(|| self.param(ARTICLE_PARAM))
+            .flatten()
+    }
+
+    /// Applies the rule, logging the outcome. `article` is the one at
+    /// [`Match::article`], `None` when it doesn't exist or wasn't loaded
+    /// because the caller isn't signed in.
+    pub fn decide(&self, subject: &Subject, article: Option<&Authors>) -> Decision {
+        let (decision, granted_by) = self.evaluate(subject, article);
+        if decision == Decision::Allow {
+            tracing::debug!(
+                authz.rule = self.rule(),
+                authz.decision = decision.as_str(),
+                authz.granted_by = granted_by.map(Relation::as_str),
+                user_id = subject.user_id,
+                "Request authorized"
+            );
+        } else {
+            tracing::info!(
+                authz.rule = self.rule(),
+                authz.decision = decision.as_str(),
+                user_id = subject.user_id,
+                "Request denied"
+            );
+        }
+        decision
+    }
+
+    /// The decision and the relation that allowed it, if one did.
+    fn evaluate(
+        &self,
+        subject: &Subject,
+        article: Option<&Authors>,
+    ) -> (Decision, Option<Relation>) {
+        if let Some(role) = self.rule.role
+            && !subject.has(role)
+        {
+            return (Decision::Unauthenticated, None);
+        }
+        if self.rule.allow.is_empty() {
+            return (Decision::Allow, None);
+        }
+        let Some(user_id) = subject.user_id else {
+            return (Decision::Unauthenticated, None);
+        };
+
+        let mut missing = false;
+        for &relation in &self.rule.allow {
+            let holds = match relation {
+                Relation::Myself => {
+                    self.param(USER_PARAM)
+                        .and_then(|param| param.parse::<i32>().ok())
+                        == Some(user_id)
+                }
+                Relation::Owner | Relation::Author => match article {
+                    None => {
+                        missing = true;
+                        false
+                    }
+                    Some(authors) => {
+                        authors.owner == user_id
+                            || (relation == Relation::Author
+                                && authors.co_authors.contains(&user_id))
+                    }
+                },
+            };
+            if holds {
+                return (Decision::Allow, Some(relation));
+            }
+        }
+        if missing {
+            (Decision::NotFound, None)
+        } else {
+            (Decision::Forbidden, None)
+        }
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+        [[rule]]
+        name = "article.update"
+        route = "PUT /articles/{slug}"
+        allow = ["author"]
+
+        [[rule]]
+        name = "article.delete"
+        route = "DELETE /articles/{slug}"
+        allow = ["owner"]
+
+        [[rule]]
+        name = "article.remove_author"
+        route = "DELETE /articles/{slug}/authors/{user_id}"
+        allow = ["owner", "self"]
+
+        [[rule]]
+        name = "admin"
+        route = "* /admin/{*path}"
+        role = "admin"
+    "#;
+
+    const ALICE: Subject = Subject {
+        user_id: Some(1),
+        admin: false,
+    };
+    const BOB: Subject = Subject {
+        user_id: Some(2),
+        admin: false,
+    };
+
+    fn authors() -> Authors {
+        Authors {
+            owner: 1,
+            co_authors: vec![1, 3],
+        }
+    }
+
+    fn decide(method: &str, path: &str, subject: Subject, article: Option<Authors>) -> Decision {
+        let policy = Policy::from_toml(POLICY).unwrap();
+        policy
+            .find(method, path)
+            .unwrap()
+            .decide(&subject, article.as_ref())
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let policy = Policy::from_toml(POLICY).unwrap();
+
+        let matched = policy.find("PUT", "/articles/hello").unwrap();
+        assert_eq!(matched.rule(), "article.update");
+        assert_eq!(matched.article(), Some("hello"));
+        assert_eq!(
+            policy.find("DELETE", "/admin/flags/x").unwrap().article(),
+            None
+        );
+        assert!(matched.needs_user());
+        assert!(!policy.find("GET", "/admin/config").unwrap().needs_user());
+        assert!(policy.find("GET", "/articles/hello").is_none());
+        assert_eq!(
+            policy.find("PUT", "/articles/caf%C3%A9").unwrap().article(),
+            Some("café")
+        );
+    }
+
+    #[test]
+    fn test_ownership() {
+        let co_author = Subject {
+            user_id: Some(3),
+            ..Subject::default()
+        };
+
+        assert_eq!(
+            decide("PUT", "/articles/a", co_author, Some(authors())),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide("DELETE", "/articles/a", co_author, Some(authors())),
+            Decision::Forbidden
+        );
+        assert_eq!(
+            decide("DELETE", "/articles/a", ALICE, Some(authors())),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide("PUT", "/articles/a", BOB, Some(authors())),
+            Decision::Forbidden
+        );
+        assert_eq!(decide("PUT", "/articles/a", BOB, None), Decision::NotFound);
+        assert_eq!(
+            decide("PUT", "/articles/a", Subject::default(), None),
+            Decision::Unauthenticated
+        );
+    }
+
+    #[test]
+    fn test_self_or_owner() {
+        assert_eq!(
+            decide("DELETE", "/articles/a/authors/3", ALICE, Some(authors())),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide("DELETE", "/articles/a/authors/2", BOB, Some(authors())),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide("DELETE", "/articles/a/authors/3", BOB, Some(authors())),
+            Decision::Forbidden
+        );
+    }
+
+    #[test]
+    fn test_roles() {
+        let admin = Subject {
+            admin: true,
+            ..Subject::default()
+        };
+
+        assert_eq!(
+            decide("PUT", "/admin/flags/x", admin, None),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide("GET", "/admin/config", Subject::default(), None),
+            Decision::Unauthenticated
+        );
+        assert_eq!(
+            decide("GET", "/admin/config", ALICE, None),
+            Decision::Unauthenticated
+        );
+    }
+
+    #[test]
+    fn test_require_reports_every_missing_rule() {
+        let policy = Policy::from_toml(POLICY).unwrap();
+
+        assert!(policy.require(&["article.update", "admin"]).is_ok());
+        assert_eq!(
+            policy
+                .require(&["article.update", "article.transfer", "article.publish"])
+                .unwrap_err()
+                .errors,
+            [
+                "article.transfer: required rule is missing",
+                "article.publish: required rule is missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_policies_report_every_problem() {
+        let err = Policy::from_toml(
+            r#"
+            [[rule]]
+            name = "a"
+            route = "PUT /articles/{id}"
+            allow = ["owner"]
+
+            [[rule]]
+            name = "a"
+            route = "/articles"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.errors,
+            [
+                "a: `owner` needs a `{slug}` in the route",
+                "a: duplicate rule name",
+                "a: expected a method and a path, like `PUT /articles/{slug}`",
+            ]
+        );
+
+        assert!(
+            Policy::from_toml("[[rule]]\nname = \"a\"\nroute = \"GET /\"\nrole = \"root\"")
+                .is_err()
+        );
+    }
+}
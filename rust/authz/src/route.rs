@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+
+use percent_encoding::percent_decode_str;
+
+/// A rule's `route`, such as `PUT /articles/{slug}`, split into segments
+/// once when the policy loads.
+#[derive(Debug, Clone)]
+pub(crate) struct Route {
+    /// `None` for `*`, any method.
+    method: Option<String>,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    /// `{*name}`, the rest of the path, possibly nothing.
+    Rest,
+}
+
+impl Route {
+    pub(crate) fn parse(route: &str) -> Result<Self, String> {
+        let (method, path) = route
+            .split_once(' ')
+            .ok_or("expected a method and a path, like `PUT /articles/{slug}`")?;
+        let method = match method {
+            "*" => None,
+            method if method.bytes().all(|b| b.is_ascii_uppercase()) => Some(method.to_string()),
+            method => return Err(format!("`{method}` isn't an uppercase method or `*`")),
+        };
+        let path = path
+            .trim()
+            .strip_prefix('/')
+            .ok_or("the path must start with `/`")?;
+
+        let mut segments = Vec::new();
+        for segment in split(path) {
+            if segments.last() == Some(&Segment::Rest) {
+                return Err("`{*...}` must be the last segment".to_string());
+            }
+            segments.push(match segment.strip_prefix('{') {
+                Some(param) => {
+                    let name = param
+                        .strip_suffix('}')
+                        .ok_or_else(|| format!("unclosed `{{` in `{segment}`"))?;
+                    match name.strip_prefix('*') {
+                        Some(_) => Segment::Rest,
+                        None if name.is_empty() => return Err("empty `{}`".to_string()),
+                        None => Segment::Param(name.to_string()),
+                    }
+                }
+                None => Segment::Literal(segment.to_string()),
+            });
+        }
+        Ok(Self { method, segments })
+    }
+
+    pub(crate) fn has_param(&self, name: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Param(param) if param == name))
+    }
+
+    /// The path parameters, when `method` and `path` match. A trailing `/`
+    /// doesn't matter. `path` is as the request spelled it: each segment is
+    /// percent-decoded after splitting, as the frameworks' `Path` extractors
+    /// do, so a slug like `café` is the one the handler will look up.
+    pub(crate) fn matches<'r, 'p>(
+        &'r self,
+        method: &str,
+        path: &'p str,
+    ) -> Option<Vec<(&'r str, Cow<'p, str>)>> {
+        if self.method.as_deref().is_some_and(|m| m != method) {
+            return None;
+        }
+        let mut params = Vec::new();
+        let mut parts = split(path.trim_start_matches('/'))
+            .map(|part| percent_decode_str(part).decode_utf8_lossy());
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest => return Some(params),
+                Segment::Literal(literal) => {
+                    if parts.next()? != literal.as_str() {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => params.push((name.as_str(), parts.next()?)),
+            }
+        }
+        parts.next().is_none().then_some(params)
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_literals_and_params() {
+        let route = Route::parse("DELETE /articles/{slug}/authors/{user_id}").unwrap();
+
+        assert_eq!(
+            route.matches("DELETE", "/articles/hello/authors/7/"),
+            Some(vec![("slug", "hello".into()), ("user_id", "7".into())])
+        );
+        assert_eq!(route.matches("GET", "/articles/hello/authors/7"), None);
+        assert_eq!(route.matches("DELETE", "/articles/hello/authors"), None);
+        assert_eq!(route.matches("DELETE", "/articles/hello/authors/7/x"), None);
+        assert!(route.has_param("user_id"));
+        assert!(!route.has_param("id"));
+    }
+
+    #[test]
+    fn test_decodes_each_segment() {
+        let route = Route::parse("PUT /articles/{slug}").unwrap();
+
+        assert_eq!(
+            route.matches("PUT", "/articles/caf%C3%A9"),
+            Some(vec![("slug", "café".into())])
+        );
+        assert_eq!(
+            route.matches("PUT", "/%61rticles/a%2Fb"),
+            Some(vec![("slug", "a/b".into())])
+        );
+    }
+
+    #[test]
+    fn test_rest_and_any_method() {
+        let route = Route::parse("* /admin/{*path}").unwrap();
+
+        assert_eq!(route.matches("PUT", "/admin/flags/x"), Some(vec![]));
+        assert_eq!(route.matches("GET", "/admin"), Some(vec![]));
+        assert_eq!(route.matches("GET", "/administrators"), None);
+    }
+
+    #[test]
+    fn test_rejects_malformed_routes() {
+        for route in [
+            "/articles",
+            "put /articles",
+            "PUT articles",
+            "PUT /articles/{slug",
+            "PUT /articles/{}",
+            "PUT /{*rest}/articles",
+        ] {
+            assert!(Route::parse(route).is_err(), "{route}");
+        }
+    }
+}
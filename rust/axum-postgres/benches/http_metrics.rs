@@ -0,0 +1,138 @@
+//! Recording `http.requests.total` and `http.request.duration` for one
+//! response, with the attributes built from `String`s per request as they
+//! used to be and with `record_http_request`'s prebuilt ones, into an SDK
+//! meter provider. `cargo bench --bench http_metrics`; the allocations per
+//! request, counted while every core records a mix of responses, are
+//! printed before the timings.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::Once;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use opentelemetry::{KeyValue, global};
+use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+use rust_axum_postgres::telemetry::http::record_http_request;
+use rust_axum_postgres::telemetry::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL};
+
+/// Counts every allocation, so the bench can tell how many a request costs.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const LATENCY: Duration = Duration::from_millis(12);
+const REQUESTS_PER_THREAD: u64 = 50_000;
+
+/// Mostly reads, as the articles API serves.
+const RESPONSES: [(Method, StatusCode); 8] = [
+    (Method::GET, StatusCode::OK),
+    (Method::GET, StatusCode::OK),
+    (Method::GET, StatusCode::OK),
+    (Method::GET, StatusCode::NOT_MODIFIED),
+    (Method::GET, StatusCode::NOT_FOUND),
+    (Method::POST, StatusCode::CREATED),
+    (Method::PUT, StatusCode::OK),
+    (Method::DELETE, StatusCode::SERVICE_UNAVAILABLE),
+];
+
+/// What `HttpOnResponse` did before: six `String`s for two arrays.
+fn record_with_strings(method: &Method, status: StatusCode, latency: Duration) {
+    let status = status.as_u16();
+    let status_class = format!("{}xx", status / 100);
+    HTTP_REQUESTS_TOTAL.add(
+        1,
+        &[
+            KeyValue::new("http.method", method.to_string()),
+            KeyValue::new("http.status_code", status.to_string()),
+            KeyValue::new("http.status_class", status_class.clone()),
+        ],
+    );
+    HTTP_REQUEST_DURATION.record(
+        latency.as_secs_f64() * 1000.0,
+        &[
+            KeyValue::new("http.method", method.to_string()),
+            KeyValue::new("http.status_code", status.to_string()),
+            KeyValue::new("http.status_class", status_class),
+        ],
+    );
+}
+
+/// Installed before the instruments are first used, so they aggregate like
+/// they do in the api rather than into the no-op provider.
+fn install_meter_provider() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(InMemoryMetricExporter::default()).build())
+            .build();
+        global::set_meter_provider(provider);
+    });
+}
+
+/// Allocations per request with `record` called from every core at once,
+/// after a first pass that creates each series.
+fn allocations_per_request(record: fn(&Method, StatusCode, Duration)) -> f64 {
+    for (method, status) in &RESPONSES {
+        record(method, *status, LATENCY);
+    }
+    let threads = thread::available_parallelism().map_or(4, |n| n.get()) as u64;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for (method, status) in RESPONSES.iter().cycle().take(REQUESTS_PER_THREAD as usize)
+                {
+                    record(method, *status, LATENCY);
+                }
+            });
+        }
+    });
+    // Spawning the threads allocates too; it's a rounding error over this
+    // many requests.
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    allocations as f64 / (threads * REQUESTS_PER_THREAD) as f64
+}
+
+fn record(c: &mut Criterion) {
+    install_meter_provider();
+    println!(
+        "allocations per request: strings {:.2}, prebuilt {:.2}",
+        allocations_per_request(record_with_strings),
+        allocations_per_request(record_http_request)
+    );
+
+    let mut group = c.benchmark_group("record_http_request");
+    group.bench_function(BenchmarkId::from_parameter("strings"), |b| {
+        b.iter(|| record_with_strings(black_box(&Method::GET), black_box(StatusCode::OK), LATENCY))
+    });
+    group.bench_function(BenchmarkId::from_parameter("prebuilt"), |b| {
+        b.iter(|| record_http_request(black_box(&Method::GET), black_box(StatusCode::OK), LATENCY))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, record);
+criterion_main!(benches);
@@ -0,0 +1,129 @@
+//! The signature check `AuthService::validate_token` does per request,
+//! decoding and verifying the JWT, against answering from `JwtCache`. Both
+//! skip the session lookup, which the cache doesn't replace.
+//! `cargo bench --bench jwt_validation`; the cost per validation with every
+//! core validating at once, where the cache's shard locks would contend, is
+//! printed before the timings.
+
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use rust_axum_postgres::models::OrgId;
+use rust_axum_postgres::services::{JwtCache, Principal, VerifiedToken};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const SECRET: &[u8] = b"bench-secret-bench-secret-bench-secret";
+/// Distinct signed-in users hitting the instance.
+const TOKENS: usize = 1_000;
+const VALIDATIONS_PER_THREAD: usize = 200_000;
+
+/// The shape of `services::auth::Claims`.
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: i32,
+    org: OrgId,
+    jti: Uuid,
+    exp: i64,
+    iat: i64,
+}
+
+fn tokens() -> Vec<String> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    (0..TOKENS as i32)
+        .map(|sub| {
+            let claims = Claims {
+                sub,
+                org: OrgId(1),
+                jti: Uuid::new_v4(),
+                exp: now + 3600,
+                iat: now,
+            };
+            encode(
+                &Header::default(),
+                &claims,
+                &EncodingKey::from_secret(SECRET),
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+fn decoded(token: &str) -> VerifiedToken {
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(SECRET),
+        &Validation::default(),
+    )
+    .unwrap()
+    .claims;
+    VerifiedToken {
+        principal: Principal {
+            user_id: claims.sub,
+            org: claims.org,
+        },
+        jti: Some(claims.jti),
+        expires_at: claims.exp,
+    }
+}
+
+fn cached(cache: &JwtCache, token: &str) -> VerifiedToken {
+    cache.get(token, 0).unwrap_or_else(|| {
+        let verified = decoded(token);
+        cache.insert(token, 0, verified);
+        verified
+    })
+}
+
+/// Nanoseconds per validation with `validate` called from every core.
+fn contended_ns(tokens: &[String], validate: &(dyn Fn(&str) -> VerifiedToken + Sync)) -> f64 {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for offset in 0..threads {
+            scope.spawn(move || {
+                for token in tokens
+                    .iter()
+                    .cycle()
+                    .skip(offset * 7)
+                    .take(VALIDATIONS_PER_THREAD)
+                {
+                    black_box(validate(token));
+                }
+            });
+        }
+    });
+    let elapsed = started.elapsed().as_nanos() as f64;
+    // Wall time over all validations: what each costs the instance.
+    elapsed / (threads * VALIDATIONS_PER_THREAD) as f64
+}
+
+fn validate(c: &mut Criterion) {
+    let tokens = tokens();
+    let cache = JwtCache::new(TOKENS * 2);
+    for token in &tokens {
+        cached(&cache, token);
+    }
+    println!(
+        "ns per validation, all cores: decode {:.0}, cached {:.0}",
+        contended_ns(&tokens, &decoded),
+        contended_ns(&tokens, &|token| cached(&cache, token))
+    );
+
+    let token = &tokens[0];
+    let mut group = c.benchmark_group("validate_token");
+    group.bench_function(BenchmarkId::from_parameter("decode"), |b| {
+        b.iter(|| decoded(black_box(token)))
+    });
+    group.bench_function(BenchmarkId::from_parameter("cached"), |b| {
+        b.iter(|| cached(&cache, black_box(token)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, validate);
+criterion_main!(benches);
@@ -0,0 +1,95 @@
+//! JSON against MessagePack for a page of articles and a create body, the
+//! payloads a mobile client moves most. `cargo bench --bench serialization`;
+//! the encoded sizes are printed before the timings.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rust_axum_postgres::models::{
+    ArticleDto, ArticlesResponse, CreateArticleInput, ProfileResponse,
+};
+use time::OffsetDateTime;
+
+const PAGE_SIZE: i32 = 20;
+
+fn author(id: i32) -> ProfileResponse {
+    ProfileResponse {
+        id,
+        email: format!("user{id}@example.com"),
+        username: format!("user{id}"),
+        name: format!("User {id}"),
+        bio: "Writes about Rust and observability.".to_string(),
+        image: format!("https://cdn.example.com/avatars/{id}.png"),
+    }
+}
+
+fn page() -> ArticlesResponse {
+    let body = "Tracing async Rust services end to end. ".repeat(40);
+    let articles = (0..PAGE_SIZE)
+        .map(|id| ArticleDto {
+            id,
+            slug: format!("tracing-async-rust-{id}"),
+            title: format!("Tracing async Rust, part {id}"),
+            description: "Spans, metrics and logs from one instrumentation layer.".to_string(),
+            body_html: format!("<p>{body}</p>\n"),
+            body: body.clone(),
+            favorites_count: id * 3,
+            favorited: id % 2 == 0,
+            views_count: i64::from(id) * 250,
+            cover_image: String::new(),
+            reading_time_minutes: Some(2),
+            summary: Some("How the pieces fit together.".to_string()),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            updated_at: OffsetDateTime::UNIX_EPOCH,
+            author: author(id),
+            authors: vec![author(id), author(id + 1)],
+        })
+        .collect();
+    ArticlesResponse {
+        articles,
+        total: 1_000,
+    }
+}
+
+fn encode(c: &mut Criterion) {
+    let page = page();
+    let json = serde_json::to_vec(&page).unwrap();
+    let msgpack = rmp_serde::to_vec_named(&page).unwrap();
+    println!(
+        "page of {PAGE_SIZE}: json {} bytes, msgpack {} bytes",
+        json.len(),
+        msgpack.len()
+    );
+
+    let mut group = c.benchmark_group("encode_articles_page");
+    group.throughput(Throughput::Elements(PAGE_SIZE as u64));
+    group.bench_function(BenchmarkId::from_parameter("json"), |b| {
+        b.iter(|| serde_json::to_vec(black_box(&page)).unwrap())
+    });
+    group.bench_function(BenchmarkId::from_parameter("msgpack"), |b| {
+        b.iter(|| rmp_serde::to_vec_named(black_box(&page)).unwrap())
+    });
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let input = serde_json::json!({
+        "title": "Tracing async Rust",
+        "description": "Spans, metrics and logs from one instrumentation layer.",
+        "body": "Tracing async Rust services end to end. ".repeat(40),
+    });
+    let json = serde_json::to_vec(&input).unwrap();
+    let msgpack = rmp_serde::to_vec_named(&input).unwrap();
+
+    let mut group = c.benchmark_group("decode_create_article");
+    group.bench_function(BenchmarkId::from_parameter("json"), |b| {
+        b.iter(|| serde_json::from_slice::<CreateArticleInput>(black_box(&json)).unwrap())
+    });
+    group.bench_function(BenchmarkId::from_parameter("msgpack"), |b| {
+        b.iter(|| rmp_serde::from_slice::<CreateArticleInput>(black_box(&msgpack)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,2 @@
+-- Object key of the uploaded cover image (users.image already exists for avatars)
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS cover_image VARCHAR(500) NOT NULL DEFAULT '';
@@ -0,0 +1,11 @@
+-- Incremented in batches by the in-process view buffer, so it trails reads by
+-- up to ARTICLE_VIEWS_FLUSH_SECS
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS views_count BIGINT NOT NULL DEFAULT 0;
+
+-- A view isn't an edit: limit the updated_at trigger to the other columns so
+-- view flushes leave updated_at alone
+DROP TRIGGER IF EXISTS update_articles_updated_at ON articles;
+CREATE TRIGGER update_articles_updated_at
+    BEFORE UPDATE OF slug, title, description, body, author_id, favorites_count, cover_image
+    ON articles
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
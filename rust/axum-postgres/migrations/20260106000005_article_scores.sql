@@ -0,0 +1,10 @@
+-- Trending scores, recomputed from favorites, views and age by the worker
+-- every TRENDING_REFRESH_SECS. GET /api/articles/trending reads them instead
+-- of scoring every article per request.
+CREATE TABLE IF NOT EXISTS article_scores (
+    article_id INTEGER PRIMARY KEY REFERENCES articles(id) ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_article_scores_score ON article_scores(score DESC);
@@ -0,0 +1,53 @@
+-- Everyone who can edit an article. articles.author_id stays the owner, who
+-- is always listed here as well (kept in sync by the trigger below).
+CREATE TABLE IF NOT EXISTS article_authors (
+    article_id INTEGER NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (article_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_article_authors_user_id ON article_authors(user_id);
+
+INSERT INTO article_authors (article_id, user_id, added_at)
+SELECT id, author_id, created_at FROM articles
+ON CONFLICT DO NOTHING;
+
+CREATE OR REPLACE FUNCTION add_owner_to_article_authors()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO article_authors (article_id, user_id)
+    VALUES (NEW.id, NEW.author_id)
+    ON CONFLICT DO NOTHING;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'add_article_owner_as_author') THEN
+        CREATE TRIGGER add_article_owner_as_author
+            AFTER INSERT OR UPDATE OF author_id ON articles
+            FOR EACH ROW EXECUTE FUNCTION add_owner_to_article_authors();
+    END IF;
+END $$;
+
+-- An article's authors as JSON profiles, owner first, then co-authors in the
+-- order they were added
+CREATE OR REPLACE FUNCTION article_authors_json(article INTEGER)
+RETURNS JSON AS $$
+    SELECT COALESCE(
+        json_agg(
+            json_build_object(
+                'id', u.id, 'email', u.email, 'name', u.name,
+                'bio', COALESCE(u.bio, ''), 'image', COALESCE(u.image, '')
+            )
+            ORDER BY aa.user_id = a.author_id DESC, aa.added_at, u.id
+        ),
+        '[]'::json
+    )
+    FROM article_authors aa
+    JOIN articles a ON a.id = aa.article_id
+    JOIN users u ON u.id = aa.user_id
+    WHERE aa.article_id = article
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,41 @@
+-- Tenants. Existing rows move into the `default` organization, which is
+-- also where anonymous requests read from.
+CREATE TABLE IF NOT EXISTS organizations (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR(50) UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO organizations (id, slug, name) VALUES (1, 'default', 'Default')
+ON CONFLICT DO NOTHING;
+SELECT setval('organizations_id_seq', (SELECT MAX(id) FROM organizations));
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS org_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS org_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE favorites ADD COLUMN IF NOT EXISTS org_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+
+-- The defaults only backfill; an insert that forgets its tenant must fail.
+ALTER TABLE users ALTER COLUMN org_id DROP DEFAULT;
+ALTER TABLE articles ALTER COLUMN org_id DROP DEFAULT;
+ALTER TABLE favorites ALTER COLUMN org_id DROP DEFAULT;
+
+-- Slugs only need to be unique within a tenant. Emails stay globally unique
+-- so login can resolve the tenant from the user.
+ALTER TABLE articles DROP CONSTRAINT IF EXISTS articles_slug_key;
+ALTER TABLE articles ADD CONSTRAINT articles_org_id_slug_key UNIQUE (org_id, slug);
+
+-- Composite keys so an article's owner and a favorite's user and article
+-- always share the row's tenant.
+ALTER TABLE users ADD CONSTRAINT users_id_org_id_key UNIQUE (id, org_id);
+ALTER TABLE articles ADD CONSTRAINT articles_id_org_id_key UNIQUE (id, org_id);
+ALTER TABLE articles ADD CONSTRAINT articles_author_org_fkey
+    FOREIGN KEY (author_id, org_id) REFERENCES users(id, org_id) ON DELETE CASCADE;
+ALTER TABLE favorites ADD CONSTRAINT favorites_user_org_fkey
+    FOREIGN KEY (user_id, org_id) REFERENCES users(id, org_id) ON DELETE CASCADE;
+ALTER TABLE favorites ADD CONSTRAINT favorites_article_org_fkey
+    FOREIGN KEY (article_id, org_id) REFERENCES articles(id, org_id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_users_org_id ON users(org_id);
+CREATE INDEX IF NOT EXISTS idx_articles_org_id_created_at ON articles(org_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_favorites_org_id ON favorites(org_id);
@@ -0,0 +1,41 @@
+-- Row-level security for TENANCY_MODE=rls. Tenant queries switch to
+-- `app_tenant` inside their transaction and set `app.current_org`; the
+-- policies only apply to that role, so the table owner (TENANCY_MODE=app,
+-- cross-tenant queries and migrations) is unaffected.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'app_tenant') THEN
+        CREATE ROLE app_tenant NOLOGIN;
+    END IF;
+END
+$$;
+
+-- `SET ROLE app_tenant` needs membership unless the app connects as a
+-- superuser.
+GRANT app_tenant TO CURRENT_USER;
+
+GRANT USAGE ON SCHEMA public TO app_tenant;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO app_tenant;
+GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO app_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public
+    GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO app_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public
+    GRANT USAGE, SELECT ON SEQUENCES TO app_tenant;
+
+-- `current_setting` without `missing_ok` raises when the setting is absent,
+-- so a query that reaches the role without a tenant fails instead of
+-- matching nothing.
+ALTER TABLE users ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON users TO app_tenant
+    USING (org_id = current_setting('app.current_org')::int)
+    WITH CHECK (org_id = current_setting('app.current_org')::int);
+
+ALTER TABLE articles ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON articles TO app_tenant
+    USING (org_id = current_setting('app.current_org')::int)
+    WITH CHECK (org_id = current_setting('app.current_org')::int);
+
+ALTER TABLE favorites ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON favorites TO app_tenant
+    USING (org_id = current_setting('app.current_org')::int)
+    WITH CHECK (org_id = current_setting('app.current_org')::int);
@@ -0,0 +1,5 @@
+-- Trigram index for `GET /api/articles/suggest`. `<%` (word similarity)
+-- matches a prefix of any word in the title and can use this index.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_articles_title_trgm ON articles USING gin (title gin_trgm_ops);
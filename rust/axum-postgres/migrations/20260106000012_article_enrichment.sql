@@ -0,0 +1,5 @@
+-- Filled in by the worker's `article_enrichment` job after each create or
+-- edit, so both are NULL until it has run. Not in the updated_at trigger's
+-- column list: enrichment isn't an edit.
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS reading_time_minutes INTEGER;
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS summary TEXT;
@@ -0,0 +1,6 @@
+-- Keyset pagination for /api/v2/articles compares (created_at, id), so the
+-- tie-breaker needs to be in the index too. It covers everything the old
+-- (org_id, created_at) index did.
+CREATE INDEX IF NOT EXISTS idx_articles_org_id_created_at_id
+    ON articles(org_id, created_at DESC, id DESC);
+DROP INDEX IF EXISTS idx_articles_org_id_created_at;
@@ -0,0 +1,5 @@
+-- A producer that may send the same work twice (a webhook sender retrying a
+-- delivery) enqueues it under a key; the second insert finds the first job.
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS idempotency_key TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_idempotency_key
+    ON jobs(idempotency_key) WHERE idempotency_key IS NOT NULL;
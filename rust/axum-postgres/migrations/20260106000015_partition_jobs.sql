@@ -0,0 +1,64 @@
+-- jobs only grows, so it is range-partitioned by created_at month: the
+-- worker's lookups stay within one month's indexes and an old month can be
+-- dropped whole. The worker creates upcoming months ahead of time and moves
+-- rows that landed in jobs_default into their own month
+-- (src/jobs/partitions.rs).
+ALTER TABLE jobs RENAME TO jobs_unpartitioned;
+DROP TRIGGER IF EXISTS update_jobs_updated_at ON jobs_unpartitioned;
+ALTER TABLE jobs_unpartitioned DROP CONSTRAINT jobs_pkey;
+DROP INDEX IF EXISTS idx_jobs_status_scheduled;
+DROP INDEX IF EXISTS idx_jobs_kind;
+DROP INDEX IF EXISTS idx_jobs_idempotency_key;
+
+CREATE TABLE jobs (LIKE jobs_unpartitioned INCLUDING DEFAULTS INCLUDING CONSTRAINTS)
+    PARTITION BY RANGE (created_at);
+-- A primary key on a partitioned table has to include the partition key.
+ALTER TABLE jobs ADD PRIMARY KEY (id, created_at);
+CREATE INDEX idx_jobs_status_scheduled ON jobs(status, scheduled_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_jobs_kind ON jobs(kind);
+CREATE TABLE jobs_default PARTITION OF jobs DEFAULT;
+
+-- For the same reason a unique index can't cover idempotency keys across
+-- months, so they move to a table of their own.
+CREATE TABLE job_idempotency_keys (
+    idempotency_key TEXT PRIMARY KEY,
+    job_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Every month with jobs, through three months from now (MONTHS_AHEAD).
+DO $$
+DECLARE
+    month DATE;
+BEGIN
+    FOR month IN
+        SELECT generate_series(
+            date_trunc('month', COALESCE(
+                (SELECT MIN(created_at) FROM jobs_unpartitioned),
+                CURRENT_TIMESTAMP
+            ) AT TIME ZONE 'UTC'),
+            date_trunc('month', CURRENT_TIMESTAMP AT TIME ZONE 'UTC') + INTERVAL '3 months',
+            INTERVAL '1 month'
+        )::date
+    LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF jobs FOR VALUES FROM (%L) TO (%L)',
+            to_char(month, '"jobs_p"YYYY_MM'),
+            month::text || ' 00:00:00+00',
+            (month + INTERVAL '1 month')::date::text || ' 00:00:00+00'
+        );
+    END LOOP;
+END $$;
+
+INSERT INTO jobs SELECT * FROM jobs_unpartitioned;
+INSERT INTO job_idempotency_keys (idempotency_key, job_id, created_at)
+SELECT idempotency_key, id, created_at FROM jobs_unpartitioned
+WHERE idempotency_key IS NOT NULL;
+
+ALTER SEQUENCE jobs_id_seq OWNED BY jobs.id;
+DROP TABLE jobs_unpartitioned;
+
+CREATE TRIGGER update_jobs_updated_at
+    BEFORE UPDATE ON jobs
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
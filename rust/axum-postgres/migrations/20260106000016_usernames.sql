@@ -0,0 +1,51 @@
+-- Handles, for profile URLs and the author filter: display names change and
+-- needn't be unique. Lowercase letters, digits, `-` and `_`, 3 to 30
+-- characters, starting with a letter or digit. Unique within a tenant, like
+-- article slugs.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS username VARCHAR(30);
+
+-- Backfill from the display name, slugified with `-` and cut to 20
+-- characters. A user whose slug is taken in their organization, or shorter
+-- than 3 characters, gets `_<id>` appended: slugs never contain `_`, so those
+-- can't collide.
+WITH slugs AS (
+    SELECT id, org_id,
+           rtrim(left(trim(BOTH '-' FROM regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g')), 20), '-') AS slug
+    FROM users
+    WHERE username IS NULL
+),
+ranked AS (
+    SELECT id, slug, row_number() OVER (PARTITION BY org_id, slug ORDER BY id) AS rank
+    FROM slugs
+)
+UPDATE users u
+SET username = CASE
+    WHEN r.slug = '' THEN 'user_' || u.id
+    WHEN r.rank > 1 OR length(r.slug) < 3 THEN r.slug || '_' || u.id
+    ELSE r.slug
+END
+FROM ranked r
+WHERE u.id = r.id;
+
+ALTER TABLE users ALTER COLUMN username SET NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_org_id_username_key UNIQUE (org_id, username);
+ALTER TABLE users ADD CONSTRAINT users_username_check
+    CHECK (username ~ '^[a-z0-9][a-z0-9_-]{2,29}$');
+
+CREATE OR REPLACE FUNCTION article_authors_json(article INTEGER)
+RETURNS JSON AS $$
+    SELECT COALESCE(
+        json_agg(
+            json_build_object(
+                'id', u.id, 'email', u.email, 'username', u.username, 'name', u.name,
+                'bio', COALESCE(u.bio, ''), 'image', COALESCE(u.image, '')
+            )
+            ORDER BY aa.user_id = a.author_id DESC, aa.added_at, u.id
+        ),
+        '[]'::json
+    )
+    FROM article_authors aa
+    JOIN articles a ON a.id = aa.article_id
+    JOIN users u ON u.id = aa.user_id
+    WHERE aa.article_id = article
+$$ LANGUAGE sql STABLE;
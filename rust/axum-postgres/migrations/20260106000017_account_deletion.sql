@@ -0,0 +1,3 @@
+-- Deleted accounts. The row stays, anonymized, so articles kept under the
+-- `anonymize` policy still have an author; lookups skip it.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
@@ -0,0 +1,26 @@
+-- TOTP two-factor authentication, one row per user. `enabled_at` stays NULL
+-- between setup and the first confirmed code. The secret is stored as is,
+-- since checking a code needs it. `last_step` is the time step of the last
+-- accepted code, so a code can't be used twice; `failed_attempts` and
+-- `last_attempt_at` drive the lockout.
+CREATE TABLE IF NOT EXISTS user_two_factor (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    enabled_at TIMESTAMPTZ,
+    last_step BIGINT,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_attempt_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Single-use codes for a lost authenticator. Only the SHA-256 of a code is
+-- stored, as with api_tokens.
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user_id ON recovery_codes(user_id);
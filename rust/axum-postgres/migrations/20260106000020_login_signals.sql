@@ -0,0 +1,31 @@
+-- Devices each user has signed in from, told apart by user agent, for the
+-- new device signal. The address and location are those of the device's
+-- last sign-in; the location is NULL when GEOIP_CSV_PATH doesn't cover the
+-- address.
+CREATE TABLE IF NOT EXISTS user_devices (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT NOT NULL,
+    last_ip TEXT,
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, user_agent)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_devices_user_id_last_seen_at
+    ON user_devices(user_id, last_seen_at DESC);
+
+-- Wrong passwords for existing accounts. Rows older than
+-- SECURITY_FAILED_LOGIN_WINDOW_SECS are deleted as the user's next failure
+-- is recorded.
+CREATE TABLE IF NOT EXISTS login_failures (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_failures_user_id_created_at
+    ON login_failures(user_id, created_at);
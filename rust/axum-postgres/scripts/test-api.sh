@@ -77,7 +77,8 @@ test_endpoint "GET" "/api/health" "200" "" "" "Health check"
 # Register User
 TIMESTAMP=$(date +%s)
 USER_EMAIL="test${TIMESTAMP}@example.com"
-USER_DATA="{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"name\":\"Test User\"}"
+USERNAME="test-${TIMESTAMP}"
+USER_DATA="{\"email\":\"$USER_EMAIL\",\"password\":\"password123\",\"username\":\"$USERNAME\",\"name\":\"Test User\"}"
 
 log_info "Registering user: $USER_EMAIL"
 REGISTER_RESPONSE=$(curl -s -X POST "$BASE_URL/api/register" \
@@ -85,6 +86,7 @@ REGISTER_RESPONSE=$(curl -s -X POST "$BASE_URL/api/register" \
     -d "$USER_DATA")
 
 TOKEN=$(echo "$REGISTER_RESPONSE" | grep -o '"token":"[^"]*"' | cut -d'"' -f4)
+USER_ID=$(echo "$REGISTER_RESPONSE" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
 
 if [ -n "$TOKEN" ]; then
     log_pass "User registration"
@@ -117,6 +119,71 @@ test_endpoint "GET" "/api/user" "200" "" "$TOKEN" "Get user profile (authenticat
 # Get User Profile - Unauthorized
 test_endpoint "GET" "/api/user" "401" "" "" "Get user profile (unauthorized)"
 
+# Usernames
+test_endpoint "POST" "/api/register" "409" "{\"email\":\"other-$USER_EMAIL\",\"password\":\"password123\",\"username\":\"$USERNAME\",\"name\":\"Other\"}" "" "Register with a taken username"
+test_endpoint "POST" "/api/register" "400" "{\"email\":\"bad-$USER_EMAIL\",\"password\":\"password123\",\"username\":\"not a handle\",\"name\":\"Bad\"}" "" "Register with an invalid username"
+test_endpoint "GET" "/api/profiles/$USERNAME" "200" "" "" "Get public profile"
+test_endpoint "GET" "/api/profiles/no-such-user-$TIMESTAMP" "404" "" "" "Get public profile (unknown username)"
+
+# Two-factor authentication: enrolling only starts until a code confirms it,
+# so the logins below still get a token straight away
+log_info "Starting two-factor setup"
+SETUP_RESPONSE=$(curl -s -X POST "$BASE_URL/api/user/2fa/setup" \
+    -H "Authorization: Bearer $TOKEN")
+
+if echo "$SETUP_RESPONSE" | grep -q '"otpauth_uri":"otpauth://totp/'; then
+    log_pass "Two-factor setup returns an otpauth URI"
+else
+    log_fail "Two-factor setup - no otpauth URI"
+    echo "$SETUP_RESPONSE"
+fi
+test_endpoint "POST" "/api/user/2fa/verify" "400" '{"code":"000000x"}' "$TOKEN" "Confirm two-factor setup (invalid code)"
+test_endpoint "POST" "/api/user/2fa/setup" "401" "" "" "Two-factor setup (unauthorized)"
+test_endpoint "POST" "/api/login/2fa" "401" '{"challenge_token":"not-a-challenge","code":"123456"}' "" "Two-factor login (invalid challenge)"
+test_endpoint "POST" "/api/login/2fa" "401" "{\"challenge_token\":\"$TOKEN\",\"code\":\"123456\"}" "" "Two-factor login (access token as challenge)"
+
+# Personal Access Tokens
+log_info "Creating personal access token"
+PAT_RESPONSE=$(curl -s -X POST "$BASE_URL/api/user/tokens" \
+    -H "Content-Type: application/json" \
+    -H "Authorization: Bearer $TOKEN" \
+    -d '{"name":"test-api","scopes":["read"]}')
+
+PAT=$(echo "$PAT_RESPONSE" | grep -o '"token":"pat_[^"]*"' | cut -d'"' -f4)
+PAT_ID=$(echo "$PAT_RESPONSE" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+
+if [ -n "$PAT" ]; then
+    log_pass "Create personal access token"
+    test_endpoint "GET" "/api/user" "200" "" "$PAT" "Get user profile (read token)"
+    test_endpoint "POST" "/api/articles" "403" '{"title":"t","description":"d","body":"b"}' "$PAT" "Create article (read token)"
+    test_endpoint "POST" "/api/user/tokens" "403" '{"name":"nested"}' "$PAT" "Create token with a token"
+    test_endpoint "GET" "/api/user/tokens" "200" "" "$TOKEN" "List personal access tokens"
+    test_endpoint "DELETE" "/api/user/tokens/$PAT_ID" "204" "" "$TOKEN" "Revoke personal access token"
+    test_endpoint "GET" "/api/user" "401" "" "$PAT" "Get user profile (revoked token)"
+else
+    log_fail "Create personal access token - no token received"
+    echo "$PAT_RESPONSE"
+fi
+echo ""
+
+# Sessions: the login above started a second one, which is signed out here
+log_info "Listing sessions"
+SESSIONS_RESPONSE=$(curl -s "$BASE_URL/api/user/sessions" \
+    -H "Authorization: Bearer $LOGIN_TOKEN")
+LOGIN_SESSION_ID=$(echo "$SESSIONS_RESPONSE" | grep -o '{"id":[0-9]*,[^}]*"current":true' | grep -o '^{"id":[0-9]*' | cut -d: -f2)
+
+if [ -n "$LOGIN_SESSION_ID" ]; then
+    log_pass "List sessions marks the current one"
+    test_endpoint "DELETE" "/api/user/sessions/$LOGIN_SESSION_ID" "204" "" "$TOKEN" "Revoke a session"
+    test_endpoint "GET" "/api/user" "401" "" "$LOGIN_TOKEN" "Get user profile (revoked session)"
+    test_endpoint "DELETE" "/api/user/sessions/$LOGIN_SESSION_ID" "404" "" "$TOKEN" "Revoke a session twice"
+else
+    log_fail "List sessions - no current session"
+    echo "$SESSIONS_RESPONSE"
+fi
+test_endpoint "GET" "/api/user/sessions" "401" "" "" "List sessions (unauthorized)"
+echo ""
+
 # Create Article
 ARTICLE_DATA="{\"title\":\"Test Article $TIMESTAMP\",\"description\":\"Test description\",\"body\":\"This is the article body.\"}"
 log_info "Creating article"
@@ -130,18 +197,123 @@ ARTICLE_SLUG=$(echo "$CREATE_RESPONSE" | grep -o '"slug":"[^"]*"' | cut -d'"' -f
 if [ -n "$ARTICLE_SLUG" ]; then
     log_pass "Create article"
     echo "Article slug: $ARTICLE_SLUG"
+    if echo "$CREATE_RESPONSE" | grep -qF '"body_html":"<p>This is the article body.</p>'; then
+        log_pass "Article body rendered as HTML"
+    else
+        log_fail "Article body_html missing"
+    fi
 else
     log_fail "Create article - no slug received"
     echo "$CREATE_RESPONSE"
 fi
+test_endpoint "POST" "/api/articles" "400" '{"title":"  ","body":"b"}' "$TOKEN" "Create article (blank title)"
 echo ""
 
 # List Articles
 test_endpoint "GET" "/api/articles" "200" "" "" "List articles (public)"
+test_endpoint "GET" "/api/articles/trending" "200" "" "" "Trending articles (public)"
+test_endpoint "GET" "/api/articles?author=$USERNAME" "200" "" "" "List articles by author"
+
+# API versions: /api/v1 is /api; /api/v2 is camelCase with cursors
+test_endpoint "GET" "/api/v1/articles?limit=1" "200" "" "" "List articles (v1)"
+log_info "Testing: List articles (v2, two pages)"
+V2_PAGE=$(curl -s "$BASE_URL/api/v2/articles?limit=1")
+V2_CURSOR=$(echo "$V2_PAGE" | grep -o '"nextCursor":"[^"]*"' | cut -d'"' -f4)
+if [ -n "$V2_CURSOR" ] && echo "$V2_PAGE" | grep -q '"bodyHtml"'; then
+    V2_NEXT=$(curl -s -o /dev/null -w '%{http_code}' "$BASE_URL/api/v2/articles?limit=1&cursor=$V2_CURSOR")
+    if [ "$V2_NEXT" = "200" ]; then
+        log_pass "List articles (v2) with nextCursor"
+    else
+        log_fail "List articles (v2) next page (got: $V2_NEXT)"
+    fi
+else
+    log_fail "List articles (v2) - no nextCursor or camelCase fields"
+    echo "$V2_PAGE"
+fi
+log_info "Testing: Structured error (v2)"
+V2_ERROR=$(curl -s "$BASE_URL/api/v2/articles/no-such-article")
+if echo "$V2_ERROR" | grep -q '"error":{"code":"NotFound"'; then
+    log_pass "Structured error (v2)"
+else
+    log_fail "Structured error (v2) - unexpected body"
+    echo "$V2_ERROR"
+fi
+echo ""
+
+# Request deadlines
+for case in "2S 200" "0m 504" "soon 400"; do
+    set -- $case
+    log_info "Testing: List articles with x-request-deadline: $1"
+    DEADLINE_STATUS=$(curl -s -o /dev/null -w '%{http_code}' -H "x-request-deadline: $1" "$BASE_URL/api/articles")
+    if [ "$DEADLINE_STATUS" = "$2" ]; then
+        log_pass "Request deadline $1 (status: $DEADLINE_STATUS)"
+    else
+        log_fail "Request deadline $1 (expected: $2, got: $DEADLINE_STATUS)"
+    fi
+done
+echo ""
+
+# Suggestions
+if [ -n "$ARTICLE_SLUG" ]; then
+    log_info "Testing: Suggest articles"
+    SUGGEST_RESPONSE=$(curl -s "$BASE_URL/api/articles/suggest?q=article%20$TIMESTAMP")
+    if echo "$SUGGEST_RESPONSE" | grep -q "\"slug\":\"$ARTICLE_SLUG\""; then
+        log_pass "Suggest articles by title"
+    else
+        log_fail "Suggest articles - $ARTICLE_SLUG not suggested"
+        echo "$SUGGEST_RESPONSE"
+    fi
+    echo ""
+fi
+
+# Export Articles
+test_endpoint "GET" "/api/articles/export?format=ndjson" "200" "" "$TOKEN" "Export articles (ndjson)"
+test_endpoint "GET" "/api/articles/export?format=csv" "200" "" "$TOKEN" "Export articles (csv)"
+test_endpoint "GET" "/api/articles/export" "401" "" "" "Export articles (unauthorized)"
+
+# Image Uploads
+IMAGE_FILE=$(mktemp)
+printf '\x89PNG\r\n\x1a\n\0\0\0\rIHDR' > "$IMAGE_FILE"
+
+test_upload() {
+    local path=$1
+    local expected_status=$2
+    local content_type=$3
+    local description=$4
+
+    log_info "Testing: $description"
+    local status
+    status=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL$path" \
+        -H "Authorization: Bearer $TOKEN" \
+        -F "image=@$IMAGE_FILE;type=$content_type")
+
+    if [ "$status" = "$expected_status" ]; then
+        log_pass "$description (HTTP $status)"
+    else
+        log_fail "$description - expected $expected_status, got $status"
+    fi
+    echo ""
+}
+
+test_upload "/api/user/image" "201" "image/png" "Upload avatar"
+test_upload "/api/user/image" "415" "image/gif" "Upload avatar (content type mismatch)"
+if [ -n "$ARTICLE_SLUG" ]; then
+    test_upload "/api/articles/$ARTICLE_SLUG/cover" "201" "image/png" "Upload article cover"
+fi
+rm -f "$IMAGE_FILE"
 
 # Get Single Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "GET" "/api/articles/$ARTICLE_SLUG" "200" "" "" "Get single article"
+
+    log_info "Testing: View count"
+    VIEWS=$(curl -s "$BASE_URL/api/articles/$ARTICLE_SLUG" | grep -o '"views_count":[0-9]*' | cut -d: -f2)
+    if [ "$VIEWS" = "2" ]; then
+        log_pass "View count includes buffered views ($VIEWS)"
+    else
+        log_fail "View count - expected 2, got ${VIEWS:-none}"
+    fi
+    echo ""
 fi
 
 # Update Article
@@ -166,23 +338,221 @@ if [ -n "$ARTICLE_SLUG" ]; then
     echo ""
 fi
 
+# Co-authors and Transfer (the test user is the only author)
+if [ -n "$ARTICLE_SLUG" ]; then
+    test_endpoint "POST" "/api/articles/$ARTICLE_SLUG/authors" "404" '{"user_id":2147483647}' "$TOKEN" "Add co-author (unknown user)"
+    test_endpoint "POST" "/api/articles/$ARTICLE_SLUG/transfer" "401" '{"user_id":1}' "" "Transfer article (unauthorized)"
+    test_endpoint "PUT" "/api/v1/articles/$ARTICLE_SLUG" "401" '{"title":"t"}' "" "Update article under /api/v1 (unauthorized)"
+fi
+test_endpoint "PUT" "/api/articles/no-such-article" "404" '{"title":"t"}' "$TOKEN" "Update article (not found)"
+
 # Favorite Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "POST" "/api/articles/$ARTICLE_SLUG/favorite" "200" "" "$TOKEN" "Favorite article"
+
+    ARTICLE_ID=$(echo "$CREATE_RESPONSE" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+    log_info "Testing: Bulk favorite status"
+    FAVORITED_RESPONSE=$(curl -s -X POST "$BASE_URL/api/articles/favorited" \
+        -H "Content-Type: application/json" \
+        -H "Authorization: Bearer $TOKEN" \
+        -d "{\"article_ids\":[$ARTICLE_ID,2147483647]}")
+    if echo "$FAVORITED_RESPONSE" | grep -q "\"$ARTICLE_ID\":true" && echo "$FAVORITED_RESPONSE" | grep -q '"2147483647":false'; then
+        log_pass "Bulk favorite status"
+    else
+        log_fail "Bulk favorite status"
+        echo "$FAVORITED_RESPONSE"
+    fi
+    echo ""
+    TOO_MANY_IDS=$(seq -s, 1 101)
+    test_endpoint "POST" "/api/articles/favorited" "400" "{\"article_ids\":[$TOO_MANY_IDS]}" "$TOKEN" "Bulk favorite status (too many IDs)"
+    test_endpoint "POST" "/api/articles/favorited" "401" '{"article_ids":[1]}' "" "Bulk favorite status (unauthorized)"
+fi
+
+# Own articles and favorites
+if [ -n "$ARTICLE_SLUG" ]; then
+    test_endpoint "GET" "/api/user/articles" "200" "" "$TOKEN" "List own articles"
+    test_endpoint "GET" "/api/user/favorites?limit=5" "200" "" "$TOKEN" "List favorited articles"
 fi
+test_endpoint "GET" "/api/user/favorites" "401" "" "" "List favorited articles (unauthorized)"
 
 # Unfavorite Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "DELETE" "/api/articles/$ARTICLE_SLUG/favorite" "200" "" "$TOKEN" "Unfavorite article"
 fi
 
+# Economic Context (needs REPORT_SERVICE_URL, so only the auth check is deterministic)
+if [ -n "$ARTICLE_SLUG" ]; then
+    test_endpoint "GET" "/api/articles/$ARTICLE_SLUG/economic-context" "401" "" "" "Economic context (unauthorized)"
+fi
+
 # Delete Article
 if [ -n "$ARTICLE_SLUG" ]; then
     test_endpoint "DELETE" "/api/articles/$ARTICLE_SLUG" "204" "" "$TOKEN" "Delete article (owner)"
 fi
 
+# Admin: Feature Flags
+ADMIN_TOKEN="${ADMIN_TOKEN:-change-me-admin-token}"
+
+log_info "Testing: List feature flags (admin)"
+FLAGS_STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/api/admin/flags" \
+    -H "X-Admin-Token: $ADMIN_TOKEN")
+if [ "$FLAGS_STATUS" = "200" ]; then
+    log_pass "List feature flags (status: $FLAGS_STATUS)"
+else
+    log_fail "List feature flags - expected 200, got $FLAGS_STATUS"
+fi
+echo ""
+
+log_info "Testing: Toggle feature flag (admin)"
+TOGGLE_STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X PUT "$BASE_URL/api/admin/flags/new_feed" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" \
+    -H "Content-Type: application/json" \
+    -d '{"enabled":true}')
+if [ "$TOGGLE_STATUS" = "200" ]; then
+    log_pass "Toggle feature flag (status: $TOGGLE_STATUS)"
+else
+    log_fail "Toggle feature flag - expected 200, got $TOGGLE_STATUS"
+fi
+echo ""
+
+test_endpoint "GET" "/api/admin/flags" "401" "" "" "List feature flags (no admin token)"
+
+log_info "Testing: Update telemetry settings (admin)"
+TELEMETRY_BODY=$(curl -s -X PUT "$BASE_URL/api/admin/telemetry" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" \
+    -H "Content-Type: application/json" \
+    -d '{"log_filter":"info,sqlx=warn,tower_http=debug","sample_ratio":1.0}')
+if echo "$TELEMETRY_BODY" | grep -q '"sample_ratio":1.0'; then
+    log_pass "Update telemetry settings"
+else
+    log_fail "Update telemetry settings - got $TELEMETRY_BODY"
+fi
+echo ""
+
+test_endpoint "PUT" "/api/admin/telemetry" "401" '{"sample_ratio":0.5}' "" "Update telemetry (no admin token)"
+
+log_info "Testing: Effective config (admin)"
+CONFIG_BODY=$(curl -s "$BASE_URL/api/admin/config" -H "X-Admin-Token: $ADMIN_TOKEN")
+if echo "$CONFIG_BODY" | grep -q '"JWT_SECRET":{"value":"\[REDACTED\]"'; then
+    log_pass "Effective config redacts JWT_SECRET"
+else
+    log_fail "Effective config - expected redacted JWT_SECRET, got $CONFIG_BODY"
+fi
+echo ""
+
+log_info "Testing: Queue an email (admin)"
+EMAIL_BODY=$(curl -s -X POST "$BASE_URL/api/admin/emails" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" -H "Content-Type: application/json" \
+    -d '{"to":"ada@example.com","template":"verification","name":"Ada","url":"https://example.com/verify"}')
+if echo "$EMAIL_BODY" | grep -q '"job_id":'; then
+    log_pass "Email queued as a job"
+else
+    log_fail "Queue email - expected a job_id, got $EMAIL_BODY"
+fi
+echo ""
+
+test_endpoint "POST" "/api/admin/emails" "401" '{"to":"ada@example.com","template":"verification","name":"Ada","url":"https://example.com/verify"}' "" "Queue email (no admin token)"
+
+log_info "Testing: Audit log (admin)"
+AUDIT_BODY=$(curl -s "$BASE_URL/api/admin/audit?action=article.create&actor_id=$USER_ID" \
+    -H "X-Admin-Token: $ADMIN_TOKEN")
+if echo "$AUDIT_BODY" | grep -q '"action":"article.create"'; then
+    log_pass "Audit log records article creation"
+else
+    log_fail "Audit log - expected an article.create entry, got $AUDIT_BODY"
+fi
+echo ""
+
+test_endpoint "GET" "/api/admin/audit" "401" "" "" "Audit log (no admin token)"
+
+# Inbound webhooks: signed deliveries are queued once, replays and forgeries are not
+WEBHOOK_SECRET="${WEBHOOK_SECRET:-change-me-webhook-secret}"
+WEBHOOK_ID="msg_$(date +%s%N)"
+WEBHOOK_BODY='{"type":"invoice.paid","data":{"id":"inv_1"}}'
+send_webhook() {
+    local timestamp=$1
+    local secret=$2
+    local signature
+    signature=$(printf '%s' "$WEBHOOK_ID.$timestamp.$WEBHOOK_BODY" \
+        | openssl dgst -sha256 -hmac "$secret" -binary | base64)
+    curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/hooks/provider" \
+        -H "Content-Type: application/json" \
+        -H "webhook-id: $WEBHOOK_ID" \
+        -H "webhook-timestamp: $timestamp" \
+        -H "webhook-signature: v1,$signature" \
+        -d "$WEBHOOK_BODY"
+}
+
+for check in "$(date +%s) $WEBHOOK_SECRET 202 delivery accepted" \
+    "$(date +%s) $WEBHOOK_SECRET 200 replay acknowledged as duplicate" \
+    "$(date +%s) wrong-secret 401 forged signature rejected" \
+    "$(($(date +%s) - 3600)) $WEBHOOK_SECRET 401 stale timestamp rejected"; do
+    read -r timestamp secret expected description <<< "$check"
+    log_info "Testing: Webhook $description"
+    WEBHOOK_STATUS=$(send_webhook "$timestamp" "$secret")
+    if [ "$WEBHOOK_STATUS" = "$expected" ]; then
+        log_pass "Webhook $description (status: $WEBHOOK_STATUS)"
+    else
+        log_fail "Webhook $description - expected $expected, got $WEBHOOK_STATUS"
+    fi
+    echo ""
+done
+
+# Tenancy: an article created in another organization is invisible here
+ORG_SLUG="org-$(date +%s)"
+log_info "Testing: Create organization (admin)"
+ORG_STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/admin/orgs" \
+    -H "X-Admin-Token: $ADMIN_TOKEN" \
+    -H "Content-Type: application/json" \
+    -d "{\"slug\":\"$ORG_SLUG\",\"name\":\"Test Org\"}")
+if [ "$ORG_STATUS" = "201" ]; then
+    log_pass "Create organization (status: $ORG_STATUS)"
+else
+    log_fail "Create organization - expected 201, got $ORG_STATUS"
+fi
+echo ""
+
+test_endpoint "POST" "/api/admin/orgs" "401" '{"slug":"nope","name":"Nope"}' "" "Create organization (no admin token)"
+test_endpoint "POST" "/api/register" "404" '{"email":"nobody@example.com","password":"password123","username":"nobody","name":"Nobody","org":"no-such-org"}' "" "Register in unknown organization"
+
+ORG_TOKEN=$(curl -s -X POST "$BASE_URL/api/register" \
+    -H "Content-Type: application/json" \
+    -d "{\"email\":\"tenant_$USER_EMAIL\",\"password\":\"password123\",\"username\":\"$USERNAME\",\"name\":\"Tenant User\",\"org\":\"$ORG_SLUG\"}" \
+    | grep -o '"token":"[^"]*"' | cut -d'"' -f4)
+ORG_ARTICLE_SLUG=$(curl -s -X POST "$BASE_URL/api/articles" \
+    -H "Content-Type: application/json" \
+    -H "Authorization: Bearer $ORG_TOKEN" \
+    -d '{"title":"Tenant Only Article","body":"Hidden from other organizations"}' \
+    | grep -o '"slug":"[^"]*"' | cut -d'"' -f4)
+
+test_endpoint "GET" "/api/articles/$ORG_ARTICLE_SLUG" "200" "" "$ORG_TOKEN" "Get article (same organization)"
+test_endpoint "GET" "/api/articles/$ORG_ARTICLE_SLUG" "404" "" "$TOKEN" "Get article (other organization)"
+# Usernames are unique per organization: the tenant user took the same one
+TENANT_PROFILE=$(curl -s "$BASE_URL/api/profiles/$USERNAME" -H "Authorization: Bearer $ORG_TOKEN")
+if echo "$TENANT_PROFILE" | grep -q '"name":"Tenant User"'; then
+    log_pass "Same username in another organization"
+else
+    log_fail "Same username in another organization (got: $TENANT_PROFILE)"
+fi
+
+# Account export and deletion
+log_info "Testing: Export account data"
+ARCHIVE=$(curl -s "$BASE_URL/api/user/export" -H "Authorization: Bearer $TOKEN")
+if echo "$ARCHIVE" | grep -q "\"username\":\"$USERNAME\"" && echo "$ARCHIVE" | grep -q '"audit_log":\['; then
+    log_pass "Export account data"
+else
+    log_fail "Export account data (got: ${ARCHIVE:0:200})"
+fi
+echo ""
+test_endpoint "GET" "/api/user/export" "401" "" "" "Export account data (unauthorized)"
+test_endpoint "DELETE" "/api/user" "202" "" "$ORG_TOKEN" "Delete account"
+test_endpoint "GET" "/api/user" "401" "" "$ORG_TOKEN" "Get deleted account"
+test_endpoint "DELETE" "/api/user" "401" "" "" "Delete account (unauthorized)"
+
 # Logout
 test_endpoint "POST" "/api/logout" "200" "" "$TOKEN" "Logout"
+test_endpoint "GET" "/api/user" "401" "" "$TOKEN" "Get user profile (logged out)"
+test_endpoint "POST" "/api/logout" "200" "" "" "Logout (no token)"
 
 # Summary
 echo "========================================"
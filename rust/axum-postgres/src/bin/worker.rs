@@ -1,35 +1,29 @@
-use std::collections::HashMap;
 use std::time::Duration;
 
-use opentelemetry::propagation::TextMapPropagator;
-use opentelemetry_sdk::propagation::TraceContextPropagator;
-use tokio::signal;
-use tokio::sync::broadcast;
-use tracing_opentelemetry::OpenTelemetrySpanExt;
-
-mod config {
-    pub use rust_axum_postgres::config::*;
-}
-mod database {
-    pub use rust_axum_postgres::database::*;
-}
-mod telemetry {
-    pub use rust_axum_postgres::telemetry::*;
-}
-mod jobs {
-    pub use rust_axum_postgres::jobs::*;
-}
-
-use config::Config;
-use database::create_pool;
-use jobs::{JobQueue, NotificationHandler};
-use telemetry::init_telemetry;
+use job_worker::{Handlers, Worker, shutdown_signal};
+use mailer::EMAIL_JOB_KIND;
+
+use rust_axum_postgres::clients::ReportClient;
+use rust_axum_postgres::config::Config;
+use rust_axum_postgres::database::{TenantDb, create_pool};
+use rust_axum_postgres::jobs::{
+    ACCOUNT_ERASURE_JOB_KIND, AccountErasureHandler, ArticleEnrichmentHandler, Leadership,
+    MAINTENANCE_INTERVAL, NotificationHandler, PartitionJob, SESSION_SWEEP_INTERVAL, SessionJob,
+    TrendingJob, WebhookHandler,
+};
+use rust_axum_postgres::repository::{
+    ArticleRepository, ArticleScoreRepository, AuditRepository, FavoriteRepository,
+    JobPartitionRepository, SessionRepository, UserRepository,
+};
+use rust_axum_postgres::services::AuditRecorder;
+use rust_axum_postgres::storage::Storage;
+use rust_axum_postgres::telemetry::init_telemetry;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config = Config::from_env();
+    let config = Config::load().await?;
 
-    let telemetry_guard = init_telemetry(&config)?;
+    let mut telemetry_guard = init_telemetry(&config)?;
 
     tracing::info!(
         environment = %config.environment,
@@ -37,119 +31,77 @@ async fn main() -> anyhow::Result<()> {
     );
 
     let pool = create_pool(&config).await?;
-    let job_queue = JobQueue::new(pool);
-
-    let (shutdown_tx, _) = broadcast::channel::<()>(1);
-
-    let worker_handle = {
-        let job_queue = job_queue.clone();
-        let mut shutdown_rx = shutdown_tx.subscribe();
-
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(1));
-
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        if let Err(e) = process_job(&job_queue).await {
-                            tracing::error!(error = %e, "Error processing job");
-                        }
-                    }
-                    _ = shutdown_rx.recv() => {
-                        tracing::info!("Worker received shutdown signal");
-                        break;
-                    }
-                }
-            }
-        })
+    if let Some(planner) = telemetry_guard.query_planner.take() {
+        planner.spawn(pool.clone());
+    }
+    let (leadership, election) = Leadership::spawn(
+        "scheduler",
+        config.direct_database_url().to_string(),
+        Duration::from_secs(config.leadership_interval_secs),
+    );
+    let trending = TrendingJob::new(
+        ArticleScoreRepository::new(pool.clone()),
+        Duration::from_secs(config.trending_half_life_hours * 3600),
+    )
+    .spawn(
+        Duration::from_secs(config.trending_refresh_secs),
+        leadership.clone(),
+    );
+    let partitions = PartitionJob::new(JobPartitionRepository::new(pool.clone()))
+        .spawn(MAINTENANCE_INTERVAL, leadership.clone());
+    let sessions = SessionJob::new(SessionRepository::new(pool.clone()))
+        .spawn(SESSION_SWEEP_INTERVAL, leadership);
+    let report_client = if config.article_summaries_enabled {
+        Some(ReportClient::new(
+            &config.report_service_url,
+            Duration::from_secs(config.report_service_timeout_secs),
+        )?)
+    } else {
+        None
     };
-
-    shutdown_signal().await;
-    let _ = shutdown_tx.send(());
-
-    worker_handle.await?;
+    let tenant_db = TenantDb::new(pool.clone(), config.tenancy_mode);
+    let enrichment =
+        ArticleEnrichmentHandler::new(ArticleRepository::new(tenant_db.clone()), report_client);
+    let erasure = AccountErasureHandler::new(
+        UserRepository::new(tenant_db.clone()),
+        ArticleRepository::new(tenant_db.clone()),
+        FavoriteRepository::new(tenant_db),
+        Storage::from_config(&config)?,
+        AuditRecorder::new(AuditRepository::new(pool.clone())),
+    );
+    let mailer = mailer::from_config(&config.mailer())?;
+    tracing::info!(transport = mailer.provider(), "Mailer configured");
+    let handlers = Handlers::new()
+        .register("notification", |job| async move {
+            NotificationHandler::handle(&job).await
+        })
+        .register("article_enrichment", move |job| {
+            let enrichment = enrichment.clone();
+            async move { enrichment.handle(&job).await }
+        })
+        .register(ACCOUNT_ERASURE_JOB_KIND, move |job| {
+            let erasure = erasure.clone();
+            async move { erasure.handle(&job).await }
+        })
+        .register("webhook", |job| async move {
+            WebhookHandler::handle(&job).await
+        })
+        .register(EMAIL_JOB_KIND, move |job| {
+            let mailer = mailer.clone();
+            async move { mailer::handle_job(mailer.as_ref(), &job).await }
+        });
+
+    Worker::new(pool, handlers)
+        .persistent_statements(!config.pgbouncer_mode)
+        .run(shutdown_signal())
+        .await;
+    trending.abort();
+    partitions.abort();
+    sessions.abort();
+    election.abort();
 
     tracing::info!("Worker shutdown complete");
     telemetry_guard.shutdown();
 
     Ok(())
 }
-
-async fn process_job(job_queue: &JobQueue) -> anyhow::Result<()> {
-    let Some(job) = job_queue.dequeue().await? else {
-        return Ok(());
-    };
-
-    let parent_context = extract_trace_context(&job.trace_context);
-
-    let span = tracing::info_span!(
-        "job.process",
-        job_id = job.id,
-        job_kind = %job.kind,
-    );
-    let _ = span.set_parent(parent_context);
-    let _guard = span.enter();
-
-    tracing::info!(job_id = job.id, kind = %job.kind, "Processing job");
-
-    let result = match job.kind.as_str() {
-        "notification" => NotificationHandler::handle(&job).await,
-        _ => {
-            tracing::warn!(job_id = job.id, kind = %job.kind, "Unknown job kind");
-            Err(anyhow::anyhow!("Unknown job kind: {}", job.kind))
-        }
-    };
-
-    match result {
-        Ok(()) => {
-            job_queue.complete(job.id).await?;
-            tracing::info!(job_id = job.id, "Job completed");
-        }
-        Err(e) => {
-            job_queue.fail(job.id, &e.to_string()).await?;
-            tracing::error!(job_id = job.id, error = %e, "Job failed");
-        }
-    }
-
-    Ok(())
-}
-
-fn extract_trace_context(trace_context: &Option<serde_json::Value>) -> opentelemetry::Context {
-    let Some(ctx_value) = trace_context else {
-        return opentelemetry::Context::new();
-    };
-
-    let carrier: HashMap<String, String> = match serde_json::from_value(ctx_value.clone()) {
-        Ok(c) => c,
-        Err(_) => return opentelemetry::Context::new(),
-    };
-
-    let propagator = TraceContextPropagator::new();
-    propagator.extract(&carrier)
-}
-
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("Failed to install Ctrl+C handler");
-    };
-
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("Failed to install signal handler")
-            .recv()
-            .await;
-    };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
-    }
-
-    tracing::info!("Shutdown signal received");
-}
@@ -0,0 +1,3 @@
+mod report;
+
+pub use report::{CreateReportRequest, EconomicReport, ReportClient, SummaryRequest};
@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use opentelemetry::propagation::{Injector, TextMapPropagator};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::{Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::error::{AppError, AppResult};
+use crate::middleware::{Deadline, REQUEST_DEADLINE_HEADER, format_timeout};
+
+#[derive(Debug, Serialize)]
+pub struct CreateReportRequest {
+    pub indicators: Vec<String>,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSection {
+    pub heading: String,
+    pub content: String,
+}
+
+/// The subset of an ai-report-generator report that the articles API exposes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EconomicReport {
+    pub id: String,
+    pub title: String,
+    pub executive_summary: String,
+    pub sections: Vec<ReportSection>,
+    pub indicators_used: Vec<String>,
+    pub time_range_start: String,
+    pub time_range_end: String,
+    pub trace_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryRequest<'a> {
+    pub title: &'a str,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Summary {
+    pub summary: String,
+    pub model: String,
+    pub provider: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// HTTP client for ai-report-generator. Every request carries a W3C
+/// `traceparent` so the report pipeline joins the caller's trace, and,
+/// under a request [`Deadline`], what is left of it, so the pipeline's LLM
+/// calls stop when our caller does.
+#[derive(Clone)]
+pub struct ReportClient {
+    client: reqwest::Client,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl ReportClient {
+    pub fn new(base_url: &str, timeout: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(timeout).build()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            timeout,
+        })
+    }
+
+    fn post(&self, path: &str) -> AppResult<reqwest::RequestBuilder> {
+        let request = self
+            .client
+            .post(format!("{}{path}", self.base_url))
+            .headers(trace_headers(&Span::current()));
+        let Some(deadline) = Deadline::current() else {
+            return Ok(request);
+        };
+        let remaining = deadline.remaining();
+        if remaining.is_zero() {
+            return Err(AppError::DeadlineExceeded);
+        }
+        Ok(request
+            .timeout(remaining.min(self.timeout))
+            .header(REQUEST_DEADLINE_HEADER, format_timeout(remaining)))
+    }
+
+    #[instrument(
+        name = "report_service.create_report",
+        skip(self, request),
+        fields(
+            otel.kind = "client",
+            http.request.method = "POST",
+            url.full = %format!("{}/api/reports", self.base_url),
+            report.indicators = %request.indicators.join(","),
+            http.response.status_code = tracing::field::Empty,
+        )
+    )]
+    pub async fn create_report(&self, request: &CreateReportRequest) -> AppResult<EconomicReport> {
+        let response = self
+            .post("/api/reports")?
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Report service request failed: {}", e)))?;
+
+        read_response(response).await
+    }
+
+    #[instrument(
+        name = "report_service.summarize",
+        skip(self, request),
+        fields(
+            otel.kind = "client",
+            http.request.method = "POST",
+            url.full = %format!("{}/api/summaries", self.base_url),
+            http.response.status_code = tracing::field::Empty,
+        )
+    )]
+    pub async fn summarize(&self, request: &SummaryRequest<'_>) -> AppResult<Summary> {
+        let response = self
+            .post("/api/summaries")?
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Report service request failed: {}", e)))?;
+
+        read_response(response).await
+    }
+}
+
+/// Records the status on the current client span and maps a 4xx to
+/// `Validation` (the service's message is meant for the caller), a 504 for
+/// the deadline we forwarded to `DeadlineExceeded`, and anything else
+/// unsuccessful to `Upstream`.
+async fn read_response<T: DeserializeOwned>(response: reqwest::Response) -> AppResult<T> {
+    let status = response.status();
+    Span::current().record("http.response.status_code", status.as_u16() as i64);
+
+    if status == reqwest::StatusCode::GATEWAY_TIMEOUT && Deadline::current().is_some() {
+        return Err(AppError::DeadlineExceeded);
+    }
+    if status.is_client_error() {
+        let message = response
+            .json::<ErrorBody>()
+            .await
+            .map(|body| body.error)
+            .unwrap_or_else(|_| format!("Report service rejected the request ({})", status));
+        return Err(AppError::Validation(message));
+    }
+    if !status.is_success() {
+        return Err(AppError::Upstream(format!(
+            "Report service returned {}",
+            status
+        )));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Invalid report service response: {}", e)))
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+fn trace_headers(span: &Span) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    TraceContextPropagator::new()
+        .inject_context(&span.context(), &mut HeaderInjector(&mut headers));
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_injector_writes_traceparent() {
+        let span_context = SpanContext::new(
+            TraceId::from(0x4bf92f3577b34da6a3ce929d0e0e4736),
+            SpanId::from(0x00f067aa0ba902b7),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let cx = opentelemetry::Context::new().with_remote_span_context(span_context);
+
+        let mut headers = HeaderMap::new();
+        TraceContextPropagator::new().inject_context(&cx, &mut HeaderInjector(&mut headers));
+
+        assert_eq!(
+            headers["traceparent"],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requests_forward_the_deadline() {
+        let client = ReportClient::new("http://reports:8080/", Duration::from_secs(120)).unwrap();
+
+        let plain = client.post("/api/summaries").unwrap().build().unwrap();
+        assert_eq!(plain.url().as_str(), "http://reports:8080/api/summaries");
+        assert!(plain.headers().get(REQUEST_DEADLINE_HEADER).is_none());
+
+        let budget = Duration::from_secs(2);
+        let request = Deadline::after(budget)
+            .run(async { Ok(client.post("/api/summaries")?.build().unwrap()) })
+            .await
+            .unwrap();
+        let forwarded = request.headers()[REQUEST_DEADLINE_HEADER].to_str().unwrap();
+        let forwarded_ms: u128 = forwarded.strip_suffix('m').unwrap().parse().unwrap();
+        assert!(forwarded_ms <= budget.as_millis());
+        assert!(request.timeout().is_some_and(|timeout| *timeout <= budget));
+    }
+
+    #[test]
+    fn test_report_deserializes_from_service_payload() {
+        let report: EconomicReport = serde_json::from_value(serde_json::json!({
+            "id": "7f1c2a9e-5b1d-4c1e-9a57-3c3f5e2d8b10",
+            "title": "Labor market",
+            "executive_summary": "Unemployment fell.",
+            "sections": [{ "heading": "Trends", "content": "..." }],
+            "indicators_used": ["UNRATE"],
+            "time_range_start": "2021-01-01",
+            "time_range_end": "2023-12-31",
+            "total_data_points": 36,
+            "total_tokens": 1200,
+            "total_cost_usd": 0.01,
+            "providers_used": ["openai"],
+            "generation_duration_ms": 5000,
+            "trace_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+        }))
+        .expect("report should deserialize");
+
+        assert_eq!(report.sections.len(), 1);
+        assert_eq!(report.indicators_used, vec!["UNRATE"]);
+    }
+}
@@ -1,3 +1,11 @@
+mod plan;
 mod pool;
+mod query;
+mod retry;
+mod tenant;
 
+pub use plan::{QueryPlanner, query_plans};
 pub use pool::create_pool;
+pub use query::{Persistent, Traced};
+pub use retry::retrying;
+pub use tenant::{TenancyMode, TenantConn, TenantDb};
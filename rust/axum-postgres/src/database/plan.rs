@@ -0,0 +1,419 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{SpanKind, TraceContextExt};
+use opentelemetry_sdk::trace::{Sampler, SamplingDecision, ShouldSample};
+use sqlx::{PgPool, Row};
+use tokio::sync::mpsc;
+use tracing::dispatcher::WeakDispatch;
+use tracing::field::{Field, Visit};
+use tracing::span::Id;
+use tracing::{Dispatch, Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::Config;
+use crate::telemetry::DB_QUERY_PLANS;
+
+/// Plans waiting for the planner; past this, slow queries go without one.
+const QUEUE_SIZE: usize = 16;
+
+/// Builds the two halves of `QUERY_PLAN_THRESHOLD_MS` plan capture. When it
+/// is 0 (the default) there is no planner and the layer does nothing; it is
+/// installed anyway because an `Option` layer doesn't pass
+/// `on_register_dispatch` on.
+pub fn query_plans(config: &Config) -> (QueryPlanLayer, Option<QueryPlanner>) {
+    if config.query_plan_threshold_ms == 0 {
+        let layer = QueryPlanLayer {
+            dispatch: OnceLock::new(),
+            capture: None,
+        };
+        return (layer, None);
+    }
+    let (layer, planner) = capture(
+        config.query_plan_sample_ratio,
+        config.query_plan_max_per_minute,
+    );
+    (layer, Some(planner))
+}
+
+fn capture(sample_ratio: f64, max_per_minute: u32) -> (QueryPlanLayer, QueryPlanner) {
+    let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+    let layer = QueryPlanLayer {
+        dispatch: OnceLock::new(),
+        capture: Some(Capture {
+            sender,
+            sample_ratio,
+            limit: RateLimit::new(max_per_minute),
+        }),
+    };
+    (layer, QueryPlanner { receiver })
+}
+
+/// Watches for sqlx's slow-statement warning, which the pool logs for
+/// queries over `QUERY_PLAN_THRESHOLD_MS` from inside the repository's
+/// `db.*` span. For a sampled trace, within the rate limit, it keeps that
+/// span open and hands the query to the [`QueryPlanner`].
+pub struct QueryPlanLayer {
+    dispatch: OnceLock<WeakDispatch>,
+    capture: Option<Capture>,
+}
+
+struct Capture {
+    sender: mpsc::Sender<PlanRequest>,
+    sample_ratio: f64,
+    limit: RateLimit,
+}
+
+impl<S> Layer<S> for QueryPlanLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_register_dispatch(&self, subscriber: &Dispatch) {
+        let _ = self.dispatch.set(subscriber.downgrade());
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(capture) = &self.capture else {
+            return;
+        };
+        if event.metadata().target() != "sqlx::query" {
+            return;
+        }
+        let mut statement = SlowStatement::default();
+        event.record(&mut statement);
+        // The planner's own EXPLAIN runs outside any span.
+        let (Some(sql), Some(span)) = (statement.sql(), ctx.event_span(event)) else {
+            return;
+        };
+        let Some(dispatch) = self.dispatch.get().and_then(WeakDispatch::upgrade) else {
+            return;
+        };
+        let id = span.id();
+        drop(span);
+
+        if let Err(outcome) = capture.request(&dispatch, id, sql, statement.elapsed) {
+            DB_QUERY_PLANS.add(1, &[KeyValue::new("outcome", outcome)]);
+        }
+    }
+}
+
+impl Capture {
+    /// Errs with the `db.query.plans` outcome when the query gets no plan.
+    fn request(
+        &self,
+        dispatch: &Dispatch,
+        id: Id,
+        sql: String,
+        elapsed: Duration,
+    ) -> Result<(), &'static str> {
+        // Sampled by trace id, so a trace gets the plans of all its slow
+        // queries or none, and only for traces that are exported.
+        let Some(cx) = tracing_opentelemetry::get_otel_context(&id, dispatch) else {
+            return Err("sampled_out");
+        };
+        let span_context = cx.span().span_context().clone();
+        if !span_context.is_sampled() || !sampled(&span_context, self.sample_ratio) {
+            return Err("sampled_out");
+        }
+        if !self.limit.allow() {
+            return Err("rate_limited");
+        }
+        let request = PlanRequest {
+            span: HeldSpan::new(dispatch, id),
+            sql,
+            elapsed,
+        };
+        self.sender.try_send(request).map_err(|_| "dropped")
+    }
+}
+
+fn sampled(span_context: &opentelemetry::trace::SpanContext, ratio: f64) -> bool {
+    let result = Sampler::TraceIdRatioBased(ratio).should_sample(
+        None,
+        span_context.trace_id(),
+        "db.query.plan",
+        &SpanKind::Internal,
+        &[],
+        &[],
+    );
+    result.decision == SamplingDecision::RecordAndSample
+}
+
+/// Runs `EXPLAIN` for the queries the [`QueryPlanLayer`] hands over, one at
+/// a time, and records each plan as a `Query plan` event on the span of the
+/// query it explains.
+pub struct QueryPlanner {
+    receiver: mpsc::Receiver<PlanRequest>,
+}
+
+impl QueryPlanner {
+    pub fn spawn(mut self, pool: PgPool) {
+        tokio::spawn(async move {
+            while let Some(request) = self.receiver.recv().await {
+                let plan = explain(&pool, &request.sql).await;
+                request.record(plan);
+            }
+        });
+    }
+}
+
+/// The plan Postgres would use. Queries with bind parameters get the
+/// generic plan (`GENERIC_PLAN`, Postgres 16+), since their values are
+/// gone by now; that is the plan a prepared statement settles on after
+/// its first few executions. `EXPLAIN` without `ANALYZE` only plans, so
+/// it is safe for writes.
+async fn explain(pool: &PgPool, sql: &str) -> Result<serde_json::Value, sqlx::Error> {
+    let options = if sql.contains('$') {
+        "(GENERIC_PLAN, FORMAT JSON)"
+    } else {
+        "(FORMAT JSON)"
+    };
+    // Plain text protocol: `$1` is only a placeholder to `GENERIC_PLAN` if
+    // the statement isn't prepared with parameters.
+    let explain = format!("EXPLAIN {options} {sql}");
+    let row = sqlx::raw_sql(&explain).fetch_one(pool).await?;
+    row.try_get(0)
+}
+
+struct PlanRequest {
+    span: HeldSpan,
+    sql: String,
+    elapsed: Duration,
+}
+
+impl PlanRequest {
+    fn record(self, plan: Result<serde_json::Value, sqlx::Error>) {
+        let Self { span, elapsed, .. } = self;
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        let outcome = tracing::dispatcher::with_default(&span.dispatch, || match plan {
+            Ok(plan) => {
+                tracing::info!(
+                    parent: &span.id,
+                    db.query.plan = %plan,
+                    db.query.duration_ms = elapsed_ms,
+                    "Query plan"
+                );
+                "captured"
+            }
+            Err(e) => {
+                tracing::warn!(
+                    parent: &span.id,
+                    error = %e,
+                    "Query plan capture failed"
+                );
+                "failed"
+            }
+        });
+        DB_QUERY_PLANS.add(1, &[KeyValue::new("outcome", outcome)]);
+    }
+}
+
+/// A reference that keeps a span from closing, so the plan, which arrives
+/// after the query's future has finished, can still be recorded on it. The
+/// OTel span's end time is its last exit, so the wait doesn't show in its
+/// duration.
+struct HeldSpan {
+    dispatch: Dispatch,
+    id: Id,
+}
+
+impl HeldSpan {
+    fn new(dispatch: &Dispatch, id: Id) -> Self {
+        Self {
+            dispatch: dispatch.clone(),
+            id: dispatch.clone_span(&id),
+        }
+    }
+}
+
+impl Drop for HeldSpan {
+    fn drop(&mut self) {
+        self.dispatch.try_close(self.id.clone());
+    }
+}
+
+/// The fields of sqlx's `sqlx::query` events. Only slow ones have
+/// `slow_threshold`.
+#[derive(Default)]
+struct SlowStatement {
+    slow: bool,
+    summary: String,
+    statement: String,
+    elapsed: Duration,
+}
+
+impl SlowStatement {
+    /// `db.statement` is left empty when `summary`, its first few words,
+    /// is already the whole query.
+    fn sql(&self) -> Option<String> {
+        if !self.slow {
+            return None;
+        }
+        let sql = match self.statement.trim() {
+            "" => self.summary.trim(),
+            statement => statement,
+        };
+        let explainable = !sql.is_empty()
+            && !sql
+                .get(..7)
+                .is_some_and(|start| start.eq_ignore_ascii_case("EXPLAIN"));
+        explainable.then(|| sql.to_string())
+    }
+}
+
+impl Visit for SlowStatement {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed = Duration::try_from_secs_f64(value).unwrap_or_default();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, _value: &dyn std::fmt::Debug) {
+        if field.name() == "slow_threshold" {
+            self.slow = true;
+        }
+    }
+}
+
+/// At most `max` per minute, in fixed one-minute windows.
+struct RateLimit {
+    max: u32,
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RateLimit {
+    fn new(max: u32) -> Self {
+        Self {
+            max,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    fn allow(&self) -> bool {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.0.elapsed() >= Duration::from_secs(60) {
+            *window = (Instant::now(), 0);
+        }
+        if window.1 >= self.max {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::Value;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// What sqlx logs for a slow query, from inside `span`.
+    fn slow_statement(sql: &str) {
+        tracing::warn!(
+            target: "sqlx::query",
+            summary = "select id from articles …",
+            db.statement = format!("\n\n{sql}\n"),
+            rows_returned = 1,
+            elapsed_secs = 0.75,
+            slow_threshold = ?Duration::from_millis(500),
+            "slow statement: execution time exceeded alert threshold"
+        );
+    }
+
+    #[test]
+    fn test_plan_is_recorded_on_the_slow_query_span() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let (layer, mut planner) = capture(1.0, 10);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .with(layer);
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let sql = "SELECT id FROM articles WHERE slug = $1";
+        tracing::info_span!("db.article.find_by_slug").in_scope(|| slow_statement(sql));
+
+        // Held open until the plan is in.
+        assert!(exporter.get_finished_spans().unwrap().is_empty());
+        let request = planner.receiver.try_recv().unwrap();
+        assert_eq!(request.sql, sql);
+        assert_eq!(request.elapsed, Duration::from_millis(750));
+        request.record(Ok(
+            serde_json::json!([{ "Plan": { "Node Type": "Index Scan" } }]),
+        ));
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let event = spans[0]
+            .events
+            .iter()
+            .find(|e| e.name == "Query plan")
+            .expect("plan event");
+        let plan = event
+            .attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == "db.query.plan")
+            .map(|kv| kv.value.clone());
+        assert_eq!(
+            plan,
+            Some(Value::from(r#"[{"Plan":{"Node Type":"Index Scan"}}]"#))
+        );
+    }
+
+    #[test]
+    fn test_only_sampled_slow_queries_in_a_span_are_requested() {
+        let provider = SdkTracerProvider::builder().build();
+        let (layer, mut planner) = capture(0.0, 10);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .with(layer);
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        tracing::info_span!("db.article.list").in_scope(|| slow_statement("SELECT 1"));
+        slow_statement("SELECT 1");
+        assert!(planner.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_slow_statement_sql() {
+        let mut short = SlowStatement {
+            slow: true,
+            summary: "SELECT 1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(short.sql().as_deref(), Some("SELECT 1"));
+
+        short.summary = "explain select 1".to_string();
+        assert_eq!(short.sql(), None);
+
+        let fast = SlowStatement {
+            summary: "SELECT 1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(fast.sql(), None);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let limit = RateLimit::new(2);
+        assert!(limit.allow());
+        assert!(limit.allow());
+        assert!(!limit.allow());
+    }
+}
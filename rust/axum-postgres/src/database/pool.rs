@@ -1,18 +1,54 @@
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use std::str::FromStr;
+use std::time::Duration;
 
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, PgConnection, PgPool};
+
+use super::query::set_persistent_statements;
+use super::retry::set_retry_policy;
 use crate::config::Config;
 
+/// With `PGBOUNCER_MODE`, `DATABASE_URL` may point at pgbouncer in
+/// transaction mode, where consecutive transactions can land on different
+/// server connections. Nothing may outlive a transaction there: queries are
+/// not kept as prepared statements, the startup packet leaves out
+/// `extra_float_digits` (which pgbouncer rejects) and migrations, whose
+/// advisory lock is held across theirs, run over `DATABASE_DIRECT_URL`.
 pub async fn create_pool(config: &Config) -> Result<PgPool, sqlx::Error> {
+    let mut options = PgConnectOptions::from_str(&config.database_url)?;
+    set_retry_policy(
+        Duration::from_millis(config.db_query_timeout_ms),
+        config.db_retry_max_attempts,
+    );
+    if config.pgbouncer_mode {
+        set_persistent_statements(false);
+        options = options.statement_cache_capacity(0).extra_float_digits(None);
+    }
+    // sqlx warns about statements slower than this from inside the query's
+    // span, which is what `QueryPlanLayer` listens for; its default is 1s.
+    if config.query_plan_threshold_ms > 0 {
+        options = options.log_slow_statements(
+            log::LevelFilter::Warn,
+            Duration::from_millis(config.query_plan_threshold_ms),
+        );
+    }
+
     let pool = PgPoolOptions::new()
         .max_connections(25)
         .min_connections(5)
-        .acquire_timeout(std::time::Duration::from_secs(5))
-        .connect(&config.database_url)
+        .acquire_timeout(Duration::from_secs(5))
+        .connect_with(options)
         .await?;
 
     tracing::info!("Database connection pool created");
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    if config.database_direct_url.is_empty() {
+        sqlx::migrate!("./migrations").run(&pool).await?;
+    } else {
+        let mut conn = PgConnection::connect(&config.database_direct_url).await?;
+        sqlx::migrate!("./migrations").run(&mut conn).await?;
+        conn.close().await?;
+    }
 
     tracing::info!("Database migrations completed");
 
@@ -0,0 +1,241 @@
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+
+use crate::middleware::Deadline;
+use crate::telemetry::DB_RETRIES;
+
+/// `DB_QUERY_TIMEOUT_MS` and `DB_RETRY_MAX_ATTEMPTS`. Set by
+/// [`create_pool`](super::create_pool).
+static QUERY_TIMEOUT_MS: AtomicU64 = AtomicU64::new(5_000);
+static MAX_ATTEMPTS: AtomicU32 = AtomicU32::new(3);
+
+pub(crate) fn set_retry_policy(query_timeout: Duration, max_attempts: u32) {
+    QUERY_TIMEOUT_MS.store(query_timeout.as_millis() as u64, Ordering::Relaxed);
+    MAX_ATTEMPTS.store(max_attempts.max(1), Ordering::Relaxed);
+}
+
+/// Before the second attempt; doubled before each one after.
+const BACKOFF: Duration = Duration::from_millis(10);
+
+/// Runs a repository call, giving each attempt `DB_QUERY_TIMEOUT_MS` and
+/// running it again when Postgres aborted it as a serialization failure
+/// (`40001`) or a deadlock victim (`40P01`). Both roll the transaction
+/// back, so `attempt` must redo all of it, scope and commit included:
+///
+/// ```ignore
+/// retrying(|| async move {
+///     let mut conn = self.db.scope(org).await?;
+///     // ...
+///     conn.finish().await?;
+///     Ok(article)
+/// })
+/// .await
+/// ```
+///
+/// A retry that would outlast the request [`Deadline`] isn't made. A
+/// timed-out attempt isn't retried; it fails as an I/O `TimedOut` error.
+pub async fn retrying<T, F>(attempt: impl FnMut() -> F) -> Result<T, sqlx::Error>
+where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    let timeout = Duration::from_millis(QUERY_TIMEOUT_MS.load(Ordering::Relaxed));
+    run(timeout, MAX_ATTEMPTS.load(Ordering::Relaxed), attempt).await
+}
+
+async fn run<T, F>(
+    timeout: Duration,
+    max_attempts: u32,
+    mut attempt: impl FnMut() -> F,
+) -> Result<T, sqlx::Error>
+where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut attempts = 1;
+    let mut backoff = BACKOFF;
+    loop {
+        let result = if timeout.is_zero() {
+            attempt().await
+        } else {
+            tokio::time::timeout(timeout, attempt())
+                .await
+                .unwrap_or_else(|_| Err(timed_out(timeout)))
+        };
+        let error = match result {
+            Err(error) => error,
+            ok => return ok,
+        };
+        let Some(code) = transient_code(&error) else {
+            return Err(error);
+        };
+        let out_of_time = Deadline::current().is_some_and(|d| d.remaining() <= backoff);
+        if attempts >= max_attempts || out_of_time {
+            tracing::warn!(error = %error, attempts, "Giving up on transient database error");
+            return Err(error);
+        }
+
+        DB_RETRIES.add(1, &[KeyValue::new("db.response.status_code", code)]);
+        tracing::info!(error = %error, attempt = attempts, "Retrying transient database error");
+        tokio::time::sleep(backoff).await;
+        attempts += 1;
+        backoff *= 2;
+    }
+}
+
+/// The SQLSTATE of an error that running the same transaction again can
+/// fix.
+fn transient_code(error: &sqlx::Error) -> Option<&'static str> {
+    match error.as_database_error()?.code()?.as_ref() {
+        "40001" => Some("40001"),
+        "40P01" => Some("40P01"),
+        _ => None,
+    }
+}
+
+fn timed_out(timeout: Duration) -> sqlx::Error {
+    sqlx::Error::Io(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("query timed out after {}ms", timeout.as_millis()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::error::Error as StdError;
+    use std::fmt;
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    #[derive(Debug)]
+    struct PgError(&'static str);
+
+    impl fmt::Display for PgError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "SQLSTATE {}", self.0)
+        }
+    }
+
+    impl StdError for PgError {}
+
+    impl DatabaseError for PgError {
+        fn message(&self) -> &str {
+            self.0
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn pg_error(code: &'static str) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(PgError(code)))
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried_until_one_succeeds() {
+        let mut calls = 0;
+        let result = run(Duration::ZERO, 3, || {
+            calls += 1;
+            let call = calls;
+            async move {
+                match call {
+                    1 => Err(pg_error("40001")),
+                    2 => Err(pg_error("40P01")),
+                    _ => Ok(call),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_metric_recorded(
+            "db.retries",
+            &[KeyValue::new("db.response.status_code", "40001")],
+        );
+        assert_metric_recorded(
+            "db.retries",
+            &[KeyValue::new("db.response.status_code", "40P01")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_are_bounded() {
+        let mut calls = 0;
+        let result: Result<(), _> = run(Duration::ZERO, 2, || {
+            calls += 1;
+            async { Err(pg_error("40001")) }
+        })
+        .await;
+
+        assert_eq!(transient_code(&result.unwrap_err()), Some("40001"));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_fail_on_the_first_attempt() {
+        let mut calls = 0;
+        let result: Result<(), _> = run(Duration::ZERO, 3, || {
+            calls += 1;
+            async { Err(pg_error("23505")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_outlasts_the_deadline() {
+        let mut calls = 0;
+        let result: Result<(), _> = Deadline::after(Duration::from_millis(5))
+            .run(async {
+                Ok(run(Duration::ZERO, 3, || {
+                    calls += 1;
+                    async { Err(pg_error("40P01")) }
+                })
+                .await)
+            })
+            .await
+            .unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_attempts_time_out() {
+        let result = run(Duration::from_millis(10), 3, || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+
+        let Err(sqlx::Error::Io(error)) = result else {
+            panic!("expected a timeout, got {result:?}");
+        };
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use opentelemetry::KeyValue;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction, pool::PoolConnection};
+use tracing::Instrument;
+
+use super::Traced;
+use crate::middleware::Deadline;
+use crate::models::OrgId;
+use crate::telemetry::DB_TENANT_SCOPE_DURATION;
+
+/// The role the RLS policies in `20260106000010_row_level_security.sql`
+/// apply to.
+const TENANT_ROLE: &str = "app_tenant";
+
+/// `TENANCY_MODE`. Both modes keep the `org_id` predicates in every query;
+/// `rls` additionally runs tenant queries under row-level security, so a
+/// forgotten predicate returns nothing instead of another tenant's rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenancyMode {
+    App,
+    Rls,
+}
+
+impl TenancyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Rls => "rls",
+        }
+    }
+}
+
+impl FromStr for TenancyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "app" => Ok(Self::App),
+            "rls" => Ok(Self::Rls),
+            other => Err(format!("expected app or rls, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for TenancyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The pool, plus how queries on tenant tables are isolated. Queries that
+/// deliberately span tenants use [`TenantDb::pool`] directly.
+#[derive(Clone)]
+pub struct TenantDb {
+    pool: PgPool,
+    mode: TenancyMode,
+}
+
+impl TenantDb {
+    pub fn new(pool: PgPool, mode: TenancyMode) -> Self {
+        Self { pool, mode }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// A connection for queries on behalf of `org`. In `rls` mode it is a
+    /// transaction that has switched to the tenant role with
+    /// `app.current_org` set, which costs a `BEGIN`, a `set_config` and,
+    /// in [`TenantConn::finish`], a `COMMIT`. Under a request [`Deadline`]
+    /// it is a transaction in either mode, with `statement_timeout` set to
+    /// what is left, so Postgres stops work nobody will wait for.
+    pub async fn scope(&self, org: OrgId) -> Result<TenantConn, sqlx::Error> {
+        let deadline = Deadline::current();
+        if self.mode == TenancyMode::App && deadline.is_none() {
+            return Ok(TenantConn {
+                conn: Conn::Pooled(self.pool.acquire().await?),
+                mode: self.mode,
+                overhead: Duration::ZERO,
+            });
+        }
+
+        let started = Instant::now();
+        let tx = self
+            .begin_scoped(org, deadline)
+            .instrument(tracing::info_span!("db.tenant.scope", org.id = org.0))
+            .await?;
+        Ok(TenantConn {
+            conn: Conn::Scoped(tx),
+            mode: self.mode,
+            overhead: started.elapsed(),
+        })
+    }
+
+    async fn begin_scoped(
+        &self,
+        org: OrgId,
+        deadline: Option<Deadline>,
+    ) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // A `statement_timeout` of 0 would disable it.
+        let statement_timeout =
+            deadline.map(|deadline| deadline.remaining().as_millis().max(1).to_string());
+        // `SET LOCAL` can't take bind parameters; `set_config(.., true)` is
+        // the same thing as a function.
+        match self.mode {
+            TenancyMode::App => {
+                sqlx::query("SELECT set_config('statement_timeout', $1, true)")
+                    .bind(statement_timeout)
+                    .traced(&self.pool)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            TenancyMode::Rls => {
+                sqlx::query(
+                    "SELECT set_config('role', $1, true), set_config('app.current_org', $2, true), \
+                     set_config('statement_timeout', COALESCE($3, current_setting('statement_timeout')), true)",
+                )
+                .bind(TENANT_ROLE)
+                .bind(org.0.to_string())
+                .bind(statement_timeout)
+                .traced(&self.pool)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        Ok(tx)
+    }
+}
+
+enum Conn {
+    Pooled(PoolConnection<Postgres>),
+    Scoped(Transaction<'static, Postgres>),
+}
+
+/// Derefs to the connection to run tenant queries on. Call
+/// [`TenantConn::finish`] afterwards: dropping a scoped connection rolls
+/// its transaction back.
+pub struct TenantConn {
+    conn: Conn,
+    mode: TenancyMode,
+    overhead: Duration,
+}
+
+impl TenantConn {
+    pub async fn finish(self) -> Result<(), sqlx::Error> {
+        let Conn::Scoped(tx) = self.conn else {
+            return Ok(());
+        };
+        let started = Instant::now();
+        tx.commit()
+            .instrument(tracing::info_span!("db.tenant.commit"))
+            .await?;
+        let overhead = self.overhead + started.elapsed();
+        DB_TENANT_SCOPE_DURATION.record(
+            overhead.as_secs_f64() * 1000.0,
+            &[KeyValue::new("tenancy.mode", self.mode.as_str())],
+        );
+        Ok(())
+    }
+}
+
+impl Deref for TenantConn {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match &self.conn {
+            Conn::Pooled(conn) => conn,
+            Conn::Scoped(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for TenantConn {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match &mut self.conn {
+            Conn::Pooled(conn) => conn,
+            Conn::Scoped(tx) => tx,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenancy_mode_round_trips() {
+        for mode in [TenancyMode::App, TenancyMode::Rls] {
+            assert_eq!(mode.to_string().parse::<TenancyMode>(), Ok(mode));
+        }
+        assert_eq!("RLS".parse::<TenancyMode>(), Ok(TenancyMode::Rls));
+        assert!("row".parse::<TenancyMode>().is_err());
+    }
+}
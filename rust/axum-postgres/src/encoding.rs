@@ -0,0 +1,244 @@
+//! JSON or MessagePack, chosen per request: responses by `Accept`, request
+//! bodies by `Content-Type`. MessagePack bodies carry the same field names as
+//! the JSON ones, so clients decode them into the same structures. Errors stay
+//! JSON.
+
+use std::convert::Infallible;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{HeaderMap, HeaderValue, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::AppError;
+
+pub const MSGPACK: &str = "application/msgpack";
+/// Also accepted, as requests and from older clients.
+const MSGPACK_ALIASES: [&str; 3] = [MSGPACK, "application/x-msgpack", "application/vnd.msgpack"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl Encoding {
+    /// MessagePack when the client prefers it: by `q`, then by how specific
+    /// the range naming it is, so `application/msgpack, */*` picks it but
+    /// `application/*` or no `Accept` at all keeps JSON.
+    pub fn negotiate(accept: &str) -> Self {
+        let mut json = (0.0, 0);
+        let mut msgpack = (0.0, 0);
+        for range in accept.split(',') {
+            let mut params = range.split(';');
+            let media_type = params
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let q = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let specificity = |exact: bool| match media_type.as_str() {
+                _ if exact => Some(2),
+                "application/*" => Some(1),
+                "*/*" => Some(0),
+                _ => None,
+            };
+            // The most specific range that matches sets the quality.
+            if let Some(s) = specificity(media_type == "application/json")
+                && s >= json.1
+            {
+                json = (q, s);
+            }
+            if let Some(s) = specificity(MSGPACK_ALIASES.contains(&media_type.as_str()))
+                && s >= msgpack.1
+            {
+                msgpack = (q, s);
+            }
+        }
+        if msgpack.0 > 0.0 && msgpack > json {
+            Encoding::MsgPack
+        } else {
+            Encoding::Json
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: T) -> Encoded<T> {
+        Encoded(self, value)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Encoding {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .map(Encoding::negotiate)
+            .unwrap_or_default())
+    }
+}
+
+/// A response body in the [`Encoding`] the request asked for.
+pub struct Encoded<T>(pub Encoding, pub T);
+
+impl<T: Serialize> IntoResponse for Encoded<T> {
+    fn into_response(self) -> Response {
+        let Encoded(encoding, value) = self;
+        let mut response = match encoding {
+            Encoding::Json => Json(value).into_response(),
+            // Named, so maps keep the field names JSON clients see.
+            Encoding::MsgPack => match rmp_serde::to_vec_named(&value) {
+                Ok(body) => ([(header::CONTENT_TYPE, MSGPACK)], body).into_response(),
+                Err(e) => {
+                    AppError::Internal(format!("MessagePack encoding failed: {e}")).into_response()
+                }
+            },
+        };
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+/// Like [`Json`], but also takes a MessagePack body sent with a MessagePack
+/// `Content-Type`.
+pub struct Decoded<T>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned> FromRequest<S> for Decoded<T> {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_msgpack(request.headers()) {
+            return Json::<T>::from_request(request, state)
+                .await
+                .map(|Json(value)| Decoded(value))
+                .map_err(IntoResponse::into_response);
+        }
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        rmp_serde::from_slice(&body).map(Decoded).map_err(|e| {
+            AppError::Validation(format!("Invalid MessagePack body: {e}")).into_response()
+        })
+    }
+}
+
+fn is_msgpack(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| {
+            MSGPACK_ALIASES.contains(&media_type.trim().to_ascii_lowercase().as_str())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, http::StatusCode, routing::post};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Input {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn input() -> Input {
+        Input {
+            title: "Hello".to_string(),
+            tags: vec!["rust".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        for (accept, expected) in [
+            ("", Encoding::Json),
+            ("*/*", Encoding::Json),
+            ("application/json", Encoding::Json),
+            ("application/msgpack", Encoding::MsgPack),
+            ("application/x-msgpack", Encoding::MsgPack),
+            ("application/msgpack, */*", Encoding::MsgPack),
+            (
+                "application/msgpack;q=0.5, application/json",
+                Encoding::Json,
+            ),
+            (
+                "application/json;q=0.5, application/msgpack",
+                Encoding::MsgPack,
+            ),
+            ("application/msgpack;q=0", Encoding::Json),
+            ("application/*", Encoding::Json),
+            ("text/html", Encoding::Json),
+        ] {
+            assert_eq!(Encoding::negotiate(accept), expected, "Accept: {accept}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_response_keeps_field_names() {
+        let response = Encoding::MsgPack.encode(input()).into_response();
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], MSGPACK);
+        assert_eq!(response.headers()[header::VARY], "accept");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let value: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "title": "Hello", "tags": ["rust"] })
+        );
+    }
+
+    async fn decode(content_type: &str, body: Vec<u8>) -> Result<Input, StatusCode> {
+        let app = Router::new().route(
+            "/",
+            post(|Decoded(input): Decoded<Input>| async move { Encoding::MsgPack.encode(input) }),
+        );
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        if response.status() != StatusCode::OK {
+            return Err(response.status());
+        }
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        Ok(rmp_serde::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_decoded_takes_either_body() {
+        let msgpack = rmp_serde::to_vec_named(&input()).unwrap();
+        let json = serde_json::to_vec(&input()).unwrap();
+
+        assert_eq!(decode(MSGPACK, msgpack).await, Ok(input()));
+        assert_eq!(decode("application/json", json).await, Ok(input()));
+        assert_eq!(
+            decode(MSGPACK, b"{}".to_vec()).await,
+            Err(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            decode("text/plain", b"{}".to_vec()).await,
+            Err(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        );
+    }
+}
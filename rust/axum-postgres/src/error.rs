@@ -1,14 +1,19 @@
+use std::time::Duration;
+
 use axum::{
     Json,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
     response::{IntoResponse, Response},
 };
-use opentelemetry::trace::TraceContextExt;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{Status, TraceContextExt};
 use serde_json::json;
 use thiserror::Error;
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::telemetry::current_request_id;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Authentication required")]
@@ -29,17 +34,71 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
 
+    #[error("Upstream service error: {0}")]
+    Upstream(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Request deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("Too many failed attempts, retry in {}s", .0.as_secs())]
+    Locked(Duration),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] crate::storage::StorageError),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-fn get_trace_id() -> Option<String> {
+impl AppError {
+    /// The variant name, reported as `exception.type`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized => "Unauthorized",
+            AppError::InvalidCredentials => "InvalidCredentials",
+            AppError::Forbidden => "Forbidden",
+            AppError::NotFound(_) => "NotFound",
+            AppError::Conflict(_) => "Conflict",
+            AppError::Validation(_) => "Validation",
+            AppError::PayloadTooLarge(_) => "PayloadTooLarge",
+            AppError::UnsupportedMediaType(_) => "UnsupportedMediaType",
+            AppError::Database(_) => "Database",
+            AppError::Jwt(_) => "Jwt",
+            AppError::Upstream(_) => "Upstream",
+            AppError::ServiceUnavailable(_) => "ServiceUnavailable",
+            AppError::DeadlineExceeded => "DeadlineExceeded",
+            AppError::Locked(_) => "Locked",
+            AppError::Storage(_) => "Storage",
+            AppError::Internal(_) => "Internal",
+        }
+    }
+}
+
+/// The code and client-facing message of an error response, kept in its
+/// extensions so [`structured_errors`](crate::middleware::structured_errors)
+/// can render the v2 shape without parsing the v1 body.
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub code: &'static str,
+    pub message: String,
+}
+
+pub(crate) fn get_trace_id() -> Option<String> {
     let span = Span::current();
     let context = span.context();
     let span_ref = context.span();
@@ -61,6 +120,20 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg.clone()),
+            AppError::UnsupportedMediaType(msg) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg.clone())
+            }
+            // `statement_timeout`, which a request deadline sets.
+            AppError::Database(e) if is_query_canceled(e) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "Request deadline exceeded".to_string(),
+            ),
+            // `DB_QUERY_TIMEOUT_MS`, from `database::retrying`.
+            AppError::Database(sqlx::Error::Io(e)) if e.kind() == std::io::ErrorKind::TimedOut => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "Database query timed out".to_string(),
+            ),
             AppError::Database(e) => {
                 tracing::error!(error = %e, "Database error");
                 (
@@ -72,6 +145,23 @@ impl IntoResponse for AppError {
                 tracing::warn!(error = %e, "JWT error");
                 (StatusCode::UNAUTHORIZED, "Invalid token".to_string())
             }
+            AppError::Upstream(msg) => {
+                tracing::error!(error = %msg, "Upstream service error");
+                (StatusCode::BAD_GATEWAY, msg.clone())
+            }
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone()),
+            AppError::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
+            AppError::Locked(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::Storage(crate::storage::StorageError::NotFound(_)) => {
+                (StatusCode::NOT_FOUND, "Image not found".to_string())
+            }
+            AppError::Storage(e) => {
+                tracing::error!(error = %e, "Storage error");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
             AppError::Internal(msg) => {
                 tracing::error!(error = %msg, "Internal error");
                 (
@@ -81,30 +171,100 @@ impl IntoResponse for AppError {
             }
         };
 
-        let body = if let Some(trace_id) = get_trace_id() {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-                "trace_id": trace_id,
-            })
-        } else {
-            json!({
-                "error": error_message,
-                "status": status.as_u16(),
-            })
-        };
+        let mut body = json!({
+            "error": error_message.clone(),
+            "status": status.as_u16(),
+        });
+        if let Some(trace_id) = get_trace_id() {
+            body["trace_id"] = trace_id.into();
+        }
+        if let Some(request_id) = current_request_id() {
+            body["request_id"] = request_id.into();
+        }
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let AppError::Locked(retry_after) = &self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs()));
+        }
+        response.extensions_mut().insert(ErrorDetails {
+            code: self.kind(),
+            message: error_message,
+        });
+        response
     }
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
+impl From<blog_domain::ValidationError> for AppError {
+    fn from(e: blog_domain::ValidationError) -> Self {
+        AppError::Validation(e.to_string())
+    }
+}
+
+impl From<two_factor::Error<sqlx::Error>> for AppError {
+    fn from(e: two_factor::Error<sqlx::Error>) -> Self {
+        match e {
+            two_factor::Error::AlreadyEnabled => {
+                AppError::Conflict("Two-factor authentication is already enabled".to_string())
+            }
+            two_factor::Error::NotEnrolled => {
+                AppError::Validation("Two-factor authentication is not set up".to_string())
+            }
+            two_factor::Error::InvalidCode => {
+                AppError::Validation("Invalid two-factor code".to_string())
+            }
+            two_factor::Error::Locked(retry_after) => AppError::Locked(retry_after),
+            two_factor::Error::Store(e) => AppError::Database(e),
+        }
+    }
+}
+
+fn is_query_canceled(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|db| db.code())
+        .is_some_and(|code| code == "57014")
+}
+
+/// Marks the current span as failed when the result is an error:
+/// `otel.status_code=ERROR` plus an `exception` event with the error's type
+/// and message. Services call it on the result of each instrumented method.
+pub trait RecordErr {
+    fn record_err(self) -> Self;
+}
+
+impl<T> RecordErr for AppResult<T> {
+    fn record_err(self) -> Self {
+        if let Err(err) = &self {
+            let span = Span::current();
+            let message = err.to_string();
+            span.set_status(Status::error(message.clone()));
+            span.add_event(
+                "exception",
+                vec![
+                    KeyValue::new("exception.type", err.kind()),
+                    KeyValue::new("exception.message", message),
+                ],
+            );
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum::http::StatusCode;
 
+    #[test]
+    fn test_record_err_passes_result_through() {
+        let error: AppResult<()> = Err(AppError::Conflict("slug taken".to_string())).record_err();
+        assert_eq!(error.unwrap_err().kind(), "Conflict");
+        assert_eq!(Ok::<_, AppError>(1).record_err().unwrap(), 1);
+    }
+
     #[test]
     fn test_unauthorized_error() {
         let error = AppError::Unauthorized;
@@ -162,6 +322,27 @@ mod tests {
                 AppError::Validation("test".to_string()),
                 StatusCode::BAD_REQUEST,
             ),
+            (
+                AppError::PayloadTooLarge("test".to_string()),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                AppError::UnsupportedMediaType("test".to_string()),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
+            (
+                AppError::Upstream("test".to_string()),
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                AppError::ServiceUnavailable("test".to_string()),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (AppError::DeadlineExceeded, StatusCode::GATEWAY_TIMEOUT),
+            (
+                AppError::Locked(Duration::from_secs(60)),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
             (
                 AppError::Internal("test".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -176,11 +357,23 @@ mod tests {
                 AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
                 AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
                 AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+                AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg.clone()),
+                AppError::UnsupportedMediaType(msg) => {
+                    (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg.clone())
+                }
                 AppError::Database(_) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
                 ),
                 AppError::Jwt(_) => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
+                AppError::Upstream(msg) => (StatusCode::BAD_GATEWAY, msg.clone()),
+                AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone()),
+                AppError::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, error.to_string()),
+                AppError::Locked(_) => (StatusCode::TOO_MANY_REQUESTS, error.to_string()),
+                AppError::Storage(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                ),
                 AppError::Internal(_) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "Internal server error".to_string(),
@@ -190,6 +383,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_locked_response_has_retry_after() {
+        let response = AppError::Locked(Duration::from_secs(90)).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "90");
+    }
+
+    #[test]
+    fn test_query_timeout_is_a_gateway_timeout() {
+        let timed_out = std::io::Error::new(std::io::ErrorKind::TimedOut, "query timed out");
+        let response = AppError::Database(sqlx::Error::Io(timed_out)).into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let response = AppError::Database(sqlx::Error::Io(reset)).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[test]
     fn test_app_result_ok() {
         fn returns_ok() -> AppResult<i32> {
@@ -0,0 +1,48 @@
+use std::convert::Infallible;
+use std::marker::PhantomData;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::AppState;
+
+pub trait FlagName {
+    const NAME: &'static str;
+}
+
+macro_rules! feature_flag {
+    ($ty:ident, $name:literal) => {
+        pub struct $ty;
+
+        impl FlagName for $ty {
+            const NAME: &'static str = $name;
+        }
+    };
+}
+
+feature_flag!(NewFeed, "new_feed");
+
+/// Evaluates flag `F` for the current request, e.g. `new_feed: Flag<NewFeed>`.
+pub struct Flag<F: FlagName> {
+    enabled: bool,
+    _flag: PhantomData<F>,
+}
+
+impl<F: FlagName> Flag<F> {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl<F: FlagName> FromRequestParts<AppState> for Flag<F> {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Flag {
+            enabled: state.feature_flags.is_enabled(F::NAME),
+            _flag: PhantomData,
+        })
+    }
+}
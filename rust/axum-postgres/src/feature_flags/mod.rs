@@ -0,0 +1,240 @@
+mod extractor;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+use tracing::{Instrument, Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    error::{AppError, AppResult},
+    models::{
+        ALL_ENVIRONMENTS, Actor, AuditEntry, FeatureFlag, FeatureFlagDto, FeatureFlagsResponse,
+    },
+    repository::FeatureFlagRepository,
+    services::AuditRecorder,
+    telemetry::FEATURE_FLAG_EVALUATIONS,
+};
+
+pub use extractor::{Flag, NewFeed};
+
+#[derive(Clone)]
+pub struct FeatureFlags {
+    repo: FeatureFlagRepository,
+    audit: AuditRecorder,
+    environment: String,
+    cache: Arc<RwLock<HashMap<String, FeatureFlag>>>,
+}
+
+impl FeatureFlags {
+    pub fn new(repo: FeatureFlagRepository, audit: AuditRecorder, environment: &str) -> Self {
+        Self {
+            repo,
+            audit,
+            environment: environment.to_string(),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    #[instrument(name = "feature_flags.refresh", skip(self))]
+    pub async fn refresh(&self) -> AppResult<usize> {
+        let rows = self.repo.list_for_environment(&self.environment).await?;
+        let resolved = resolve(rows, &self.environment);
+        let count = resolved.len();
+
+        *self.cache.write().expect("feature flag cache poisoned") = resolved;
+
+        tracing::debug!(flags = count, "Feature flags refreshed");
+
+        Ok(count)
+    }
+
+    pub fn spawn_refresh(&self, interval: Duration) -> JoinHandle<()> {
+        let flags = self.clone();
+
+        tokio::spawn(
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = flags.refresh().await {
+                        tracing::warn!(error = %e, "Failed to refresh feature flags");
+                    }
+                }
+            }
+            .instrument(tracing::info_span!("feature_flags.refresher")),
+        )
+    }
+
+    /// Unknown flags evaluate to `false`. Every evaluation is recorded on the
+    /// current span so traces can be grouped by the variant a request saw.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let enabled = self
+            .cache
+            .read()
+            .expect("feature flag cache poisoned")
+            .get(name)
+            .is_some_and(|flag| flag.enabled);
+
+        Span::current().set_attribute(format!("feature_flag.{}", name), enabled);
+        FEATURE_FLAG_EVALUATIONS.add(
+            1,
+            &[
+                KeyValue::new("feature_flag.key", name.to_string()),
+                KeyValue::new("feature_flag.result.value", enabled),
+            ],
+        );
+
+        enabled
+    }
+
+    pub fn list(&self) -> FeatureFlagsResponse {
+        let cache = self.cache.read().expect("feature flag cache poisoned");
+        let mut flags: Vec<FeatureFlagDto> = cache.values().cloned().map(to_dto).collect();
+        flags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        FeatureFlagsResponse {
+            environment: self.environment.clone(),
+            flags,
+        }
+    }
+
+    #[instrument(name = "feature_flags.set", skip(self, description))]
+    pub async fn set(
+        &self,
+        name: &str,
+        environment: Option<&str>,
+        enabled: bool,
+        description: Option<&str>,
+    ) -> AppResult<FeatureFlagDto> {
+        if !is_valid_name(name) {
+            return Err(AppError::Validation(
+                "Flag name must be lowercase letters, digits and underscores".to_string(),
+            ));
+        }
+
+        let environment = environment.unwrap_or(&self.environment);
+        let previous = self.repo.find(name, environment).await?;
+        let flag = self
+            .repo
+            .upsert(name, environment, enabled, description)
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(
+                    Actor::Admin,
+                    "feature_flag.set",
+                    "feature_flag",
+                    format!("{environment}/{name}"),
+                )
+                .changes(
+                    previous.as_ref().map_or(Value::Null, audit_fields),
+                    audit_fields(&flag),
+                ),
+            )
+            .await;
+
+        self.refresh().await?;
+
+        tracing::info!(flag = name, environment, enabled, "Feature flag updated");
+
+        Ok(to_dto(flag))
+    }
+}
+
+fn audit_fields(flag: &FeatureFlag) -> Value {
+    json!({ "enabled": flag.enabled, "description": flag.description })
+}
+
+/// Environment-specific rows override the `*` default for the same flag.
+fn resolve(rows: Vec<FeatureFlag>, environment: &str) -> HashMap<String, FeatureFlag> {
+    let mut resolved: HashMap<String, FeatureFlag> = HashMap::new();
+
+    for row in rows {
+        if row.environment != environment && row.environment != ALL_ENVIRONMENTS {
+            continue;
+        }
+        let replace = resolved
+            .get(&row.name)
+            .is_none_or(|existing| existing.environment == ALL_ENVIRONMENTS);
+        if replace {
+            resolved.insert(row.name.clone(), row);
+        }
+    }
+
+    resolved
+}
+
+fn to_dto(flag: FeatureFlag) -> FeatureFlagDto {
+    FeatureFlagDto {
+        name: flag.name,
+        enabled: flag.enabled,
+        source: flag.environment,
+        description: flag.description,
+        updated_at: flag.updated_at,
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 100
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    fn flag(name: &str, environment: &str, enabled: bool) -> FeatureFlag {
+        FeatureFlag {
+            name: name.to_string(),
+            environment: environment.to_string(),
+            enabled,
+            description: String::new(),
+            updated_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_resolve_prefers_environment_row() {
+        let rows = vec![
+            flag("new_feed", "*", false),
+            flag("new_feed", "production", true),
+        ];
+        let resolved = resolve(rows, "production");
+        assert!(resolved["new_feed"].enabled);
+        assert_eq!(resolved["new_feed"].environment, "production");
+
+        let rows = vec![
+            flag("new_feed", "production", true),
+            flag("new_feed", "*", false),
+        ];
+        let resolved = resolve(rows, "production");
+        assert!(resolved["new_feed"].enabled);
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_default() {
+        let rows = vec![flag("new_feed", "*", true), flag("other", "staging", true)];
+        let resolved = resolve(rows, "production");
+        assert!(resolved["new_feed"].enabled);
+        assert!(!resolved.contains_key("other"));
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("new_feed"));
+        assert!(is_valid_name("v2_search"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("New-Feed"));
+    }
+}
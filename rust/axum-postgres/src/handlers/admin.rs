@@ -0,0 +1,115 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde_json::json;
+
+use crate::{
+    AppState,
+    config::EffectiveConfig,
+    error::AppResult,
+    middleware::AdminAuth,
+    models::{
+        Actor, AuditEntry, AuditLogQuery, AuditLogResponse, CreateOrganizationInput,
+        FeatureFlagDto, FeatureFlagsResponse, Organization, OrganizationsResponse,
+        UpdateFeatureFlagInput,
+    },
+    telemetry::{TelemetrySettings, UpdateTelemetryInput},
+};
+use mailer::EmailJob;
+
+pub async fn get_config(State(state): State<AppState>, _admin: AdminAuth) -> Json<EffectiveConfig> {
+    Json(state.config.effective())
+}
+
+pub async fn list_feature_flags(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Json<FeatureFlagsResponse> {
+    Json(state.feature_flags.list())
+}
+
+pub async fn update_feature_flag(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(name): Path<String>,
+    Json(input): Json<UpdateFeatureFlagInput>,
+) -> AppResult<Json<FeatureFlagDto>> {
+    let flag = state
+        .feature_flags
+        .set(
+            &name,
+            input.environment.as_deref(),
+            input.enabled,
+            input.description.as_deref(),
+        )
+        .await?;
+
+    Ok(Json(flag))
+}
+
+pub async fn get_telemetry(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Json<TelemetrySettings> {
+    Json(state.telemetry.settings())
+}
+
+pub async fn update_telemetry(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(input): Json<UpdateTelemetryInput>,
+) -> AppResult<Json<TelemetrySettings>> {
+    let before = state.telemetry.settings();
+    let settings = state.telemetry.update(input)?;
+
+    // The telemetry handle is built before the database pool exists, so
+    // its changes are audited here rather than inside it.
+    state
+        .audit
+        .record(
+            AuditEntry::new(Actor::Admin, "telemetry.update", "telemetry", "settings")
+                .changes(json!(before), json!(settings)),
+        )
+        .await;
+
+    Ok(Json(settings))
+}
+
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Query(query): Query<AuditLogQuery>,
+) -> AppResult<Json<AuditLogResponse>> {
+    Ok(Json(state.audit.list(query).await?))
+}
+
+pub async fn create_organization(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(input): Json<CreateOrganizationInput>,
+) -> AppResult<(StatusCode, Json<Organization>)> {
+    let org = state.auth_service.create_organization(input).await?;
+
+    Ok((StatusCode::CREATED, Json(org)))
+}
+
+pub async fn list_organizations(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> AppResult<Json<OrganizationsResponse>> {
+    Ok(Json(state.auth_service.list_organizations().await?))
+}
+
+/// Queues any template to any address, to check the mail transport end to
+/// end; the worker sends it.
+pub async fn send_email(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(email): Json<EmailJob>,
+) -> AppResult<(StatusCode, Json<serde_json::Value>)> {
+    let job_id = state.job_queue.enqueue_email(email).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "job_id": job_id }))))
+}
@@ -1,86 +1,260 @@
+use authz::Authors;
 use axum::{
-    Json,
+    Extension,
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
 };
 
 use crate::{
     AppState,
+    encoding::{Decoded, Encoded, Encoding},
     error::AppResult,
+    feature_flags::{Flag, NewFeed},
     middleware::{AuthUser, OptionalAuthUser},
     models::{
-        ArticleResponse, ArticlesResponse, CreateArticleInput, ListArticlesQuery,
-        UpdateArticleInput,
+        AddAuthorInput, ArticleResponse, ArticlesResponse, CreateArticleInput, ExportArticlesQuery,
+        FavoritedLookupInput, FavoritedResponse, ListArticlesQuery, SuggestArticlesQuery,
+        SuggestionsResponse, TransferArticleInput, TrendingArticlesQuery, UpdateArticleInput,
+        UserArticlesQuery,
     },
+    services::{EconomicContextQuery, EconomicContextResponse},
 };
 
 pub async fn create_article(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
-    Json(input): Json<CreateArticleInput>,
-) -> AppResult<(StatusCode, Json<ArticleResponse>)> {
-    let response = state.article_service.create(user_id, input).await?;
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Decoded(input): Decoded<CreateArticleInput>,
+) -> AppResult<(StatusCode, Encoded<ArticleResponse>)> {
+    let response = state.article_service.create(org, user_id, input).await?;
 
-    Ok((StatusCode::CREATED, Json(response)))
+    Ok((StatusCode::CREATED, encoding.encode(response)))
 }
 
 pub async fn get_article(
     State(state): State<AppState>,
-    OptionalAuthUser(user_id): OptionalAuthUser,
+    encoding: Encoding,
+    OptionalAuthUser { user_id, org }: OptionalAuthUser,
     Path(slug): Path<String>,
-) -> AppResult<Json<ArticleResponse>> {
-    let response = state.article_service.get(&slug, user_id).await?;
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state.article_service.get(org, &slug, user_id).await?;
 
-    Ok(Json(response))
+    Ok(encoding.encode(response))
+}
+
+pub async fn get_economic_context(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { org, .. }: AuthUser,
+    Path(slug): Path<String>,
+    Query(query): Query<EconomicContextQuery>,
+) -> AppResult<Encoded<EconomicContextResponse>> {
+    let response = state
+        .economic_context_service
+        .get(org, &slug, query)
+        .await?;
+
+    Ok(encoding.encode(response))
 }
 
 pub async fn list_articles(
     State(state): State<AppState>,
-    OptionalAuthUser(user_id): OptionalAuthUser,
+    encoding: Encoding,
+    OptionalAuthUser { user_id, org }: OptionalAuthUser,
+    new_feed: Flag<NewFeed>,
     Query(query): Query<ListArticlesQuery>,
-) -> AppResult<Json<ArticlesResponse>> {
-    let response = state.article_service.list(query, user_id).await?;
+) -> AppResult<Encoded<ArticlesResponse>> {
+    let response = state
+        .article_service
+        .list(org, query, user_id, new_feed.is_enabled())
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn list_user_articles(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Query(query): Query<UserArticlesQuery>,
+) -> AppResult<Encoded<ArticlesResponse>> {
+    let response = state
+        .article_service
+        .list_by_user(org, user_id, query)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
 
-    Ok(Json(response))
+pub async fn list_user_favorites(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Query(query): Query<UserArticlesQuery>,
+) -> AppResult<Encoded<ArticlesResponse>> {
+    let response = state
+        .article_service
+        .list_favorites(org, user_id, query)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn trending_articles(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    OptionalAuthUser { user_id, org }: OptionalAuthUser,
+    Query(query): Query<TrendingArticlesQuery>,
+) -> AppResult<Encoded<ArticlesResponse>> {
+    let response = state.article_service.trending(org, query, user_id).await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn suggest_articles(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    OptionalAuthUser { org, .. }: OptionalAuthUser,
+    Query(query): Query<SuggestArticlesQuery>,
+) -> AppResult<Encoded<SuggestionsResponse>> {
+    let response = state
+        .suggest_service
+        .suggest(org, &query.q, query.limit)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn export_articles(
+    State(state): State<AppState>,
+    AuthUser { org, .. }: AuthUser,
+    Query(query): Query<ExportArticlesQuery>,
+) -> Response {
+    let format = query.format;
+    let body = Body::from_stream(state.article_service.export(org, format));
+    let disposition = format!("attachment; filename=\"articles.{}\"", format.as_str());
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
 }
 
 pub async fn update_article(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
     Path(slug): Path<String>,
-    Json(input): Json<UpdateArticleInput>,
-) -> AppResult<Json<ArticleResponse>> {
-    let response = state.article_service.update(&slug, user_id, input).await?;
+    Decoded(input): Decoded<UpdateArticleInput>,
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state
+        .article_service
+        .update(org, &slug, &authors, user_id, input)
+        .await?;
 
-    Ok(Json(response))
+    Ok(encoding.encode(response))
 }
 
 pub async fn delete_article(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
     Path(slug): Path<String>,
 ) -> AppResult<StatusCode> {
-    state.article_service.delete(&slug, user_id).await?;
+    state
+        .article_service
+        .delete(org, &slug, &authors, user_id)
+        .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub async fn add_article_author(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
+    Path(slug): Path<String>,
+    Decoded(input): Decoded<AddAuthorInput>,
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state
+        .article_service
+        .add_author(org, &slug, &authors, user_id, input)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn remove_article_author(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
+    Path((slug, author_id)): Path<(String, i32)>,
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state
+        .article_service
+        .remove_author(org, &slug, &authors, user_id, author_id)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn transfer_article(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
+    Path(slug): Path<String>,
+    Decoded(input): Decoded<TransferArticleInput>,
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state
+        .article_service
+        .transfer(org, &slug, &authors, user_id, input)
+        .await?;
+
+    Ok(encoding.encode(response))
+}
+
 pub async fn favorite_article(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
     Path(slug): Path<String>,
-) -> AppResult<Json<ArticleResponse>> {
-    let response = state.article_service.favorite(&slug, user_id).await?;
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state.article_service.favorite(org, &slug, user_id).await?;
+
+    Ok(encoding.encode(response))
+}
+
+pub async fn favorited_articles(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Decoded(input): Decoded<FavoritedLookupInput>,
+) -> AppResult<Encoded<FavoritedResponse>> {
+    let response = state.article_service.favorited(org, user_id, input).await?;
 
-    Ok(Json(response))
+    Ok(encoding.encode(response))
 }
 
 pub async fn unfavorite_article(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
     Path(slug): Path<String>,
-) -> AppResult<Json<ArticleResponse>> {
-    let response = state.article_service.unfavorite(&slug, user_id).await?;
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state
+        .article_service
+        .unfavorite(org, &slug, user_id)
+        .await?;
 
-    Ok(Json(response))
+    Ok(encoding.encode(response))
 }
@@ -1,40 +1,217 @@
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use serde_json::{Value, json};
 
 use crate::{
     AppState,
     error::AppResult,
-    middleware::AuthUser,
-    models::{LoginInput, ProfileResponse, RegisterInput, UserResponse},
+    middleware::{AuthUser, JwtAuthUser, OptionalAuthUser},
+    models::{
+        ApiTokensResponse, ClientInfo, CreateApiTokenInput, CreatedApiTokenResponse,
+        DeletedAccountResponse, LoginInput, LoginOutcome, ProfileResponse, PublicProfileResponse,
+        RecoveryCodesResponse, RegisterInput, SessionsResponse, TwoFactorChallengeResponse,
+        TwoFactorCodeInput, TwoFactorLoginInput, TwoFactorSetupResponse, UserResponse,
+    },
+    services::Principal,
 };
 
 pub async fn register(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(input): Json<RegisterInput>,
 ) -> AppResult<(StatusCode, Json<UserResponse>)> {
-    let user = state.auth_service.register(input).await?;
+    let user = state.auth_service.register(input, &client).await?;
 
     Ok((StatusCode::CREATED, Json(UserResponse { user })))
 }
 
 pub async fn login(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(input): Json<LoginInput>,
+) -> AppResult<Response> {
+    Ok(match state.auth_service.login(input, &client).await? {
+        LoginOutcome::Authenticated(user) => Json(UserResponse { user }).into_response(),
+        LoginOutcome::TwoFactorRequired(two_factor) => {
+            Json(TwoFactorChallengeResponse { two_factor }).into_response()
+        }
+    })
+}
+
+pub async fn login_two_factor(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(input): Json<TwoFactorLoginInput>,
 ) -> AppResult<Json<UserResponse>> {
-    let user = state.auth_service.login(input).await?;
+    let user = state.auth_service.login_two_factor(input, &client).await?;
 
     Ok(Json(UserResponse { user }))
 }
 
+pub async fn setup_two_factor(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, org, .. }: JwtAuthUser,
+) -> AppResult<Json<TwoFactorSetupResponse>> {
+    let setup = state
+        .auth_service
+        .setup_two_factor(Principal { user_id, org })
+        .await?;
+
+    Ok(Json(setup))
+}
+
+pub async fn verify_two_factor(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, org, .. }: JwtAuthUser,
+    Json(input): Json<TwoFactorCodeInput>,
+) -> AppResult<Json<RecoveryCodesResponse>> {
+    let codes = state
+        .auth_service
+        .enable_two_factor(Principal { user_id, org }, &input.code)
+        .await?;
+
+    Ok(Json(codes))
+}
+
 pub async fn get_user(
     State(state): State<AppState>,
-    AuthUser(user_id): AuthUser,
+    AuthUser { user_id, org }: AuthUser,
 ) -> AppResult<Json<ProfileResponse>> {
-    let user = state.auth_service.get_user(user_id).await?;
+    let user = state
+        .auth_service
+        .get_user(Principal { user_id, org })
+        .await?;
 
     Ok(Json(ProfileResponse::from(user)))
 }
 
-pub async fn logout() -> Json<Value> {
-    Json(json!({ "message": "Logged out successfully" }))
+pub async fn delete_user(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, org, .. }: JwtAuthUser,
+) -> AppResult<(StatusCode, Json<DeletedAccountResponse>)> {
+    let deleted = state
+        .account_service
+        .delete(Principal { user_id, org })
+        .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(deleted)))
+}
+
+pub async fn export_user(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, org, .. }: JwtAuthUser,
+) -> AppResult<impl IntoResponse> {
+    let archive = state
+        .account_service
+        .export(Principal { user_id, org })
+        .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"account.json\"",
+            ),
+        ],
+        Body::from_stream(archive),
+    ))
+}
+
+/// Anonymous callers see the default organization's users.
+pub async fn get_profile(
+    State(state): State<AppState>,
+    OptionalAuthUser { org, .. }: OptionalAuthUser,
+    Path(username): Path<String>,
+) -> AppResult<Json<PublicProfileResponse>> {
+    let user = state.auth_service.get_profile(org, &username).await?;
+
+    Ok(Json(PublicProfileResponse::from(user)))
+}
+
+/// Revokes the session of a JWT sent with it; without one there's
+/// nothing to revoke, and it answers the same.
+pub async fn logout(
+    State(state): State<AppState>,
+    jwt: Option<JwtAuthUser>,
+) -> AppResult<Json<Value>> {
+    if let Some(JwtAuthUser {
+        user_id,
+        org,
+        session_id,
+    }) = jwt
+    {
+        state
+            .auth_service
+            .logout(Principal { user_id, org }, session_id)
+            .await?;
+    }
+
+    Ok(Json(json!({ "message": "Logged out successfully" })))
+}
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    JwtAuthUser {
+        user_id,
+        org,
+        session_id,
+    }: JwtAuthUser,
+) -> AppResult<Json<SessionsResponse>> {
+    let sessions = state
+        .auth_service
+        .list_sessions(Principal { user_id, org }, session_id)
+        .await?;
+
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, org, .. }: JwtAuthUser,
+    Path(session_id): Path<i32>,
+) -> AppResult<StatusCode> {
+    state
+        .auth_service
+        .revoke_session(Principal { user_id, org }, session_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, .. }: JwtAuthUser,
+    Json(input): Json<CreateApiTokenInput>,
+) -> AppResult<(StatusCode, Json<CreatedApiTokenResponse>)> {
+    let token = state.auth_service.create_api_token(user_id, input).await?;
+
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+pub async fn list_api_tokens(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, .. }: JwtAuthUser,
+) -> AppResult<Json<ApiTokensResponse>> {
+    let tokens = state.auth_service.list_api_tokens(user_id).await?;
+
+    Ok(Json(tokens))
+}
+
+pub async fn revoke_api_token(
+    State(state): State<AppState>,
+    JwtAuthUser { user_id, .. }: JwtAuthUser,
+    Path(token_id): Path<i32>,
+) -> AppResult<StatusCode> {
+    state
+        .auth_service
+        .revoke_api_token(user_id, token_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
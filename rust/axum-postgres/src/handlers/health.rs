@@ -1,31 +1,31 @@
 use axum::{Json, extract::State, http::StatusCode};
-use serde_json::{Value, json};
-use sqlx::Row;
+use health::Health;
+use sqlx::PgPool;
 
 use crate::AppState;
+use crate::database::Persistent;
 
-pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
-    let db_status = sqlx::query("SELECT 1 as one")
-        .fetch_one(&state.pool)
-        .await
-        .map(|row: sqlx::postgres::PgRow| {
-            let _: i32 = row.get("one");
-            "healthy"
-        })
-        .unwrap_or("unhealthy");
+const SERVICE: &str = "rust-axum-postgres";
+
+/// Liveness: answers without touching the database.
+pub async fn liveness() -> (StatusCode, Json<Health>) {
+    respond(Health::live(SERVICE))
+}
 
-    let status = if db_status == "healthy" {
-        StatusCode::OK
-    } else {
-        StatusCode::SERVICE_UNAVAILABLE
-    };
+/// Readiness, and `/api/health`: 503 while the database is unreachable.
+pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<Health>) {
+    respond(Health::ready(SERVICE, ping(&state.pool)).await)
+}
+
+async fn ping(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1")
+        .persistent_statement()
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
 
-    (
-        status,
-        Json(json!({
-            "status": if status == StatusCode::OK { "ok" } else { "error" },
-            "database": db_status,
-            "service": "rust-axum-postgres",
-        })),
-    )
+fn respond(health: Health) -> (StatusCode, Json<Health>) {
+    let status = StatusCode::from_u16(health.status_code()).expect("200 or 503");
+    (status, Json(health))
 }
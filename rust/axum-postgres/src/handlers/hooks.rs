@@ -0,0 +1,28 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+
+use crate::{AppState, error::AppResult, services::WebhookReceipt};
+
+/// 202 for a new delivery, 200 for one already queued, so the sender stops
+/// retrying either way.
+pub async fn receive_provider_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AppResult<(StatusCode, Json<WebhookReceipt>)> {
+    let receipt = state
+        .webhook_service
+        .receive("provider", &headers, &body)
+        .await?;
+    let status = if receipt.duplicate {
+        StatusCode::OK
+    } else {
+        StatusCode::ACCEPTED
+    };
+
+    Ok((status, Json(receipt)))
+}
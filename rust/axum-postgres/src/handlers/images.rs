@@ -0,0 +1,125 @@
+use authz::Authors;
+use axum::{
+    Extension, Json,
+    extract::{Multipart, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use bytes::{Bytes, BytesMut};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    error::{AppError, AppResult},
+    middleware::AuthUser,
+    services::{ImageKind, ImageResponse, too_large},
+    storage::Storage,
+};
+
+const IMAGE_FIELD: &str = "image";
+
+#[derive(Debug, Deserialize)]
+pub struct SignedImageQuery {
+    pub expires: i64,
+    pub sig: String,
+}
+
+pub async fn upload_avatar(
+    State(state): State<AppState>,
+    AuthUser { user_id, org }: AuthUser,
+    multipart: Multipart,
+) -> AppResult<(StatusCode, Json<ImageResponse>)> {
+    let (content_type, data) = read_image_field(multipart, state.image_service.max_bytes()).await?;
+    let response = state
+        .image_service
+        .upload_avatar(org, user_id, content_type.as_deref(), data)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn upload_cover(
+    State(state): State<AppState>,
+    AuthUser { user_id, org }: AuthUser,
+    Extension(authors): Extension<Authors>,
+    Path(slug): Path<String>,
+    multipart: Multipart,
+) -> AppResult<(StatusCode, Json<ImageResponse>)> {
+    let (content_type, data) = read_image_field(multipart, state.image_service.max_bytes()).await?;
+    let response = state
+        .image_service
+        .upload_cover(org, &slug, &authors, user_id, content_type.as_deref(), data)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn serve_image(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    Query(query): Query<SignedImageQuery>,
+) -> AppResult<Response> {
+    // S3 objects are served by the bucket via presigned URLs; only the local
+    // backend routes image reads through the API.
+    let Storage::Local(local) = &state.storage else {
+        return Err(AppError::NotFound("Image not found".to_string()));
+    };
+
+    if !local.verify(&key, query.expires, &query.sig) {
+        return Err(AppError::Forbidden);
+    }
+
+    let data = local.get(&key).await?;
+    let content_type = key
+        .rsplit_once('.')
+        .and_then(|(_, ext)| ImageKind::from_extension(ext))
+        .map(|kind| kind.content_type())
+        .unwrap_or("application/octet-stream");
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CACHE_CONTROL, "private, max-age=300".to_string()),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// Reads the `image` field chunk by chunk so an oversized upload is rejected
+/// as soon as it crosses the limit instead of being buffered in full.
+async fn read_image_field(
+    mut multipart: Multipart,
+    max_bytes: usize,
+) -> AppResult<(Option<String>, Bytes)> {
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(format!("Invalid multipart body: {}", e)))?
+    {
+        if field.name() != Some(IMAGE_FIELD) {
+            continue;
+        }
+
+        let content_type = field.content_type().map(|ct| ct.to_string());
+        let mut data = BytesMut::new();
+
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| AppError::Validation(format!("Invalid multipart body: {}", e)))?
+        {
+            if data.len() + chunk.len() > max_bytes {
+                return Err(too_large(max_bytes));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        return Ok((content_type, data.freeze()));
+    }
+
+    Err(AppError::Validation(format!(
+        "Multipart field '{}' is required",
+        IMAGE_FIELD
+    )))
+}
@@ -1,10 +1,26 @@
+mod admin;
 mod articles;
 mod auth;
 mod health;
+mod hooks;
+mod images;
+pub mod v2;
 
+pub use admin::{
+    create_organization, get_config, get_telemetry, list_audit_log, list_feature_flags,
+    list_organizations, send_email, update_feature_flag, update_telemetry,
+};
 pub use articles::{
-    create_article, delete_article, favorite_article, get_article, list_articles,
-    unfavorite_article, update_article,
+    add_article_author, create_article, delete_article, export_articles, favorite_article,
+    favorited_articles, get_article, get_economic_context, list_articles, list_user_articles,
+    list_user_favorites, remove_article_author, suggest_articles, transfer_article,
+    trending_articles, unfavorite_article, update_article,
+};
+pub use auth::{
+    create_api_token, delete_user, export_user, get_profile, get_user, list_api_tokens,
+    list_sessions, login, login_two_factor, logout, register, revoke_api_token, revoke_session,
+    setup_two_factor, verify_two_factor,
 };
-pub use auth::{get_user, login, logout, register};
-pub use health::health_check;
+pub use health::{health_check, liveness};
+pub use hooks::receive_provider_webhook;
+pub use images::{serve_image, upload_avatar, upload_cover};
@@ -0,0 +1,102 @@
+//! `/api/v2`: the same services as the v1 handlers, converted to the v2
+//! models on the way out. Errors get the v2 shape from
+//! [`structured_errors`](crate::middleware::structured_errors).
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    AppState,
+    encoding::{Decoded, Encoded, Encoding},
+    error::{AppError, AppResult},
+    middleware::{AuthUser, OptionalAuthUser},
+    models::{
+        ArticleCursor, ClientInfo, CreateArticleInput, LoginInput, LoginOutcome,
+        v2::{
+            ArticleResponse, ArticlesResponse, ListArticlesQuery, TwoFactorChallengeResponse,
+            TwoFactorLoginInput, UserResponse,
+        },
+    },
+};
+
+pub async fn login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(input): Json<LoginInput>,
+) -> AppResult<Response> {
+    Ok(match state.auth_service.login(input, &client).await? {
+        LoginOutcome::Authenticated(user) => {
+            Json(UserResponse { user: user.into() }).into_response()
+        }
+        LoginOutcome::TwoFactorRequired(challenge) => {
+            Json(TwoFactorChallengeResponse::from(challenge)).into_response()
+        }
+    })
+}
+
+pub async fn login_two_factor(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(input): Json<TwoFactorLoginInput>,
+) -> AppResult<Json<UserResponse>> {
+    let user = state
+        .auth_service
+        .login_two_factor(input.into(), &client)
+        .await?;
+
+    Ok(Json(UserResponse { user: user.into() }))
+}
+
+pub async fn list_articles(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    OptionalAuthUser { user_id, org }: OptionalAuthUser,
+    Query(query): Query<ListArticlesQuery>,
+) -> AppResult<Encoded<ArticlesResponse>> {
+    let after = match query.cursor.as_deref() {
+        Some(cursor) => Some(
+            ArticleCursor::decode(cursor)
+                .ok_or_else(|| AppError::Validation("Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+    let page = state
+        .article_service
+        .list_page(org, query.limit, after, query.author.as_deref(), user_id)
+        .await?;
+
+    Ok(encoding.encode(page.into()))
+}
+
+pub async fn get_article(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    OptionalAuthUser { user_id, org }: OptionalAuthUser,
+    Path(slug): Path<String>,
+) -> AppResult<Encoded<ArticleResponse>> {
+    let response = state.article_service.get(org, &slug, user_id).await?;
+
+    Ok(encoding.encode(ArticleResponse {
+        article: response.article.into(),
+    }))
+}
+
+pub async fn create_article(
+    State(state): State<AppState>,
+    encoding: Encoding,
+    AuthUser { user_id, org }: AuthUser,
+    Decoded(input): Decoded<CreateArticleInput>,
+) -> AppResult<(StatusCode, Encoded<ArticleResponse>)> {
+    let response = state.article_service.create(org, user_id, input).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        encoding.encode(ArticleResponse {
+            article: response.article.into(),
+        }),
+    ))
+}
@@ -0,0 +1,165 @@
+use job_worker::Job;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::instrument;
+
+use crate::{
+    models::{Actor, AuditEntry, DeletionPolicy, OrgId},
+    repository::{ArticleRepository, FavoriteRepository, UserRepository},
+    services::AuditRecorder,
+    storage::{Storage, is_object_key},
+};
+
+pub const ACCOUNT_ERASURE_JOB_KIND: &str = "account_erasure";
+
+/// Articles deleted per statement, so erasing a prolific author doesn't hold
+/// one long transaction.
+const ARTICLE_BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountErasurePayload {
+    pub org_id: OrgId,
+    pub user_id: i32,
+    pub policy: DeletionPolicy,
+    /// The avatar before the account row was anonymized.
+    pub avatar: String,
+}
+
+/// The part of `DELETE /api/user` that can take a while: favorites, the
+/// avatar and, under [`DeletionPolicy::Delete`], the user's articles and their
+/// cover images. Every step can run again, so a retried job picks up where
+/// a failed one stopped.
+#[derive(Clone)]
+pub struct AccountErasureHandler {
+    user_repo: UserRepository,
+    article_repo: ArticleRepository,
+    favorite_repo: FavoriteRepository,
+    storage: Storage,
+    audit: AuditRecorder,
+}
+
+impl AccountErasureHandler {
+    pub fn new(
+        user_repo: UserRepository,
+        article_repo: ArticleRepository,
+        favorite_repo: FavoriteRepository,
+        storage: Storage,
+        audit: AuditRecorder,
+    ) -> Self {
+        Self {
+            user_repo,
+            article_repo,
+            favorite_repo,
+            storage,
+            audit,
+        }
+    }
+
+    /// Fails, to be retried, until the account row is anonymized: the job is
+    /// enqueued just before that.
+    #[instrument(
+        name = "job.account_erasure.handle",
+        skip(self, job),
+        fields(job_id = job.id, user_id = tracing::field::Empty, policy = tracing::field::Empty)
+    )]
+    pub async fn handle(&self, job: &Job) -> Result<(), anyhow::Error> {
+        let payload: AccountErasurePayload = serde_json::from_value(job.payload.clone())?;
+        let AccountErasurePayload {
+            org_id: org,
+            user_id,
+            policy,
+            ..
+        } = payload;
+        let span = tracing::Span::current();
+        span.record("user_id", user_id);
+        span.record("policy", policy.as_str());
+
+        if !self.user_repo.is_deleted(org, user_id).await? {
+            anyhow::bail!("user {user_id} is not deleted yet");
+        }
+
+        let favorites = self.favorite_repo.delete_all_by_user(org, user_id).await?;
+        self.delete_image(&payload.avatar).await?;
+
+        let (mut articles, mut co_authorships) = (0, 0);
+        if policy == DeletionPolicy::Delete {
+            loop {
+                let covers = self
+                    .article_repo
+                    .delete_batch_by_owner(org, user_id, ARTICLE_BATCH_SIZE)
+                    .await?;
+                articles += covers.len();
+                for cover in &covers {
+                    // The article row is gone, so a retry wouldn't find this
+                    // cover again; log it rather than fail.
+                    if let Err(e) = self.delete_image(cover).await {
+                        tracing::warn!(key = %cover, error = %e, "Failed to delete cover image");
+                    }
+                }
+                if (covers.len() as i64) < ARTICLE_BATCH_SIZE {
+                    break;
+                }
+            }
+            co_authorships = self
+                .article_repo
+                .remove_co_authorships(org, user_id)
+                .await?;
+        }
+
+        self.audit
+            .record(
+                AuditEntry::new(Actor::User(user_id), "user.erase", "user", user_id).changes(
+                    Value::Null,
+                    json!({
+                        "policy": policy,
+                        "favorites_removed": favorites,
+                        "articles_deleted": articles,
+                        "co_authorships_removed": co_authorships,
+                    }),
+                ),
+            )
+            .await;
+        tracing::info!(
+            user_id,
+            policy = %policy,
+            favorites_removed = favorites,
+            articles_deleted = articles,
+            co_authorships_removed = co_authorships,
+            "Account erased"
+        );
+        Ok(())
+    }
+
+    /// Legacy images are absolute URLs this service doesn't store.
+    async fn delete_image(&self, value: &str) -> Result<(), anyhow::Error> {
+        if !is_object_key(value) {
+            return Ok(());
+        }
+        self.storage.delete(value).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_round_trips() {
+        let payload = AccountErasurePayload {
+            org_id: OrgId(3),
+            user_id: 9,
+            policy: DeletionPolicy::Delete,
+            avatar: "avatars/9/a.png".to_string(),
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            value,
+            json!({"org_id": 3, "user_id": 9, "policy": "delete", "avatar": "avatars/9/a.png"})
+        );
+
+        let parsed: AccountErasurePayload = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.policy, DeletionPolicy::Delete);
+        assert_eq!(parsed.org_id, OrgId(3));
+    }
+}
@@ -0,0 +1,122 @@
+use job_worker::Job;
+use opentelemetry::KeyValue;
+use serde::Deserialize;
+use tracing::instrument;
+
+use crate::{
+    clients::{ReportClient, SummaryRequest},
+    models::OrgId,
+    repository::ArticleRepository,
+    telemetry::{ARTICLES_ENRICHED, org_attribute},
+};
+
+/// Silent reading speed the estimate assumes.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Whole minutes, rounded up, and never 0 so a card always shows one.
+pub fn reading_time_minutes(body: &str) -> i32 {
+    let words = body.split_whitespace().count();
+    words.div_ceil(WORDS_PER_MINUTE).max(1) as i32
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArticleEnrichmentPayload {
+    pub org_id: OrgId,
+    pub article_id: i32,
+}
+
+/// Computes an article's reading time and, when given a report client, asks
+/// ai-report-generator for a two-sentence summary, so the LLM providers,
+/// fallback and cost tracking live in one service.
+#[derive(Clone)]
+pub struct ArticleEnrichmentHandler {
+    repo: ArticleRepository,
+    report_client: Option<ReportClient>,
+}
+
+impl ArticleEnrichmentHandler {
+    pub fn new(repo: ArticleRepository, report_client: Option<ReportClient>) -> Self {
+        Self {
+            repo,
+            report_client,
+        }
+    }
+
+    /// The reading time is written even when the summary fails; the
+    /// error then fails the job so the worker retries the summary.
+    #[instrument(
+        name = "job.article_enrichment.handle",
+        skip(self, job),
+        fields(job_id = job.id, article_id = tracing::field::Empty, summary = tracing::field::Empty)
+    )]
+    pub async fn handle(&self, job: &Job) -> Result<(), anyhow::Error> {
+        let payload: ArticleEnrichmentPayload = serde_json::from_value(job.payload.clone())?;
+        let org = payload.org_id;
+        let span = tracing::Span::current();
+        span.record("article_id", payload.article_id);
+
+        let Some(article) = self.repo.find_by_id(org, payload.article_id).await? else {
+            tracing::info!(
+                article_id = payload.article_id,
+                "Article deleted before enrichment"
+            );
+            return Ok(());
+        };
+        let reading_time = reading_time_minutes(&article.body);
+
+        let summary = match &self.report_client {
+            None => Ok(None),
+            Some(client) => client
+                .summarize(&SummaryRequest {
+                    title: &article.title,
+                    text: &article.body,
+                })
+                .await
+                .map(|summary| Some(summary.summary)),
+        };
+        let outcome = match &summary {
+            Ok(Some(_)) => "generated",
+            Ok(None) => "disabled",
+            Err(_) => "failed",
+        };
+        span.record("summary", outcome);
+        ARTICLES_ENRICHED.add(1, &[org_attribute(org), KeyValue::new("summary", outcome)]);
+
+        let stored = summary.as_ref().ok().and_then(Option::as_deref);
+        self.repo
+            .update_enrichment(org, article.id, reading_time, stored)
+            .await?;
+
+        tracing::info!(
+            article_id = article.id,
+            reading_time_minutes = reading_time,
+            summary = outcome,
+            "Article enriched"
+        );
+
+        summary.map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_time_rounds_up_to_whole_minutes() {
+        assert_eq!(reading_time_minutes(""), 1);
+        assert_eq!(reading_time_minutes("one two three"), 1);
+        assert_eq!(reading_time_minutes(&"word ".repeat(200)), 1);
+        assert_eq!(reading_time_minutes(&"word ".repeat(201)), 2);
+        assert_eq!(reading_time_minutes(&"word\n\n".repeat(1_000)), 5);
+    }
+
+    #[test]
+    fn test_payload_matches_the_enqueued_job() {
+        let payload: ArticleEnrichmentPayload =
+            serde_json::from_value(serde_json::json!({"org_id": 3, "article_id": 9})).unwrap();
+
+        assert_eq!(payload.org_id, OrgId(3));
+        assert_eq!(payload.article_id, 9);
+    }
+}
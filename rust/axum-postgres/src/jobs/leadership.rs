@@ -0,0 +1,212 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use sqlx::{Connection, PgConnection};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::telemetry::LEADERSHIP_IS_LEADER;
+
+/// The first key of the two-key advisory lock; the second is the hashed
+/// election name. sqlx's migration lock takes the single `bigint` form, so
+/// the two never collide.
+const LOCK_NAMESPACE: i32 = 0x4c45_4144;
+
+/// Elects one replica to run the singleton tasks called `name`, like the
+/// trending refresh, through a Postgres advisory lock. The leader holds the
+/// lock on a connection of its own; the others try to take it every
+/// interval, so when the leader exits or loses its connection, Postgres
+/// releases the lock and another replica takes over within one interval.
+#[derive(Clone)]
+pub struct Leadership {
+    name: &'static str,
+    leader: Arc<AtomicBool>,
+}
+
+impl Leadership {
+    /// Starts the election over `database_url`. The lock is session-level,
+    /// so behind pgbouncer in transaction mode this must be the direct URL.
+    pub fn spawn(
+        name: &'static str,
+        database_url: String,
+        interval: Duration,
+    ) -> (Self, JoinHandle<()>) {
+        let leadership = Self {
+            name,
+            leader: Arc::new(AtomicBool::new(false)),
+        };
+        leadership.record(false);
+        let mut election = Election {
+            leadership: leadership.clone(),
+            database_url,
+            conn: None,
+            timeout: interval,
+        };
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                election.poll().await;
+            }
+        });
+        (leadership, task)
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, leader: bool) {
+        LEADERSHIP_IS_LEADER.record(
+            u64::from(leader),
+            &[KeyValue::new("leadership.name", self.name)],
+        );
+    }
+
+    /// Records the poll's outcome and, when it changed hands, a
+    /// `leadership.change` span with the reason as an event.
+    fn set(&self, leader: bool, error: Option<&sqlx::Error>) {
+        self.record(leader);
+        if self.leader.swap(leader, Ordering::Relaxed) == leader {
+            return;
+        }
+        let span = tracing::info_span!(
+            "leadership.change",
+            leadership.name = self.name,
+            leadership.is_leader = leader,
+        );
+        let _entered = span.enter();
+        match error {
+            _ if leader => tracing::info!("Leadership acquired"),
+            Some(e) => tracing::warn!(error = %e, "Leadership lost"),
+            None => tracing::warn!("Leadership lost"),
+        }
+    }
+}
+
+struct Election {
+    leadership: Leadership,
+    database_url: String,
+    conn: Option<PgConnection>,
+    /// How long a poll may take. A leader that can't reach Postgres within
+    /// it steps down; its lock goes once Postgres notices the connection is
+    /// gone, which is what a new leader waits for.
+    timeout: Duration,
+}
+
+impl Election {
+    async fn poll(&mut self) {
+        let result = match tokio::time::timeout(self.timeout, self.try_lead()).await {
+            Ok(result) => result,
+            Err(_) => Err(sqlx::Error::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "leader election timed out",
+            ))),
+        };
+        match result {
+            Ok(leader) => self.leadership.set(leader, None),
+            Err(e) => {
+                // Closing the connection releases the lock, if it was held.
+                self.conn = None;
+                if !self.leadership.is_leader() {
+                    tracing::debug!(error = %e, "Leader election failed");
+                }
+                self.leadership.set(false, Some(&e));
+            }
+        }
+    }
+
+    async fn try_lead(&mut self) -> Result<bool, sqlx::Error> {
+        let conn = match &mut self.conn {
+            Some(conn) => conn,
+            None => self
+                .conn
+                .insert(PgConnection::connect(&self.database_url).await?),
+        };
+        // The lock lasts as long as the session, so while the connection is
+        // alive the leader keeps it.
+        if self.leadership.is_leader() {
+            conn.ping().await?;
+            return Ok(true);
+        }
+        sqlx::query_scalar("SELECT pg_try_advisory_lock($1, hashtext($2))")
+            .bind(LOCK_NAMESPACE)
+            .bind(self.leadership.name)
+            .fetch_one(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::Value;
+
+    use super::*;
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    fn election(leadership: &Leadership) -> Election {
+        Election {
+            leadership: leadership.clone(),
+            database_url: "postgres://localhost:1/db".to_string(),
+            conn: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn leadership() -> Leadership {
+        Leadership {
+            name: "test",
+            leader: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_is_not_leader() {
+        let spans = SpanCapture::start();
+        let leadership = leadership();
+
+        election(&leadership).poll().await;
+
+        assert!(!leadership.is_leader());
+        assert!(spans.spans().is_empty());
+        assert_metric_recorded(
+            "leadership.is_leader",
+            &[KeyValue::new("leadership.name", "test")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leader_steps_down_when_its_connection_fails() {
+        let spans = SpanCapture::start();
+        let leadership = leadership();
+        leadership.set(true, None);
+
+        election(&leadership).poll().await;
+
+        assert!(!leadership.is_leader());
+        let changes: Vec<_> = spans
+            .spans()
+            .into_iter()
+            .filter(|s| s.name == "leadership.change")
+            .collect();
+        assert_eq!(changes.len(), 2);
+        assert_attributes(
+            &changes[1],
+            &[
+                KeyValue::new("leadership.name", "test"),
+                KeyValue::new("leadership.is_leader", false),
+            ],
+        );
+        let lost = changes[1].events.iter().next().unwrap();
+        assert_eq!(lost.name, "Leadership lost");
+        assert!(
+            lost.attributes
+                .iter()
+                .any(|kv| kv.key.as_str() == "error" && matches!(kv.value, Value::String(_)))
+        );
+    }
+}
@@ -1,7 +1,35 @@
 #[allow(dead_code)]
+mod account_erasure;
+#[allow(dead_code)]
+mod enrichment;
+#[allow(dead_code)]
+mod leadership;
+#[allow(dead_code)]
 mod notification;
+#[allow(dead_code)]
+mod partitions;
 mod queue;
+#[allow(dead_code)]
+mod sessions;
+#[allow(dead_code)]
+mod trending;
+#[allow(dead_code)]
+mod webhook;
 
+#[allow(unused_imports)]
+pub use account_erasure::{ACCOUNT_ERASURE_JOB_KIND, AccountErasureHandler, AccountErasurePayload};
+#[allow(unused_imports)]
+pub use enrichment::ArticleEnrichmentHandler;
+#[allow(unused_imports)]
+pub use leadership::Leadership;
 #[allow(unused_imports)]
 pub use notification::NotificationHandler;
+#[allow(unused_imports)]
+pub use partitions::{MAINTENANCE_INTERVAL, PartitionJob};
 pub use queue::JobQueue;
+#[allow(unused_imports)]
+pub use sessions::{SESSION_SWEEP_INTERVAL, SessionJob};
+#[allow(unused_imports)]
+pub use trending::TrendingJob;
+#[allow(unused_imports)]
+pub use webhook::WebhookHandler;
@@ -1,24 +1,18 @@
-use serde::Deserialize;
+use domain_events::{ArticleCreated, Envelope};
+use job_worker::Job;
 use tracing::instrument;
 
-use super::queue::Job;
-
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-pub struct NotificationPayload {
-    pub article_id: i32,
-    pub title: String,
-}
-
 #[allow(dead_code)]
 pub struct NotificationHandler;
 
 impl NotificationHandler {
     #[instrument(name = "job.notification.handle", skip(job), fields(job_id = job.id))]
     pub async fn handle(job: &Job) -> Result<(), anyhow::Error> {
-        let payload: NotificationPayload = serde_json::from_value(job.payload.clone())?;
+        let event = Envelope::<ArticleCreated>::from_value(job.payload.clone())?;
+        let payload = event.data;
 
         tracing::info!(
+            event_id = %event.id,
             article_id = payload.article_id,
             title = %payload.title,
             "Processing notification for new article"
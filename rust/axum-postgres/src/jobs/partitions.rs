@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use time::{Date, Month, OffsetDateTime};
+use tokio::task::JoinHandle;
+use tracing::{Instrument, instrument};
+
+use super::Leadership;
+use crate::{
+    error::{AppResult, RecordErr},
+    repository::JobPartitionRepository,
+};
+
+/// Months created ahead of the current one, so a stalled maintenance task
+/// has that long before new jobs start landing in `jobs_default`.
+pub const MONTHS_AHEAD: u32 = 3;
+
+/// A month changes rarely; hourly is plenty to stay ahead of it.
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How often a replica without leadership checks whether it has it now, so
+/// a new leader doesn't wait out a whole `MAINTENANCE_INTERVAL` first.
+const FOLLOWER_POLL: Duration = Duration::from_secs(10);
+
+/// The first day of `date`'s month.
+pub fn month_start(date: Date) -> Date {
+    date.replace_day(1).expect("every month has a first day")
+}
+
+/// The first day of the month `months` after `month`'s.
+pub fn add_months(month: Date, months: u32) -> Date {
+    let index = month.year() * 12 + i32::from(u8::from(month.month())) - 1 + months as i32;
+    let month = Month::try_from((index.rem_euclid(12) + 1) as u8).expect("month in 1..=12");
+    Date::from_calendar_date(index.div_euclid(12), month, 1).expect("first of a month")
+}
+
+/// The partition holding `month`'s jobs, matching the migration's naming.
+pub fn partition_name(month: Date) -> String {
+    format!("jobs_p{:04}_{:02}", month.year(), u8::from(month.month()))
+}
+
+/// Keeps `jobs` partitioned: creates the coming months' partitions, and
+/// moves rows that landed in `jobs_default` into a partition of their own.
+#[derive(Clone)]
+pub struct PartitionJob {
+    repo: JobPartitionRepository,
+}
+
+impl PartitionJob {
+    pub fn new(repo: JobPartitionRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Returns how many partitions it created.
+    #[instrument(
+        name = "job.partitions.maintain",
+        skip(self),
+        fields(created = tracing::field::Empty, moved = tracing::field::Empty)
+    )]
+    pub async fn maintain(&self) -> AppResult<u64> {
+        async move {
+            let current = month_start(OffsetDateTime::now_utc().date());
+            let mut months: Vec<Date> = (0..=MONTHS_AHEAD)
+                .map(|ahead| add_months(current, ahead))
+                .collect();
+            months.extend(self.repo.stranded_months().await?);
+            months.sort();
+            months.dedup();
+
+            let (mut created, mut moved) = (0, 0);
+            for month in months {
+                let name = partition_name(month);
+                if self.repo.exists(&name).await? {
+                    continue;
+                }
+                moved += self.repo.attach(&name, month, add_months(month, 1)).await?;
+                created += 1;
+            }
+            let span = tracing::Span::current();
+            span.record("created", created);
+            span.record("moved", moved);
+
+            if created > 0 {
+                tracing::info!(created, moved, "Job partitions created");
+            }
+
+            Ok(created)
+        }
+        .await
+        .record_err()
+    }
+
+    /// Maintains every `interval` while `leadership` is held, so of several
+    /// worker replicas only one runs the DDL.
+    pub fn spawn(&self, interval: Duration, leadership: Leadership) -> JoinHandle<()> {
+        let job = self.clone();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    if !leadership.is_leader() {
+                        tokio::time::sleep(FOLLOWER_POLL).await;
+                        continue;
+                    }
+                    if let Err(e) = job.maintain().await {
+                        tracing::warn!(error = %e, "Failed to maintain job partitions");
+                    }
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            .instrument(tracing::info_span!("job.partitions.scheduler")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    #[test]
+    fn test_month_start() {
+        assert_eq!(month_start(date!(2026 - 10 - 14)), date!(2026 - 10 - 01));
+        assert_eq!(month_start(date!(2026 - 10 - 01)), date!(2026 - 10 - 01));
+    }
+
+    #[test]
+    fn test_add_months_rolls_over_the_year() {
+        assert_eq!(add_months(date!(2026 - 10 - 01), 0), date!(2026 - 10 - 01));
+        assert_eq!(add_months(date!(2026 - 10 - 01), 2), date!(2026 - 12 - 01));
+        assert_eq!(add_months(date!(2026 - 10 - 01), 3), date!(2027 - 01 - 01));
+        assert_eq!(add_months(date!(2026 - 12 - 01), 13), date!(2028 - 01 - 01));
+    }
+
+    #[test]
+    fn test_partition_name() {
+        assert_eq!(partition_name(date!(2026 - 01 - 01)), "jobs_p2026_01");
+        assert_eq!(partition_name(date!(2026 - 12 - 01)), "jobs_p2026_12");
+    }
+}
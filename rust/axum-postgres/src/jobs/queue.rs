@@ -1,19 +1,14 @@
-use serde::{Deserialize, Serialize};
+use domain_events::{ArticleCreated, Envelope};
+use mailer::{EMAIL_JOB_KIND, EmailJob};
+use serde::Serialize;
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use tracing::{Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::telemetry::{JOBS_COMPLETED, JOBS_ENQUEUED, JOBS_FAILED};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Job {
-    pub id: i64,
-    pub kind: String,
-    pub payload: serde_json::Value,
-    pub status: String,
-    pub attempts: i32,
-    pub trace_context: Option<serde_json::Value>,
-}
+use crate::database::Persistent;
+use crate::models::OrgId;
+use crate::telemetry::JOBS_ENQUEUED;
 
 #[derive(Clone)]
 pub struct JobQueue {
@@ -40,6 +35,7 @@ impl JobQueue {
         .bind(kind)
         .bind(&payload_json)
         .bind(&trace_context)
+        .persistent_statement()
         .fetch_one(&self.pool)
         .await?;
 
@@ -51,93 +47,95 @@ impl JobQueue {
         Ok(job_id)
     }
 
-    #[instrument(name = "job.enqueue_notification", skip(self))]
-    pub async fn enqueue_notification(
+    /// Enqueues at most one job per `key`. Returns the job's id and whether
+    /// it already existed, in which case `payload` is dropped.
+    #[instrument(name = "job.enqueue_once", skip(self, payload))]
+    pub async fn enqueue_once<T: Serialize>(
         &self,
-        article_id: i32,
-        title: &str,
-    ) -> Result<i64, sqlx::Error> {
-        let payload = serde_json::json!({
-            "article_id": article_id,
-            "title": title,
-        });
-
-        self.enqueue("notification", payload).await
-    }
+        kind: &str,
+        key: &str,
+        payload: T,
+    ) -> Result<(i64, bool), sqlx::Error> {
+        let trace_context = self.capture_trace_context();
+        let payload_json = serde_json::to_value(&payload).unwrap_or(serde_json::Value::Null);
 
-    pub async fn dequeue(&self) -> Result<Option<Job>, sqlx::Error> {
-        let result = sqlx::query(
+        // Keys are kept unique in `job_idempotency_keys`, since a unique
+        // index on the partitioned `jobs` would have to include `created_at`.
+        // `DO NOTHING` returns no row for a duplicate, so then no job is
+        // inserted and its id is looked up separately.
+        let inserted: Option<i64> = sqlx::query_scalar(
             r#"
-            UPDATE jobs
-            SET status = 'processing',
-                started_at = NOW(),
-                attempts = attempts + 1
-            WHERE id = (
-                SELECT id FROM jobs
-                WHERE status = 'pending'
-                  AND scheduled_at <= NOW()
-                  AND attempts < max_attempts
-                ORDER BY priority DESC, scheduled_at ASC
-                FOR UPDATE SKIP LOCKED
-                LIMIT 1
+            WITH key AS (
+                INSERT INTO job_idempotency_keys (idempotency_key, job_id)
+                VALUES ($4, nextval('jobs_id_seq'))
+                ON CONFLICT (idempotency_key) DO NOTHING
+                RETURNING job_id
             )
-            RETURNING id, kind, payload, status, attempts, trace_context
+            INSERT INTO jobs (id, kind, payload, trace_context, idempotency_key)
+            SELECT job_id, $1, $2, $3, $4 FROM key
+            RETURNING id
             "#,
         )
+        .bind(kind)
+        .bind(&payload_json)
+        .bind(&trace_context)
+        .bind(key)
+        .persistent_statement()
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|row| Job {
-            id: row.get("id"),
-            kind: row.get("kind"),
-            payload: row.get("payload"),
-            status: row.get("status"),
-            attempts: row.get("attempts"),
-            trace_context: row.get("trace_context"),
-        }))
-    }
+        if let Some(job_id) = inserted {
+            JOBS_ENQUEUED.add(1, &[]);
+            tracing::info!(job_id, kind, "Job enqueued");
+            return Ok((job_id, false));
+        }
 
-    pub async fn complete(&self, job_id: i64) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            UPDATE jobs
-            SET status = 'completed', completed_at = NOW()
-            WHERE id = $1
-            "#,
+        let job_id: i64 = sqlx::query_scalar(
+            "SELECT job_id FROM job_idempotency_keys WHERE idempotency_key = $1",
         )
-        .bind(job_id)
-        .execute(&self.pool)
+        .bind(key)
+        .persistent_statement()
+        .fetch_one(&self.pool)
         .await?;
+        tracing::info!(job_id, kind, "Job already enqueued");
 
-        JOBS_COMPLETED.add(1, &[]);
-        Ok(())
+        Ok((job_id, true))
     }
 
-    pub async fn fail(&self, job_id: i64, error: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            UPDATE jobs
-            SET status = CASE
-                    WHEN attempts >= max_attempts THEN 'failed'
-                    ELSE 'pending'
-                END,
-                failed_at = NOW(),
-                error_message = $2
-            WHERE id = $1
-            "#,
-        )
-        .bind(job_id)
-        .bind(error)
-        .execute(&self.pool)
-        .await?;
+    #[instrument(
+        name = "job.enqueue_email",
+        skip(self, email),
+        fields(email.template = email.template.name())
+    )]
+    pub async fn enqueue_email(&self, email: EmailJob) -> Result<i64, sqlx::Error> {
+        self.enqueue(EMAIL_JOB_KIND, email).await
+    }
 
-        JOBS_FAILED.add(1, &[]);
-        Ok(())
+    #[instrument(
+        name = "job.enqueue_notification",
+        skip(self, event),
+        fields(article_id = event.article_id, title = %event.title)
+    )]
+    pub async fn enqueue_notification(&self, event: ArticleCreated) -> Result<i64, sqlx::Error> {
+        self.enqueue("notification", Envelope::new(event)).await
+    }
+
+    #[instrument(name = "job.enqueue_article_enrichment", skip(self, org))]
+    pub async fn enqueue_article_enrichment(
+        &self,
+        org: OrgId,
+        article_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        let payload = serde_json::json!({
+            "org_id": org,
+            "article_id": article_id,
+        });
+
+        self.enqueue("article_enrichment", payload).await
     }
 
     fn capture_trace_context(&self) -> Option<serde_json::Value> {
         use opentelemetry::trace::TraceContextExt;
-        use tracing_opentelemetry::OpenTelemetrySpanExt;
 
         let span = Span::current();
         let context = span.context();
@@ -161,3 +159,71 @@ impl JobQueue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use job_worker::Job;
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::SpanId;
+    use tracing::Instrument;
+
+    use super::*;
+    use crate::jobs::NotificationHandler;
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_child_of};
+
+    fn job(trace_context: Option<serde_json::Value>) -> Job {
+        Job {
+            id: 7,
+            kind: "notification".to_string(),
+            payload: Envelope::new(ArticleCreated {
+                article_id: 1,
+                org_id: None,
+                slug: "hello".to_string(),
+                title: "Hello".to_string(),
+                author_id: 1,
+            })
+            .to_value(),
+            status: "processing".to_string(),
+            attempts: 1,
+            trace_context,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_span_continues_the_enqueuing_trace() {
+        let spans = SpanCapture::start();
+        let queue = JobQueue::new(PgPool::connect_lazy("postgres://localhost/db").unwrap());
+        let trace_context =
+            tracing::info_span!("POST /api/articles").in_scope(|| queue.capture_trace_context());
+        assert!(trace_context.is_some());
+
+        let job = job(trace_context);
+        NotificationHandler::handle(&job)
+            .instrument(job.span())
+            .await
+            .unwrap();
+
+        let request = spans.span("POST /api/articles");
+        let process = spans.span("job.process");
+        let handle = spans.span("job.notification.handle");
+        assert_child_of(&process, &request);
+        assert_child_of(&handle, &process);
+        assert_attributes(
+            &process,
+            &[
+                KeyValue::new("job_id", 7),
+                KeyValue::new("job_kind", "notification"),
+            ],
+        );
+        assert_attributes(&handle, &[KeyValue::new("job_id", 7)]);
+    }
+
+    #[tokio::test]
+    async fn test_job_without_trace_context_starts_a_trace() {
+        let spans = SpanCapture::start();
+        let job = job(Some(serde_json::json!("not a carrier")));
+        job.span().in_scope(|| {});
+
+        assert_eq!(spans.span("job.process").parent_span_id, SpanId::INVALID);
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::{Instrument, instrument};
+
+use super::Leadership;
+use crate::{
+    error::{AppResult, RecordErr},
+    repository::SessionRepository,
+    telemetry::{SESSIONS_ACTIVE, org_attribute},
+};
+
+/// Also how stale `auth.sessions.active` can get.
+pub const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often a replica without leadership checks whether it has it now.
+const FOLLOWER_POLL: Duration = Duration::from_secs(10);
+
+/// Deletes the rows of expired sessions and records how many are active
+/// per organization.
+#[derive(Clone)]
+pub struct SessionJob {
+    repo: SessionRepository,
+}
+
+impl SessionJob {
+    pub fn new(repo: SessionRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Returns how many expired sessions it deleted.
+    #[instrument(
+        name = "job.sessions.sweep",
+        skip(self),
+        fields(deleted = tracing::field::Empty)
+    )]
+    pub async fn sweep(&self) -> AppResult<u64> {
+        async move {
+            let deleted = self.repo.delete_expired().await?;
+            tracing::Span::current().record("deleted", deleted);
+
+            for (org, active) in self.repo.count_active().await? {
+                SESSIONS_ACTIVE.record(active as u64, &[org_attribute(org)]);
+            }
+
+            if deleted > 0 {
+                tracing::info!(deleted, "Expired sessions deleted");
+            }
+
+            Ok(deleted)
+        }
+        .await
+        .record_err()
+    }
+
+    /// Sweeps every `interval` while `leadership` is held, so only one
+    /// worker replica reports the gauge.
+    pub fn spawn(&self, interval: Duration, leadership: Leadership) -> JoinHandle<()> {
+        let job = self.clone();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    if !leadership.is_leader() {
+                        tokio::time::sleep(FOLLOWER_POLL).await;
+                        continue;
+                    }
+                    if let Err(e) = job.sweep().await {
+                        tracing::warn!(error = %e, "Failed to sweep sessions");
+                    }
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            .instrument(tracing::info_span!("job.sessions.scheduler")),
+        )
+    }
+}
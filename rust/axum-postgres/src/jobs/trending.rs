@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tracing::{Instrument, instrument};
+
+use super::Leadership;
+use crate::{
+    error::{AppResult, RecordErr},
+    models::ArticleEngagement,
+    repository::ArticleScoreRepository,
+};
+
+/// A favorite counts as this many views.
+pub const FAVORITE_WEIGHT: f64 = 10.0;
+
+/// Engagement (views plus weighted favorites) halved for every `half_life`
+/// of the article's age, so a new article with a little traffic outranks an
+/// old one that has stopped getting any.
+pub fn trending_score(
+    article: &ArticleEngagement,
+    now: OffsetDateTime,
+    half_life: Duration,
+) -> f64 {
+    let engagement = article.views_count as f64 + FAVORITE_WEIGHT * article.favorites_count as f64;
+    let age = (now - article.created_at).as_seconds_f64().max(0.0);
+    engagement * 0.5_f64.powf(age / half_life.as_secs_f64())
+}
+
+/// Recomputes every article's score into `article_scores`.
+#[derive(Clone)]
+pub struct TrendingJob {
+    repo: ArticleScoreRepository,
+    half_life: Duration,
+}
+
+impl TrendingJob {
+    pub fn new(repo: ArticleScoreRepository, half_life: Duration) -> Self {
+        Self { repo, half_life }
+    }
+
+    #[instrument(
+        name = "job.trending.refresh",
+        skip(self),
+        fields(articles = tracing::field::Empty)
+    )]
+    pub async fn refresh(&self) -> AppResult<u64> {
+        async move {
+            let now = OffsetDateTime::now_utc();
+            let articles = self.repo.engagement().await?;
+            tracing::Span::current().record("articles", articles.len() as i64);
+
+            let (ids, scores): (Vec<i32>, Vec<f64>) = articles
+                .iter()
+                .map(|a| (a.id, trending_score(a, now, self.half_life)))
+                .unzip();
+            let scored = self.repo.upsert(&ids, &scores).await?;
+
+            tracing::info!(articles = scored, "Trending scores refreshed");
+
+            Ok(scored)
+        }
+        .await
+        .record_err()
+    }
+
+    /// Refreshes every `interval` while `leadership` is held, so of several
+    /// worker replicas only one does.
+    pub fn spawn(&self, interval: Duration, leadership: Leadership) -> JoinHandle<()> {
+        let job = self.clone();
+
+        tokio::spawn(
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    if !leadership.is_leader() {
+                        continue;
+                    }
+                    if let Err(e) = job.refresh().await {
+                        tracing::warn!(error = %e, "Failed to refresh trending scores");
+                    }
+                }
+            }
+            .instrument(tracing::info_span!("job.trending.scheduler")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::Status;
+    use sqlx::postgres::PgPoolOptions;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::telemetry::testing::SpanCapture;
+
+    const NOW: OffsetDateTime = datetime!(2026-01-10 12:00:00 UTC);
+    const DAY: Duration = Duration::from_secs(24 * 3600);
+
+    fn article(favorites: i32, views: i64, created_at: OffsetDateTime) -> ArticleEngagement {
+        ArticleEngagement {
+            id: 1,
+            favorites_count: favorites,
+            views_count: views,
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_new_article_scores_its_engagement() {
+        assert_eq!(trending_score(&article(2, 30, NOW), NOW, DAY), 50.0);
+        assert_eq!(trending_score(&article(0, 0, NOW), NOW, DAY), 0.0);
+    }
+
+    #[test]
+    fn test_score_halves_every_half_life() {
+        let day_old = trending_score(&article(0, 100, datetime!(2026-01-09 12:00 UTC)), NOW, DAY);
+        let two_days_old =
+            trending_score(&article(0, 100, datetime!(2026-01-08 12:00 UTC)), NOW, DAY);
+        assert!((day_old - 50.0).abs() < 1e-9);
+        assert!((two_days_old - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_recent_engagement_outranks_old_engagement() {
+        let old_hit = article(50, 5_000, datetime!(2026-01-01 12:00 UTC));
+        let new_post = article(3, 40, datetime!(2026-01-10 09:00 UTC));
+        assert!(trending_score(&new_post, NOW, DAY) > trending_score(&old_hit, NOW, DAY));
+    }
+
+    #[test]
+    fn test_favorites_outweigh_views() {
+        let created = datetime!(2026-01-10 00:00 UTC);
+        assert!(
+            trending_score(&article(1, 0, created), NOW, DAY)
+                > trending_score(&article(0, 9, created), NOW, DAY)
+        );
+    }
+
+    #[test]
+    fn test_future_created_at_is_not_boosted() {
+        let skewed = article(0, 10, datetime!(2026-01-10 13:00 UTC));
+        assert_eq!(trending_score(&skewed, NOW, DAY), 10.0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_is_recorded_on_its_span() {
+        let spans = SpanCapture::start();
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/db")
+            .expect("lazy pool");
+        let job = TrendingJob::new(ArticleScoreRepository::new(pool), DAY);
+
+        assert!(job.refresh().await.is_err());
+
+        let refresh = spans.span("job.trending.refresh");
+        assert!(matches!(refresh.status, Status::Error { .. }));
+        assert!(refresh.events.iter().any(|e| e.name == "exception"));
+    }
+}
@@ -0,0 +1,34 @@
+use job_worker::Job;
+use tracing::{Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::services::{WebhookDelivery, span_link};
+
+pub struct WebhookHandler;
+
+impl WebhookHandler {
+    /// `job.process` continues the trace of the request that accepted the
+    /// delivery; this span also links to the sender's.
+    #[instrument(
+        name = "job.webhook.handle",
+        skip(job),
+        fields(job_id = job.id, webhook.id = tracing::field::Empty)
+    )]
+    pub async fn handle(job: &Job) -> Result<(), anyhow::Error> {
+        let delivery: WebhookDelivery = serde_json::from_value(job.payload.clone())?;
+        let span = Span::current();
+        span.record("webhook.id", delivery.delivery_id.as_str());
+        if let Some(cx) = delivery.traceparent.as_deref().and_then(span_link) {
+            span.add_link(cx);
+        }
+
+        tracing::info!(
+            webhook.source = %delivery.source,
+            webhook.id = %delivery.delivery_id,
+            webhook.event_type = delivery.event_type.as_deref().unwrap_or("unknown"),
+            "Processing webhook delivery"
+        );
+
+        Ok(())
+    }
+}
@@ -1,23 +1,54 @@
+pub mod clients;
 pub mod config;
 pub mod database;
+pub mod encoding;
 pub mod error;
+pub mod feature_flags;
 pub mod handlers;
 pub mod jobs;
 pub mod middleware;
 pub mod models;
 pub mod repository;
 pub mod routes;
+pub mod server;
 pub mod services;
+pub mod storage;
+pub mod synthetic;
 pub mod telemetry;
+pub mod tls;
 
 pub use config::Config;
 
-use services::{ArticleService, AuthService};
+use std::sync::Arc;
+
+use authz::Policy;
+use feature_flags::FeatureFlags;
+use jobs::JobQueue;
+use middleware::LoadShedder;
+use services::{
+    AccountService, ArticleService, AuditRecorder, AuthService, EconomicContextService,
+    ImageService, SuggestService, WebhookService,
+};
 use sqlx::PgPool;
+use storage::Storage;
+use telemetry::TelemetryControl;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub policy: Arc<Policy>,
+    pub account_service: AccountService,
     pub auth_service: AuthService,
     pub article_service: ArticleService,
+    pub audit: AuditRecorder,
+    pub economic_context_service: EconomicContextService,
+    pub feature_flags: FeatureFlags,
+    pub image_service: ImageService,
+    pub job_queue: JobQueue,
+    pub load_shedder: LoadShedder,
+    pub storage: Storage,
+    pub suggest_service: SuggestService,
+    pub telemetry: TelemetryControl,
+    pub webhook_service: WebhookService,
 }
@@ -1,8 +1,9 @@
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
+use authz::Policy;
 use axum::http::{Request, Response, StatusCode};
-use opentelemetry::KeyValue;
 use sqlx::PgPool;
 use tokio::net::TcpListener;
 use tokio::signal;
@@ -14,36 +15,76 @@ use tower_http::{
 };
 use tracing::Span;
 
+mod clients;
 mod config;
 mod database;
+mod encoding;
 mod error;
+mod feature_flags;
 mod handlers;
 mod jobs;
 mod middleware;
 mod models;
 mod repository;
 mod routes;
+mod server;
 mod services;
+mod storage;
+mod synthetic;
 mod telemetry;
+mod tls;
 
-use config::Config;
-use database::create_pool;
+use clients::ReportClient;
+use config::{Config, RotatingSecret};
+use database::{TenantDb, create_pool};
+use feature_flags::FeatureFlags;
 use jobs::JobQueue;
-use repository::{ArticleRepository, FavoriteRepository, UserRepository};
-use services::{ArticleService, AuthService};
-use telemetry::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL, TelemetryGuard, init_telemetry};
+use middleware::LoadShedder;
+use repository::{
+    ApiTokenRepository, ArticleRepository, AuditRepository, FavoriteRepository,
+    FeatureFlagRepository, LoginSignalRepository, OrganizationRepository, SessionRepository,
+    TwoFactorRepository, UserRepository,
+};
+use server::ServerTuning;
+use services::{
+    AccountService, ArticleService, AuditRecorder, AuthService, EconomicContextService, GeoIp,
+    ImageService, LoginSignals, MarkdownRenderer, SuggestService, ViewBuffer, WebhookService,
+    parse_date,
+};
+use storage::Storage;
+use synthetic::SyntheticProbe;
+use telemetry::{
+    HttpMetricsLayer, MeteredListener, TelemetryControl, TelemetryGuard, init_telemetry,
+    spawn_counter_flush,
+};
+use tls::TlsListener;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub policy: Arc<Policy>,
+    pub account_service: AccountService,
     pub auth_service: AuthService,
     pub article_service: ArticleService,
+    pub audit: AuditRecorder,
+    pub economic_context_service: EconomicContextService,
+    pub feature_flags: FeatureFlags,
+    pub image_service: ImageService,
+    pub job_queue: JobQueue,
+    pub load_shedder: LoadShedder,
+    pub storage: Storage,
+    pub suggest_service: SuggestService,
+    pub telemetry: TelemetryControl,
+    pub webhook_service: WebhookService,
 }
 
 const X_REQUEST_ID: &str = "x-request-id";
 
 #[derive(Clone)]
-struct HttpMakeSpan;
+struct HttpMakeSpan {
+    scheme: &'static str,
+}
 
 impl<B> MakeSpan<B> for HttpMakeSpan {
     fn make_span(&mut self, request: &Request<B>) -> Span {
@@ -63,8 +104,9 @@ impl<B> MakeSpan<B> for HttpMakeSpan {
             http.method = %method,
             http.route = %path,
             http.target = %uri,
-            http.scheme = "http",
+            http.scheme = self.scheme,
             http.flavor = ?request.version(),
+            network.protocol.version = server::protocol_version(request.version()),
             http.user_agent = request.headers()
                 .get("user-agent")
                 .and_then(|v| v.to_str().ok())
@@ -76,17 +118,14 @@ impl<B> MakeSpan<B> for HttpMakeSpan {
     }
 }
 
+/// The span's status and the request's log line. The metrics are
+/// recorded by `HttpMetricsLayer`, which sees the request's method.
 #[derive(Clone)]
 struct HttpOnResponse;
 
 impl<B> OnResponse<B> for HttpOnResponse {
     fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
         let status = response.status().as_u16();
-        let method = span
-            .metadata()
-            .and_then(|m| m.fields().field("http.method"))
-            .map(|_| "unknown")
-            .unwrap_or("unknown");
 
         span.record("http.response.status_code", status as i64);
 
@@ -96,30 +135,9 @@ impl<B> OnResponse<B> for HttpOnResponse {
             span.record("otel.status_code", "OK");
         }
 
-        let latency_ms = latency.as_secs_f64() * 1000.0;
-        let status_class = format!("{}xx", status / 100);
-
-        HTTP_REQUESTS_TOTAL.add(
-            1,
-            &[
-                KeyValue::new("http.method", method.to_string()),
-                KeyValue::new("http.status_code", status.to_string()),
-                KeyValue::new("http.status_class", status_class.clone()),
-            ],
-        );
-
-        HTTP_REQUEST_DURATION.record(
-            latency_ms,
-            &[
-                KeyValue::new("http.method", method.to_string()),
-                KeyValue::new("http.status_code", status.to_string()),
-                KeyValue::new("http.status_class", status_class),
-            ],
-        );
-
         tracing::info!(
             http.response.status_code = status,
-            latency_ms = latency_ms,
+            latency_ms = latency.as_secs_f64() * 1000.0,
             "finished processing request"
         );
     }
@@ -127,37 +145,178 @@ impl<B> OnResponse<B> for HttpOnResponse {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config = Config::from_env();
+    let config = Config::load().await?;
 
-    let telemetry_guard: TelemetryGuard = init_telemetry(&config)?;
+    let mut telemetry_guard: TelemetryGuard = init_telemetry(&config)?;
 
     tracing::info!(
         port = config.port,
         environment = %config.environment,
+        secrets_provider = config.secrets.provider_name().unwrap_or("none"),
         "Starting server"
     );
 
     let pool = create_pool(&config).await?;
+    if let Some(planner) = telemetry_guard.query_planner.take() {
+        planner.spawn(pool.clone());
+    }
 
-    let user_repo = UserRepository::new(pool.clone());
-    let article_repo = ArticleRepository::new(pool.clone());
-    let favorite_repo = FavoriteRepository::new(pool.clone());
+    let tenant_db = TenantDb::new(pool.clone(), config.tenancy_mode);
+    let user_repo = UserRepository::new(tenant_db.clone());
+    let article_repo = ArticleRepository::new(tenant_db.clone());
+    let favorite_repo = FavoriteRepository::new(tenant_db);
     let job_queue = JobQueue::new(pool.clone());
+    let audit = AuditRecorder::new(AuditRepository::new(pool.clone()));
+
+    let feature_flags = FeatureFlags::new(
+        FeatureFlagRepository::new(pool.clone()),
+        audit.clone(),
+        &config.environment,
+    );
+    let flag_count = feature_flags.refresh().await?;
+    feature_flags.spawn_refresh(Duration::from_secs(config.feature_flags_refresh_secs));
+
+    tracing::info!(flags = flag_count, "Feature flags loaded");
+
+    let storage = Storage::from_config(&config)?;
+
+    tracing::info!(backend = storage.backend_name(), "Image storage configured");
+
+    let image_service = ImageService::new(
+        storage.clone(),
+        user_repo.clone(),
+        article_repo.clone(),
+        audit.clone(),
+        config.upload_max_bytes,
+    );
+    let jwt_secret = RotatingSecret::new("JWT_SECRET", config.jwt_secret.clone());
+    jwt_secret.spawn_refresh(
+        config.secrets.clone(),
+        Duration::from_secs(config.secrets_refresh_secs),
+    );
+    let token_repo = ApiTokenRepository::new(pool.clone());
+    let two_factor_repo = TwoFactorRepository::new(pool.clone());
+    let session_repo = SessionRepository::new(pool.clone());
+    let login_signal_repo = LoginSignalRepository::new(pool.clone());
+    let geoip = if config.geoip_csv_path.is_empty() {
+        GeoIp::default()
+    } else {
+        let path = config.geoip_csv_path.clone();
+        let geoip = tokio::task::spawn_blocking(move || GeoIp::load(&path))
+            .await?
+            .map_err(|e| anyhow::anyhow!("GEOIP_CSV_PATH {e}"))?;
+        tracing::info!(networks = geoip.networks(), "GeoIP data loaded");
+        geoip
+    };
+    let policy = middleware::load_policy(&config.authz_policy_file)?;
+    let account_service = AccountService::new(
+        user_repo.clone(),
+        token_repo.clone(),
+        two_factor_repo.clone(),
+        session_repo.clone(),
+        login_signal_repo.clone(),
+        article_repo.clone(),
+        favorite_repo.clone(),
+        job_queue.clone(),
+        storage.clone(),
+        audit.clone(),
+        config.account_deletion_policy,
+    );
+    let auth_service = AuthService::new(
+        user_repo,
+        token_repo,
+        OrganizationRepository::new(pool.clone()),
+        two_factor_repo,
+        session_repo,
+        LoginSignals::new(login_signal_repo, geoip, audit.clone(), &config),
+        storage.clone(),
+        audit.clone(),
+        jwt_secret,
+        &config,
+    );
+    let report_client = if config.report_service_url.is_empty() {
+        None
+    } else {
+        Some(ReportClient::new(
+            &config.report_service_url,
+            Duration::from_secs(config.report_service_timeout_secs),
+        )?)
+    };
+    let economic_context_service = EconomicContextService::new(
+        article_repo.clone(),
+        report_client,
+        &config.economic_context_indicators,
+        parse_date(
+            "ECONOMIC_CONTEXT_DATA_END",
+            &config.economic_context_data_end,
+        )?,
+    );
+    let suggest_service = SuggestService::new(
+        article_repo.clone(),
+        Duration::from_millis(config.suggest_timeout_ms),
+        config.suggest_cache_size,
+        Duration::from_secs(config.suggest_cache_ttl_secs),
+    );
+    if config.metrics_counter_batch_ms > 0 {
+        spawn_counter_flush(Duration::from_millis(config.metrics_counter_batch_ms));
+    }
+    let views = ViewBuffer::new(article_repo.clone());
+    views.spawn_flush(Duration::from_secs(config.article_views_flush_secs));
+    let webhook_service = WebhookService::new(job_queue.clone(), &config);
+    let article_service = ArticleService::new(
+        article_repo,
+        favorite_repo,
+        job_queue.clone(),
+        storage.clone(),
+        views.clone(),
+        audit.clone(),
+        MarkdownRenderer::new(config.markdown_cache_size),
+    );
 
-    let auth_service = AuthService::new(user_repo, &config);
-    let article_service = ArticleService::new(article_repo, favorite_repo, job_queue);
+    let load_shedder = LoadShedder::new(
+        config.load_shed_max_in_flight,
+        Duration::from_millis(config.load_shed_max_queue_delay_ms),
+        Duration::from_secs(config.load_shed_retry_after_secs),
+    );
+    load_shedder.spawn_probe();
+    load_shedder.register_gauges();
 
     let state = AppState {
         pool,
+        config: Arc::new(config.clone()),
+        policy: Arc::new(policy),
+        account_service,
         auth_service,
         article_service,
+        audit,
+        economic_context_service,
+        feature_flags,
+        image_service,
+        job_queue,
+        load_shedder: load_shedder.clone(),
+        storage,
+        suggest_service,
+        telemetry: telemetry_guard.control.clone(),
+        webhook_service,
     };
 
+    let scheme = if config.tls_enabled() {
+        "https"
+    } else {
+        "http"
+    };
     let app = routes::create_router(state)
+        .layer(axum::middleware::from_fn(middleware::propagate_deadline))
+        .layer(axum::middleware::from_fn(middleware::structured_errors))
+        .layer(axum::middleware::from_fn_with_state(
+            load_shedder,
+            middleware::track_in_flight,
+        ))
         .layer(PropagateRequestIdLayer::new(X_REQUEST_ID.parse().unwrap()))
+        .layer(HttpMetricsLayer)
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(HttpMakeSpan)
+                .make_span_with(HttpMakeSpan { scheme })
                 .on_response(HttpOnResponse),
         )
         .layer(SetRequestIdLayer::new(
@@ -176,13 +335,46 @@ async fn main() -> anyhow::Result<()> {
         );
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
-    let listener = TcpListener::bind(addr).await?;
+    let listener = MeteredListener::new(TcpListener::bind(addr).await?)?;
 
-    tracing::info!(%addr, "Server listening");
+    tracing::info!(%addr, scheme, "Server listening");
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    if config.synthetic_check_interval_secs > 0 {
+        SyntheticProbe::new(
+            &format!("{scheme}://127.0.0.1:{}", config.port),
+            Duration::from_secs(config.synthetic_check_timeout_secs),
+        )?
+        .spawn(Duration::from_secs(config.synthetic_check_interval_secs));
+    }
+
+    let tuning = ServerTuning::from_config(&config);
+    if config.tls_enabled() {
+        let tls_config =
+            tls::load_config(&config.tls_cert_path, &config.tls_key_path, tuning.http2)?;
+        if config.http_redirect_port != 0 {
+            let redirect_addr = SocketAddr::from(([0, 0, 0, 0], config.http_redirect_port));
+            let redirect_listener = MeteredListener::new(TcpListener::bind(redirect_addr).await?)?;
+            tracing::info!(addr = %redirect_addr, "Redirecting HTTP to HTTPS");
+            tokio::spawn(
+                axum::serve(redirect_listener, tls::redirect_app(config.port))
+                    .with_graceful_shutdown(shutdown_signal())
+                    .into_future(),
+            );
+        }
+        server::serve(
+            TlsListener::new(listener, tls_config)?,
+            app,
+            &tuning,
+            shutdown_signal(),
+        )
+        .await;
+    } else {
+        server::serve(listener, app, &tuning, shutdown_signal()).await;
+    }
+
+    if let Err(e) = views.flush().await {
+        tracing::warn!(error = %e, "Failed to flush article views on shutdown");
+    }
 
     tracing::info!("Server shutdown complete");
     telemetry_guard.shutdown();
@@ -215,3 +407,127 @@ async fn shutdown_signal() {
 
     tracing::info!("Shutdown signal received");
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::get;
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::Status;
+    use tower::ServiceExt;
+
+    use super::*;
+    use error::AppError;
+    use telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    /// The request-id and trace layers from `main`, around stub routes.
+    fn app() -> Router {
+        Router::new()
+            .route("/api/teapot", get(|| async { StatusCode::IM_A_TEAPOT }))
+            .route(
+                "/api/unavailable",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            )
+            .route(
+                "/api/missing",
+                get(|| async { Err::<(), _>(AppError::NotFound("Article not found".into())) }),
+            )
+            .layer(PropagateRequestIdLayer::new(X_REQUEST_ID.parse().unwrap()))
+            .layer(HttpMetricsLayer)
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(HttpMakeSpan { scheme: "http" })
+                    .on_response(HttpOnResponse),
+            )
+            .layer(SetRequestIdLayer::new(
+                X_REQUEST_ID.parse().unwrap(),
+                MakeRequestUuid,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_http_span_has_request_attributes() {
+        let spans = SpanCapture::start();
+        let request = Request::get("/api/teapot?page=2")
+            .header(X_REQUEST_ID, "req-123")
+            .header("user-agent", "curl/8.5.0")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(response.headers()[X_REQUEST_ID], "req-123");
+        drop(response);
+
+        let span = spans.span("GET /api/teapot");
+        assert_eq!(span.status, Status::Ok);
+        assert_attributes(
+            &span,
+            &[
+                KeyValue::new("http.method", "GET"),
+                KeyValue::new("http.route", "/api/teapot"),
+                KeyValue::new("http.target", "/api/teapot?page=2"),
+                KeyValue::new("http.user_agent", "curl/8.5.0"),
+                KeyValue::new("http.request_id", "req-123"),
+                KeyValue::new("http.response.status_code", 418),
+            ],
+        );
+        let attributes = [
+            KeyValue::new("http.method", "GET"),
+            KeyValue::new("http.status_code", "418"),
+            KeyValue::new("http.status_class", "4xx"),
+        ];
+        assert_metric_recorded("http.requests.total", &attributes);
+        assert_metric_recorded("http.request.duration", &attributes);
+    }
+
+    #[tokio::test]
+    async fn test_http_span_marks_server_errors() {
+        let spans = SpanCapture::start();
+        let request = Request::get("/api/unavailable")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let request_id = response.headers()[X_REQUEST_ID]
+            .to_str()
+            .unwrap()
+            .to_string();
+        drop(response);
+
+        let span = spans.span("GET /api/unavailable");
+        assert!(matches!(span.status, Status::Error { .. }));
+        // Generated by `SetRequestIdLayer` when the caller sends none.
+        assert!(!request_id.is_empty());
+        assert_attributes(
+            &span,
+            &[
+                KeyValue::new("http.request_id", request_id),
+                KeyValue::new("http.response.status_code", 503),
+            ],
+        );
+        assert_metric_recorded(
+            "http.requests.total",
+            &[KeyValue::new("http.status_code", "503")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_body_carries_trace_and_request_ids() {
+        let spans = SpanCapture::start();
+        let request = Request::get("/api/missing")
+            .header(X_REQUEST_ID, "req-456")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let span = spans.span("GET /api/missing");
+        assert_eq!(body["error"], "Article not found");
+        assert_eq!(body["request_id"], "req-456");
+        assert_eq!(body["trace_id"], span.span_context.trace_id().to_string());
+    }
+}
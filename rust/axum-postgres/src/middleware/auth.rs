@@ -1,11 +1,29 @@
+use std::convert::Infallible;
+
 use axum::{
-    extract::FromRequestParts,
+    extract::{FromRequestParts, OptionalFromRequestParts},
     http::{header::AUTHORIZATION, request::Parts},
 };
 
-use crate::{AppState, error::AppError};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    AppState,
+    error::AppError,
+    models::{OrgId, TokenScope},
+    services::{API_TOKEN_PREFIX, JwtSession, Principal},
+};
+
+const X_ADMIN_TOKEN: &str = "x-admin-token";
 
-pub struct AuthUser(pub i32);
+/// Accepts a JWT or a personal access token. Tokens need the `write` scope
+/// for anything other than safe methods. `org` is the tenant the token was
+/// issued in.
+pub struct AuthUser {
+    pub user_id: i32,
+    pub org: OrgId,
+}
 
 impl FromRequestParts<AppState> for AuthUser {
     type Rejection = AppError;
@@ -14,13 +32,22 @@ impl FromRequestParts<AppState> for AuthUser {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let token = extract_token(parts)?;
-        let user_id = state.auth_service.validate_token(&token)?;
-        Ok(AuthUser(user_id))
+        let principal = match parts.extensions.get::<Principal>() {
+            Some(principal) => *principal,
+            None => authenticate_request(parts, state).await?,
+        };
+        Ok(AuthUser {
+            user_id: principal.user_id,
+            org: principal.org,
+        })
     }
 }
 
-pub struct OptionalAuthUser(pub Option<i32>);
+/// Anonymous requests read from the default organization.
+pub struct OptionalAuthUser {
+    pub user_id: Option<i32>,
+    pub org: OrgId,
+}
 
 impl FromRequestParts<AppState> for OptionalAuthUser {
     type Rejection = AppError;
@@ -29,16 +56,132 @@ impl FromRequestParts<AppState> for OptionalAuthUser {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        match extract_token(parts) {
-            Ok(token) => match state.auth_service.validate_token(&token) {
-                Ok(user_id) => Ok(OptionalAuthUser(Some(user_id))),
-                Err(_) => Ok(OptionalAuthUser(None)),
+        let principal = match parts.extensions.get::<Principal>() {
+            Some(principal) => Some(*principal),
+            None => authenticate_request(parts, state).await.ok(),
+        };
+        Ok(match principal {
+            Some(principal) => OptionalAuthUser {
+                user_id: Some(principal.user_id),
+                org: principal.org,
             },
-            Err(_) => Ok(OptionalAuthUser(None)),
+            None => {
+                record_org(OrgId::DEFAULT);
+                OptionalAuthUser {
+                    user_id: None,
+                    org: OrgId::DEFAULT,
+                }
+            }
+        })
+    }
+}
+
+/// Like [`AuthUser`] but rejects personal access tokens, for endpoints such
+/// as token management that a leaked token must not be able to reach.
+/// `session_id` is `None` for JWTs issued before sessions were tracked.
+pub struct JwtAuthUser {
+    pub user_id: i32,
+    pub org: OrgId,
+    pub session_id: Option<i32>,
+}
+
+impl FromRequestParts<AppState> for JwtAuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = extract_token(parts)?;
+        if token.starts_with(API_TOKEN_PREFIX) {
+            Span::current().set_attribute("auth.token.kind", "pat");
+            return Err(AppError::Forbidden);
         }
+        Span::current().set_attribute("auth.token.kind", "jwt");
+        let JwtSession {
+            principal,
+            session_id,
+        } = state.auth_service.validate_token(&token).await?;
+        record_org(principal.org);
+        Ok(JwtAuthUser {
+            user_id: principal.user_id,
+            org: principal.org,
+            session_id,
+        })
     }
 }
 
+/// `None` for anything that isn't a valid JWT.
+impl OptionalFromRequestParts<AppState> for JwtAuthUser {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(
+            <Self as FromRequestParts<AppState>>::from_request_parts(parts, state)
+                .await
+                .ok(),
+        )
+    }
+}
+
+pub struct AdminAuth;
+
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        authenticate_admin(parts, state)?;
+        Ok(AdminAuth)
+    }
+}
+
+/// The caller behind the bearer token. The
+/// [`authorize`](super::authorize) middleware stores it in the request's
+/// extensions, where [`AuthUser`] and [`OptionalAuthUser`] pick it up
+/// rather than validating the token twice.
+pub(super) async fn authenticate_request(
+    parts: &Parts,
+    state: &AppState,
+) -> Result<Principal, AppError> {
+    let token = extract_token(parts)?;
+    authenticate(parts, state, &token).await
+}
+
+pub(super) fn authenticate_admin(parts: &Parts, state: &AppState) -> Result<(), AppError> {
+    let token = parts
+        .headers
+        .get(X_ADMIN_TOKEN)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::Unauthorized)?;
+    state.auth_service.validate_admin_token(token)
+}
+
+async fn authenticate(parts: &Parts, state: &AppState, token: &str) -> Result<Principal, AppError> {
+    let span = Span::current();
+    let principal = if token.starts_with(API_TOKEN_PREFIX) {
+        span.set_attribute("auth.token.kind", "pat");
+        state
+            .auth_service
+            .validate_api_token(token, TokenScope::required_for(&parts.method))
+            .await?
+    } else {
+        span.set_attribute("auth.token.kind", "jwt");
+        state.auth_service.validate_token(token).await?.principal
+    };
+    record_org(principal.org);
+    Ok(principal)
+}
+
+fn record_org(org: OrgId) {
+    Span::current().set_attribute("org.id", i64::from(org.0));
+}
+
 fn extract_token(parts: &Parts) -> Result<String, AppError> {
     let auth_header = parts
         .headers
@@ -0,0 +1,212 @@
+use authz::{Decision, Policy, Subject};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::KeyValue;
+
+use super::auth::{authenticate_admin, authenticate_request};
+use crate::{AppState, error::AppError, telemetry::AUTHZ_DENIED};
+
+/// The rules in `config/policy.toml`, built in so the binary runs without
+/// the file.
+const DEFAULT_POLICY: &str = include_str!("../../config/policy.toml");
+
+/// Rules the article service relies on instead of checking ownership
+/// itself. A request no rule matches is let through, so a policy without
+/// one of these is refused.
+const REQUIRED_RULES: &[&str] = &[
+    "article.update",
+    "article.delete",
+    "article.add_author",
+    "article.remove_author",
+    "article.transfer",
+    "article.upload_cover",
+];
+
+/// `AUTHZ_POLICY_FILE`, or the built-in policy when it's empty.
+pub fn load_policy(path: &str) -> anyhow::Result<Policy> {
+    let source = if path.is_empty() {
+        DEFAULT_POLICY.to_string()
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("AUTHZ_POLICY_FILE {path}: {e}"))?
+    };
+    let policy = Policy::from_toml(&source)?;
+    policy.require(REQUIRED_RULES)?;
+    Ok(policy)
+}
+
+/// Applies the authorization policy before the handler runs. Rules are
+/// written against paths under the API root, which is what a router nested
+/// at `/api`, `/api/v1` or `/api/v2` sees. A request no rule matches is
+/// left to the handler and its extractors. An `owner` or `author` rule's
+/// handler gets the [`Authors`](authz::Authors) it was decided on as an
+/// extension.
+pub async fn authorize(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let policy = state.policy.clone();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let Some(matched) = policy.find(method.as_str(), &path) else {
+        return Ok(next.run(request).await);
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let principal = if matched.needs_user() {
+        Some(authenticate_request(&parts, &state).await)
+    } else {
+        None
+    };
+    let admin = authenticate_admin(&parts, &state);
+    let subject = Subject {
+        user_id: principal
+            .as_ref()
+            .and_then(|principal| principal.as_ref().ok())
+            .map(|principal| principal.user_id),
+        admin: admin.is_ok(),
+    };
+    let article = match (matched.article(), &principal) {
+        (Some(slug), Some(Ok(principal))) => {
+            state.article_service.authors(principal.org, slug).await?
+        }
+        _ => None,
+    };
+
+    let decision = matched.decide(&subject, article.as_ref());
+    if decision != Decision::Allow {
+        AUTHZ_DENIED.add(
+            1,
+            &[
+                KeyValue::new("authz.rule", matched.rule().to_string()),
+                KeyValue::new("authz.decision", decision.as_str()),
+            ],
+        );
+    }
+    match decision {
+        Decision::Allow => {}
+        // The credential's own error says what was wrong with it, such as a
+        // token without the `write` scope.
+        Decision::Unauthenticated => {
+            return Err(match (principal, admin) {
+                (Some(Err(e)), _) => e,
+                (_, Err(e)) if !matched.needs_user() => e,
+                _ => AppError::Unauthorized,
+            });
+        }
+        Decision::Forbidden => return Err(AppError::Forbidden),
+        Decision::NotFound => return Err(AppError::NotFound("Article not found".to_string())),
+    }
+
+    if let Some(Ok(principal)) = principal {
+        parts.extensions.insert(principal);
+    }
+    // The service changes the article only while its authors are still
+    // these, so a transfer or removal since can't ride on this decision.
+    if let Some(authors) = article {
+        parts.extensions.insert(authors);
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Router,
+        body::Body,
+        extract::Path,
+        middleware::{Next, from_fn},
+        routing::put,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rule_sees_the_slug_the_handler_extracts() {
+        let policy = Arc::new(load_policy("").unwrap());
+        let seen = Arc::new(Mutex::new(None));
+        let layer = {
+            let seen = seen.clone();
+            // The lookup `authorize` starts with, under a nested router.
+            from_fn(move |request: Request, next: Next| {
+                let (policy, seen) = (policy.clone(), seen.clone());
+                async move {
+                    let matched = policy.find(request.method().as_str(), request.uri().path());
+                    *seen.lock().unwrap() = matched.and_then(|m| m.article().map(String::from));
+                    next.run(request).await
+                }
+            })
+        };
+        let app = Router::new().nest(
+            "/api",
+            Router::new()
+                .route(
+                    "/articles/{slug}",
+                    put(|Path(slug): Path<String>| async { slug }),
+                )
+                .route_layer(layer),
+        );
+
+        let response = app
+            .oneshot(
+                Request::put("/api/articles/caf%C3%A9")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], "café".as_bytes());
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("café"));
+    }
+
+    #[test]
+    fn test_policy_without_an_ownership_rule_is_refused() {
+        let path = std::env::temp_dir().join(format!("policy-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            DEFAULT_POLICY.replace("article.delete", "article.remove"),
+        )
+        .unwrap();
+
+        let err = load_policy(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            err.to_string()
+                .contains("article.delete: required rule is missing"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_built_in_policy_covers_the_ownership_checks() {
+        let policy = load_policy("").unwrap();
+
+        for (method, path, rule) in [
+            ("PUT", "/articles/a", "article.update"),
+            ("DELETE", "/articles/a", "article.delete"),
+            ("POST", "/articles/a/authors", "article.add_author"),
+            ("DELETE", "/articles/a/authors/7", "article.remove_author"),
+            ("POST", "/articles/a/transfer", "article.transfer"),
+            ("POST", "/articles/a/cover", "article.upload_cover"),
+            ("PUT", "/admin/flags/x", "admin"),
+        ] {
+            assert_eq!(
+                policy.find(method, path).map(|m| m.rule().to_string()),
+                Some(rule.to_string()),
+                "{method} {path}"
+            );
+        }
+        assert!(policy.find("GET", "/articles/a").is_none());
+        assert!(policy.find("POST", "/articles/a/favorite").is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, header::USER_AGENT, request::Parts},
+};
+
+use crate::{AppState, models::ClientInfo};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Longer user agents are cut, at a character boundary.
+const USER_AGENT_MAX_LEN: usize = 512;
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(ClientInfo {
+            user_agent: user_agent[..user_agent.floor_char_boundary(USER_AGENT_MAX_LEN)]
+                .to_string(),
+            ip: client_ip(&parts.headers, peer, state.config.trusted_proxy_hops),
+        })
+    }
+}
+
+/// The connection's address, or with `trusted_hops` proxies in front, the
+/// address the outermost of them saw: that many entries from the right of
+/// `X-Forwarded-For`, as each proxy appends the address it got the request
+/// from. Entries further left are whatever the client sent.
+fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted_hops: usize) -> Option<IpAddr> {
+    if trusted_hops == 0 {
+        return peer.map(|ip| ip.to_canonical());
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let entry = forwarded.get(forwarded.len().checked_sub(trusted_hops)?)?;
+    entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(forwarded: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in forwarded {
+            headers.append(X_FORWARDED_FOR, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_without_proxies_the_peer_is_the_client() {
+        let spoofed = headers(&["203.0.113.9"]);
+        assert_eq!(
+            client_ip(&spoofed, Some(ip("10.0.0.1")), 0),
+            Some(ip("10.0.0.1"))
+        );
+        assert_eq!(
+            client_ip(&spoofed, Some(ip("::ffff:10.0.0.1")), 0),
+            Some(ip("10.0.0.1"))
+        );
+        assert_eq!(client_ip(&spoofed, None, 0), None);
+    }
+
+    #[test]
+    fn test_trusted_hops_count_from_the_right() {
+        let forwarded = headers(&["198.51.100.1, 203.0.113.9", "192.0.2.7"]);
+        let peer = Some(ip("10.0.0.1"));
+
+        assert_eq!(client_ip(&forwarded, peer, 1), Some(ip("192.0.2.7")));
+        assert_eq!(client_ip(&forwarded, peer, 2), Some(ip("203.0.113.9")));
+        assert_eq!(client_ip(&forwarded, peer, 3), Some(ip("198.51.100.1")));
+        assert_eq!(client_ip(&forwarded, peer, 4), None);
+        assert_eq!(client_ip(&headers(&[]), peer, 1), None);
+    }
+
+    #[test]
+    fn test_forwarded_entries_can_carry_a_port() {
+        assert_eq!(
+            client_ip(&headers(&["192.0.2.7:51234"]), None, 1),
+            Some(ip("192.0.2.7"))
+        );
+        assert_eq!(
+            client_ip(&headers(&["[2001:db8::1]:443"]), None, 1),
+            Some(ip("2001:db8::1"))
+        );
+        assert_eq!(client_ip(&headers(&["unknown"]), None, 1), None);
+    }
+}
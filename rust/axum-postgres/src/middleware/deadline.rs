@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::time::Instant;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::error::{AppError, AppResult};
+
+/// The caller's remaining budget in grpc-timeout form (`2S`, `1500m`).
+/// Relative rather than a timestamp, so it doesn't depend on both clocks
+/// agreeing; each hop forwards what is left of it.
+pub const REQUEST_DEADLINE_HEADER: &str = "x-request-deadline";
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+/// When the caller stops waiting for the response. It is in the request's
+/// extensions for handlers, and [`Deadline::current`] finds it further down
+/// the same task, which is how the database and outbound clients see it
+/// without it being threaded through every service method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    /// The deadline of the request this task is handling, if its caller
+    /// sent one.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|deadline| *deadline).ok()
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Runs `work` with this as the current deadline, dropping it with
+    /// [`AppError::DeadlineExceeded`] once the deadline passes.
+    pub async fn run<T>(self, work: impl Future<Output = AppResult<T>>) -> AppResult<T> {
+        CURRENT
+            .scope(self, tokio::time::timeout_at(self.0, work))
+            .await
+            .map_err(|_| AppError::DeadlineExceeded)?
+    }
+}
+
+/// A grpc-timeout value: up to 8 digits and a unit of `H`, `M`, `S`, `m`
+/// (milliseconds), `u` or `n`.
+pub fn parse_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(n * 3600),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    })
+}
+
+/// The header value for the next hop, rounded down so it never outlives
+/// ours.
+pub fn format_timeout(remaining: Duration) -> String {
+    match remaining.as_millis() {
+        ms @ 0..100_000_000 => format!("{ms}m"),
+        _ => format!("{}S", remaining.as_secs().min(99_999_999)),
+    }
+}
+
+/// Reads [`REQUEST_DEADLINE_HEADER`] and runs the rest of the request under
+/// it, answering 504 when it passes, or straight away when nothing is left
+/// of it. Without the header nothing changes: the request still has the
+/// server's own timeout.
+pub async fn propagate_deadline(mut request: Request, next: Next) -> Response {
+    let Some(value) = request.headers().get(REQUEST_DEADLINE_HEADER) else {
+        return next.run(request).await;
+    };
+    let Some(budget) = value.to_str().ok().and_then(parse_timeout) else {
+        return AppError::Validation(format!(
+            "{REQUEST_DEADLINE_HEADER} must be a grpc-timeout value such as 2S or 1500m"
+        ))
+        .into_response();
+    };
+    tracing::Span::current().set_attribute("http.request.deadline_ms", budget.as_millis() as i64);
+    if budget.is_zero() {
+        return AppError::DeadlineExceeded.into_response();
+    }
+
+    let deadline = Deadline::after(budget);
+    request.extensions_mut().insert(deadline);
+    deadline
+        .run(async move { Ok(next.run(request).await) })
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use axum::{Router, body::Body, http::StatusCode, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_timeout("1500m"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("250u"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_timeout("0m"), Some(Duration::ZERO));
+
+        for invalid in ["", "S", "2", "2s", "-1S", "1.5S", "123456789S", "2 S", "2é"] {
+            assert_eq!(parse_timeout(invalid), None, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_format_timeout_round_trips() {
+        for remaining in [Duration::from_millis(1999), Duration::from_secs(200_000)] {
+            let formatted = format_timeout(remaining);
+            let parsed = parse_timeout(&formatted).expect("formatted value parses");
+            assert!(parsed <= remaining && remaining - parsed < Duration::from_secs(1));
+        }
+        assert_eq!(format_timeout(Duration::from_micros(1999)), "1m");
+    }
+
+    #[tokio::test]
+    async fn test_run_sets_the_current_deadline() {
+        assert_eq!(Deadline::current(), None);
+
+        let deadline = Deadline::after(Duration::from_secs(5));
+        let seen = deadline.run(async { Ok(Deadline::current()) }).await;
+
+        assert_eq!(seen.unwrap(), Some(deadline));
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_work_at_the_deadline() {
+        let deadline = Deadline::after(Duration::from_millis(20));
+        let result = deadline
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(())
+            })
+            .await;
+
+        assert!(matches!(result, Err(AppError::DeadlineExceeded)));
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_expired_budget_skips_the_handler() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let handler_ran = ran.clone();
+        let app = Router::new()
+            .route(
+                "/",
+                get(move || async move { handler_ran.store(true, Ordering::Relaxed) }),
+            )
+            .layer(axum::middleware::from_fn(propagate_deadline));
+
+        let response = app
+            .oneshot(
+                Request::get("/")
+                    .header(REQUEST_DEADLINE_HEADER, "0m")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(!ran.load(Ordering::Relaxed));
+    }
+}
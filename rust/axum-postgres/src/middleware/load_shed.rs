@@ -0,0 +1,246 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use opentelemetry::KeyValue;
+use tokio::time::Instant;
+
+use crate::error::AppError;
+use crate::telemetry::{HTTP_REQUESTS_SHED, METER};
+
+/// How often the runtime's queue delay is sampled.
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tracks how loaded the server is, for shedding the routes that can wait
+/// (listings) before the ones that can't (health, auth, writes). Two
+/// signals: requests in flight, and how long a task waits for a runtime
+/// worker, which rises when the runtime is saturated even if requests
+/// are few.
+#[derive(Clone)]
+pub struct LoadShedder(Arc<ShedState>);
+
+struct ShedState {
+    max_in_flight: usize,
+    max_queue_delay: Duration,
+    retry_after_secs: u64,
+    in_flight: AtomicUsize,
+    queue_delay_micros: AtomicU64,
+}
+
+impl LoadShedder {
+    /// A zero `max_in_flight` or `max_queue_delay` turns that signal off.
+    pub fn new(max_in_flight: usize, max_queue_delay: Duration, retry_after: Duration) -> Self {
+        Self(Arc::new(ShedState {
+            max_in_flight,
+            max_queue_delay,
+            retry_after_secs: retry_after.as_secs().max(1),
+            in_flight: AtomicUsize::new(0),
+            queue_delay_micros: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.0.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn queue_delay(&self) -> Duration {
+        Duration::from_micros(self.0.queue_delay_micros.load(Ordering::Relaxed))
+    }
+
+    /// Why a low-priority request should be shed now, if it should.
+    pub fn overloaded(&self) -> Option<&'static str> {
+        let state = &self.0;
+        if state.max_in_flight > 0 && self.in_flight() > state.max_in_flight {
+            Some("in_flight")
+        } else if !state.max_queue_delay.is_zero() && self.queue_delay() > state.max_queue_delay {
+            Some("queue_delay")
+        } else {
+            None
+        }
+    }
+
+    fn enter(&self) -> InFlight {
+        self.0.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.clone())
+    }
+
+    /// Samples the queue delay: a task that yields goes to the back of its
+    /// worker's queue, so the time until it runs again is what every other
+    /// task is waiting too.
+    pub fn spawn_probe(&self) {
+        let shedder = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROBE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let started = Instant::now();
+                tokio::task::yield_now().await;
+                shedder
+                    .0
+                    .queue_delay_micros
+                    .store(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Reports `http.server.active_requests` and `runtime.queue_delay`. Call
+    /// once the meter provider is installed.
+    pub fn register_gauges(&self) {
+        let shedder = self.clone();
+        METER
+            .u64_observable_gauge("http.server.active_requests")
+            .with_description("Requests being handled, against LOAD_SHED_MAX_IN_FLIGHT")
+            .with_unit("{request}")
+            .with_callback(move |observer| observer.observe(shedder.in_flight() as u64, &[]))
+            .build();
+        let shedder = self.clone();
+        METER
+            .f64_observable_gauge("runtime.queue_delay")
+            .with_description(
+                "Time a ready task last waited for a runtime worker, against LOAD_SHED_MAX_QUEUE_DELAY_MS",
+            )
+            .with_unit("ms")
+            .with_callback(move |observer| {
+                observer.observe(shedder.queue_delay().as_secs_f64() * 1000.0, &[])
+            })
+            .build();
+    }
+}
+
+struct InFlight(LoadShedder);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts every request towards `LOAD_SHED_MAX_IN_FLIGHT`.
+pub async fn track_in_flight(
+    State(shedder): State<LoadShedder>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _in_flight = shedder.enter();
+    next.run(request).await
+}
+
+/// Answers 503 with `Retry-After` instead of running the handler while the
+/// server is overloaded. Only layered onto low-priority routes.
+pub async fn shed_low_priority(
+    State(shedder): State<LoadShedder>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(reason) = shedder.overloaded() else {
+        return next.run(request).await;
+    };
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    HTTP_REQUESTS_SHED.add(
+        1,
+        &[
+            KeyValue::new("http.route", route),
+            KeyValue::new("shed.reason", reason),
+        ],
+    );
+
+    let mut response =
+        AppError::ServiceUnavailable("Server is overloaded, retry later".to_string())
+            .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(shedder.0.retry_after_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router, body::Body, http::StatusCode, middleware::from_fn_with_state, routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    const LIMIT: Duration = Duration::from_millis(50);
+
+    fn app(shedder: &LoadShedder) -> Router {
+        Router::new()
+            .route(
+                "/api/list",
+                get(|| async { "list" })
+                    .layer(from_fn_with_state(shedder.clone(), shed_low_priority)),
+            )
+            .route("/api/health", get(|| async { "ok" }))
+            .layer(from_fn_with_state(shedder.clone(), track_in_flight))
+    }
+
+    async fn status(app: Router, uri: &str) -> (StatusCode, Option<HeaderValue>) {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        (
+            response.status(),
+            response.headers().get(RETRY_AFTER).cloned(),
+        )
+    }
+
+    #[test]
+    fn test_overloaded_by_either_signal() {
+        let shedder = LoadShedder::new(2, LIMIT, Duration::from_secs(1));
+        assert_eq!(shedder.overloaded(), None);
+
+        let held = [shedder.enter(), shedder.enter(), shedder.enter()];
+        assert_eq!(shedder.overloaded(), Some("in_flight"));
+        drop(held);
+        assert_eq!(shedder.in_flight(), 0);
+
+        shedder
+            .0
+            .queue_delay_micros
+            .store(80_000, Ordering::Relaxed);
+        assert_eq!(shedder.overloaded(), Some("queue_delay"));
+
+        let disabled = LoadShedder::new(0, Duration::ZERO, Duration::from_secs(1));
+        disabled
+            .0
+            .queue_delay_micros
+            .store(80_000, Ordering::Relaxed);
+        let _held = [disabled.enter(), disabled.enter()];
+        assert_eq!(disabled.overloaded(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sheds_low_priority_routes_only() {
+        let shedder = LoadShedder::new(1, LIMIT, Duration::from_secs(2));
+        let app = app(&shedder);
+        assert_eq!(status(app.clone(), "/api/list").await.0, StatusCode::OK);
+
+        let _busy = shedder.enter();
+        let (shed, retry_after) = status(app.clone(), "/api/list").await;
+        assert_eq!(shed, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(retry_after, Some(HeaderValue::from(2)));
+        assert_eq!(status(app, "/api/health").await.0, StatusCode::OK);
+
+        assert_metric_recorded(
+            "http.requests.shed",
+            &[
+                KeyValue::new("http.route", "/api/list"),
+                KeyValue::new("shed.reason", "in_flight"),
+            ],
+        );
+    }
+}
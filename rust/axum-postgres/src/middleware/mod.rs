@@ -1,3 +1,12 @@
 mod auth;
+mod authz;
+mod client;
+mod deadline;
+mod load_shed;
+mod structured_errors;
 
-pub use auth::{AuthUser, OptionalAuthUser};
+pub use auth::{AdminAuth, AuthUser, JwtAuthUser, OptionalAuthUser};
+pub use authz::{authorize, load_policy};
+pub use deadline::{Deadline, REQUEST_DEADLINE_HEADER, format_timeout, propagate_deadline};
+pub use load_shed::{LoadShedder, shed_low_priority, track_in_flight};
+pub use structured_errors::structured_errors;
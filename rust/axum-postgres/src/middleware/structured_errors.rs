@@ -0,0 +1,145 @@
+use axum::{
+    Json,
+    body::to_bytes,
+    extract::Request,
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    error::{ErrorDetails, get_trace_id},
+    telemetry::current_request_id,
+};
+
+const V2_PREFIX: &str = "/api/v2/";
+
+/// Longer bodies are not an extractor's rejection message.
+const MAX_MESSAGE_BYTES: usize = 4 * 1024;
+
+/// Renders every `/api/v2` error as
+/// `{"error": {"code", "message", "status", "traceId", "requestId"}}`. Runs
+/// outside the deadline and load-shedding layers so their errors, axum's
+/// extractor rejections and unmatched routes get the same shape as a
+/// handler's. v1 responses pass through untouched.
+pub async fn structured_errors(request: Request, next: Next) -> Response {
+    if !request.uri().path().starts_with(V2_PREFIX) {
+        return next.run(request).await;
+    }
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let (code, message) = match parts.extensions.remove::<ErrorDetails>() {
+        Some(details) => (details.code.to_string(), details.message),
+        // axum's rejections are plain text; its 404 and 405 are empty.
+        None => {
+            let text = to_bytes(body, MAX_MESSAGE_BYTES)
+                .await
+                .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+                .unwrap_or_default();
+            let reason = status.canonical_reason().unwrap_or("Error");
+            let message = if text.is_empty() {
+                reason.to_string()
+            } else {
+                text
+            };
+            (code_for(status, reason), message)
+        }
+    };
+
+    let mut error = json!({
+        "code": code,
+        "message": message,
+        "status": status.as_u16(),
+    });
+    if let Some(trace_id) = get_trace_id() {
+        error["traceId"] = trace_id.into();
+    }
+    if let Some(request_id) = current_request_id() {
+        error["requestId"] = request_id.into();
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    (parts, Json(json!({ "error": error }))).into_response()
+}
+
+/// The [`AppError::kind`](crate::error::AppError::kind) a handler would have
+/// used, else the reason phrase: `Method Not Allowed` is `MethodNotAllowed`.
+fn code_for(status: StatusCode, reason: &str) -> String {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "Validation".to_string(),
+        _ => reason.replace(' ', ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware::from_fn, routing::get};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::error::AppError;
+
+    fn app() -> Router {
+        let missing = || async { Err::<(), _>(AppError::NotFound("Article not found".into())) };
+        let query =
+            |axum::extract::Query(_): axum::extract::Query<Vec<(String, i64)>>| async { "ok" };
+        Router::new()
+            .route("/api/v2/missing", get(missing))
+            .route("/api/v2/query", get(query))
+            .route("/api/missing", get(missing))
+            .layer(from_fn(structured_errors))
+    }
+
+    async fn get_json(uri: &str) -> (StatusCode, Value) {
+        let response = app()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_app_errors_are_nested_with_their_kind() {
+        let (status, body) = get_json("/api/v2/missing").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            body,
+            json!({ "error": { "code": "NotFound", "message": "Article not found", "status": 404 } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejections_and_unmatched_routes_get_the_same_shape() {
+        let (status, body) = get_json("/api/v2/query?limit=ten").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "Validation");
+        assert!(
+            body["error"]["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Failed to deserialize query string")
+        );
+
+        let (status, body) = get_json("/api/v2/nothing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NotFound");
+        assert_eq!(body["error"]["message"], "Not Found");
+    }
+
+    #[tokio::test]
+    async fn test_v1_errors_are_untouched() {
+        let (status, body) = get_json("/api/missing").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, json!({ "error": "Article not found", "status": 404 }));
+    }
+}
@@ -0,0 +1,157 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+use super::OrgId;
+
+/// What a personal access token may do. `read` tokens are limited to safe
+/// methods (`GET`, `HEAD`, ...); `write` tokens may also mutate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Write,
+}
+
+impl TokenScope {
+    pub fn required_for(method: &Method) -> Self {
+        if method.is_safe() {
+            TokenScope::Read
+        } else {
+            TokenScope::Write
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: OffsetDateTime,
+    pub last_used_at: Option<OffsetDateTime>,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl ApiToken {
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.iter().any(|s| s == scope.as_str())
+    }
+}
+
+/// A usable token and the tenant of the user it belongs to.
+#[derive(Debug, Clone, FromRow)]
+pub struct ActiveApiToken {
+    #[sqlx(flatten)]
+    pub token: ApiToken,
+    pub org_id: OrgId,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenInput {
+    pub name: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<TokenScope>,
+    /// Never expires when unset.
+    pub expires_in_days: Option<i64>,
+}
+
+fn default_scopes() -> Vec<TokenScope> {
+    vec![TokenScope::Read]
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenDto {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl From<ApiToken> for ApiTokenDto {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            created_at: token.created_at,
+            last_used_at: token.last_used_at,
+            expires_at: token.expires_at,
+        }
+    }
+}
+
+/// The only response that carries the token itself.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiTokenResponse {
+    pub token: String,
+    pub api_token: ApiTokenDto,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokensResponse {
+    pub api_tokens: Vec<ApiTokenDto>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_input_defaults_to_read_scope() {
+        let input: CreateApiTokenInput =
+            serde_json::from_str(r#"{"name": "ci"}"#).expect("deserialization should succeed");
+        assert_eq!(input.scopes, [TokenScope::Read]);
+        assert!(input.expires_in_days.is_none());
+
+        let input: CreateApiTokenInput =
+            serde_json::from_str(r#"{"name": "ci", "scopes": ["read", "write"]}"#)
+                .expect("deserialization should succeed");
+        assert_eq!(input.scopes, [TokenScope::Read, TokenScope::Write]);
+        assert!(
+            serde_json::from_str::<CreateApiTokenInput>(r#"{"name": "ci", "scopes": ["admin"]}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_has_scope() {
+        let token = ApiToken {
+            id: 1,
+            user_id: 2,
+            name: "ci".to_string(),
+            token_prefix: "pat_0123abcd".to_string(),
+            scopes: vec!["read".to_string()],
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            last_used_at: None,
+            expires_at: None,
+        };
+        assert!(token.has_scope(TokenScope::Read));
+        assert!(!token.has_scope(TokenScope::Write));
+    }
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(TokenScope::required_for(&Method::GET), TokenScope::Read);
+        assert_eq!(TokenScope::required_for(&Method::HEAD), TokenScope::Read);
+        assert_eq!(TokenScope::required_for(&Method::POST), TokenScope::Write);
+        assert_eq!(TokenScope::required_for(&Method::DELETE), TokenScope::Write);
+    }
+}
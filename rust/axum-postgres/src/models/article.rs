@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use sqlx::FromRow;
+use sqlx::types::Json;
 use time::OffsetDateTime;
 
 use super::ProfileResponse;
@@ -13,6 +17,8 @@ pub struct Article {
     pub body: String,
     pub author_id: i32,
     pub favorites_count: i32,
+    pub views_count: i64,
+    pub cover_image: String,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
@@ -26,12 +32,47 @@ pub struct ArticleWithAuthor {
     pub body: String,
     pub author_id: i32,
     pub favorites_count: i32,
+    pub views_count: i64,
+    pub cover_image: String,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    pub reading_time_minutes: Option<i32>,
+    pub summary: Option<String>,
+    pub author_username: String,
     pub author_name: String,
     pub author_email: String,
     pub author_bio: String,
     pub author_image: String,
+    /// The owner first, then co-authors.
+    pub authors: Json<Vec<ProfileResponse>>,
+}
+
+impl ArticleWithAuthor {
+    /// The owner or a co-author.
+    pub fn is_author(&self, user_id: i32) -> bool {
+        self.authors.iter().any(|author| author.id == user_id)
+    }
+
+    /// The editable fields, as recorded in the audit log.
+    pub fn audit_fields(&self) -> Value {
+        json!({
+            "slug": self.slug,
+            "title": self.title,
+            "description": self.description,
+            "body": self.body,
+            "author_id": self.author_id,
+            "cover_image": self.cover_image,
+        })
+    }
+}
+
+/// What an article's trending score is computed from.
+#[derive(Debug, Clone, FromRow)]
+pub struct ArticleEngagement {
+    pub id: i32,
+    pub favorites_count: i32,
+    pub views_count: i64,
+    pub created_at: OffsetDateTime,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,65 +93,185 @@ pub struct ArticleDto {
     pub title: String,
     pub description: String,
     pub body: String,
+    /// `body` rendered from markdown and sanitized.
+    pub body_html: String,
     pub favorites_count: i32,
     pub favorited: bool,
+    pub views_count: i64,
+    pub cover_image: String,
+    /// `None` until the enrichment job has run.
+    pub reading_time_minutes: Option<i32>,
+    pub summary: Option<String>,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
     pub author: ProfileResponse,
+    pub authors: Vec<ProfileResponse>,
 }
 
 impl ArticleDto {
-    pub fn from_article_with_author(article: ArticleWithAuthor, favorited: bool) -> Self {
+    pub fn from_article_with_author(
+        article: ArticleWithAuthor,
+        favorited: bool,
+        body_html: String,
+    ) -> Self {
         Self {
             id: article.id,
             slug: article.slug,
             title: article.title,
             description: article.description,
             body: article.body,
+            body_html,
             favorites_count: article.favorites_count,
             favorited,
+            views_count: article.views_count,
+            cover_image: article.cover_image,
+            reading_time_minutes: article.reading_time_minutes,
+            summary: article.summary,
             created_at: article.created_at,
             updated_at: article.updated_at,
             author: ProfileResponse {
                 id: article.author_id,
                 email: article.author_email,
+                username: article.author_username,
                 name: article.author_name,
                 bio: article.author_bio,
                 image: article.author_image,
             },
+            authors: article.authors.0,
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
-pub struct CreateArticleInput {
-    pub title: String,
-    pub description: Option<String>,
-    pub body: String,
+pub struct AddAuthorInput {
+    pub user_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferArticleInput {
+    pub user_id: i32,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct UpdateArticleInput {
-    pub title: Option<String>,
-    pub description: Option<String>,
-    pub body: Option<String>,
+pub struct FavoritedLookupInput {
+    pub article_ids: Vec<i32>,
+}
+
+/// Every requested ID, `true` where the caller has favorited it. IDs that
+/// don't exist in the caller's organization are `false`.
+#[derive(Debug, Serialize)]
+pub struct FavoritedResponse {
+    pub favorited: BTreeMap<i32, bool>,
+}
+
+/// Where a keyset page left off: the last article's `(created_at, id)`, in
+/// the newest-first order the page was read in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArticleCursor {
+    pub created_at: OffsetDateTime,
+    pub id: i32,
+}
+
+impl ArticleCursor {
+    pub fn after(article: &ArticleWithAuthor) -> Self {
+        Self {
+            created_at: article.created_at,
+            id: article.id,
+        }
+    }
+
+    /// Hex, so clients pass it back rather than building one, and the format
+    /// can change without breaking them.
+    pub fn encode(&self) -> String {
+        let micros = self.created_at.unix_timestamp_nanos() / 1_000;
+        hex::encode(format!("{micros}:{}", self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let (micros, id) = raw.split_once(':')?;
+        let nanos = micros.parse::<i128>().ok()?.checked_mul(1_000)?;
+        Some(Self {
+            created_at: OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// A keyset page; `next` is `None` on the last one.
+#[derive(Debug)]
+pub struct ArticlePage {
+    pub articles: Vec<ArticleDto>,
+    pub next: Option<ArticleCursor>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct ListArticlesQuery {
+pub struct TrendingArticlesQuery {
     #[serde(default = "default_limit")]
     pub limit: i64,
     #[serde(default)]
     pub offset: i64,
-    pub author: Option<String>,
 }
 
 fn default_limit() -> i64 {
     20
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SuggestArticlesQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default = "default_suggest_limit")]
+    pub limit: i64,
+}
+
+fn default_suggest_limit() -> i64 {
+    5
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ArticleSuggestion {
+    pub slug: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuggestionsResponse {
+    pub suggestions: Vec<ArticleSuggestion>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportArticlesQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,36 +286,77 @@ mod tests {
             body: "The body of the article".to_string(),
             author_id: 42,
             favorites_count: 10,
+            views_count: 250,
+            cover_image: String::new(),
             created_at: datetime!(2024-01-15 10:30:00 UTC),
             updated_at: datetime!(2024-01-16 15:45:00 UTC),
+            reading_time_minutes: Some(1),
+            summary: Some("A test summary.".to_string()),
+            author_username: "john-doe".to_string(),
             author_name: "John Doe".to_string(),
             author_email: "john@example.com".to_string(),
             author_bio: "A test user".to_string(),
             author_image: "https://example.com/avatar.jpg".to_string(),
+            authors: Json(vec![
+                ProfileResponse {
+                    id: 42,
+                    email: "john@example.com".to_string(),
+                    username: "john-doe".to_string(),
+                    name: "John Doe".to_string(),
+                    bio: "A test user".to_string(),
+                    image: "https://example.com/avatar.jpg".to_string(),
+                },
+                ProfileResponse {
+                    id: 43,
+                    email: "jane@example.com".to_string(),
+                    username: "jane-roe".to_string(),
+                    name: "Jane Roe".to_string(),
+                    bio: String::new(),
+                    image: String::new(),
+                },
+            ]),
         }
     }
 
     #[test]
     fn test_article_dto_from_article_with_author() {
         let article = create_test_article_with_author();
-        let dto = ArticleDto::from_article_with_author(article.clone(), true);
+        let dto =
+            ArticleDto::from_article_with_author(article.clone(), true, "<p>Body</p>\n".into());
 
         assert_eq!(dto.id, article.id);
         assert_eq!(dto.slug, article.slug);
         assert_eq!(dto.title, article.title);
         assert_eq!(dto.description, article.description);
         assert_eq!(dto.body, article.body);
+        assert_eq!(dto.body_html, "<p>Body</p>\n");
         assert_eq!(dto.favorites_count, article.favorites_count);
         assert!(dto.favorited);
+        assert_eq!(dto.views_count, 250);
+        assert_eq!(dto.reading_time_minutes, Some(1));
+        assert_eq!(dto.summary, article.summary);
         assert_eq!(dto.author.id, article.author_id);
+        assert_eq!(dto.author.username, article.author_username);
         assert_eq!(dto.author.name, article.author_name);
         assert_eq!(dto.author.email, article.author_email);
     }
 
+    #[test]
+    fn test_article_authors() {
+        let article = create_test_article_with_author();
+        assert!(article.is_author(42));
+        assert!(article.is_author(43));
+        assert!(!article.is_author(44));
+
+        let dto = ArticleDto::from_article_with_author(article, false, String::new());
+        let names: Vec<_> = dto.authors.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["John Doe", "Jane Roe"]);
+    }
+
     #[test]
     fn test_article_dto_not_favorited() {
         let article = create_test_article_with_author();
-        let dto = ArticleDto::from_article_with_author(article, false);
+        let dto = ArticleDto::from_article_with_author(article, false, String::new());
 
         assert!(!dto.favorited);
     }
@@ -162,7 +364,7 @@ mod tests {
     #[test]
     fn test_article_response_serialization() {
         let article = create_test_article_with_author();
-        let dto = ArticleDto::from_article_with_author(article, true);
+        let dto = ArticleDto::from_article_with_author(article, true, String::new());
         let response = ArticleResponse { article: dto };
 
         let json = serde_json::to_string(&response).expect("serialization should succeed");
@@ -174,7 +376,7 @@ mod tests {
     #[test]
     fn test_articles_response_serialization() {
         let article = create_test_article_with_author();
-        let dto = ArticleDto::from_article_with_author(article, false);
+        let dto = ArticleDto::from_article_with_author(article, false, String::new());
         let response = ArticlesResponse {
             articles: vec![dto],
             total: 1,
@@ -186,58 +388,29 @@ mod tests {
     }
 
     #[test]
-    fn test_create_article_input_deserialization() {
-        let json =
-            r#"{"title": "New Article", "description": "Description", "body": "Body content"}"#;
-        let input: CreateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, "New Article");
-        assert_eq!(input.description, Some("Description".to_string()));
-        assert_eq!(input.body, "Body content");
-    }
-
-    #[test]
-    fn test_create_article_input_without_description() {
-        let json = r#"{"title": "New Article", "body": "Body content"}"#;
-        let input: CreateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, "New Article");
-        assert!(input.description.is_none());
-        assert_eq!(input.body, "Body content");
+    fn test_export_query_defaults_to_ndjson() {
+        let query: ExportArticlesQuery =
+            serde_json::from_str("{}").expect("deserialization should succeed");
+        assert_eq!(query.format, ExportFormat::Ndjson);
     }
 
     #[test]
-    fn test_update_article_input_partial() {
-        let json = r#"{"title": "Updated Title"}"#;
-        let input: UpdateArticleInput =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.title, Some("Updated Title".to_string()));
-        assert!(input.description.is_none());
-        assert!(input.body.is_none());
+    fn test_export_query_csv() {
+        let query: ExportArticlesQuery =
+            serde_json::from_str(r#"{"format": "csv"}"#).expect("deserialization should succeed");
+        assert_eq!(query.format, ExportFormat::Csv);
+        assert_eq!(query.format.content_type(), "text/csv; charset=utf-8");
     }
 
     #[test]
-    fn test_list_articles_query_defaults() {
-        let json = r#"{}"#;
-        let query: ListArticlesQuery =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(query.limit, 20);
-        assert_eq!(query.offset, 0);
-        assert!(query.author.is_none());
-    }
+    fn test_article_cursor_round_trips() {
+        let cursor = ArticleCursor {
+            created_at: datetime!(2024-01-15 10:30:00.123456 UTC),
+            id: 42,
+        };
 
-    #[test]
-    fn test_list_articles_query_with_params() {
-        let json = r#"{"limit": 10, "offset": 5, "author": "john"}"#;
-        let query: ListArticlesQuery =
-            serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(query.limit, 10);
-        assert_eq!(query.offset, 5);
-        assert_eq!(query.author, Some("john".to_string()));
+        assert_eq!(ArticleCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(ArticleCursor::decode("not-hex"), None);
+        assert_eq!(ArticleCursor::decode(&hex::encode("42")), None);
     }
 }
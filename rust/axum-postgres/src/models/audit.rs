@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// Who performed an audited operation. The admin API authenticates with a
+/// shared token, so admin entries have no user id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    User(i32),
+    Admin,
+}
+
+impl Actor {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Actor::User(_) => "user",
+            Actor::Admin => "admin",
+        }
+    }
+
+    pub fn id(&self) -> Option<i32> {
+        match self {
+            Actor::User(id) => Some(*id),
+            Actor::Admin => None,
+        }
+    }
+}
+
+/// An operation to record, built by the service that performed it.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub actor: Actor,
+    pub action: &'static str,
+    pub entity_type: &'static str,
+    pub entity_id: String,
+    pub changes: Value,
+}
+
+impl AuditEntry {
+    pub fn new(
+        actor: Actor,
+        action: &'static str,
+        entity_type: &'static str,
+        entity_id: impl ToString,
+    ) -> Self {
+        Self {
+            actor,
+            action,
+            entity_type,
+            entity_id: entity_id.to_string(),
+            changes: Value::Object(Map::new()),
+        }
+    }
+
+    /// `before` is `null` for creations and `after` is `null` for deletions.
+    pub fn changes(mut self, before: Value, after: Value) -> Self {
+        self.changes = diff(&before, &after);
+        self
+    }
+}
+
+/// `{"field": {"before": .., "after": ..}}` for every top-level field whose
+/// value differs. A missing field or a non-object side counts as `null`.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new && !changes.contains_key(key) {
+            changes.insert(key.clone(), json!({ "before": old, "after": new }));
+        }
+    }
+    Value::Object(changes)
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub actor_type: String,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub changes: Value,
+    pub trace_id: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// Filters for `GET /api/admin/audit`; every one that is set must match.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogQuery {
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+    pub actor_type: Option<String>,
+    pub actor_id: Option<i32>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditLogEntry>,
+    pub total: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_keeps_only_changed_fields() {
+        let before = json!({"title": "Old", "body": "Same", "description": "x"});
+        let after = json!({"title": "New", "body": "Same", "description": ""});
+        assert_eq!(
+            diff(&before, &after),
+            json!({
+                "title": {"before": "Old", "after": "New"},
+                "description": {"before": "x", "after": ""},
+            })
+        );
+        assert_eq!(diff(&before, &before), json!({}));
+    }
+
+    #[test]
+    fn test_diff_of_creation_and_deletion() {
+        let article = json!({"slug": "hello", "title": "Hello"});
+        assert_eq!(
+            diff(&Value::Null, &article),
+            json!({
+                "slug": {"before": null, "after": "hello"},
+                "title": {"before": null, "after": "Hello"},
+            })
+        );
+        assert_eq!(
+            diff(&article, &Value::Null),
+            json!({
+                "slug": {"before": "hello", "after": null},
+                "title": {"before": "Hello", "after": null},
+            })
+        );
+    }
+
+    #[test]
+    fn test_actor() {
+        assert_eq!(Actor::User(7).kind(), "user");
+        assert_eq!(Actor::User(7).id(), Some(7));
+        assert_eq!(Actor::Admin.kind(), "admin");
+        assert_eq!(Actor::Admin.id(), None);
+    }
+}
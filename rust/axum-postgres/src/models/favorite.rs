@@ -1,3 +1,4 @@
+use serde::Serialize;
 use sqlx::FromRow;
 use time::OffsetDateTime;
 
@@ -8,3 +9,12 @@ pub struct Favorite {
     pub article_id: i32,
     pub created_at: OffsetDateTime,
 }
+
+/// A favorite in a user's data export.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FavoritedArticle {
+    pub slug: String,
+    pub title: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub favorited_at: OffsetDateTime,
+}
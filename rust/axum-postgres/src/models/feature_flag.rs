@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+pub const ALL_ENVIRONMENTS: &str = "*";
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub environment: String,
+    pub enabled: bool,
+    pub description: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagDto {
+    pub name: String,
+    pub enabled: bool,
+    pub source: String,
+    pub description: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagsResponse {
+    pub environment: String,
+    pub flags: Vec<FeatureFlagDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFeatureFlagInput {
+    pub enabled: bool,
+    pub environment: Option<String>,
+    pub description: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_feature_flag_input_deserialization() {
+        let input: UpdateFeatureFlagInput =
+            serde_json::from_str(r#"{"enabled": true}"#).expect("deserialization should succeed");
+
+        assert!(input.enabled);
+        assert!(input.environment.is_none());
+        assert!(input.description.is_none());
+    }
+}
@@ -1,7 +1,26 @@
+mod api_token;
 mod article;
+mod audit;
 mod favorite;
+mod feature_flag;
+mod organization;
+mod security;
+mod session;
 mod user;
+pub mod v2;
 
+pub use api_token::*;
 pub use article::*;
+pub use audit::*;
+pub use blog_domain::{
+    CreateArticleInput, ListArticlesQuery, LoginInput, ProfileResponse, PublicProfile,
+    PublicProfileResponse, RecoveryCodesResponse, TwoFactorChallenge, TwoFactorChallengeResponse,
+    TwoFactorCodeInput, TwoFactorLoginInput, TwoFactorSetupResponse, UpdateArticleInput,
+    UserArticlesQuery,
+};
 pub use favorite::*;
+pub use feature_flag::*;
+pub use organization::*;
+pub use security::*;
+pub use session::*;
 pub use user::*;
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// The tenant a row belongs to. Tenant-scoped repository methods take one,
+/// so a query can't be written without deciding whose data it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct OrgId(pub i32);
+
+impl OrgId {
+    /// Holds the rows that predate tenancy, and serves anonymous requests.
+    pub const DEFAULT: OrgId = OrgId(1);
+}
+
+pub const DEFAULT_ORG_SLUG: &str = "default";
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Organization {
+    pub id: OrgId,
+    pub slug: String,
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationInput {
+    pub slug: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationsResponse {
+    pub organizations: Vec<Organization>,
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// Mean Earth radius, as the haversine formula uses it.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Where an IP address is, by the GeoIP data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GeoPoint {
+    /// Great-circle distance.
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// The user's most recent sign-in with a known location.
+#[derive(Debug, Clone, FromRow)]
+pub struct LastSignIn {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub last_ip: Option<String>,
+    pub last_seen_at: OffsetDateTime,
+}
+
+impl LastSignIn {
+    pub fn location(&self) -> GeoPoint {
+        GeoPoint {
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+    }
+}
+
+/// A device the user has signed in from, as their data export lists it.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct UserDevice {
+    pub user_agent: String,
+    pub last_ip: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub first_seen_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_seen_at: OffsetDateTime,
+}
+
+/// What the login signals report, as `security.event` on OpenTelemetry
+/// events and metrics and, prefixed with `security.`, as the audit action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityEvent {
+    NewDevice,
+    ImpossibleTravel,
+    RepeatedFailures,
+}
+
+impl SecurityEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEvent::NewDevice => "login.new_device",
+            SecurityEvent::ImpossibleTravel => "login.impossible_travel",
+            SecurityEvent::RepeatedFailures => "login.repeated_failures",
+        }
+    }
+
+    pub fn audit_action(&self) -> &'static str {
+        match self {
+            SecurityEvent::NewDevice => "security.login.new_device",
+            SecurityEvent::ImpossibleTravel => "security.login.impossible_travel",
+            SecurityEvent::RepeatedFailures => "security.login.repeated_failures",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_km() {
+        let london = GeoPoint {
+            latitude: 51.5074,
+            longitude: -0.1278,
+        };
+        let new_york = GeoPoint {
+            latitude: 40.7128,
+            longitude: -74.0060,
+        };
+
+        assert!((london.distance_km(&new_york) - 5570.0).abs() < 10.0);
+        assert!((new_york.distance_km(&london) - london.distance_km(&new_york)).abs() < 1e-9);
+        assert_eq!(london.distance_km(&london), 0.0);
+    }
+
+    #[test]
+    fn test_audit_action_prefixes_the_event() {
+        for event in [
+            SecurityEvent::NewDevice,
+            SecurityEvent::ImpossibleTravel,
+            SecurityEvent::RepeatedFailures,
+        ] {
+            assert_eq!(event.audit_action(), format!("security.{}", event.as_str()));
+        }
+    }
+}
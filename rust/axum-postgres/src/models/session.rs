@@ -0,0 +1,84 @@
+use std::net::IpAddr;
+
+use serde::Serialize;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// The device a login or registration came from, recorded on the session
+/// it starts. `ip` is `None` when the connection's address is unknown, or
+/// `TRUSTED_PROXY_HOPS` names more proxies than `X-Forwarded-For` lists.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: String,
+    pub ip: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_agent: String,
+    pub created_at: OffsetDateTime,
+    pub last_seen_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+/// `current` marks the session of the token making the request.
+#[derive(Debug, Serialize)]
+pub struct SessionDto {
+    pub id: i32,
+    pub user_agent: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_seen_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub current: bool,
+}
+
+impl SessionDto {
+    pub fn new(session: Session, current: Option<i32>) -> Self {
+        Self {
+            current: current == Some(session.id),
+            id: session.id,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionDto>,
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_session_dto_marks_the_current_session() {
+        let session = Session {
+            id: 7,
+            user_id: 1,
+            user_agent: "curl/8.5.0".to_string(),
+            created_at: datetime!(2024-01-15 10:30:00 UTC),
+            last_seen_at: datetime!(2024-01-16 15:45:00 UTC),
+            expires_at: datetime!(2024-01-22 10:30:00 UTC),
+        };
+
+        assert!(SessionDto::new(session.clone(), Some(7)).current);
+        assert!(!SessionDto::new(session.clone(), Some(8)).current);
+
+        let json = serde_json::to_value(SessionDto::new(session, None)).unwrap();
+        assert_eq!(json["current"], false);
+        assert_eq!(json["user_agent"], "curl/8.5.0");
+        assert_eq!(json["last_seen_at"], "2024-01-16T15:45:00Z");
+        assert!(json.get("user_id").is_none());
+    }
+}
@@ -1,13 +1,20 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
 
+use super::{OrgId, ProfileResponse, PublicProfile, PublicProfileResponse, TwoFactorChallenge};
+
 #[derive(Debug, Clone, FromRow, Serialize)]
 pub struct User {
     pub id: i32,
+    pub org_id: OrgId,
     pub email: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    pub username: String,
     pub name: String,
     pub bio: String,
     pub image: String,
@@ -21,13 +28,12 @@ pub struct User {
 pub struct RegisterInput {
     pub email: String,
     pub password: String,
+    /// Handle for `/api/profiles/{username}` and `?author=`, unique within
+    /// the organization.
+    pub username: String,
     pub name: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct LoginInput {
-    pub email: String,
-    pub password: String,
+    /// Organization slug; the default organization when unset.
+    pub org: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -35,10 +41,20 @@ pub struct UserResponse {
     pub user: UserWithToken,
 }
 
+/// What a correct password gets: the user and their token, or, with
+/// two-factor authentication enabled, a challenge for the code.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Authenticated(UserWithToken),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserWithToken {
     pub id: i32,
+    pub org_id: OrgId,
     pub email: String,
+    pub username: String,
     pub name: String,
     pub bio: String,
     pub image: String,
@@ -49,7 +65,9 @@ impl UserWithToken {
     pub fn from_user(user: &User, token: String) -> Self {
         Self {
             id: user.id,
+            org_id: user.org_id,
             email: user.email.clone(),
+            username: user.username.clone(),
             name: user.name.clone(),
             bio: user.bio.clone(),
             image: user.image.clone(),
@@ -58,20 +76,12 @@ impl UserWithToken {
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ProfileResponse {
-    pub id: i32,
-    pub email: String,
-    pub name: String,
-    pub bio: String,
-    pub image: String,
-}
-
 impl From<User> for ProfileResponse {
     fn from(user: User) -> Self {
         Self {
             id: user.id,
             email: user.email,
+            username: user.username,
             name: user.name,
             bio: user.bio,
             image: user.image,
@@ -79,6 +89,65 @@ impl From<User> for ProfileResponse {
     }
 }
 
+impl From<User> for PublicProfileResponse {
+    fn from(user: User) -> Self {
+        Self {
+            profile: PublicProfile {
+                username: user.username,
+                name: user.name,
+                bio: user.bio,
+                image: user.image,
+            },
+        }
+    }
+}
+
+/// What `DELETE /api/user` does with the articles a user authored. Either
+/// way their favorites and avatar are removed and their account row is
+/// anonymized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletionPolicy {
+    /// Articles stay, credited to "Deleted user".
+    Anonymize,
+    /// Articles they own are deleted and they are removed as a co-author.
+    Delete,
+}
+
+impl DeletionPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Anonymize => "anonymize",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+impl FromStr for DeletionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "anonymize" => Ok(Self::Anonymize),
+            "delete" => Ok(Self::Delete),
+            other => Err(format!("expected anonymize or delete, got {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for DeletionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `DELETE /api/user`: the account is gone; the job removes the rest.
+#[derive(Debug, Serialize)]
+pub struct DeletedAccountResponse {
+    pub job_id: i64,
+    pub policy: DeletionPolicy,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,8 +156,10 @@ mod tests {
     fn create_test_user() -> User {
         User {
             id: 1,
+            org_id: OrgId(3),
             email: "test@example.com".to_string(),
             password_hash: "hashed_password".to_string(),
+            username: "test-user".to_string(),
             name: "Test User".to_string(),
             bio: "A bio".to_string(),
             image: "https://example.com/avatar.jpg".to_string(),
@@ -105,6 +176,7 @@ mod tests {
         let user_with_token = UserWithToken::from_user(&user, token.clone());
 
         assert_eq!(user_with_token.id, user.id);
+        assert_eq!(user_with_token.org_id, user.org_id);
         assert_eq!(user_with_token.email, user.email);
         assert_eq!(user_with_token.name, user.name);
         assert_eq!(user_with_token.bio, user.bio);
@@ -137,22 +209,20 @@ mod tests {
 
     #[test]
     fn test_register_input_deserialization() {
-        let json = r#"{"email": "new@example.com", "password": "secret123", "name": "New User"}"#;
+        let json = r#"{"email": "new@example.com", "password": "secret123", "username": "new-user", "name": "New User"}"#;
         let input: RegisterInput =
             serde_json::from_str(json).expect("deserialization should succeed");
 
         assert_eq!(input.email, "new@example.com");
         assert_eq!(input.password, "secret123");
+        assert_eq!(input.username, "new-user");
         assert_eq!(input.name, "New User");
-    }
+        assert!(input.org.is_none());
 
-    #[test]
-    fn test_login_input_deserialization() {
-        let json = r#"{"email": "test@example.com", "password": "secret123"}"#;
-        let input: LoginInput = serde_json::from_str(json).expect("deserialization should succeed");
-
-        assert_eq!(input.email, "test@example.com");
-        assert_eq!(input.password, "secret123");
+        let json = r#"{"email": "a@example.com", "password": "p", "username": "a-1", "name": "A", "org": "acme"}"#;
+        let input: RegisterInput =
+            serde_json::from_str(json).expect("deserialization should succeed");
+        assert_eq!(input.org.as_deref(), Some("acme"));
     }
 
     #[test]
@@ -166,6 +236,16 @@ mod tests {
         let json = serde_json::to_string(&response).expect("serialization should succeed");
         assert!(json.contains("\"user\":{"));
         assert!(json.contains("\"token\":\"token\""));
+        assert!(json.contains("\"org_id\":3"));
+    }
+
+    #[test]
+    fn test_public_profile_omits_email() {
+        let response = PublicProfileResponse::from(create_test_user());
+
+        let json = serde_json::to_string(&response).expect("serialization should succeed");
+        assert!(json.contains("\"profile\":{\"username\":\"test-user\""));
+        assert!(!json.contains("test@example.com"));
     }
 
     #[test]
@@ -177,4 +257,21 @@ mod tests {
         assert!(json.contains("\"id\":1"));
         assert!(json.contains("\"email\":\"test@example.com\""));
     }
+
+    #[test]
+    fn test_deletion_policy_parses_case_insensitively() {
+        assert_eq!(
+            "Anonymize".parse::<DeletionPolicy>(),
+            Ok(DeletionPolicy::Anonymize)
+        );
+        assert_eq!(
+            "delete".parse::<DeletionPolicy>(),
+            Ok(DeletionPolicy::Delete)
+        );
+        assert!("purge".parse::<DeletionPolicy>().is_err());
+        assert_eq!(
+            serde_json::to_value(DeletionPolicy::Delete).unwrap(),
+            serde_json::json!("delete")
+        );
+    }
 }
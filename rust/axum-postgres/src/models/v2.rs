@@ -0,0 +1,242 @@
+//! The `/api/v2` contract: the v1 models in camelCase, and keyset pages
+//! instead of offset and total. Built from the v1 models, so the services
+//! underneath don't know which version they're serving.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::{OrgId, ProfileResponse};
+
+#[derive(Debug, Serialize)]
+pub struct ArticleResponse {
+    pub article: ArticleDto,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticlesResponse {
+    pub articles: Vec<ArticleDto>,
+    /// Pass back as `cursor` for the next page; `null` on the last one.
+    pub next_cursor: Option<String>,
+}
+
+impl From<super::ArticlePage> for ArticlesResponse {
+    fn from(page: super::ArticlePage) -> Self {
+        Self {
+            articles: page.articles.into_iter().map(ArticleDto::from).collect(),
+            next_cursor: page.next.map(|cursor| cursor.encode()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleDto {
+    pub id: i32,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub body_html: String,
+    pub favorites_count: i32,
+    pub favorited: bool,
+    pub views_count: i64,
+    pub cover_image: String,
+    pub reading_time_minutes: Option<i32>,
+    pub summary: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    pub author: ProfileResponse,
+    pub authors: Vec<ProfileResponse>,
+}
+
+impl From<super::ArticleDto> for ArticleDto {
+    fn from(article: super::ArticleDto) -> Self {
+        Self {
+            id: article.id,
+            slug: article.slug,
+            title: article.title,
+            description: article.description,
+            body: article.body,
+            body_html: article.body_html,
+            favorites_count: article.favorites_count,
+            favorited: article.favorited,
+            views_count: article.views_count,
+            cover_image: article.cover_image,
+            reading_time_minutes: article.reading_time_minutes,
+            summary: article.summary,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+            author: article.author,
+            authors: article.authors,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListArticlesQuery {
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    /// `nextCursor` from the previous page; the first page without it.
+    pub cursor: Option<String>,
+    /// The author's `username`.
+    pub author: Option<String>,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    pub user: UserWithToken,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserWithToken {
+    pub id: i32,
+    pub org_id: OrgId,
+    pub email: String,
+    pub name: String,
+    pub bio: String,
+    pub image: String,
+    pub token: String,
+}
+
+impl From<super::UserWithToken> for UserWithToken {
+    fn from(user: super::UserWithToken) -> Self {
+        Self {
+            id: user.id,
+            org_id: user.org_id,
+            email: user.email,
+            name: user.name,
+            bio: user.bio,
+            image: user.image,
+            token: user.token,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor: TwoFactorChallenge,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorChallenge {
+    pub challenge_token: String,
+    pub expires_in: i64,
+}
+
+impl From<super::TwoFactorChallenge> for TwoFactorChallengeResponse {
+    fn from(challenge: super::TwoFactorChallenge) -> Self {
+        Self {
+            two_factor: TwoFactorChallenge {
+                challenge_token: challenge.challenge_token,
+                expires_in: challenge.expires_in,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorLoginInput {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+impl From<TwoFactorLoginInput> for super::TwoFactorLoginInput {
+    fn from(input: TwoFactorLoginInput) -> Self {
+        Self {
+            challenge_token: input.challenge_token,
+            code: input.code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    use crate::models::{self, ArticleCursor, ArticlePage};
+
+    fn article() -> models::ArticleDto {
+        let author = ProfileResponse {
+            id: 42,
+            email: "john@example.com".to_string(),
+            username: "john-doe".to_string(),
+            name: "John Doe".to_string(),
+            bio: String::new(),
+            image: String::new(),
+        };
+        models::ArticleDto {
+            id: 1,
+            slug: "test-article".to_string(),
+            title: "Test Article".to_string(),
+            description: String::new(),
+            body: "Body".to_string(),
+            body_html: "<p>Body</p>\n".to_string(),
+            favorites_count: 3,
+            favorited: true,
+            views_count: 250,
+            cover_image: String::new(),
+            reading_time_minutes: None,
+            summary: None,
+            created_at: datetime!(2024-01-15 10:30:00 UTC),
+            updated_at: datetime!(2024-01-16 15:45:00 UTC),
+            authors: vec![author.clone()],
+            author,
+        }
+    }
+
+    #[test]
+    fn test_articles_serialize_in_camel_case() {
+        let cursor = ArticleCursor {
+            created_at: datetime!(2024-01-15 10:30:00 UTC),
+            id: 1,
+        };
+        let response = ArticlesResponse::from(ArticlePage {
+            articles: vec![article()],
+            next: Some(cursor),
+        });
+        let json = serde_json::to_value(&response).expect("serialization should succeed");
+
+        assert_eq!(json["nextCursor"], cursor.encode());
+        let article = &json["articles"][0];
+        assert_eq!(article["bodyHtml"], "<p>Body</p>\n");
+        assert_eq!(article["favoritesCount"], 3);
+        assert_eq!(article["createdAt"], "2024-01-15T10:30:00Z");
+        assert!(article.get("body_html").is_none());
+        assert!(json.get("total").is_none());
+    }
+
+    #[test]
+    fn test_two_factor_challenge_serializes_in_camel_case() {
+        let response = TwoFactorChallengeResponse::from(models::TwoFactorChallenge {
+            challenge_token: "jwt".to_string(),
+            expires_in: 300,
+        });
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({ "twoFactor": { "challengeToken": "jwt", "expiresIn": 300 } })
+        );
+    }
+
+    #[test]
+    fn test_last_page_has_null_cursor() {
+        let response = ArticlesResponse::from(ArticlePage {
+            articles: vec![],
+            next: None,
+        });
+        let json = serde_json::to_value(&response).expect("serialization should succeed");
+
+        assert!(json["nextCursor"].is_null());
+    }
+}
@@ -0,0 +1,127 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::database::Traced;
+use crate::models::{ActiveApiToken, ApiToken};
+
+#[derive(Clone)]
+pub struct ApiTokenRepository {
+    pool: PgPool,
+}
+
+impl ApiTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.api_token.create", skip(self, token_hash))]
+    pub async fn create(
+        &self,
+        user_id: i32,
+        name: &str,
+        token_hash: &str,
+        token_prefix: &str,
+        scopes: &[&str],
+        expires_at: Option<OffsetDateTime>,
+    ) -> Result<ApiToken, sqlx::Error> {
+        sqlx::query_as::<_, ApiToken>(
+            r#"
+            INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, token_prefix, scopes, created_at, last_used_at, expires_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(token_hash)
+        .bind(token_prefix)
+        .bind(scopes)
+        .bind(expires_at)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.api_token.list_for_user", skip(self))]
+    pub async fn list_for_user(&self, user_id: i32) -> Result<Vec<ApiToken>, sqlx::Error> {
+        sqlx::query_as::<_, ApiToken>(
+            r#"
+            SELECT id, user_id, name, token_prefix, scopes, created_at, last_used_at, expires_at
+            FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at DESC, id DESC
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Neither revoked nor expired.
+    #[instrument(name = "db.api_token.find_active", skip(self, token_hash))]
+    pub async fn find_active(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ActiveApiToken>, sqlx::Error> {
+        sqlx::query_as::<_, ActiveApiToken>(
+            r#"
+            -- all tenants: the token determines the tenant
+            SELECT
+                t.id, t.user_id, t.name, t.token_prefix, t.scopes,
+                t.created_at, t.last_used_at, t.expires_at, u.org_id
+            FROM api_tokens t
+            JOIN users u ON u.id = t.user_id
+            WHERE t.token_hash = $1
+                AND t.revoked_at IS NULL
+                AND (t.expires_at IS NULL OR t.expires_at > CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(token_hash)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.api_token.touch", skip(self))]
+    pub async fn touch(&self, id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(id)
+            .traced(&self.pool)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "db.api_token.revoke", skip(self))]
+    pub async fn revoke(&self, id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.api_token.revoke_all", skip(self))]
+    pub async fn revoke_all(&self, user_id: i32) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
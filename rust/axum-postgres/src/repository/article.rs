@@ -1,218 +1,917 @@
-use sqlx::{PgPool, Row};
+use authz::Authors;
+use futures_util::stream::BoxStream;
+use sqlx::Row;
 use tracing::instrument;
 
-use crate::models::{Article, ArticleWithAuthor};
+use super::article_query::{ArticleFilter, ArticleOrder, ArticleQuery};
+use crate::database::{TenantConn, TenantDb, Traced, retrying};
+use crate::models::{Article, ArticleCursor, ArticleSuggestion, ArticleWithAuthor, OrgId};
 
 #[derive(Clone)]
 pub struct ArticleRepository {
-    pool: PgPool,
+    db: TenantDb,
 }
 
 impl ArticleRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: TenantDb) -> Self {
+        Self { db }
+    }
+
+    /// The connection [`ArticleRepository::stream_all`] reads from, held by
+    /// the caller for as long as the stream.
+    pub async fn scope(&self, org: OrgId) -> Result<TenantConn, sqlx::Error> {
+        self.db.scope(org).await
     }
 
     #[instrument(name = "db.article.create", skip(self))]
     pub async fn create(
         &self,
+        org: OrgId,
         slug: &str,
         title: &str,
         description: &str,
         body: &str,
         author_id: i32,
     ) -> Result<Article, sqlx::Error> {
-        sqlx::query_as::<_, Article>(
-            r#"
-            INSERT INTO articles (slug, title, description, body, author_id)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, slug, title, description, body, author_id, favorites_count, created_at, updated_at
-            "#,
-        )
-        .bind(slug)
-        .bind(title)
-        .bind(description)
-        .bind(body)
-        .bind(author_id)
-        .fetch_one(&self.pool)
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let article = sqlx::query_as::<_, Article>(
+                r#"
+                INSERT INTO articles (slug, title, description, body, author_id, org_id)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id, slug, title, description, body, author_id, favorites_count, views_count, cover_image, created_at, updated_at
+                "#,
+            )
+            .bind(slug)
+            .bind(title)
+            .bind(description)
+            .bind(body)
+            .bind(author_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(article)
+        })
         .await
     }
 
     #[instrument(name = "db.article.find_by_slug", skip(self))]
-    pub async fn find_by_slug(&self, slug: &str) -> Result<Option<ArticleWithAuthor>, sqlx::Error> {
-        sqlx::query_as::<_, ArticleWithAuthor>(
-            r#"
-            SELECT
-                a.id, a.slug, a.title, a.description, a.body, a.author_id,
-                a.favorites_count, a.created_at, a.updated_at,
-                u.name as author_name, u.email as author_email,
-                u.bio as author_bio, u.image as author_image
-            FROM articles a
-            JOIN users u ON a.author_id = u.id
-            WHERE a.slug = $1
-            "#,
-        )
-        .bind(slug)
-        .fetch_optional(&self.pool)
+    pub async fn find_by_slug(
+        &self,
+        org: OrgId,
+        slug: &str,
+    ) -> Result<Option<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let article = sqlx::query_as::<_, ArticleWithAuthor>(
+                r#"
+                SELECT
+                    a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
+                FROM articles a
+                JOIN users u ON a.author_id = u.id
+                WHERE a.slug = $1 AND a.org_id = $2
+                "#,
+            )
+            .bind(slug)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(article)
+        })
+        .await
+    }
+
+    /// The owner and everyone in `article_authors`, the owner included,
+    /// sorted: the mutations taking an [`Authors`] compare against it.
+    #[instrument(name = "db.article.find_authors", skip(self))]
+    pub async fn find_authors(
+        &self,
+        org: OrgId,
+        slug: &str,
+    ) -> Result<Option<(i32, Vec<i32>)>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let authors = sqlx::query_as::<_, (i32, Vec<i32>)>(
+                r#"
+                SELECT a.author_id,
+                    ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id)
+                FROM articles a
+                WHERE a.slug = $1 AND a.org_id = $2
+                "#,
+            )
+            .bind(slug)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(authors)
+        })
         .await
     }
 
     #[instrument(name = "db.article.find_by_id", skip(self))]
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<ArticleWithAuthor>, sqlx::Error> {
-        sqlx::query_as::<_, ArticleWithAuthor>(
-            r#"
-            SELECT
-                a.id, a.slug, a.title, a.description, a.body, a.author_id,
-                a.favorites_count, a.created_at, a.updated_at,
-                u.name as author_name, u.email as author_email,
-                u.bio as author_bio, u.image as author_image
-            FROM articles a
-            JOIN users u ON a.author_id = u.id
-            WHERE a.id = $1
-            "#,
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
+    pub async fn find_by_id(
+        &self,
+        org: OrgId,
+        id: i32,
+    ) -> Result<Option<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let article = sqlx::query_as::<_, ArticleWithAuthor>(
+                r#"
+                SELECT
+                    a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
+                FROM articles a
+                JOIN users u ON a.author_id = u.id
+                WHERE a.id = $1 AND a.org_id = $2
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(article)
+        })
         .await
     }
 
     #[instrument(name = "db.article.list", skip(self))]
     pub async fn list(
         &self,
+        org: OrgId,
+        filter: ArticleFilter<'_>,
+        order: ArticleOrder,
         limit: i64,
         offset: i64,
-        author_name: Option<&str>,
     ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
-        if let Some(author) = author_name {
-            sqlx::query_as::<_, ArticleWithAuthor>(
+        retrying(|| async move {
+            let query = ArticleQuery::list(org, filter, order, limit, offset);
+            let mut conn = self.db.scope(org).await?;
+            let articles = query
+                .query_as::<ArticleWithAuthor>()
+                .traced(self.db.pool())
+                .fetch_all(&mut *conn)
+                .await?;
+            conn.finish().await?;
+            Ok(articles)
+        })
+        .await
+    }
+
+    /// Newest first, starting after `after`. The `(created_at, id)` row
+    /// comparison walks `idx_articles_org_id_created_at_id`, so a deep page
+    /// costs the same as the first, unlike `OFFSET`.
+    #[instrument(name = "db.article.list_after", skip(self))]
+    pub async fn list_after(
+        &self,
+        org: OrgId,
+        limit: i64,
+        after: Option<ArticleCursor>,
+        author: Option<&str>,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let articles = sqlx::query_as::<_, ArticleWithAuthor>(
                 r#"
                 SELECT
                     a.id, a.slug, a.title, a.description, a.body, a.author_id,
-                    a.favorites_count, a.created_at, a.updated_at,
-                    u.name as author_name, u.email as author_email,
-                    u.bio as author_bio, u.image as author_image
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
                 FROM articles a
                 JOIN users u ON a.author_id = u.id
-                WHERE u.name = $1
-                ORDER BY a.created_at DESC
-                LIMIT $2 OFFSET $3
+                WHERE a.org_id = $1
+                  AND ($3::timestamptz IS NULL OR (a.created_at, a.id) < ($3, $4))
+                  AND ($5::text IS NULL OR EXISTS (
+                      SELECT 1 FROM article_authors aa
+                      JOIN users au ON au.id = aa.user_id
+                      WHERE aa.article_id = a.id AND au.username = $5
+                  ))
+                ORDER BY a.created_at DESC, a.id DESC
+                LIMIT $2
                 "#,
             )
+            .bind(org)
+            .bind(limit)
+            .bind(after.map(|cursor| cursor.created_at))
+            .bind(after.map(|cursor| cursor.id))
             .bind(author)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(articles)
+        })
+        .await
+    }
+
+    /// The articles `user_id` is an author of, co-authored ones included,
+    /// newest first.
+    #[instrument(name = "db.article.list_by_author", skip(self))]
+    pub async fn list_by_author(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let articles = sqlx::query_as::<_, ArticleWithAuthor>(
+                r#"
+                SELECT
+                    a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
+                FROM article_authors aa
+                JOIN articles a ON a.id = aa.article_id
+                JOIN users u ON a.author_id = u.id
+                WHERE aa.user_id = $1 AND a.org_id = $2
+                ORDER BY a.created_at DESC, a.id DESC
+                LIMIT $3 OFFSET $4
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
             .bind(limit)
             .bind(offset)
-            .fetch_all(&self.pool)
-            .await
-        } else {
-            sqlx::query_as::<_, ArticleWithAuthor>(
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(articles)
+        })
+        .await
+    }
+
+    #[instrument(name = "db.article.count_by_author", skip(self))]
+    pub async fn count_by_author(&self, org: OrgId, user_id: i32) -> Result<i64, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
+                r#"
+                SELECT COUNT(*) as count
+                FROM article_authors aa
+                JOIN articles a ON a.id = aa.article_id
+                WHERE aa.user_id = $1 AND a.org_id = $2
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<i64, _>("count"))
+        })
+        .await
+    }
+
+    /// Most recently favorited first.
+    #[instrument(name = "db.article.list_favorited_by", skip(self))]
+    pub async fn list_favorited_by(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let articles = sqlx::query_as::<_, ArticleWithAuthor>(
                 r#"
                 SELECT
                     a.id, a.slug, a.title, a.description, a.body, a.author_id,
-                    a.favorites_count, a.created_at, a.updated_at,
-                    u.name as author_name, u.email as author_email,
-                    u.bio as author_bio, u.image as author_image
-                FROM articles a
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
+                FROM favorites f
+                JOIN articles a ON a.id = f.article_id
+                JOIN users u ON a.author_id = u.id
+                WHERE f.user_id = $1 AND f.org_id = $2
+                ORDER BY f.created_at DESC, f.id DESC
+                LIMIT $3 OFFSET $4
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
+            .bind(limit)
+            .bind(offset)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(articles)
+        })
+        .await
+    }
+
+    /// Articles by their last computed trending score. Articles created since
+    /// the last refresh aren't scored yet and don't appear.
+    #[instrument(name = "db.article.trending", skip(self))]
+    pub async fn trending(
+        &self,
+        org: OrgId,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArticleWithAuthor>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let articles = sqlx::query_as::<_, ArticleWithAuthor>(
+                r#"
+                SELECT
+                    a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                    a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                    a.reading_time_minutes, a.summary,
+                    u.username as author_username, u.name as author_name, u.email as author_email,
+                    u.bio as author_bio, u.image as author_image,
+                    article_authors_json(a.id) as authors
+                FROM article_scores s
+                JOIN articles a ON a.id = s.article_id
                 JOIN users u ON a.author_id = u.id
-                ORDER BY a.created_at DESC
+                WHERE a.org_id = $3
+                ORDER BY s.score DESC, a.id DESC
                 LIMIT $1 OFFSET $2
                 "#,
             )
             .bind(limit)
             .bind(offset)
-            .fetch_all(&self.pool)
-            .await
-        }
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(articles)
+        })
+        .await
     }
 
-    #[instrument(name = "db.article.count", skip(self))]
-    pub async fn count(&self, author_name: Option<&str>) -> Result<i64, sqlx::Error> {
-        let row = if let Some(author) = author_name {
-            sqlx::query(
+    #[instrument(name = "db.article.count_trending", skip(self))]
+    pub async fn count_trending(&self, org: OrgId) -> Result<i64, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
                 r#"
                 SELECT COUNT(*) as count
-                FROM articles a
-                JOIN users u ON a.author_id = u.id
-                WHERE u.name = $1
+                FROM article_scores s
+                JOIN articles a ON a.id = s.article_id
+                WHERE a.org_id = $1
                 "#,
             )
-            .bind(author)
-            .fetch_one(&self.pool)
-            .await?
-        } else {
-            sqlx::query("SELECT COUNT(*) as count FROM articles")
-                .fetch_one(&self.pool)
-                .await?
-        };
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<i64, _>("count"))
+        })
+        .await
+    }
+
+    /// Titles containing a word that starts like `q`, closest first. `<%`
+    /// is what lets the planner use `idx_articles_title_trgm`.
+    #[instrument(name = "db.article.suggest", skip(self))]
+    pub async fn suggest(
+        &self,
+        org: OrgId,
+        q: &str,
+        limit: i64,
+    ) -> Result<Vec<ArticleSuggestion>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let suggestions = sqlx::query_as::<_, ArticleSuggestion>(
+                r#"
+                SELECT slug, title
+                FROM articles
+                WHERE org_id = $3 AND $1 <% title
+                ORDER BY word_similarity($1, title) DESC, created_at DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(q)
+            .bind(limit)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(suggestions)
+        })
+        .await
+    }
 
-        Ok(row.get::<i64, _>("count"))
+    pub fn stream_all<'c>(
+        &self,
+        conn: &'c mut TenantConn,
+        org: OrgId,
+    ) -> BoxStream<'c, Result<ArticleWithAuthor, sqlx::Error>> {
+        sqlx::query_as::<_, ArticleWithAuthor>(
+            r#"
+            SELECT
+                a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                a.reading_time_minutes, a.summary,
+                u.username as author_username, u.name as author_name, u.email as author_email,
+                u.bio as author_bio, u.image as author_image,
+                article_authors_json(a.id) as authors
+            FROM articles a
+            JOIN users u ON a.author_id = u.id
+            WHERE a.org_id = $1
+            ORDER BY a.id
+            "#,
+        )
+        .bind(org)
+        .traced(self.db.pool())
+        .fetch(&mut **conn)
     }
 
-    #[instrument(name = "db.article.update", skip(self))]
+    /// The articles `user_id` is an author of, co-authored ones included,
+    /// oldest first.
+    pub fn stream_by_author<'c>(
+        &self,
+        conn: &'c mut TenantConn,
+        org: OrgId,
+        user_id: i32,
+    ) -> BoxStream<'c, Result<ArticleWithAuthor, sqlx::Error>> {
+        sqlx::query_as::<_, ArticleWithAuthor>(
+            r#"
+            SELECT
+                a.id, a.slug, a.title, a.description, a.body, a.author_id,
+                a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+                a.reading_time_minutes, a.summary,
+                u.username as author_username, u.name as author_name, u.email as author_email,
+                u.bio as author_bio, u.image as author_image,
+                article_authors_json(a.id) as authors
+            FROM article_authors aa
+            JOIN articles a ON a.id = aa.article_id
+            JOIN users u ON a.author_id = u.id
+            WHERE aa.user_id = $1 AND a.org_id = $2
+            ORDER BY a.id
+            "#,
+        )
+        .bind(user_id)
+        .bind(org)
+        .traced(self.db.pool())
+        .fetch(&mut **conn)
+    }
+
+    #[instrument(name = "db.article.count", skip(self))]
+    pub async fn count(&self, org: OrgId, filter: ArticleFilter<'_>) -> Result<i64, sqlx::Error> {
+        retrying(|| async move {
+            let query = ArticleQuery::count(org, filter);
+            let mut conn = self.db.scope(org).await?;
+            let row = query
+                .query()
+                .traced(self.db.pool())
+                .fetch_one(&mut *conn)
+                .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<i64, _>("count"))
+        })
+        .await
+    }
+
+    /// `None` unless the article's authors are still `authors`, the ones
+    /// the authorization policy allowed the change for. So are the other
+    /// mutations taking an [`Authors`].
+    #[instrument(name = "db.article.update", skip(self, authors))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn update(
         &self,
+        org: OrgId,
         id: i32,
+        authors: &Authors,
         slug: Option<&str>,
         title: Option<&str>,
         description: Option<&str>,
         body: Option<&str>,
-    ) -> Result<Article, sqlx::Error> {
-        sqlx::query_as::<_, Article>(
-            r#"
-            UPDATE articles
-            SET
-                slug = COALESCE($2, slug),
-                title = COALESCE($3, title),
-                description = COALESCE($4, description),
-                body = COALESCE($5, body)
-            WHERE id = $1
-            RETURNING id, slug, title, description, body, author_id, favorites_count, created_at, updated_at
-            "#,
-        )
-        .bind(id)
-        .bind(slug)
-        .bind(title)
-        .bind(description)
-        .bind(body)
-        .fetch_one(&self.pool)
+    ) -> Result<Option<Article>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let article = sqlx::query_as::<_, Article>(
+                r#"
+                UPDATE articles a
+                SET
+                    slug = COALESCE($2, slug),
+                    title = COALESCE($3, title),
+                    description = COALESCE($4, description),
+                    body = COALESCE($5, body)
+                WHERE a.id = $1 AND a.org_id = $6 AND a.author_id = $7
+                    AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $8
+                RETURNING id, slug, title, description, body, author_id, favorites_count, views_count, cover_image, created_at, updated_at
+                "#,
+            )
+            .bind(id)
+            .bind(slug)
+            .bind(title)
+            .bind(description)
+            .bind(body)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(article)
+        })
         .await
     }
 
-    #[instrument(name = "db.article.delete", skip(self))]
-    pub async fn delete(&self, id: i32) -> Result<(), sqlx::Error> {
-        sqlx::query("DELETE FROM articles WHERE id = $1")
+    #[instrument(name = "db.article.update_cover_image", skip(self, authors))]
+    pub async fn update_cover_image(
+        &self,
+        org: OrgId,
+        id: i32,
+        authors: &Authors,
+        cover_image: &str,
+    ) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                r#"
+                UPDATE articles a
+                SET cover_image = $2
+                WHERE a.id = $1 AND a.org_id = $3 AND a.author_id = $4
+                    AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $5
+                "#,
+            )
             .bind(id)
-            .execute(&self.pool)
+            .bind(cover_image)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    #[instrument(name = "db.article.update_enrichment", skip(self, summary))]
+    pub async fn update_enrichment(
+        &self,
+        org: OrgId,
+        id: i32,
+        reading_time_minutes: i32,
+        summary: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            sqlx::query(
+                r#"
+                UPDATE articles
+                SET reading_time_minutes = $2, summary = $3
+                WHERE id = $1 AND org_id = $4
+                "#,
+            )
+            .bind(id)
+            .bind(reading_time_minutes)
+            .bind(summary)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "db.article.delete", skip(self, authors))]
+    pub async fn delete(
+        &self,
+        org: OrgId,
+        id: i32,
+        authors: &Authors,
+    ) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                r#"
+                DELETE FROM articles a
+                WHERE a.id = $1 AND a.org_id = $2 AND a.author_id = $3
+                    AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $4
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Deletes up to `limit` of the articles `user_id` owns, returning their
+    /// cover images.
+    #[instrument(name = "db.article.delete_batch_by_owner", skip(self))]
+    pub async fn delete_batch_by_owner(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        limit: i64,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let rows = sqlx::query(
+                r#"
+                DELETE FROM articles
+                WHERE id IN (
+                    SELECT id FROM articles
+                    WHERE author_id = $1 AND org_id = $2
+                    ORDER BY id
+                    LIMIT $3
+                )
+                RETURNING cover_image
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
+            .bind(limit)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
             .await?;
-        Ok(())
+            conn.finish().await?;
+
+            Ok(rows
+                .iter()
+                .map(|r| r.get::<String, _>("cover_image"))
+                .collect())
+        })
+        .await
+    }
+
+    /// Removes `user_id` from the articles they co-author but don't own.
+    #[instrument(name = "db.article.remove_co_authorships", skip(self))]
+    pub async fn remove_co_authorships(
+        &self,
+        org: OrgId,
+        user_id: i32,
+    ) -> Result<u64, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                r#"
+                DELETE FROM article_authors aa
+                USING articles a
+                WHERE aa.user_id = $1 AND a.id = aa.article_id
+                    AND a.author_id <> $1 AND a.org_id = $2
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(result.rows_affected())
+        })
+        .await
     }
 
     #[instrument(name = "db.article.exists_by_slug", skip(self))]
-    pub async fn exists_by_slug(&self, slug: &str) -> Result<bool, sqlx::Error> {
-        let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM articles WHERE slug = $1) as exists")
+    pub async fn exists_by_slug(&self, org: OrgId, slug: &str) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
+                "SELECT EXISTS(SELECT 1 FROM articles WHERE slug = $1 AND org_id = $2) as exists",
+            )
             .bind(slug)
-            .fetch_one(&self.pool)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<bool, _>("exists"))
+        })
+        .await
+    }
+
+    /// Adds `user_id` as a co-author. `Some(false)` if there is no such user
+    /// in the article's tenant or they already are an author.
+    #[instrument(name = "db.article.add_author", skip(self, authors))]
+    pub async fn add_author(
+        &self,
+        org: OrgId,
+        id: i32,
+        authors: &Authors,
+        user_id: i32,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let (authorized, added) = sqlx::query_as::<_, (bool, bool)>(
+                r#"
+                WITH article AS (
+                    SELECT a.id, a.org_id
+                    FROM articles a
+                    WHERE a.id = $1 AND a.org_id = $3 AND a.author_id = $4
+                        AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $5
+                ), added AS (
+                    INSERT INTO article_authors (article_id, user_id)
+                    SELECT article.id, u.id
+                    FROM article
+                    JOIN users u ON u.org_id = article.org_id
+                    WHERE u.id = $2
+                    ON CONFLICT (article_id, user_id) DO NOTHING
+                    RETURNING 1
+                )
+                SELECT EXISTS (SELECT 1 FROM article), EXISTS (SELECT 1 FROM added)
+                "#,
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(authorized.then_some(added))
+        })
+        .await
+    }
+
+    /// `Some(false)` if `user_id` isn't a co-author.
+    #[instrument(name = "db.article.remove_author", skip(self, authors))]
+    pub async fn remove_author(
+        &self,
+        org: OrgId,
+        id: i32,
+        authors: &Authors,
+        user_id: i32,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let (authorized, removed) = sqlx::query_as::<_, (bool, bool)>(
+                r#"
+                WITH article AS (
+                    SELECT a.id
+                    FROM articles a
+                    WHERE a.id = $1 AND a.org_id = $3 AND a.author_id = $4
+                        AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $5
+                ), removed AS (
+                    DELETE FROM article_authors aa
+                    USING article
+                    WHERE aa.article_id = article.id AND aa.user_id = $2
+                    RETURNING 1
+                )
+                SELECT EXISTS (SELECT 1 FROM article), EXISTS (SELECT 1 FROM removed)
+                "#,
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
             .await?;
+            conn.finish().await?;
+            Ok(authorized.then_some(removed))
+        })
+        .await
+    }
 
-        Ok(row.get::<bool, _>("exists"))
+    /// Makes `user_id` the owner; a trigger adds them to `article_authors`
+    /// and the previous owner stays a co-author. `Some(false)` if there is
+    /// no such user in the article's tenant.
+    #[instrument(name = "db.article.transfer", skip(self, authors))]
+    pub async fn transfer(
+        &self,
+        org: OrgId,
+        id: i32,
+        authors: &Authors,
+        user_id: i32,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let (authorized, transferred) = sqlx::query_as::<_, (bool, bool)>(
+                r#"
+                WITH article AS (
+                    SELECT a.id, a.org_id
+                    FROM articles a
+                    WHERE a.id = $1 AND a.org_id = $3 AND a.author_id = $4
+                        AND ARRAY(SELECT aa.user_id FROM article_authors aa WHERE aa.article_id = a.id ORDER BY aa.user_id) = $5
+                ), transferred AS (
+                    UPDATE articles a
+                    SET author_id = u.id
+                    FROM article, users u
+                    WHERE a.id = article.id AND u.id = $2 AND u.org_id = article.org_id
+                    RETURNING 1
+                )
+                SELECT EXISTS (SELECT 1 FROM article), EXISTS (SELECT 1 FROM transferred)
+                "#,
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(org)
+            .bind(authors.owner)
+            .bind(&authors.co_authors)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(authorized.then_some(transferred))
+        })
+        .await
     }
 
     #[instrument(name = "db.article.increment_favorites", skip(self))]
-    pub async fn increment_favorites(&self, id: i32) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE articles SET favorites_count = favorites_count + 1 WHERE id = $1")
+    pub async fn increment_favorites(&self, org: OrgId, id: i32) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            sqlx::query(
+                "UPDATE articles SET favorites_count = favorites_count + 1 WHERE id = $1 AND org_id = $2",
+            )
             .bind(id)
-            .execute(&self.pool)
+            .bind(org)
+                .traced(self.db.pool())
+                .execute(&mut *conn)
+                .await?;
+            conn.finish().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Adds `views[i]` to the views of article `ids[i]` in one statement. The
+    /// ids come from tenant-scoped reads, and one flush covers every tenant.
+    #[instrument(name = "db.article.add_views", skip(self, ids, views), fields(articles = ids.len()))]
+    pub async fn add_views(&self, ids: &[i32], views: &[i64]) -> Result<u64, sqlx::Error> {
+        retrying(|| async move {
+            let result = sqlx::query(
+                r#"
+                -- all tenants
+                UPDATE articles a
+                SET views_count = a.views_count + v.views
+                FROM UNNEST($1::int[], $2::bigint[]) AS v(id, views)
+                WHERE a.id = v.id
+                "#,
+            )
+            .bind(ids)
+            .bind(views)
+            .traced(self.db.pool())
+            .execute(self.db.pool())
             .await?;
-        Ok(())
+            Ok(result.rows_affected())
+        })
+        .await
     }
 
     #[instrument(name = "db.article.decrement_favorites", skip(self))]
-    pub async fn decrement_favorites(&self, id: i32) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "UPDATE articles SET favorites_count = GREATEST(favorites_count - 1, 0) WHERE id = $1",
-        )
-        .bind(id)
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+    pub async fn decrement_favorites(&self, org: OrgId, id: i32) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            sqlx::query(
+                r#"
+                UPDATE articles SET favorites_count = GREATEST(favorites_count - 1, 0)
+                WHERE id = $1 AND org_id = $2
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(())
+        })
+        .await
     }
 }
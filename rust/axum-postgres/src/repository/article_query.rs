@@ -0,0 +1,221 @@
+//! The SQL of the article listing and its count, built from the filters a
+//! request sets. Each filter adds its predicate and bind only when it is
+//! set, so another filter is one more `if` in [`ArticleQuery::filtered`]
+//! rather than another copy of both queries per combination of the others.
+
+use std::fmt::Write as _;
+
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::{Query, QueryAs};
+use sqlx::{FromRow, Postgres};
+
+use crate::models::OrgId;
+
+/// What a listing is narrowed to; an article must match every filter set.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArticleFilter<'a> {
+    /// A `username` among the article's authors, co-authors included.
+    pub author: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArticleOrder {
+    Newest,
+    /// Most favorited first, then newest: the `new_feed` ranking.
+    MostFavorited,
+}
+
+/// A bind parameter, in the order of its `$n`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bind<'a> {
+    Org(OrgId),
+    Text(&'a str),
+    BigInt(i64),
+}
+
+const SELECT_ARTICLES: &str = r#"
+    SELECT
+        a.id, a.slug, a.title, a.description, a.body, a.author_id,
+        a.favorites_count, a.views_count, a.cover_image, a.created_at, a.updated_at,
+        a.reading_time_minutes, a.summary,
+        u.username as author_username, u.name as author_name, u.email as author_email,
+        u.bio as author_bio, u.image as author_image,
+        article_authors_json(a.id) as authors
+    FROM articles a
+    JOIN users u ON a.author_id = u.id
+    WHERE a.org_id = "#;
+
+const COUNT_ARTICLES: &str = "SELECT COUNT(*) as count FROM articles a WHERE a.org_id = ";
+
+const AUTHOR_IS: &str = r#"
+    AND EXISTS (
+        SELECT 1 FROM article_authors aa
+        JOIN users au ON au.id = aa.user_id AND au.org_id = a.org_id
+        WHERE aa.article_id = a.id AND au.username = "#;
+
+#[derive(Debug)]
+pub struct ArticleQuery<'a> {
+    sql: String,
+    binds: Vec<Bind<'a>>,
+}
+
+impl<'a> ArticleQuery<'a> {
+    /// A page of `ArticleWithAuthor`s matching `filter`.
+    pub fn list(
+        org: OrgId,
+        filter: ArticleFilter<'a>,
+        order: ArticleOrder,
+        limit: i64,
+        offset: i64,
+    ) -> Self {
+        let mut query = Self::filtered(SELECT_ARTICLES, org, filter);
+        query.push(match order {
+            ArticleOrder::Newest => "\n    ORDER BY a.created_at DESC",
+            ArticleOrder::MostFavorited => {
+                "\n    ORDER BY a.favorites_count DESC, a.created_at DESC"
+            }
+        });
+        query.push("\n    LIMIT ");
+        query.push_bind(Bind::BigInt(limit));
+        query.push(" OFFSET ");
+        query.push_bind(Bind::BigInt(offset));
+        query
+    }
+
+    /// How many articles match `filter`, as `count`.
+    pub fn count(org: OrgId, filter: ArticleFilter<'a>) -> Self {
+        Self::filtered(COUNT_ARTICLES, org, filter)
+    }
+
+    /// `select` ends with the tenant predicate, so every query is scoped
+    /// before any filter is added.
+    fn filtered(select: &str, org: OrgId, filter: ArticleFilter<'a>) -> Self {
+        let mut query = Self {
+            sql: select.to_string(),
+            binds: Vec::new(),
+        };
+        query.push_bind(Bind::Org(org));
+        if let Some(author) = filter.author {
+            query.push(AUTHOR_IS);
+            query.push_bind(Bind::Text(author));
+            query.push("\n    )");
+        }
+        query
+    }
+
+    fn push(&mut self, sql: &str) {
+        self.sql.push_str(sql);
+    }
+
+    fn push_bind(&mut self, bind: Bind<'a>) {
+        self.binds.push(bind);
+        let _ = write!(self.sql, "${}", self.binds.len());
+    }
+
+    #[cfg(test)]
+    fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    #[cfg(test)]
+    fn binds(&self) -> &[Bind<'a>] {
+        &self.binds
+    }
+
+    pub fn query_as<'q, T>(&'q self) -> QueryAs<'q, Postgres, T, PgArguments>
+    where
+        T: for<'r> FromRow<'r, PgRow>,
+    {
+        self.binds
+            .iter()
+            .fold(sqlx::query_as(&self.sql), |query, bind| match *bind {
+                Bind::Org(org) => query.bind(org),
+                Bind::Text(text) => query.bind(text),
+                Bind::BigInt(n) => query.bind(n),
+            })
+    }
+
+    pub fn query(&self) -> Query<'_, Postgres, PgArguments> {
+        self.binds
+            .iter()
+            .fold(sqlx::query(&self.sql), |query, bind| match *bind {
+                Bind::Org(org) => query.bind(org),
+                Bind::Text(text) => query.bind(text),
+                Bind::BigInt(n) => query.bind(n),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORG: OrgId = OrgId(3);
+
+    /// The SQL on one line, as the assertions spell it.
+    fn sql(query: &ArticleQuery<'_>) -> String {
+        query.sql().split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_unfiltered_list_binds_only_the_tenant_and_page() {
+        let query = ArticleQuery::list(ORG, ArticleFilter::default(), ArticleOrder::Newest, 20, 40);
+
+        assert!(sql(&query).ends_with(
+            "FROM articles a JOIN users u ON a.author_id = u.id \
+             WHERE a.org_id = $1 ORDER BY a.created_at DESC LIMIT $2 OFFSET $3"
+        ));
+        assert!(!query.sql().contains("article_authors aa"));
+        assert_eq!(
+            query.binds(),
+            [Bind::Org(ORG), Bind::BigInt(20), Bind::BigInt(40)]
+        );
+    }
+
+    #[test]
+    fn test_author_filter_comes_before_the_page() {
+        let filter = ArticleFilter {
+            author: Some("alice"),
+        };
+        let query = ArticleQuery::list(ORG, filter, ArticleOrder::MostFavorited, 10, 0);
+
+        assert!(sql(&query).ends_with(
+            "WHERE a.org_id = $1 AND EXISTS ( SELECT 1 FROM article_authors aa \
+             JOIN users au ON au.id = aa.user_id AND au.org_id = a.org_id \
+             WHERE aa.article_id = a.id AND au.username = $2 ) \
+             ORDER BY a.favorites_count DESC, a.created_at DESC LIMIT $3 OFFSET $4"
+        ));
+        assert_eq!(
+            query.binds(),
+            [
+                Bind::Org(ORG),
+                Bind::Text("alice"),
+                Bind::BigInt(10),
+                Bind::BigInt(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_applies_the_same_filter() {
+        let filter = ArticleFilter {
+            author: Some("bob"),
+        };
+        let count = ArticleQuery::count(ORG, filter);
+        let list = ArticleQuery::list(ORG, filter, ArticleOrder::Newest, 20, 0);
+
+        assert!(sql(&count).starts_with("SELECT COUNT(*) as count FROM articles a"));
+        let predicate = |sql: &str| {
+            let start = sql.find("WHERE a.org_id").unwrap();
+            let end = sql.find(" ORDER BY").unwrap_or(sql.len());
+            sql[start..end].to_string()
+        };
+        assert_eq!(predicate(&sql(&count)), predicate(&sql(&list)));
+        assert_eq!(count.binds(), [Bind::Org(ORG), Bind::Text("bob")]);
+
+        assert_eq!(
+            sql(&ArticleQuery::count(ORG, ArticleFilter::default())),
+            "SELECT COUNT(*) as count FROM articles a WHERE a.org_id = $1"
+        );
+    }
+}
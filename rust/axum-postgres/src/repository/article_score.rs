@@ -0,0 +1,53 @@
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::database::Traced;
+use crate::models::ArticleEngagement;
+
+#[derive(Clone)]
+pub struct ArticleScoreRepository {
+    pool: PgPool,
+}
+
+impl ArticleScoreRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Scores are computed for every tenant in one pass; reads filter them by
+    /// the article's tenant.
+    #[instrument(name = "db.article_score.engagement", skip(self))]
+    pub async fn engagement(&self) -> Result<Vec<ArticleEngagement>, sqlx::Error> {
+        sqlx::query_as::<_, ArticleEngagement>(
+            r#"
+            -- all tenants
+            SELECT id, favorites_count, views_count, created_at FROM articles
+            "#,
+        )
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Sets `scores[i]` as the score of article `ids[i]` in one statement.
+    #[instrument(name = "db.article_score.upsert", skip(self, ids, scores), fields(articles = ids.len()))]
+    pub async fn upsert(&self, ids: &[i32], scores: &[f64]) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            -- all tenants
+            INSERT INTO article_scores (article_id, score, computed_at)
+            SELECT s.id, s.score, CURRENT_TIMESTAMP
+            FROM UNNEST($1::int[], $2::float8[]) AS s(id, score)
+            JOIN articles a ON a.id = s.id
+            ON CONFLICT (article_id) DO UPDATE
+            SET score = EXCLUDED.score, computed_at = EXCLUDED.computed_at
+            "#,
+        )
+        .bind(ids)
+        .bind(scores)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
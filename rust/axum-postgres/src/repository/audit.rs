@@ -0,0 +1,110 @@
+use futures_util::stream::BoxStream;
+use sqlx::{PgPool, Row};
+use tracing::instrument;
+
+use crate::database::Traced;
+use crate::models::{AuditEntry, AuditLogEntry, AuditLogQuery};
+
+/// Unset filters bind as NULL and match everything.
+const FILTER: &str = r#"
+    WHERE ($1::text IS NULL OR actor_type = $1)
+        AND ($2::int IS NULL OR actor_id = $2)
+        AND ($3::text IS NULL OR action = $3)
+        AND ($4::text IS NULL OR entity_type = $4)
+        AND ($5::text IS NULL OR entity_id = $5)
+"#;
+
+#[derive(Clone)]
+pub struct AuditRepository {
+    pool: PgPool,
+}
+
+impl AuditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.audit_log.insert", skip(self, entry), fields(action = entry.action))]
+    pub async fn insert(
+        &self,
+        entry: &AuditEntry,
+        trace_id: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (actor_type, actor_id, action, entity_type, entity_id, changes, trace_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(entry.actor.kind())
+        .bind(entry.actor.id())
+        .bind(entry.action)
+        .bind(entry.entity_type)
+        .bind(&entry.entity_id)
+        .bind(&entry.changes)
+        .bind(trace_id)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Newest first.
+    #[instrument(name = "db.audit_log.list", skip(self))]
+    pub async fn list(&self, query: &AuditLogQuery) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+        let sql = format!(
+            r#"
+            SELECT id, actor_type, actor_id, action, entity_type, entity_id, changes, trace_id, created_at
+            FROM audit_log
+            {FILTER}
+            ORDER BY created_at DESC, id DESC
+            LIMIT $6 OFFSET $7
+            "#
+        );
+        sqlx::query_as::<_, AuditLogEntry>(&sql)
+            .bind(&query.actor_type)
+            .bind(query.actor_id)
+            .bind(&query.action)
+            .bind(&query.entity_type)
+            .bind(&query.entity_id)
+            .bind(query.limit)
+            .bind(query.offset)
+            .traced(&self.pool)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// What `user_id` did, oldest first.
+    pub fn stream_by_actor(
+        &self,
+        user_id: i32,
+    ) -> BoxStream<'_, Result<AuditLogEntry, sqlx::Error>> {
+        sqlx::query_as::<_, AuditLogEntry>(
+            r#"
+            SELECT id, actor_type, actor_id, action, entity_type, entity_id, changes, trace_id, created_at
+            FROM audit_log
+            WHERE actor_type = 'user' AND actor_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch(&self.pool)
+    }
+
+    #[instrument(name = "db.audit_log.count", skip(self))]
+    pub async fn count(&self, query: &AuditLogQuery) -> Result<i64, sqlx::Error> {
+        let sql = format!("SELECT COUNT(*) as count FROM audit_log {FILTER}");
+        let row = sqlx::query(&sql)
+            .bind(&query.actor_type)
+            .bind(query.actor_id)
+            .bind(&query.action)
+            .bind(&query.entity_type)
+            .bind(&query.entity_id)
+            .traced(&self.pool)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get::<i64, _>("count"))
+    }
+}
@@ -1,80 +1,199 @@
-use sqlx::{PgPool, Row};
+use futures_util::stream::BoxStream;
+use sqlx::Row;
 use tracing::instrument;
 
-use crate::models::Favorite;
+use crate::database::{TenantConn, TenantDb, Traced, retrying};
+use crate::models::{Favorite, FavoritedArticle, OrgId};
 
 #[derive(Clone)]
 pub struct FavoriteRepository {
-    pool: PgPool,
+    db: TenantDb,
 }
 
 impl FavoriteRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: TenantDb) -> Self {
+        Self { db }
     }
 
     #[instrument(name = "db.favorite.create", skip(self))]
-    pub async fn create(&self, user_id: i32, article_id: i32) -> Result<Favorite, sqlx::Error> {
-        sqlx::query_as::<_, Favorite>(
-            r#"
-            INSERT INTO favorites (user_id, article_id)
-            VALUES ($1, $2)
-            ON CONFLICT (user_id, article_id) DO UPDATE SET user_id = $1
-            RETURNING id, user_id, article_id, created_at
-            "#,
-        )
-        .bind(user_id)
-        .bind(article_id)
-        .fetch_one(&self.pool)
+    pub async fn create(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        article_id: i32,
+    ) -> Result<Favorite, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let favorite = sqlx::query_as::<_, Favorite>(
+                r#"
+                INSERT INTO favorites (user_id, article_id, org_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, article_id) DO UPDATE SET user_id = $1
+                RETURNING id, user_id, article_id, created_at
+                "#,
+            )
+            .bind(user_id)
+            .bind(article_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(favorite)
+        })
         .await
     }
 
     #[instrument(name = "db.favorite.delete", skip(self))]
-    pub async fn delete(&self, user_id: i32, article_id: i32) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query("DELETE FROM favorites WHERE user_id = $1 AND article_id = $2")
+    pub async fn delete(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        article_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                "DELETE FROM favorites WHERE user_id = $1 AND article_id = $2 AND org_id = $3",
+            )
             .bind(user_id)
             .bind(article_id)
-            .execute(&self.pool)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
             .await?;
+            conn.finish().await?;
 
-        Ok(result.rows_affected() > 0)
+            Ok(result.rows_affected() > 0)
+        })
+        .await
     }
 
     #[instrument(name = "db.favorite.exists", skip(self))]
-    pub async fn exists(&self, user_id: i32, article_id: i32) -> Result<bool, sqlx::Error> {
-        let row = sqlx::query(
-            r#"
-            SELECT EXISTS(
-                SELECT 1 FROM favorites WHERE user_id = $1 AND article_id = $2
-            ) as exists
-            "#,
-        )
-        .bind(user_id)
-        .bind(article_id)
-        .fetch_one(&self.pool)
-        .await?;
+    pub async fn exists(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        article_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM favorites WHERE user_id = $1 AND article_id = $2 AND org_id = $3
+                ) as exists
+                "#,
+            )
+            .bind(user_id)
+            .bind(article_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
 
-        Ok(row.get::<bool, _>("exists"))
+            Ok(row.get::<bool, _>("exists"))
+        })
+        .await
+    }
+
+    #[instrument(name = "db.favorite.count_by_user", skip(self))]
+    pub async fn count_by_user(&self, org: OrgId, user_id: i32) -> Result<i64, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
+                "SELECT COUNT(*) as count FROM favorites WHERE user_id = $1 AND org_id = $2",
+            )
+            .bind(user_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<i64, _>("count"))
+        })
+        .await
     }
 
     #[instrument(name = "db.favorite.is_favorited_batch", skip(self, article_ids))]
     pub async fn is_favorited_batch(
         &self,
+        org: OrgId,
         user_id: i32,
         article_ids: &[i32],
     ) -> Result<Vec<i32>, sqlx::Error> {
-        let rows = sqlx::query(
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let rows = sqlx::query(
+                r#"
+                SELECT article_id
+                FROM favorites
+                WHERE user_id = $1 AND article_id = ANY($2) AND org_id = $3
+                "#,
+            )
+            .bind(user_id)
+            .bind(article_ids)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_all(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(rows.iter().map(|r| r.get::<i32, _>("article_id")).collect())
+        })
+        .await
+    }
+
+    /// Removes every favorite of `user_id` and takes them off the articles'
+    /// counts. Returns how many there were.
+    #[instrument(name = "db.favorite.delete_all_by_user", skip(self))]
+    pub async fn delete_all_by_user(&self, org: OrgId, user_id: i32) -> Result<u64, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                r#"
+                WITH removed AS (
+                    DELETE FROM favorites WHERE user_id = $1 AND org_id = $2
+                    RETURNING article_id
+                )
+                UPDATE articles a
+                SET favorites_count = GREATEST(a.favorites_count - 1, 0)
+                FROM removed r
+                WHERE a.id = r.article_id
+                "#,
+            )
+            .bind(user_id)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+
+    /// Most recently favorited first.
+    pub fn stream_by_user<'c>(
+        &self,
+        conn: &'c mut TenantConn,
+        org: OrgId,
+        user_id: i32,
+    ) -> BoxStream<'c, Result<FavoritedArticle, sqlx::Error>> {
+        sqlx::query_as::<_, FavoritedArticle>(
             r#"
-            SELECT article_id
-            FROM favorites
-            WHERE user_id = $1 AND article_id = ANY($2)
+            SELECT a.slug, a.title, f.created_at as favorited_at
+            FROM favorites f
+            JOIN articles a ON a.id = f.article_id
+            WHERE f.user_id = $1 AND f.org_id = $2
+            ORDER BY f.created_at DESC, f.id DESC
             "#,
         )
         .bind(user_id)
-        .bind(article_ids)
-        .fetch_all(&self.pool)
-        .await?;
-
-        Ok(rows.iter().map(|r| r.get::<i32, _>("article_id")).collect())
+        .bind(org)
+        .traced(self.db.pool())
+        .fetch(&mut **conn)
     }
 }
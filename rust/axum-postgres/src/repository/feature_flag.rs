@@ -0,0 +1,84 @@
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::database::Traced;
+use crate::models::{ALL_ENVIRONMENTS, FeatureFlag};
+
+#[derive(Clone)]
+pub struct FeatureFlagRepository {
+    pool: PgPool,
+}
+
+impl FeatureFlagRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.feature_flag.list_for_environment", skip(self))]
+    pub async fn list_for_environment(
+        &self,
+        environment: &str,
+    ) -> Result<Vec<FeatureFlag>, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(
+            r#"
+            SELECT name, environment, enabled, description, updated_at
+            FROM feature_flags
+            WHERE environment = $1 OR environment = $2
+            ORDER BY name, environment
+            "#,
+        )
+        .bind(environment)
+        .bind(ALL_ENVIRONMENTS)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.feature_flag.find", skip(self))]
+    pub async fn find(
+        &self,
+        name: &str,
+        environment: &str,
+    ) -> Result<Option<FeatureFlag>, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(
+            r#"
+            SELECT name, environment, enabled, description, updated_at
+            FROM feature_flags
+            WHERE name = $1 AND environment = $2
+            "#,
+        )
+        .bind(name)
+        .bind(environment)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.feature_flag.upsert", skip(self))]
+    pub async fn upsert(
+        &self,
+        name: &str,
+        environment: &str,
+        enabled: bool,
+        description: Option<&str>,
+    ) -> Result<FeatureFlag, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(
+            r#"
+            INSERT INTO feature_flags (name, environment, enabled, description)
+            VALUES ($1, $2, $3, COALESCE($4, ''))
+            ON CONFLICT (name, environment) DO UPDATE SET
+                enabled = EXCLUDED.enabled,
+                description = COALESCE($4, feature_flags.description),
+                updated_at = CURRENT_TIMESTAMP
+            RETURNING name, environment, enabled, description, updated_at
+            "#,
+        )
+        .bind(name)
+        .bind(environment)
+        .bind(enabled)
+        .bind(description)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+}
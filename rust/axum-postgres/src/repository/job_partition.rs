@@ -0,0 +1,94 @@
+use sqlx::PgPool;
+use time::Date;
+use tracing::instrument;
+
+use crate::database::Traced;
+
+/// Lock waits past this give up on a partition until the next run, rather
+/// than holding up the worker's queries queued behind the DDL.
+const LOCK_TIMEOUT: &str = "5s";
+
+/// The monthly partitions of `jobs`. Names and bounds are built from dates,
+/// never from input, so they are safe to format into DDL.
+#[derive(Clone)]
+pub struct JobPartitionRepository {
+    pool: PgPool,
+}
+
+impl JobPartitionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.job_partition.exists", skip(self))]
+    pub async fn exists(&self, name: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(name)
+            .traced(&self.pool)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// The first day of every month with a job in `jobs_default`, which
+    /// catches whatever no month partition covered when it was inserted.
+    #[instrument(name = "db.job_partition.stranded_months", skip(self))]
+    pub async fn stranded_months(&self) -> Result<Vec<Date>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT date_trunc('month', created_at AT TIME ZONE 'UTC')::date
+            FROM jobs_default
+            ORDER BY 1
+            "#,
+        )
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Creates partition `name` for `[from, to)` and moves its rows out of
+    /// `jobs_default` into it, in one transaction. A new partition can't be
+    /// attached while the default holds rows in its range, so it starts as
+    /// a plain table and is attached once they have moved. Returns how many
+    /// moved.
+    #[instrument(name = "db.job_partition.attach", skip(self))]
+    pub async fn attach(&self, name: &str, from: Date, to: Date) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT set_config('lock_timeout', $1, true)")
+            .bind(LOCK_TIMEOUT)
+            .traced(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "CREATE TABLE {name} (LIKE jobs INCLUDING DEFAULTS INCLUDING CONSTRAINTS)"
+        ))
+        .traced(&self.pool)
+        .execute(&mut *tx)
+        .await?;
+        let moved = sqlx::query(&format!(
+            r#"
+            WITH moved AS (
+                DELETE FROM jobs_default
+                WHERE created_at >= $1::date::timestamp AT TIME ZONE 'UTC'
+                  AND created_at < $2::date::timestamp AT TIME ZONE 'UTC'
+                RETURNING *
+            )
+            INSERT INTO {name} SELECT * FROM moved
+            "#
+        ))
+        .bind(from)
+        .bind(to)
+        .traced(&self.pool)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query(&format!(
+            "ALTER TABLE jobs ATTACH PARTITION {name} \
+             FOR VALUES FROM ('{from} 00:00:00+00') TO ('{to} 00:00:00+00')"
+        ))
+        .traced(&self.pool)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(moved)
+    }
+}
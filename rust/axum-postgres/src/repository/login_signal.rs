@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::database::{Traced, retrying};
+use crate::models::{GeoPoint, LastSignIn, UserDevice};
+
+/// `user_devices` and `login_failures`, keyed by user like `sessions`.
+#[derive(Clone)]
+pub struct LoginSignalRepository {
+    pool: PgPool,
+}
+
+impl LoginSignalRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.login_signal.last_sign_in", skip(self))]
+    pub async fn last_sign_in(&self, user_id: i32) -> Result<Option<LastSignIn>, sqlx::Error> {
+        sqlx::query_as::<_, LastSignIn>(
+            r#"
+            SELECT latitude, longitude, last_ip, last_seen_at
+            FROM user_devices
+            WHERE user_id = $1 AND latitude IS NOT NULL AND longitude IS NOT NULL
+            ORDER BY last_seen_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Returns whether the device is new to the user. `xmax` is zero only
+    /// on a row this statement inserted.
+    #[instrument(name = "db.login_signal.record_device", skip(self, user_agent, ip))]
+    pub async fn record_device(
+        &self,
+        user_id: i32,
+        user_agent: &str,
+        ip: Option<&str>,
+        location: Option<GeoPoint>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO user_devices (user_id, user_agent, last_ip, latitude, longitude)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, user_agent) DO UPDATE
+                SET last_ip = EXCLUDED.last_ip,
+                    latitude = EXCLUDED.latitude,
+                    longitude = EXCLUDED.longitude,
+                    last_seen_at = CURRENT_TIMESTAMP
+            RETURNING xmax = 0
+            "#,
+        )
+        .bind(user_id)
+        .bind(user_agent)
+        .bind(ip)
+        .bind(location.map(|l| l.latitude))
+        .bind(location.map(|l| l.longitude))
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Records a wrong password and returns how many the user has had
+    /// within `window`, this one included, deleting older ones.
+    #[instrument(name = "db.login_signal.record_failure", skip(self, ip))]
+    pub async fn record_failure(
+        &self,
+        user_id: i32,
+        ip: Option<&str>,
+        window: Duration,
+    ) -> Result<i64, sqlx::Error> {
+        retrying(|| async move {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query(
+                r#"
+                DELETE FROM login_failures
+                WHERE user_id = $1 AND created_at <= CURRENT_TIMESTAMP - make_interval(secs => $2)
+                "#,
+            )
+            .bind(user_id)
+            .bind(window.as_secs_f64())
+            .traced(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("INSERT INTO login_failures (user_id, ip) VALUES ($1, $2)")
+                .bind(user_id)
+                .bind(ip)
+                .traced(&self.pool)
+                .execute(&mut *tx)
+                .await?;
+            let failures =
+                sqlx::query_scalar("SELECT COUNT(*) FROM login_failures WHERE user_id = $1")
+                    .bind(user_id)
+                    .traced(&self.pool)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            tx.commit().await?;
+            Ok(failures)
+        })
+        .await
+    }
+
+    #[instrument(name = "db.login_signal.list_devices", skip(self))]
+    pub async fn list_devices(&self, user_id: i32) -> Result<Vec<UserDevice>, sqlx::Error> {
+        sqlx::query_as::<_, UserDevice>(
+            r#"
+            SELECT user_agent, last_ip, latitude, longitude, first_seen_at, last_seen_at
+            FROM user_devices
+            WHERE user_id = $1
+            ORDER BY last_seen_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// The rows keep addresses and user agents.
+    #[instrument(name = "db.login_signal.delete_for_user", skip(self))]
+    pub async fn delete_for_user(&self, user_id: i32) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("DELETE FROM user_devices WHERE user_id = $1")
+                .bind(user_id)
+                .traced(&self.pool)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM login_failures WHERE user_id = $1")
+                .bind(user_id)
+                .traced(&self.pool)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await
+        })
+        .await
+    }
+}
@@ -1,7 +1,145 @@
+mod api_token;
 mod article;
+mod article_query;
+#[allow(dead_code)]
+mod article_score;
+mod audit;
 mod favorite;
+mod feature_flag;
+#[allow(dead_code)]
+mod job_partition;
+mod login_signal;
+mod organization;
+mod session;
+mod two_factor;
 mod user;
 
+pub use api_token::ApiTokenRepository;
 pub use article::ArticleRepository;
+pub use article_query::{ArticleFilter, ArticleOrder};
+pub use article_score::ArticleScoreRepository;
+pub use audit::AuditRepository;
 pub use favorite::FavoriteRepository;
+pub use feature_flag::FeatureFlagRepository;
+#[allow(unused_imports)]
+pub use job_partition::JobPartitionRepository;
+pub use login_signal::LoginSignalRepository;
+pub use organization::OrganizationRepository;
+pub use session::SessionRepository;
+pub use two_factor::TwoFactorRepository;
 pub use user::UserRepository;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    /// Tables with an `org_id` column.
+    const TENANT_TABLES: &[&str] = &["users", "articles", "favorites"];
+
+    /// Marks a query that deliberately spans tenants.
+    const ALL_TENANTS: &str = "-- all tenants";
+
+    /// The contents of every string literal, raw or not, in a source file.
+    fn string_literals(source: &str) -> Vec<&str> {
+        let mut literals = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find('"') {
+            let raw = rest[..start].ends_with("r#");
+            let body = &rest[start + 1..];
+            let end = if raw {
+                body.find("\"#")
+            } else {
+                body.char_indices()
+                    .find(|&(i, c)| c == '"' && !body[..i].ends_with('\\'))
+                    .map(|(i, _)| i)
+            };
+            let Some(end) = end else { break };
+            literals.push(&body[..end]);
+            rest = &body[end + 1..];
+        }
+        literals
+    }
+
+    fn touches_tenant_table(sql: &str) -> bool {
+        let words: Vec<String> = sql
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        words.windows(2).any(|pair| {
+            matches!(
+                pair[0].as_str(),
+                "from" | "join" | "into" | "update" | "using"
+            ) && TENANT_TABLES.contains(&pair[1].as_str())
+        })
+    }
+
+    fn is_scoped(sql: &str) -> bool {
+        sql.contains("org_id") || sql.contains(ALL_TENANTS)
+    }
+
+    #[test]
+    fn test_every_query_on_a_tenant_table_is_scoped() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/repository");
+        let mut checked = 0;
+        let mut unscoped = Vec::new();
+
+        for entry in fs::read_dir(&dir).expect("repository directory") {
+            let path = entry.expect("directory entry").path();
+            let source = fs::read_to_string(&path).expect("repository source");
+            // Test fixtures, including this module's, aren't queries.
+            let source = source.split("#[cfg(test)]").next().unwrap_or_default();
+            for sql in string_literals(source) {
+                if !touches_tenant_table(sql) {
+                    continue;
+                }
+                checked += 1;
+                if !is_scoped(sql) {
+                    unscoped.push(format!("{}:\n{}", path.display(), sql.trim()));
+                }
+            }
+        }
+
+        assert!(
+            checked >= 20,
+            "only found {checked} queries; is the literal scan broken?"
+        );
+        assert!(
+            unscoped.is_empty(),
+            "queries on tenant tables without an org_id predicate or `{ALL_TENANTS}`:\n\n{}",
+            unscoped.join("\n\n")
+        );
+    }
+
+    #[test]
+    fn test_scope_check_catches_a_forgotten_predicate() {
+        let forgotten = "DELETE FROM articles WHERE id = $1";
+        assert!(touches_tenant_table(forgotten));
+        assert!(!is_scoped(forgotten));
+
+        assert!(is_scoped(
+            "DELETE FROM articles WHERE id = $1 AND org_id = $2"
+        ));
+        assert!(is_scoped("-- all tenants\nSELECT id FROM articles"));
+        assert!(touches_tenant_table(
+            "SELECT EXISTS(SELECT 1 FROM favorites WHERE user_id = $1)"
+        ));
+        assert!(!touches_tenant_table("SELECT * FROM api_tokens"));
+        assert!(!touches_tenant_table("SELECT * FROM article_scores"));
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let source = r###"
+            sqlx::query("SELECT \"a\" FROM articles").bind(x);
+            sqlx::query(r#"
+                UPDATE users SET name = 'x'
+            "#);
+        "###;
+        let literals = string_literals(source);
+        assert_eq!(literals.len(), 2);
+        assert_eq!(literals[0], r#"SELECT \"a\" FROM articles"#);
+        assert_eq!(literals[1].trim(), "UPDATE users SET name = 'x'");
+    }
+}
@@ -0,0 +1,53 @@
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::database::Traced;
+use crate::models::Organization;
+
+#[derive(Clone)]
+pub struct OrganizationRepository {
+    pool: PgPool,
+}
+
+impl OrganizationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.organization.create", skip(self))]
+    pub async fn create(&self, slug: &str, name: &str) -> Result<Organization, sqlx::Error> {
+        sqlx::query_as::<_, Organization>(
+            r#"
+            INSERT INTO organizations (slug, name)
+            VALUES ($1, $2)
+            RETURNING id, slug, name, created_at
+            "#,
+        )
+        .bind(slug)
+        .bind(name)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.organization.find_by_slug", skip(self))]
+    pub async fn find_by_slug(&self, slug: &str) -> Result<Option<Organization>, sqlx::Error> {
+        sqlx::query_as::<_, Organization>(
+            "SELECT id, slug, name, created_at FROM organizations WHERE slug = $1",
+        )
+        .bind(slug)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.organization.list", skip(self))]
+    pub async fn list(&self) -> Result<Vec<Organization>, sqlx::Error> {
+        sqlx::query_as::<_, Organization>(
+            "SELECT id, slug, name, created_at FROM organizations ORDER BY id",
+        )
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
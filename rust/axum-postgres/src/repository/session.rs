@@ -0,0 +1,153 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::database::Traced;
+use crate::models::{OrgId, Session};
+
+#[derive(Clone)]
+pub struct SessionRepository {
+    pool: PgPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "db.session.create", skip(self, jti, user_agent))]
+    pub async fn create(
+        &self,
+        user_id: i32,
+        jti: Uuid,
+        user_agent: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<i32, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO sessions (user_id, jti, user_agent, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(jti)
+        .bind(user_agent)
+        .bind(expires_at)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Neither revoked nor expired.
+    #[instrument(name = "db.session.find_active", skip(self, jti))]
+    pub async fn find_active(&self, jti: Uuid) -> Result<Option<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, user_id, user_agent, created_at, last_seen_at, expires_at
+            FROM sessions
+            WHERE jti = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(jti)
+        .traced(&self.pool)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.session.touch", skip(self))]
+    pub async fn touch(&self, id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(id)
+            .traced(&self.pool)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "db.session.list_for_user", skip(self))]
+    pub async fn list_for_user(&self, user_id: i32) -> Result<Vec<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, user_id, user_agent, created_at, last_seen_at, expires_at
+            FROM sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            ORDER BY last_seen_at DESC, id DESC
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[instrument(name = "db.session.revoke", skip(self))]
+    pub async fn revoke(&self, id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND user_id = $2
+                AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .traced(&self.pool)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes rather than revokes, since a row keeps the user agent. Returns
+    /// how many of the deleted sessions were still active.
+    #[instrument(name = "db.session.delete_for_user", skip(self))]
+    pub async fn delete_for_user(&self, user_id: i32) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            WITH deleted AS (
+                DELETE FROM sessions WHERE user_id = $1
+                RETURNING revoked_at, expires_at
+            )
+            SELECT COUNT(*) FROM deleted
+            WHERE revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(user_id)
+        .traced(&self.pool)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Once a session's token has expired its row has no use left, revoked
+    /// or not.
+    #[instrument(name = "db.session.delete_expired", skip(self))]
+    pub async fn delete_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= CURRENT_TIMESTAMP")
+            .traced(&self.pool)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Every organization, those without sessions included, so a gauge
+    /// recorded from this drops to zero rather than keeping its last value.
+    #[instrument(name = "db.session.count_active", skip(self))]
+    pub async fn count_active(&self) -> Result<Vec<(OrgId, i64)>, sqlx::Error> {
+        sqlx::query_as::<_, (OrgId, i64)>(
+            r#"
+            -- all tenants: one count per organization
+            SELECT o.id, COUNT(s.id)
+            FROM organizations o
+            LEFT JOIN users u ON u.org_id = o.id
+            LEFT JOIN sessions s ON s.user_id = u.id
+                AND s.revoked_at IS NULL AND s.expires_at > CURRENT_TIMESTAMP
+            GROUP BY o.id
+            ORDER BY o.id
+            "#,
+        )
+        .traced(&self.pool)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
@@ -1,74 +1,229 @@
-use sqlx::{PgPool, Row};
+use sqlx::Row;
 use tracing::instrument;
 
-use crate::models::User;
+use crate::database::{TenantDb, Traced, retrying};
+use crate::models::{OrgId, User};
 
 #[derive(Clone)]
 pub struct UserRepository {
-    pool: PgPool,
+    db: TenantDb,
 }
 
 impl UserRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: TenantDb) -> Self {
+        Self { db }
     }
 
     #[instrument(name = "db.user.create", skip(self, password_hash))]
     pub async fn create(
         &self,
+        org: OrgId,
         email: &str,
         password_hash: &str,
+        username: &str,
         name: &str,
     ) -> Result<User, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            r#"
-            INSERT INTO users (email, password_hash, name)
-            VALUES ($1, $2, $3)
-            RETURNING id, email, password_hash, name, bio, image, created_at, updated_at
-            "#,
-        )
-        .bind(email)
-        .bind(password_hash)
-        .bind(name)
-        .fetch_one(&self.pool)
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (org_id, email, password_hash, username, name)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, org_id, email, password_hash, username, name, bio, image, created_at, updated_at
+                "#,
+            )
+            .bind(org)
+            .bind(email)
+            .bind(password_hash)
+            .bind(username)
+            .bind(name)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(user)
+        })
         .await
     }
 
+    /// Emails are unique across tenants, so this is how login finds the user's
+    /// tenant.
     #[instrument(name = "db.user.find_by_email", skip(self))]
     pub async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            r#"
-            SELECT id, email, password_hash, name, bio, image, created_at, updated_at
-            FROM users
-            WHERE email = $1
-            "#,
-        )
-        .bind(email)
-        .fetch_optional(&self.pool)
+        retrying(|| async move {
+            sqlx::query_as::<_, User>(
+                r#"
+                -- all tenants
+                SELECT id, org_id, email, password_hash, username, name, bio, image, created_at, updated_at
+                FROM users
+                WHERE email = $1 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(email)
+            .traced(self.db.pool())
+            .fetch_optional(self.db.pool())
+            .await
+        })
         .await
     }
 
     #[instrument(name = "db.user.find_by_id", skip(self))]
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<User>, sqlx::Error> {
-        sqlx::query_as::<_, User>(
-            r#"
-            SELECT id, email, password_hash, name, bio, image, created_at, updated_at
-            FROM users
-            WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
+    pub async fn find_by_id(&self, org: OrgId, id: i32) -> Result<Option<User>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                SELECT id, org_id, email, password_hash, username, name, bio, image, created_at, updated_at
+                FROM users
+                WHERE id = $1 AND org_id = $2 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(user)
+        })
+        .await
+    }
+
+    #[instrument(name = "db.user.find_by_username", skip(self))]
+    pub async fn find_by_username(
+        &self,
+        org: OrgId,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                SELECT id, org_id, email, password_hash, username, name, bio, image, created_at, updated_at
+                FROM users
+                WHERE username = $1 AND org_id = $2 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(username)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_optional(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(user)
+        })
         .await
     }
 
     #[instrument(name = "db.user.exists_by_email", skip(self))]
     pub async fn exists_by_email(&self, email: &str) -> Result<bool, sqlx::Error> {
-        let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) as exists")
+        retrying(|| async move {
+            let row = sqlx::query(
+                r#"
+                -- all tenants
+                SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) as exists
+                "#,
+            )
             .bind(email)
-            .fetch_one(&self.pool)
+            .traced(self.db.pool())
+            .fetch_one(self.db.pool())
             .await?;
 
-        Ok(row.get::<bool, _>("exists"))
+            Ok(row.get::<bool, _>("exists"))
+        })
+        .await
+    }
+
+    #[instrument(name = "db.user.update_image", skip(self))]
+    pub async fn update_image(&self, org: OrgId, id: i32, image: &str) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            sqlx::query("UPDATE users SET image = $2 WHERE id = $1 AND org_id = $3")
+                .bind(id)
+                .bind(image)
+                .bind(org)
+                .traced(self.db.pool())
+                .execute(&mut *conn)
+                .await?;
+            conn.finish().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "db.user.update_password_hash", skip(self, password_hash))]
+    pub async fn update_password_hash(
+        &self,
+        org: OrgId,
+        id: i32,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1 AND org_id = $3")
+                .bind(id)
+                .bind(password_hash)
+                .bind(org)
+                .traced(self.db.pool())
+                .execute(&mut *conn)
+                .await?;
+            conn.finish().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Marks the user deleted and overwrites what identifies them. Their email
+    /// and username are freed for a new registration. Returns `false` when
+    /// the user was already deleted.
+    #[instrument(name = "db.user.soft_delete", skip(self))]
+    pub async fn soft_delete(&self, org: OrgId, id: i32) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET deleted_at = CURRENT_TIMESTAMP,
+                    email = 'deleted-' || id || '@deleted.invalid',
+                    username = 'deleted_' || id,
+                    name = 'Deleted user',
+                    bio = '',
+                    image = '',
+                    password_hash = ''
+                WHERE id = $1 AND org_id = $2 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .traced(self.db.pool())
+            .execute(&mut *conn)
+            .await?;
+            conn.finish().await?;
+            Ok(result.rows_affected() == 1)
+        })
+        .await
+    }
+
+    #[instrument(name = "db.user.is_deleted", skip(self))]
+    pub async fn is_deleted(&self, org: OrgId, id: i32) -> Result<bool, sqlx::Error> {
+        retrying(|| async move {
+            let mut conn = self.db.scope(org).await?;
+            let row = sqlx::query(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM users WHERE id = $1 AND org_id = $2 AND deleted_at IS NOT NULL
+                ) as exists
+                "#,
+            )
+            .bind(id)
+            .bind(org)
+            .traced(self.db.pool())
+            .fetch_one(&mut *conn)
+            .await?;
+            conn.finish().await?;
+
+            Ok(row.get::<bool, _>("exists"))
+        })
+        .await
     }
 }
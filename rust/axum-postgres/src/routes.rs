@@ -1,29 +1,154 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
     routing::{delete, get, post, put},
 };
 
-use crate::{AppState, handlers};
+use crate::{
+    AppState, handlers,
+    middleware::{authorize, shed_low_priority},
+};
+
+/// Headroom for multipart boundaries and part headers on top of the image
+/// itself; the per-field limit is enforced separately while reading.
+const MULTIPART_OVERHEAD_BYTES: usize = 16 * 1024;
 
+/// v1 is served at `/api/v1` and, for the clients that predate versioning,
+/// at `/api`; its contract is frozen. New shapes go in v2, which can cover
+/// an endpoint at a time since both sit on the same services. Both check
+/// the authorization policy before their handlers.
 pub fn create_router(state: AppState) -> Router {
+    let authz = from_fn_with_state(state.clone(), authorize);
+    let v1 = v1_routes(&state).route_layer(authz.clone());
+
+    Router::new()
+        .route("/healthz", get(handlers::liveness))
+        .route("/readyz", get(handlers::health_check))
+        .nest("/api", v1.clone())
+        .nest("/api/v1", v1)
+        .nest("/api/v2", v2_routes(&state).route_layer(authz))
+        .with_state(state)
+}
+
+fn v1_routes(state: &AppState) -> Router<AppState> {
+    let upload_limit =
+        DefaultBodyLimit::max(state.image_service.max_bytes() + MULTIPART_OVERHEAD_BYTES);
+    // Listings are what an overloaded server gives up first: a client can
+    // retry them, and they're the heaviest reads.
+    let shed = from_fn_with_state(state.load_shedder.clone(), shed_low_priority);
+
     Router::new()
-        .route("/api/health", get(handlers::health_check))
-        .route("/api/register", post(handlers::register))
-        .route("/api/login", post(handlers::login))
-        .route("/api/user", get(handlers::get_user))
-        .route("/api/logout", post(handlers::logout))
-        .route("/api/articles", get(handlers::list_articles))
-        .route("/api/articles", post(handlers::create_article))
-        .route("/api/articles/{slug}", get(handlers::get_article))
-        .route("/api/articles/{slug}", put(handlers::update_article))
-        .route("/api/articles/{slug}", delete(handlers::delete_article))
-        .route(
-            "/api/articles/{slug}/favorite",
+        .route("/health", get(handlers::health_check))
+        .route("/register", post(handlers::register))
+        .route("/login", post(handlers::login))
+        .route("/login/2fa", post(handlers::login_two_factor))
+        .route("/user", get(handlers::get_user))
+        .route("/user", delete(handlers::delete_user))
+        .route("/logout", post(handlers::logout))
+        .route(
+            "/user/articles",
+            get(handlers::list_user_articles).layer(shed.clone()),
+        )
+        .route(
+            "/user/favorites",
+            get(handlers::list_user_favorites).layer(shed.clone()),
+        )
+        .route(
+            "/user/export",
+            get(handlers::export_user).layer(shed.clone()),
+        )
+        .route("/user/2fa/setup", post(handlers::setup_two_factor))
+        .route("/user/2fa/verify", post(handlers::verify_two_factor))
+        .route("/user/tokens", get(handlers::list_api_tokens))
+        .route("/user/tokens", post(handlers::create_api_token))
+        .route("/user/tokens/{id}", delete(handlers::revoke_api_token))
+        .route("/user/sessions", get(handlers::list_sessions))
+        .route("/user/sessions/{id}", delete(handlers::revoke_session))
+        .route(
+            "/user/image",
+            post(handlers::upload_avatar).layer(upload_limit),
+        )
+        .route("/images/{*key}", get(handlers::serve_image))
+        .route("/profiles/{username}", get(handlers::get_profile))
+        .route(
+            "/articles",
+            get(handlers::list_articles).layer(shed.clone()),
+        )
+        .route("/articles", post(handlers::create_article))
+        .route(
+            "/articles/export",
+            get(handlers::export_articles).layer(shed.clone()),
+        )
+        .route(
+            "/articles/trending",
+            get(handlers::trending_articles).layer(shed.clone()),
+        )
+        .route(
+            "/articles/suggest",
+            get(handlers::suggest_articles).layer(shed),
+        )
+        .route("/articles/favorited", post(handlers::favorited_articles))
+        .route("/articles/{slug}", get(handlers::get_article))
+        .route("/articles/{slug}", put(handlers::update_article))
+        .route("/articles/{slug}", delete(handlers::delete_article))
+        .route(
+            "/articles/{slug}/economic-context",
+            get(handlers::get_economic_context),
+        )
+        .route(
+            "/articles/{slug}/authors",
+            post(handlers::add_article_author),
+        )
+        .route(
+            "/articles/{slug}/authors/{user_id}",
+            delete(handlers::remove_article_author),
+        )
+        .route(
+            "/articles/{slug}/transfer",
+            post(handlers::transfer_article),
+        )
+        .route(
+            "/articles/{slug}/favorite",
             post(handlers::favorite_article),
         )
         .route(
-            "/api/articles/{slug}/favorite",
+            "/articles/{slug}/favorite",
             delete(handlers::unfavorite_article),
         )
-        .with_state(state)
+        .route(
+            "/articles/{slug}/cover",
+            post(handlers::upload_cover).layer(upload_limit),
+        )
+        .route("/hooks/provider", post(handlers::receive_provider_webhook))
+        .route("/admin/config", get(handlers::get_config))
+        .route("/admin/audit", get(handlers::list_audit_log))
+        .route(
+            "/admin/orgs",
+            get(handlers::list_organizations).post(handlers::create_organization),
+        )
+        .route("/admin/emails", post(handlers::send_email))
+        .route("/admin/flags", get(handlers::list_feature_flags))
+        .route("/admin/flags/{name}", put(handlers::update_feature_flag))
+        .route(
+            "/admin/telemetry",
+            get(handlers::get_telemetry).put(handlers::update_telemetry),
+        )
+}
+
+/// camelCase bodies, keyset pagination and nested errors (see
+/// [`structured_errors`](crate::middleware::structured_errors)).
+fn v2_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/login", post(handlers::v2::login))
+        .route("/login/2fa", post(handlers::v2::login_two_factor))
+        .route(
+            "/articles",
+            get(handlers::v2::list_articles).layer(from_fn_with_state(
+                state.load_shedder.clone(),
+                shed_low_priority,
+            )),
+        )
+        .route("/articles", post(handlers::v2::create_article))
+        .route("/articles/{slug}", get(handlers::v2::get_article))
 }
@@ -0,0 +1,220 @@
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{Extension, Router, extract::ConnectInfo, http::Version, serve::Listener};
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::{conn::auto::Builder, graceful::GracefulShutdown};
+use hyper_util::service::TowerToHyperService;
+use tokio::sync::Semaphore;
+use tower::Layer;
+
+use crate::config::Config;
+
+/// How long an HTTP/2 keep-alive ping may go unanswered before the
+/// connection is closed (hyper's default).
+const HTTP2_PING_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The connection settings `axum::serve` keeps to hyper's defaults, named
+/// after their actix-web `HttpServer` counterparts so the two examples can
+/// be run under the same ones.
+#[derive(Debug, Clone)]
+pub struct ServerTuning {
+    pub http2: bool,
+    /// Zero advertises no limit.
+    pub http2_max_concurrent_streams: u32,
+    /// How long an HTTP/1.1 connection may wait for the next request's
+    /// headers, and how often an HTTP/2 connection is pinged. Zero turns
+    /// keep-alive off.
+    pub keep_alive: Duration,
+    /// Zero is no limit.
+    pub max_connections: usize,
+}
+
+impl ServerTuning {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            http2: config.http2_enabled,
+            http2_max_concurrent_streams: config.http2_max_concurrent_streams,
+            keep_alive: Duration::from_secs(config.http_keep_alive_secs),
+            max_connections: config.max_connections,
+        }
+    }
+
+    fn builder(&self) -> Builder<TokioExecutor> {
+        let mut builder = Builder::new(TokioExecutor::new());
+        let keep_alive = (!self.keep_alive.is_zero()).then_some(self.keep_alive);
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(keep_alive.is_some())
+            .header_read_timeout(keep_alive);
+        if !self.http2 {
+            return builder.http1_only();
+        }
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(
+                (self.http2_max_concurrent_streams > 0)
+                    .then_some(self.http2_max_concurrent_streams),
+            )
+            .keep_alive_interval(keep_alive)
+            .keep_alive_timeout(HTTP2_PING_TIMEOUT);
+        builder
+    }
+}
+
+/// `axum::serve` with [`ServerTuning`]: HTTP/1.1, and HTTP/2 by ALPN or
+/// prior knowledge. At `max_connections` the listener stops accepting, so
+/// further clients wait in the kernel's backlog rather than being reset.
+/// Requests carry the peer address as `ConnectInfo<L::Addr>`.
+/// Once `signal` completes, open connections finish their requests and
+/// close before this returns.
+pub async fn serve<L>(
+    mut listener: L,
+    app: Router,
+    tuning: &ServerTuning,
+    signal: impl Future<Output = ()>,
+) where
+    L: Listener,
+    L::Addr: Clone + Send + Sync + 'static,
+{
+    let builder = tuning.builder();
+    let limit =
+        (tuning.max_connections > 0).then(|| Arc::new(Semaphore::new(tuning.max_connections)));
+    let graceful = GracefulShutdown::new();
+    let mut signal = pin!(signal);
+
+    loop {
+        let permit = match &limit {
+            Some(limit) => tokio::select! {
+                permit = limit.clone().acquire_owned() => permit.ok(),
+                () = &mut signal => break,
+            },
+            None => None,
+        };
+        let (io, addr) = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = &mut signal => break,
+        };
+
+        let service = TowerToHyperService::new(Extension(ConnectInfo(addr)).layer(app.clone()));
+        let connection = builder
+            .serve_connection(TokioIo::new(io), service)
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!(error = %e, "Connection closed with an error");
+            }
+            drop(permit);
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// `network.protocol.version` for a request, as OpenTelemetry spells it.
+pub fn protocol_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_11 => "1.1",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{extract::Request, routing::get};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    const TUNING: ServerTuning = ServerTuning {
+        http2: true,
+        http2_max_concurrent_streams: 200,
+        keep_alive: Duration::from_secs(75),
+        max_connections: 0,
+    };
+
+    async fn spawn(tuning: ServerTuning) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/",
+            get(|request: Request| async move { protocol_version(request.version()) }),
+        );
+        tokio::spawn(async move { serve(listener, app, &tuning, std::future::pending()).await });
+        addr
+    }
+
+    async fn version(client: reqwest::Client, addr: SocketAddr) -> reqwest::Result<String> {
+        client
+            .get(format!("http://{addr}/"))
+            .timeout(Duration::from_millis(500))
+            .send()
+            .await?
+            .text()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_serves_http2_only_when_enabled() {
+        let http1 = reqwest::Client::builder().http1_only().build().unwrap();
+        let http2 = reqwest::Client::builder()
+            .http2_prior_knowledge()
+            .build()
+            .unwrap();
+
+        let addr = spawn(TUNING).await;
+        assert_eq!(version(http1.clone(), addr).await.unwrap(), "1.1");
+        assert_eq!(version(http2.clone(), addr).await.unwrap(), "2");
+
+        let addr = spawn(ServerTuning {
+            http2: false,
+            ..TUNING
+        })
+        .await;
+        assert_eq!(version(http1, addr).await.unwrap(), "1.1");
+        assert!(version(http2, addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_the_peer_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.ip().to_string() }),
+        );
+        tokio::spawn(async move { serve(listener, app, &TUNING, std::future::pending()).await });
+
+        assert_eq!(
+            version(reqwest::Client::new(), addr).await.unwrap(),
+            "127.0.0.1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stops_accepting_at_max_connections() {
+        let addr = spawn(ServerTuning {
+            max_connections: 1,
+            ..TUNING
+        })
+        .await;
+        let client = reqwest::Client::new();
+
+        let idle = TcpStream::connect(addr).await.unwrap();
+        assert!(version(client.clone(), addr).await.is_err());
+
+        drop(idle);
+        assert_eq!(version(client, addr).await.unwrap(), "1.1");
+    }
+}
@@ -0,0 +1,436 @@
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use opentelemetry::KeyValue;
+use serde::Serialize;
+use serde_json::{Value, json};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tokio::sync::mpsc;
+use tracing::{Instrument, instrument};
+
+use super::article::resolve_images;
+use super::{AuditRecorder, Principal, export, markdown};
+use crate::{
+    error::{AppError, AppResult, RecordErr},
+    jobs::{ACCOUNT_ERASURE_JOB_KIND, AccountErasurePayload, JobQueue},
+    models::{
+        Actor, ApiTokenDto, ArticleDto, AuditEntry, DeletedAccountResponse, DeletionPolicy,
+        ProfileResponse, SessionDto, UserDevice,
+    },
+    repository::{
+        ApiTokenRepository, ArticleRepository, FavoriteRepository, LoginSignalRepository,
+        SessionRepository, TwoFactorRepository, UserRepository,
+    },
+    storage::Storage,
+    telemetry::{SESSIONS_REVOKED, USERS_DELETED, org_attribute},
+};
+
+const EXPORT_CHUNK_BYTES: usize = 32 * 1024;
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+
+/// `DELETE /api/user` and `GET /api/user/export`.
+#[derive(Clone)]
+pub struct AccountService {
+    user_repo: UserRepository,
+    token_repo: ApiTokenRepository,
+    two_factor_repo: TwoFactorRepository,
+    session_repo: SessionRepository,
+    login_signal_repo: LoginSignalRepository,
+    article_repo: ArticleRepository,
+    favorite_repo: FavoriteRepository,
+    job_queue: JobQueue,
+    storage: Storage,
+    audit: AuditRecorder,
+    policy: DeletionPolicy,
+}
+
+impl AccountService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: ApiTokenRepository,
+        two_factor_repo: TwoFactorRepository,
+        session_repo: SessionRepository,
+        login_signal_repo: LoginSignalRepository,
+        article_repo: ArticleRepository,
+        favorite_repo: FavoriteRepository,
+        job_queue: JobQueue,
+        storage: Storage,
+        audit: AuditRecorder,
+        policy: DeletionPolicy,
+    ) -> Self {
+        Self {
+            user_repo,
+            token_repo,
+            two_factor_repo,
+            session_repo,
+            login_signal_repo,
+            article_repo,
+            favorite_repo,
+            job_queue,
+            storage,
+            audit,
+            policy,
+        }
+    }
+
+    /// Revokes the user's tokens, removes their sessions, known devices,
+    /// login failures, two-factor secret and recovery codes and anonymizes
+    /// their account row, which frees
+    /// their email and username, then leaves favorites, the avatar and,
+    /// under [`DeletionPolicy::Delete`], articles to the `account_erasure`
+    /// job. The job is enqueued first and waits for the row to be
+    /// anonymized, so a failure in between is retried by calling this again
+    /// rather than leaving the account half erased. Removing the sessions
+    /// ends every JWT that carries one; older tokens without a session are
+    /// still accepted until they expire.
+    #[instrument(name = "account.delete", skip(self, principal), fields(user_id = principal.user_id))]
+    pub async fn delete(&self, principal: Principal) -> AppResult<DeletedAccountResponse> {
+        async move {
+            let Principal { user_id, org } = principal;
+            let user = self
+                .user_repo
+                .find_by_id(org, user_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+            let payload = AccountErasurePayload {
+                org_id: org,
+                user_id,
+                policy: self.policy,
+                avatar: user.image,
+            };
+            let (job_id, _) = self
+                .job_queue
+                .enqueue_once(
+                    ACCOUNT_ERASURE_JOB_KIND,
+                    &format!("{ACCOUNT_ERASURE_JOB_KIND}:{user_id}"),
+                    payload,
+                )
+                .await?;
+            let tokens_revoked = self.token_repo.revoke_all(user_id).await?;
+            let sessions_revoked = self.session_repo.delete_for_user(user_id).await?;
+            self.login_signal_repo.delete_for_user(user_id).await?;
+            self.two_factor_repo.delete_for_user(user_id).await?;
+            if !self.user_repo.soft_delete(org, user_id).await? {
+                return Err(AppError::NotFound("User not found".to_string()));
+            }
+
+            SESSIONS_REVOKED.add(
+                sessions_revoked as u64,
+                &[
+                    org_attribute(org),
+                    KeyValue::new("reason", "account_deleted"),
+                ],
+            );
+            USERS_DELETED.add(
+                1,
+                &[
+                    org_attribute(org),
+                    KeyValue::new("policy", self.policy.as_str()),
+                ],
+            );
+            // The entry outlives the account, so it records nothing that
+            // identified them.
+            self.audit
+                .record(
+                    AuditEntry::new(Actor::User(user_id), "user.delete", "user", user_id).changes(
+                        Value::Null,
+                        json!({
+                            "policy": self.policy,
+                            "api_tokens_revoked": tokens_revoked,
+                            "sessions_revoked": sessions_revoked,
+                            "job_id": job_id,
+                        }),
+                    ),
+                )
+                .await;
+            tracing::info!(user_id, job_id, policy = %self.policy, "Account deleted");
+
+            Ok(DeletedAccountResponse {
+                job_id,
+                policy: self.policy,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    /// The user's account, API tokens, active sessions, known devices, the
+    /// articles they author, their favorites and their audit log entries as
+    /// one JSON object, streamed like the article export.
+    #[instrument(name = "account.export", skip(self, principal), fields(user_id = principal.user_id))]
+    pub async fn export(
+        &self,
+        principal: Principal,
+    ) -> AppResult<impl Stream<Item = Result<Bytes, AppError>> + Send + 'static> {
+        let Principal { user_id, org } = principal;
+        let (user, tokens, sessions, devices) = async {
+            let user = self
+                .user_repo
+                .find_by_id(org, user_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            let tokens = self.token_repo.list_for_user(user_id).await?;
+            let sessions = self.session_repo.list_for_user(user_id).await?;
+            let devices = self.login_signal_repo.list_devices(user_id).await?;
+            Ok::<_, AppError>((user, tokens, sessions, devices))
+        }
+        .await
+        .record_err()?;
+        self.audit
+            .record(AuditEntry::new(
+                Actor::User(user_id),
+                "user.export",
+                "user",
+                user_id,
+            ))
+            .await;
+
+        let (tx, rx) = mpsc::channel::<Result<Bytes, AppError>>(EXPORT_CHANNEL_CAPACITY);
+        let service = self.clone();
+        tokio::spawn(
+            async move {
+                let mut user = ProfileResponse::from(user);
+                user.image = service.storage.resolve_image(&user.image);
+                let tokens: Vec<ApiTokenDto> = tokens.into_iter().map(ApiTokenDto::from).collect();
+                let sessions: Vec<SessionDto> = sessions
+                    .into_iter()
+                    .map(|session| SessionDto::new(session, None))
+                    .collect();
+
+                let mut archive = Archive::new(tx);
+                match service
+                    .write(&mut archive, principal, &user, &tokens, &sessions, &devices)
+                    .await
+                {
+                    Ok(()) => {
+                        archive.finish().await;
+                        tracing::info!(bytes = archive.bytes, "Account export completed");
+                    }
+                    Err(ArchiveError::Disconnected) => {
+                        tracing::warn!(
+                            bytes = archive.bytes,
+                            "Client disconnected during account export"
+                        );
+                    }
+                    Err(ArchiveError::Database(e)) => {
+                        tracing::error!(error = %e, "Account export failed");
+                        let _ = archive.tx.send(Err(AppError::Database(e))).await;
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+
+        Ok(export::body(
+            rx,
+            [KeyValue::new("export.format", "json"), org_attribute(org)],
+        ))
+    }
+
+    async fn write(
+        &self,
+        archive: &mut Archive,
+        principal: Principal,
+        user: &ProfileResponse,
+        tokens: &[ApiTokenDto],
+        sessions: &[SessionDto],
+        devices: &[UserDevice],
+    ) -> Result<(), ArchiveError> {
+        let Principal { user_id, org } = principal;
+        let exported_at = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        archive.field("exported_at", &exported_at);
+        archive.field("user", user);
+        archive.field("api_tokens", tokens);
+        archive.field("sessions", sessions);
+        archive.field("devices", devices);
+
+        let mut conn = self.article_repo.scope(org).await?;
+        archive.open_array("articles");
+        let mut articles = self.article_repo.stream_by_author(&mut conn, org, user_id);
+        while let Some(article) = articles.next().await {
+            let article = resolve_images(&self.storage, article?);
+            let body_html = markdown::render(&article.body);
+            archive
+                .element(&ArticleDto::from_article_with_author(
+                    article, false, body_html,
+                ))
+                .await?;
+        }
+        drop(articles);
+        archive.close_array();
+
+        archive.open_array("favorites");
+        let mut favorites = self.favorite_repo.stream_by_user(&mut conn, org, user_id);
+        while let Some(favorite) = favorites.next().await {
+            archive.element(&favorite?).await?;
+        }
+        drop(favorites);
+        archive.close_array();
+        if let Err(e) = conn.finish().await {
+            tracing::warn!(error = %e, "Failed to close export transaction");
+        }
+
+        archive.open_array("audit_log");
+        let mut entries = self.audit.stream_by_actor(user_id);
+        while let Some(entry) = entries.next().await {
+            archive.element(&entry?).await?;
+        }
+        archive.close_array();
+        Ok(())
+    }
+}
+
+enum ArchiveError {
+    Database(sqlx::Error),
+    Disconnected,
+}
+
+impl From<sqlx::Error> for ArchiveError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+/// Writes a JSON object a field at a time into chunks of about
+/// [`EXPORT_CHUNK_BYTES`]. The bounded channel is the backpressure point, as
+/// in the article export.
+struct Archive {
+    buf: Vec<u8>,
+    tx: mpsc::Sender<Result<Bytes, AppError>>,
+    /// Whether the next field or element needs a comma before it.
+    separate: bool,
+    bytes: u64,
+}
+
+impl Archive {
+    fn new(tx: mpsc::Sender<Result<Bytes, AppError>>) -> Self {
+        let mut buf = Vec::with_capacity(EXPORT_CHUNK_BYTES);
+        buf.push(b'{');
+        Self {
+            buf,
+            tx,
+            separate: false,
+            bytes: 0,
+        }
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.separate {
+            self.buf.push(b',');
+        }
+        // Keys are literals, so they need no escaping.
+        self.buf.push(b'"');
+        self.buf.extend_from_slice(key.as_bytes());
+        self.buf.extend_from_slice(b"\":");
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) {
+        self.key(key);
+        write_value(&mut self.buf, value);
+        self.separate = true;
+    }
+
+    fn open_array(&mut self, key: &str) {
+        self.key(key);
+        self.buf.push(b'[');
+        self.separate = false;
+    }
+
+    async fn element<T: Serialize>(&mut self, value: &T) -> Result<(), ArchiveError> {
+        if self.separate {
+            self.buf.push(b',');
+        }
+        write_value(&mut self.buf, value);
+        self.separate = true;
+        if self.buf.len() >= EXPORT_CHUNK_BYTES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn close_array(&mut self) {
+        self.buf.push(b']');
+        self.separate = true;
+    }
+
+    async fn flush(&mut self) -> Result<(), ArchiveError> {
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(EXPORT_CHUNK_BYTES));
+        self.bytes += chunk.len() as u64;
+        self.tx
+            .send(Ok(Bytes::from(chunk)))
+            .await
+            .map_err(|_| ArchiveError::Disconnected)
+    }
+
+    async fn finish(&mut self) {
+        self.buf.push(b'}');
+        let _ = self.flush().await;
+    }
+}
+
+fn write_value<T: Serialize + ?Sized>(buf: &mut Vec<u8>, value: &T) {
+    if serde_json::to_writer(&mut *buf, value).is_err() {
+        buf.extend_from_slice(b"null");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(mut rx: mpsc::Receiver<Result<Bytes, AppError>>) -> Value {
+        let mut body = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        serde_json::from_slice(&body).expect("archive is valid JSON")
+    }
+
+    #[tokio::test]
+    async fn test_archive_writes_one_json_object() {
+        let (tx, rx) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+        let collected = tokio::spawn(collect(rx));
+
+        let mut archive = Archive::new(tx);
+        archive.field("user", &json!({"username": "jake"}));
+        archive.open_array("empty");
+        archive.close_array();
+        archive.open_array("articles");
+        for slug in ["a", "b"] {
+            archive
+                .element(&json!({ "slug": slug }))
+                .await
+                .unwrap_or_else(|_| panic!("receiver is open"));
+        }
+        archive.close_array();
+        archive.finish().await;
+        drop(archive);
+
+        assert_eq!(
+            collected.await.unwrap(),
+            json!({
+                "user": {"username": "jake"},
+                "empty": [],
+                "articles": [{"slug": "a"}, {"slug": "b"}],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_archive_flushes_large_arrays_in_chunks() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let mut archive = Archive::new(tx);
+        archive.open_array("articles");
+        let body = "x".repeat(1024);
+        for _ in 0..64 {
+            assert!(archive.element(&json!({ "body": body })).await.is_ok());
+        }
+
+        let first = rx.recv().await.unwrap().unwrap();
+        assert!(first.len() >= EXPORT_CHUNK_BYTES);
+        assert!(first.len() < 2 * EXPORT_CHUNK_BYTES);
+    }
+}
@@ -1,23 +1,47 @@
-use tracing::instrument;
-
+use std::collections::BTreeMap;
+
+use authz::Authors;
+use blog_domain::MAX_PAGE_SIZE;
+use bytes::Bytes;
+use domain_events::ArticleCreated;
+use futures_util::{Stream, StreamExt};
+use opentelemetry::KeyValue;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::{Instrument, instrument};
+
+use super::{AuditRecorder, MarkdownRenderer, ViewBuffer, export};
 use crate::{
-    error::{AppError, AppResult},
+    error::{AppError, AppResult, RecordErr},
     jobs::JobQueue,
     models::{
-        ArticleDto, ArticleResponse, ArticlesResponse, CreateArticleInput, ListArticlesQuery,
-        UpdateArticleInput,
+        Actor, AddAuthorInput, ArticleCursor, ArticleDto, ArticlePage, ArticleResponse,
+        ArticleWithAuthor, ArticlesResponse, AuditEntry, CreateArticleInput, ExportFormat,
+        FavoritedLookupInput, FavoritedResponse, ListArticlesQuery, OrgId, TransferArticleInput,
+        TrendingArticlesQuery, UpdateArticleInput, UserArticlesQuery,
     },
-    repository::{ArticleRepository, FavoriteRepository},
+    repository::{ArticleFilter, ArticleOrder, ArticleRepository, FavoriteRepository},
+    storage::Storage,
     telemetry::{
-        ARTICLES_CREATED, ARTICLES_DELETED, ARTICLES_UPDATED, FAVORITES_ADDED, FAVORITES_REMOVED,
+        ARTICLES_CREATED, ARTICLES_DELETED, ARTICLES_TRANSFERRED, ARTICLES_UPDATED,
+        FAVORITES_ADDED, FAVORITES_REMOVED, org_attribute,
     },
 };
 
+const EXPORT_CHUNK_BYTES: usize = 32 * 1024;
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+/// Enough for a few pages of feed cards; more should be split by the client.
+const MAX_FAVORITED_LOOKUP: usize = 100;
+
 #[derive(Clone)]
 pub struct ArticleService {
     article_repo: ArticleRepository,
     favorite_repo: FavoriteRepository,
     job_queue: JobQueue,
+    storage: Storage,
+    views: ViewBuffer,
+    audit: AuditRecorder,
+    markdown: MarkdownRenderer,
 }
 
 impl ArticleService {
@@ -25,245 +49,819 @@ impl ArticleService {
         article_repo: ArticleRepository,
         favorite_repo: FavoriteRepository,
         job_queue: JobQueue,
+        storage: Storage,
+        views: ViewBuffer,
+        audit: AuditRecorder,
+        markdown: MarkdownRenderer,
     ) -> Self {
         Self {
             article_repo,
             favorite_repo,
             job_queue,
+            storage,
+            views,
+            audit,
+            markdown,
         }
     }
 
-    #[instrument(name = "article.create", skip(self, input), fields(author_id))]
+    #[instrument(name = "article.create", skip(self, org, input), fields(author_id))]
     pub async fn create(
         &self,
+        org: OrgId,
         author_id: i32,
         input: CreateArticleInput,
     ) -> AppResult<ArticleResponse> {
-        let slug = self.generate_slug(&input.title);
-        let final_slug = if self.article_repo.exists_by_slug(&slug).await? {
-            format!(
-                "{}-{}",
-                slug,
-                time::OffsetDateTime::now_utc().unix_timestamp()
-            )
-        } else {
-            slug
-        };
-
-        let article = self
-            .article_repo
-            .create(
-                &final_slug,
-                &input.title,
-                input.description.as_deref().unwrap_or(""),
-                &input.body,
-                author_id,
-            )
-            .await?;
-
-        let article_with_author =
-            self.article_repo
-                .find_by_id(article.id)
-                .await?
-                .ok_or(AppError::Internal(
-                    "Failed to fetch created article".to_string(),
-                ))?;
-
-        if let Err(e) = self
-            .job_queue
-            .enqueue_notification(article.id, &article.title)
-            .await
-        {
-            tracing::warn!(article_id = article.id, error = %e, "Failed to enqueue notification");
+        async move {
+            let (title, body) = input.title_and_body()?;
+            let slug = self.generate_slug(title);
+            let final_slug = if self.article_repo.exists_by_slug(org, &slug).await? {
+                format!(
+                    "{}-{}",
+                    slug,
+                    time::OffsetDateTime::now_utc().unix_timestamp()
+                )
+            } else {
+                slug
+            };
+
+            let article = self
+                .article_repo
+                .create(org, &final_slug,
+                    title,
+                    input.description.as_deref().unwrap_or(""),
+                    body,
+                    author_id,
+                )
+                .await?;
+
+            let article_with_author =
+                self.article_repo
+                    .find_by_id(org, article.id)
+                    .await?
+                    .ok_or(AppError::Internal(
+                        "Failed to fetch created article".to_string(),
+                    ))?;
+
+            if let Err(e) = self
+                .job_queue
+                .enqueue_notification(ArticleCreated {
+                    article_id: article.id,
+                    org_id: Some(org.0),
+                    slug: article.slug.clone(),
+                    title: article.title.clone(),
+                    author_id,
+                })
+                .await
+            {
+                tracing::warn!(article_id = article.id, error = %e, "Failed to enqueue notification");
+            }
+            self.enqueue_enrichment(org, article.id).await;
+
+            self.audit
+                .record(
+                    AuditEntry::new(Actor::User(author_id), "article.create", "article", article.id)
+                        .changes(Value::Null, article_with_author.audit_fields()),
+                )
+                .await;
+
+            ARTICLES_CREATED.add(1, &[org_attribute(org)]);
+
+            tracing::info!(article_id = article.id, slug = %article.slug, "Article created");
+
+            Ok(ArticleResponse {
+                article: self.present(article_with_author, false),
+            })
         }
-
-        ARTICLES_CREATED.add(1, &[]);
-
-        tracing::info!(article_id = article.id, slug = %article.slug, "Article created");
-
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(article_with_author, false),
-        })
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.get", skip(self))]
-    pub async fn get(&self, slug: &str, user_id: Option<i32>) -> AppResult<ArticleResponse> {
-        let article = self
+    /// Who wrote the article at `slug`, for the authorization policy's
+    /// `owner` and `author` rules.
+    pub async fn authors(&self, org: OrgId, slug: &str) -> AppResult<Option<Authors>> {
+        Ok(self
             .article_repo
-            .find_by_slug(slug)
+            .find_authors(org, slug)
             .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+            .map(|(owner, co_authors)| Authors { owner, co_authors }))
+    }
 
-        let favorited = if let Some(uid) = user_id {
-            self.favorite_repo.exists(uid, article.id).await?
-        } else {
-            false
-        };
+    #[instrument(name = "article.get", skip(self, org))]
+    pub async fn get(
+        &self,
+        org: OrgId,
+        slug: &str,
+        user_id: Option<i32>,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let mut article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(article, favorited),
-        })
+            article.views_count += self.views.record(article.id);
+
+            let favorited = if let Some(uid) = user_id {
+                self.favorite_repo.exists(org, uid, article.id).await?
+            } else {
+                false
+            };
+
+            Ok(ArticleResponse {
+                article: self.present(article, favorited),
+            })
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.list", skip(self))]
+    #[instrument(name = "article.list", skip(self, org))]
     pub async fn list(
         &self,
+        org: OrgId,
         query: ListArticlesQuery,
         user_id: Option<i32>,
+        rank_by_favorites: bool,
     ) -> AppResult<ArticlesResponse> {
-        let articles = self
-            .article_repo
-            .list(query.limit, query.offset, query.author.as_deref())
-            .await?;
+        async move {
+            let filter = ArticleFilter {
+                author: query.author.as_deref(),
+            };
+            let order = if rank_by_favorites {
+                ArticleOrder::MostFavorited
+            } else {
+                ArticleOrder::Newest
+            };
+            let articles = self
+                .article_repo
+                .list(org, filter, order, query.limit, query.offset)
+                .await?;
+
+            let total = self.article_repo.count(org, filter).await?;
+
+            self.present_page(org, articles, total, user_id).await
+        }
+        .await
+        .record_err()
+    }
 
-        let total = self.article_repo.count(query.author.as_deref()).await?;
+    /// Keyset pages for v2: newest first, with no `COUNT(*)` and a cost that
+    /// doesn't grow with depth. The `NewFeed` ranking is offset-only, since
+    /// favorite counts move between pages.
+    #[instrument(name = "article.list_page", skip(self, org))]
+    pub async fn list_page(
+        &self,
+        org: OrgId,
+        limit: i64,
+        after: Option<ArticleCursor>,
+        author: Option<&str>,
+        user_id: Option<i32>,
+    ) -> AppResult<ArticlePage> {
+        async move {
+            let limit = limit.clamp(1, MAX_PAGE_SIZE);
+            // One extra tells whether there's another page.
+            let mut articles = self
+                .article_repo
+                .list_after(org, limit + 1, after, author)
+                .await?;
+            let next = if articles.len() as i64 > limit {
+                articles.truncate(limit as usize);
+                articles.last().map(ArticleCursor::after)
+            } else {
+                None
+            };
+
+            Ok(ArticlePage {
+                articles: self.present_all(org, articles, user_id).await?,
+                next,
+            })
+        }
+        .await
+        .record_err()
+    }
 
-        let article_ids: Vec<i32> = articles.iter().map(|a| a.id).collect();
+    #[instrument(name = "article.trending", skip(self, org))]
+    pub async fn trending(
+        &self,
+        org: OrgId,
+        query: TrendingArticlesQuery,
+        user_id: Option<i32>,
+    ) -> AppResult<ArticlesResponse> {
+        async move {
+            let articles = self
+                .article_repo
+                .trending(org, query.limit, query.offset)
+                .await?;
+            let total = self.article_repo.count_trending(org).await?;
+
+            self.present_page(org, articles, total, user_id).await
+        }
+        .await
+        .record_err()
+    }
 
-        let favorited_ids = if let Some(uid) = user_id {
-            self.favorite_repo
-                .is_favorited_batch(uid, &article_ids)
-                .await?
-        } else {
-            vec![]
-        };
+    /// The caller's own articles, co-authored ones included, newest first.
+    #[instrument(name = "article.list_by_user", skip(self, org))]
+    pub async fn list_by_user(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        query: UserArticlesQuery,
+    ) -> AppResult<ArticlesResponse> {
+        async move {
+            let articles = self
+                .article_repo
+                .list_by_author(org, user_id, query.limit(), query.offset())
+                .await?;
+            let total = self.article_repo.count_by_author(org, user_id).await?;
+
+            self.present_page(org, articles, total, Some(user_id)).await
+        }
+        .await
+        .record_err()
+    }
 
-        let articles_dto: Vec<ArticleDto> = articles
-            .into_iter()
-            .map(|a| {
-                let favorited = favorited_ids.contains(&a.id);
-                ArticleDto::from_article_with_author(a, favorited)
+    /// The articles the caller has favorited, most recently favorited first.
+    #[instrument(name = "article.list_favorites", skip(self, org))]
+    pub async fn list_favorites(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        query: UserArticlesQuery,
+    ) -> AppResult<ArticlesResponse> {
+        async move {
+            let articles = self
+                .article_repo
+                .list_favorited_by(org, user_id, query.limit(), query.offset())
+                .await?;
+            let total = self.favorite_repo.count_by_user(org, user_id).await?;
+
+            Ok(ArticlesResponse {
+                articles: articles
+                    .into_iter()
+                    .map(|a| self.present(a, true))
+                    .collect(),
+                total,
             })
-            .collect();
-
-        Ok(ArticlesResponse {
-            articles: articles_dto,
-            total,
-        })
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.update", skip(self, input))]
+    /// `authors` are the ones the authorization policy allowed the change
+    /// for, as are the other methods' taking them: if they've changed since,
+    /// the request answers 409 instead of acting on a stale decision.
+    #[instrument(name = "article.update", skip(self, org, authors, input))]
     pub async fn update(
         &self,
+        org: OrgId,
         slug: &str,
+        authors: &Authors,
         user_id: i32,
         input: UpdateArticleInput,
     ) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
-
-        if article.author_id != user_id {
-            return Err(AppError::Forbidden);
-        }
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        let new_slug = input.title.as_ref().map(|t| self.generate_slug(t));
+            let new_slug = input.title.as_ref().map(|t| self.generate_slug(t));
 
-        self.article_repo
-            .update(
-                article.id,
-                new_slug.as_deref(),
-                input.title.as_deref(),
-                input.description.as_deref(),
-                input.body.as_deref(),
-            )
-            .await?;
-
-        let updated_article =
             self.article_repo
-                .find_by_id(article.id)
+                .update(
+                    org,
+                    article.id,
+                    authors,
+                    new_slug.as_deref(),
+                    input.title.as_deref(),
+                    input.description.as_deref(),
+                    input.body.as_deref(),
+                )
                 .await?
-                .ok_or(AppError::Internal(
-                    "Failed to fetch updated article".to_string(),
-                ))?;
+                .ok_or_else(authors_changed)?;
+
+            let updated_article =
+                self.article_repo
+                    .find_by_id(org, article.id)
+                    .await?
+                    .ok_or(AppError::Internal(
+                        "Failed to fetch updated article".to_string(),
+                    ))?;
+
+            if input.title.is_some() || input.body.is_some() {
+                self.enqueue_enrichment(org, article.id).await;
+            }
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "article.update",
+                        "article",
+                        article.id,
+                    )
+                    .changes(article.audit_fields(), updated_article.audit_fields()),
+                )
+                .await;
+
+            let favorited = self.favorite_repo.exists(org, user_id, article.id).await?;
+
+            ARTICLES_UPDATED.add(1, &[org_attribute(org)]);
+
+            tracing::info!(article_id = article.id, "Article updated");
+
+            Ok(ArticleResponse {
+                article: self.present(updated_article, favorited),
+            })
+        }
+        .await
+        .record_err()
+    }
 
-        let favorited = self.favorite_repo.exists(user_id, article.id).await?;
+    #[instrument(name = "article.delete", skip(self, org, authors))]
+    pub async fn delete(
+        &self,
+        org: OrgId,
+        slug: &str,
+        authors: &Authors,
+        user_id: i32,
+    ) -> AppResult<()> {
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
 
-        ARTICLES_UPDATED.add(1, &[]);
+            if !self.article_repo.delete(org, article.id, authors).await? {
+                return Err(authors_changed());
+            }
 
-        tracing::info!(article_id = article.id, "Article updated");
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "article.delete",
+                        "article",
+                        article.id,
+                    )
+                    .changes(article.audit_fields(), Value::Null),
+                )
+                .await;
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, favorited),
-        })
+            ARTICLES_DELETED.add(1, &[org_attribute(org)]);
+
+            tracing::info!(article_id = article.id, "Article deleted");
+
+            Ok(())
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.delete", skip(self))]
-    pub async fn delete(&self, slug: &str, user_id: i32) -> AppResult<()> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+    #[instrument(name = "article.add_author", skip(self, org, authors, input), fields(author_id = input.user_id))]
+    pub async fn add_author(
+        &self,
+        org: OrgId,
+        slug: &str,
+        authors: &Authors,
+        user_id: i32,
+        input: AddAuthorInput,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let article = self.find(org, slug).await?;
+
+            if !article.is_author(input.user_id) {
+                if !self
+                    .article_repo
+                    .add_author(org, article.id, authors, input.user_id)
+                    .await?
+                    .ok_or_else(authors_changed)?
+                {
+                    return Err(AppError::NotFound("User not found".to_string()));
+                }
+                self.audit
+                    .record(
+                        AuditEntry::new(
+                            Actor::User(user_id),
+                            "article.add_author",
+                            "article",
+                            article.id,
+                        )
+                        .changes(
+                            json!({ "co_author_id": null }),
+                            json!({ "co_author_id": input.user_id }),
+                        ),
+                    )
+                    .await;
+                tracing::info!(
+                    article_id = article.id,
+                    author_id = input.user_id,
+                    "Co-author added"
+                );
+            }
+
+            self.reload(org, article.id, user_id).await
+        }
+        .await
+        .record_err()
+    }
 
-        if article.author_id != user_id {
-            return Err(AppError::Forbidden);
+    #[instrument(name = "article.remove_author", skip(self, org, authors))]
+    pub async fn remove_author(
+        &self,
+        org: OrgId,
+        slug: &str,
+        authors: &Authors,
+        user_id: i32,
+        author_id: i32,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+            if author_id == article.author_id {
+                return Err(AppError::Validation(
+                    "The owner can't be removed; transfer the article first".to_string(),
+                ));
+            }
+            if !self
+                .article_repo
+                .remove_author(org, article.id, authors, author_id)
+                .await?
+                .ok_or_else(authors_changed)?
+            {
+                return Err(AppError::NotFound("Author not found".to_string()));
+            }
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "article.remove_author",
+                        "article",
+                        article.id,
+                    )
+                    .changes(
+                        json!({ "co_author_id": author_id }),
+                        json!({ "co_author_id": null }),
+                    ),
+                )
+                .await;
+
+            tracing::info!(article_id = article.id, author_id, "Co-author removed");
+
+            self.reload(org, article.id, user_id).await
         }
+        .await
+        .record_err()
+    }
 
-        self.article_repo.delete(article.id).await?;
+    #[instrument(name = "article.transfer", skip(self, org, authors, input), fields(new_owner_id = input.user_id))]
+    pub async fn transfer(
+        &self,
+        org: OrgId,
+        slug: &str,
+        authors: &Authors,
+        user_id: i32,
+        input: TransferArticleInput,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let article = self.find(org, slug).await?;
+
+            if input.user_id != article.author_id {
+                if !self
+                    .article_repo
+                    .transfer(org, article.id, authors, input.user_id)
+                    .await?
+                    .ok_or_else(authors_changed)?
+                {
+                    return Err(AppError::NotFound("User not found".to_string()));
+                }
+                self.audit
+                    .record(
+                        AuditEntry::new(
+                            Actor::User(user_id),
+                            "article.transfer",
+                            "article",
+                            article.id,
+                        )
+                        .changes(
+                            json!({ "author_id": article.author_id }),
+                            json!({ "author_id": input.user_id }),
+                        ),
+                    )
+                    .await;
+                ARTICLES_TRANSFERRED.add(1, &[org_attribute(org)]);
+                tracing::info!(
+                    article_id = article.id,
+                    from = user_id,
+                    to = input.user_id,
+                    "Article transferred"
+                );
+            }
+
+            self.reload(org, article.id, user_id).await
+        }
+        .await
+        .record_err()
+    }
 
-        ARTICLES_DELETED.add(1, &[]);
+    #[instrument(name = "article.favorite", skip(self, org))]
+    pub async fn favorite(
+        &self,
+        org: OrgId,
+        slug: &str,
+        user_id: i32,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+            let already_favorited = self.favorite_repo.exists(org, user_id, article.id).await?;
+
+            if !already_favorited {
+                self.favorite_repo.create(org, user_id, article.id).await?;
+                self.article_repo
+                    .increment_favorites(org, article.id)
+                    .await?;
+                self.audit
+                    .record(
+                        AuditEntry::new(
+                            Actor::User(user_id),
+                            "article.favorite",
+                            "article",
+                            article.id,
+                        )
+                        .changes(json!({ "favorited": false }), json!({ "favorited": true })),
+                    )
+                    .await;
+                FAVORITES_ADDED.add(1, &[org_attribute(org)]);
+                tracing::info!(article_id = article.id, user_id, "Article favorited");
+            }
+
+            let updated_article = self
+                .article_repo
+                .find_by_id(org, article.id)
+                .await?
+                .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
 
-        tracing::info!(article_id = article.id, "Article deleted");
+            Ok(ArticleResponse {
+                article: self.present(updated_article, true),
+            })
+        }
+        .await
+        .record_err()
+    }
 
-        Ok(())
+    #[instrument(name = "article.unfavorite", skip(self, org))]
+    pub async fn unfavorite(
+        &self,
+        org: OrgId,
+        slug: &str,
+        user_id: i32,
+    ) -> AppResult<ArticleResponse> {
+        async move {
+            let article = self
+                .article_repo
+                .find_by_slug(org, slug)
+                .await?
+                .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+            let was_favorited = self.favorite_repo.delete(org, user_id, article.id).await?;
+
+            if was_favorited {
+                self.article_repo
+                    .decrement_favorites(org, article.id)
+                    .await?;
+                self.audit
+                    .record(
+                        AuditEntry::new(
+                            Actor::User(user_id),
+                            "article.unfavorite",
+                            "article",
+                            article.id,
+                        )
+                        .changes(json!({ "favorited": true }), json!({ "favorited": false })),
+                    )
+                    .await;
+                FAVORITES_REMOVED.add(1, &[org_attribute(org)]);
+                tracing::info!(article_id = article.id, user_id, "Article unfavorited");
+            }
+
+            let updated_article = self
+                .article_repo
+                .find_by_id(org, article.id)
+                .await?
+                .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
+
+            Ok(ArticleResponse {
+                article: self.present(updated_article, false),
+            })
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "article.favorite", skip(self))]
-    pub async fn favorite(&self, slug: &str, user_id: i32) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+    /// The caller's favorite status for each of `input.article_ids`, so a
+    /// feed can mark its cards without a request per article.
+    #[instrument(
+        name = "article.favorited",
+        skip(self, org, input),
+        fields(articles = input.article_ids.len())
+    )]
+    pub async fn favorited(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        input: FavoritedLookupInput,
+    ) -> AppResult<FavoritedResponse> {
+        async move {
+            if input.article_ids.len() > MAX_FAVORITED_LOOKUP {
+                return Err(AppError::Validation(format!(
+                    "article_ids must have at most {MAX_FAVORITED_LOOKUP} entries"
+                )));
+            }
+
+            let favorited_ids = if input.article_ids.is_empty() {
+                vec![]
+            } else {
+                self.favorite_repo
+                    .is_favorited_batch(org, user_id, &input.article_ids)
+                    .await?
+            };
+
+            Ok(FavoritedResponse {
+                favorited: favorited_map(&input.article_ids, &favorited_ids),
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "article.export", skip(self, org), fields(format = format.as_str()))]
+    pub fn export(
+        &self,
+        org: OrgId,
+        format: ExportFormat,
+    ) -> impl Stream<Item = Result<Bytes, AppError>> + Send + 'static {
+        // The bounded channel is the backpressure point: when the client reads
+        // slowly, `send` waits and the database cursor stops being polled.
+        let (tx, rx) = mpsc::channel::<Result<Bytes, AppError>>(EXPORT_CHANNEL_CAPACITY);
+        let article_repo = self.article_repo.clone();
+        let storage = self.storage.clone();
+
+        tokio::spawn(
+            async move {
+                let mut conn = match article_repo.scope(org).await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Article export failed");
+                        let _ = tx.send(Err(AppError::Database(e))).await;
+                        return;
+                    }
+                };
+                let mut rows = article_repo.stream_all(&mut conn, org);
+                let mut buf = Vec::with_capacity(EXPORT_CHUNK_BYTES);
+                let mut total_rows: u64 = 0;
+                export::write_header(format, &mut buf);
+
+                while let Some(row) = rows.next().await {
+                    match row {
+                        Ok(article) => {
+                            let article = resolve_images(&storage, article);
+                            export::write_row(format, article, &mut buf);
+                            total_rows += 1;
+                        }
+                        Err(e) => {
+                            tracing::error!(error = %e, rows = total_rows, "Article export failed");
+                            let _ = tx.send(Err(AppError::Database(e))).await;
+                            return;
+                        }
+                    }
+
+                    if buf.len() >= EXPORT_CHUNK_BYTES {
+                        let chunk =
+                            std::mem::replace(&mut buf, Vec::with_capacity(EXPORT_CHUNK_BYTES));
+                        if tx.send(Ok(Bytes::from(chunk))).await.is_err() {
+                            tracing::warn!(rows = total_rows, "Client disconnected during export");
+                            return;
+                        }
+                    }
+                }
+
+                drop(rows);
+                if let Err(e) = conn.finish().await {
+                    tracing::warn!(error = %e, "Failed to close export transaction");
+                }
+
+                if !buf.is_empty() {
+                    let _ = tx.send(Ok(Bytes::from(buf))).await;
+                }
+
+                tracing::info!(rows = total_rows, "Article export completed");
+            }
+            .in_current_span(),
+        );
 
-        let already_favorited = self.favorite_repo.exists(user_id, article.id).await?;
+        export::body(
+            rx,
+            [
+                KeyValue::new("export.format", format.as_str()),
+                org_attribute(org),
+            ],
+        )
+    }
 
-        if !already_favorited {
-            self.favorite_repo.create(user_id, article.id).await?;
-            self.article_repo.increment_favorites(article.id).await?;
-            FAVORITES_ADDED.add(1, &[]);
-            tracing::info!(article_id = article.id, user_id, "Article favorited");
+    /// The article is usable without its reading time and summary, so a
+    /// failed enqueue is only logged.
+    async fn enqueue_enrichment(&self, org: OrgId, article_id: i32) {
+        if let Err(e) = self
+            .job_queue
+            .enqueue_article_enrichment(org, article_id)
+            .await
+        {
+            tracing::warn!(article_id, error = %e, "Failed to enqueue article enrichment");
         }
+    }
 
-        let updated_article = self
+    async fn find(&self, org: OrgId, slug: &str) -> AppResult<ArticleWithAuthor> {
+        self.article_repo
+            .find_by_slug(org, slug)
+            .await?
+            .ok_or(AppError::NotFound("Article not found".to_string()))
+    }
+
+    async fn reload(&self, org: OrgId, id: i32, user_id: i32) -> AppResult<ArticleResponse> {
+        let article = self
             .article_repo
-            .find_by_id(article.id)
+            .find_by_id(org, id)
             .await?
             .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
+        let favorited = self.favorite_repo.exists(org, user_id, id).await?;
 
         Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, true),
+            article: self.present(article, favorited),
         })
     }
 
-    #[instrument(name = "article.unfavorite", skip(self))]
-    pub async fn unfavorite(&self, slug: &str, user_id: i32) -> AppResult<ArticleResponse> {
-        let article = self
-            .article_repo
-            .find_by_slug(slug)
-            .await?
-            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+    async fn present_page(
+        &self,
+        org: OrgId,
+        articles: Vec<ArticleWithAuthor>,
+        total: i64,
+        user_id: Option<i32>,
+    ) -> AppResult<ArticlesResponse> {
+        Ok(ArticlesResponse {
+            articles: self.present_all(org, articles, user_id).await?,
+            total,
+        })
+    }
 
-        let was_favorited = self.favorite_repo.delete(user_id, article.id).await?;
+    async fn present_all(
+        &self,
+        org: OrgId,
+        articles: Vec<ArticleWithAuthor>,
+        user_id: Option<i32>,
+    ) -> AppResult<Vec<ArticleDto>> {
+        let article_ids: Vec<i32> = articles.iter().map(|a| a.id).collect();
 
-        if was_favorited {
-            self.article_repo.decrement_favorites(article.id).await?;
-            FAVORITES_REMOVED.add(1, &[]);
-            tracing::info!(article_id = article.id, user_id, "Article unfavorited");
-        }
+        let favorited_ids = if let Some(uid) = user_id {
+            self.favorite_repo
+                .is_favorited_batch(org, uid, &article_ids)
+                .await?
+        } else {
+            vec![]
+        };
 
-        let updated_article = self
-            .article_repo
-            .find_by_id(article.id)
-            .await?
-            .ok_or(AppError::Internal("Failed to fetch article".to_string()))?;
+        Ok(articles
+            .into_iter()
+            .map(|a| {
+                let favorited = favorited_ids.contains(&a.id);
+                self.present(a, favorited)
+            })
+            .collect())
+    }
 
-        Ok(ArticleResponse {
-            article: ArticleDto::from_article_with_author(updated_article, false),
-        })
+    fn present(&self, article: ArticleWithAuthor, favorited: bool) -> ArticleDto {
+        let body_html = self
+            .markdown
+            .body_html(article.id, article.updated_at, &article.body);
+        ArticleDto::from_article_with_author(
+            resolve_images(&self.storage, article),
+            favorited,
+            body_html,
+        )
     }
 
     fn generate_slug(&self, title: &str) -> String {
@@ -271,6 +869,31 @@ impl ArticleService {
     }
 }
 
+pub(super) fn resolve_images(
+    storage: &Storage,
+    mut article: ArticleWithAuthor,
+) -> ArticleWithAuthor {
+    article.cover_image = storage.resolve_image(&article.cover_image);
+    article.author_image = storage.resolve_image(&article.author_image);
+    for author in article.authors.iter_mut() {
+        author.image = storage.resolve_image(&author.image);
+    }
+    article
+}
+
+/// The article's authors aren't the ones the request was authorized for
+/// any more, say after a transfer; retrying asks the policy again.
+pub(super) fn authors_changed() -> AppError {
+    AppError::Conflict("Article authors changed; try again".to_string())
+}
+
+fn favorited_map(requested: &[i32], favorited: &[i32]) -> BTreeMap<i32, bool> {
+    requested
+        .iter()
+        .map(|id| (*id, favorited.contains(id)))
+        .collect()
+}
+
 pub fn generate_slug(title: &str) -> String {
     title
         .to_lowercase()
@@ -287,6 +910,16 @@ pub fn generate_slug(title: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_favorited_map_covers_every_requested_id() {
+        let map = favorited_map(&[3, 1, 2, 3], &[3]);
+
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [(1, false), (2, false), (3, true)]
+        );
+    }
+
     #[test]
     fn test_generate_slug_simple() {
         assert_eq!(generate_slug("Hello World"), "hello-world");
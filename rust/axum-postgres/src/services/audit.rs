@@ -0,0 +1,123 @@
+use futures_util::stream::BoxStream;
+use opentelemetry::{KeyValue, trace::TraceContextExt};
+use tracing::{Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    error::{AppResult, RecordErr},
+    models::{AuditEntry, AuditLogEntry, AuditLogQuery, AuditLogResponse},
+    repository::AuditRepository,
+    telemetry::AUDIT_WRITE_ERRORS,
+};
+
+/// Writes `audit_log` entries for the services' mutating operations, tagged
+/// with the trace they happened in.
+#[derive(Clone)]
+pub struct AuditRecorder {
+    repo: AuditRepository,
+}
+
+impl AuditRecorder {
+    pub fn new(repo: AuditRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Called after the operation has succeeded, so a failed write is logged
+    /// and counted rather than failing a change that has already been made.
+    #[instrument(
+        name = "audit.record",
+        skip(self, entry),
+        fields(audit.action = entry.action, audit.entity_id = %entry.entity_id)
+    )]
+    pub async fn record(&self, entry: AuditEntry) {
+        let trace_id = current_trace_id();
+        if let Err(e) = self.repo.insert(&entry, trace_id.as_deref()).await {
+            AUDIT_WRITE_ERRORS.add(1, &[KeyValue::new("audit.action", entry.action)]);
+            tracing::warn!(
+                action = entry.action,
+                entity_id = %entry.entity_id,
+                error = %e,
+                "Failed to write audit log entry"
+            );
+        }
+    }
+
+    #[instrument(name = "audit.list", skip(self))]
+    pub async fn list(&self, query: AuditLogQuery) -> AppResult<AuditLogResponse> {
+        async move {
+            let entries = self.repo.list(&query).await?;
+            let total = self.repo.count(&query).await?;
+
+            Ok(AuditLogResponse { entries, total })
+        }
+        .await
+        .record_err()
+    }
+
+    /// What `user_id` did, oldest first, for their data export.
+    pub fn stream_by_actor(
+        &self,
+        user_id: i32,
+    ) -> BoxStream<'_, Result<AuditLogEntry, sqlx::Error>> {
+        self.repo.stream_by_actor(user_id)
+    }
+}
+
+fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use opentelemetry::trace::Status;
+    use serde_json::{Value, json};
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::models::Actor;
+    use crate::telemetry::testing::{SpanCapture, assert_child_of, assert_metric_recorded};
+
+    fn unreachable_recorder() -> AuditRecorder {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/db")
+            .expect("lazy pool");
+        AuditRecorder::new(AuditRepository::new(pool))
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_does_not_fail_the_caller() {
+        let spans = SpanCapture::start();
+        let recorder = unreachable_recorder();
+
+        let entry = AuditEntry::new(Actor::User(1), "article.delete", "article", 42)
+            .changes(json!({"title": "Gone"}), Value::Null);
+        recorder.record(entry).await;
+
+        let record = spans.span("audit.record");
+        let insert = spans.span("db.audit_log.insert");
+        assert_child_of(&insert, &record);
+        assert!(matches!(record.status, Status::Unset));
+        assert_metric_recorded(
+            "audit.write.errors",
+            &[KeyValue::new("audit.action", "article.delete")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_list_is_recorded_on_its_span() {
+        let spans = SpanCapture::start();
+        let recorder = unreachable_recorder();
+
+        assert!(recorder.list(AuditLogQuery::default()).await.is_err());
+
+        let list = spans.span("audit.list");
+        assert!(matches!(list.status, Status::Error { .. }));
+    }
+}
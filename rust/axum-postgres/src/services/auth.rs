@@ -1,107 +1,772 @@
-use argon2::{
-    Argon2,
-    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
-};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use blog_domain::normalize_username;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use opentelemetry::KeyValue;
 use serde::{Deserialize, Serialize};
-use time::{Duration, OffsetDateTime};
-use tracing::instrument;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{Span, instrument};
+use two_factor::{TwoFactor, Verified};
+use uuid::Uuid;
 
 use crate::{
-    config::Config,
-    error::{AppError, AppResult},
-    models::{LoginInput, RegisterInput, User, UserWithToken},
-    repository::UserRepository,
-    telemetry::USERS_REGISTERED,
+    config::{Config, RotatingSecret},
+    error::{AppError, AppResult, RecordErr},
+    models::{
+        Actor, ApiTokenDto, ApiTokensResponse, AuditEntry, ClientInfo, CreateApiTokenInput,
+        CreateOrganizationInput, CreatedApiTokenResponse, DEFAULT_ORG_SLUG, LoginInput,
+        LoginOutcome, OrgId, Organization, OrganizationsResponse, RecoveryCodesResponse,
+        RegisterInput, SessionDto, SessionsResponse, TokenScope, TwoFactorChallenge,
+        TwoFactorLoginInput, TwoFactorSetupResponse, User, UserWithToken,
+    },
+    repository::{
+        ApiTokenRepository, OrganizationRepository, SessionRepository, TwoFactorRepository,
+        UserRepository,
+    },
+    services::{AuditRecorder, JwtCache, LoginSignals, PasswordHashing, VerifiedToken},
+    storage::Storage,
+    telemetry::{
+        PASSWORD_REHASHES, SESSIONS_CREATED, SESSIONS_REVOKED, USERS_REGISTERED, org_attribute,
+    },
 };
 
+/// Bearer tokens starting with this are personal access tokens; anything
+/// else is treated as a JWT.
+pub const API_TOKEN_PREFIX: &str = "pat_";
+
+/// `last_used_at` is written at most this often per token.
+const API_TOKEN_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+const API_TOKEN_MAX_EXPIRY_DAYS: i64 = 365;
+
+/// `last_seen_at` is written at most this often per session.
+const SESSION_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// The `aud` of a two-factor challenge. A JWT with an audience the
+/// validator wasn't told to expect fails validation, so
+/// [`AuthService::validate_token`] refuses challenges as access tokens.
+const TWO_FACTOR_AUDIENCE: &str = "login.2fa";
+
+/// Time to type a code after the password.
+const TWO_FACTOR_CHALLENGE_TTL: Duration = Duration::minutes(5);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: i32,
+    /// Tokens issued before tenancy have no `org` and belong to the default
+    /// organization.
+    #[serde(default = "default_org")]
+    pub org: OrgId,
+    /// Names the token's row in `sessions`. Tokens issued before sessions
+    /// were tracked have none and are accepted until they expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
     pub exp: i64,
     pub iat: i64,
 }
 
+fn default_org() -> OrgId {
+    OrgId::DEFAULT
+}
+
+/// Proves the password step of a login with two-factor authentication
+/// enabled; `POST /api/login/2fa` exchanges it and a code for a token.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChallengeClaims {
+    sub: i32,
+    org: OrgId,
+    aud: String,
+    exp: i64,
+    iat: i64,
+}
+
+/// An authenticated user and the tenant every query on their behalf is
+/// scoped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Principal {
+    pub user_id: i32,
+    pub org: OrgId,
+}
+
+/// A valid JWT's principal and the session it was issued for, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JwtSession {
+    pub principal: Principal,
+    pub session_id: Option<i32>,
+}
+
 #[derive(Clone)]
 pub struct AuthService {
     user_repo: UserRepository,
-    jwt_secret: String,
+    token_repo: ApiTokenRepository,
+    org_repo: OrganizationRepository,
+    storage: Storage,
+    audit: AuditRecorder,
+    passwords: PasswordHashing,
+    two_factor: TwoFactor<TwoFactorRepository>,
+    session_repo: SessionRepository,
+    signals: LoginSignals,
+    jwt_secret: RotatingSecret,
+    jwt_cache: JwtCache,
     jwt_expires_in_hours: i64,
+    admin_token: String,
 }
 
 impl AuthService {
-    pub fn new(user_repo: UserRepository, config: &Config) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: UserRepository,
+        token_repo: ApiTokenRepository,
+        org_repo: OrganizationRepository,
+        two_factor_repo: TwoFactorRepository,
+        session_repo: SessionRepository,
+        signals: LoginSignals,
+        storage: Storage,
+        audit: AuditRecorder,
+        jwt_secret: RotatingSecret,
+        config: &Config,
+    ) -> Self {
         Self {
             user_repo,
-            jwt_secret: config.jwt_secret.clone(),
+            token_repo,
+            org_repo,
+            storage,
+            audit,
+            passwords: PasswordHashing::from_config(config),
+            two_factor: TwoFactor::new(
+                two_factor_repo,
+                &config.two_factor_issuer,
+                config.two_factor_lockout(),
+            ),
+            session_repo,
+            signals,
+            jwt_secret,
+            jwt_cache: JwtCache::new(config.jwt_cache_capacity),
             jwt_expires_in_hours: config.jwt_expires_in_hours,
+            admin_token: config.admin_token.clone(),
         }
     }
 
-    #[instrument(name = "auth.register", skip(self, input), fields(email = %input.email))]
-    pub async fn register(&self, input: RegisterInput) -> AppResult<UserWithToken> {
-        if self.user_repo.exists_by_email(&input.email).await? {
-            return Err(AppError::Conflict("Email already registered".to_string()));
+    #[instrument(name = "auth.register", skip(self, input, client), fields(email = %input.email))]
+    pub async fn register(
+        &self,
+        input: RegisterInput,
+        client: &ClientInfo,
+    ) -> AppResult<UserWithToken> {
+        async move {
+            let username = normalize_username(&input.username)?;
+            if self.user_repo.exists_by_email(&input.email).await? {
+                return Err(AppError::Conflict("Email already registered".to_string()));
+            }
+
+            let org = self
+                .org_repo
+                .find_by_slug(input.org.as_deref().unwrap_or(DEFAULT_ORG_SLUG))
+                .await?
+                .ok_or(AppError::NotFound("Organization not found".to_string()))?;
+
+            let password_hash = self.passwords.hash(&input.password).await?;
+
+            // No lookup first: the unique constraint also catches two
+            // registrations racing for the same name.
+            let user = self
+                .user_repo
+                .create(org.id, &input.email, &password_hash, &username, &input.name)
+                .await
+                .map_err(|e| match &e {
+                    sqlx::Error::Database(db)
+                        if db.constraint() == Some("users_org_id_username_key") =>
+                    {
+                        AppError::Conflict("Username already taken".to_string())
+                    }
+                    _ => AppError::from(e),
+                })?;
+
+            let token = self.issue_token(user.id, user.org_id, client).await?;
+            let principal = Principal {
+                user_id: user.id,
+                org: user.org_id,
+            };
+            self.signals.login_succeeded(principal, client, true).await;
+
+            self.audit
+                .record(
+                    AuditEntry::new(Actor::User(user.id), "user.register", "user", user.id)
+                        .changes(
+                            Value::Null,
+                            json!({
+                                "email": user.email,
+                                "username": user.username,
+                                "name": user.name,
+                                "org_id": user.org_id,
+                            }),
+                        ),
+                )
+                .await;
+
+            USERS_REGISTERED.add(1, &[org_attribute(user.org_id)]);
+
+            tracing::info!(user_id = user.id, org_id = user.org_id.0, "User registered");
+
+            Ok(UserWithToken::from_user(&self.present(user), token))
         }
+        .await
+        .record_err()
+    }
 
-        let password_hash = self.hash_password(&input.password)?;
+    /// With two-factor authentication enabled a correct password only gets
+    /// a challenge; [`AuthService::login_two_factor`] finishes the login.
+    #[instrument(name = "auth.login", skip(self, input, client), fields(email = %input.email))]
+    pub async fn login(&self, input: LoginInput, client: &ClientInfo) -> AppResult<LoginOutcome> {
+        async move {
+            let user = self
+                .user_repo
+                .find_by_email(&input.email)
+                .await?
+                .ok_or(AppError::InvalidCredentials)?;
 
-        let user = self
-            .user_repo
-            .create(&input.email, &password_hash, &input.name)
-            .await?;
+            let principal = Principal {
+                user_id: user.id,
+                org: user.org_id,
+            };
+            let verified = self
+                .passwords
+                .verify(&input.password, &user.password_hash)
+                .await;
+            if let Err(AppError::InvalidCredentials) = verified {
+                self.signals.login_failed(principal, client).await;
+            }
+            if verified? {
+                self.rehash_password(&user, &input.password).await;
+            }
 
-        let token = self.generate_token(user.id)?;
+            if self.two_factor.is_enabled(user.id).await? {
+                tracing::info!(user_id = user.id, "Two-factor code required");
+                return Ok(LoginOutcome::TwoFactorRequired(
+                    self.generate_challenge(user.id, user.org_id)?,
+                ));
+            }
 
-        USERS_REGISTERED.add(1, &[]);
+            let token = self.issue_token(user.id, user.org_id, client).await?;
+            self.signals.login_succeeded(principal, client, false).await;
 
-        tracing::info!(user_id = user.id, "User registered");
+            tracing::info!(user_id = user.id, "User logged in");
 
-        Ok(UserWithToken::from_user(&user, token))
+            Ok(LoginOutcome::Authenticated(UserWithToken::from_user(
+                &self.present(user),
+                token,
+            )))
+        }
+        .await
+        .record_err()
     }
 
-    #[instrument(name = "auth.login", skip(self, input), fields(email = %input.email))]
-    pub async fn login(&self, input: LoginInput) -> AppResult<UserWithToken> {
-        let user = self
-            .user_repo
-            .find_by_email(&input.email)
-            .await?
-            .ok_or(AppError::InvalidCredentials)?;
+    /// A wrong code answers 401 like a wrong password, and counts towards
+    /// the lockout.
+    #[instrument(
+        name = "auth.login_two_factor",
+        skip(self, input, client),
+        fields(user_id)
+    )]
+    pub async fn login_two_factor(
+        &self,
+        input: TwoFactorLoginInput,
+        client: &ClientInfo,
+    ) -> AppResult<UserWithToken> {
+        async move {
+            let principal = self.validate_challenge(&input.challenge_token)?;
+            Span::current().record("user_id", principal.user_id);
+            let user = self
+                .user_repo
+                .find_by_id(principal.org, principal.user_id)
+                .await?
+                .ok_or(AppError::Unauthorized)?;
 
-        self.verify_password(&input.password, &user.password_hash)?;
+            let verified = match self.two_factor.verify(user.id, &input.code).await {
+                Ok(verified) => verified,
+                Err(two_factor::Error::InvalidCode) => {
+                    self.signals.login_failed(principal, client).await;
+                    return Err(AppError::InvalidCredentials);
+                }
+                Err(two_factor::Error::NotEnrolled) => return Err(AppError::InvalidCredentials),
+                Err(e) => return Err(AppError::from(e)),
+            };
+            if let Verified::RecoveryCode { remaining } = verified {
+                self.audit
+                    .record(
+                        AuditEntry::new(
+                            Actor::User(user.id),
+                            "user.recovery_code.use",
+                            "user",
+                            user.id,
+                        )
+                        .changes(Value::Null, json!({ "remaining": remaining })),
+                    )
+                    .await;
+                tracing::warn!(user_id = user.id, remaining, "Recovery code used");
+            }
 
-        let token = self.generate_token(user.id)?;
+            let token = self.issue_token(user.id, user.org_id, client).await?;
+            self.signals.login_succeeded(principal, client, false).await;
 
-        tracing::info!(user_id = user.id, "User logged in");
+            tracing::info!(user_id = user.id, "User logged in");
 
-        Ok(UserWithToken::from_user(&user, token))
+            Ok(UserWithToken::from_user(&self.present(user), token))
+        }
+        .await
+        .record_err()
+    }
+
+    /// Starts over with a new secret until a code confirms one.
+    #[instrument(name = "auth.setup_two_factor", skip(self))]
+    pub async fn setup_two_factor(
+        &self,
+        principal: Principal,
+    ) -> AppResult<TwoFactorSetupResponse> {
+        async move {
+            let user = self
+                .user_repo
+                .find_by_id(principal.org, principal.user_id)
+                .await?
+                .ok_or(AppError::NotFound("User not found".to_string()))?;
+            let setup = self.two_factor.setup(user.id, &user.email).await?;
+
+            Ok(TwoFactorSetupResponse {
+                secret: setup.secret,
+                otpauth_uri: setup.otpauth_uri,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.enable_two_factor", skip(self, code))]
+    pub async fn enable_two_factor(
+        &self,
+        principal: Principal,
+        code: &str,
+    ) -> AppResult<RecoveryCodesResponse> {
+        async move {
+            let user_id = principal.user_id;
+            let recovery_codes = self.two_factor.confirm(user_id, code).await?;
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "user.two_factor.enable",
+                        "user",
+                        user_id,
+                    )
+                    .changes(
+                        json!({ "two_factor": false }),
+                        json!({ "two_factor": true }),
+                    ),
+                )
+                .await;
+
+            tracing::info!(user_id, "Two-factor authentication enabled");
+
+            Ok(RecoveryCodesResponse { recovery_codes })
+        }
+        .await
+        .record_err()
     }
 
     #[instrument(name = "auth.get_user", skip(self))]
-    pub async fn get_user(&self, user_id: i32) -> AppResult<User> {
-        self.user_repo
-            .find_by_id(user_id)
-            .await?
-            .ok_or(AppError::NotFound("User not found".to_string()))
-    }
-
-    #[instrument(name = "auth.validate_token", skip(self, token))]
-    pub fn validate_token(&self, token: &str) -> AppResult<i32> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )?;
+    pub async fn get_user(&self, principal: Principal) -> AppResult<User> {
+        async move {
+            self.user_repo
+                .find_by_id(principal.org, principal.user_id)
+                .await?
+                .map(|user| self.present(user))
+                .ok_or(AppError::NotFound("User not found".to_string()))
+        }
+        .await
+        .record_err()
+    }
+
+    /// Looks `username` up in `org` only: handles are unique per tenant.
+    #[instrument(name = "auth.get_profile", skip(self))]
+    pub async fn get_profile(&self, org: OrgId, username: &str) -> AppResult<User> {
+        async move {
+            self.user_repo
+                .find_by_username(org, &username.to_ascii_lowercase())
+                .await?
+                .map(|user| self.present(user))
+                .ok_or(AppError::NotFound("Profile not found".to_string()))
+        }
+        .await
+        .record_err()
+    }
+
+    /// Tokens signed with the secret that was just rotated out stay valid
+    /// until they expire or the secret rotates again. A token whose session
+    /// has been revoked is refused. A token seen before skips the signature
+    /// check, but not the session lookup.
+    #[instrument(name = "auth.validate_token", skip(self, token), fields(auth.session.id))]
+    pub async fn validate_token(&self, token: &str) -> AppResult<JwtSession> {
+        async move {
+            let VerifiedToken { principal, jti, .. } = self.verify_jwt(token)?;
+            let Some(jti) = jti else {
+                return Ok(JwtSession {
+                    principal,
+                    session_id: None,
+                });
+            };
+
+            let session = self
+                .session_repo
+                .find_active(jti)
+                .await?
+                .filter(|session| session.user_id == principal.user_id)
+                .ok_or(AppError::Unauthorized)?;
+            Span::current().record("auth.session.id", session.id);
+
+            if OffsetDateTime::now_utc() - session.last_seen_at >= SESSION_TOUCH_INTERVAL
+                && let Err(e) = self.session_repo.touch(session.id).await
+            {
+                tracing::warn!(session_id = session.id, error = %e, "Failed to record session use");
+            }
+
+            Ok(JwtSession {
+                principal,
+                session_id: Some(session.id),
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.validate_api_token", skip(self, token), fields(auth.token.id))]
+    pub async fn validate_api_token(&self, token: &str, scope: TokenScope) -> AppResult<Principal> {
+        async move {
+            let active = self
+                .token_repo
+                .find_active(&hash_api_token(token))
+                .await?
+                .ok_or(AppError::Unauthorized)?;
+            let api_token = active.token;
+            Span::current().record("auth.token.id", api_token.id);
+
+            if !api_token.has_scope(scope) {
+                return Err(AppError::Forbidden);
+            }
+
+            let stale = api_token.last_used_at.is_none_or(|used| {
+                OffsetDateTime::now_utc() - used >= API_TOKEN_TOUCH_INTERVAL
+            });
+            if stale && let Err(e) = self.token_repo.touch(api_token.id).await {
+                tracing::warn!(token_id = api_token.id, error = %e, "Failed to record API token use");
+            }
+
+            Ok(Principal {
+                user_id: api_token.user_id,
+                org: active.org_id,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    /// The user's active sessions, most recently seen first; `current` is
+    /// the caller's.
+    #[instrument(name = "auth.list_sessions", skip(self, principal), fields(user_id = principal.user_id))]
+    pub async fn list_sessions(
+        &self,
+        principal: Principal,
+        current: Option<i32>,
+    ) -> AppResult<SessionsResponse> {
+        async move {
+            let sessions = self.session_repo.list_for_user(principal.user_id).await?;
+
+            Ok(SessionsResponse {
+                sessions: sessions
+                    .into_iter()
+                    .map(|session| SessionDto::new(session, current))
+                    .collect(),
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    /// Signs a device out: its token is refused from the next request on.
+    #[instrument(name = "auth.revoke_session", skip(self, principal), fields(user_id = principal.user_id))]
+    pub async fn revoke_session(&self, principal: Principal, session_id: i32) -> AppResult<()> {
+        async move {
+            let user_id = principal.user_id;
+            if !self.session_repo.revoke(session_id, user_id).await? {
+                return Err(AppError::NotFound("Session not found".to_string()));
+            }
+            self.jwt_cache.forget_user(user_id);
+            SESSIONS_REVOKED.add(
+                1,
+                &[
+                    org_attribute(principal.org),
+                    KeyValue::new("reason", "revoked"),
+                ],
+            );
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "session.revoke",
+                        "session",
+                        session_id,
+                    )
+                    .changes(json!({ "revoked": false }), json!({ "revoked": true })),
+                )
+                .await;
+
+            tracing::info!(user_id, session_id, "Session revoked");
+
+            Ok(())
+        }
+        .await
+        .record_err()
+    }
+
+    /// Revokes the session of the token logging out. Tokens without one
+    /// are left to expire.
+    #[instrument(name = "auth.logout", skip(self, principal), fields(user_id = principal.user_id))]
+    pub async fn logout(&self, principal: Principal, session_id: Option<i32>) -> AppResult<()> {
+        async move {
+            let Some(session_id) = session_id else {
+                return Ok(());
+            };
+            if self
+                .session_repo
+                .revoke(session_id, principal.user_id)
+                .await?
+            {
+                self.jwt_cache.forget_user(principal.user_id);
+                SESSIONS_REVOKED.add(
+                    1,
+                    &[
+                        org_attribute(principal.org),
+                        KeyValue::new("reason", "logout"),
+                    ],
+                );
+                tracing::info!(user_id = principal.user_id, session_id, "Logged out");
+            }
+
+            Ok(())
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.create_api_token", skip(self, input), fields(name = %input.name))]
+    pub async fn create_api_token(
+        &self,
+        user_id: i32,
+        input: CreateApiTokenInput,
+    ) -> AppResult<CreatedApiTokenResponse> {
+        async move {
+            let name = input.name.trim();
+            if name.is_empty() || name.len() > 100 {
+                return Err(AppError::Validation(
+                    "Token name must be 1 to 100 characters".to_string(),
+                ));
+            }
+            if input.scopes.is_empty() {
+                return Err(AppError::Validation(
+                    "A token needs at least one scope".to_string(),
+                ));
+            }
+            let expires_at = match input.expires_in_days {
+                Some(days) if (1..=API_TOKEN_MAX_EXPIRY_DAYS).contains(&days) => {
+                    Some(OffsetDateTime::now_utc() + Duration::days(days))
+                }
+                Some(_) => {
+                    return Err(AppError::Validation(format!(
+                        "expires_in_days must be between 1 and {API_TOKEN_MAX_EXPIRY_DAYS}"
+                    )));
+                }
+                None => None,
+            };
+
+            let mut scopes: Vec<&str> = input.scopes.iter().map(TokenScope::as_str).collect();
+            scopes.sort_unstable();
+            scopes.dedup();
+
+            let token = generate_api_token();
+            let api_token = self
+                .token_repo
+                .create(
+                    user_id,
+                    name,
+                    &hash_api_token(&token),
+                    &token[..API_TOKEN_PREFIX.len() + 8],
+                    &scopes,
+                    expires_at,
+                )
+                .await?;
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "api_token.create",
+                        "api_token",
+                        api_token.id,
+                    )
+                    .changes(
+                        Value::Null,
+                        json!({
+                            "name": api_token.name,
+                            "scopes": api_token.scopes,
+                            "expires_at": api_token.expires_at.and_then(|at| at.format(&Rfc3339).ok()),
+                        }),
+                    ),
+                )
+                .await;
+
+            tracing::info!(user_id, token_id = api_token.id, scopes = ?scopes, "API token created");
+
+            Ok(CreatedApiTokenResponse {
+                token,
+                api_token: ApiTokenDto::from(api_token),
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.list_api_tokens", skip(self))]
+    pub async fn list_api_tokens(&self, user_id: i32) -> AppResult<ApiTokensResponse> {
+        async move {
+            let tokens = self.token_repo.list_for_user(user_id).await?;
+
+            Ok(ApiTokensResponse {
+                api_tokens: tokens.into_iter().map(ApiTokenDto::from).collect(),
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.revoke_api_token", skip(self))]
+    pub async fn revoke_api_token(&self, user_id: i32, token_id: i32) -> AppResult<()> {
+        async move {
+            if !self.token_repo.revoke(token_id, user_id).await? {
+                return Err(AppError::NotFound("API token not found".to_string()));
+            }
 
-        Ok(token_data.claims.sub)
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::User(user_id),
+                        "api_token.revoke",
+                        "api_token",
+                        token_id,
+                    )
+                    .changes(json!({ "revoked": false }), json!({ "revoked": true })),
+                )
+                .await;
+
+            tracing::info!(user_id, token_id, "API token revoked");
+
+            Ok(())
+        }
+        .await
+        .record_err()
     }
 
-    fn generate_token(&self, user_id: i32) -> AppResult<String> {
+    #[instrument(name = "auth.create_organization", skip(self, input), fields(slug = %input.slug))]
+    pub async fn create_organization(
+        &self,
+        input: CreateOrganizationInput,
+    ) -> AppResult<Organization> {
+        async move {
+            if !is_valid_org_slug(&input.slug) {
+                return Err(AppError::Validation(
+                    "Organization slug must be 1 to 50 lowercase letters, digits and hyphens"
+                        .to_string(),
+                ));
+            }
+            let name = input.name.trim();
+            if name.is_empty() {
+                return Err(AppError::Validation(
+                    "Organization name is required".to_string(),
+                ));
+            }
+            if self.org_repo.find_by_slug(&input.slug).await?.is_some() {
+                return Err(AppError::Conflict(
+                    "Organization slug already taken".to_string(),
+                ));
+            }
+
+            let org = self.org_repo.create(&input.slug, name).await?;
+
+            self.audit
+                .record(
+                    AuditEntry::new(
+                        Actor::Admin,
+                        "organization.create",
+                        "organization",
+                        org.id.0,
+                    )
+                    .changes(Value::Null, json!({ "slug": org.slug, "name": org.name })),
+                )
+                .await;
+
+            tracing::info!(org_id = org.id.0, slug = %org.slug, "Organization created");
+
+            Ok(org)
+        }
+        .await
+        .record_err()
+    }
+
+    #[instrument(name = "auth.list_organizations", skip(self))]
+    pub async fn list_organizations(&self) -> AppResult<OrganizationsResponse> {
+        async move {
+            Ok(OrganizationsResponse {
+                organizations: self.org_repo.list().await?,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    /// The admin API is disabled entirely when `ADMIN_TOKEN` is unset.
+    pub fn validate_admin_token(&self, token: &str) -> AppResult<()> {
+        if self.admin_token.is_empty() {
+            return Err(AppError::Forbidden);
+        }
+        let expected = Sha256::digest(self.admin_token.as_bytes());
+        let actual = Sha256::digest(token.as_bytes());
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(AppError::Unauthorized)
+        }
+    }
+
+    fn present(&self, mut user: User) -> User {
+        user.image = self.storage.resolve_image(&user.image);
+        user
+    }
+
+    /// A JWT and the session it names.
+    async fn issue_token(
+        &self,
+        user_id: i32,
+        org: OrgId,
+        client: &ClientInfo,
+    ) -> AppResult<String> {
         let now = OffsetDateTime::now_utc();
         let exp = now + Duration::hours(self.jwt_expires_in_hours);
+        let jti = Uuid::new_v4();
 
         let claims = Claims {
             sub: user_id,
+            org,
+            jti: Some(jti),
             exp: exp.unix_timestamp(),
             iat: now.unix_timestamp(),
         };
@@ -109,41 +774,177 @@ impl AuthService {
         let token = encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
+            &EncodingKey::from_secret(self.jwt_secret.current().as_bytes()),
         )?;
 
+        let session_id = self
+            .session_repo
+            .create(user_id, jti, &client.user_agent, exp)
+            .await?;
+        SESSIONS_CREATED.add(1, &[org_attribute(org)]);
+        tracing::debug!(user_id, session_id, "Session started");
+
         Ok(token)
     }
 
-    fn hash_password(&self, password: &str) -> AppResult<String> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
+    fn verify_jwt(&self, token: &str) -> AppResult<VerifiedToken> {
+        // Read before decoding: a rotation during it leaves the entry stale.
+        let generation = self.jwt_secret.generation();
+        if let Some(verified) = self.jwt_cache.get(token, generation) {
+            return Ok(verified);
+        }
+        let claims = self.decode_claims(token)?;
+        let verified = VerifiedToken {
+            principal: Principal {
+                user_id: claims.sub,
+                org: claims.org,
+            },
+            jti: claims.jti,
+            expires_at: claims.exp,
+        };
+        self.jwt_cache.insert(token, generation, verified);
+        Ok(verified)
+    }
 
-        argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map(|hash| hash.to_string())
-            .map_err(|e| AppError::Internal(format!("Password hashing failed: {}", e)))
+    fn decode_claims(&self, token: &str) -> AppResult<Claims> {
+        let mut error = None;
+        for secret in self.jwt_secret.candidates() {
+            match decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &Validation::default(),
+            ) {
+                Ok(token_data) => return Ok(token_data.claims),
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error.map_or(AppError::Unauthorized, AppError::from))
+    }
+
+    fn generate_challenge(&self, user_id: i32, org: OrgId) -> AppResult<TwoFactorChallenge> {
+        let now = OffsetDateTime::now_utc();
+        let claims = ChallengeClaims {
+            sub: user_id,
+            org,
+            aud: TWO_FACTOR_AUDIENCE.to_string(),
+            exp: (now + TWO_FACTOR_CHALLENGE_TTL).unix_timestamp(),
+            iat: now.unix_timestamp(),
+        };
+
+        let challenge_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.jwt_secret.current().as_bytes()),
+        )?;
+
+        Ok(TwoFactorChallenge {
+            challenge_token,
+            expires_in: TWO_FACTOR_CHALLENGE_TTL.whole_seconds(),
+        })
+    }
+
+    fn validate_challenge(&self, token: &str) -> AppResult<Principal> {
+        let mut validation = Validation::default();
+        validation.set_audience(&[TWO_FACTOR_AUDIENCE]);
+        let mut error = None;
+        for secret in self.jwt_secret.candidates() {
+            match decode::<ChallengeClaims>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &validation,
+            ) {
+                Ok(token_data) => {
+                    return Ok(Principal {
+                        user_id: token_data.claims.sub,
+                        org: token_data.claims.org,
+                    });
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error.map_or(AppError::Unauthorized, AppError::from))
     }
 
-    fn verify_password(&self, password: &str, hash: &str) -> AppResult<()> {
-        let parsed_hash = PasswordHash::new(hash)
-            .map_err(|e| AppError::Internal(format!("Invalid hash: {}", e)))?;
+    /// Replaces a hash made with other parameters than the configured ones,
+    /// while the password is at hand. The login has succeeded either way,
+    /// so a failure is logged and the next login tries again.
+    async fn rehash_password(&self, user: &User, password: &str) {
+        let result = async {
+            let hash = self.passwords.hash(password).await?;
+            self.user_repo
+                .update_password_hash(user.org_id, user.id, &hash)
+                .await?;
+            Ok::<_, AppError>(())
+        }
+        .await;
 
-        Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .map_err(|_| AppError::InvalidCredentials)
+        let outcome = match &result {
+            Ok(()) => "upgraded",
+            Err(e) => {
+                tracing::warn!(user_id = user.id, error = %e, "Failed to rehash password");
+                "failed"
+            }
+        };
+        PASSWORD_REHASHES.add(
+            1,
+            &[
+                org_attribute(user.org_id),
+                KeyValue::new("outcome", outcome),
+            ],
+        );
     }
 }
 
+fn is_valid_org_slug(slug: &str) -> bool {
+    (1..=50).contains(&slug.len())
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn generate_api_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{API_TOKEN_PREFIX}{}", hex::encode(bytes))
+}
+
+/// Tokens are 256 random bits, so a fast unsalted hash is enough to make a
+/// leaked `api_tokens` table useless while keeping lookups indexable.
+fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    };
+
     use super::*;
 
+    #[test]
+    fn test_generated_api_tokens() {
+        let token = generate_api_token();
+        assert!(token.starts_with(API_TOKEN_PREFIX));
+        assert_eq!(token.len(), API_TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_api_token());
+
+        let hash = hash_api_token(&token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_api_token(&token));
+        assert_ne!(hash, hash_api_token(&generate_api_token()));
+    }
+
     fn create_test_claims(user_id: i32, hours_offset: i64) -> Claims {
         let now = OffsetDateTime::now_utc();
         let exp = now + Duration::hours(hours_offset);
         Claims {
             sub: user_id,
+            org: OrgId(7),
+            jti: Some(Uuid::new_v4()),
             exp: exp.unix_timestamp(),
             iat: now.unix_timestamp(),
         }
@@ -220,6 +1021,46 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_challenge_is_not_an_access_token() {
+        let secret = "test-secret-key-for-jwt";
+        let now = OffsetDateTime::now_utc();
+        let claims = ChallengeClaims {
+            sub: 42,
+            org: OrgId(3),
+            aud: TWO_FACTOR_AUDIENCE.to_string(),
+            exp: (now + TWO_FACTOR_CHALLENGE_TTL).unix_timestamp(),
+            iat: now.unix_timestamp(),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .expect("encoding should succeed");
+        let key = DecodingKey::from_secret(secret.as_bytes());
+
+        let as_access = decode::<Claims>(&token, &key, &Validation::default());
+        assert!(matches!(
+            as_access.unwrap_err().kind(),
+            jsonwebtoken::errors::ErrorKind::InvalidAudience
+        ));
+
+        let mut validation = Validation::default();
+        validation.set_audience(&[TWO_FACTOR_AUDIENCE]);
+        let challenge = decode::<ChallengeClaims>(&token, &key, &validation).unwrap();
+        assert_eq!(challenge.claims.sub, 42);
+
+        // And an access token isn't a challenge.
+        let access = encode(
+            &Header::default(),
+            &create_test_claims(42, 24),
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+        assert!(decode::<ChallengeClaims>(&access, &key, &validation).is_err());
+    }
+
     #[test]
     fn test_password_hash_and_verify() {
         let password = "secure_password_123";
@@ -262,7 +1103,26 @@ mod tests {
         let parsed: Claims = serde_json::from_str(&json).expect("deserialization should succeed");
 
         assert_eq!(claims.sub, parsed.sub);
+        assert_eq!(claims.org, parsed.org);
         assert_eq!(claims.exp, parsed.exp);
         assert_eq!(claims.iat, parsed.iat);
     }
+
+    #[test]
+    fn test_claims_without_org_belong_to_the_default_org() {
+        let parsed: Claims = serde_json::from_str(r#"{"sub": 42, "exp": 0, "iat": 0}"#)
+            .expect("deserialization should succeed");
+
+        assert_eq!(parsed.org, OrgId::DEFAULT);
+    }
+
+    #[test]
+    fn test_org_slug_validation() {
+        assert!(is_valid_org_slug("acme"));
+        assert!(is_valid_org_slug("acme-2"));
+        assert!(!is_valid_org_slug(""));
+        assert!(!is_valid_org_slug("Acme"));
+        assert!(!is_valid_org_slug("acme_corp"));
+        assert!(!is_valid_org_slug(&"a".repeat(51)));
+    }
 }
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use time::{
+    Date, Duration, OffsetDateTime, format_description::BorrowedFormatItem,
+    macros::format_description,
+};
+use tracing::instrument;
+
+use crate::{
+    clients::{CreateReportRequest, EconomicReport, ReportClient},
+    error::{AppError, AppResult},
+    models::OrgId,
+    repository::ArticleRepository,
+};
+
+const DATE_FORMAT: &[BorrowedFormatItem<'static>] = format_description!("[year]-[month]-[day]");
+const DEFAULT_WINDOW: Duration = Duration::days(3 * 365);
+
+#[derive(Debug, Deserialize)]
+pub struct EconomicContextQuery {
+    pub indicators: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EconomicContextResponse {
+    pub slug: String,
+    pub title: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub published_at: OffsetDateTime,
+    pub report: EconomicReport,
+}
+
+/// Asks ai-report-generator for a report on the economy around the time an
+/// article was published.
+#[derive(Clone)]
+pub struct EconomicContextService {
+    article_repo: ArticleRepository,
+    client: Option<ReportClient>,
+    indicators: Vec<String>,
+    data_end: Date,
+}
+
+impl EconomicContextService {
+    pub fn new(
+        article_repo: ArticleRepository,
+        client: Option<ReportClient>,
+        indicators: &str,
+        data_end: Date,
+    ) -> Self {
+        Self {
+            article_repo,
+            client,
+            indicators: split_indicators(indicators),
+            data_end,
+        }
+    }
+
+    #[instrument(name = "economic_context.get", skip(self, org, query))]
+    pub async fn get(
+        &self,
+        org: OrgId,
+        slug: &str,
+        query: EconomicContextQuery,
+    ) -> AppResult<EconomicContextResponse> {
+        let client = self.client.as_ref().ok_or_else(|| {
+            AppError::ServiceUnavailable("Report service is not configured".to_string())
+        })?;
+
+        let article = self
+            .article_repo
+            .find_by_slug(org, slug)
+            .await?
+            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+        let request = self.build_request(article.created_at.date(), query)?;
+        let report = client.create_report(&request).await?;
+
+        tracing::info!(report_id = %report.id, "Economic context generated");
+
+        Ok(EconomicContextResponse {
+            slug: article.slug,
+            title: article.title,
+            published_at: article.created_at,
+            report,
+        })
+    }
+
+    /// Defaults to the three years before publication, clamped to the last
+    /// date the report service has data for.
+    fn build_request(
+        &self,
+        published: Date,
+        query: EconomicContextQuery,
+    ) -> AppResult<CreateReportRequest> {
+        let end = match query.end_date.as_deref() {
+            Some(value) => parse_date("end_date", value)?,
+            None => published.min(self.data_end),
+        };
+        let start = match query.start_date.as_deref() {
+            Some(value) => parse_date("start_date", value)?,
+            None => end - DEFAULT_WINDOW,
+        };
+        if start >= end {
+            return Err(AppError::Validation(
+                "start_date must be before end_date".to_string(),
+            ));
+        }
+
+        let indicators = query
+            .indicators
+            .as_deref()
+            .map(split_indicators)
+            .unwrap_or_else(|| self.indicators.clone());
+        if indicators.is_empty() {
+            return Err(AppError::Validation(
+                "indicators must not be empty".to_string(),
+            ));
+        }
+
+        Ok(CreateReportRequest {
+            indicators,
+            start_date: format_date(start),
+            end_date: format_date(end),
+        })
+    }
+}
+
+pub fn parse_date(field: &str, value: &str) -> AppResult<Date> {
+    Date::parse(value, DATE_FORMAT)
+        .map_err(|_| AppError::Validation(format!("invalid {}, use YYYY-MM-DD", field)))
+}
+
+fn format_date(date: Date) -> String {
+    date.format(DATE_FORMAT).unwrap_or_default()
+}
+
+fn split_indicators(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|code| code.trim().to_uppercase())
+        .filter(|code| !code.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::postgres::PgPoolOptions;
+    use time::macros::date;
+
+    use super::*;
+    use crate::database::{TenancyMode, TenantDb};
+
+    fn create_test_service() -> EconomicContextService {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/test")
+            .expect("lazy pool");
+        EconomicContextService::new(
+            ArticleRepository::new(TenantDb::new(pool, TenancyMode::App)),
+            None,
+            "unrate, cpiaucsl,",
+            date!(2023 - 12 - 31),
+        )
+    }
+
+    fn query(start: Option<&str>, end: Option<&str>) -> EconomicContextQuery {
+        EconomicContextQuery {
+            indicators: None,
+            start_date: start.map(String::from),
+            end_date: end.map(String::from),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_window_is_clamped_to_data_end() {
+        let service = create_test_service();
+        let request = service
+            .build_request(date!(2026 - 03 - 01), query(None, None))
+            .unwrap();
+
+        assert_eq!(request.indicators, vec!["UNRATE", "CPIAUCSL"]);
+        assert_eq!(request.end_date, "2023-12-31");
+        assert_eq!(request.start_date, "2020-12-31");
+    }
+
+    #[tokio::test]
+    async fn test_explicit_dates_are_validated() {
+        let service = create_test_service();
+
+        let request = service
+            .build_request(
+                date!(2026 - 03 - 01),
+                query(Some("2010-01-01"), Some("2012-01-01")),
+            )
+            .unwrap();
+        assert_eq!(request.start_date, "2010-01-01");
+
+        assert!(
+            service
+                .build_request(
+                    date!(2026 - 03 - 01),
+                    query(Some("2012-01-01"), Some("2010-01-01"))
+                )
+                .is_err()
+        );
+        assert!(
+            service
+                .build_request(date!(2026 - 03 - 01), query(None, Some("31/12/2020")))
+                .is_err()
+        );
+    }
+}
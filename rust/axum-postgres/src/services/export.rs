@@ -0,0 +1,180 @@
+use bytes::Bytes;
+use futures_util::{Stream, stream};
+use opentelemetry::KeyValue;
+use tokio::sync::mpsc;
+
+use super::markdown;
+use crate::{
+    error::AppError,
+    models::{ArticleDto, ArticleWithAuthor, ExportFormat},
+    telemetry::HTTP_RESPONSE_STREAMED_BYTES,
+};
+
+pub const CSV_HEADER: &str =
+    "id,slug,title,description,body,favorites_count,author_id,author_name,created_at,updated_at\n";
+
+pub fn write_header(format: ExportFormat, buf: &mut Vec<u8>) {
+    if format == ExportFormat::Csv {
+        buf.extend_from_slice(CSV_HEADER.as_bytes());
+    }
+}
+
+pub fn write_row(format: ExportFormat, article: ArticleWithAuthor, buf: &mut Vec<u8>) {
+    match format {
+        ExportFormat::Ndjson => {
+            let body_html = markdown::render(&article.body);
+            let dto = ArticleDto::from_article_with_author(article, false, body_html);
+            if serde_json::to_writer(&mut *buf, &dto).is_ok() {
+                buf.push(b'\n');
+            }
+        }
+        ExportFormat::Csv => {
+            let fields = [
+                article.id.to_string(),
+                article.slug,
+                article.title,
+                article.description,
+                article.body,
+                article.favorites_count.to_string(),
+                article.author_id.to_string(),
+                article.author_name,
+                format_timestamp(article.created_at),
+                format_timestamp(article.updated_at),
+            ];
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    buf.push(b',');
+                }
+                write_csv_field(field, buf);
+            }
+            buf.push(b'\n');
+        }
+    }
+}
+
+/// The response body for an export writing into `rx`. Each chunk is counted
+/// in `http.response.streamed_bytes` when hyper takes it, so an export the
+/// client abandons counts only what was handed over, not what was queued.
+pub fn body(
+    rx: mpsc::Receiver<Result<Bytes, AppError>>,
+    attrs: [KeyValue; 2],
+) -> impl Stream<Item = Result<Bytes, AppError>> + Send + 'static {
+    stream::unfold((rx, attrs), |(mut rx, attrs)| async move {
+        let item = rx.recv().await?;
+        if let Ok(chunk) = &item {
+            HTTP_RESPONSE_STREAMED_BYTES.add(chunk.len() as u64, &attrs);
+        }
+        Some((item, (rx, attrs)))
+    })
+}
+
+fn format_timestamp(ts: time::OffsetDateTime) -> String {
+    ts.format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default()
+}
+
+fn write_csv_field(field: &str, buf: &mut Vec<u8>) {
+    if field.contains([',', '"', '\n', '\r']) {
+        buf.push(b'"');
+        buf.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+        buf.push(b'"');
+    } else {
+        buf.extend_from_slice(field.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::OrgId,
+        telemetry::{org_attribute, testing::metric_sum},
+    };
+    use futures_util::StreamExt;
+    use sqlx::types::Json;
+    use time::macros::datetime;
+
+    fn create_test_article() -> ArticleWithAuthor {
+        ArticleWithAuthor {
+            id: 7,
+            slug: "hello-world".to_string(),
+            title: "Hello, \"World\"".to_string(),
+            description: "plain".to_string(),
+            body: "line one\nline two".to_string(),
+            author_id: 3,
+            favorites_count: 2,
+            views_count: 0,
+            cover_image: String::new(),
+            created_at: datetime!(2024-01-15 10:30:00 UTC),
+            updated_at: datetime!(2024-01-16 15:45:00 UTC),
+            reading_time_minutes: None,
+            summary: None,
+            author_username: "jane".to_string(),
+            author_name: "Jane".to_string(),
+            author_email: "jane@example.com".to_string(),
+            author_bio: String::new(),
+            author_image: String::new(),
+            authors: Json(vec![]),
+        }
+    }
+
+    #[test]
+    fn test_csv_field_plain() {
+        let mut buf = Vec::new();
+        write_csv_field("simple", &mut buf);
+        assert_eq!(buf, b"simple");
+    }
+
+    #[test]
+    fn test_csv_field_quotes_and_escapes() {
+        let mut buf = Vec::new();
+        write_csv_field("say \"hi\", ok", &mut buf);
+        assert_eq!(String::from_utf8(buf).unwrap(), "\"say \"\"hi\"\", ok\"");
+    }
+
+    #[test]
+    fn test_csv_row() {
+        let mut buf = Vec::new();
+        write_header(ExportFormat::Csv, &mut buf);
+        write_row(ExportFormat::Csv, create_test_article(), &mut buf);
+        let out = String::from_utf8(buf).unwrap();
+
+        assert!(out.starts_with(CSV_HEADER));
+        assert!(out.contains(
+            "7,hello-world,\"Hello, \"\"World\"\"\",plain,\"line one\nline two\",2,3,Jane,"
+        ));
+        assert!(out.ends_with("2024-01-16T15:45:00Z\n"));
+    }
+
+    #[test]
+    fn test_ndjson_row() {
+        let mut buf = Vec::new();
+        write_header(ExportFormat::Ndjson, &mut buf);
+        write_row(ExportFormat::Ndjson, create_test_article(), &mut buf);
+        let out = String::from_utf8(buf).unwrap();
+
+        assert_eq!(out.matches('\n').count(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
+        assert_eq!(parsed["slug"], "hello-world");
+        assert_eq!(parsed["author"]["name"], "Jane");
+    }
+
+    #[tokio::test]
+    async fn test_body_counts_only_chunks_taken() {
+        let attrs = [
+            KeyValue::new("export.format", "csv"),
+            org_attribute(OrgId(9_874)),
+        ];
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(Ok(Bytes::from_static(b"first"))).await.unwrap();
+        tx.send(Ok(Bytes::from_static(b"never read")))
+            .await
+            .unwrap();
+
+        let mut body = Box::pin(body(rx, attrs.clone()));
+        assert_eq!(body.next().await.unwrap().unwrap(), "first");
+        drop(body);
+
+        assert_eq!(metric_sum("http.response.streamed_bytes", &attrs), 5);
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::models::GeoPoint;
+
+/// IP networks and their coordinates, read from CSV files with `network`,
+/// `latitude` and `longitude` columns, like MaxMind's GeoLite2 City blocks
+/// files. Fields can't contain commas. Empty until `GEOIP_CSV_PATH` names
+/// a file, and then every lookup misses.
+#[derive(Clone, Default)]
+pub struct GeoIp {
+    ranges: Arc<Vec<GeoRange>>,
+}
+
+/// IPv4 networks are kept as IPv4-mapped IPv6 ones, so both families
+/// search the same list.
+struct GeoRange {
+    start: u128,
+    end: u128,
+    location: GeoPoint,
+}
+
+impl GeoIp {
+    /// `paths` is comma-separated, for GeoLite2's separate IPv4 and IPv6
+    /// files.
+    pub fn load(paths: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+            read_csv(BufReader::new(file), &mut ranges).map_err(|e| format!("{path}: {e}"))?;
+        }
+        ranges.sort_unstable_by_key(|range| range.start);
+        Ok(Self {
+            ranges: Arc::new(ranges),
+        })
+    }
+
+    /// How many networks have coordinates.
+    pub fn networks(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoPoint> {
+        let ip = to_bits(ip);
+        let index = self
+            .ranges
+            .partition_point(|range| range.start <= ip)
+            .checked_sub(1)?;
+        let range = &self.ranges[index];
+        (ip <= range.end).then_some(range.location)
+    }
+}
+
+/// Rows without coordinates, which GeoLite2 has for networks it only
+/// knows the country of, are skipped.
+fn read_csv(reader: impl BufRead, ranges: &mut Vec<GeoRange>) -> Result<(), String> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .ok_or("the file is empty")?
+        .map_err(|e| e.to_string())?;
+    let column = |name: &str| {
+        header
+            .split(',')
+            .position(|column| column.trim().trim_matches('"') == name)
+            .ok_or_else(|| format!("no `{name}` column"))
+    };
+    let (network, latitude, longitude) = (
+        column("network")?,
+        column("latitude")?,
+        column("longitude")?,
+    );
+
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|f| f.trim().trim_matches('"'))
+            .collect();
+        let coordinate = |column: usize| fields.get(column).and_then(|f| f.parse::<f64>().ok());
+        let (Some(latitude), Some(longitude)) = (coordinate(latitude), coordinate(longitude))
+        else {
+            continue;
+        };
+        let (start, end) = fields
+            .get(network)
+            .and_then(|network| parse_network(network))
+            .ok_or_else(|| format!("line {}: invalid network", index + 2))?;
+        ranges.push(GeoRange {
+            start,
+            end,
+            location: GeoPoint {
+                latitude,
+                longitude,
+            },
+        });
+    }
+    Ok(())
+}
+
+/// The first and last address of a CIDR network.
+fn parse_network(network: &str) -> Option<(u128, u128)> {
+    let (address, prefix) = network.split_once('/')?;
+    let address: IpAddr = address.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok()?;
+    let prefix = match address {
+        IpAddr::V4(_) if prefix <= 32 => prefix + 96,
+        IpAddr::V6(_) if prefix <= 128 => prefix,
+        _ => return None,
+    };
+    let host = u128::MAX.checked_shr(prefix).unwrap_or(0);
+    let bits = to_bits(address);
+    Some((bits & !host, bits | host))
+}
+
+fn to_bits(ip: IpAddr) -> u128 {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const BLOCKS: &str = "\
+network,geoname_id,registered_country_geoname_id,represented_country_geoname_id,is_anonymous_proxy,is_satellite_provider,postal_code,latitude,longitude,accuracy_radius
+1.0.0.0/24,2077456,2077456,,0,0,,-33.4940,143.2104,1000
+81.2.69.160/27,2643743,2635167,,0,0,EC4M,51.5142,-0.0931,10
+2001:db8::/32,2950159,2921044,,0,0,10178,52.5200,13.4050,20
+9.9.9.0/24,6252001,6252001,,0,0,,,,
+";
+
+    fn geoip() -> GeoIp {
+        let mut ranges = Vec::new();
+        read_csv(Cursor::new(BLOCKS), &mut ranges).unwrap();
+        ranges.sort_unstable_by_key(|range| range.start);
+        GeoIp {
+            ranges: Arc::new(ranges),
+        }
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_lookup_finds_the_network() {
+        let geoip = geoip();
+        assert_eq!(geoip.networks(), 3);
+
+        let london = geoip.lookup(ip("81.2.69.191")).unwrap();
+        assert_eq!((london.latitude, london.longitude), (51.5142, -0.0931));
+        assert_eq!(geoip.lookup(ip("81.2.69.160")), Some(london));
+        assert_eq!(geoip.lookup(ip("::ffff:81.2.69.170")), Some(london));
+        assert_eq!(geoip.lookup(ip("81.2.69.192")), None);
+        assert_eq!(geoip.lookup(ip("81.2.69.159")), None);
+
+        assert_eq!(geoip.lookup(ip("2001:db8:1::7")).unwrap().latitude, 52.52);
+        assert_eq!(geoip.lookup(ip("2001:db9::1")), None);
+    }
+
+    #[test]
+    fn test_rows_without_coordinates_are_skipped() {
+        assert_eq!(geoip().lookup(ip("9.9.9.9")), None);
+    }
+
+    #[test]
+    fn test_empty_geoip_misses() {
+        assert_eq!(GeoIp::default().lookup(ip("1.0.0.1")), None);
+        assert_eq!(GeoIp::load("").unwrap().networks(), 0);
+    }
+
+    #[test]
+    fn test_read_csv_rejects_bad_files() {
+        let mut ranges = Vec::new();
+        assert_eq!(
+            read_csv(Cursor::new("network,latitude\n"), &mut ranges),
+            Err("no `longitude` column".to_string())
+        );
+        assert_eq!(
+            read_csv(
+                Cursor::new("network,latitude,longitude\n1.0.0.0/24,1,2\n1.0.0.0/33,1,2\n"),
+                &mut ranges
+            ),
+            Err("line 3: invalid network".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let (start, end) = parse_network("10.0.0.0/8").unwrap();
+        assert_eq!(start, to_bits(ip("10.0.0.0")));
+        assert_eq!(end, to_bits(ip("10.255.255.255")));
+        assert_eq!(parse_network("::/0"), Some((0, u128::MAX)));
+        assert_eq!(parse_network("10.0.0.1/32").map(|(s, e)| e - s), Some(0));
+        assert_eq!(parse_network("10.0.0.0"), None);
+    }
+}
@@ -0,0 +1,319 @@
+use authz::Authors;
+use bytes::Bytes;
+use opentelemetry::KeyValue;
+use serde::Serialize;
+use serde_json::json;
+use tracing::instrument;
+
+use super::{AuditRecorder, article::authors_changed};
+use crate::{
+    error::{AppError, AppResult},
+    models::{Actor, AuditEntry, OrgId},
+    repository::{ArticleRepository, UserRepository},
+    storage::Storage,
+    telemetry::{IMAGES_UPLOADED, org_attribute},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageKind {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "image/png" => Some(ImageKind::Png),
+            "image/jpeg" | "image/jpg" => Some(ImageKind::Jpeg),
+            "image/gif" => Some(ImageKind::Gif),
+            "image/webp" => Some(ImageKind::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "png" => Some(ImageKind::Png),
+            "jpg" | "jpeg" => Some(ImageKind::Jpeg),
+            "gif" => Some(ImageKind::Gif),
+            "webp" => Some(ImageKind::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageKind::Png)
+        } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageKind::Jpeg)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(ImageKind::Gif)
+        } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(ImageKind::Webp)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageKind::Png => "image/png",
+            ImageKind::Jpeg => "image/jpeg",
+            ImageKind::Gif => "image/gif",
+            ImageKind::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageKind::Png => "png",
+            ImageKind::Jpeg => "jpg",
+            ImageKind::Gif => "gif",
+            ImageKind::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageDto {
+    pub key: String,
+    pub url: String,
+    pub content_type: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageResponse {
+    pub image: ImageDto,
+}
+
+#[derive(Clone)]
+pub struct ImageService {
+    storage: Storage,
+    user_repo: UserRepository,
+    article_repo: ArticleRepository,
+    audit: AuditRecorder,
+    max_bytes: usize,
+}
+
+impl ImageService {
+    pub fn new(
+        storage: Storage,
+        user_repo: UserRepository,
+        article_repo: ArticleRepository,
+        audit: AuditRecorder,
+        max_bytes: usize,
+    ) -> Self {
+        Self {
+            storage,
+            user_repo,
+            article_repo,
+            audit,
+            max_bytes,
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    #[instrument(name = "image.upload_avatar", skip(self, org, data), fields(size = data.len()))]
+    pub async fn upload_avatar(
+        &self,
+        org: OrgId,
+        user_id: i32,
+        content_type: Option<&str>,
+        data: Bytes,
+    ) -> AppResult<ImageResponse> {
+        let kind = self.validate(content_type, &data)?;
+        let key = object_key("avatars", user_id, kind);
+        let user = self
+            .user_repo
+            .find_by_id(org, user_id)
+            .await?
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+        self.storage
+            .put(&key, kind.content_type(), data.clone())
+            .await?;
+        self.user_repo.update_image(org, user_id, &key).await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(Actor::User(user_id), "user.upload_image", "user", user_id)
+                    .changes(json!({ "image": user.image }), json!({ "image": key })),
+            )
+            .await;
+
+        IMAGES_UPLOADED.add(
+            1,
+            &[KeyValue::new("image.kind", "avatar"), org_attribute(org)],
+        );
+
+        tracing::info!(user_id, key = %key, "Avatar uploaded");
+
+        Ok(self.response(key, kind, data.len()))
+    }
+
+    /// `authors` are the ones the authorization policy allowed the upload
+    /// for; the article keeps its old cover if they've changed since.
+    #[instrument(name = "image.upload_cover", skip(self, org, authors, data), fields(size = data.len()))]
+    pub async fn upload_cover(
+        &self,
+        org: OrgId,
+        slug: &str,
+        authors: &Authors,
+        user_id: i32,
+        content_type: Option<&str>,
+        data: Bytes,
+    ) -> AppResult<ImageResponse> {
+        let article = self
+            .article_repo
+            .find_by_slug(org, slug)
+            .await?
+            .ok_or(AppError::NotFound("Article not found".to_string()))?;
+
+        let kind = self.validate(content_type, &data)?;
+        let key = object_key("covers", article.id, kind);
+
+        self.storage
+            .put(&key, kind.content_type(), data.clone())
+            .await?;
+        if !self
+            .article_repo
+            .update_cover_image(org, article.id, authors, &key)
+            .await?
+        {
+            return Err(authors_changed());
+        }
+
+        self.audit
+            .record(
+                AuditEntry::new(
+                    Actor::User(user_id),
+                    "article.upload_cover",
+                    "article",
+                    article.id,
+                )
+                .changes(
+                    json!({ "cover_image": article.cover_image }),
+                    json!({ "cover_image": key }),
+                ),
+            )
+            .await;
+
+        IMAGES_UPLOADED.add(
+            1,
+            &[KeyValue::new("image.kind", "cover"), org_attribute(org)],
+        );
+
+        tracing::info!(article_id = article.id, key = %key, "Cover image uploaded");
+
+        Ok(self.response(key, kind, data.len()))
+    }
+
+    fn validate(&self, content_type: Option<&str>, data: &[u8]) -> AppResult<ImageKind> {
+        if data.is_empty() {
+            return Err(AppError::Validation("Image file is empty".to_string()));
+        }
+        if data.len() > self.max_bytes {
+            return Err(too_large(self.max_bytes));
+        }
+        validate_image(content_type, data)
+    }
+
+    fn response(&self, key: String, kind: ImageKind, size: usize) -> ImageResponse {
+        ImageResponse {
+            image: ImageDto {
+                url: self.storage.signed_url(&key),
+                key,
+                content_type: kind.content_type().to_string(),
+                size,
+            },
+        }
+    }
+}
+
+pub fn too_large(max_bytes: usize) -> AppError {
+    AppError::PayloadTooLarge(format!("Image exceeds maximum size of {} bytes", max_bytes))
+}
+
+/// The declared content type must be one we accept and must agree with the
+/// file's magic bytes, so a renamed script can't be stored as an image.
+fn validate_image(content_type: Option<&str>, data: &[u8]) -> AppResult<ImageKind> {
+    let declared = content_type
+        .and_then(ImageKind::from_content_type)
+        .ok_or_else(|| {
+            AppError::UnsupportedMediaType(
+                "Image must be one of image/png, image/jpeg, image/gif, image/webp".to_string(),
+            )
+        })?;
+
+    match ImageKind::sniff(data) {
+        Some(actual) if actual == declared => Ok(actual),
+        _ => Err(AppError::UnsupportedMediaType(
+            "Image content does not match its content type".to_string(),
+        )),
+    }
+}
+
+fn object_key(prefix: &str, owner_id: i32, kind: ImageKind) -> String {
+    format!(
+        "{}/{}/{}.{}",
+        prefix,
+        owner_id,
+        uuid::Uuid::new_v4(),
+        kind.extension()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+
+    #[test]
+    fn test_sniff_known_formats() {
+        assert_eq!(ImageKind::sniff(PNG), Some(ImageKind::Png));
+        assert_eq!(ImageKind::sniff(JPEG), Some(ImageKind::Jpeg));
+        assert_eq!(ImageKind::sniff(b"GIF89a...."), Some(ImageKind::Gif));
+        assert_eq!(
+            ImageKind::sniff(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(ImageKind::Webp)
+        );
+        assert_eq!(ImageKind::sniff(b"<html></html>"), None);
+    }
+
+    #[test]
+    fn test_validate_image_accepts_matching_type() {
+        let kind = validate_image(Some("image/png"), PNG).expect("png should validate");
+        assert_eq!(kind, ImageKind::Png);
+    }
+
+    #[test]
+    fn test_validate_image_rejects_mismatched_type() {
+        let result = validate_image(Some("image/png"), JPEG);
+        assert!(matches!(result, Err(AppError::UnsupportedMediaType(_))));
+    }
+
+    #[test]
+    fn test_validate_image_rejects_unsupported_type() {
+        let result = validate_image(Some("image/svg+xml"), b"<svg/>");
+        assert!(matches!(result, Err(AppError::UnsupportedMediaType(_))));
+
+        let result = validate_image(None, PNG);
+        assert!(matches!(result, Err(AppError::UnsupportedMediaType(_))));
+    }
+
+    #[test]
+    fn test_object_key_layout() {
+        let key = object_key("avatars", 7, ImageKind::Webp);
+        assert!(key.starts_with("avatars/7/"));
+        assert!(key.ends_with(".webp"));
+        assert!(crate::storage::validate_key(&key).is_ok());
+    }
+}
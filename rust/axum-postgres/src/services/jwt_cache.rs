@@ -0,0 +1,163 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use opentelemetry::KeyValue;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::auth::Principal;
+use super::lru::LruCache;
+use crate::telemetry::AUTH_JWT_CACHE_LOOKUPS;
+
+/// Each shard has its own lock, so concurrent requests only contend when
+/// their tokens hash to the same one.
+const SHARDS: usize = 16;
+
+/// What verifying a JWT's signature established. The session it names is
+/// still looked up on every request, so revoking it elsewhere takes effect
+/// at once; only the decode and HMAC are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedToken {
+    pub principal: Principal,
+    pub jti: Option<Uuid>,
+    /// The token's `exp`; a hit is refused from then on. Decoding allows
+    /// some leeway past it, the cache doesn't.
+    pub expires_at: i64,
+}
+
+type Shard = Mutex<LruCache<[u8; 32], Entry>>;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    token: VerifiedToken,
+    /// [`RotatingSecret::generation`](crate::config::RotatingSecret::generation)
+    /// when the signature was checked. A rotation retires a signing secret,
+    /// so entries from before it are misses.
+    generation: u64,
+}
+
+/// Verified JWTs by the SHA-256 of the token, so the cache holds no
+/// bearer credentials. A capacity of 0 disables it.
+#[derive(Clone)]
+pub struct JwtCache {
+    shards: Arc<[Shard; SHARDS]>,
+    enabled: bool,
+}
+
+impl JwtCache {
+    pub fn new(capacity: usize) -> Self {
+        let per_shard = capacity.div_ceil(SHARDS);
+        Self {
+            shards: Arc::new(std::array::from_fn(|_| {
+                Mutex::new(LruCache::new(per_shard, None))
+            })),
+            enabled: capacity > 0,
+        }
+    }
+
+    pub fn get(&self, token: &str, generation: u64) -> Option<VerifiedToken> {
+        if !self.enabled {
+            return None;
+        }
+        let key = key(token);
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let hit = self
+            .shard(&key)
+            .get(&key)
+            .filter(|entry| entry.generation == generation && entry.token.expires_at > now)
+            .map(|entry| entry.token);
+        let outcome = if hit.is_some() { "hit" } else { "miss" };
+        AUTH_JWT_CACHE_LOOKUPS.add(1, &[KeyValue::new("cache", outcome)]);
+        hit
+    }
+
+    pub fn insert(&self, token: &str, generation: u64, verified: VerifiedToken) {
+        if !self.enabled {
+            return;
+        }
+        let key = key(token);
+        self.shard(&key).insert(
+            key,
+            Entry {
+                token: verified,
+                generation,
+            },
+        );
+    }
+
+    /// Drops every token of `user_id`, for when their sessions are revoked
+    /// here: the next request with one verifies it again and is refused by
+    /// the session lookup, rather than the entry lingering until evicted.
+    pub fn forget_user(&self, user_id: i32) {
+        for shard in self.shards.iter() {
+            shard
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|entry| entry.token.principal.user_id != user_id);
+        }
+    }
+
+    fn shard(&self, key: &[u8; 32]) -> MutexGuard<'_, LruCache<[u8; 32], Entry>> {
+        self.shards[usize::from(key[0]) % SHARDS]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn key(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OrgId;
+
+    fn verified(user_id: i32, expires_in: i64) -> VerifiedToken {
+        VerifiedToken {
+            principal: Principal {
+                user_id,
+                org: OrgId(3),
+            },
+            jti: Some(Uuid::new_v4()),
+            expires_at: OffsetDateTime::now_utc().unix_timestamp() + expires_in,
+        }
+    }
+
+    #[test]
+    fn test_hits_until_expiry_or_rotation() {
+        let cache = JwtCache::new(64);
+        let token = verified(1, 3600);
+        cache.insert("a.b.c", 0, token);
+
+        assert_eq!(cache.get("a.b.c", 0), Some(token));
+        assert_eq!(cache.get("a.b.d", 0), None);
+        assert_eq!(cache.get("a.b.c", 1), None);
+
+        cache.insert("e.f.g", 0, verified(1, 0));
+        assert_eq!(cache.get("e.f.g", 0), None);
+    }
+
+    #[test]
+    fn test_forget_user_drops_only_their_tokens() {
+        let cache = JwtCache::new(64);
+        let alice = verified(1, 3600);
+        let bob = verified(2, 3600);
+        for (i, token) in [alice, alice, bob].into_iter().enumerate() {
+            cache.insert(&format!("token-{i}"), 0, token);
+        }
+
+        cache.forget_user(1);
+
+        assert_eq!(cache.get("token-0", 0), None);
+        assert_eq!(cache.get("token-1", 0), None);
+        assert_eq!(cache.get("token-2", 0), Some(bob));
+    }
+
+    #[test]
+    fn test_zero_capacity_disables() {
+        let cache = JwtCache::new(0);
+        cache.insert("a.b.c", 0, verified(1, 3600));
+        assert_eq!(cache.get("a.b.c", 0), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use serde_json::{Value, json};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use super::{AuditRecorder, GeoIp, Principal};
+use crate::{
+    config::Config,
+    models::{Actor, AuditEntry, ClientInfo, GeoPoint, SecurityEvent},
+    repository::LoginSignalRepository,
+    telemetry::{SECURITY_EVENTS, org_attribute},
+};
+
+/// GeoIP locations are only good to tens or hundreds of kilometres, so
+/// closer sign-ins never count as travel, however quick.
+const TRAVEL_MIN_DISTANCE_KM: f64 = 500.0;
+
+/// Two sign-ins within the same second are treated as a second apart.
+const TRAVEL_MIN_ELAPSED_SECS: f64 = 1.0;
+
+/// Journeys from one sign-in's location to the next's faster than
+/// `max_kmh`; `None` for plausible ones.
+pub fn impossible_travel(
+    from: GeoPoint,
+    to: GeoPoint,
+    elapsed: time::Duration,
+    max_kmh: f64,
+) -> Option<Travel> {
+    let distance_km = from.distance_km(&to);
+    let hours = elapsed.as_seconds_f64().max(TRAVEL_MIN_ELAPSED_SECS) / 3600.0;
+    let speed_kmh = distance_km / hours;
+    (distance_km >= TRAVEL_MIN_DISTANCE_KM && speed_kmh > max_kmh).then_some(Travel {
+        distance_km,
+        speed_kmh,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Travel {
+    pub distance_km: f64,
+    pub speed_kmh: f64,
+}
+
+/// Watches logins for a new device, impossible travel between two
+/// sign-ins and repeated wrong passwords or codes. Each signal is written
+/// to the audit log and emitted as a warning event with `security.*`
+/// attributes, on the span and through the OTLP log exporter. None of it
+/// fails a login: errors are only logged.
+#[derive(Clone)]
+pub struct LoginSignals {
+    repo: LoginSignalRepository,
+    geoip: GeoIp,
+    audit: AuditRecorder,
+    failure_threshold: u32,
+    failure_window: Duration,
+    max_travel_kmh: f64,
+}
+
+impl LoginSignals {
+    pub fn new(
+        repo: LoginSignalRepository,
+        geoip: GeoIp,
+        audit: AuditRecorder,
+        config: &Config,
+    ) -> Self {
+        Self {
+            repo,
+            geoip,
+            audit,
+            failure_threshold: config.security_failed_login_threshold,
+            failure_window: Duration::from_secs(config.security_failed_login_window_secs),
+            max_travel_kmh: config.security_max_travel_kmh,
+        }
+    }
+
+    /// Records the device and where it signed in from. A registration only
+    /// records them, since every device is new then.
+    #[instrument(
+        name = "security.login_succeeded",
+        skip(self, principal, client),
+        fields(user_id = principal.user_id)
+    )]
+    pub async fn login_succeeded(
+        &self,
+        principal: Principal,
+        client: &ClientInfo,
+        registration: bool,
+    ) {
+        if let Err(e) = self.check_sign_in(principal, client, registration).await {
+            tracing::warn!(error = %e, "Failed to check login signals");
+        }
+    }
+
+    /// A wrong password or code for `principal`. Every
+    /// `SECURITY_FAILED_LOGIN_THRESHOLD`th within the window raises
+    /// [`SecurityEvent::RepeatedFailures`], so an attack that carries on
+    /// keeps raising it at that pace.
+    #[instrument(
+        name = "security.login_failed",
+        skip(self, principal, client),
+        fields(user_id = principal.user_id)
+    )]
+    pub async fn login_failed(&self, principal: Principal, client: &ClientInfo) {
+        let ip = client.ip.map(|ip| ip.to_string());
+        let failures = match self
+            .repo
+            .record_failure(principal.user_id, ip.as_deref(), self.failure_window)
+            .await
+        {
+            Ok(failures) => failures,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to record login failure");
+                return;
+            }
+        };
+        if failures % i64::from(self.failure_threshold) != 0 {
+            return;
+        }
+
+        let event = SecurityEvent::RepeatedFailures;
+        let window_secs = self.failure_window.as_secs();
+        tracing::warn!(
+            security.event = event.as_str(),
+            security.user.id = principal.user_id,
+            security.client.ip = ip.as_deref().unwrap_or_default(),
+            security.failures.count = failures,
+            security.failures.window_secs = window_secs,
+            "Repeated login failures"
+        );
+        self.record(
+            event,
+            principal,
+            json!({
+                "ip": ip,
+                "failures": failures,
+                "window_secs": window_secs,
+            }),
+        )
+        .await;
+    }
+
+    async fn check_sign_in(
+        &self,
+        principal: Principal,
+        client: &ClientInfo,
+        registration: bool,
+    ) -> Result<(), sqlx::Error> {
+        let ip = client.ip.map(|ip| ip.to_string());
+        let location = client.ip.and_then(|ip| self.geoip.lookup(ip));
+        let previous = match location {
+            Some(_) => self.repo.last_sign_in(principal.user_id).await?,
+            None => None,
+        };
+        let new_device = self
+            .repo
+            .record_device(
+                principal.user_id,
+                &client.user_agent,
+                ip.as_deref(),
+                location,
+            )
+            .await?;
+
+        if new_device && !registration {
+            let event = SecurityEvent::NewDevice;
+            tracing::warn!(
+                security.event = event.as_str(),
+                security.user.id = principal.user_id,
+                security.client.ip = ip.as_deref().unwrap_or_default(),
+                security.client.user_agent = %client.user_agent,
+                "Sign-in from a new device"
+            );
+            self.record(
+                event,
+                principal,
+                json!({ "ip": ip, "user_agent": client.user_agent }),
+            )
+            .await;
+        }
+
+        let (Some(location), Some(previous)) = (location, previous) else {
+            return Ok(());
+        };
+        let elapsed = OffsetDateTime::now_utc() - previous.last_seen_at;
+        let Some(travel) =
+            impossible_travel(previous.location(), location, elapsed, self.max_travel_kmh)
+        else {
+            return Ok(());
+        };
+        let event = SecurityEvent::ImpossibleTravel;
+        let elapsed_secs = elapsed.whole_seconds().max(0);
+        tracing::warn!(
+            security.event = event.as_str(),
+            security.user.id = principal.user_id,
+            security.client.ip = ip.as_deref().unwrap_or_default(),
+            security.client.previous_ip = previous.last_ip.as_deref().unwrap_or_default(),
+            security.geo.distance_km = travel.distance_km.round(),
+            security.geo.elapsed_secs = elapsed_secs,
+            security.geo.speed_kmh = travel.speed_kmh.round(),
+            "Impossible travel between sign-ins"
+        );
+        self.record(
+            event,
+            principal,
+            json!({
+                "ip": ip,
+                "previous_ip": previous.last_ip,
+                "distance_km": travel.distance_km.round(),
+                "elapsed_secs": elapsed_secs,
+            }),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// The entry names the account as its actor, though with repeated
+    /// failures that's whoever was trying it.
+    async fn record(&self, event: SecurityEvent, principal: Principal, details: Value) {
+        SECURITY_EVENTS.add(
+            1,
+            &[
+                KeyValue::new("security.event", event.as_str()),
+                org_attribute(principal.org),
+            ],
+        );
+        self.audit
+            .record(
+                AuditEntry::new(
+                    Actor::User(principal.user_id),
+                    event.audit_action(),
+                    "user",
+                    principal.user_id,
+                )
+                .changes(Value::Null, details),
+            )
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONDON: GeoPoint = GeoPoint {
+        latitude: 51.5074,
+        longitude: -0.1278,
+    };
+    const NEW_YORK: GeoPoint = GeoPoint {
+        latitude: 40.7128,
+        longitude: -74.0060,
+    };
+    const PARIS: GeoPoint = GeoPoint {
+        latitude: 48.8566,
+        longitude: 2.3522,
+    };
+
+    #[test]
+    fn test_impossible_travel_flags_journeys_faster_than_the_limit() {
+        let travel = impossible_travel(LONDON, NEW_YORK, time::Duration::hours(1), 1000.0)
+            .expect("5570 km in an hour");
+        assert!((travel.distance_km - 5570.0).abs() < 10.0);
+        assert!((travel.speed_kmh - travel.distance_km).abs() < 1e-6);
+
+        assert_eq!(
+            impossible_travel(LONDON, NEW_YORK, time::Duration::hours(8), 1000.0),
+            None
+        );
+        assert!(impossible_travel(LONDON, NEW_YORK, time::Duration::ZERO, 1000.0).is_some());
+    }
+
+    #[test]
+    fn test_impossible_travel_ignores_short_distances() {
+        // About 340 km, which a GeoIP error can account for.
+        assert_eq!(
+            impossible_travel(LONDON, PARIS, time::Duration::minutes(1), 1000.0),
+            None
+        );
+        assert_eq!(
+            impossible_travel(LONDON, LONDON, time::Duration::ZERO, 1000.0),
+            None
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Least-recently-used, with an optional TTL. Eviction scans every entry,
+/// which is fine at the few thousand entries the in-process caches need and
+/// keeps this free of a linked list. A capacity of 0 disables it.
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    ttl: Option<Duration>,
+    entries: HashMap<K, CacheEntry<V>>,
+    tick: u64,
+}
+
+struct CacheEntry<V> {
+    value: V,
+    inserted: Instant,
+    used: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::with_capacity(capacity),
+            tick: 0,
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.get_mut(key)?;
+        if self.ttl.is_some_and(|ttl| entry.inserted.elapsed() >= ttl) {
+            self.entries.remove(key);
+            return None;
+        }
+        self.tick += 1;
+        entry.used = self.tick;
+        Some(entry.value.clone())
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                inserted: Instant::now(),
+                used: self.tick,
+            },
+        );
+    }
+
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        self.entries.retain(|_, entry| keep(&entry.value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2, TTL);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+
+    #[test]
+    fn test_lru_overwrite_does_not_evict() {
+        let mut cache = LruCache::new(2, TTL);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("a", 10);
+
+        assert_eq!(cache.get(&"a"), Some(10));
+        assert_eq!(cache.get(&"b"), Some(2));
+    }
+
+    #[test]
+    fn test_lru_expires_and_disables() {
+        let mut expired = LruCache::new(2, Some(Duration::ZERO));
+        expired.insert("a", 1);
+        assert_eq!(expired.get(&"a"), None);
+
+        let mut forever = LruCache::new(2, None);
+        forever.insert("a", 1);
+        assert_eq!(forever.get(&"a"), Some(1));
+
+        let mut disabled = LruCache::new(0, TTL);
+        disabled.insert("a", 1);
+        assert_eq!(disabled.get(&"a"), None);
+    }
+}
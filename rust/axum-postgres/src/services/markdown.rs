@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use opentelemetry::KeyValue;
+use pulldown_cmark::{Options, Parser, html};
+use time::OffsetDateTime;
+
+use super::lru::LruCache;
+use crate::telemetry::ARTICLES_MARKDOWN_RENDERED;
+
+/// Bodies are written by any signed-in user, so whatever the markdown
+/// produced (raw HTML included) goes through an allow-list: no scripts,
+/// styles or event handlers, and links only to http(s) and mailto.
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("noopener noreferrer nofollow"));
+    builder
+});
+
+/// A body as sanitized HTML, without the cache. For one-off renders such
+/// as exports, which would otherwise push every article through it.
+pub fn render(body: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut unsafe_html = String::with_capacity(body.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(body, options));
+    SANITIZER.clean(&unsafe_html).to_string()
+}
+
+type CacheKey = (i32, OffsetDateTime);
+
+/// Renders article bodies for `body_html`, keeping the result per article
+/// revision: every edit moves `updated_at`, and views and enrichment
+/// don't, so `(id, updated_at)` changes exactly when the body can have.
+#[derive(Clone)]
+pub struct MarkdownRenderer {
+    cache: Arc<Mutex<LruCache<CacheKey, String>>>,
+}
+
+impl MarkdownRenderer {
+    pub fn new(cache_size: usize) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(LruCache::new(cache_size, None))),
+        }
+    }
+
+    pub fn body_html(&self, id: i32, updated_at: OffsetDateTime, body: &str) -> String {
+        let key = (id, updated_at);
+        if let Some(hit) = self
+            .cache
+            .lock()
+            .expect("markdown cache poisoned")
+            .get(&key)
+        {
+            ARTICLES_MARKDOWN_RENDERED.add(1, &[KeyValue::new("cache", "hit")]);
+            return hit;
+        }
+
+        let html = render(body);
+        ARTICLES_MARKDOWN_RENDERED.add(1, &[KeyValue::new("cache", "miss")]);
+        self.cache
+            .lock()
+            .expect("markdown cache poisoned")
+            .insert(key, html.clone());
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    /// Every payload here used to be a way past some sanitizer.
+    const XSS_PAYLOADS: &[&str] = &[
+        "<script>alert(1)</script>",
+        "<SCRIPT SRC=//evil.example/x.js></SCRIPT>",
+        "<img src=x onerror=alert(1)>",
+        "<svg onload=alert(1)>",
+        "<body onload=alert(1)>",
+        "<iframe src=\"javascript:alert(1)\"></iframe>",
+        "<a href=\"javascript:alert(1)\">x</a>",
+        "<a href=\"JaVaScRiPt:alert(1)\">x</a>",
+        "<a href=\" javascript:alert(1)\">x</a>",
+        "<a href=\"&#106;avascript:alert(1)\">x</a>",
+        "<a href=\"java\tscript:alert(1)\">x</a>",
+        "<a href=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\">x</a>",
+        "<a href=\"vbscript:msgbox(1)\">x</a>",
+        "<div style=\"background:url(javascript:alert(1))\">x</div>",
+        "<style>*{background:url(javascript:alert(1))}</style>",
+        "<object data=\"javascript:alert(1)\"></object>",
+        "<embed src=\"javascript:alert(1)\">",
+        "<form action=\"javascript:alert(1)\"><button>x</button></form>",
+        "<math><mtext><table><mglyph><style><img src=x onerror=alert(1)>",
+        "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\">",
+        "<<script>script>alert(1)<</script>/script>",
+        "<img src=\"x\" \" onerror=\"alert(1)\">",
+        "<p onmouseover=\"alert(1)\">hover</p>",
+        "[x](javascript:alert(1))",
+        "[x](JAVASCRIPT:alert(1))",
+        "[x](&#x6A;avascript:alert(1))",
+        "[x](<javascript:alert(1)>)",
+        "[x](data:text/html,<script>alert(1)</script>)",
+        "![x](javascript:alert(1))",
+        "![x\" onerror=\"alert(1)](x.png)",
+        "[x](https://example.com \"t\\\" onmouseover=\\\"alert(1)\")",
+        "<javascript:alert(1)>",
+        "[x]: javascript:alert(1)\n\n[x]",
+        "`<script>` <script>alert(1)</script>",
+        "| a |\n|---|\n| <img src=x onerror=alert(1)> |",
+    ];
+
+    /// `(tag, [(attribute, value)])` for every tag in `html`. The
+    /// sanitizer re-serializes everything, escaping `<` in text and
+    /// quoting every attribute with `"`, so this needn't be a real parser.
+    fn tags(html: &str) -> Vec<(String, Vec<(String, String)>)> {
+        let mut tags = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..].find('>').expect("unclosed tag") + start;
+            let inner = rest[start + 1..end].trim_end_matches('/');
+            rest = &rest[end + 1..];
+            if inner.starts_with('/') {
+                continue;
+            }
+
+            let (name, mut attrs) = inner.split_once(' ').unwrap_or((inner, ""));
+            let mut parsed = Vec::new();
+            while let Some(eq) = attrs.find("=\"") {
+                let attr = attrs[..eq].trim().to_lowercase();
+                let value_end = attrs[eq + 2..].find('"').expect("unquoted attribute") + eq + 2;
+                parsed.push((attr, attrs[eq + 2..value_end].replace("&amp;", "&")));
+                attrs = &attrs[value_end + 1..];
+            }
+            tags.push((name.to_lowercase(), parsed));
+        }
+        tags
+    }
+
+    fn assert_safe(input: &str, html: &str) {
+        const FORBIDDEN_TAGS: &[&str] = &[
+            "script", "style", "iframe", "object", "embed", "form", "svg", "math", "body",
+        ];
+        for (tag, attrs) in tags(html) {
+            assert!(
+                !FORBIDDEN_TAGS.contains(&tag.as_str()),
+                "<{tag}> kept from {input:?}: {html}"
+            );
+            for (attr, value) in attrs {
+                assert!(
+                    !attr.starts_with("on") && attr != "style",
+                    "{attr} kept from {input:?}: {html}"
+                );
+                if attr == "href" || attr == "src" {
+                    // Browsers drop whitespace and control characters from
+                    // URLs, and without an RFC 3986 scheme before the first
+                    // `:` the URL is relative.
+                    let url: String = value
+                        .chars()
+                        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+                        .collect();
+                    let scheme = url
+                        .split_once(':')
+                        .map(|(s, _)| s.to_lowercase())
+                        .filter(|s| {
+                            s.starts_with(|c: char| c.is_ascii_alphabetic())
+                                && s.chars()
+                                    .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+                        });
+                    assert!(
+                        matches!(scheme.as_deref(), None | Some("http" | "https" | "mailto")),
+                        "{attr}={value:?} kept from {input:?}: {html}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let html = render(
+            "# Title\n\nSome *emphasis* and `code`.\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n~~gone~~",
+        );
+
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<em>emphasis</em>"));
+        assert!(html.contains("<code>code</code>"));
+        assert!(html.contains("<td>1</td>"));
+        assert!(html.contains("<del>gone</del>"));
+    }
+
+    #[test]
+    fn test_render_keeps_safe_links_and_images() {
+        let html = render("[docs](https://example.com/a?b=1&c=2) ![cat](/img/cat.png)");
+
+        assert!(html.contains(
+            "<a href=\"https://example.com/a?b=1&amp;c=2\" rel=\"noopener noreferrer nofollow\">docs</a>"
+        ));
+        assert!(html.contains("<img src=\"/img/cat.png\" alt=\"cat\">"));
+    }
+
+    #[test]
+    fn test_render_escapes_code() {
+        let html = render("```\n<script>alert(1)</script>\n```");
+
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert_safe("code block", &html);
+    }
+
+    #[test]
+    fn test_render_neutralizes_xss_payloads() {
+        for payload in XSS_PAYLOADS {
+            let html = render(payload);
+            assert_safe(payload, &html);
+            assert!(!html.contains("alert(1)</script>"), "{payload:?}: {html}");
+        }
+    }
+
+    #[test]
+    fn test_body_html_is_cached_per_revision() {
+        let renderer = MarkdownRenderer::new(10);
+        let first = datetime!(2026-01-06 10:00 UTC);
+        let edited = datetime!(2026-01-06 11:00 UTC);
+
+        assert_eq!(renderer.body_html(1, first, "*a*"), "<p><em>a</em></p>\n");
+        assert_eq!(renderer.body_html(1, first, "*b*"), "<p><em>a</em></p>\n");
+        assert_eq!(renderer.body_html(1, edited, "*b*"), "<p><em>b</em></p>\n");
+        assert_metric_recorded(
+            "articles.markdown.rendered",
+            &[KeyValue::new("cache", "hit")],
+        );
+    }
+
+    fn fragment() -> impl Strategy<Value = String> {
+        prop_oneof![
+            prop::sample::select(vec![
+                "<script>",
+                "</script>",
+                "<img src=x ",
+                "onerror=",
+                "alert(1)",
+                "<svg ",
+                "onload=",
+                "<a href=\"",
+                "\">",
+                "javascript:",
+                "JaVaScRiPt:",
+                "java\tscript:",
+                "data:text/html,",
+                "vbscript:",
+                "&#106;",
+                "&#x3C;",
+                "[x](",
+                "![x](",
+                ")",
+                "](",
+                "<",
+                ">",
+                "\"",
+                "'",
+                "`",
+                "```\n",
+                "\n\n",
+                "| a |\n|---|\n| ",
+                " style=",
+                "<iframe ",
+                "<style>",
+                "<math>",
+                "<!--",
+                "-->",
+                "<![CDATA[",
+            ])
+            .prop_map(str::to_string),
+            "[a-zA-Z0-9 <>=\"'/:();&#*_\\[\\]!\n-]{0,12}",
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2_000))]
+
+        #[test]
+        fn prop_render_never_emits_active_content(
+            fragments in prop::collection::vec(fragment(), 0..24)
+        ) {
+            let input = fragments.concat();
+            assert_safe(&input, &render(&input));
+        }
+    }
+}
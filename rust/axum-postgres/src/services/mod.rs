@@ -1,5 +1,35 @@
+mod account;
 mod article;
+mod audit;
 mod auth;
+mod economic_context;
+pub mod export;
+mod geoip;
+mod image;
+mod jwt_cache;
+mod login_signals;
+mod lru;
+mod markdown;
+mod password;
+mod suggest;
+mod views;
+mod webhook;
 
+pub use account::AccountService;
 pub use article::ArticleService;
-pub use auth::AuthService;
+pub use audit::AuditRecorder;
+pub use auth::{API_TOKEN_PREFIX, AuthService, JwtSession, Principal};
+pub use economic_context::{
+    EconomicContextQuery, EconomicContextResponse, EconomicContextService, parse_date,
+};
+pub use geoip::GeoIp;
+pub use image::{ImageKind, ImageResponse, ImageService, too_large};
+pub use jwt_cache::{JwtCache, VerifiedToken};
+pub use login_signals::LoginSignals;
+pub use markdown::MarkdownRenderer;
+pub use password::PasswordHashing;
+pub use suggest::SuggestService;
+pub use views::ViewBuffer;
+pub use webhook::{
+    WebhookDelivery, WebhookReceipt, WebhookService, span_link, webhook_secret_bytes,
+};
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use argon2::{
+    Algorithm, Argon2, Params, Version,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
+use tokio::sync::Semaphore;
+
+use crate::{
+    config::Config,
+    error::{AppError, AppResult},
+};
+
+/// Argon2id with the `PASSWORD_HASH_*` parameters. Hashes run on the
+/// blocking pool, at most one per CPU at a time: each holds
+/// `PASSWORD_HASH_MEMORY_KIB`, so a burst of logins queues instead of
+/// multiplying that.
+#[derive(Clone)]
+pub struct PasswordHashing {
+    params: Params,
+    permits: Arc<Semaphore>,
+}
+
+impl PasswordHashing {
+    pub fn new(params: Params) -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            params,
+            permits: Arc::new(Semaphore::new(cpus)),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config
+                .password_hash_params()
+                .expect("PASSWORD_HASH_* are validated"),
+        )
+    }
+
+    pub async fn hash(&self, password: &str) -> AppResult<String> {
+        let argon2 = self.argon2();
+        let password = password.to_string();
+        self.run(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| AppError::Internal(format!("Password hashing failed: {e}")))
+        })
+        .await
+    }
+
+    /// Checks `password` against `hash` with the parameters it was made
+    /// with. `Ok(true)` when it matches but was made with other parameters
+    /// than the configured ones, so it should be hashed again.
+    pub async fn verify(&self, password: &str, hash: &str) -> AppResult<bool> {
+        let params = self.params.clone();
+        let password = password.to_string();
+        let hash = hash.to_string();
+        self.run(move || {
+            let parsed = PasswordHash::new(&hash)
+                .map_err(|e| AppError::Internal(format!("Invalid hash: {e}")))?;
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .map_err(|_| AppError::InvalidCredentials)?;
+            Ok(is_outdated(&parsed, &params))
+        })
+        .await
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        hash: impl FnOnce() -> AppResult<T> + Send + 'static,
+    ) -> AppResult<T> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| AppError::Internal(format!("Password hashing unavailable: {e}")))?;
+        tokio::task::spawn_blocking(hash)
+            .await
+            .map_err(|e| AppError::Internal(format!("Password hashing task failed: {e}")))?
+    }
+}
+
+/// Whether `hash` was made with another algorithm, version or cost than
+/// `params`.
+fn is_outdated(hash: &PasswordHash<'_>, params: &Params) -> bool {
+    if hash.algorithm != Algorithm::Argon2id.ident() || hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+    match Params::try_from(hash) {
+        Ok(used) => {
+            used.m_cost() != params.m_cost()
+                || used.t_cost() != params.t_cost()
+                || used.p_cost() != params.p_cost()
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small enough to keep the tests fast.
+    fn params(m_cost: u32, t_cost: u32) -> Params {
+        Params::new(m_cost, t_cost, 1, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let hashing = PasswordHashing::new(params(1024, 1));
+        let hash = hashing.hash("secure_password_123").await.unwrap();
+
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert!(!hashing.verify("secure_password_123", &hash).await.unwrap());
+        assert!(matches!(
+            hashing.verify("wrong_password", &hash).await,
+            Err(AppError::InvalidCredentials)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hash_from_other_parameters_is_outdated() {
+        let old = PasswordHashing::new(params(1024, 1))
+            .hash("secure_password_123")
+            .await
+            .unwrap();
+        let hashing = PasswordHashing::new(params(2048, 2));
+
+        assert!(hashing.verify("secure_password_123", &old).await.unwrap());
+        let new = hashing.hash("secure_password_123").await.unwrap();
+        assert!(!hashing.verify("secure_password_123", &new).await.unwrap());
+    }
+
+    #[test]
+    fn test_other_algorithms_are_outdated() {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2i = Argon2::new(Algorithm::Argon2i, Version::V0x13, params(1024, 1))
+            .hash_password(b"secure_password_123", &salt)
+            .unwrap();
+
+        assert!(is_outdated(&argon2i, &params(1024, 1)));
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use opentelemetry::KeyValue;
+use tracing::instrument;
+
+use super::lru::LruCache;
+use crate::{
+    error::{AppError, AppResult, RecordErr},
+    models::{ArticleSuggestion, OrgId, SuggestionsResponse},
+    repository::ArticleRepository,
+    telemetry::{ARTICLES_SUGGEST_DURATION, org_attribute},
+};
+
+/// What a suggestion should take end to end. Going over it doesn't fail the
+/// request (`SUGGEST_TIMEOUT_MS` does that); it marks the span and lands
+/// above the 30ms bucket of `articles.suggest.duration`.
+const SUGGEST_LATENCY_BUDGET: Duration = Duration::from_millis(30);
+
+/// Below this a trigram match is mostly noise, so it isn't worth a query.
+const MIN_QUERY_CHARS: usize = 2;
+const MAX_QUERY_CHARS: usize = 100;
+const MAX_LIMIT: i64 = 10;
+
+type CacheKey = (OrgId, String, i64);
+
+/// Title completions for a search box, kept apart from
+/// `GET /api/articles` so it can answer from memory and give up on a slow
+/// database instead of queueing keystrokes behind it.
+#[derive(Clone)]
+pub struct SuggestService {
+    article_repo: ArticleRepository,
+    cache: Arc<Mutex<LruCache<CacheKey, Vec<ArticleSuggestion>>>>,
+    timeout: Duration,
+}
+
+impl SuggestService {
+    pub fn new(
+        article_repo: ArticleRepository,
+        timeout: Duration,
+        cache_size: usize,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            article_repo,
+            cache: Arc::new(Mutex::new(LruCache::new(cache_size, Some(cache_ttl)))),
+            timeout,
+        }
+    }
+
+    #[instrument(
+        name = "article.suggest",
+        skip(self, org),
+        fields(suggest.source = tracing::field::Empty, suggest.over_budget = tracing::field::Empty)
+    )]
+    pub async fn suggest(&self, org: OrgId, q: &str, limit: i64) -> AppResult<SuggestionsResponse> {
+        async move {
+            let started = Instant::now();
+            let q = normalize(q);
+            if q.chars().count() > MAX_QUERY_CHARS {
+                return Err(AppError::Validation(format!(
+                    "q must be at most {MAX_QUERY_CHARS} characters"
+                )));
+            }
+            let key = (org, q, limit.clamp(1, MAX_LIMIT));
+
+            let (source, result) = if key.1.chars().count() < MIN_QUERY_CHARS {
+                ("skipped", Ok(Vec::new()))
+            } else if let Some(hit) = self.cache.lock().expect("suggest cache poisoned").get(&key) {
+                ("cache", Ok(hit))
+            } else {
+                ("db", self.fetch(&key).await)
+            };
+
+            let elapsed = started.elapsed();
+            let span = tracing::Span::current();
+            span.record("suggest.source", source);
+            span.record("suggest.over_budget", elapsed > SUGGEST_LATENCY_BUDGET);
+            ARTICLES_SUGGEST_DURATION.record(
+                elapsed.as_secs_f64() * 1000.0,
+                &[org_attribute(org), KeyValue::new("suggest.source", source)],
+            );
+
+            Ok(SuggestionsResponse {
+                suggestions: result?,
+            })
+        }
+        .await
+        .record_err()
+    }
+
+    async fn fetch(&self, key: &CacheKey) -> AppResult<Vec<ArticleSuggestion>> {
+        let (org, q, limit) = key;
+        let suggestions =
+            tokio::time::timeout(self.timeout, self.article_repo.suggest(*org, q, *limit))
+                .await
+                .map_err(|_| AppError::ServiceUnavailable("Suggestions timed out".to_string()))??;
+
+        self.cache
+            .lock()
+            .expect("suggest cache poisoned")
+            .insert(key.clone(), suggestions.clone());
+        Ok(suggestions)
+    }
+}
+
+/// Trigram matching ignores case and runs of whitespace, so the cache does
+/// too.
+fn normalize(q: &str) -> String {
+    q.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::database::{TenancyMode, TenantDb};
+    use crate::telemetry::testing::{SpanCapture, assert_metric_recorded};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn unreachable_service() -> SuggestService {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/db")
+            .expect("lazy pool");
+        let repo = ArticleRepository::new(TenantDb::new(pool, TenancyMode::App));
+        SuggestService::new(repo, Duration::from_secs(1), 10, TTL)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Rust\t  Async "), "rust async");
+    }
+
+    #[tokio::test]
+    async fn test_short_query_skips_the_database() {
+        let spans = SpanCapture::start();
+        let service = unreachable_service();
+        let org = OrgId(9271);
+
+        let response = service.suggest(org, " r ", 5).await.unwrap();
+
+        assert!(response.suggestions.is_empty());
+        assert!(spans.spans().iter().all(|s| s.name != "db.article.suggest"));
+        assert_metric_recorded(
+            "articles.suggest.duration",
+            &[
+                org_attribute(org),
+                KeyValue::new("suggest.source", "skipped"),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_the_database() {
+        let service = unreachable_service();
+        let org = OrgId(9272);
+        let cached = vec![ArticleSuggestion {
+            slug: "rust-async".to_string(),
+            title: "Rust Async".to_string(),
+        }];
+        service
+            .cache
+            .lock()
+            .unwrap()
+            .insert((org, "rust".to_string(), 5), cached);
+
+        let response = service.suggest(org, "Rust", 5).await.unwrap();
+
+        assert_eq!(response.suggestions[0].slug, "rust-async");
+        assert_metric_recorded(
+            "articles.suggest.duration",
+            &[org_attribute(org), KeyValue::new("suggest.source", "cache")],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_failure_still_records_latency() {
+        let service = unreachable_service();
+        let org = OrgId(9273);
+
+        assert!(service.suggest(org, "rust", 5).await.is_err());
+        assert_metric_recorded(
+            "articles.suggest.duration",
+            &[org_attribute(org), KeyValue::new("suggest.source", "db")],
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::{Instrument, instrument};
+
+use crate::{
+    error::{AppResult, RecordErr},
+    repository::ArticleRepository,
+    telemetry::{ARTICLE_VIEWS_ROWS_WRITTEN, ARTICLES_VIEWED},
+};
+
+/// Counts article views in memory and writes them in one batched `UPDATE`
+/// per flush, so a burst of reads costs one write per article rather than
+/// one per read.
+#[derive(Clone)]
+pub struct ViewBuffer {
+    repo: ArticleRepository,
+    pending: Arc<Mutex<HashMap<i32, i64>>>,
+}
+
+impl ViewBuffer {
+    pub fn new(repo: ArticleRepository) -> Self {
+        Self {
+            repo,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the article's views buffered since the last flush, this one
+    /// included.
+    pub fn record(&self, article_id: i32) -> i64 {
+        ARTICLES_VIEWED.add(1, &[]);
+
+        let mut pending = self.pending.lock().expect("view buffer poisoned");
+        let views = pending.entry(article_id).or_insert(0);
+        *views += 1;
+        *views
+    }
+
+    /// Writes the buffered views. If the write fails they go back into the
+    /// buffer for the next flush.
+    #[instrument(
+        name = "article_views.flush",
+        skip(self),
+        fields(articles = tracing::field::Empty, views = tracing::field::Empty)
+    )]
+    pub async fn flush(&self) -> AppResult<usize> {
+        async move {
+            let batch = std::mem::take(&mut *self.pending.lock().expect("view buffer poisoned"));
+            if batch.is_empty() {
+                return Ok(0);
+            }
+
+            let (ids, views): (Vec<i32>, Vec<i64>) = batch.iter().unzip();
+            let span = tracing::Span::current();
+            span.record("articles", ids.len() as i64);
+            span.record("views", views.iter().sum::<i64>());
+
+            match self.repo.add_views(&ids, &views).await {
+                Ok(rows) => {
+                    ARTICLE_VIEWS_ROWS_WRITTEN.add(rows, &[]);
+                    tracing::debug!(articles = ids.len(), rows, "Article views flushed");
+                    Ok(ids.len())
+                }
+                Err(e) => {
+                    let mut pending = self.pending.lock().expect("view buffer poisoned");
+                    for (id, views) in batch {
+                        *pending.entry(id).or_insert(0) += views;
+                    }
+                    Err(e.into())
+                }
+            }
+        }
+        .await
+        .record_err()
+    }
+
+    pub fn spawn_flush(&self, interval: Duration) -> JoinHandle<()> {
+        let buffer = self.clone();
+
+        tokio::spawn(
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = buffer.flush().await {
+                        tracing::warn!(error = %e, "Failed to flush article views");
+                    }
+                }
+            }
+            .instrument(tracing::info_span!("article_views.flusher")),
+        )
+    }
+
+    #[cfg(test)]
+    fn pending(&self, article_id: i32) -> i64 {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&article_id)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::Status;
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+    use crate::database::{TenancyMode, TenantDb};
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    /// A buffer whose flushes fail quickly.
+    fn unreachable_buffer() -> ViewBuffer {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://localhost:1/db")
+            .expect("lazy pool");
+        ViewBuffer::new(ArticleRepository::new(TenantDb::new(
+            pool,
+            TenancyMode::App,
+        )))
+    }
+
+    #[tokio::test]
+    async fn test_record_coalesces_views_per_article() {
+        let buffer = unreachable_buffer();
+        assert_eq!(buffer.flush().await.unwrap(), 0);
+
+        assert_eq!(buffer.record(1), 1);
+        assert_eq!(buffer.record(2), 1);
+        assert_eq!(buffer.record(1), 2);
+        assert_eq!(buffer.record(1), 3);
+
+        assert_eq!(buffer.pending(1), 3);
+        assert_eq!(buffer.pending(2), 1);
+        assert_metric_recorded("articles.viewed", &[]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_the_views() {
+        let spans = SpanCapture::start();
+        let buffer = unreachable_buffer();
+        buffer.record(1);
+        buffer.record(1);
+        buffer.record(2);
+        assert!(buffer.flush().await.is_err());
+        assert_eq!(buffer.record(1), 3);
+        assert_eq!(buffer.pending(2), 1);
+
+        let flush = spans.span("article_views.flush");
+        assert!(matches!(flush.status, Status::Error { .. }));
+        assert_attributes(
+            &flush,
+            &[KeyValue::new("articles", 2), KeyValue::new("views", 3)],
+        );
+    }
+}
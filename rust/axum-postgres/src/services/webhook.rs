@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use opentelemetry::KeyValue;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{Span, instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    config::Config,
+    error::{AppError, AppResult},
+    jobs::JobQueue,
+    storage::signing::verify_hmac_sha256,
+    telemetry::WEBHOOKS_RECEIVED,
+};
+
+pub const WEBHOOK_ID_HEADER: &str = "webhook-id";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "webhook-timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "webhook-signature";
+
+/// Secrets in the `whsec_<base64>` form Standard Webhooks senders hand out
+/// are decoded; anything else is used as is.
+pub fn webhook_secret_bytes(secret: &str) -> Option<Vec<u8>> {
+    match secret.strip_prefix("whsec_") {
+        Some(encoded) => STANDARD.decode(encoded).ok(),
+        None => Some(secret.as_bytes().to_vec()),
+    }
+}
+
+/// What a handled delivery becomes: the payload of its `webhook` job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub source: String,
+    pub delivery_id: String,
+    pub event_type: Option<String>,
+    /// The sender's span for the delivery, linked from ours.
+    pub traceparent: Option<String>,
+    pub event: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookReceipt {
+    pub delivery_id: String,
+    pub job_id: i64,
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    MissingHeaders,
+    InvalidSignature,
+    Stale,
+    InvalidBody,
+}
+
+impl Rejection {
+    fn outcome(self) -> &'static str {
+        match self {
+            Rejection::MissingHeaders => "missing_headers",
+            Rejection::InvalidSignature => "invalid_signature",
+            Rejection::Stale => "stale",
+            Rejection::InvalidBody => "invalid_body",
+        }
+    }
+
+    fn into_error(self) -> AppError {
+        match self {
+            Rejection::MissingHeaders => AppError::Validation(format!(
+                "{WEBHOOK_ID_HEADER}, {WEBHOOK_TIMESTAMP_HEADER} and {WEBHOOK_SIGNATURE_HEADER} headers are required"
+            )),
+            Rejection::InvalidSignature | Rejection::Stale => AppError::Unauthorized,
+            Rejection::InvalidBody => {
+                AppError::Validation("Webhook body must be a JSON object".to_string())
+            }
+        }
+    }
+}
+
+/// Receives signed webhooks (Standard Webhooks: an HMAC-SHA256 over
+/// `{id}.{timestamp}.{body}`) and hands each delivery to the worker as a
+/// job keyed by its id. The timestamp bounds how long a captured delivery
+/// can be replayed; the key makes a replay inside that window, or the
+/// sender's own retry, a no-op.
+#[derive(Clone)]
+pub struct WebhookService {
+    secret: Option<Arc<[u8]>>,
+    tolerance: Duration,
+    job_queue: JobQueue,
+}
+
+impl WebhookService {
+    pub fn new(job_queue: JobQueue, config: &Config) -> Self {
+        let secret = Some(config.webhook_secret.as_str())
+            .filter(|secret| !secret.is_empty())
+            .and_then(webhook_secret_bytes)
+            .map(Arc::from);
+        Self {
+            secret,
+            tolerance: Duration::from_secs(config.webhook_tolerance_secs),
+            job_queue,
+        }
+    }
+
+    /// Disabled entirely (403) when `WEBHOOK_SECRET` is unset.
+    #[instrument(
+        name = "webhook.receive",
+        skip(self, headers, body),
+        fields(
+            webhook.id = tracing::field::Empty,
+            webhook.event_type = tracing::field::Empty,
+            webhook.duplicate = tracing::field::Empty,
+        )
+    )]
+    pub async fn receive(
+        &self,
+        source: &'static str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> AppResult<WebhookReceipt> {
+        let Some(secret) = &self.secret else {
+            return Err(AppError::Forbidden);
+        };
+        let span = Span::current();
+        // The delivery's trace is the sender's, so it is linked rather than
+        // continued: our trace starts at our server span either way.
+        let traceparent = header(headers, "traceparent").map(str::to_string);
+        if let Some(cx) = traceparent.as_deref().and_then(span_link) {
+            span.add_link(cx);
+        }
+
+        let record = |outcome: &'static str| {
+            WEBHOOKS_RECEIVED.add(
+                1,
+                &[
+                    KeyValue::new("webhook.source", source),
+                    KeyValue::new("outcome", outcome),
+                ],
+            );
+        };
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let delivery = match verify(secret, self.tolerance, headers, body, now) {
+            Ok((delivery_id, event)) => WebhookDelivery {
+                source: source.to_string(),
+                delivery_id,
+                event_type: event["type"].as_str().map(str::to_string),
+                traceparent,
+                event,
+            },
+            Err(rejection) => {
+                tracing::warn!(
+                    webhook.source = source,
+                    outcome = rejection.outcome(),
+                    "Webhook rejected"
+                );
+                record(rejection.outcome());
+                return Err(rejection.into_error());
+            }
+        };
+        span.record("webhook.id", delivery.delivery_id.as_str());
+        if let Some(event_type) = &delivery.event_type {
+            span.record("webhook.event_type", event_type.as_str());
+        }
+
+        let delivery_id = delivery.delivery_id.clone();
+        let key = format!("webhook:{source}:{delivery_id}");
+        let (job_id, duplicate) = self
+            .job_queue
+            .enqueue_once("webhook", &key, delivery)
+            .await?;
+        span.record("webhook.duplicate", duplicate);
+        record(if duplicate { "duplicate" } else { "accepted" });
+
+        Ok(WebhookReceipt {
+            delivery_id,
+            job_id,
+            duplicate,
+        })
+    }
+}
+
+/// The remote span a W3C `traceparent` names, for [`OpenTelemetrySpanExt::add_link`].
+pub fn span_link(traceparent: &str) -> Option<SpanContext> {
+    let carrier = HashMap::from([("traceparent".to_string(), traceparent.to_string())]);
+    let cx = TraceContextPropagator::new().extract(&carrier);
+    let span_context = cx.span().span_context().clone();
+    span_context.is_valid().then_some(span_context)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// The delivery's id and JSON body, once its signature and timestamp check
+/// out.
+fn verify(
+    secret: &[u8],
+    tolerance: Duration,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(String, serde_json::Value), Rejection> {
+    let (Some(id), Some(timestamp), Some(signatures)) = (
+        header(headers, WEBHOOK_ID_HEADER),
+        header(headers, WEBHOOK_TIMESTAMP_HEADER),
+        header(headers, WEBHOOK_SIGNATURE_HEADER),
+    ) else {
+        return Err(Rejection::MissingHeaders);
+    };
+    if id.is_empty() {
+        return Err(Rejection::MissingHeaders);
+    }
+
+    let signed = [id.as_bytes(), b".", timestamp.as_bytes(), b".", body].concat();
+    // Space-separated `v1,<base64>` entries; more than one while the sender
+    // rotates its secret.
+    let valid = signatures
+        .split(' ')
+        .filter_map(|entry| entry.strip_prefix("v1,"))
+        .filter_map(|signature| STANDARD.decode(signature).ok())
+        .any(|signature| verify_hmac_sha256(secret, &signed, &signature));
+    if !valid {
+        return Err(Rejection::InvalidSignature);
+    }
+
+    // Checked after the signature, so only the sender can learn the window.
+    let sent_at: i64 = timestamp.parse().map_err(|_| Rejection::Stale)?;
+    if now.abs_diff(sent_at) > tolerance.as_secs() {
+        return Err(Rejection::Stale);
+    }
+
+    match serde_json::from_slice(body) {
+        Ok(event @ serde_json::Value::Object(_)) => Ok((id.to_string(), event)),
+        _ => Err(Rejection::InvalidBody),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::signing::hmac_sha256;
+
+    const SECRET: &[u8] = b"test-webhook-secret";
+    const TOLERANCE: Duration = Duration::from_secs(300);
+    const NOW: i64 = 1_760_000_000;
+    const BODY: &[u8] = br#"{"type":"invoice.paid","data":{"id":"inv_1"}}"#;
+
+    /// What a sender with `secret` puts in `webhook-signature`.
+    fn sign(secret: &[u8], id: &str, timestamp: i64, body: &[u8]) -> String {
+        let signed = [
+            id.as_bytes(),
+            b".",
+            timestamp.to_string().as_bytes(),
+            b".",
+            body,
+        ]
+        .concat();
+        format!("v1,{}", STANDARD.encode(hmac_sha256(secret, &signed)))
+    }
+
+    fn headers(id: &str, timestamp: i64, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(WEBHOOK_ID_HEADER, id.parse().unwrap());
+        headers.insert(WEBHOOK_TIMESTAMP_HEADER, timestamp.into());
+        headers.insert(WEBHOOK_SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    fn signed(timestamp: i64, body: &[u8]) -> HeaderMap {
+        headers("msg_1", timestamp, &sign(SECRET, "msg_1", timestamp, body))
+    }
+
+    #[test]
+    fn test_valid_delivery() {
+        let (id, event) = verify(SECRET, TOLERANCE, &signed(NOW, BODY), BODY, NOW).unwrap();
+
+        assert_eq!(id, "msg_1");
+        assert_eq!(event["type"], "invoice.paid");
+    }
+
+    #[test]
+    fn test_signature_covers_body_id_and_timestamp() {
+        let headers = signed(NOW, BODY);
+        let tampered = br#"{"type":"invoice.paid","data":{"id":"inv_2"}}"#;
+        assert_eq!(
+            verify(SECRET, TOLERANCE, &headers, tampered, NOW),
+            Err(Rejection::InvalidSignature)
+        );
+
+        let signature = headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap();
+        for other in [
+            self::headers("msg_2", NOW, signature),
+            self::headers("msg_1", NOW - 1, signature),
+        ] {
+            assert_eq!(
+                verify(SECRET, TOLERANCE, &other, BODY, NOW),
+                Err(Rejection::InvalidSignature)
+            );
+        }
+        assert_eq!(
+            verify(b"other-secret", TOLERANCE, &headers, BODY, NOW),
+            Err(Rejection::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_any_listed_signature_passes() {
+        let valid = sign(SECRET, "msg_1", NOW, BODY);
+        let rotated = format!("v1,bm9wZQ== {valid}");
+
+        assert!(
+            verify(
+                SECRET,
+                TOLERANCE,
+                &headers("msg_1", NOW, &rotated),
+                BODY,
+                NOW
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_outside_tolerance_is_rejected() {
+        for sent_at in [NOW - 301, NOW + 301] {
+            assert_eq!(
+                verify(SECRET, TOLERANCE, &signed(sent_at, BODY), BODY, NOW),
+                Err(Rejection::Stale)
+            );
+        }
+        assert!(verify(SECRET, TOLERANCE, &signed(NOW - 300, BODY), BODY, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_missing_headers_and_bad_bodies() {
+        let mut headers = signed(NOW, BODY);
+        headers.remove(WEBHOOK_TIMESTAMP_HEADER);
+        assert_eq!(
+            verify(SECRET, TOLERANCE, &headers, BODY, NOW),
+            Err(Rejection::MissingHeaders)
+        );
+
+        for body in [&b"not json"[..], b"[1, 2]"] {
+            assert_eq!(
+                verify(SECRET, TOLERANCE, &signed(NOW, body), body, NOW),
+                Err(Rejection::InvalidBody)
+            );
+        }
+    }
+
+    #[test]
+    fn test_webhook_secret_bytes() {
+        assert_eq!(
+            webhook_secret_bytes("whsec_c2VjcmV0").as_deref(),
+            Some(&b"secret"[..])
+        );
+        assert_eq!(
+            webhook_secret_bytes("plain").as_deref(),
+            Some(&b"plain"[..])
+        );
+        assert_eq!(webhook_secret_bytes("whsec_!!"), None);
+    }
+
+    #[test]
+    fn test_span_link() {
+        let cx = span_link("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(
+            cx.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert!(cx.is_remote());
+        assert_eq!(span_link("garbage"), None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use time::OffsetDateTime;
+
+use super::{StorageError, signing, validate_key};
+use crate::config::Config;
+
+pub struct LocalStorage {
+    root: PathBuf,
+    signing_secret: String,
+    public_base_url: String,
+    url_ttl_secs: i64,
+}
+
+impl LocalStorage {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            root: PathBuf::from(&config.storage_local_dir),
+            signing_secret: config.storage_signing_secret.clone(),
+            public_base_url: config.public_base_url.trim_end_matches('/').to_string(),
+            url_ttl_secs: config.signed_url_ttl_secs,
+        }
+    }
+
+    pub async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &data).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        validate_key(key)?;
+        match tokio::fs::read(self.root.join(key)).await {
+            Ok(data) => Ok(Bytes::from(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StorageError::NotFound(key.to_string()))
+            }
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(StorageError::Io(e)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn signed_url(&self, key: &str) -> String {
+        let expires = OffsetDateTime::now_utc().unix_timestamp() + self.url_ttl_secs;
+        let signature = hex::encode(signing::hmac_sha256(
+            self.signing_secret.as_bytes(),
+            string_to_sign(key, expires).as_bytes(),
+        ));
+        format!(
+            "{}/api/images/{}?expires={}&sig={}",
+            self.public_base_url,
+            signing::uri_encode(key, true),
+            expires,
+            signature
+        )
+    }
+
+    pub fn verify(&self, key: &str, expires: i64, signature: &str) -> bool {
+        if expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        signing::verify_hmac_sha256(
+            self.signing_secret.as_bytes(),
+            string_to_sign(key, expires).as_bytes(),
+            &signature,
+        )
+    }
+}
+
+fn string_to_sign(key: &str, expires: i64) -> String {
+    format!("{key}:{expires}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_storage() -> LocalStorage {
+        LocalStorage {
+            root: PathBuf::from("/tmp/unused"),
+            signing_secret: "test-secret".to_string(),
+            public_base_url: "http://localhost:8080".to_string(),
+            url_ttl_secs: 60,
+        }
+    }
+
+    fn query_param<'a>(url: &'a str, name: &str) -> &'a str {
+        url.split(['?', '&'])
+            .find_map(|p| p.strip_prefix(&format!("{name}=")))
+            .expect("param present")
+    }
+
+    #[test]
+    fn test_signed_url_verifies() {
+        let storage = create_test_storage();
+        let url = storage.signed_url("avatars/1/a.png");
+
+        assert!(url.starts_with("http://localhost:8080/api/images/avatars/1/a.png?"));
+        let expires: i64 = query_param(&url, "expires").parse().unwrap();
+        let sig = query_param(&url, "sig");
+        assert!(storage.verify("avatars/1/a.png", expires, sig));
+        assert!(!storage.verify("avatars/2/a.png", expires, sig));
+    }
+
+    #[test]
+    fn test_expired_signature_rejected() {
+        let storage = create_test_storage();
+        let expires = OffsetDateTime::now_utc().unix_timestamp() - 1;
+        let sig = hex::encode(signing::hmac_sha256(
+            b"test-secret",
+            string_to_sign("a.png", expires).as_bytes(),
+        ));
+        assert!(!storage.verify("a.png", expires, &sig));
+    }
+}
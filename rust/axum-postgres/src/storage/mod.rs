@@ -0,0 +1,155 @@
+mod local;
+mod s3;
+pub(crate) mod signing;
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::config::Config;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+
+    #[error("Object not found: {0}")]
+    NotFound(String),
+
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+#[derive(Clone)]
+pub enum Storage {
+    Local(Arc<LocalStorage>),
+    S3(Arc<S3Storage>),
+}
+
+impl Storage {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        match config.storage_backend.as_str() {
+            "local" => Ok(Storage::Local(Arc::new(LocalStorage::new(config)))),
+            "s3" => Ok(Storage::S3(Arc::new(S3Storage::new(config)?))),
+            other => anyhow::bail!("Unknown STORAGE_BACKEND: {other}"),
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Storage::Local(_) => "local",
+            Storage::S3(_) => "s3",
+        }
+    }
+
+    #[instrument(
+        name = "storage.put",
+        skip(self, data),
+        fields(storage.backend = self.backend_name(), storage.size = data.len())
+    )]
+    pub async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        data: Bytes,
+    ) -> Result<(), StorageError> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(s) => s.put(key, data).await,
+            Storage::S3(s) => s.put(key, content_type, data).await,
+        }
+    }
+
+    /// Deleting a key that doesn't exist succeeds.
+    #[instrument(
+        name = "storage.delete",
+        skip(self),
+        fields(storage.backend = self.backend_name())
+    )]
+    pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(s) => s.delete(key).await,
+            Storage::S3(s) => s.delete(key).await,
+        }
+    }
+
+    pub fn signed_url(&self, key: &str) -> String {
+        match self {
+            Storage::Local(s) => s.signed_url(key),
+            Storage::S3(s) => s.signed_url(key),
+        }
+    }
+
+    /// Stored image values are either object keys written by an upload or
+    /// legacy absolute URLs; only the former need signing.
+    pub fn resolve_image(&self, value: &str) -> String {
+        if is_object_key(value) {
+            self.signed_url(value)
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// Whether an image value is an object key rather than empty or a legacy URL.
+pub fn is_object_key(value: &str) -> bool {
+    !(value.is_empty() || value.starts_with("http://") || value.starts_with("https://"))
+}
+
+pub(crate) fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && key
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..")
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key_accepts_nested_keys() {
+        assert!(validate_key("avatars/1/abc-def.png").is_ok());
+    }
+
+    #[test]
+    fn test_validate_key_rejects_traversal() {
+        assert!(validate_key("../etc/passwd").is_err());
+        assert!(validate_key("avatars/../../secret").is_err());
+        assert!(validate_key("/absolute/path.png").is_err());
+        assert!(validate_key("avatars//double.png").is_err());
+        assert!(validate_key("").is_err());
+    }
+
+    #[test]
+    fn test_is_object_key_skips_urls() {
+        assert!(is_object_key("avatars/9/a.png"));
+        assert!(!is_object_key(""));
+        assert!(!is_object_key("https://example.com/a.png"));
+    }
+
+    #[test]
+    fn test_validate_key_rejects_unsafe_chars() {
+        assert!(validate_key("avatars/1/a b.png").is_err());
+        assert!(validate_key("avatars/1/a?b=c.png").is_err());
+    }
+}
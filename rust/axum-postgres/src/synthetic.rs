@@ -0,0 +1,152 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use opentelemetry::KeyValue;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use crate::telemetry::{SYNTHETIC_CHECK_DURATION, SYNTHETIC_CHECK_SUCCESS};
+
+/// Check name and the API path it requests.
+const CHECKS: &[(&str, &str)] = &[
+    ("health", "/api/health"),
+    ("articles.list", "/api/articles?limit=1"),
+];
+
+/// Calls the service's own API over loopback on a timer, so
+/// `synthetic.check.success` reports availability even when no users are.
+pub struct SyntheticProbe {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl SyntheticProbe {
+    pub fn new(base_url: &str, timeout: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            // The certificate is for the public name rather than
+            // 127.0.0.1, and it is our own listener on the other end.
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .user_agent("synthetic-probe")
+                .danger_accept_invalid_certs(base_url.starts_with("https://"))
+                .build()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// The first round runs one `interval` after spawning.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.run().await;
+            }
+        })
+    }
+
+    pub async fn run(&self) {
+        for (name, path) in CHECKS {
+            run_check(name, self.get(path)).await;
+        }
+    }
+
+    async fn get(&self, path: &str) -> anyhow::Result<()> {
+        self.client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Runs `check` in a `synthetic.check` span and records
+/// `synthetic.check.success` and `synthetic.check.duration` for it.
+pub async fn run_check(name: &'static str, check: impl Future<Output = anyhow::Result<()>>) {
+    let span = tracing::info_span!(
+        "synthetic.check",
+        otel.name = %format!("synthetic.check {name}"),
+        synthetic.check.name = name,
+        otel.status_code = tracing::field::Empty,
+    );
+    let start = Instant::now();
+    let result = check.instrument(span.clone()).await;
+    let duration = start.elapsed().as_secs_f64() * 1000.0;
+
+    SYNTHETIC_CHECK_SUCCESS.record(
+        u64::from(result.is_ok()),
+        &[KeyValue::new("synthetic.check.name", name)],
+    );
+    SYNTHETIC_CHECK_DURATION.record(
+        duration,
+        &[
+            KeyValue::new("synthetic.check.name", name),
+            KeyValue::new(
+                "outcome",
+                if result.is_ok() { "success" } else { "failure" },
+            ),
+        ],
+    );
+
+    match result {
+        Ok(()) => {
+            span.record("otel.status_code", "OK");
+        }
+        Err(e) => {
+            span.record("otel.status_code", "ERROR");
+            span.in_scope(|| tracing::warn!(check = name, error = %e, "Synthetic check failed"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use opentelemetry::trace::Status;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::telemetry::testing::{SpanCapture, assert_attributes, assert_metric_recorded};
+
+    #[tokio::test]
+    async fn test_probe_records_each_check() {
+        let app = Router::new()
+            .route("/api/health", get(|| async { StatusCode::OK }))
+            .route(
+                "/api/articles",
+                get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let spans = SpanCapture::start();
+        SyntheticProbe::new(&format!("http://{addr}/"), Duration::from_secs(5))
+            .unwrap()
+            .run()
+            .await;
+
+        assert_eq!(spans.span("synthetic.check health").status, Status::Ok);
+        let failed = spans.span("synthetic.check articles.list");
+        assert!(matches!(failed.status, Status::Error { .. }));
+        assert_attributes(
+            &failed,
+            &[KeyValue::new("synthetic.check.name", "articles.list")],
+        );
+        assert_metric_recorded(
+            "synthetic.check.duration",
+            &[
+                KeyValue::new("synthetic.check.name", "articles.list"),
+                KeyValue::new("outcome", "failure"),
+            ],
+        );
+        assert_metric_recorded(
+            "synthetic.check.success",
+            &[KeyValue::new("synthetic.check.name", "health")],
+        );
+    }
+}
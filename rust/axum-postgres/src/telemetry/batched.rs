@@ -0,0 +1,219 @@
+//! Domain counters that can add up their increments per thread and hand
+//! the SDK one `add` per series and flush, instead of every increment
+//! taking the SDK's shared aggregation. Off unless
+//! `OTEL_METRICS_COUNTER_BATCH_MS` is set; then an increment is a
+//! thread-local lookup and an uncontended atomic add, and reaches the
+//! exporter up to that interval late.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::Counter;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+static BATCHING: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static COUNTERS: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// This thread's cells, by counter id.
+    static CELLS: RefCell<HashMap<usize, Vec<Arc<Cell>>>> = RefCell::new(HashMap::new());
+}
+
+/// One thread's increments of one series since the last flush.
+struct Cell {
+    attributes: Vec<KeyValue>,
+    count: AtomicU64,
+}
+
+struct Shared {
+    counter: Counter<u64>,
+    /// Every thread's cells, for the flush. Locked only to add a cell and
+    /// to flush, never to increment.
+    cells: Mutex<Vec<Arc<Cell>>>,
+}
+
+impl Shared {
+    fn flush(&self) {
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        cells.retain(|cell| {
+            // Checked before the swap: a cell only this list holds belongs
+            // to a thread that has exited, so nothing can add to it after.
+            let live = Arc::strong_count(cell) > 1;
+            let count = cell.count.swap(0, Ordering::Relaxed);
+            if count > 0 {
+                self.counter.add(count, &cell.attributes);
+            }
+            live
+        });
+    }
+}
+
+/// A `Counter<u64>` whose increments can be batched; `add` takes the same
+/// arguments, so call sites don't change.
+pub struct BatchedCounter {
+    id: usize,
+    shared: Arc<Shared>,
+}
+
+impl BatchedCounter {
+    pub fn new(counter: Counter<u64>) -> Self {
+        let shared = Arc::new(Shared {
+            counter,
+            cells: Mutex::new(Vec::new()),
+        });
+        COUNTERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::downgrade(&shared));
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            shared,
+        }
+    }
+
+    pub fn add(&self, value: u64, attributes: &[KeyValue]) {
+        if BATCHING.load(Ordering::Relaxed) {
+            self.batch(value, attributes);
+        } else {
+            self.shared.counter.add(value, attributes);
+        }
+    }
+
+    fn batch(&self, value: u64, attributes: &[KeyValue]) {
+        let batched = CELLS.try_with(|cells| {
+            let mut cells = cells.borrow_mut();
+            let cells = cells.entry(self.id).or_default();
+            let index = match cells.iter().position(|cell| cell.attributes == attributes) {
+                Some(index) => index,
+                None => {
+                    let cell = Arc::new(Cell {
+                        attributes: attributes.to_vec(),
+                        count: AtomicU64::new(0),
+                    });
+                    self.shared
+                        .cells
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(cell.clone());
+                    cells.push(cell);
+                    cells.len() - 1
+                }
+            };
+            cells[index].count.fetch_add(value, Ordering::Relaxed);
+        });
+        // The thread is exiting and its cells are gone.
+        if batched.is_err() {
+            self.shared.counter.add(value, attributes);
+        }
+    }
+
+    #[cfg(test)]
+    fn flush(&self) {
+        self.shared.flush();
+    }
+}
+
+/// Hands every counter's batched increments to the SDK. Called by the
+/// flusher and by `TelemetryGuard::shutdown`, before the last export.
+pub fn flush_counters() {
+    let counters: Vec<Arc<Shared>> = {
+        let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+        counters.retain(|counter| counter.strong_count() > 0);
+        counters.iter().filter_map(Weak::upgrade).collect()
+    };
+    for counter in counters {
+        counter.flush();
+    }
+}
+
+/// Turns batching on and flushes every `interval`.
+pub fn spawn_counter_flush(interval: Duration) -> JoinHandle<()> {
+    BATCHING.store(true, Ordering::Relaxed);
+    tokio::spawn(
+        async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                flush_counters();
+            }
+        }
+        .instrument(tracing::info_span!("metrics.counter_flusher")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::telemetry::METER;
+    use crate::telemetry::testing::{assert_metric_recorded, metric_sum};
+
+    const THREADS: u64 = 8;
+    const INCREMENTS: u64 = 10_000;
+
+    #[test]
+    fn test_batched_totals_match_the_increments() {
+        let counter = BatchedCounter::new(METER.u64_counter("test.batched.total").build());
+        let orgs = [KeyValue::new("org.id", 1), KeyValue::new("org.id", 2)];
+        let done = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            // Flushes race the increments, as the flusher's do.
+            let flusher = scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    counter.flush();
+                    thread::yield_now();
+                }
+            });
+            let adders: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        for i in 0..INCREMENTS {
+                            let org = &orgs[usize::from(i % 4 == 0)];
+                            counter.batch(1 + i % 2, std::slice::from_ref(org));
+                        }
+                    })
+                })
+                .collect();
+            for adder in adders {
+                adder.join().unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+            flusher.join().unwrap();
+        });
+        counter.flush();
+
+        // Per thread, org 2 gets every fourth increment: 2,500 of value 1.
+        // Org 1 gets 2,500 of value 1 and 5,000 of value 2.
+        assert_eq!(
+            metric_sum("test.batched.total", &orgs[1..]),
+            THREADS * 2_500
+        );
+        assert_eq!(
+            metric_sum("test.batched.total", &orgs[..1]),
+            THREADS * 12_500
+        );
+        // The threads have exited, so their cells are dropped.
+        assert!(counter.shared.cells.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_counters_add_inline_until_batching_starts() {
+        let counter = BatchedCounter::new(METER.u64_counter("test.inline.total").build());
+        counter.add(3, &[KeyValue::new("org.id", 7)]);
+
+        assert_metric_recorded("test.inline.total", &[KeyValue::new("org.id", 7)]);
+        assert_eq!(
+            metric_sum("test.inline.total", &[KeyValue::new("org.id", 7)]),
+            3
+        );
+    }
+}
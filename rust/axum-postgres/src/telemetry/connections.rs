@@ -0,0 +1,160 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::serve::Listener;
+use opentelemetry::KeyValue;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::{
+    HTTP_SERVER_ACCEPT_ERRORS, HTTP_SERVER_ACTIVE_CONNECTIONS, HTTP_SERVER_CONNECTION_DURATION,
+    HTTP_SERVER_CONNECTIONS_ACCEPTED,
+};
+
+/// A `TcpListener` for `axum::serve` that records each connection it hands
+/// out: accepted, open and for how long, plus accept failures, none of
+/// which the per-request HTTP metrics can see.
+pub struct MeteredListener {
+    inner: TcpListener,
+    attributes: [KeyValue; 1],
+}
+
+impl MeteredListener {
+    pub fn new(inner: TcpListener) -> io::Result<Self> {
+        let port = inner.local_addr()?.port();
+        Ok(Self {
+            inner,
+            attributes: [KeyValue::new("server.port", i64::from(port))],
+        })
+    }
+}
+
+impl Listener for MeteredListener {
+    type Io = MeteredStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            match self.inner.accept().await {
+                Ok((stream, addr)) => {
+                    HTTP_SERVER_CONNECTIONS_ACCEPTED.add(1, &self.attributes);
+                    HTTP_SERVER_ACTIVE_CONNECTIONS.add(1, &self.attributes);
+                    let stream = MeteredStream {
+                        inner: stream,
+                        opened: Instant::now(),
+                        attributes: self.attributes.clone(),
+                    };
+                    return (stream, addr);
+                }
+                Err(e) => {
+                    let mut attributes = self.attributes.to_vec();
+                    attributes.push(KeyValue::new("error.type", e.to_string()));
+                    HTTP_SERVER_ACCEPT_ERRORS.add(1, &attributes);
+                    // The same as axum's own listener: a connection that
+                    // went away before it was accepted is not our problem,
+                    // anything else (usually running out of file
+                    // descriptors) gets a pause before trying again.
+                    if !is_connection_error(&e) {
+                        tracing::error!(error = %e, "Failed to accept connection");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// An accepted connection, counted as active until hyper drops it.
+pub struct MeteredStream {
+    inner: TcpStream,
+    opened: Instant,
+    attributes: [KeyValue; 1],
+}
+
+impl Drop for MeteredStream {
+    fn drop(&mut self) {
+        HTTP_SERVER_ACTIVE_CONNECTIONS.add(-1, &self.attributes);
+        HTTP_SERVER_CONNECTION_DURATION.record(
+            self.opened.elapsed().as_secs_f64() * 1000.0,
+            &self.attributes,
+        );
+    }
+}
+
+impl AsyncRead for MeteredStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MeteredStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    #[tokio::test]
+    async fn test_records_accepted_active_and_closed_connections() {
+        let mut listener =
+            MeteredListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let port = [KeyValue::new("server.port", i64::from(addr.port()))];
+
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await;
+        assert_metric_recorded("http.server.connections.accepted", &port);
+        assert_metric_recorded("http.server.active_connections", &port);
+
+        drop(stream);
+        assert_metric_recorded("http.server.connection.duration", &port);
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use opentelemetry::{
+    Context, KeyValue,
+    trace::{Link, SpanKind, TraceId},
+};
+use opentelemetry_sdk::trace::{Sampler, SamplingResult, ShouldSample};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::error::{AppError, AppResult};
+
+pub const DEFAULT_LOG_FILTER: &str = "info,sqlx=warn,tower_http=debug";
+
+/// Trace-id ratio sampler whose ratio can change while the service runs.
+#[derive(Debug, Clone)]
+pub struct RatioSampler {
+    ratio: Arc<AtomicU64>,
+}
+
+impl RatioSampler {
+    pub fn new(ratio: f64) -> Self {
+        Self {
+            ratio: Arc::new(AtomicU64::new(ratio.to_bits())),
+        }
+    }
+
+    pub fn ratio(&self) -> f64 {
+        f64::from_bits(self.ratio.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, ratio: f64) {
+        self.ratio.store(ratio.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl ShouldSample for RatioSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        Sampler::TraceIdRatioBased(self.ratio()).should_sample(
+            parent_context,
+            trace_id,
+            name,
+            span_kind,
+            attributes,
+            links,
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelemetrySettings {
+    pub log_filter: String,
+    pub sample_ratio: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTelemetryInput {
+    pub log_filter: Option<String>,
+    pub sample_ratio: Option<f64>,
+}
+
+/// Runtime handle on the log filter and trace sampler installed by
+/// `init_telemetry`.
+#[derive(Clone)]
+pub struct TelemetryControl {
+    filter: reload::Handle<EnvFilter, Registry>,
+    log_filter: Arc<RwLock<String>>,
+    sampler: RatioSampler,
+}
+
+impl TelemetryControl {
+    pub fn new(
+        filter: reload::Handle<EnvFilter, Registry>,
+        log_filter: &str,
+        sampler: RatioSampler,
+    ) -> Self {
+        Self {
+            filter,
+            log_filter: Arc::new(RwLock::new(log_filter.to_string())),
+            sampler,
+        }
+    }
+
+    pub fn settings(&self) -> TelemetrySettings {
+        TelemetrySettings {
+            log_filter: self.log_filter.read().unwrap().clone(),
+            sample_ratio: self.sampler.ratio(),
+        }
+    }
+
+    /// Validates both fields before applying either, so a bad filter never
+    /// leaves the sampler half-updated.
+    pub fn update(&self, input: UpdateTelemetryInput) -> AppResult<TelemetrySettings> {
+        let filter = input
+            .log_filter
+            .map(|directives| parse_filter(&directives).map(|filter| (directives, filter)))
+            .transpose()?;
+        if let Some(ratio) = input.sample_ratio {
+            validate_ratio(ratio)?;
+        }
+
+        if let Some((directives, filter)) = filter {
+            self.filter
+                .reload(filter)
+                .map_err(|e| AppError::Internal(format!("Failed to reload log filter: {}", e)))?;
+            *self.log_filter.write().unwrap() = directives;
+        }
+        if let Some(ratio) = input.sample_ratio {
+            self.sampler.set(ratio);
+        }
+
+        let settings = self.settings();
+        tracing::info!(
+            log_filter = %settings.log_filter,
+            sample_ratio = settings.sample_ratio,
+            "Telemetry settings updated"
+        );
+
+        Ok(settings)
+    }
+}
+
+pub fn parse_filter(directives: &str) -> AppResult<EnvFilter> {
+    EnvFilter::try_new(directives)
+        .map_err(|e| AppError::Validation(format!("Invalid log filter: {}", e)))
+}
+
+fn validate_ratio(ratio: f64) -> AppResult<()> {
+    if (0.0..=1.0).contains(&ratio) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(
+            "sample_ratio must be between 0.0 and 1.0".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::trace::SamplingDecision;
+
+    use super::*;
+
+    fn sample(sampler: &RatioSampler) -> SamplingDecision {
+        sampler
+            .should_sample(
+                None,
+                TraceId::from(u128::MAX / 2),
+                "test",
+                &SpanKind::Internal,
+                &[],
+                &[],
+            )
+            .decision
+    }
+
+    #[test]
+    fn test_ratio_sampler_follows_updates() {
+        let sampler = RatioSampler::new(1.0);
+        assert_eq!(sample(&sampler), SamplingDecision::RecordAndSample);
+
+        sampler.set(0.0);
+        assert_eq!(sampler.ratio(), 0.0);
+        assert_eq!(sample(&sampler), SamplingDecision::Drop);
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        assert!(parse_filter("info,sqlx=warn").is_ok());
+        assert!(parse_filter("info,sqlx=loud").is_err());
+        assert!(validate_ratio(0.25).is_ok());
+        assert!(validate_ratio(1.5).is_err());
+    }
+}
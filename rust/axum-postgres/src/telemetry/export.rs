@@ -0,0 +1,248 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use opentelemetry::{Context, InstrumentationScope, KeyValue};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::logs::{LogBatch, LogExporter, LogProcessor, SdkLogRecord};
+use opentelemetry_sdk::trace::{Span, SpanData, SpanExporter, SpanProcessor};
+
+use super::metrics::{METER, OTEL_EXPORTER_DROPPED, OTEL_EXPORTER_EXPORTED};
+
+/// Tracks what sits in one batch processor's queue, so a falling-behind
+/// exporter shows up in `otel.exporter.queue.size` and
+/// `otel.exporter.dropped` rather than as gaps in the backend.
+#[derive(Debug, Clone)]
+pub struct ExportQueue(Arc<QueueState>);
+
+#[derive(Debug)]
+struct QueueState {
+    signal: &'static str,
+    capacity: usize,
+    queued: AtomicUsize,
+}
+
+impl ExportQueue {
+    /// `capacity` is the processor's `max_queue_size`.
+    pub fn new(signal: &'static str, capacity: usize) -> Self {
+        Self(Arc::new(QueueState {
+            signal,
+            capacity,
+            queued: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn size(&self) -> usize {
+        self.0.queued.load(Ordering::Acquire)
+    }
+
+    /// Counts an item handed to the processor, or a drop when the queue is
+    /// already full, since the processor discards it then.
+    fn enqueue(&self) {
+        let accepted = self
+            .0
+            .queued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                (queued < self.0.capacity).then_some(queued + 1)
+            })
+            .is_ok();
+        if !accepted {
+            OTEL_EXPORTER_DROPPED.add(1, &[KeyValue::new("signal", self.0.signal)]);
+        }
+    }
+
+    fn exported(&self, count: usize, result: &OTelSdkResult) {
+        let _ = self
+            .0
+            .queued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                Some(queued.saturating_sub(count))
+            });
+        OTEL_EXPORTER_EXPORTED.add(
+            count as u64,
+            &[
+                KeyValue::new("signal", self.0.signal),
+                KeyValue::new(
+                    "outcome",
+                    if result.is_ok() { "success" } else { "failure" },
+                ),
+            ],
+        );
+    }
+}
+
+/// Reports `otel.exporter.queue.size` and `otel.exporter.queue.capacity` for
+/// each queue. Call once the meter provider is installed.
+pub fn register_queue_gauges(queues: Vec<ExportQueue>) {
+    let sizes = queues.clone();
+    METER
+        .u64_observable_gauge("otel.exporter.queue.size")
+        .with_description("Spans or log records waiting in the batch processor")
+        .with_unit("{item}")
+        .with_callback(move |observer| {
+            for queue in &sizes {
+                observer.observe(
+                    queue.size() as u64,
+                    &[KeyValue::new("signal", queue.0.signal)],
+                );
+            }
+        })
+        .build();
+    METER
+        .u64_observable_gauge("otel.exporter.queue.capacity")
+        .with_description("Batch processor queue size limit")
+        .with_unit("{item}")
+        .with_callback(move |observer| {
+            for queue in &queues {
+                observer.observe(
+                    queue.0.capacity as u64,
+                    &[KeyValue::new("signal", queue.0.signal)],
+                );
+            }
+        })
+        .build();
+}
+
+/// Wraps a batch processor, and the exporter inside it, with the same
+/// [`ExportQueue`].
+#[derive(Debug)]
+pub struct Tracked<T> {
+    inner: T,
+    queue: ExportQueue,
+}
+
+impl<T> Tracked<T> {
+    pub fn new(inner: T, queue: ExportQueue) -> Self {
+        Self { inner, queue }
+    }
+}
+
+impl<P: SpanProcessor> SpanProcessor for Tracked<P> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        self.queue.enqueue();
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for Tracked<E> {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let count = batch.len();
+        let result = self.inner.export(batch).await;
+        self.queue.exported(count, &result);
+        result
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+impl<P: LogProcessor> LogProcessor for Tracked<P> {
+    fn emit(&self, data: &mut SdkLogRecord, instrumentation: &InstrumentationScope) {
+        self.queue.enqueue();
+        self.inner.emit(data, instrumentation);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+impl<E: LogExporter> LogExporter for Tracked<E> {
+    async fn export(&self, batch: LogBatch<'_>) -> OTelSdkResult {
+        let count = batch.iter().count();
+        let result = self.inner.export(batch).await;
+        self.queue.exported(count, &result);
+        result
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::trace::{
+        BatchConfigBuilder, BatchSpanProcessor, InMemorySpanExporter, SdkTracerProvider,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_queue_drains_on_export_and_counts_overflow() {
+        let queue = ExportQueue::new("traces", 2);
+        for _ in 0..3 {
+            queue.enqueue();
+        }
+        assert_eq!(queue.size(), 2);
+
+        queue.exported(2, &Ok(()));
+        assert_eq!(queue.size(), 0);
+        queue.exported(1, &Ok(()));
+        assert_eq!(queue.size(), 0);
+    }
+
+    #[test]
+    fn test_tracked_batch_processor_exports_spans() {
+        let queue = ExportQueue::new("traces", 16);
+        let exporter = InMemorySpanExporter::default();
+        let processor = BatchSpanProcessor::builder(Tracked::new(exporter.clone(), queue.clone()))
+            .with_batch_config(
+                BatchConfigBuilder::default()
+                    .with_max_queue_size(16)
+                    .build(),
+            )
+            .build();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(Tracked::new(processor, queue.clone()))
+            .build();
+
+        let tracer = provider.tracer("test");
+        tracer.in_span("first", |_| {});
+        tracer.in_span("second", |_| {});
+        assert_eq!(queue.size(), 2);
+
+        provider.force_flush().unwrap();
+        assert_eq!(exporter.get_finished_spans().unwrap().len(), 2);
+        assert_eq!(queue.size(), 0);
+    }
+}
@@ -1,95 +1,295 @@
-use opentelemetry::KeyValue;
 use opentelemetry::global;
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
 use opentelemetry_otlp::WithExportConfig;
-use opentelemetry_sdk::{Resource, logs::SdkLoggerProvider, trace::SdkTracerProvider};
+use opentelemetry_sdk::{
+    logs::{self, BatchLogProcessor, SdkLoggerProvider},
+    metrics::{PeriodicReader, SdkMeterProvider, Temporality},
+    trace::{self, BatchSpanProcessor, Sampler, SdkTracerProvider},
+};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
+use tracing::Subscriber;
 use tracing_opentelemetry::OpenTelemetryLayer;
-use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{
+    Layer, layer::SubscriberExt, registry::LookupSpan, reload, util::SubscriberInitExt,
+};
 
+use super::control::{RatioSampler, TelemetryControl, parse_filter};
+use super::correlation::{CorrelatedFormat, CorrelationLayer};
+use super::export::{ExportQueue, Tracked, register_queue_gauges};
+use super::otlp::ExporterAuth;
+use super::resource::resource;
 use crate::config::Config;
+use crate::database::{QueryPlanner, query_plans};
 
 pub struct TelemetryGuard {
     pub tracer_provider: SdkTracerProvider,
     pub logger_provider: SdkLoggerProvider,
+    pub meter_provider: SdkMeterProvider,
+    pub control: TelemetryControl,
+    /// Set with `QUERY_PLAN_THRESHOLD_MS`; spawn it once the pool exists.
+    pub query_planner: Option<QueryPlanner>,
 }
 
 impl TelemetryGuard {
     pub fn shutdown(&self) {
+        super::flush_counters();
         if let Err(e) = self.tracer_provider.shutdown() {
             eprintln!("Error shutting down tracer provider: {e}");
         }
         if let Err(e) = self.logger_provider.shutdown() {
             eprintln!("Error shutting down logger provider: {e}");
         }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Error shutting down meter provider: {e}");
+        }
     }
 }
 
 pub fn init_telemetry(config: &Config) -> anyhow::Result<TelemetryGuard> {
-    let resource = Resource::builder()
-        .with_service_name(config.otel_service_name.clone())
-        .with_attribute(KeyValue::new("service.version", "1.0.0"))
-        .with_attribute(KeyValue::new("service.namespace", "examples"))
-        .with_attribute(KeyValue::new(
-            "deployment.environment",
-            config.environment.clone(),
-        ))
-        .with_attribute(KeyValue::new("environment", config.environment.clone()))
+    if config.otel_sdk_disabled {
+        return init_without_sdk(config);
+    }
+
+    let resource = resource(config);
+    let auth = ExporterAuth::from_config(config)?;
+
+    // Metrics first, so the `LazyLock` instruments bind to this provider.
+    let metric_exporter = metric_exporter(
+        &config.otel_exporter_endpoint,
+        &auth,
+        config.metrics_temporality,
+    )?;
+
+    let metric_reader = PeriodicReader::builder(metric_exporter)
+        .with_interval(Duration::from_secs(15))
+        .build();
+
+    let meter_provider = config
+        .metric_views()
+        .map_err(anyhow::Error::msg)?
+        .register(SdkMeterProvider::builder())
+        .with_reader(metric_reader)
+        .with_resource(resource.clone())
         .build();
 
-    let trace_exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
+    global::set_meter_provider(meter_provider.clone());
+
+    let trace_exporter = auth
+        .apply(opentelemetry_otlp::SpanExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
 
+    let sampler = RatioSampler::new(config.trace_sample_ratio);
+
+    let span_queue = ExportQueue::new("traces", config.span_batch_queue_size);
+    let span_processor =
+        BatchSpanProcessor::builder(Tracked::new(trace_exporter, span_queue.clone()))
+            .with_batch_config(
+                trace::BatchConfigBuilder::default()
+                    .with_max_queue_size(config.span_batch_queue_size)
+                    .with_max_export_batch_size(config.span_batch_size)
+                    .with_scheduled_delay(Duration::from_millis(config.span_batch_delay_ms))
+                    .build(),
+            )
+            .build();
+
     let tracer_provider = SdkTracerProvider::builder()
-        .with_batch_exporter(trace_exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(sampler.clone())))
+        .with_span_processor(Tracked::new(span_processor, span_queue.clone()))
         .with_resource(resource.clone())
         .build();
 
     global::set_tracer_provider(tracer_provider.clone());
 
-    let log_exporter = opentelemetry_otlp::LogExporter::builder()
-        .with_tonic()
+    let log_exporter = auth
+        .apply(opentelemetry_otlp::LogExporter::builder().with_tonic())
         .with_endpoint(&config.otel_exporter_endpoint)
         .with_timeout(Duration::from_secs(10))
         .build()?;
 
+    let log_queue = ExportQueue::new("logs", config.log_batch_queue_size);
+    let log_processor = BatchLogProcessor::builder(Tracked::new(log_exporter, log_queue.clone()))
+        .with_batch_config(
+            logs::BatchConfigBuilder::default()
+                .with_max_queue_size(config.log_batch_queue_size)
+                .with_max_export_batch_size(config.log_batch_size)
+                .with_scheduled_delay(Duration::from_millis(config.log_batch_delay_ms))
+                .build(),
+        )
+        .build();
+
     let logger_provider = SdkLoggerProvider::builder()
-        .with_batch_exporter(log_exporter)
+        .with_log_processor(Tracked::new(log_processor, log_queue.clone()))
         .with_resource(resource)
         .build();
 
+    register_queue_gauges(vec![span_queue, log_queue]);
+
     let otel_log_layer = OpenTelemetryTracingBridge::new(&logger_provider);
 
     let tracer = global::tracer(config.otel_service_name.clone());
     let telemetry_layer = OpenTelemetryLayer::new(tracer);
 
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,sqlx=warn,tower_http=debug"));
+    // The filter sits behind a reload layer so `PUT /api/admin/telemetry`
+    // can swap it without restarting.
+    let (env_filter, filter_handle) = reload::Layer::new(parse_filter(&config.log_filter)?);
 
-    let fmt_layer = if config.is_production() {
-        tracing_subscriber::fmt::layer().json().boxed()
-    } else {
-        tracing_subscriber::fmt::layer().pretty().boxed()
-    };
+    let (query_plan_layer, query_planner) = query_plans(config);
 
     tracing_subscriber::registry()
         .with(env_filter)
         .with(telemetry_layer)
+        .with(CorrelationLayer::default())
+        .with(query_plan_layer)
         .with(otel_log_layer)
-        .with(fmt_layer)
+        .with(fmt_layer(config))
         .init();
 
     tracing::info!(
         service = %config.otel_service_name,
         endpoint = %config.otel_exporter_endpoint,
-        "Telemetry initialized with OTLP trace and log export"
+        sample_ratio = config.trace_sample_ratio,
+        "Telemetry initialized with OTLP trace, metric, and log export"
     );
 
     Ok(TelemetryGuard {
         tracer_provider,
         logger_provider,
+        meter_provider,
+        control: TelemetryControl::new(filter_handle, &config.log_filter, sampler),
+        query_planner,
+    })
+}
+
+/// `OTEL_SDK_DISABLED=true`: the same stdout logs and request IDs, but no
+/// spans, metrics or log records reach an SDK. The global providers stay the
+/// no-op ones, so instrumented code costs what it would without
+/// OpenTelemetry, which is what `cargo xtask bench` compares against.
+fn init_without_sdk(config: &Config) -> anyhow::Result<TelemetryGuard> {
+    let sampler = RatioSampler::new(config.trace_sample_ratio);
+    let (env_filter, filter_handle) = reload::Layer::new(parse_filter(&config.log_filter)?);
+    let (query_plan_layer, query_planner) = query_plans(config);
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(CorrelationLayer::default())
+        .with(query_plan_layer)
+        .with(fmt_layer(config))
+        .init();
+
+    tracing::info!(
+        service = %config.otel_service_name,
+        "Telemetry disabled by OTEL_SDK_DISABLED, nothing is exported"
+    );
+
+    Ok(TelemetryGuard {
+        tracer_provider: SdkTracerProvider::builder().build(),
+        logger_provider: SdkLoggerProvider::builder().build(),
+        meter_provider: SdkMeterProvider::builder().build(),
+        control: TelemetryControl::new(filter_handle, &config.log_filter, sampler),
+        query_planner,
     })
 }
+
+fn fmt_layer<S>(config: &Config) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let service_name = config.otel_service_name.clone();
+    if config.is_production() {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .map_event_format(|f| CorrelatedFormat::json(f, service_name))
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer()
+            .pretty()
+            .map_event_format(|f| CorrelatedFormat::text(f, service_name))
+            .boxed()
+    }
+}
+
+/// `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE`. Backends that only
+/// ingest deltas, such as some Scout ingestion paths, need `delta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsTemporality {
+    Cumulative,
+    Delta,
+    LowMemory,
+}
+
+impl FromStr for MetricsTemporality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cumulative" => Ok(Self::Cumulative),
+            "delta" => Ok(Self::Delta),
+            "lowmemory" => Ok(Self::LowMemory),
+            other => Err(format!(
+                "expected cumulative, delta or lowmemory, got {other:?}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MetricsTemporality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cumulative => "cumulative",
+            Self::Delta => "delta",
+            Self::LowMemory => "lowmemory",
+        })
+    }
+}
+
+impl From<MetricsTemporality> for Temporality {
+    fn from(temporality: MetricsTemporality) -> Self {
+        match temporality {
+            MetricsTemporality::Cumulative => Temporality::Cumulative,
+            MetricsTemporality::Delta => Temporality::Delta,
+            MetricsTemporality::LowMemory => Temporality::LowMemory,
+        }
+    }
+}
+
+fn metric_exporter(
+    endpoint: &str,
+    auth: &ExporterAuth,
+    temporality: MetricsTemporality,
+) -> anyhow::Result<opentelemetry_otlp::MetricExporter> {
+    Ok(auth
+        .apply(opentelemetry_otlp::MetricExporter::builder().with_tonic())
+        .with_endpoint(endpoint)
+        .with_timeout(Duration::from_secs(10))
+        .with_temporality(temporality.into())
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metric_exporter_uses_temporality_preference() {
+        for (preference, expected) in [
+            ("cumulative", Temporality::Cumulative),
+            ("DELTA", Temporality::Delta),
+        ] {
+            let temporality = preference.parse().unwrap();
+            let exporter = metric_exporter(
+                "http://localhost:4317",
+                &ExporterAuth::default(),
+                temporality,
+            )
+            .unwrap();
+            assert_eq!(exporter.temporality(), expected);
+        }
+        assert!("sum".parse::<MetricsTemporality>().is_err());
+    }
+}
@@ -1,10 +1,23 @@
 use opentelemetry::{
-    global,
-    metrics::{Counter, Histogram, Meter},
+    KeyValue, global,
+    metrics::{Counter, Gauge, Histogram, Meter, UpDownCounter},
 };
 use std::sync::LazyLock;
 
-pub static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("rust-axum-postgres"));
+use super::batched::BatchedCounter;
+use crate::models::OrgId;
+
+pub static METER: LazyLock<Meter> = LazyLock::new(|| {
+    #[cfg(test)]
+    super::testing::install_meter_provider();
+    global::meter("rust-axum-postgres")
+});
+
+/// The per-tenant attribute on domain counters. Tenants are created by an
+/// admin, so the cardinality is bounded by the number of organizations.
+pub fn org_attribute(org: OrgId) -> KeyValue {
+    KeyValue::new("org.id", i64::from(org.0))
+}
 
 pub static HTTP_REQUESTS_TOTAL: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
@@ -25,45 +38,216 @@ pub static HTTP_REQUEST_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
         .build()
 });
 
-pub static ARTICLES_CREATED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static HTTP_SERVER_CONNECTIONS_ACCEPTED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("http.server.connections.accepted")
+        .with_description("TCP connections accepted by the HTTP server")
+        .with_unit("{connection}")
+        .build()
+});
+
+pub static HTTP_SERVER_ACCEPT_ERRORS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("http.server.accept.errors")
+        .with_description("Failed accepts on the HTTP listener, by error.type")
+        .with_unit("{error}")
+        .build()
+});
+
+pub static HTTP_SERVER_ACTIVE_CONNECTIONS: LazyLock<UpDownCounter<i64>> = LazyLock::new(|| {
+    METER
+        .i64_up_down_counter("http.server.active_connections")
+        .with_description("TCP connections open to the HTTP server, idle keep-alives included")
+        .with_unit("{connection}")
+        .build()
+});
+
+pub static HTTP_SERVER_CONNECTION_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("http.server.connection.duration")
+        .with_description("How long TCP connections to the HTTP server stay open")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            10.0, 100.0, 1000.0, 5000.0, 15000.0, 30000.0, 60000.0, 300000.0, 900000.0,
+        ])
+        .build()
+});
+
+pub static TLS_HANDSHAKE_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("tls.handshake.duration")
+        .with_description(
+            "Time from accepting a connection to finishing its TLS handshake, failed ones by error.type",
+        )
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0,
+        ])
+        .build()
+});
+
+pub static HTTP_REQUESTS_SHED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("http.requests.shed")
+        .with_description("Low-priority requests answered 503 because the server was overloaded")
+        .with_unit("{request}")
+        .build()
+});
+
+pub static ARTICLES_CREATED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("articles.created")
+            .with_description("Total articles created")
+            .build(),
+    )
+});
+
+pub static ARTICLES_UPDATED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("articles.updated")
+            .with_description("Total articles updated")
+            .build(),
+    )
+});
+
+pub static ARTICLES_DELETED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("articles.deleted")
+            .with_description("Total articles deleted")
+            .build(),
+    )
+});
+
+pub static ARTICLES_TRANSFERRED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("articles.transferred")
+            .with_description("Total article ownership transfers")
+            .build(),
+    )
+});
+
+pub static ARTICLES_VIEWED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("articles.viewed")
+            .with_description("Total article views")
+            .with_unit("{view}")
+            .build(),
+    )
+});
+
+pub static ARTICLE_VIEWS_ROWS_WRITTEN: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("articles.viewed.rows_written")
+        .with_description("Article rows updated by view buffer flushes")
+        .with_unit("{row}")
+        .build()
+});
+
+pub static ARTICLES_ENRICHED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("articles.enriched")
+        .with_description("Articles given a reading time by the enrichment job, by summary outcome")
+        .with_unit("{article}")
+        .build()
+});
+
+pub static ARTICLES_MARKDOWN_RENDERED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("articles.markdown.rendered")
+        .with_description("Article bodies presented as HTML, by whether the render was cached")
+        .with_unit("{article}")
+        .build()
+});
+
+pub static FAVORITES_ADDED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("favorites.added")
+            .with_description("Total favorites added")
+            .build(),
+    )
+});
+
+pub static FAVORITES_REMOVED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("favorites.removed")
+            .with_description("Total favorites removed")
+            .build(),
+    )
+});
+
+pub static USERS_REGISTERED: LazyLock<BatchedCounter> = LazyLock::new(|| {
+    BatchedCounter::new(
+        METER
+            .u64_counter("users.registered")
+            .with_description("Total users registered")
+            .build(),
+    )
+});
+
+pub static USERS_DELETED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("users.deleted")
+        .with_description("Total accounts deleted")
+        .build()
+});
+
+pub static PASSWORD_REHASHES: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("auth.password.rehash")
+        .with_description("Password hashes upgraded to the configured parameters at login")
+        .build()
+});
+
+pub static SESSIONS_CREATED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("articles.created")
-        .with_description("Total articles created")
+        .u64_counter("auth.sessions.created")
+        .with_description("Sessions started by a login or registration")
+        .with_unit("{session}")
         .build()
 });
 
-pub static ARTICLES_UPDATED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static SESSIONS_REVOKED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("articles.updated")
-        .with_description("Total articles updated")
+        .u64_counter("auth.sessions.revoked")
+        .with_description("Sessions ended before they expired, by reason")
+        .with_unit("{session}")
         .build()
 });
 
-pub static ARTICLES_DELETED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static SESSIONS_ACTIVE: LazyLock<Gauge<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("articles.deleted")
-        .with_description("Total articles deleted")
+        .u64_gauge("auth.sessions.active")
+        .with_description("Sessions neither expired nor revoked, per organization")
+        .with_unit("{session}")
         .build()
 });
 
-pub static FAVORITES_ADDED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static AUTH_JWT_CACHE_LOOKUPS: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("favorites.added")
-        .with_description("Total favorites added")
+        .u64_counter("auth.jwt_cache.lookups")
+        .with_description("JWT validations answered from the verified token cache, by hit or miss")
         .build()
 });
 
-pub static FAVORITES_REMOVED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static AUTHZ_DENIED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("favorites.removed")
-        .with_description("Total favorites removed")
+        .u64_counter("authz.denied")
+        .with_description("Requests the authorization policy refused, by rule and decision")
         .build()
 });
 
-pub static USERS_REGISTERED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static SECURITY_EVENTS: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("users.registered")
-        .with_description("Total users registered")
+        .u64_counter("security.events")
+        .with_description("Login anomaly signals raised, by security.event")
+        .with_unit("{event}")
         .build()
 });
 
@@ -74,16 +258,123 @@ pub static JOBS_ENQUEUED: LazyLock<Counter<u64>> = LazyLock::new(|| {
         .build()
 });
 
-pub static JOBS_COMPLETED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static WEBHOOKS_RECEIVED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("webhooks.received")
+        .with_description("Inbound webhook deliveries, by webhook.source and outcome")
+        .with_unit("{delivery}")
+        .build()
+});
+
+pub static HTTP_RESPONSE_STREAMED_BYTES: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("http.response.streamed_bytes")
+        .with_description("Total bytes written to streamed HTTP responses")
+        .with_unit("By")
+        .build()
+});
+
+pub static IMAGES_UPLOADED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("images.uploaded")
+        .with_description("Total images uploaded")
+        .build()
+});
+
+pub static FEATURE_FLAG_EVALUATIONS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("feature_flag.evaluations")
+        .with_description("Total feature flag evaluations")
+        .build()
+});
+
+pub static AUDIT_WRITE_ERRORS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("audit.write.errors")
+        .with_description("Audit log entries that failed to write")
+        .with_unit("{entry}")
+        .build()
+});
+
+pub static DB_TENANT_SCOPE_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("db.tenant.scope.duration")
+        .with_description(
+            "Time spent beginning, scoping and committing row-level security tenant transactions",
+        )
+        .with_unit("ms")
+        .with_boundaries(vec![
+            0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0,
+        ])
+        .build()
+});
+
+pub static DB_RETRIES: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("db.retries")
+        .with_description("Repository calls run again after a serialization failure or deadlock")
+        .build()
+});
+
+/// Fine-grained around the 30ms budget in `services::suggest`, rather than
+/// reusing `http.request.duration`'s boundaries.
+pub static DB_QUERY_PLANS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("db.query.plans")
+        .with_description("Slow queries considered for plan capture, by outcome")
+        .build()
+});
+
+pub static ARTICLES_SUGGEST_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("articles.suggest.duration")
+        .with_description("Time to answer an article title suggestion, by where it came from")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 2.0, 5.0, 10.0, 15.0, 20.0, 25.0, 30.0, 40.0, 50.0, 75.0, 100.0, 250.0,
+        ])
+        .build()
+});
+
+pub static OTEL_EXPORTER_EXPORTED: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("jobs.completed")
-        .with_description("Total jobs completed successfully")
+        .u64_counter("otel.exporter.exported")
+        .with_description("Spans and log records passed to the OTLP exporter, by outcome")
+        .with_unit("{item}")
+        .build()
+});
+
+pub static OTEL_EXPORTER_DROPPED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("otel.exporter.dropped")
+        .with_description("Spans and log records dropped because the export queue was full")
+        .with_unit("{item}")
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_SUCCESS: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("synthetic.check.success")
+        .with_description(
+            "1 if the last synthetic check of the service's own critical path passed, else 0",
+        )
+        .build()
+});
+
+pub static SYNTHETIC_CHECK_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    METER
+        .f64_histogram("synthetic.check.duration")
+        .with_description("Synthetic check duration in milliseconds")
+        .with_unit("ms")
+        .with_boundaries(vec![
+            1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+        ])
         .build()
 });
 
-pub static JOBS_FAILED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+pub static LEADERSHIP_IS_LEADER: LazyLock<Gauge<u64>> = LazyLock::new(|| {
     METER
-        .u64_counter("jobs.failed")
-        .with_description("Total jobs failed")
+        .u64_gauge("leadership.is_leader")
+        .with_description("1 while this replica leads the election, by leadership.name, else 0")
         .build()
 });
@@ -1,5 +1,25 @@
+mod batched;
+mod connections;
+mod control;
+mod correlation;
+mod export;
+pub mod http;
 mod init;
 mod metrics;
+mod otlp;
+mod resource;
+#[cfg(test)]
+pub mod testing;
+mod views;
 
-pub use init::{TelemetryGuard, init_telemetry};
+pub use batched::{flush_counters, spawn_counter_flush};
+pub use connections::{MeteredListener, MeteredStream};
+pub use control::{
+    DEFAULT_LOG_FILTER, TelemetryControl, TelemetrySettings, UpdateTelemetryInput, parse_filter,
+};
+pub use correlation::current_request_id;
+pub use http::HttpMetricsLayer;
+pub use init::{MetricsTemporality, TelemetryGuard, init_telemetry};
 pub use metrics::*;
+pub use otlp::parse_headers;
+pub use views::MetricViews;
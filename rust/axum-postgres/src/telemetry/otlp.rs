@@ -0,0 +1,168 @@
+use anyhow::Context;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry_otlp::WithTonicConfig;
+use opentelemetry_otlp::tonic_types::metadata::MetadataMap;
+use opentelemetry_otlp::tonic_types::transport::{Certificate, ClientTlsConfig, Identity};
+
+use crate::config::Config;
+
+/// The headers and TLS settings every OTLP exporter is built with, so the
+/// service can export straight to an authenticated endpoint such as Scout's
+/// (`x-scout-api-key` over mTLS) instead of through a local collector.
+#[derive(Debug, Clone, Default)]
+pub struct ExporterAuth {
+    headers: HeaderMap,
+    tls: Option<ClientTlsConfig>,
+}
+
+impl ExporterAuth {
+    /// Reads the CA bundle and client certificate files, so a bad path fails
+    /// startup rather than every export.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let headers = parse_headers(&config.otel_exporter_headers)
+            .map_err(|e| anyhow::anyhow!("OTEL_EXPORTER_OTLP_HEADERS {e}"))?;
+        if !config.otel_exporter_endpoint.starts_with("https://") {
+            return Ok(Self { headers, tls: None });
+        }
+
+        let mut tls = ClientTlsConfig::new().with_enabled_roots();
+        if !config.otel_exporter_certificate.is_empty() {
+            let ca = read_pem(
+                "OTEL_EXPORTER_OTLP_CERTIFICATE",
+                &config.otel_exporter_certificate,
+            )?;
+            tls = tls.ca_certificate(Certificate::from_pem(ca));
+        }
+        if !config.otel_exporter_client_certificate.is_empty() {
+            let cert = read_pem(
+                "OTEL_EXPORTER_OTLP_CLIENT_CERTIFICATE",
+                &config.otel_exporter_client_certificate,
+            )?;
+            let key = read_pem(
+                "OTEL_EXPORTER_OTLP_CLIENT_KEY",
+                &config.otel_exporter_client_key,
+            )?;
+            tls = tls.identity(Identity::from_pem(cert, key));
+        }
+        Ok(Self {
+            headers,
+            tls: Some(tls),
+        })
+    }
+
+    pub fn apply<B: WithTonicConfig>(&self, builder: B) -> B {
+        let builder = builder.with_metadata(MetadataMap::from_headers(self.headers.clone()));
+        match &self.tls {
+            Some(tls) => builder.with_tls_config(tls.clone()),
+            None => builder,
+        }
+    }
+}
+
+fn read_pem(key: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("{key}: cannot read {path}"))
+}
+
+/// Parses `OTEL_EXPORTER_OTLP_HEADERS`: comma-separated `key=value` pairs with
+/// percent-encoded values. Errors name the header but never echo its value,
+/// since that is usually an API key.
+pub fn parse_headers(raw: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for entry in raw.split(',').filter(|e| !e.trim().is_empty()) {
+        let Some((key, value)) = entry.split_once('=') else {
+            return Err("must be comma-separated key=value pairs".to_string());
+        };
+        let key = key.trim();
+        let name = HeaderName::from_bytes(key.to_ascii_lowercase().as_bytes())
+            .map_err(|_| format!("has an invalid header name {key:?}"))?;
+        let value = percent_decode(value.trim())
+            .and_then(|v| HeaderValue::from_bytes(&v).ok())
+            .ok_or_else(|| format!("has an invalid value for {key:?}"))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let mut bytes = value.bytes();
+    let mut decoded = Vec::with_capacity(value.len());
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::Layers;
+
+    fn config(env: &[(&str, &str)]) -> Config {
+        let env = [
+            ("DATABASE_URL", "postgres://localhost/db"),
+            ("JWT_SECRET", "test-secret"),
+        ]
+        .iter()
+        .chain(env)
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        Config::from_layers(Layers::from_parts(env, HashMap::new(), &[]), None).unwrap()
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers(" X-Scout-Api-Key = abc%3D%3D ,tenant=shop,").unwrap();
+        assert_eq!(headers["x-scout-api-key"], "abc==");
+        assert_eq!(headers["tenant"], "shop");
+        assert!(parse_headers("").unwrap().is_empty());
+
+        for (raw, message) in [
+            ("x-scout-api-key", "must be comma-separated key=value pairs"),
+            ("bad key=s3cret", "has an invalid header name \"bad key\""),
+            (
+                "x-scout-api-key=s3cret%zz",
+                "has an invalid value for \"x-scout-api-key\"",
+            ),
+        ] {
+            assert_eq!(parse_headers(raw).unwrap_err(), message);
+        }
+    }
+
+    #[test]
+    fn test_tls_only_for_https_endpoints() {
+        let auth = ExporterAuth::from_config(&config(&[(
+            "OTEL_EXPORTER_OTLP_HEADERS",
+            "x-scout-api-key=s3cret",
+        )]))
+        .unwrap();
+        assert_eq!(auth.headers["x-scout-api-key"], "s3cret");
+        assert!(auth.tls.is_none());
+
+        let auth = ExporterAuth::from_config(&config(&[(
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            "https://otlp.example.com:4317",
+        )]))
+        .unwrap();
+        assert!(auth.tls.is_some());
+
+        let err = ExporterAuth::from_config(&config(&[
+            (
+                "OTEL_EXPORTER_OTLP_ENDPOINT",
+                "https://otlp.example.com:4317",
+            ),
+            ("OTEL_EXPORTER_OTLP_CERTIFICATE", "/nonexistent/ca.pem"),
+        ]))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "OTEL_EXPORTER_OTLP_CERTIFICATE: cannot read /nonexistent/ca.pem"
+        );
+    }
+}
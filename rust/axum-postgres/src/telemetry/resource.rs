@@ -0,0 +1,177 @@
+use opentelemetry::KeyValue;
+use opentelemetry_resource_detectors::{
+    ContainerResourceDetector, HostResourceDetector, OsResourceDetector, ProcessResourceDetector,
+    ServiceInstanceIdResourceDetector,
+};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::resource::{EnvResourceDetector, ResourceDetector};
+
+use crate::config::Config;
+
+/// Kubernetes downward-API variables, set in the pod spec from
+/// `metadata.namespace`, `metadata.name`, `metadata.uid` and `spec.nodeName`.
+const K8S_ENV: &[(&str, &str)] = &[
+    ("K8S_NAMESPACE_NAME", "k8s.namespace.name"),
+    ("K8S_POD_NAME", "k8s.pod.name"),
+    ("K8S_POD_UID", "k8s.pod.uid"),
+    ("K8S_NODE_NAME", "k8s.node.name"),
+];
+
+/// The service identity from config plus what can be detected about where the
+/// process runs: host, OS, process, container id, k8s pod and a generated
+/// `service.instance.id`, so each replica is its own instance.
+/// `OTEL_RESOURCE_ATTRIBUTES` still overrides anything detected.
+pub fn resource(config: &Config) -> Resource {
+    Resource::builder()
+        .with_detectors(&[
+            Box::new(FallbackDetector::from_env()),
+            Box::new(HostResourceDetector::default()),
+            Box::new(OsResourceDetector),
+            Box::new(ProcessResourceDetector),
+            Box::new(ContainerResourceDetector),
+            Box::new(ServiceInstanceIdResourceDetector),
+            Box::new(EnvResourceDetector::new()),
+        ])
+        .with_service_name(config.otel_service_name.clone())
+        .with_attribute(KeyValue::new("service.version", "1.0.0"))
+        .with_attribute(KeyValue::new("service.namespace", "examples"))
+        .with_attribute(KeyValue::new(
+            "deployment.environment",
+            config.environment.clone(),
+        ))
+        .with_attribute(KeyValue::new("environment", config.environment.clone()))
+        .build()
+}
+
+/// Attributes the bundled detectors don't cover: k8s metadata from the
+/// downward API, `os.name`/`os.version` from `/etc/os-release`, and a
+/// `host.name` for images without a `hostname` binary (the host detector's
+/// value wins when it has one).
+struct FallbackDetector {
+    env: Vec<(String, String)>,
+    os_release: Option<String>,
+    hostname: Option<String>,
+}
+
+impl FallbackDetector {
+    fn from_env() -> Self {
+        Self {
+            env: std::env::vars().collect(),
+            os_release: std::fs::read_to_string("/etc/os-release").ok(),
+            hostname: std::fs::read_to_string("/etc/hostname").ok(),
+        }
+    }
+
+    fn var(&self, key: &str) -> Option<&str> {
+        self.env
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn attributes(&self) -> Vec<KeyValue> {
+        let mut attributes: Vec<KeyValue> = K8S_ENV
+            .iter()
+            .filter_map(|(var, key)| Some(KeyValue::new(*key, self.var(var)?.to_string())))
+            .collect();
+
+        let os_release = self.os_release.as_deref().unwrap_or_default();
+        for (field, key) in [("NAME", "os.name"), ("VERSION_ID", "os.version")] {
+            let value = os_release.lines().find_map(|line| {
+                let value = line.strip_prefix(field)?.strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            });
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                attributes.push(KeyValue::new(key, value));
+            }
+        }
+
+        let hostname = self
+            .hostname
+            .as_deref()
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .or_else(|| self.var("HOSTNAME"));
+        if let Some(hostname) = hostname {
+            attributes.push(KeyValue::new("host.name", hostname.to_string()));
+        }
+        attributes
+    }
+}
+
+impl ResourceDetector for FallbackDetector {
+    fn detect(&self) -> Resource {
+        Resource::builder_empty()
+            .with_attributes(self.attributes())
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use opentelemetry::{Key, Value};
+
+    use super::*;
+    use crate::config::Layers;
+
+    fn attributes(detector: &FallbackDetector) -> HashMap<String, String> {
+        detector
+            .attributes()
+            .into_iter()
+            .map(|kv| (kv.key.to_string(), kv.value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fallback_detects_k8s_os_and_host() {
+        let detector = FallbackDetector {
+            env: vec![
+                ("K8S_NAMESPACE_NAME".to_string(), "shop".to_string()),
+                ("K8S_POD_NAME".to_string(), "api-7d9f-x2k4q".to_string()),
+                ("K8S_NODE_NAME".to_string(), " ".to_string()),
+                ("HOSTNAME".to_string(), "api-7d9f-x2k4q".to_string()),
+            ],
+            os_release: Some("NAME=\"Debian GNU/Linux\"\nVERSION_ID=\"12\"\n".to_string()),
+            hostname: None,
+        };
+
+        let attributes = attributes(&detector);
+        assert_eq!(attributes["k8s.namespace.name"], "shop");
+        assert_eq!(attributes["k8s.pod.name"], "api-7d9f-x2k4q");
+        assert!(!attributes.contains_key("k8s.node.name"));
+        assert_eq!(attributes["os.name"], "Debian GNU/Linux");
+        assert_eq!(attributes["os.version"], "12");
+        assert_eq!(attributes["host.name"], "api-7d9f-x2k4q");
+    }
+
+    #[test]
+    fn test_resource_has_detected_and_configured_attributes() {
+        let env = [
+            ("DATABASE_URL", "postgres://localhost/db"),
+            ("JWT_SECRET", "test-secret"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let config =
+            Config::from_layers(Layers::from_parts(env, HashMap::new(), &[]), None).unwrap();
+
+        let detected = resource(&config);
+        let get = |key: &'static str| detected.get(&Key::from_static_str(key));
+        assert_eq!(get("service.name"), Some(Value::from("rust-axum-postgres")));
+        assert_eq!(get("os.type"), Some(Value::from(std::env::consts::OS)));
+        assert_eq!(
+            get("process.pid"),
+            Some(Value::I64(std::process::id() as i64))
+        );
+        let instance_id = get("service.instance.id").expect("service.instance.id");
+        // Generated once per process, so every provider shares it.
+        assert_eq!(
+            resource(&config).get(&Key::from_static_str("service.instance.id")),
+            Some(instance_id)
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use axum::{
+    Router,
+    http::{HeaderMap, Uri, header::HOST, uri::Authority},
+    response::{IntoResponse, Redirect},
+    serve::Listener,
+};
+use opentelemetry::KeyValue;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{self, ProtocolVersion, ServerConfig, crypto::ring};
+use tokio_rustls::{Accept, TlsAcceptor, server::TlsStream};
+
+use crate::error::AppError;
+use crate::telemetry::{MeteredListener, MeteredStream, TLS_HANDSHAKE_DURATION};
+
+/// The rustls server config for `TLS_CERT_PATH` (PEM certificate chain,
+/// leaf first) and `TLS_KEY_PATH` (PEM private key, PKCS#8, PKCS#1 or SEC1).
+/// ALPN offers `h2` ahead of `http/1.1` when `http2` is set.
+pub fn load_config(
+    cert_path: &str,
+    key_path: &str,
+    http2: bool,
+) -> anyhow::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("TLS_CERT_PATH {cert_path} is not a PEM certificate chain"))?;
+    if certs.is_empty() {
+        anyhow::bail!("TLS_CERT_PATH {cert_path} has no certificates");
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("TLS_KEY_PATH {key_path} is not a PEM private key"))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("TLS_KEY_PATH does not match the TLS_CERT_PATH certificate")?;
+    config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok(Arc::new(config))
+}
+
+/// Serves HTTPS on a [`MeteredListener`]. The handshake runs when hyper
+/// first reads from the connection, in that connection's own task, so a
+/// slow or silent client never holds up `accept`.
+pub struct TlsListener {
+    inner: MeteredListener,
+    acceptor: TlsAcceptor,
+    attributes: [KeyValue; 1],
+}
+
+impl TlsListener {
+    pub fn new(inner: MeteredListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let port = inner.local_addr()?.port();
+        Ok(Self {
+            inner,
+            acceptor: TlsAcceptor::from(config),
+            attributes: [KeyValue::new("server.port", i64::from(port))],
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsConnection;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (stream, addr) = self.inner.accept().await;
+        let connection = TlsConnection {
+            state: State::Handshaking(Box::new(self.acceptor.accept(stream))),
+            accepted: Instant::now(),
+            attributes: self.attributes.clone(),
+        };
+        (connection, addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+pub struct TlsConnection {
+    state: State,
+    accepted: Instant,
+    attributes: [KeyValue; 1],
+}
+
+enum State {
+    Handshaking(Box<Accept<MeteredStream>>),
+    Ready(Box<TlsStream<MeteredStream>>),
+    Failed,
+}
+
+impl TlsConnection {
+    /// Drives the handshake to completion, recording how it went in
+    /// `tls.handshake.duration`, then hands out the decrypted stream.
+    fn poll_stream(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&mut TlsStream<MeteredStream>>> {
+        if let State::Handshaking(accept) = &mut self.state {
+            let result = ready!(Pin::new(accept.as_mut()).poll(cx));
+            let mut attributes = self.attributes.to_vec();
+            let elapsed_ms = self.accepted.elapsed().as_secs_f64() * 1000.0;
+            match result {
+                Ok(stream) => {
+                    let version = match stream.get_ref().1.protocol_version() {
+                        Some(ProtocolVersion::TLSv1_3) => "1.3",
+                        Some(ProtocolVersion::TLSv1_2) => "1.2",
+                        _ => "unknown",
+                    };
+                    attributes.push(KeyValue::new("tls.protocol.version", version));
+                    TLS_HANDSHAKE_DURATION.record(elapsed_ms, &attributes);
+                    self.state = State::Ready(Box::new(stream));
+                }
+                Err(e) => {
+                    attributes.push(KeyValue::new("error.type", handshake_error_type(&e)));
+                    TLS_HANDSHAKE_DURATION.record(elapsed_ms, &attributes);
+                    tracing::debug!(error = %e, "TLS handshake failed");
+                    self.state = State::Failed;
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+        match &mut self.state {
+            State::Ready(stream) => Poll::Ready(Ok(stream)),
+            _ => Poll::Ready(Err(io::ErrorKind::NotConnected.into())),
+        }
+    }
+}
+
+/// The rustls error when there is one (`AlertReceived(UnknownCA)` from a
+/// client that doesn't trust the certificate, `PeerIncompatible(..)`),
+/// otherwise the I/O error kind, usually `UnexpectedEof` from a client that
+/// hung up or a port scanner.
+fn handshake_error_type(e: &io::Error) -> String {
+    match e
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+    {
+        Some(tls_error) => format!("{tls_error:?}"),
+        None => format!("{:?}", e.kind()),
+    }
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_stream(cx))?;
+        Pin::new(stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let stream = ready!(self.get_mut().poll_stream(cx))?;
+        Pin::new(stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().state {
+            State::Ready(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().state {
+            State::Ready(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// Served on `HTTP_REDIRECT_PORT`: every request gets a permanent redirect
+/// to the same URL over HTTPS on `https_port`. 308 rather than 301 so a
+/// POST is repeated as a POST.
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        match https_location(https_port, &headers, &uri) {
+            Some(location) => Redirect::permanent(&location).into_response(),
+            None => {
+                AppError::Validation("A valid Host header is required".to_string()).into_response()
+            }
+        }
+    })
+}
+
+fn https_location(https_port: u16, headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    let host = headers
+        .get(HOST)?
+        .to_str()
+        .ok()?
+        .parse::<Authority>()
+        .ok()?;
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    Some(match https_port {
+        443 => format!("https://{}{path}", host.host()),
+        port => format!("https://{}:{port}{path}", host.host()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, http::StatusCode, routing::get};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::TlsConnector;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore, pki_types::ServerName};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::telemetry::testing::assert_metric_recorded;
+
+    /// A self-signed certificate for `localhost`, written to a temporary
+    /// directory as `TLS_CERT_PATH` and `TLS_KEY_PATH` would point to.
+    fn write_certificate(name: &str) -> (String, String, CertificateDer<'static>) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = std::env::temp_dir().join(format!("tls-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+        (
+            cert_path.display().to_string(),
+            key_path.display().to_string(),
+            certified.cert.der().clone(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_and_records_the_handshake() {
+        let (cert_path, key_path, cert) = write_certificate("serve");
+        let config = load_config(&cert_path, &key_path, false).unwrap();
+        let listener = TlsListener::new(
+            MeteredListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap()).unwrap(),
+            config,
+        )
+        .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(|| async { "secure" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).unwrap();
+        let client = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut tls = TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+        tls.write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        tls.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("secure"), "{response}");
+        assert_metric_recorded(
+            "tls.handshake.duration",
+            &[
+                KeyValue::new("server.port", i64::from(addr.port())),
+                KeyValue::new("tls.protocol.version", "1.3"),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_fails_the_handshake() {
+        let (cert_path, key_path, _) = write_certificate("plain");
+        let mut listener = TlsListener::new(
+            MeteredListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap()).unwrap(),
+            load_config(&cert_path, &key_path, false).unwrap(),
+        )
+        .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let (mut connection, _) = listener.accept().await;
+        let mut buf = [0; 16];
+        let mut buf = ReadBuf::new(&mut buf);
+        let result =
+            std::future::poll_fn(|cx| Pin::new(&mut connection).poll_read(cx, &mut buf)).await;
+
+        assert!(result.is_err());
+        assert_metric_recorded(
+            "tls.handshake.duration",
+            &[
+                KeyValue::new("server.port", i64::from(addr.port())),
+                KeyValue::new("error.type", "InvalidMessage(InvalidContentType)"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_load_config_rejects_bad_files() {
+        let (cert_path, key_path, _) = write_certificate("bad");
+
+        let err = load_config(&key_path, &key_path, false).unwrap_err();
+        assert!(err.to_string().contains("has no certificates"), "{err}");
+        let err = load_config(&cert_path, &cert_path, false).unwrap_err();
+        assert!(err.to_string().starts_with("TLS_KEY_PATH"), "{err}");
+        let err = load_config("/nonexistent/cert.pem", &key_path, false).unwrap_err();
+        assert!(err.to_string().starts_with("TLS_CERT_PATH"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_redirects_to_https() {
+        for (https_port, host, uri, location) in [
+            (
+                443,
+                "example.com",
+                "/api/articles?limit=5",
+                "https://example.com/api/articles?limit=5",
+            ),
+            (8443, "example.com:8080", "/", "https://example.com:8443/"),
+            (
+                8443,
+                "[::1]:8080",
+                "/api/health",
+                "https://[::1]:8443/api/health",
+            ),
+        ] {
+            let response = redirect_app(https_port)
+                .oneshot(
+                    Request::post(uri)
+                        .header(HOST, host)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+            assert_eq!(response.headers()["location"], location);
+        }
+
+        let response = redirect_app(443)
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Deserialize;
+
+use crate::ValidationError;
+
+/// The most articles one page can hold, whatever `limit` asks for.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateArticleInput {
+    pub title: String,
+    pub description: Option<String>,
+    pub body: String,
+}
+
+impl CreateArticleInput {
+    /// The title and body, trimmed; neither may be blank.
+    pub fn title_and_body(&self) -> Result<(&str, &str), ValidationError> {
+        Ok((
+            required("Title", &self.title)?,
+            required("Body", &self.body)?,
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateArticleInput {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListArticlesQuery {
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+    /// The author's `username`.
+    pub author: Option<String>,
+}
+
+/// A page of `GET /api/user/articles` or `GET /api/user/favorites`.
+#[derive(Debug, Deserialize)]
+pub struct UserArticlesQuery {
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+impl UserArticlesQuery {
+    /// `limit` within `1..=MAX_PAGE_SIZE`.
+    pub fn limit(&self) -> i64 {
+        self.limit.clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.max(0)
+    }
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+fn required<'a>(field: &'static str, value: &'a str) -> Result<&'a str, ValidationError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ValidationError::Empty(field));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_article_input_deserialization() {
+        let json =
+            r#"{"title": "New Article", "description": "Description", "body": "Body content"}"#;
+        let input: CreateArticleInput =
+            serde_json::from_str(json).expect("deserialization should succeed");
+
+        assert_eq!(input.title, "New Article");
+        assert_eq!(input.description, Some("Description".to_string()));
+        assert_eq!(input.body, "Body content");
+    }
+
+    #[test]
+    fn test_create_article_input_without_description() {
+        let json = r#"{"title": "New Article", "body": "Body content"}"#;
+        let input: CreateArticleInput =
+            serde_json::from_str(json).expect("deserialization should succeed");
+
+        assert_eq!(input.title, "New Article");
+        assert!(input.description.is_none());
+        assert_eq!(input.body, "Body content");
+    }
+
+    #[test]
+    fn test_title_and_body_are_trimmed_and_required() {
+        let input = |title: &str, body: &str| CreateArticleInput {
+            title: title.to_string(),
+            description: None,
+            body: body.to_string(),
+        };
+
+        assert_eq!(
+            input(" Title ", "Body\n").title_and_body(),
+            Ok(("Title", "Body"))
+        );
+        assert_eq!(
+            input("  ", "Body").title_and_body(),
+            Err(ValidationError::Empty("Title"))
+        );
+        assert_eq!(
+            input("Title", "").title_and_body().unwrap_err().to_string(),
+            "Body must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_update_article_input_partial() {
+        let json = r#"{"title": "Updated Title"}"#;
+        let input: UpdateArticleInput =
+            serde_json::from_str(json).expect("deserialization should succeed");
+
+        assert_eq!(input.title, Some("Updated Title".to_string()));
+        assert!(input.description.is_none());
+        assert!(input.body.is_none());
+    }
+
+    #[test]
+    fn test_list_articles_query_defaults() {
+        let query: ListArticlesQuery =
+            serde_json::from_str("{}").expect("deserialization should succeed");
+
+        assert_eq!(query.limit, 20);
+        assert_eq!(query.offset, 0);
+        assert!(query.author.is_none());
+    }
+
+    #[test]
+    fn test_list_articles_query_with_params() {
+        let json = r#"{"limit": 10, "offset": 5, "author": "john"}"#;
+        let query: ListArticlesQuery =
+            serde_json::from_str(json).expect("deserialization should succeed");
+
+        assert_eq!(query.limit, 10);
+        assert_eq!(query.offset, 5);
+        assert_eq!(query.author, Some("john".to_string()));
+    }
+
+    #[test]
+    fn test_user_articles_query_is_clamped() {
+        let query: UserArticlesQuery =
+            serde_json::from_str("{}").expect("deserialization should succeed");
+        assert_eq!((query.limit(), query.offset()), (20, 0));
+
+        let query: UserArticlesQuery = serde_json::from_str(r#"{"limit": 500, "offset": -3}"#)
+            .expect("deserialization should succeed");
+        assert_eq!((query.limit(), query.offset()), (MAX_PAGE_SIZE, 0));
+
+        let query = UserArticlesQuery {
+            limit: 0,
+            offset: 7,
+        };
+        assert_eq!((query.limit(), query.offset()), (1, 7));
+    }
+}
@@ -0,0 +1,17 @@
+use thiserror::Error;
+
+/// Why an input was rejected. The message is meant for the client.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("Username must be {min} to {max} characters")]
+    UsernameLength { min: usize, max: usize },
+
+    #[error("Username may only contain letters, digits, '-' and '_'")]
+    UsernameCharacters,
+
+    #[error("Username must start with a letter or digit")]
+    UsernameStart,
+
+    #[error("{0} must not be empty")]
+    Empty(&'static str),
+}
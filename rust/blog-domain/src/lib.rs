@@ -0,0 +1,14 @@
+//! The request inputs, response DTOs and validation rules of the blog API
+//! that the axum-postgres and actix-postgres examples both serve. The
+//! examples deserialize into these, check them with the rules here and map
+//! [`ValidationError`] to their own responses, so their contracts can't
+//! drift apart. What only one example has, like organizations, stays in
+//! that example.
+
+mod article;
+mod error;
+mod user;
+
+pub use article::*;
+pub use error::ValidationError;
+pub use user::*;
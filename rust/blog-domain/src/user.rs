@@ -0,0 +1,155 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ValidationError;
+
+/// Enforced by the `users_username_check` constraint as well.
+pub const USERNAME_LEN: RangeInclusive<usize> = 3..=30;
+
+/// Lowercased, so `Ada` and `ada` can't both register; then checked to be
+/// safe in a URL path without escaping.
+pub fn normalize_username(username: &str) -> Result<String, ValidationError> {
+    let username = username.trim().to_ascii_lowercase();
+    if !USERNAME_LEN.contains(&username.len()) {
+        return Err(ValidationError::UsernameLength {
+            min: *USERNAME_LEN.start(),
+            max: *USERNAME_LEN.end(),
+        });
+    }
+    if !username
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+    {
+        return Err(ValidationError::UsernameCharacters);
+    }
+    if !username.as_bytes()[0].is_ascii_alphanumeric() {
+        return Err(ValidationError::UsernameStart);
+    }
+    Ok(username)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginInput {
+    pub email: String,
+    pub password: String,
+}
+
+/// Pass `challenge_token` back to `POST /api/login/2fa` with a code within
+/// `expires_in` seconds.
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    pub challenge_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor: TwoFactorChallenge,
+}
+
+/// `code` is six digits from the authenticator app or a recovery code.
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginInput {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeInput {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Shown once, when two-factor authentication is enabled.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// A user as the signed-in API shows them, on their own account and as an
+/// article's author. Deserializable so the examples can read it back from
+/// JSON aggregated in SQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileResponse {
+    pub id: i32,
+    pub email: String,
+    pub username: String,
+    pub name: String,
+    pub bio: String,
+    pub image: String,
+}
+
+/// `GET /api/profiles/{username}`: what anyone may see of a user, so no
+/// email.
+#[derive(Debug, Serialize)]
+pub struct PublicProfile {
+    pub username: String,
+    pub name: String,
+    pub bio: String,
+    pub image: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicProfileResponse {
+    pub profile: PublicProfile,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_username() {
+        assert_eq!(
+            normalize_username(" Ada_Lovelace ").unwrap(),
+            "ada_lovelace"
+        );
+        assert_eq!(normalize_username("r2-d2").unwrap(), "r2-d2");
+
+        for invalid in [
+            "ab",
+            "ada lovelace",
+            "ada/..",
+            "-ada",
+            "_ada",
+            "adé",
+            &"a".repeat(31),
+        ] {
+            assert!(
+                normalize_username(invalid).is_err(),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_username_errors_name_the_rule() {
+        assert_eq!(
+            normalize_username("ab").unwrap_err().to_string(),
+            "Username must be 3 to 30 characters"
+        );
+        assert_eq!(
+            normalize_username("ada lovelace").unwrap_err(),
+            ValidationError::UsernameCharacters
+        );
+        assert_eq!(
+            normalize_username("-ada").unwrap_err(),
+            ValidationError::UsernameStart
+        );
+    }
+
+    #[test]
+    fn test_login_input_deserialization() {
+        let json = r#"{"email": "test@example.com", "password": "secret123"}"#;
+        let input: LoginInput = serde_json::from_str(json).expect("deserialization should succeed");
+
+        assert_eq!(input.email, "test@example.com");
+        assert_eq!(input.password, "secret123");
+    }
+}
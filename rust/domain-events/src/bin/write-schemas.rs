@@ -0,0 +1,11 @@
+//! Regenerates `schemas/` from the event types, after adding an event or a
+//! version.
+
+fn main() -> std::io::Result<()> {
+    for entry in domain_events::registry() {
+        let path = entry.path();
+        std::fs::write(&path, entry.to_json())?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A payload with a fixed type and version. Changes a consumer could trip
+/// over (a removed or renamed field, a changed type) need a new version; a
+/// new optional field does not.
+pub trait DomainEvent: Serialize + DeserializeOwned + JsonSchema {
+    /// Dotted name consumers route on, e.g. `article.created`.
+    const TYPE: &'static str;
+    const VERSION: u32;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("expected {expected}, got {actual}")]
+    WrongType { expected: String, actual: String },
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// What goes on the wire: the event with the metadata every consumer needs to
+/// route, deduplicate and order it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Envelope<E> {
+    /// Unique per event; a consumer that sees an id twice has a redelivery.
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub version: u32,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String", extend("format" = "date-time"))]
+    pub occurred_at: OffsetDateTime,
+    pub data: E,
+}
+
+impl<E: DomainEvent> Envelope<E> {
+    pub fn new(data: E) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type: E::TYPE.to_string(),
+            version: E::VERSION,
+            occurred_at: OffsetDateTime::now_utc(),
+            data,
+        }
+    }
+
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("events serialize to JSON")
+    }
+
+    /// Parses an envelope, refusing one of another type or version rather
+    /// than reading whichever of its fields happen to match.
+    pub fn from_value(value: serde_json::Value) -> Result<Self, Error> {
+        let actual = (
+            value["type"].as_str().unwrap_or_default(),
+            value["version"].as_u64().unwrap_or_default(),
+        );
+        if actual != (E::TYPE, u64::from(E::VERSION)) {
+            return Err(Error::WrongType {
+                expected: format!("{} v{}", E::TYPE, E::VERSION),
+                actual: format!("{} v{}", actual.0, actual.1),
+            });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArticleCreated;
+
+    fn article() -> ArticleCreated {
+        ArticleCreated {
+            article_id: 7,
+            org_id: None,
+            slug: "hello".to_string(),
+            title: "Hello".to_string(),
+            author_id: 3,
+        }
+    }
+
+    #[test]
+    fn test_envelope_round_trips() {
+        let envelope = Envelope::new(article());
+        let value = envelope.to_value();
+
+        assert_eq!(value["type"], "article.created");
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["data"]["slug"], "hello");
+        assert!(value["data"].get("org_id").is_none());
+        assert_eq!(
+            Envelope::<ArticleCreated>::from_value(value).unwrap(),
+            envelope
+        );
+    }
+
+    #[test]
+    fn test_other_types_and_versions_are_refused() {
+        let mut value = Envelope::new(article()).to_value();
+        value["version"] = 2.into();
+
+        let err = Envelope::<ArticleCreated>::from_value(value).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected article.created v1, got article.created v2"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::DomainEvent;
+
+/// An article was published. axum-postgres sets `org_id`, its tenant;
+/// actix-postgres has no tenants and leaves it out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ArticleCreated {
+    pub article_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<i32>,
+    pub slug: String,
+    pub title: String,
+    pub author_id: i32,
+}
+
+impl DomainEvent for ArticleCreated {
+    const TYPE: &'static str = "article.created";
+    const VERSION: u32 = 1;
+}
+
+/// A user signed up. `org_id` as in [`ArticleCreated`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UserRegistered {
+    pub user_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<i32>,
+    pub email: String,
+    pub name: String,
+}
+
+impl DomainEvent for UserRegistered {
+    const TYPE: &'static str = "user.registered";
+    const VERSION: u32 = 1;
+}
+
+/// ai-report-generator finished a report and stored it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReportCompleted {
+    pub report_id: Uuid,
+    pub title: String,
+    /// Indicator codes, e.g. `UNRATE`.
+    pub indicators: Vec<String>,
+    /// `completed`, or `degraded` when some sections fell back to statistics.
+    pub status: String,
+    pub data_points: u32,
+    pub total_tokens: u32,
+    pub total_cost_usd: f64,
+    pub duration_ms: u64,
+    /// The trace that generated it, so a consumer can link back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+impl DomainEvent for ReportCompleted {
+    const TYPE: &'static str = "report.completed";
+    const VERSION: u32 = 1;
+}
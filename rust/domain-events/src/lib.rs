@@ -0,0 +1,11 @@
+//! The events the Rust examples publish, as versioned payloads in a common
+//! [`Envelope`], with the JSON Schema of each checked in under `schemas/` so
+//! consumers in any language can validate against the same contract.
+
+mod envelope;
+mod events;
+mod registry;
+
+pub use envelope::{DomainEvent, Envelope, Error};
+pub use events::{ArticleCreated, ReportCompleted, UserRegistered};
+pub use registry::{SchemaEntry, registry};
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use schemars::{Schema, schema_for};
+
+use crate::{ArticleCreated, DomainEvent, Envelope, ReportCompleted, UserRegistered};
+
+/// The JSON Schema of one event version, envelope included.
+pub struct SchemaEntry {
+    pub event_type: &'static str,
+    pub version: u32,
+    pub schema: Schema,
+}
+
+impl SchemaEntry {
+    fn of<E: DomainEvent>() -> Self {
+        let mut schema = schema_for!(Envelope<E>);
+        schema.insert(
+            "title".to_string(),
+            format!("{} v{}", E::TYPE, E::VERSION).into(),
+        );
+        // The envelope is generic; pin what this version's type and version
+        // are, so a validator rejects the others.
+        let properties = schema
+            .get_mut("properties")
+            .and_then(|properties| properties.as_object_mut())
+            .expect("an envelope has properties");
+        properties.insert("type".to_string(), serde_json::json!({ "const": E::TYPE }));
+        properties.insert(
+            "version".to_string(),
+            serde_json::json!({ "const": E::VERSION }),
+        );
+        Self {
+            event_type: E::TYPE,
+            version: E::VERSION,
+            schema,
+        }
+    }
+
+    /// Where the schema is checked in, e.g. `schemas/article.created.v1.json`.
+    pub fn path(&self) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("schemas")
+            .join(format!("{}.v{}.json", self.event_type, self.version))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.schema).expect("schemas serialize to JSON") + "\n"
+    }
+}
+
+/// The current version of every event. Older versions' files stay in
+/// `schemas/` for consumers that still read them.
+pub fn registry() -> Vec<SchemaEntry> {
+    vec![
+        SchemaEntry::of::<ArticleCreated>(),
+        SchemaEntry::of::<UserRegistered>(),
+        SchemaEntry::of::<ReportCompleted>(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_in_schemas_are_current() {
+        for entry in registry() {
+            let path = entry.path();
+            let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                checked_in == entry.to_json(),
+                "{} is out of date; run `cargo run --bin write-schemas`",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_schemas_pin_type_and_version() {
+        let entry = SchemaEntry::of::<ArticleCreated>();
+        let properties = &entry.schema.as_value()["properties"];
+
+        assert_eq!(properties["type"]["const"], "article.created");
+        assert_eq!(properties["version"]["const"], 1);
+        assert_eq!(properties["occurred_at"]["format"], "date-time");
+    }
+}
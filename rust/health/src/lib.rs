@@ -0,0 +1,141 @@
+//! The health checks behind `/healthz`, `/readyz` and `/api/health` in the
+//! axum-postgres and actix-postgres examples. The checks and their JSON live
+//! here and each framework only turns a [`Health`] into its own response, so
+//! the two report health the same way.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How long the database has to answer a readiness check. A probe that hangs
+/// on a stuck pool would otherwise time out in the load balancer instead of
+/// saying why.
+pub const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Check {
+    Healthy,
+    Unhealthy,
+}
+
+/// The body of a health response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Health {
+    pub status: Status,
+    /// `None` for liveness, which doesn't check the database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<Check>,
+    pub service: &'static str,
+}
+
+impl Health {
+    /// Liveness: the process is up and serving. A database outage doesn't
+    /// fail it, since restarting the server wouldn't fix one.
+    pub fn live(service: &'static str) -> Self {
+        Self {
+            status: Status::Ok,
+            database: None,
+            service,
+        }
+    }
+
+    /// Readiness: `ping` reached the database within [`DATABASE_TIMEOUT`].
+    pub async fn ready<E: Display>(
+        service: &'static str,
+        ping: impl Future<Output = Result<(), E>>,
+    ) -> Self {
+        Self::ready_within(service, DATABASE_TIMEOUT, ping).await
+    }
+
+    async fn ready_within<E: Display>(
+        service: &'static str,
+        timeout: Duration,
+        ping: impl Future<Output = Result<(), E>>,
+    ) -> Self {
+        let database = match tokio::time::timeout(timeout, ping).await {
+            Ok(Ok(())) => Check::Healthy,
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Health check database ping failed");
+                Check::Unhealthy
+            }
+            Err(_) => {
+                tracing::warn!(
+                    timeout_ms = timeout.as_millis() as u64,
+                    "Health check database ping timed out"
+                );
+                Check::Unhealthy
+            }
+        };
+        Self {
+            status: match database {
+                Check::Healthy => Status::Ok,
+                Check::Unhealthy => Status::Error,
+            },
+            database: Some(database),
+            service,
+        }
+    }
+
+    /// 200, or 503 when a check failed.
+    pub fn status_code(&self) -> u16 {
+        match self.status {
+            Status::Ok => 200,
+            Status::Error => 503,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_live_skips_the_database() {
+        let health = Health::live("api");
+        assert_eq!(health.status_code(), 200);
+        assert_eq!(
+            serde_json::to_value(&health).unwrap(),
+            json!({ "status": "ok", "service": "api" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ready_reports_the_database() {
+        let health = Health::ready("api", async { Ok::<_, String>(()) }).await;
+        assert_eq!(health.status_code(), 200);
+        assert_eq!(
+            serde_json::to_value(&health).unwrap(),
+            json!({ "status": "ok", "database": "healthy", "service": "api" })
+        );
+
+        let health = Health::ready("api", async { Err("connection refused") }).await;
+        assert_eq!(health.status_code(), 503);
+        assert_eq!(
+            serde_json::to_value(&health).unwrap(),
+            json!({ "status": "error", "database": "unhealthy", "service": "api" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ready_gives_up_on_a_hung_ping() {
+        let health = Health::ready_within(
+            "api",
+            Duration::from_millis(10),
+            std::future::pending::<Result<(), String>>(),
+        )
+        .await;
+        assert_eq!(health.database, Some(Check::Unhealthy));
+        assert_eq!(health.status_code(), 503);
+    }
+}
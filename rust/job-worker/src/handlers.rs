@@ -0,0 +1,77 @@
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::future::Future;
+
+use crate::Job;
+
+type Handler = Box<dyn Fn(Job) -> BoxFuture<'static, anyhow::Result<()>> + Send + Sync>;
+
+/// The handler for each job kind the worker processes.
+#[derive(Default)]
+pub struct Handlers {
+    by_kind: HashMap<String, Handler>,
+}
+
+impl Handlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `handler` for jobs of `kind`, replacing any handler already
+    /// registered for it.
+    pub fn register<F, Fut>(mut self, kind: &str, handler: F) -> Self
+    where
+        F: Fn(Job) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.by_kind.insert(
+            kind.to_string(),
+            Box::new(move |job| Box::pin(handler(job))),
+        );
+        self
+    }
+
+    /// Fails jobs of a kind with no handler, so they are retried and finally
+    /// marked `failed` rather than left `processing`.
+    pub async fn dispatch(&self, job: Job) -> anyhow::Result<()> {
+        match self.by_kind.get(&job.kind) {
+            Some(handler) => handler(job).await,
+            None => {
+                tracing::warn!(job_id = job.id, kind = %job.kind, "Unknown job kind");
+                Err(anyhow::anyhow!("Unknown job kind: {}", job.kind))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(kind: &str) -> Job {
+        Job {
+            id: 1,
+            kind: kind.to_string(),
+            payload: serde_json::json!({"ok": true}),
+            status: "processing".to_string(),
+            attempts: 1,
+            trace_context: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_by_kind() {
+        let handlers = Handlers::new()
+            .register("notification", |job| async move {
+                anyhow::ensure!(job.payload["ok"] == true, "bad payload");
+                Ok(())
+            })
+            .register("broken", |_| async { anyhow::bail!("handler failed") });
+
+        handlers.dispatch(job("notification")).await.unwrap();
+        let err = handlers.dispatch(job("broken")).await.unwrap_err();
+        assert_eq!(err.to_string(), "handler failed");
+        let err = handlers.dispatch(job("digest")).await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown job kind: digest");
+    }
+}
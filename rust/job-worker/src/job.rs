@@ -0,0 +1,43 @@
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub trace_context: Option<serde_json::Value>,
+}
+
+impl Job {
+    /// The `job.process` span the worker runs this job in, continuing the
+    /// trace of the request that enqueued it.
+    pub fn span(&self) -> Span {
+        let span = tracing::info_span!(
+            "job.process",
+            job_id = self.id,
+            job_kind = %self.kind,
+        );
+        let _ = span.set_parent(self.parent_context());
+        span
+    }
+
+    fn parent_context(&self) -> opentelemetry::Context {
+        let Some(ctx_value) = &self.trace_context else {
+            return opentelemetry::Context::new();
+        };
+
+        let carrier: HashMap<String, String> = match serde_json::from_value(ctx_value.clone()) {
+            Ok(c) => c,
+            Err(_) => return opentelemetry::Context::new(),
+        };
+
+        TraceContextPropagator::new().extract(&carrier)
+    }
+}
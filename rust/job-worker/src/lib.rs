@@ -0,0 +1,13 @@
+//! The background job worker behind the `jobs` table of the axum-postgres and
+//! actix-postgres examples: it claims jobs with `FOR UPDATE SKIP LOCKED`, runs
+//! each in a `job.process` span that continues the trace of the request that
+//! enqueued it, and hands it to the handler registered for its kind.
+
+mod handlers;
+mod job;
+mod metrics;
+mod worker;
+
+pub use handlers::Handlers;
+pub use job::Job;
+pub use worker::{Worker, shutdown_signal};
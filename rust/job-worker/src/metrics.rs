@@ -0,0 +1,21 @@
+use opentelemetry::{
+    global,
+    metrics::{Counter, Meter},
+};
+use std::sync::LazyLock;
+
+static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("job-worker"));
+
+pub static JOBS_COMPLETED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("jobs.completed")
+        .with_description("Total jobs completed successfully")
+        .build()
+});
+
+pub static JOBS_FAILED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("jobs.failed")
+        .with_description("Total jobs failed")
+        .build()
+});
@@ -0,0 +1,203 @@
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{PgPool, Row};
+use std::future::Future;
+use std::time::Duration;
+use tokio::signal;
+use tracing::Instrument;
+
+use crate::metrics::{JOBS_COMPLETED, JOBS_FAILED};
+use crate::{Handlers, Job};
+
+/// Polls the `jobs` table and runs each claimed job through [`Handlers`].
+pub struct Worker {
+    pool: PgPool,
+    handlers: Handlers,
+    poll_interval: Duration,
+    persistent: bool,
+}
+
+impl Worker {
+    pub fn new(pool: PgPool, handlers: Handlers) -> Self {
+        Self {
+            pool,
+            handlers,
+            poll_interval: Duration::from_secs(1),
+            persistent: true,
+        }
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Whether the worker's queries are kept as prepared statements on the
+    /// connection, the default. Turn it off behind pgbouncer in transaction
+    /// mode.
+    pub fn persistent_statements(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    /// Processes one job per tick until `shutdown` resolves. A job already
+    /// claimed is finished first, so it is not left `processing`.
+    pub async fn run(self, shutdown: impl Future<Output = ()>) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.process_next().await {
+                        tracing::error!(error = %e, "Error processing job");
+                    }
+                }
+                _ = &mut shutdown => {
+                    tracing::info!("Worker received shutdown signal");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns whether a job was claimed.
+    pub async fn process_next(&self) -> anyhow::Result<bool> {
+        let Some((job, created_at)) = self.dequeue().await? else {
+            return Ok(false);
+        };
+
+        let span = job.span();
+        async {
+            let job_id = job.id;
+            tracing::info!(job_id, kind = %job.kind, "Processing job");
+
+            match self.handlers.dispatch(job).await {
+                Ok(()) => {
+                    self.complete(job_id, created_at).await?;
+                    tracing::info!(job_id, "Job completed");
+                }
+                Err(e) => {
+                    self.fail(job_id, created_at, &e.to_string()).await?;
+                    tracing::error!(job_id, error = %e, "Job failed");
+                }
+            }
+
+            Ok(true)
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Returns the job's `created_at` with it, which `complete` and `fail`
+    /// match on too: where `jobs` is partitioned by it, that takes them
+    /// straight to the job's partition.
+    async fn dequeue(&self) -> Result<Option<(Job, OffsetDateTime)>, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'processing',
+                started_at = NOW(),
+                attempts = attempts + 1
+            WHERE (id, created_at) = (
+                SELECT id, created_at FROM jobs
+                WHERE status = 'pending'
+                  AND scheduled_at <= NOW()
+                  AND attempts < max_attempts
+                ORDER BY priority DESC, scheduled_at ASC
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING id, kind, payload, status, attempts, trace_context, created_at
+            "#,
+        )
+        .persistent(self.persistent)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|row| {
+            let job = Job {
+                id: row.get("id"),
+                kind: row.get("kind"),
+                payload: row.get("payload"),
+                status: row.get("status"),
+                attempts: row.get("attempts"),
+                trace_context: row.get("trace_context"),
+            };
+            (job, row.get("created_at"))
+        }))
+    }
+
+    async fn complete(&self, job_id: i64, created_at: OffsetDateTime) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'completed', completed_at = NOW()
+            WHERE id = $1 AND created_at = $2
+            "#,
+        )
+        .bind(job_id)
+        .bind(created_at)
+        .persistent(self.persistent)
+        .execute(&self.pool)
+        .await?;
+
+        JOBS_COMPLETED.add(1, &[]);
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        job_id: i64,
+        created_at: OffsetDateTime,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = CASE
+                    WHEN attempts >= max_attempts THEN 'failed'
+                    ELSE 'pending'
+                END,
+                failed_at = NOW(),
+                error_message = $3
+            WHERE id = $1 AND created_at = $2
+            "#,
+        )
+        .bind(job_id)
+        .bind(created_at)
+        .bind(error)
+        .persistent(self.persistent)
+        .execute(&self.pool)
+        .await?;
+
+        JOBS_FAILED.add(1, &[]);
+        Ok(())
+    }
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on unix.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received");
+}
@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid mailer config: {0}")]
+    Config(String),
+
+    #[error("invalid address {0:?}")]
+    Address(String),
+
+    #[error("message could not be built: {0}")]
+    Message(#[from] lettre::error::Error),
+
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+
+    #[error("webhook request failed: {0}")]
+    Webhook(#[from] reqwest::Error),
+
+    #[error("webhook answered {0}")]
+    Rejected(u16),
+}
+
+impl Error {
+    /// The variant name, reported as `error.type` on `email.failed`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Config(_) => "Config",
+            Error::Address(_) => "Address",
+            Error::Message(_) => "Message",
+            Error::Smtp(_) => "Smtp",
+            Error::Webhook(_) => "Webhook",
+            Error::Rejected(_) => "Rejected",
+        }
+    }
+}
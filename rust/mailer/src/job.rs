@@ -0,0 +1,95 @@
+use job_worker::Job;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{Mailer, Template};
+
+/// The job kind `email` jobs are enqueued and registered under.
+pub const EMAIL_JOB_KIND: &str = "email";
+
+/// An `email` job's payload: the recipient and the template, flattened, e.g.
+/// `{"to": "ada@example.com", "template": "verification", "name": "Ada",
+/// "url": "..."}`. Rendering happens in the worker, so a template change
+/// applies to emails already queued.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailJob {
+    pub to: String,
+    #[serde(flatten)]
+    pub template: Template,
+}
+
+/// Sends an `email` job's email. A failed send fails the job, so the worker
+/// retries it up to the job's `max_attempts`.
+#[instrument(name = "job.email.handle", skip(mailer, job), fields(job_id = job.id))]
+pub async fn handle_job(mailer: &dyn Mailer, job: &Job) -> anyhow::Result<()> {
+    let EmailJob { to, template } = serde_json::from_value(job.payload.clone())?;
+    mailer.send(&template.render(&to)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures_util::future::BoxFuture;
+
+    use super::*;
+    use crate::{Email, Error};
+
+    #[derive(Default)]
+    struct Recording {
+        sent: Mutex<Vec<Email>>,
+    }
+
+    impl Mailer for Recording {
+        fn provider(&self) -> &'static str {
+            "recording"
+        }
+
+        fn deliver<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), Error>> {
+            self.sent.lock().unwrap().push(email.clone());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn job(payload: serde_json::Value) -> Job {
+        Job {
+            id: 1,
+            kind: EMAIL_JOB_KIND.to_string(),
+            payload,
+            status: "processing".to_string(),
+            attempts: 1,
+            trace_context: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_job_renders_and_sends() {
+        let mailer = Recording::default();
+        let payload = serde_json::to_value(EmailJob {
+            to: "ada@example.com".to_string(),
+            template: Template::Verification {
+                name: "Ada".to_string(),
+                url: "https://example.com/verify".to_string(),
+            },
+        })
+        .unwrap();
+        assert_eq!(payload["template"], "verification");
+
+        handle_job(&mailer, &job(payload)).await.unwrap();
+
+        let sent = mailer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "ada@example.com");
+        assert_eq!(sent[0].subject, "Confirm your email address");
+    }
+
+    #[tokio::test]
+    async fn test_handle_job_rejects_unknown_template() {
+        let mailer = Recording::default();
+        let payload = serde_json::json!({ "to": "ada@example.com", "template": "newsletter" });
+
+        assert!(handle_job(&mailer, &job(payload)).await.is_err());
+        assert!(mailer.sent.lock().unwrap().is_empty());
+    }
+}
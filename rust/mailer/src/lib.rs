@@ -0,0 +1,17 @@
+//! Outbound email for the axum-postgres and actix-postgres examples: a
+//! [`Mailer`] trait with SMTP, log-only and webhook transports, the
+//! [`Template`]s the examples send, and the handler for the `email` jobs
+//! that queue them, so a request never waits on a mail server.
+
+mod error;
+mod job;
+mod mailer;
+mod metrics;
+mod template;
+mod transport;
+
+pub use error::Error;
+pub use job::{EMAIL_JOB_KIND, EmailJob, handle_job};
+pub use mailer::{Email, Mailer, MailerConfig, from_config};
+pub use template::{DigestArticle, Template};
+pub use transport::{LogMailer, SmtpMailer, WebhookMailer};
@@ -0,0 +1,25 @@
+use opentelemetry::{
+    global,
+    metrics::{Counter, Meter},
+};
+use std::sync::LazyLock;
+
+static METER: LazyLock<Meter> = LazyLock::new(|| global::meter("mailer"));
+
+pub static EMAIL_SENT: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("email.sent")
+        .with_description("Emails handed to the transport, by email.provider and email.template")
+        .with_unit("{email}")
+        .build()
+});
+
+pub static EMAIL_FAILED: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("email.failed")
+        .with_description(
+            "Emails the transport failed to send, by email.provider, email.template and error.type",
+        )
+        .with_unit("{email}")
+        .build()
+});
@@ -0,0 +1,181 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Email;
+
+/// The emails the examples send. Serialized with a `template` tag, which is
+/// how they travel in an `email` job's payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "template", rename_all = "snake_case")]
+pub enum Template {
+    Verification {
+        name: String,
+        url: String,
+    },
+    PasswordReset {
+        name: String,
+        url: String,
+        expires_in_minutes: u32,
+    },
+    Digest {
+        name: String,
+        articles: Vec<DigestArticle>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestArticle {
+    pub title: String,
+    pub url: String,
+}
+
+impl Template {
+    /// `email.template` on spans and metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::Verification { .. } => "verification",
+            Template::PasswordReset { .. } => "password_reset",
+            Template::Digest { .. } => "digest",
+        }
+    }
+
+    pub fn render(&self, to: &str) -> Email {
+        let (subject, text, html) = match self {
+            Template::Verification { name, url } => (
+                "Confirm your email address".to_string(),
+                format!(
+                    "Hi {name},\n\nConfirm your email address by opening this link:\n{url}\n\nIf you didn't sign up, ignore this email.\n"
+                ),
+                format!(
+                    "<p>Hi {},</p><p><a href=\"{}\">Confirm your email address</a></p><p>If you didn't sign up, ignore this email.</p>",
+                    escape(name),
+                    escape(url)
+                ),
+            ),
+            Template::PasswordReset {
+                name,
+                url,
+                expires_in_minutes,
+            } => (
+                "Reset your password".to_string(),
+                format!(
+                    "Hi {name},\n\nReset your password with this link, valid for {expires_in_minutes} minutes:\n{url}\n\nIf you didn't ask for a reset, your password is unchanged.\n"
+                ),
+                format!(
+                    "<p>Hi {},</p><p><a href=\"{}\">Reset your password</a> (valid for {expires_in_minutes} minutes).</p><p>If you didn't ask for a reset, your password is unchanged.</p>",
+                    escape(name),
+                    escape(url)
+                ),
+            ),
+            Template::Digest { name, articles } => {
+                let mut text = format!("Hi {name},\n\nNew since your last digest:\n\n");
+                let mut html = format!(
+                    "<p>Hi {},</p><p>New since your last digest:</p><ul>",
+                    escape(name)
+                );
+                for article in articles {
+                    let _ = writeln!(text, "- {}: {}", article.title, article.url);
+                    let _ = write!(
+                        html,
+                        "<li><a href=\"{}\">{}</a></li>",
+                        escape(&article.url),
+                        escape(&article.title)
+                    );
+                }
+                html.push_str("</ul>");
+                let subject = match articles.len() {
+                    1 => "1 new article".to_string(),
+                    n => format!("{n} new articles"),
+                };
+                (subject, text, html)
+            }
+        };
+        Email {
+            to: to.to_string(),
+            template: self.name(),
+            subject,
+            text,
+            html,
+        }
+    }
+}
+
+/// Names and titles come from users, so they are escaped in the HTML part.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_verification() {
+        let email = Template::Verification {
+            name: "Ada".to_string(),
+            url: "https://example.com/verify?token=abc&u=1".to_string(),
+        }
+        .render("ada@example.com");
+
+        assert_eq!(email.to, "ada@example.com");
+        assert_eq!(email.template, "verification");
+        assert_eq!(email.subject, "Confirm your email address");
+        assert!(
+            email
+                .text
+                .contains("https://example.com/verify?token=abc&u=1")
+        );
+        assert!(
+            email
+                .html
+                .contains("href=\"https://example.com/verify?token=abc&amp;u=1\"")
+        );
+    }
+
+    #[test]
+    fn test_digest_escapes_user_content() {
+        let email = Template::Digest {
+            name: "<b>Ada</b>".to_string(),
+            articles: vec![DigestArticle {
+                title: "Rust & \"OTel\"".to_string(),
+                url: "https://example.com/a/1".to_string(),
+            }],
+        }
+        .render("ada@example.com");
+
+        assert_eq!(email.subject, "1 new article");
+        assert!(
+            email
+                .text
+                .contains("- Rust & \"OTel\": https://example.com/a/1")
+        );
+        assert!(email.html.contains("<p>Hi &lt;b&gt;Ada&lt;/b&gt;,</p>"));
+        assert!(email.html.contains(">Rust &amp; &quot;OTel&quot;</a>"));
+    }
+
+    #[test]
+    fn test_serialized_with_template_tag() {
+        let template = Template::PasswordReset {
+            name: "Ada".to_string(),
+            url: "https://example.com/reset".to_string(),
+            expires_in_minutes: 30,
+        };
+
+        let value = serde_json::to_value(&template).unwrap();
+        assert_eq!(value["template"], "password_reset");
+        assert_eq!(value["expires_in_minutes"], 30);
+        assert_eq!(serde_json::from_value::<Template>(value).unwrap(), template);
+    }
+}
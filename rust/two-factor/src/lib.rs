@@ -0,0 +1,486 @@
+//! TOTP two-factor authentication for the axum-postgres and actix-postgres
+//! examples: enrollment, the code a login asks for once it's enabled,
+//! single-use recovery codes and a lockout after repeated wrong codes. The
+//! flow lives in [`TwoFactor`] and each example only stores its state
+//! through a [`Store`], so the two can't enforce it differently.
+
+mod recovery;
+mod store;
+mod totp;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+pub use recovery::{RECOVERY_CODE_COUNT, generate_recovery_codes, hash_recovery_code};
+pub use store::{Attempt, Enrollment, Store};
+pub use totp::{DIGITS, STEP_SECS, Totp};
+
+#[derive(Debug, Error)]
+pub enum Error<E> {
+    #[error("two-factor authentication is already enabled")]
+    AlreadyEnabled,
+
+    #[error("two-factor authentication is not set up")]
+    NotEnrolled,
+
+    #[error("invalid two-factor code")]
+    InvalidCode,
+
+    #[error("too many invalid two-factor codes, retry in {}s", .0.as_secs())]
+    Locked(Duration),
+
+    #[error(transparent)]
+    Store(E),
+}
+
+/// How many wrong codes in a row lock a user out, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lockout {
+    pub max_failures: u32,
+    pub duration: Duration,
+}
+
+/// `POST /api/user/2fa/setup`: the secret to add to an authenticator app,
+/// as text and as the URI a QR code carries.
+#[derive(Debug, Clone)]
+pub struct Setup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// How a login's second step was passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verified {
+    Totp,
+    RecoveryCode { remaining: u32 },
+}
+
+#[derive(Clone)]
+pub struct TwoFactor<S> {
+    store: S,
+    issuer: String,
+    lockout: Lockout,
+}
+
+impl<S: Store> TwoFactor<S> {
+    /// `issuer` names the service in authenticator apps.
+    pub fn new(store: S, issuer: impl Into<String>, lockout: Lockout) -> Self {
+        Self {
+            store,
+            issuer: issuer.into(),
+            lockout,
+        }
+    }
+
+    /// A new secret, pending until [`TwoFactor::confirm`]. Calling it again
+    /// before then replaces the secret.
+    pub async fn setup(&self, user_id: i32, account: &str) -> Result<Setup, Error<S::Error>> {
+        let totp = Totp::generate();
+        let secret = totp.to_base32();
+        if !self
+            .store
+            .save_pending(user_id, &secret)
+            .await
+            .map_err(Error::Store)?
+        {
+            return Err(Error::AlreadyEnabled);
+        }
+        Ok(Setup {
+            otpauth_uri: totp.uri(&self.issuer, account),
+            secret,
+        })
+    }
+
+    /// Enables two-factor authentication once `code` shows the app has the
+    /// pending secret. Returns the recovery codes, which are only stored
+    /// hashed, so this is the one time they can be shown.
+    pub async fn confirm(&self, user_id: i32, code: &str) -> Result<Vec<String>, Error<S::Error>> {
+        let enrollment = self.enrollment(user_id).await?;
+        if enrollment.enabled {
+            return Err(Error::AlreadyEnabled);
+        }
+        self.claim_attempt(user_id).await?;
+
+        let step = totp(&enrollment)?
+            .verify(&normalize(code), now(), enrollment.last_step)
+            .ok_or(Error::InvalidCode)?;
+        let codes = generate_recovery_codes();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| hash_recovery_code(code).expect("generated codes hash"))
+            .collect();
+        if !self
+            .store
+            .enable(user_id, step, &hashes)
+            .await
+            .map_err(Error::Store)?
+        {
+            return Err(Error::AlreadyEnabled);
+        }
+        Ok(codes)
+    }
+
+    pub async fn is_enabled(&self, user_id: i32) -> Result<bool, Error<S::Error>> {
+        Ok(self
+            .store
+            .enrollment(user_id)
+            .await
+            .map_err(Error::Store)?
+            .is_some_and(|enrollment| enrollment.enabled))
+    }
+
+    /// The second step of a login: a code from the app, or an unused
+    /// recovery code.
+    pub async fn verify(&self, user_id: i32, code: &str) -> Result<Verified, Error<S::Error>> {
+        let enrollment = self.enrollment(user_id).await?;
+        if !enrollment.enabled {
+            return Err(Error::NotEnrolled);
+        }
+        self.claim_attempt(user_id).await?;
+
+        let code = normalize(code);
+        if code.len() == DIGITS {
+            let step = totp(&enrollment)?
+                .verify(&code, now(), enrollment.last_step)
+                .ok_or(Error::InvalidCode)?;
+            // Loses to a concurrent request that used the same code.
+            if !self
+                .store
+                .accept_step(user_id, step)
+                .await
+                .map_err(Error::Store)?
+            {
+                return Err(Error::InvalidCode);
+            }
+            return Ok(Verified::Totp);
+        }
+
+        let hash = hash_recovery_code(&code).ok_or(Error::InvalidCode)?;
+        match self
+            .store
+            .use_recovery_code(user_id, &hash)
+            .await
+            .map_err(Error::Store)?
+        {
+            Some(remaining) => Ok(Verified::RecoveryCode { remaining }),
+            None => Err(Error::InvalidCode),
+        }
+    }
+
+    async fn enrollment(&self, user_id: i32) -> Result<Enrollment, Error<S::Error>> {
+        self.store
+            .enrollment(user_id)
+            .await
+            .map_err(Error::Store)?
+            .ok_or(Error::NotEnrolled)
+    }
+
+    async fn claim_attempt(&self, user_id: i32) -> Result<(), Error<S::Error>> {
+        match self
+            .store
+            .claim_attempt(user_id, self.lockout.max_failures, self.lockout.duration)
+            .await
+            .map_err(Error::Store)?
+        {
+            Attempt::Allowed => Ok(()),
+            Attempt::Locked(retry_after) => Err(Error::Locked(retry_after)),
+        }
+    }
+}
+
+/// A stored secret that doesn't decode can't match any code.
+fn totp<E>(enrollment: &Enrollment) -> Result<Totp, Error<E>> {
+    Totp::from_base32(&enrollment.secret).ok_or(Error::InvalidCode)
+}
+
+/// Apps show codes as `123 456`.
+fn normalize(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct User {
+        enrollment: Option<Enrollment>,
+        recovery_codes: Vec<(String, bool)>,
+        failures: u32,
+        last_attempt: Option<SystemTime>,
+    }
+
+    #[derive(Default)]
+    struct MemoryStore(Mutex<HashMap<i32, User>>);
+
+    impl Store for MemoryStore {
+        type Error = Infallible;
+
+        async fn enrollment(&self, user_id: i32) -> Result<Option<Enrollment>, Infallible> {
+            let users = self.0.lock().unwrap();
+            Ok(users.get(&user_id).and_then(|u| u.enrollment.clone()))
+        }
+
+        async fn save_pending(&self, user_id: i32, secret: &str) -> Result<bool, Infallible> {
+            let mut users = self.0.lock().unwrap();
+            let user = users.entry(user_id).or_default();
+            if user.enrollment.as_ref().is_some_and(|e| e.enabled) {
+                return Ok(false);
+            }
+            user.enrollment = Some(Enrollment {
+                secret: secret.to_string(),
+                enabled: false,
+                last_step: None,
+            });
+            Ok(true)
+        }
+
+        async fn enable(
+            &self,
+            user_id: i32,
+            step: i64,
+            recovery_code_hashes: &[String],
+        ) -> Result<bool, Infallible> {
+            let mut users = self.0.lock().unwrap();
+            let user = users.get_mut(&user_id).unwrap();
+            let enrollment = user.enrollment.as_mut().unwrap();
+            if enrollment.enabled {
+                return Ok(false);
+            }
+            enrollment.enabled = true;
+            enrollment.last_step = Some(step);
+            user.failures = 0;
+            user.recovery_codes = recovery_code_hashes
+                .iter()
+                .map(|hash| (hash.clone(), false))
+                .collect();
+            Ok(true)
+        }
+
+        async fn accept_step(&self, user_id: i32, step: i64) -> Result<bool, Infallible> {
+            let mut users = self.0.lock().unwrap();
+            let user = users.get_mut(&user_id).unwrap();
+            let enrollment = user.enrollment.as_mut().unwrap();
+            if enrollment.last_step.is_some_and(|last| last >= step) {
+                return Ok(false);
+            }
+            enrollment.last_step = Some(step);
+            user.failures = 0;
+            Ok(true)
+        }
+
+        async fn use_recovery_code(
+            &self,
+            user_id: i32,
+            code_hash: &str,
+        ) -> Result<Option<u32>, Infallible> {
+            let mut users = self.0.lock().unwrap();
+            let user = users.get_mut(&user_id).unwrap();
+            let Some(code) = user
+                .recovery_codes
+                .iter_mut()
+                .find(|(hash, used)| hash == code_hash && !used)
+            else {
+                return Ok(None);
+            };
+            code.1 = true;
+            user.failures = 0;
+            Ok(Some(
+                user.recovery_codes.iter().filter(|(_, used)| !used).count() as u32,
+            ))
+        }
+
+        async fn claim_attempt(
+            &self,
+            user_id: i32,
+            max_failures: u32,
+            lockout: Duration,
+        ) -> Result<Attempt, Infallible> {
+            let mut users = self.0.lock().unwrap();
+            let user = users.entry(user_id).or_default();
+            let now = SystemTime::now();
+            if user.failures >= max_failures
+                && let Some(elapsed) = user.last_attempt.and_then(|at| now.duration_since(at).ok())
+                && elapsed < lockout
+            {
+                return Ok(Attempt::Locked(lockout - elapsed));
+            }
+            user.failures += 1;
+            user.last_attempt = Some(now);
+            Ok(Attempt::Allowed)
+        }
+    }
+
+    const USER: i32 = 7;
+
+    fn two_factor() -> TwoFactor<MemoryStore> {
+        TwoFactor::new(
+            MemoryStore::default(),
+            "Example",
+            Lockout {
+                max_failures: 3,
+                duration: Duration::from_secs(60),
+            },
+        )
+    }
+
+    fn current_code(secret: &str) -> String {
+        Totp::from_base32(secret)
+            .unwrap()
+            .code(now().div_euclid(STEP_SECS))
+    }
+
+    /// A code from a step the lenient window doesn't reach.
+    fn wrong_code(secret: &str) -> String {
+        Totp::from_base32(secret)
+            .unwrap()
+            .code(now().div_euclid(STEP_SECS) + 5)
+    }
+
+    async fn enabled(two_factor: &TwoFactor<MemoryStore>) -> (String, Vec<String>) {
+        let setup = two_factor.setup(USER, "ada@example.com").await.unwrap();
+        let codes = two_factor
+            .confirm(USER, &current_code(&setup.secret))
+            .await
+            .unwrap();
+        (setup.secret, codes)
+    }
+
+    #[tokio::test]
+    async fn test_setup_and_confirm_enable() {
+        let two_factor = two_factor();
+        let setup = two_factor.setup(USER, "ada@example.com").await.unwrap();
+
+        assert!(
+            setup
+                .otpauth_uri
+                .starts_with("otpauth://totp/Example:ada%40example.com?")
+        );
+        assert!(setup.otpauth_uri.contains(&setup.secret));
+        assert!(!two_factor.is_enabled(USER).await.unwrap());
+        assert!(matches!(
+            two_factor.verify(USER, "123456").await,
+            Err(Error::NotEnrolled)
+        ));
+        assert!(matches!(
+            two_factor.confirm(USER, &wrong_code(&setup.secret)).await,
+            Err(Error::InvalidCode)
+        ));
+
+        let codes = two_factor
+            .confirm(USER, &current_code(&setup.secret))
+            .await
+            .unwrap();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(two_factor.is_enabled(USER).await.unwrap());
+        assert!(matches!(
+            two_factor.setup(USER, "ada@example.com").await,
+            Err(Error::AlreadyEnabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_needs_setup() {
+        assert!(matches!(
+            two_factor().confirm(USER, "123456").await,
+            Err(Error::NotEnrolled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_a_code_works_once() {
+        let two_factor = two_factor();
+        let (secret, _) = enabled(&two_factor).await;
+
+        // confirm used the current step already.
+        assert!(matches!(
+            two_factor.verify(USER, &current_code(&secret)).await,
+            Err(Error::InvalidCode)
+        ));
+        let next = Totp::from_base32(&secret)
+            .unwrap()
+            .code(now().div_euclid(STEP_SECS) + 1);
+        let spaced = format!("{} {}", &next[..3], &next[3..]);
+        assert_eq!(
+            two_factor.verify(USER, &spaced).await.unwrap(),
+            Verified::Totp
+        );
+        assert!(matches!(
+            two_factor.verify(USER, &next).await,
+            Err(Error::InvalidCode)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_work_once() {
+        let two_factor = two_factor();
+        let (_, codes) = enabled(&two_factor).await;
+
+        assert_eq!(
+            two_factor
+                .verify(USER, &codes[0].to_uppercase())
+                .await
+                .unwrap(),
+            Verified::RecoveryCode {
+                remaining: RECOVERY_CODE_COUNT as u32 - 1
+            }
+        );
+        assert!(matches!(
+            two_factor.verify(USER, &codes[0]).await,
+            Err(Error::InvalidCode)
+        ));
+        assert!(matches!(
+            two_factor.verify(USER, "aaaa-bbbb-cccc-dddd").await,
+            Err(Error::InvalidCode)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_codes_lock_out() {
+        let two_factor = two_factor();
+        let (secret, codes) = enabled(&two_factor).await;
+
+        for _ in 0..3 {
+            assert!(matches!(
+                two_factor.verify(USER, &wrong_code(&secret)).await,
+                Err(Error::InvalidCode)
+            ));
+        }
+        // Even the right code is refused until the lockout ends.
+        match two_factor.verify(USER, &codes[0]).await {
+            Err(Error::Locked(retry_after)) => {
+                assert!(retry_after > Duration::from_secs(55), "{retry_after:?}")
+            }
+            other => panic!("expected a lockout, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_valid_code_clears_failures() {
+        let two_factor = two_factor();
+        let (secret, codes) = enabled(&two_factor).await;
+
+        for _ in 0..2 {
+            assert!(two_factor.verify(USER, &wrong_code(&secret)).await.is_err());
+        }
+        assert!(two_factor.verify(USER, &codes[0]).await.is_ok());
+        for _ in 0..2 {
+            assert!(matches!(
+                two_factor.verify(USER, &wrong_code(&secret)).await,
+                Err(Error::InvalidCode)
+            ));
+        }
+        assert!(two_factor.verify(USER, &codes[1]).await.is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+use data_encoding::BASE32_NOPAD;
+use sha2::{Digest, Sha256};
+
+/// Issued when two-factor authentication is enabled; each works once.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 80 bits, 16 base32 characters.
+const RECOVERY_CODE_BYTES: usize = 10;
+const RECOVERY_CODE_LEN: usize = 16;
+
+/// Lowercase and in groups of four, `abcd-efgh-ijkl-mnop`, to read off a
+/// printout.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+            getrandom::getrandom(&mut bytes).expect("the OS random source is available");
+            let code = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+            code.as_bytes()
+                .chunks(4)
+                .map(|group| std::str::from_utf8(group).expect("base32 is ASCII"))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// What is stored of a recovery code. Codes are 80 random bits, so a fast
+/// unsalted hash is enough, as for personal access tokens. Case, dashes
+/// and spaces don't matter; `None` when `code` can't be a recovery code.
+pub fn hash_recovery_code(code: &str) -> Option<String> {
+    let normalized: String = code
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let valid =
+        normalized.len() == RECOVERY_CODE_LEN && BASE32_NOPAD.decode(normalized.as_bytes()).is_ok();
+    valid.then(|| hex::encode(Sha256::digest(normalized.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_codes_are_distinct_and_hashable() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let mut hashes: Vec<String> = codes
+            .iter()
+            .map(|code| {
+                assert_eq!(code.len(), RECOVERY_CODE_LEN + 3, "{code}");
+                hash_recovery_code(code).expect("a generated code hashes")
+            })
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), RECOVERY_CODE_COUNT);
+    }
+
+    #[test]
+    fn test_hash_ignores_case_and_separators() {
+        let hash = hash_recovery_code("abcd-efgh-ijkl-mnop").unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash_recovery_code("ABCDEFGHIJKLMNOP"), Some(hash.clone()));
+        assert_eq!(hash_recovery_code(" abcd efgh ijkl mnop "), Some(hash));
+    }
+
+    #[test]
+    fn test_hash_rejects_what_cant_be_a_code() {
+        assert_eq!(hash_recovery_code("123456"), None);
+        assert_eq!(hash_recovery_code("abcd-efgh-ijkl-mno1"), None);
+        assert_eq!(hash_recovery_code("abcd-efgh-ijkl-mnopq"), None);
+    }
+}
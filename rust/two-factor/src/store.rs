@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+/// A user's TOTP secret, as [`Store::enrollment`] returns it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enrollment {
+    /// Base32, as [`Totp::to_base32`](crate::Totp::to_base32) writes it.
+    pub secret: String,
+    /// `false` between setup and the first confirmed code.
+    pub enabled: bool,
+    /// The time step of the last accepted code.
+    pub last_step: Option<i64>,
+}
+
+/// Whether [`Store::claim_attempt`] let a code be checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    Allowed,
+    /// Too many codes in a row were wrong; try again after this long.
+    Locked(Duration),
+}
+
+/// Where an example keeps two-factor state, one enrollment and a set of
+/// recovery code hashes per user. Each method is one atomic step, so two
+/// requests for the same user can't both use a code or both skip the
+/// lockout.
+pub trait Store: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn enrollment(
+        &self,
+        user_id: i32,
+    ) -> impl Future<Output = Result<Option<Enrollment>, Self::Error>> + Send;
+
+    /// Stores a pending secret, replacing one that was never confirmed.
+    /// `false`, changing nothing, when two-factor authentication is already
+    /// enabled.
+    fn save_pending(
+        &self,
+        user_id: i32,
+        secret: &str,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Enables the pending secret with `step` as its last accepted code,
+    /// clears the failed attempts and replaces the recovery codes. `false`
+    /// when it was enabled already.
+    fn enable(
+        &self,
+        user_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Records `step` as the last accepted code and clears the failed
+    /// attempts, unless a code from `step` or later was accepted already.
+    fn accept_step(
+        &self,
+        user_id: i32,
+        step: i64,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Marks the unused recovery code with this hash used and clears the
+    /// failed attempts. The number of unused codes left, or `None` when
+    /// none matched.
+    fn use_recovery_code(
+        &self,
+        user_id: i32,
+        code_hash: &str,
+    ) -> impl Future<Output = Result<Option<u32>, Self::Error>> + Send;
+
+    /// Counts an attempt before its code is checked, so concurrent guesses
+    /// count too. Refused when the last `max_failures` attempts failed and
+    /// the latest came less than `lockout` ago; a successful code clears
+    /// the count. After a lockout each further wrong code locks again.
+    fn claim_attempt(
+        &self,
+        user_id: i32,
+        max_failures: u32,
+        lockout: Duration,
+    ) -> impl Future<Output = Result<Attempt, Self::Error>> + Send;
+}
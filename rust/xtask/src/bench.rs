@@ -0,0 +1,556 @@
+//! `cargo xtask bench`: the same requests against each example's api, first
+//! with OpenTelemetry exporting to the collector and then with
+//! `OTEL_SDK_DISABLED=true`, reported as throughput, latency percentiles
+//! and the difference between the two.
+
+use std::fmt::{self, Write as _};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use clap::{Args, ValueEnum};
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::example::{Env, Example};
+use crate::process::{self, Running};
+use crate::{database, seed};
+
+/// Tries at listing the articles before giving up on a 503, which
+/// axum-postgres sheds just after startup.
+const LIST_ATTEMPTS: u32 = 5;
+
+#[derive(Args)]
+pub struct Bench {
+    /// Examples to compare [default: axum-postgres actix-postgres]
+    examples: Vec<Example>,
+    /// Use the Postgres and collector already at DATABASE_URL and
+    /// OTEL_EXPORTER_OTLP_ENDPOINT instead of starting the compose ones
+    #[arg(long)]
+    no_compose: bool,
+    /// Concurrent connections, each sending its next request as soon as
+    /// the last is answered
+    #[arg(long, default_value_t = 32)]
+    connections: usize,
+    /// Seconds each scenario is measured for
+    #[arg(long, default_value_t = 10)]
+    duration: u64,
+    /// Seconds of unmeasured requests before each scenario
+    #[arg(long, default_value_t = 2)]
+    warmup: u64,
+    /// Scenarios to run [default: all]
+    #[arg(long = "scenario")]
+    scenarios: Vec<Scenario>,
+    /// Also write the results to this file as JSON
+    #[arg(long)]
+    json: Option<PathBuf>,
+}
+
+/// GETs both examples serve the same way, so the code under test is the
+/// framework, the middleware and the instrumentation rather than a feature
+/// only one of them has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scenario {
+    /// `GET /healthz`, which touches no database
+    Liveness,
+    /// `GET /api/articles`, a page of articles with their authors
+    ListArticles,
+    /// `GET /api/articles/{slug}` as a signed-in user
+    Article,
+    /// `GET /api/user`, the JWT and the user's row
+    CurrentUser,
+    /// `GET /api/articles/no-such-article`, the 404 path
+    MissingArticle,
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+impl Scenario {
+    fn target(self, base_url: &str, fixture: &Fixture) -> Target {
+        let (path, token, expected) = match self {
+            Scenario::Liveness => ("/healthz".to_string(), false, StatusCode::OK),
+            Scenario::ListArticles => ("/api/articles".to_string(), false, StatusCode::OK),
+            Scenario::Article => (
+                format!("/api/articles/{}", fixture.slug),
+                true,
+                StatusCode::OK,
+            ),
+            Scenario::CurrentUser => ("/api/user".to_string(), true, StatusCode::OK),
+            Scenario::MissingArticle => (
+                "/api/articles/no-such-article".to_string(),
+                false,
+                StatusCode::NOT_FOUND,
+            ),
+        };
+        Target {
+            url: format!("{base_url}{path}"),
+            token: token.then(|| fixture.token.clone()),
+            expected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Telemetry {
+    On,
+    Off,
+}
+
+impl Telemetry {
+    fn as_str(self) -> &'static str {
+        match self {
+            Telemetry::On => "on",
+            Telemetry::Off => "off",
+        }
+    }
+
+    fn env(self, env: &Env) -> Env {
+        let disabled = match self {
+            Telemetry::On => "false",
+            Telemetry::Off => "true",
+        };
+        env.with("OTEL_SDK_DISABLED", disabled)
+    }
+}
+
+/// A demo user's token and an article to read.
+struct Fixture {
+    token: String,
+    slug: String,
+}
+
+impl Fixture {
+    async fn load(client: &Client, base_url: &str) -> anyhow::Result<Self> {
+        let (email, ..) = seed::USERS[0];
+        let token = seed::login(client, base_url, email).await?;
+        let mut attempt = 1;
+        let articles: Value = loop {
+            let response = client
+                .get(format!("{base_url}/api/articles?limit=1"))
+                .send()
+                .await?;
+            if response.status() == StatusCode::SERVICE_UNAVAILABLE && attempt < LIST_ATTEMPTS {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            break response.error_for_status()?.json().await?;
+        };
+        let slug = articles["articles"][0]["slug"]
+            .as_str()
+            .context("There are no articles; run `cargo xtask seed` first")?;
+        Ok(Self {
+            token,
+            slug: slug.to_string(),
+        })
+    }
+}
+
+/// What every connection of a scenario requests.
+struct Target {
+    url: String,
+    token: Option<String>,
+    expected: StatusCode,
+}
+
+/// One scenario's measured requests.
+#[derive(Debug, Default)]
+struct Measurement {
+    /// Of the requests answered with the expected status, sorted.
+    latencies: Vec<Duration>,
+    /// Requests answered with another status or not at all.
+    errors: u64,
+    elapsed: Duration,
+}
+
+impl Measurement {
+    /// Requests answered as expected, per second.
+    fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.latencies.len() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Nearest-rank percentile; zero without any requests.
+    fn percentile(&self, percent: f64) -> Duration {
+        let count = self.latencies.len();
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = (percent / 100.0 * count as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, count) - 1]
+    }
+
+    fn max(&self) -> Duration {
+        self.latencies.last().copied().unwrap_or_default()
+    }
+}
+
+/// Like `wrk`: `connections` loops each sending a request, reading the
+/// whole response and sending the next, until `duration` is up.
+async fn drive(
+    client: &Client,
+    target: Arc<Target>,
+    connections: usize,
+    duration: Duration,
+) -> Measurement {
+    let started = Instant::now();
+    let deadline = started + duration;
+    let mut tasks = JoinSet::new();
+    for _ in 0..connections {
+        let (client, target) = (client.clone(), target.clone());
+        tasks.spawn(async move {
+            let mut latencies = Vec::new();
+            let mut errors = 0;
+            while Instant::now() < deadline {
+                let sent = Instant::now();
+                let mut request = client.get(&target.url);
+                if let Some(token) = &target.token {
+                    request = request.bearer_auth(token);
+                }
+                let answered = match request.send().await {
+                    Ok(response) => {
+                        let status = response.status();
+                        response.bytes().await.is_ok() && status == target.expected
+                    }
+                    Err(_) => false,
+                };
+                if answered {
+                    latencies.push(sent.elapsed());
+                } else {
+                    errors += 1;
+                }
+            }
+            (latencies, errors)
+        });
+    }
+
+    let mut measurement = Measurement::default();
+    for (latencies, errors) in tasks.join_all().await {
+        measurement.latencies.extend(latencies);
+        measurement.errors += errors;
+    }
+    measurement.elapsed = started.elapsed();
+    measurement.latencies.sort_unstable();
+    measurement
+}
+
+struct Run {
+    example: Example,
+    scenario: Scenario,
+    telemetry: Telemetry,
+    measurement: Measurement,
+}
+
+impl Bench {
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let examples = if self.examples.is_empty() {
+            vec![Example::AxumPostgres, Example::ActixPostgres]
+        } else {
+            self.examples.clone()
+        };
+        if let Some(example) = examples.iter().find(|example| !example.has_articles()) {
+            anyhow::bail!("{example} serves none of the benchmark scenarios");
+        }
+        anyhow::ensure!(self.connections > 0, "--connections must be positive");
+        anyhow::ensure!(self.duration > 0, "--duration must be positive");
+
+        let mut runs = Vec::new();
+        for example in examples {
+            if !self.no_compose {
+                process::compose_up(example).await?;
+            }
+            let result = self.bench(example, &mut runs).await;
+            if !self.no_compose {
+                process::compose_stop(example).await?;
+            }
+            result?;
+        }
+
+        print!("{}", self.report(&runs));
+        if let Some(path) = &self.json {
+            let json = serde_json::to_string_pretty(&self.json_report(&runs))?;
+            std::fs::write(path, json + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("Results written to {}", path.display());
+        }
+        Ok(())
+    }
+
+    fn scenarios(&self) -> Vec<Scenario> {
+        if self.scenarios.is_empty() {
+            Scenario::value_variants().to_vec()
+        } else {
+            self.scenarios.clone()
+        }
+    }
+
+    async fn bench(&self, example: Example, runs: &mut Vec<Run>) -> anyhow::Result<()> {
+        let env = Env::load(example)?;
+        let pool = database::connect(env.database_url()).await?;
+        database::migrate(example, &pool).await?;
+        database::seed(example, &pool).await?;
+        pool.close().await;
+        process::build(example, true).await?;
+
+        let client = Client::builder()
+            .danger_accept_invalid_certs(env.base_url().starts_with("https://"))
+            .pool_max_idle_per_host(self.connections)
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        let logs = example.dir().join("target").join("bench");
+        std::fs::create_dir_all(&logs)?;
+
+        for telemetry in [Telemetry::On, Telemetry::Off] {
+            let log = logs.join(format!("api-telemetry-{}.log", telemetry.as_str()));
+            println!(
+                "Benchmarking {example} with telemetry {}, api output in {}",
+                telemetry.as_str(),
+                log.display()
+            );
+            let mut running = Running::spawn_api(example, &telemetry.env(&env), &log)?;
+            let result = async {
+                running
+                    .wait_healthy(&client, &format!("{}/api/health", env.base_url()))
+                    .await?;
+                seed::seed_api(&client, &env.base_url()).await?;
+                let fixture = Fixture::load(&client, &env.base_url()).await?;
+                for scenario in self.scenarios() {
+                    let target = Arc::new(scenario.target(&env.base_url(), &fixture));
+                    let warmup = Duration::from_secs(self.warmup);
+                    if !warmup.is_zero() {
+                        drive(&client, target.clone(), self.connections, warmup).await;
+                    }
+                    let measurement = drive(
+                        &client,
+                        target,
+                        self.connections,
+                        Duration::from_secs(self.duration),
+                    )
+                    .await;
+                    println!(
+                        "  {scenario}: {:.0} requests/s, p99 {}",
+                        measurement.throughput(),
+                        millis(measurement.percentile(99.0))
+                    );
+                    runs.push(Run {
+                        example,
+                        scenario,
+                        telemetry,
+                        measurement,
+                    });
+                }
+                anyhow::Ok(())
+            }
+            .await;
+            running.shutdown().await?;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// A Markdown table per example, with a row per scenario and telemetry
+    /// setting and one for what turning telemetry on changed.
+    fn report(&self, runs: &[Run]) -> String {
+        let mut out = String::new();
+        let mut examples: Vec<Example> = runs.iter().map(|run| run.example).collect();
+        examples.dedup();
+        for example in examples {
+            let _ = writeln!(
+                out,
+                "\n## {example}\n\n{} connections, {}s per scenario\n",
+                self.connections, self.duration
+            );
+            out.push_str(
+                "| Scenario | Telemetry | Requests/s | p50 | p90 | p99 | Max | Errors |\n",
+            );
+            out.push_str(
+                "| -------- | --------- | ---------- | --- | --- | --- | --- | ------ |\n",
+            );
+            for scenario in self.scenarios() {
+                let find = |telemetry| {
+                    runs.iter().find(|run| {
+                        run.example == example
+                            && run.scenario == scenario
+                            && run.telemetry == telemetry
+                    })
+                };
+                for run in [find(Telemetry::On), find(Telemetry::Off)]
+                    .into_iter()
+                    .flatten()
+                {
+                    let m = &run.measurement;
+                    let _ = writeln!(
+                        out,
+                        "| {scenario} | {} | {:.0} | {} | {} | {} | {} | {} |",
+                        run.telemetry.as_str(),
+                        m.throughput(),
+                        millis(m.percentile(50.0)),
+                        millis(m.percentile(90.0)),
+                        millis(m.percentile(99.0)),
+                        millis(m.max()),
+                        m.errors
+                    );
+                }
+                if let (Some(on), Some(off)) = (find(Telemetry::On), find(Telemetry::Off)) {
+                    let (on, off) = (&on.measurement, &off.measurement);
+                    let _ = writeln!(
+                        out,
+                        "| {scenario} | cost | {} | {} | {} | {} | | |",
+                        throughput_change(on.throughput(), off.throughput()),
+                        added(on.percentile(50.0), off.percentile(50.0)),
+                        added(on.percentile(90.0), off.percentile(90.0)),
+                        added(on.percentile(99.0), off.percentile(99.0)),
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    fn json_report(&self, runs: &[Run]) -> Value {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        json!({
+            "connections": self.connections,
+            "duration_secs": self.duration,
+            "warmup_secs": self.warmup,
+            "runs": runs
+                .iter()
+                .map(|run| {
+                    let m = &run.measurement;
+                    json!({
+                        "example": run.example.to_string(),
+                        "scenario": run.scenario.to_string(),
+                        "telemetry": run.telemetry.as_str(),
+                        "requests": m.latencies.len(),
+                        "errors": m.errors,
+                        "requests_per_sec": m.throughput(),
+                        "p50_ms": ms(m.percentile(50.0)),
+                        "p90_ms": ms(m.percentile(90.0)),
+                        "p99_ms": ms(m.percentile(99.0)),
+                        "max_ms": ms(m.max()),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// How much throughput changed with telemetry on, as a percentage of the
+/// throughput without it.
+fn throughput_change(on: f64, off: f64) -> String {
+    if off == 0.0 {
+        return "-".to_string();
+    }
+    format!("{:+.1}%", (on - off) / off * 100.0)
+}
+
+/// The latency telemetry added, which can come out negative in the noise.
+fn added(on: Duration, off: Duration) -> String {
+    let delta = on.as_secs_f64() - off.as_secs_f64();
+    format!("{:+.2} ms", delta * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(millis: &[u64]) -> Measurement {
+        Measurement {
+            latencies: millis.iter().copied().map(Duration::from_millis).collect(),
+            errors: 0,
+            elapsed: Duration::from_secs(2),
+        }
+    }
+
+    fn bench() -> Bench {
+        Bench {
+            examples: Vec::new(),
+            no_compose: true,
+            connections: 8,
+            duration: 2,
+            warmup: 0,
+            scenarios: vec![Scenario::Liveness],
+            json: None,
+        }
+    }
+
+    #[test]
+    fn test_percentiles_are_nearest_rank() {
+        let m = measurement(&(1..=100).collect::<Vec<_>>());
+
+        assert_eq!(m.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(m.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(m.percentile(99.9), Duration::from_millis(100));
+        assert_eq!(m.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(m.max(), Duration::from_millis(100));
+        assert_eq!(m.throughput(), 50.0);
+        assert_eq!(measurement(&[]).percentile(50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_scenarios_authenticate_only_where_they_need_to() {
+        let fixture = Fixture {
+            token: "token".to_string(),
+            slug: "an-article".to_string(),
+        };
+        let target = |scenario: Scenario| scenario.target("http://localhost:8080", &fixture);
+
+        let article = target(Scenario::Article);
+        assert_eq!(article.url, "http://localhost:8080/api/articles/an-article");
+        assert_eq!(article.token.as_deref(), Some("token"));
+        assert!(target(Scenario::Liveness).token.is_none());
+        assert_eq!(
+            target(Scenario::MissingArticle).expected,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_telemetry_off_disables_the_sdk() {
+        let env = Env::load(Example::ActixPostgres).unwrap();
+
+        assert_eq!(Telemetry::Off.env(&env).vars()["OTEL_SDK_DISABLED"], "true");
+        assert_eq!(Telemetry::On.env(&env).vars()["OTEL_SDK_DISABLED"], "false");
+    }
+
+    #[test]
+    fn test_report_compares_telemetry_on_and_off() {
+        let run = |telemetry, millis: &[u64]| Run {
+            example: Example::AxumPostgres,
+            scenario: Scenario::Liveness,
+            telemetry,
+            measurement: measurement(millis),
+        };
+        let runs = [
+            run(Telemetry::On, &[2, 2, 2, 4]),
+            run(Telemetry::Off, &[1, 1, 1, 1, 1]),
+        ];
+
+        let report = bench().report(&runs);
+        assert!(report.contains("## axum-postgres"));
+        assert!(
+            report.contains("| liveness | on | 2 | 2.00 ms | 4.00 ms | 4.00 ms | 4.00 ms | 0 |")
+        );
+        assert!(
+            report.contains("| liveness | cost | -20.0% | +1.00 ms | +3.00 ms | +3.00 ms | | |")
+        );
+
+        let json = bench().json_report(&runs);
+        assert_eq!(json["runs"][1]["telemetry"], "off");
+        assert_eq!(json["runs"][1]["requests_per_sec"], 2.5);
+    }
+}
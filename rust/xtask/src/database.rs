@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use anyhow::Context as _;
+use sqlx::PgPool;
+use sqlx::migrate::Migrator;
+
+use crate::example::{Example, Schema};
+
+/// Long enough for a Postgres container that has just been started.
+const CONNECT_ATTEMPTS: u32 = 30;
+
+pub async fn connect(database_url: &str) -> anyhow::Result<PgPool> {
+    let mut attempt = 1;
+    loop {
+        match PgPool::connect(database_url).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < CONNECT_ATTEMPTS => {
+                if attempt == 1 {
+                    println!("Waiting for Postgres ({e})");
+                }
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => return Err(e).context("Postgres is not reachable at DATABASE_URL"),
+        }
+    }
+}
+
+pub async fn migrate(example: Example, pool: &PgPool) -> anyhow::Result<()> {
+    match example.schema() {
+        Schema::Migrations(dir) => {
+            let migrator = Migrator::new(example.dir().join(dir)).await?;
+            migrator.run(pool).await?;
+            println!("Migrations are up to date ({})", migrator.iter().count());
+        }
+        Schema::Sql {
+            schema, upgrades, ..
+        } => {
+            // The schema is plain CREATE TABLEs, so it only runs once.
+            let exists: bool =
+                sqlx::query_scalar("SELECT to_regclass('public.indicators') IS NOT NULL")
+                    .fetch_one(pool)
+                    .await?;
+            if exists {
+                for upgrade in upgrades {
+                    run_file(example, upgrade, pool).await?;
+                }
+                println!("Schema is already in place");
+            } else {
+                run_file(example, schema, pool).await?;
+                println!("Created the schema from {schema}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads the example's seed SQL, if it has one. It skips rows that are
+/// already there, so running it again is harmless.
+pub async fn seed(example: Example, pool: &PgPool) -> anyhow::Result<()> {
+    if let Schema::Sql { seed, .. } = example.schema() {
+        run_file(example, seed, pool).await?;
+        let (indicators, data_points): (i64, i64) = sqlx::query_as(
+            "SELECT (SELECT count(*) FROM indicators), (SELECT count(*) FROM data_points)",
+        )
+        .fetch_one(pool)
+        .await?;
+        println!("Seeded {indicators} indicators and {data_points} data points");
+    }
+    Ok(())
+}
+
+async fn run_file(example: Example, file: &str, pool: &PgPool) -> anyhow::Result<()> {
+    let path = example.dir().join(file);
+    let sql = tokio::fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    sqlx::raw_sql(&sql)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to run {file}"))?;
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Runs the Rust examples locally, in place of each example's hand-typed
+//! setup: `cargo xtask up axum-postgres` from `rust/` starts its Postgres
+//! and collector, migrates and seeds the database, and runs the api and
+//! worker until Ctrl+C.
+
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use reqwest::Client;
+use sqlx::PgPool;
+
+mod bench;
+mod database;
+mod example;
+mod process;
+mod seed;
+mod traffic;
+
+use example::{Env, Example};
+use process::Running;
+
+#[derive(Parser)]
+#[command(name = "cargo xtask", about = "Run the Rust examples locally")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start Postgres and the collector, migrate, seed, and run the example
+    /// until Ctrl+C
+    Up {
+        example: Example,
+        /// Use the Postgres and collector already at DATABASE_URL and
+        /// OTEL_EXPORTER_OTLP_ENDPOINT instead of starting the compose ones
+        #[arg(long)]
+        no_compose: bool,
+        #[arg(long)]
+        release: bool,
+        /// Generate traffic once the example is up
+        #[arg(long)]
+        traffic: bool,
+        #[command(flatten)]
+        scale: Scale,
+        #[command(flatten)]
+        load: Load,
+    },
+    /// Apply the example's migrations, or its schema, to DATABASE_URL
+    Migrate { example: Example },
+    /// Load the demo data: indicators and data points into the database,
+    /// users and articles through the running api
+    Seed {
+        example: Example,
+        #[command(flatten)]
+        scale: Scale,
+    },
+    /// Build and run the example's binaries until Ctrl+C
+    Run {
+        example: Example,
+        #[arg(long)]
+        release: bool,
+    },
+    /// Send the running example a mix of requests as the demo users
+    Traffic {
+        example: Example,
+        #[command(flatten)]
+        load: Load,
+    },
+    /// Benchmark the examples' api with telemetry on and off
+    Bench(bench::Bench),
+}
+
+/// Generated data on top of the demo data, written straight to the
+/// database.
+#[derive(Args)]
+struct Scale {
+    /// Users to generate, with articles and favorites (axum-postgres and
+    /// actix-postgres)
+    #[arg(long, default_value_t = 0)]
+    users: u32,
+    /// Years of synthetic indicator series to generate (ai-report-generator)
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=i64::from(seed::indicators::MAX_YEARS)))]
+    years: u32,
+}
+
+impl Scale {
+    async fn seed(&self, example: Example, pool: &PgPool) -> anyhow::Result<()> {
+        if self.users > 0 {
+            anyhow::ensure!(example.has_articles(), "{example} has no users to generate");
+            seed::articles::seed(example, pool, self.users).await?;
+        }
+        if self.years > 0 {
+            anyhow::ensure!(
+                !example.has_articles(),
+                "{example} has no indicators to generate"
+            );
+            seed::indicators::seed(pool, self.years).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct Load {
+    /// Requests per second
+    #[arg(long, default_value_t = 5.0)]
+    rate: f64,
+    /// Seconds to run for; 0 runs until Ctrl+C
+    #[arg(long, default_value_t = 0)]
+    duration: u64,
+}
+
+impl Load {
+    async fn run(&self, client: &Client, env: &Env, example: Example) -> anyhow::Result<()> {
+        traffic::run(
+            client,
+            &env.base_url(),
+            example,
+            self.rate,
+            Duration::from_secs(self.duration),
+        )
+        .await
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let example = match &cli.command {
+        Command::Up { example, .. }
+        | Command::Migrate { example }
+        | Command::Seed { example, .. }
+        | Command::Run { example, .. }
+        | Command::Traffic { example, .. } => *example,
+        Command::Bench(bench) => return bench.run().await,
+    };
+    let env = Env::load(example)?;
+    // The examples serve a self-signed certificate when TLS_CERT_PATH is set.
+    let client = Client::builder()
+        .danger_accept_invalid_certs(env.base_url().starts_with("https://"))
+        .build()?;
+
+    match cli.command {
+        Command::Up {
+            no_compose,
+            release,
+            traffic,
+            scale,
+            load,
+            ..
+        } => {
+            if !no_compose {
+                process::compose_up(example).await?;
+            }
+            let pool = database::connect(env.database_url()).await?;
+            database::migrate(example, &pool).await?;
+            database::seed(example, &pool).await?;
+            scale.seed(example, &pool).await?;
+            pool.close().await;
+
+            process::build(example, release).await?;
+            let mut running = Running::spawn(example, &env, release)?;
+            let started = async {
+                running
+                    .wait_healthy(&client, &format!("{}/api/health", env.base_url()))
+                    .await?;
+                if example.has_articles() {
+                    seed::seed_api(&client, &env.base_url()).await?;
+                }
+                println!("{example} is up at {}", env.base_url());
+                if traffic {
+                    load.run(&client, &env, example).await?;
+                }
+                anyhow::Ok(())
+            }
+            .await;
+            match started {
+                Ok(()) if traffic => running.shutdown().await,
+                Ok(()) => running.wait().await,
+                Err(e) => {
+                    running.shutdown().await?;
+                    Err(e)
+                }
+            }
+        }
+        Command::Migrate { .. } => {
+            let pool = database::connect(env.database_url()).await?;
+            database::migrate(example, &pool).await
+        }
+        Command::Seed { scale, .. } => {
+            let pool = database::connect(env.database_url()).await?;
+            database::seed(example, &pool).await?;
+            scale.seed(example, &pool).await?;
+            if example.has_articles() {
+                seed::seed_api(&client, &env.base_url()).await?;
+            }
+            Ok(())
+        }
+        Command::Run { release, .. } => {
+            process::build(example, release).await?;
+            Running::spawn(example, &env, release)?.wait().await
+        }
+        Command::Traffic { load, .. } => load.run(&client, &env, example).await,
+        Command::Bench(_) => unreachable!("benchmarks return above"),
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use reqwest::Client;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+
+use crate::example::{Binary, Env, Example};
+
+/// Covers a first debug build's startup, not its compile, which
+/// [`build`] has already done.
+const HEALTHY_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long the binaries get to drain after SIGTERM before being killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
+
+async fn status(command: &mut Command, what: &str) -> anyhow::Result<()> {
+    let status = command
+        .status()
+        .await
+        .with_context(|| format!("Failed to start {what}"))?;
+    anyhow::ensure!(status.success(), "{what} failed with {status}");
+    Ok(())
+}
+
+/// Starts the example's Postgres and collector from its compose file and
+/// waits for their healthchecks.
+pub async fn compose_up(example: Example) -> anyhow::Result<()> {
+    status(
+        Command::new("docker")
+            .args([
+                "compose",
+                "up",
+                "--detach",
+                "--wait",
+                "postgres",
+                "otel-collector",
+            ])
+            .current_dir(example.dir()),
+        "docker compose",
+    )
+    .await
+}
+
+/// Stops what [`compose_up`] started, keeping the data. The examples'
+/// compose files publish the same ports, so one has to stop before the
+/// other starts.
+pub async fn compose_stop(example: Example) -> anyhow::Result<()> {
+    status(
+        Command::new("docker")
+            .args(["compose", "stop", "postgres", "otel-collector"])
+            .current_dir(example.dir()),
+        "docker compose",
+    )
+    .await
+}
+
+pub async fn build(example: Example, release: bool) -> anyhow::Result<()> {
+    let mut command = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+    command.args(["build", "--bins"]).current_dir(example.dir());
+    if release {
+        command.arg("--release");
+    }
+    status(&mut command, "cargo build").await
+}
+
+/// The example's binaries, running in its directory with its [`Env`].
+pub struct Running {
+    children: Vec<(&'static str, Child)>,
+}
+
+impl Running {
+    pub fn spawn(example: Example, env: &Env, release: bool) -> anyhow::Result<Self> {
+        let mut children = Vec::new();
+        for binary in example.binaries() {
+            let child = command(example, binary, env, release)
+                .spawn()
+                .with_context(|| format!("Failed to start {}", binary.name))?;
+            children.push((binary.name, child));
+        }
+        Ok(Self { children })
+    }
+
+    /// Only the release api, without the worker, writing its output to
+    /// `log` instead of the terminal.
+    pub fn spawn_api(example: Example, env: &Env, log: &Path) -> anyhow::Result<Self> {
+        let binary = &example.binaries()[0];
+        let file = std::fs::File::create(log)
+            .with_context(|| format!("Failed to create {}", log.display()))?;
+        let child = command(example, binary, env, true)
+            .stdout(file.try_clone()?)
+            .stderr(file)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", binary.name))?;
+        Ok(Self {
+            children: vec![(binary.name, child)],
+        })
+    }
+
+    fn exited(&mut self) -> anyhow::Result<Option<(&'static str, ExitStatus)>> {
+        for (name, child) in &mut self.children {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some((name, status)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Polls `url` until it answers 2xx, failing early if a binary exits.
+    pub async fn wait_healthy(&mut self, client: &Client, url: &str) -> anyhow::Result<()> {
+        let deadline = Instant::now() + HEALTHY_TIMEOUT;
+        loop {
+            if let Some((name, status)) = self.exited()? {
+                anyhow::bail!("{name} exited with {status} before it was healthy");
+            }
+            let healthy = client
+                .get(url)
+                .timeout(Duration::from_secs(2))
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            if healthy {
+                return Ok(());
+            }
+            anyhow::ensure!(
+                Instant::now() < deadline,
+                "{url} was not healthy after {HEALTHY_TIMEOUT:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Runs until Ctrl+C or until a binary exits, then stops the rest.
+    pub async fn wait(mut self) -> anyhow::Result<()> {
+        let mut poll = tokio::time::interval(Duration::from_millis(500));
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        let exited = loop {
+            tokio::select! {
+                _ = poll.tick() => {
+                    if let Some(exited) = self.exited()? {
+                        break Some(exited);
+                    }
+                }
+                _ = &mut ctrl_c => break None,
+            }
+        };
+        self.shutdown().await?;
+        match exited {
+            Some((name, status)) if !status.success() => {
+                anyhow::bail!("{name} exited with {status}")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// SIGTERM, which every binary handles as a graceful shutdown, and
+    /// SIGKILL for any still running after [`SHUTDOWN_TIMEOUT`].
+    pub async fn shutdown(&mut self) -> anyhow::Result<()> {
+        for (_, child) in &mut self.children {
+            terminate(child)?;
+        }
+        for (name, child) in &mut self.children {
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, child.wait())
+                .await
+                .is_err()
+            {
+                println!("{name} did not stop after {SHUTDOWN_TIMEOUT:?}, killing it");
+                child.kill().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn command(example: Example, binary: &Binary, env: &Env, release: bool) -> Command {
+    let profile = if release { "release" } else { "debug" };
+    let mut command = Command::new(example.dir().join("target").join(profile).join(binary.name));
+    command
+        .current_dir(example.dir())
+        .envs(env.vars())
+        .kill_on_drop(true);
+    // As in compose, each binary reports as its own service unless the
+    // shell says otherwise.
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        command.env("OTEL_SERVICE_NAME", binary.service_name);
+    }
+    command
+}
+
+#[cfg(unix)]
+fn terminate(child: &mut Child) -> anyhow::Result<()> {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) takes no pointers; the pid is our own child's.
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) -> anyhow::Result<()> {
+    Ok(child.start_kill()?)
+}
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use argon2::{
+    Argon2, PasswordHasher,
+    password_hash::{SaltString, rand_core::OsRng},
+};
+use chrono::{Duration, Utc};
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom as _, SliceRandom as _};
+use rand::{Rng as _, SeedableRng as _};
+use rand_distr::{Distribution, Geometric, Zipf};
+use sqlx::PgPool;
+
+use crate::example::Example;
+
+/// Rows per INSERT.
+const BATCH: usize = 1_000;
+/// Article dates are spread over this many days before now.
+const HISTORY_DAYS: i64 = 730;
+/// Share of users who only read.
+const READERS: f64 = 0.6;
+/// Zipf exponent of favorites over articles: the most popular article gets
+/// a little over twice the favorites of the next.
+const FAVORITE_SKEW: f64 = 1.1;
+/// Views per favorite, roughly; axum-postgres ranks trending on both.
+const VIEWS_PER_FAVORITE: i64 = 25;
+/// Mixed into the favorites' seeds so they don't repeat the users'.
+const FAVORITES_SEED: u64 = 0x5eed;
+
+const FIRST_NAMES: [&str; 16] = [
+    "Amara", "Ben", "Chloe", "Dev", "Elena", "Farid", "Grace", "Hiro", "Ines", "Jonas", "Kofi",
+    "Lena", "Mateo", "Nadia", "Omar", "Priya",
+];
+const LAST_NAMES: [&str; 16] = [
+    "Adeyemi", "Berg", "Costa", "Dubois", "Eriksen", "Fischer", "Gupta", "Hughes", "Ivanova",
+    "Jensen", "Kim", "Lopez", "Moreau", "Novak", "Okoro", "Patel",
+];
+const TOPICS: [&str; 16] = [
+    "connection pools",
+    "trace sampling",
+    "latency budgets",
+    "retry storms",
+    "cache hit rates",
+    "database indexes",
+    "queue backlogs",
+    "error budgets",
+    "cold starts",
+    "log levels",
+    "span attributes",
+    "rate limits",
+    "request deadlines",
+    "feature flags",
+    "memory leaks",
+    "TLS handshakes",
+];
+/// `{}` is replaced with a topic.
+const TITLES: [&str; 8] = [
+    "Notes on {}",
+    "What we learned about {}",
+    "A field guide to {}",
+    "Debugging {} in production",
+    "Measuring {}",
+    "Rethinking {}",
+    "The hidden cost of {}",
+    "Getting started with {}",
+];
+const DESCRIPTIONS: [&str; 6] = [
+    "A short write-up from last quarter's incidents",
+    "What the dashboards showed and what they hid",
+    "Lessons from running it at scale",
+    "A checklist we now use on every service",
+    "The numbers before and after",
+    "Why the defaults were not enough",
+];
+const SENTENCES: [&str; 12] = [
+    "The first sign was a slow climb in p99 latency.",
+    "Nothing in the logs looked wrong at first.",
+    "Tracing the request end to end showed where the time went.",
+    "We compared the histogram before and after the change.",
+    "Most of the cost came from a handful of endpoints.",
+    "The fix was a one-line configuration change.",
+    "It took a week of data to be sure the trend was real.",
+    "Alerting on the rate of errors beat alerting on the count.",
+    "The collector dropped spans whenever its queue filled up.",
+    "Adding one attribute to the span made the pattern obvious.",
+    "Load tests only reproduced it with realistic data.",
+    "We now review this metric in every postmortem.",
+];
+
+struct User {
+    email: String,
+    username: String,
+    name: String,
+    articles: Vec<Article>,
+}
+
+struct Article {
+    slug: String,
+    title: String,
+    description: String,
+    body: String,
+    age: Duration,
+}
+
+/// User `n` and their articles. Each user has their own seed, so the same
+/// `n` always comes out the same however many users are generated.
+fn user(n: u32) -> User {
+    let mut rng = StdRng::seed_from_u64(n.into());
+    let first = FIRST_NAMES.choose(&mut rng).expect("names are not empty");
+    let last = LAST_NAMES.choose(&mut rng).expect("names are not empty");
+    // Most users never write; of those who do, a few write most articles.
+    let count = if rng.random_bool(READERS) {
+        0
+    } else {
+        1 + Geometric::new(0.35)
+            .expect("p is in (0, 1]")
+            .sample(&mut rng)
+    };
+    let articles = (0..count.min(40))
+        .map(|k| {
+            let topic = TOPICS.choose(&mut rng).expect("topics are not empty");
+            let title = TITLES
+                .choose(&mut rng)
+                .expect("titles are not empty")
+                .replace("{}", topic);
+            let title = capitalize(&title);
+            let paragraphs = (0..rng.random_range(2..=5))
+                .map(|_| {
+                    (0..rng.random_range(2..=4))
+                        .map(|_| *SENTENCES.choose(&mut rng).expect("sentences are not empty"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>();
+            Article {
+                slug: format!("{}-{n}-{k}", slugify(&title)),
+                description: DESCRIPTIONS
+                    .choose(&mut rng)
+                    .expect("descriptions are not empty")
+                    .to_string(),
+                body: paragraphs.join("\n\n"),
+                age: Duration::minutes(rng.random_range(0..HISTORY_DAYS * 24 * 60)),
+                title,
+            }
+        })
+        .collect();
+    User {
+        email: format!("user{n}@load.example.com"),
+        username: format!("user{n}"),
+        name: format!("{first} {last}"),
+        articles,
+    }
+}
+
+/// The popularity ranks, out of `articles`, of the articles user `n`
+/// favorites. A few articles get most of the favorites, as on a real site.
+fn favorites(n: u32, articles: usize) -> Vec<usize> {
+    if articles == 0 {
+        return Vec::new();
+    }
+    let mut rng = StdRng::seed_from_u64(u64::from(n) ^ FAVORITES_SEED);
+    let count = Geometric::new(0.15)
+        .expect("p is in (0, 1]")
+        .sample(&mut rng)
+        .min(articles as u64);
+    let zipf = Zipf::new(articles as f64, FAVORITE_SKEW).expect("n >= 1 and s > 0");
+    let mut picked = (0..count)
+        .map(|_| zipf.sample(&mut rng) as usize - 1)
+        .collect::<Vec<_>>();
+    picked.sort_unstable();
+    picked.dedup();
+    picked
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn slugify(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// axum-postgres' rows belong to an organization; generated ones go in
+/// `default`. Returns the extra column and its value for an INSERT.
+fn tenant(example: Example) -> (&'static str, &'static str) {
+    match example {
+        Example::AxumPostgres => (", org_id", ", 1"),
+        _ => ("", ""),
+    }
+}
+
+/// Upserts `users` generated users with their articles and favorites, all
+/// with the demo password. Rows that are already there are left alone, so
+/// running it again with the same count adds nothing.
+pub async fn seed(example: Example, pool: &PgPool, users: u32) -> anyhow::Result<()> {
+    let (org_column, org_value) = tenant(example);
+    let password_hash = Argon2::default()
+        .hash_password(
+            super::PASSWORD.as_bytes(),
+            &SaltString::generate(&mut OsRng),
+        )
+        .map_err(|e| anyhow::anyhow!("Password hashing failed: {e}"))?
+        .to_string();
+    let generated = (0..users).map(user).collect::<Vec<_>>();
+
+    let mut added_users = 0;
+    let mut user_ids = HashMap::new();
+    for batch in generated.chunks(BATCH) {
+        let emails = batch.iter().map(|u| u.email.clone()).collect::<Vec<_>>();
+        let usernames = batch.iter().map(|u| u.username.clone()).collect::<Vec<_>>();
+        let names = batch.iter().map(|u| u.name.clone()).collect::<Vec<_>>();
+        added_users += sqlx::query(&format!(
+            "INSERT INTO users (email, password_hash, username, name{org_column}) \
+             SELECT email, $2, username, name{org_value} \
+             FROM UNNEST($1::text[], $3::text[], $4::text[]) AS u(email, name, username) \
+             ON CONFLICT (email) DO NOTHING"
+        ))
+        .bind(&emails)
+        .bind(&password_hash)
+        .bind(&names)
+        .bind(&usernames)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        let ids: Vec<(String, i32)> =
+            sqlx::query_as("SELECT email, id FROM users WHERE email = ANY($1)")
+                .bind(&emails)
+                .fetch_all(pool)
+                .await?;
+        user_ids.extend(ids);
+    }
+
+    let now = Utc::now();
+    let articles = generated
+        .iter()
+        .flat_map(|u| u.articles.iter().map(|a| (user_ids[&u.email], a)))
+        .collect::<Vec<_>>();
+    let mut added_articles = 0;
+    let mut article_ids = Vec::new();
+    for batch in articles.chunks(BATCH) {
+        let slugs = batch
+            .iter()
+            .map(|(_, a)| a.slug.clone())
+            .collect::<Vec<_>>();
+        added_articles += sqlx::query(&format!(
+            "INSERT INTO articles (slug, title, description, body, author_id, created_at, updated_at{org_column}) \
+             SELECT slug, title, description, body, author_id, created_at, created_at{org_value} \
+             FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::int[], $6::timestamptz[]) \
+                 AS a(slug, title, description, body, author_id, created_at) \
+             ON CONFLICT DO NOTHING"
+        ))
+        .bind(&slugs)
+        .bind(batch.iter().map(|(_, a)| a.title.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|(_, a)| a.description.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|(_, a)| a.body.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|(author, _)| *author).collect::<Vec<_>>())
+        .bind(batch.iter().map(|(_, a)| now - a.age).collect::<Vec<_>>())
+        .execute(pool)
+        .await?
+        .rows_affected();
+        let ids: Vec<(i32,)> = sqlx::query_as("SELECT id FROM articles WHERE slug = ANY($1)")
+            .bind(&slugs)
+            .fetch_all(pool)
+            .await?;
+        article_ids.extend(ids.into_iter().map(|(id,)| id));
+    }
+
+    // Popularity has nothing to do with age or author.
+    article_ids.sort_unstable();
+    article_ids.shuffle(&mut StdRng::seed_from_u64(FAVORITES_SEED));
+    let pairs = (0..users)
+        .flat_map(|n| {
+            let user_id = user_ids[&format!("user{n}@load.example.com")];
+            let article_ids = &article_ids;
+            favorites(n, article_ids.len())
+                .into_iter()
+                .map(move |rank| (user_id, article_ids[rank]))
+        })
+        .collect::<Vec<_>>();
+    let mut added_favorites = 0;
+    for batch in pairs.chunks(BATCH * 5) {
+        added_favorites += sqlx::query(&format!(
+            "INSERT INTO favorites (user_id, article_id{org_column}) \
+             SELECT user_id, article_id{org_value} FROM UNNEST($1::int[], $2::int[]) AS f(user_id, article_id) \
+             ON CONFLICT DO NOTHING"
+        ))
+        .bind(batch.iter().map(|(user, _)| *user).collect::<Vec<_>>())
+        .bind(batch.iter().map(|(_, article)| *article).collect::<Vec<_>>())
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+
+    sqlx::query(
+        "UPDATE articles a SET favorites_count = f.count \
+         FROM (SELECT article_id, count(*)::int AS count FROM favorites \
+               WHERE article_id = ANY($1) GROUP BY article_id) f \
+         WHERE a.id = f.article_id AND a.favorites_count <> f.count",
+    )
+    .bind(&article_ids)
+    .execute(pool)
+    .await?;
+    if example == Example::AxumPostgres {
+        sqlx::query(
+            "UPDATE articles SET views_count = favorites_count * $2 + id % 97 \
+             WHERE id = ANY($1) AND views_count < favorites_count * $2 + id % 97",
+        )
+        .bind(&article_ids)
+        .bind(VIEWS_PER_FAVORITE)
+        .execute(pool)
+        .await?;
+    }
+
+    println!(
+        "Generated {users} users, {} articles and {} favorites ({added_users}, {added_articles} and {added_favorites} new)",
+        article_ids.len(),
+        pairs.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_users_are_the_same_whatever_the_count() {
+        let first = user(42);
+        let again = user(42);
+
+        assert_eq!(first.email, "user42@load.example.com");
+        assert_eq!(first.username, "user42");
+        assert_eq!(first.name, again.name);
+        assert_eq!(
+            first.articles.iter().map(|a| &a.slug).collect::<Vec<_>>(),
+            again.articles.iter().map(|a| &a.slug).collect::<Vec<_>>()
+        );
+        assert_eq!(favorites(42, 500), favorites(42, 500));
+    }
+
+    #[test]
+    fn test_favorites_concentrate_on_the_most_popular_articles() {
+        let mut counts = vec![0; 1_000];
+        for n in 0..5_000 {
+            for rank in favorites(n, counts.len()) {
+                counts[rank] += 1;
+            }
+        }
+        let total: u32 = counts.iter().sum();
+        let top: u32 = counts[..10].iter().sum();
+
+        assert!(counts[0] > counts[1] && counts[1] > counts[10]);
+        // The top 1% get over a third of the favorites.
+        assert!(top * 3 > total, "top 10 have {top} of {total}");
+    }
+}
@@ -0,0 +1,246 @@
+use std::f64::consts::TAU;
+
+use chrono::NaiveDate;
+use rand::SeedableRng as _;
+use rand::rngs::StdRng;
+use rand_distr::{Distribution, Normal};
+use sqlx::PgPool;
+
+/// The generated series end where db/seed.sql's do.
+const LAST_YEAR: i32 = 2023;
+/// The most `--years` can ask for.
+pub const MAX_YEARS: u32 = 50;
+/// How much of each month's noise carries into the next.
+const PERSISTENCE: f64 = 0.7;
+/// Length of the business cycle every series swings with.
+const CYCLE_YEARS: f64 = 8.0;
+
+#[derive(Clone, Copy)]
+enum Frequency {
+    Monthly,
+    Quarterly,
+}
+
+impl Frequency {
+    fn name(self) -> &'static str {
+        match self {
+            Frequency::Monthly => "Monthly",
+            Frequency::Quarterly => "Quarterly",
+        }
+    }
+
+    fn months(self) -> u32 {
+        match self {
+            Frequency::Monthly => 1,
+            Frequency::Quarterly => 3,
+        }
+    }
+}
+
+/// A series as trend × (1 + seasonality + cycle + noise).
+struct Series {
+    code: &'static str,
+    name: &'static str,
+    frequency: Frequency,
+    unit: &'static str,
+    /// The value in January, `MAX_YEARS` before [`LAST_YEAR`] ends.
+    start: f64,
+    /// Compound growth per year.
+    growth: f64,
+    /// Share added in each calendar month, January first.
+    seasonality: [f64; 12],
+    /// Amplitude of the business cycle, as a share; negative for series
+    /// that rise in downturns.
+    cycle: f64,
+    /// Standard deviation of the noise, as a share.
+    noise: f64,
+}
+
+const SERIES: [Series; 5] = [
+    Series {
+        code: "SYN_RETAIL",
+        name: "Retail Sales (synthetic)",
+        frequency: Frequency::Monthly,
+        unit: "Millions of Dollars",
+        start: 60_000.0,
+        growth: 0.045,
+        seasonality: [
+            -0.10, -0.12, 0.0, -0.01, 0.03, 0.01, 0.01, 0.03, -0.04, 0.0, 0.03, 0.16,
+        ],
+        cycle: 0.03,
+        noise: 0.01,
+    },
+    Series {
+        code: "SYN_UNRATE",
+        name: "Unemployment Rate (synthetic)",
+        frequency: Frequency::Monthly,
+        unit: "Percent",
+        start: 6.0,
+        growth: 0.0,
+        seasonality: [
+            0.06, 0.05, 0.02, -0.04, -0.04, 0.03, 0.04, 0.0, -0.03, -0.04, -0.04, -0.01,
+        ],
+        cycle: -0.25,
+        noise: 0.03,
+    },
+    Series {
+        code: "SYN_HOUST",
+        name: "Housing Starts (synthetic)",
+        frequency: Frequency::Monthly,
+        unit: "Thousands of Units",
+        start: 1_400.0,
+        growth: 0.003,
+        seasonality: [
+            -0.25, -0.20, 0.02, 0.14, 0.18, 0.17, 0.12, 0.10, 0.05, 0.04, -0.12, -0.25,
+        ],
+        cycle: 0.20,
+        noise: 0.05,
+    },
+    Series {
+        code: "SYN_CPI",
+        name: "Consumer Price Index (synthetic)",
+        frequency: Frequency::Monthly,
+        unit: "Index 1982-84=100",
+        start: 45.0,
+        growth: 0.035,
+        seasonality: [0.0; 12],
+        cycle: 0.005,
+        noise: 0.002,
+    },
+    Series {
+        code: "SYN_GDP",
+        name: "Gross Domestic Product (synthetic)",
+        frequency: Frequency::Quarterly,
+        unit: "Billions of Dollars",
+        start: 1_500.0,
+        growth: 0.055,
+        seasonality: [0.0; 12],
+        cycle: 0.02,
+        noise: 0.005,
+    },
+];
+
+impl Series {
+    /// Every observation from `MAX_YEARS` back to the end of [`LAST_YEAR`].
+    /// Always generated from the same start with the same seed, so a date
+    /// gets the same value whatever `--years` is.
+    fn observations(&self, seed: u64) -> Vec<(NaiveDate, f64)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let shock = Normal::new(0.0, self.noise * (1.0 - PERSISTENCE.powi(2)).sqrt())
+            .expect("the noise is not negative");
+        let first_year = LAST_YEAR - MAX_YEARS as i32 + 1;
+        let mut noise = 0.0;
+        (0..MAX_YEARS * 12)
+            .step_by(self.frequency.months() as usize)
+            .map(|month| {
+                let years = f64::from(month) / 12.0;
+                noise = PERSISTENCE * noise + shock.sample(&mut rng);
+                let factor = 1.0
+                    + self.seasonality[(month % 12) as usize]
+                    + self.cycle * (TAU * years / CYCLE_YEARS).sin()
+                    + noise;
+                let value = self.start * (1.0 + self.growth).powf(years) * factor;
+                let date =
+                    NaiveDate::from_ymd_opt(first_year + (month / 12) as i32, month % 12 + 1, 1)
+                        .expect("the first of a month is a date");
+                (date, (value * 100.0).round() / 100.0)
+            })
+            .collect()
+    }
+}
+
+/// Upserts the synthetic indicators with the last `years` of their series.
+/// Values are stable, so running it again changes nothing.
+pub async fn seed(pool: &PgPool, years: u32) -> anyhow::Result<()> {
+    let since = NaiveDate::from_ymd_opt(LAST_YEAR - years as i32 + 1, 1, 1)
+        .expect("--years is at most MAX_YEARS");
+    let mut points = 0;
+    let mut changed = 0;
+    for (seed, series) in (0..).zip(&SERIES) {
+        let description = format!(
+            "Generated by `cargo xtask seed --years`, not real data: {:.1}% a year of growth \
+             with seasonality, an {CYCLE_YEARS}-year cycle and noise.",
+            series.growth * 100.0
+        );
+        let (id,): (i32,) = sqlx::query_as(
+            "INSERT INTO indicators (code, name, frequency, unit, description) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (code) DO UPDATE SET name = EXCLUDED.name, frequency = EXCLUDED.frequency, \
+                 unit = EXCLUDED.unit, description = EXCLUDED.description \
+             RETURNING id",
+        )
+        .bind(series.code)
+        .bind(series.name)
+        .bind(series.frequency.name())
+        .bind(series.unit)
+        .bind(description)
+        .fetch_one(pool)
+        .await?;
+
+        let (dates, values): (Vec<_>, Vec<_>) = series
+            .observations(seed)
+            .into_iter()
+            .filter(|(date, _)| *date >= since)
+            .unzip();
+        points += dates.len();
+        changed += sqlx::query(
+            "INSERT INTO data_points (indicator_id, observation_date, value) \
+             SELECT $1, observation_date, value FROM UNNEST($2::date[], $3::float8[]) \
+                 AS d(observation_date, value) \
+             ON CONFLICT (indicator_id, observation_date) DO UPDATE SET value = EXCLUDED.value \
+             WHERE data_points.value IS DISTINCT FROM EXCLUDED.value",
+        )
+        .bind(id)
+        .bind(dates)
+        .bind(values)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    // The server refreshes it on a timer; this saves waiting for that.
+    sqlx::query("REFRESH MATERIALIZED VIEW indicator_summaries")
+        .execute(pool)
+        .await?;
+    println!(
+        "Generated {} synthetic indicators with {points} data points ({changed} new or changed)",
+        SERIES.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Datelike as _;
+
+    use super::*;
+
+    #[test]
+    fn test_series_keep_their_seasonality_and_trend() {
+        let retail = SERIES[0].observations(0);
+        let mean = |month: u32, years: std::ops::Range<i32>| {
+            let values = retail
+                .iter()
+                .filter(|(date, _)| date.month() == month && years.contains(&date.year()))
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>();
+            values.iter().copied().sum::<f64>() / values.len() as f64
+        };
+
+        assert_eq!(retail.len(), MAX_YEARS as usize * 12);
+        assert!(mean(12, 2014..2024) > mean(1, 2014..2024) * 1.2);
+        assert!(mean(6, 2014..2024) > mean(6, 1974..1984) * 3.0);
+        assert_eq!(SERIES[0].observations(0), retail);
+    }
+
+    #[test]
+    fn test_quarterly_series_start_each_quarter() {
+        let gdp = SERIES[4].observations(4);
+
+        assert_eq!(gdp.len(), MAX_YEARS as usize * 4);
+        assert!(
+            gdp.iter()
+                .all(|(date, _)| [1, 4, 7, 10].contains(&date.month()))
+        );
+        assert_eq!(gdp.last().unwrap().0.year(), LAST_YEAR);
+    }
+}
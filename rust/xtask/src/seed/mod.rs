@@ -0,0 +1,145 @@
+pub mod articles;
+pub mod indicators;
+
+use anyhow::Context as _;
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+
+/// Every demo user's password.
+pub const PASSWORD: &str = "password123";
+
+/// Email, username and display name.
+pub const USERS: [(&str, &str, &str); 3] = [
+    ("alice@example.com", "alice", "Alice Chen"),
+    ("bob@example.com", "bob", "Bob Okafor"),
+    ("carol@example.com", "carol", "Carol Diaz"),
+];
+
+/// Title, description and markdown body, two per user.
+const ARTICLES: [(&str, &str, &str); 6] = [
+    (
+        "Tracing a Request Across Services",
+        "Follow one request from the edge to the database",
+        "A trace is a tree of **spans**. Each service adds its own and passes the \
+         context on in the `traceparent` header.\n\n- Start at the root span\n- Look \
+         for the slowest child\n- Check its attributes",
+    ),
+    (
+        "Reading a Latency Histogram",
+        "Why the average hides your slowest users",
+        "Percentiles come from bucketed histograms. Pick buckets around your SLO, \
+         not around the mean.",
+    ),
+    (
+        "Connection Pools Under Load",
+        "What pool wait time tells you",
+        "When every connection is busy, requests queue for the pool. Chart the \
+         pool's wait time next to request latency.",
+    ),
+    (
+        "Structured Logs With Trace IDs",
+        "Jump from a log line to its trace",
+        "Logs that carry `trace_id` and `span_id` can be correlated with the \
+         request that wrote them.",
+    ),
+    (
+        "Sampling Without Losing Errors",
+        "Keep every failed trace and a share of the rest",
+        "Head sampling decides up front. Tail sampling in the collector can keep \
+         traces **after** it sees an error.",
+    ),
+    (
+        "Background Jobs and Trace Context",
+        "Linking a job to the request that queued it",
+        "The job row stores the trace context, and the worker starts its span \
+         from it, so one trace covers both.",
+    ),
+];
+
+/// Registers the demo users and gives each of them articles and favorites.
+/// Users that already exist are left alone, so seeding twice adds nothing.
+pub async fn seed_api(client: &Client, base_url: &str) -> anyhow::Result<()> {
+    let mut created = Vec::new();
+    for (email, username, name) in USERS {
+        let response = client
+            .post(format!("{base_url}/api/register"))
+            .json(&json!({
+                "email": email,
+                "password": PASSWORD,
+                "username": username,
+                "name": name,
+            }))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::CREATED => created.push(token(response).await?),
+            StatusCode::CONFLICT => println!("{email} is already registered"),
+            status => anyhow::bail!("Registering {email} failed with {status}"),
+        }
+    }
+    if created.is_empty() {
+        return Ok(());
+    }
+
+    let mut slugs = Vec::new();
+    for (i, token) in created.iter().enumerate() {
+        for (title, description, body) in ARTICLES.iter().skip(i * 2).take(2) {
+            let article: Value = client
+                .post(format!("{base_url}/api/articles"))
+                .bearer_auth(token)
+                .json(&json!({ "title": title, "description": description, "body": body }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            slugs.push(slug(&article)?.to_string());
+        }
+    }
+    // Everyone favorites the articles they didn't write.
+    for (i, token) in created.iter().enumerate() {
+        for (j, slug) in slugs.iter().enumerate() {
+            if j / 2 != i {
+                client
+                    .post(format!("{base_url}/api/articles/{slug}/favorite"))
+                    .bearer_auth(token)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+    }
+    println!(
+        "Seeded {} users and {} articles",
+        created.len(),
+        slugs.len()
+    );
+    Ok(())
+}
+
+/// A demo user's token, for generating traffic as them.
+pub async fn login(client: &Client, base_url: &str, email: &str) -> anyhow::Result<String> {
+    let response = client
+        .post(format!("{base_url}/api/login"))
+        .json(&json!({ "email": email, "password": PASSWORD }))
+        .send()
+        .await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        anyhow::bail!("{email} can't log in; run `cargo xtask seed` first");
+    }
+    token(response.error_for_status()?).await
+}
+
+async fn token(response: reqwest::Response) -> anyhow::Result<String> {
+    let body: Value = response.json().await?;
+    body["user"]["token"]
+        .as_str()
+        .map(str::to_string)
+        .context("The response has no user.token")
+}
+
+pub fn slug(article: &Value) -> anyhow::Result<&str> {
+    article["article"]["slug"]
+        .as_str()
+        .context("The response has no article.slug")
+}
@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::Rng as _;
+use rand::distr::{Distribution, weighted::WeightedIndex};
+use rand::seq::IndexedRandom as _;
+use reqwest::{Client, Method, StatusCode};
+use serde_json::{Value, json};
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::example::Example;
+use crate::seed;
+
+/// How often running totals are printed.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+/// Tries at listing the articles before giving up on a 503.
+const LIST_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Call {
+    Get(&'static str),
+    Article,
+    MissingArticle,
+    Profile,
+    Login,
+    Favorite,
+}
+
+/// What a browsing user does, by weight. Mostly reads, some writes, and a
+/// few requests that fail on purpose so error traces show up too.
+fn mix(example: Example) -> &'static [(u32, Call)] {
+    match example {
+        Example::AxumPostgres => &[
+            (1, Call::Get("/api/health")),
+            (3, Call::Get("/api/articles")),
+            (2, Call::Get("/api/articles/trending")),
+            (1, Call::Get("/api/articles/suggest?q=tra")),
+            (4, Call::Article),
+            (1, Call::MissingArticle),
+            (2, Call::Profile),
+            (1, Call::Login),
+            (1, Call::Favorite),
+        ],
+        Example::ActixPostgres => &[
+            (1, Call::Get("/api/health")),
+            (3, Call::Get("/api/articles")),
+            (4, Call::Article),
+            (1, Call::MissingArticle),
+            (2, Call::Profile),
+            (1, Call::Login),
+            (1, Call::Favorite),
+        ],
+        // Only calls that stay away from the LLM providers, which cost money.
+        Example::AiReportGenerator => &[
+            (1, Call::Get("/api/health")),
+            (3, Call::Get("/api/indicators")),
+            (2, Call::Get("/api/report-types")),
+            (1, Call::Get("/api/providers")),
+            (2, Call::Get("/api/reports")),
+            (
+                1,
+                Call::Get("/api/reports/00000000-0000-0000-0000-000000000000"),
+            ),
+        ],
+    }
+}
+
+/// The demo users' tokens and their articles' slugs.
+struct Demo {
+    tokens: Vec<String>,
+    slugs: Vec<String>,
+}
+
+impl Demo {
+    async fn load(client: &Client, base_url: &str, example: Example) -> anyhow::Result<Self> {
+        if !example.has_articles() {
+            return Ok(Self {
+                tokens: Vec::new(),
+                slugs: Vec::new(),
+            });
+        }
+        let mut tokens = Vec::new();
+        for (email, ..) in seed::USERS {
+            tokens.push(seed::login(client, base_url, email).await?);
+        }
+        // Listings are the first thing axum-postgres sheds while it is busy,
+        // as it can be just after startup.
+        let mut attempt = 1;
+        let articles: Value = loop {
+            let response = client
+                .get(format!("{base_url}/api/articles?limit=50"))
+                .send()
+                .await?;
+            if response.status() == StatusCode::SERVICE_UNAVAILABLE && attempt < LIST_ATTEMPTS {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            break response.error_for_status()?.json().await?;
+        };
+        let slugs = articles["articles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|article| article["slug"].as_str().map(str::to_string))
+            .collect::<Vec<_>>();
+        if slugs.is_empty() {
+            anyhow::bail!("There are no articles; run `cargo xtask seed` first");
+        }
+        Ok(Self { tokens, slugs })
+    }
+
+    fn request(&self, client: &Client, base_url: &str, call: Call) -> reqwest::RequestBuilder {
+        let mut rng = rand::rng();
+        let token = self.tokens.choose(&mut rng);
+        let slug = self.slugs.choose(&mut rng).map_or("", String::as_str);
+        let request = match call {
+            Call::Get(path) => client.get(format!("{base_url}{path}")),
+            Call::Article => client.get(format!("{base_url}/api/articles/{slug}")),
+            Call::MissingArticle => client.get(format!("{base_url}/api/articles/no-such-article")),
+            Call::Profile => client.get(format!("{base_url}/api/user")),
+            Call::Login => {
+                let (email, ..) = seed::USERS.choose(&mut rng).expect("there are demo users");
+                return client
+                    .post(format!("{base_url}/api/login"))
+                    .json(&json!({ "email": email, "password": seed::PASSWORD }));
+            }
+            Call::Favorite => {
+                let method = if rng.random_bool(0.5) {
+                    Method::POST
+                } else {
+                    Method::DELETE
+                };
+                client.request(method, format!("{base_url}/api/articles/{slug}/favorite"))
+            }
+        };
+        match token {
+            Some(token) if call != Call::MissingArticle => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+}
+
+/// Response counts by status, `error` for requests that got none.
+#[derive(Default)]
+struct Tally(Mutex<BTreeMap<String, u64>>);
+
+impl Tally {
+    fn record(&self, outcome: String) {
+        *self.0.lock().unwrap().entry(outcome).or_default() += 1;
+    }
+
+    fn summary(&self) -> String {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(outcome, count)| format!("{outcome}: {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Sends `rate` requests a second, drawn from the example's [`mix`], until
+/// Ctrl+C or `duration` (zero runs until Ctrl+C).
+pub async fn run(
+    client: &Client,
+    base_url: &str,
+    example: Example,
+    rate: f64,
+    duration: Duration,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(rate > 0.0, "--rate must be positive");
+    let demo = Arc::new(Demo::load(client, base_url, example).await?);
+    let mix = mix(example);
+    let weights = WeightedIndex::new(mix.iter().map(|(weight, _)| weight))?;
+    let tally = Arc::new(Tally::default());
+
+    println!("Sending {rate} requests/s to {base_url}, Ctrl+C to stop");
+    let started = Instant::now();
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut reports = tokio::time::interval_at(started + REPORT_INTERVAL, REPORT_INTERVAL);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                if !duration.is_zero() && started.elapsed() >= duration {
+                    break;
+                }
+                let call = mix[weights.sample(&mut rand::rng())].1;
+                let request = demo.request(client, base_url, call);
+                let tally = tally.clone();
+                tokio::spawn(async move {
+                    let outcome = match request.send().await {
+                        Ok(response) => response.status().as_u16().to_string(),
+                        Err(_) => "error".to_string(),
+                    };
+                    tally.record(outcome);
+                });
+            }
+            _ = reports.tick() => println!("{}", tally.summary()),
+            _ = &mut ctrl_c => break,
+        }
+    }
+    println!("Done after {:.0?}: {}", started.elapsed(), tally.summary());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_article_examples_use_demo_users() {
+        for example in [Example::AxumPostgres, Example::ActixPostgres] {
+            assert!(mix(example).iter().any(|(_, call)| *call == Call::Profile));
+        }
+        assert!(
+            mix(Example::AiReportGenerator)
+                .iter()
+                .all(|(_, call)| matches!(call, Call::Get(_)))
+        );
+    }
+
+    #[test]
+    fn test_requests_authenticate_except_the_missing_article() {
+        let demo = Demo {
+            tokens: vec!["token".to_string()],
+            slugs: vec!["an-article".to_string()],
+        };
+        let client = Client::new();
+        let request = |call| {
+            demo.request(&client, "http://localhost:8080", call)
+                .build()
+                .unwrap()
+        };
+
+        let article = request(Call::Article);
+        assert_eq!(
+            article.url().as_str(),
+            "http://localhost:8080/api/articles/an-article"
+        );
+        assert_eq!(article.headers()["authorization"], "Bearer token");
+        assert!(
+            !request(Call::MissingArticle)
+                .headers()
+                .contains_key("authorization")
+        );
+        assert_eq!(request(Call::Login).method(), Method::POST);
+    }
+}